@@ -0,0 +1,185 @@
+//! Daily spend limits. Today's tokens and dollars are summed from
+//! `usage.jsonl` once at start and then kept up to date as turns are
+//! recorded, globally and per session, so checking a turn against
+//! `budget` costs no file reads. Counters reset at local midnight.
+
+use crate::config::SpendLimits;
+use crate::reports::{UsageEntry, UsageLog};
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Spend {
+    pub usd: f64,
+    pub tokens: u64,
+}
+
+impl Spend {
+    fn add(&mut self, entry: &UsageEntry) {
+        self.usd += entry.cost_usd.unwrap_or_default();
+        self.tokens += entry.input_tokens + entry.output_tokens;
+    }
+
+    /// The limit this spend has reached, described for messages.
+    fn over(&self, usd: f64, tokens: u64) -> Option<String> {
+        if usd > 0.0 && self.usd >= usd {
+            Some(format!("${:.2} of ${usd:.2}", self.usd))
+        } else if tokens > 0 && self.tokens >= tokens {
+            Some(format!("{} of {tokens} tokens", self.tokens))
+        } else {
+            None
+        }
+    }
+}
+
+/// A limit reached today.
+#[derive(Debug, PartialEq)]
+pub struct Exceeded {
+    /// `all chats` or the session key.
+    pub scope: String,
+    /// e.g. `$5.02 of $5.00`.
+    pub usage: String,
+}
+
+pub struct SpendTracker {
+    limits: SpendLimits,
+    timezone: Tz,
+    day: NaiveDate,
+    total: Spend,
+    sessions: HashMap<String, Spend>,
+    /// Scopes whose admin notice went out today.
+    notified: HashSet<String>,
+}
+
+impl SpendTracker {
+    pub fn new(limits: SpendLimits, timezone: Option<Tz>) -> Self {
+        let timezone = timezone.unwrap_or(Tz::UTC);
+        Self {
+            limits,
+            timezone,
+            day: Utc::now().with_timezone(&timezone).date_naive(),
+            total: Spend::default(),
+            sessions: HashMap::new(),
+            notified: HashSet::new(),
+        }
+    }
+
+    /// Tracker seeded with today's entries from the usage log.
+    pub fn load(limits: SpendLimits, timezone: Option<Tz>, log: &UsageLog) -> Self {
+        let mut tracker = Self::new(limits, timezone);
+        if tracker.limits.is_set() {
+            let midnight = tracker
+                .day
+                .and_hms_opt(0, 0, 0)
+                .and_then(|start| start.and_local_timezone(tracker.timezone).earliest())
+                .map(|start| start.with_timezone(&Utc))
+                .unwrap_or_else(Utc::now);
+            for entry in log.since(midnight) {
+                tracker.record(&entry);
+            }
+        }
+        tracker
+    }
+
+    pub fn record(&mut self, entry: &UsageEntry) {
+        self.roll(entry.ts);
+        if entry.ts.with_timezone(&self.timezone).date_naive() != self.day {
+            return;
+        }
+        self.total.add(entry);
+        self.sessions
+            .entry(entry.session.clone())
+            .or_default()
+            .add(entry);
+    }
+
+    /// The first limit `session` is over, global limits first.
+    pub fn exceeded(&mut self, session: &str, now: DateTime<Utc>) -> Option<Exceeded> {
+        self.roll(now);
+        if let Some(usage) = self
+            .total
+            .over(self.limits.daily_usd, self.limits.daily_tokens)
+        {
+            return Some(Exceeded {
+                scope: "all chats".to_string(),
+                usage,
+            });
+        }
+        let spent = self.sessions.get(session).copied().unwrap_or_default();
+        spent
+            .over(self.limits.chat_daily_usd, self.limits.chat_daily_tokens)
+            .map(|usage| Exceeded {
+                scope: session.to_string(),
+                usage,
+            })
+    }
+
+    /// Whether `scope` should be reported to the admin: once per day.
+    pub fn first_notice(&mut self, scope: &str) -> bool {
+        self.notified.insert(scope.to_string())
+    }
+
+    /// Start a new day's counters once local midnight has passed.
+    fn roll(&mut self, now: DateTime<Utc>) {
+        let today = now.with_timezone(&self.timezone).date_naive();
+        if today > self.day {
+            self.day = today;
+            self.total = Spend::default();
+            self.sessions.clear();
+            self.notified.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpendTracker;
+    use crate::config::SpendLimits;
+    use crate::reports::UsageEntry;
+    use chrono::{Duration, Utc};
+
+    fn entry(session: &str, tokens: u64, cost_usd: f64) -> UsageEntry {
+        UsageEntry {
+            ts: Utc::now(),
+            session: session.to_string(),
+            provider: "openrouter".to_string(),
+            model: "openai/gpt-4o".to_string(),
+            input_tokens: tokens,
+            output_tokens: 0,
+            cost_usd: Some(cost_usd),
+            tool_calls: 0,
+            tool_loops: 0,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn limits_apply_per_chat_and_globally_until_midnight() {
+        let limits = SpendLimits {
+            daily_usd: 1.0,
+            chat_daily_tokens: 10_000,
+            ..Default::default()
+        };
+        let mut tracker = SpendTracker::new(limits, None);
+        let now = Utc::now();
+        tracker.record(&entry("telegram:1", 12_000, 0.10));
+        tracker.record(&entry("telegram:2", 2_000, 0.10));
+
+        let over = tracker.exceeded("telegram:1", now).unwrap();
+        assert_eq!(over.scope, "telegram:1");
+        assert_eq!(over.usage, "12000 of 10000 tokens");
+        assert!(tracker.exceeded("telegram:2", now).is_none());
+        assert!(tracker.first_notice("telegram:1"));
+        assert!(!tracker.first_notice("telegram:1"));
+
+        tracker.record(&entry("telegram:2", 1_000, 0.85));
+        let over = tracker.exceeded("telegram:2", now).unwrap();
+        assert_eq!(over.scope, "all chats");
+        assert_eq!(over.usage, "$1.05 of $1.00");
+
+        let tomorrow = now + Duration::days(1);
+        assert!(tracker.exceeded("telegram:1", tomorrow).is_none());
+        assert!(tracker.first_notice("telegram:1"));
+    }
+}
@@ -0,0 +1,256 @@
+//! Chat commands the agent answers itself instead of running a turn. The
+//! table below is the one place that says which commands exist, who may use
+//! them and how `/help` describes them.
+
+/// A chat command.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Help,
+    Search,
+    Skills,
+    Status,
+    Diagnostics,
+    Handoff,
+    Vars,
+    Project,
+    Footer,
+    Preview,
+    Route,
+    Model,
+    Exec,
+    Persona,
+    Get,
+    Reset,
+    Report,
+    LastRun,
+    Debug,
+}
+
+/// Who may run a command.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    Anyone,
+    Admin,
+    /// Admins, and cron jobs scheduled to run the command.
+    AdminOrCron,
+}
+
+pub struct CommandSpec {
+    pub command: Command,
+    pub name: &'static str,
+    /// Whether text may follow the name; without, only the bare command
+    /// matches and anything longer goes to the model.
+    pub takes_args: bool,
+    pub access: Access,
+    pub help: &'static str,
+}
+
+const fn spec(
+    command: Command,
+    name: &'static str,
+    takes_args: bool,
+    access: Access,
+    help: &'static str,
+) -> CommandSpec {
+    CommandSpec {
+        command,
+        name,
+        takes_args,
+        access,
+        help,
+    }
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    spec(
+        Command::Help,
+        "help",
+        false,
+        Access::Anyone,
+        "List commands",
+    ),
+    spec(
+        Command::Search,
+        "search",
+        true,
+        Access::Anyone,
+        "Search this chat's history: /search <words>",
+    ),
+    spec(
+        Command::Skills,
+        "skills",
+        false,
+        Access::Anyone,
+        "List the installed skills",
+    ),
+    spec(
+        Command::Status,
+        "status",
+        false,
+        Access::Admin,
+        "Show provider and scheduler health",
+    ),
+    spec(
+        Command::Diagnostics,
+        "diagnostics",
+        false,
+        Access::Admin,
+        "Show the configuration and internal state",
+    ),
+    spec(
+        Command::Handoff,
+        "handoff",
+        true,
+        Access::Anyone,
+        "Show or change a conversation handoff: /handoff [accept|off]",
+    ),
+    spec(
+        Command::Vars,
+        "vars",
+        true,
+        Access::Anyone,
+        "Chat variables: /vars, /vars set <name> <value>, /vars unset <name>",
+    ),
+    spec(
+        Command::Project,
+        "project",
+        true,
+        Access::Anyone,
+        "Projects: /project create|use|off|list|persona",
+    ),
+    spec(
+        Command::Footer,
+        "footer",
+        true,
+        Access::Anyone,
+        "Reply footers: /footer on|off or /footer tools on|off",
+    ),
+    spec(
+        Command::Preview,
+        "preview",
+        true,
+        Access::Anyone,
+        "Shorten long replies: /preview <characters> or /preview off",
+    ),
+    spec(
+        Command::Route,
+        "route",
+        true,
+        Access::Anyone,
+        "Model routing for this chat: /route auto|cheap|strong",
+    ),
+    spec(
+        Command::Model,
+        "model",
+        true,
+        Access::Anyone,
+        "Prefer a model in this chat: /model <name> or /model auto",
+    ),
+    spec(
+        Command::Exec,
+        "exec",
+        true,
+        Access::Anyone,
+        "Show the exec profile; admins switch it: /exec <profile>|default",
+    ),
+    spec(
+        Command::Persona,
+        "persona",
+        true,
+        Access::Anyone,
+        "Show the persona; admins switch it: /persona <name>|default",
+    ),
+    spec(
+        Command::Get,
+        "get",
+        true,
+        Access::Anyone,
+        "Download a workspace file: /get <path>",
+    ),
+    spec(
+        Command::Reset,
+        "reset",
+        false,
+        Access::Anyone,
+        "Clear this chat's conversation history",
+    ),
+    spec(
+        Command::Report,
+        "report",
+        true,
+        Access::AdminOrCron,
+        "Render a report: /report [daily|weekly|monthly] [sections]",
+    ),
+    spec(
+        Command::LastRun,
+        "lastrun",
+        false,
+        Access::Anyone,
+        "Show what the last turn did",
+    ),
+    spec(
+        Command::Debug,
+        "debug",
+        true,
+        Access::Admin,
+        "Capture turns for a bug report: /debug on|off|status",
+    ),
+];
+
+/// The command `text` invokes and its trimmed arguments, if it is one.
+pub fn parse(text: &str) -> Option<(&'static CommandSpec, &str)> {
+    let rest = text.trim().strip_prefix('/')?;
+    let (name, args) = rest
+        .split_once(char::is_whitespace)
+        .map(|(name, args)| (name, args.trim()))
+        .unwrap_or((rest, ""));
+    let spec = COMMANDS.iter().find(|spec| spec.name == name)?;
+    (spec.takes_args || args.is_empty()).then_some((spec, args))
+}
+
+/// Whether a sender may run `spec`.
+pub fn permitted(spec: &CommandSpec, is_admin: bool, from_cron: bool) -> bool {
+    match spec.access {
+        Access::Anyone => true,
+        Access::Admin => is_admin,
+        Access::AdminOrCron => is_admin || from_cron,
+    }
+}
+
+/// `/help`: the commands a sender may run.
+pub fn help(is_admin: bool) -> String {
+    let mut out = String::from("Commands:\n");
+    for spec in COMMANDS
+        .iter()
+        .filter(|spec| permitted(spec, is_admin, false))
+    {
+        out.push_str(&format!("/{} - {}\n", spec.name, spec.help));
+    }
+    out.push_str("Anything else is sent to the assistant.");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{help, parse, permitted, Command};
+
+    #[test]
+    fn parses_and_gates_commands() {
+        let (spec, args) = parse("  /search  trip plans ").unwrap();
+        assert_eq!((spec.command, args), (Command::Search, "trip plans"));
+        assert_eq!(parse("/status").unwrap().0.command, Command::Status);
+        assert!(parse("/status of my order?").is_none());
+        assert!(parse("/searching").is_none());
+        assert!(parse("search").is_none());
+
+        let status = parse("/status").unwrap().0;
+        assert!(!permitted(status, false, false));
+        assert!(permitted(status, true, false));
+        let report = parse("/report weekly").unwrap().0;
+        assert!(!permitted(report, false, false));
+        assert!(permitted(report, false, true));
+
+        assert!(!help(false).contains("/diagnostics"));
+        assert!(help(true).contains("/diagnostics"));
+    }
+}
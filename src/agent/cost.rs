@@ -0,0 +1,135 @@
+//! Rough cost estimate of a turn before it runs, so requests that will
+//! predictably be expensive (many pages to fetch, large files to read) can
+//! be confirmed first.
+
+use crate::agent::usage;
+use crate::config::AppConfig;
+use regex::Regex;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Tokens a fetched web page typically adds to the conversation.
+const TOKENS_PER_FETCH: u64 = 6_000;
+/// Cap on tokens counted for a single file (tools truncate larger outputs).
+const MAX_FILE_TOKENS: u64 = 200_000;
+/// Reply and tool-call arguments written by the model per completion.
+const OUTPUT_TOKENS_PER_CALL: u64 = 400;
+const CHARS_PER_TOKEN: u64 = 4;
+
+static URL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"https?://[^\s<>"')]+"#).unwrap());
+
+#[derive(Debug)]
+pub struct TurnEstimate {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: Option<f64>,
+    /// What makes the turn expensive, for the confirmation message.
+    pub reasons: Vec<String>,
+}
+
+impl TurnEstimate {
+    pub fn confirmation_prompt(&self, model: &str) -> String {
+        format!(
+            "This request looks expensive: about {} tokens (~${:.2} on {model}) because of {}.\nReply \"yes\" to go ahead or \"no\" to cancel.",
+            self.input_tokens + self.output_tokens,
+            self.cost_usd.unwrap_or_default(),
+            self.reasons.join(", ")
+        )
+    }
+}
+
+/// Estimate a turn: the prompt and history are re-sent on every completion,
+/// and each page to fetch or file to read adds one tool round whose output
+/// is carried through the remaining rounds.
+pub fn estimate_turn(
+    cfg: &AppConfig,
+    model: &str,
+    message: &str,
+    prompt_chars: usize,
+    history_chars: usize,
+) -> TurnEstimate {
+    let base = (prompt_chars + history_chars) as u64 / CHARS_PER_TOKEN;
+    let mut loads: Vec<u64> = Vec::new();
+    let mut reasons = Vec::new();
+
+    let urls = URL_RE.find_iter(message).count() as u64;
+    if urls > 0 {
+        loads.extend(std::iter::repeat_n(TOKENS_PER_FETCH, urls as usize));
+        reasons.push(format!("{urls} web page(s) to fetch"));
+    }
+    for path in referenced_files(&cfg.workspace_dir, message) {
+        let tokens = (std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0) / CHARS_PER_TOKEN)
+            .min(MAX_FILE_TOKENS);
+        if tokens > 0 {
+            reasons.push(format!("{} (~{tokens} tokens)", path.display()));
+            loads.push(tokens);
+        }
+    }
+
+    let calls = loads.len() as u64 + 1;
+    let mut input_tokens = base * calls;
+    for (idx, tokens) in loads.iter().enumerate() {
+        // Output of round `idx` is part of every later completion.
+        input_tokens += tokens * (calls - idx as u64 - 1);
+    }
+    let output_tokens = OUTPUT_TOKENS_PER_CALL * calls;
+    let cost_usd = usage::model_price(cfg, model)
+        .map(|price| usage::cost_usd(price, input_tokens, output_tokens));
+    TurnEstimate {
+        input_tokens,
+        output_tokens,
+        cost_usd,
+        reasons,
+    }
+}
+
+/// Existing files named in `message`, as absolute or workspace-relative paths.
+fn referenced_files(workspace: &Path, message: &str) -> Vec<std::path::PathBuf> {
+    message
+        .split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| matches!(c, '"' | '\'' | '`' | ',' | ';' | '(' | ')'))
+        })
+        .filter(|word| !word.contains("://") && (word.contains('/') || word.contains('.')))
+        .map(|word| {
+            let path = Path::new(word);
+            if path.is_absolute() {
+                path.to_path_buf()
+            } else {
+                workspace.join(path)
+            }
+        })
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::estimate_turn;
+    use crate::config::AppConfig;
+
+    #[test]
+    fn fetches_and_files_raise_the_estimate() {
+        let dir = std::env::temp_dir().join(format!("femtobot-cost-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("big.txt"), "x".repeat(400_000)).unwrap();
+        let mut cfg = AppConfig::defaults();
+        cfg.workspace_dir = dir.clone();
+
+        let plain = estimate_turn(&cfg, "openai/gpt-4o", "hello", 4_000, 0);
+        assert!(plain.reasons.is_empty());
+        assert_eq!(plain.input_tokens, 1_000);
+
+        let heavy = estimate_turn(
+            &cfg,
+            "openai/gpt-4o",
+            "summarize big.txt and https://a.example/x https://b.example/y",
+            4_000,
+            0,
+        );
+        assert_eq!(heavy.reasons.len(), 2);
+        assert!(heavy.input_tokens > 100_000);
+        assert!(heavy.cost_usd.unwrap() > 0.25);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,171 @@
+//! Incident mode: `/debug on` captures the full exchange of a session's next
+//! turns (prompt, history sent, tool transcript, provider attempts, usage and
+//! timings) into a JSONL bundle for bug reports, then switches itself off.
+//! Bundles live under `{data_dir}/debug/`, outside the workspace the agent
+//! and sync can read, with every configured secret redacted.
+
+use crate::config::AppConfig;
+use crate::error::ErrorKind;
+use anyhow::Result;
+use chrono::Local;
+use rig::completion::message::Message;
+use rig::completion::Usage;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+
+const REDACTED: &str = "[REDACTED]";
+
+pub const DEFAULT_TURNS: usize = 5;
+pub const MAX_TURNS: usize = 50;
+pub const DEBUG_USAGE: &str =
+    "Usage: /debug on [turns] [session] | off [session] | status [session]";
+
+struct Capture {
+    remaining: usize,
+    bundle: PathBuf,
+}
+
+/// One captured turn, written as a line of the bundle.
+#[derive(Serialize)]
+pub struct TurnCapture<'a> {
+    pub timestamp: String,
+    pub session: &'a str,
+    pub sender_id: &'a str,
+    pub user_message: &'a str,
+    /// Prompt actually sent, including memory and context prefixes.
+    pub prompt: &'a str,
+    pub history_sent: &'a [Message],
+    /// Assistant messages, tool calls and tool results produced by the turn.
+    pub turn_messages: &'a [Message],
+    /// Every failed provider attempt, including retries that later succeeded.
+    pub failed_attempts: &'a [String],
+    pub provider: Option<&'a str>,
+    pub model: Option<&'a str>,
+    pub usage: Option<Usage>,
+    pub duration_ms: u128,
+    pub reply: Option<&'a str>,
+    pub error: Option<&'a str>,
+    pub error_code: Option<ErrorKind>,
+}
+
+/// Sessions with an active capture, keyed by session key.
+pub struct DebugCaptures {
+    dir: PathBuf,
+    secrets: Vec<String>,
+    active: Mutex<HashMap<String, Capture>>,
+}
+
+impl DebugCaptures {
+    pub fn new(cfg: &AppConfig) -> Self {
+        Self {
+            dir: cfg.data_dir.join("debug"),
+            secrets: cfg.secret_values(),
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start (or restart) capturing the next `turns` turns of `session`.
+    pub async fn start(&self, session: &str, turns: usize) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let bundle = self.dir.join(format!(
+            "{}-{}.jsonl",
+            Local::now().format("%Y%m%d-%H%M%S"),
+            crate::history::sanitize_file_stem(session)
+        ));
+        self.active.lock().await.insert(
+            session.to_string(),
+            Capture {
+                remaining: turns.clamp(1, MAX_TURNS),
+                bundle: bundle.clone(),
+            },
+        );
+        Ok(bundle)
+    }
+
+    pub async fn stop(&self, session: &str) -> Option<PathBuf> {
+        self.active.lock().await.remove(session).map(|c| c.bundle)
+    }
+
+    /// Remaining turns and bundle path of an active capture.
+    pub async fn status(&self, session: &str) -> Option<(usize, PathBuf)> {
+        self.active
+            .lock()
+            .await
+            .get(session)
+            .map(|c| (c.remaining, c.bundle.clone()))
+    }
+
+    pub async fn is_active(&self, session: &str) -> bool {
+        self.active.lock().await.contains_key(session)
+    }
+
+    /// Append `turn` to the session's bundle. Returns the bundle path once
+    /// the last requested turn has been captured and the capture ended.
+    pub async fn record(&self, session: &str, turn: &TurnCapture<'_>) -> Result<Option<PathBuf>> {
+        let mut active = self.active.lock().await;
+        let Some(capture) = active.get_mut(session) else {
+            return Ok(None);
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&capture.bundle)?;
+        let mut line = serde_json::to_string(turn)?;
+        for secret in &self.secrets {
+            line = line.replace(secret.as_str(), REDACTED);
+        }
+        writeln!(file, "{line}")?;
+        capture.remaining -= 1;
+        if capture.remaining > 0 {
+            return Ok(None);
+        }
+        Ok(active.remove(session).map(|c| c.bundle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DebugCaptures, TurnCapture};
+    use crate::config::AppConfig;
+
+    #[tokio::test]
+    async fn bundles_go_to_the_data_dir_with_secrets_redacted() {
+        let root = std::env::temp_dir().join(format!("femtobot-debug-{}", uuid::Uuid::new_v4()));
+        let mut cfg = AppConfig::defaults();
+        cfg.data_dir = root.join("data");
+        cfg.workspace_dir = root.join("workspace");
+        cfg.openrouter_api_key = "sk-or-secret-key".to_string();
+        let captures = DebugCaptures::new(&cfg);
+
+        let bundle = captures.start("telegram:1", 1).await.unwrap();
+        assert!(bundle.starts_with(cfg.data_dir.join("debug")));
+        let turn = TurnCapture {
+            timestamp: String::new(),
+            session: "telegram:1",
+            sender_id: "1",
+            user_message: "what is my key?",
+            prompt: "key is sk-or-secret-key",
+            history_sent: &[],
+            turn_messages: &[],
+            failed_attempts: &[],
+            provider: None,
+            model: None,
+            usage: None,
+            duration_ms: 0,
+            reply: Some("It is sk-or-secret-key."),
+            error: None,
+            error_code: None,
+        };
+        assert_eq!(
+            captures.record("telegram:1", &turn).await.unwrap(),
+            Some(bundle.clone())
+        );
+        let text = std::fs::read_to_string(&bundle).unwrap();
+        assert!(text.contains("key is [REDACTED]") && !text.contains("sk-or-secret-key"));
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
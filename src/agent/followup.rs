@@ -0,0 +1,89 @@
+//! Detects promises of later action in assistant replies ("I'll check again
+//! in an hour") so they can be backed by a one-shot cron job.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Follow-ups further out than this are left to the model's own cron use.
+const MAX_FOLLOW_UP_SECS: u64 = 7 * 24 * 60 * 60;
+
+static COMMITMENT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(i'll|i will|i’ll|i'm going to|i am going to|let me)\b[^.!?\n]*?\b(check|look|follow up|get back|circle back|report|update|ping|remind|try|verify|revisit|retry)\b").unwrap()
+});
+
+static DELAY_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\bin\s+(half an hour|an?|one|two|three|four|five|six|ten|fifteen|twenty|thirty|\d+)\s+(minutes?|mins?|hours?|hrs?|days?)\b").unwrap()
+});
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Commitment {
+    pub sentence: String,
+    pub delay_secs: u64,
+}
+
+/// Return the first sentence that both promises an action and names an
+/// explicit delay.
+pub fn detect_commitment(text: &str) -> Option<Commitment> {
+    text.split_inclusive(['.', '!', '?', '\n'])
+        .map(str::trim)
+        .filter(|s| COMMITMENT_RE.is_match(s))
+        .find_map(|sentence| {
+            let delay_secs = parse_delay(sentence)?;
+            (delay_secs > 0 && delay_secs <= MAX_FOLLOW_UP_SECS).then(|| Commitment {
+                sentence: sentence.to_string(),
+                delay_secs,
+            })
+        })
+}
+
+fn parse_delay(sentence: &str) -> Option<u64> {
+    let caps = DELAY_RE.captures(sentence)?;
+    let amount = caps.get(1)?.as_str().to_lowercase();
+    if amount == "half an hour" {
+        return Some(30 * 60);
+    }
+    let count: u64 = match amount.as_str() {
+        "a" | "an" | "one" => 1,
+        "two" => 2,
+        "three" => 3,
+        "four" => 4,
+        "five" => 5,
+        "six" => 6,
+        "ten" => 10,
+        "fifteen" => 15,
+        "twenty" => 20,
+        "thirty" => 30,
+        digits => digits.parse().ok()?,
+    };
+    let unit = caps.get(2)?.as_str().to_lowercase();
+    let secs = if unit.starts_with("min") {
+        60
+    } else if unit.starts_with('h') {
+        60 * 60
+    } else {
+        24 * 60 * 60
+    };
+    count.checked_mul(secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::detect_commitment;
+
+    #[test]
+    fn detects_promise_with_delay() {
+        let c = detect_commitment("The build is still running. I'll check again in an hour.")
+            .expect("commitment");
+        assert_eq!(c.delay_secs, 3600);
+        assert_eq!(c.sentence, "I'll check again in an hour.");
+
+        let c = detect_commitment("Let me look at the queue in 15 minutes").expect("commitment");
+        assert_eq!(c.delay_secs, 900);
+    }
+
+    #[test]
+    fn ignores_statements_without_promise_or_delay() {
+        assert!(detect_commitment("The train leaves in 2 hours.").is_none());
+        assert!(detect_commitment("I'll check that right away.").is_none());
+    }
+}
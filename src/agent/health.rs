@@ -0,0 +1,225 @@
+//! Outcomes of calls to each provider route: the latest one for `/status`,
+//! plus a rolling window of successes and latencies kept in
+//! `<data_dir>/route_health.json` across restarts. With
+//! `agents.defaults.adaptive_fallback`, a route that keeps failing is tried
+//! last for a few minutes, so a provider incident costs one failed attempt
+//! every few minutes instead of one per message.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+/// Calls remembered per route.
+const WINDOW: usize = 20;
+/// A route is demoted after this many failures in a row...
+const FAILURE_STREAK: usize = 3;
+/// ...or when fewer than this share of its remembered calls succeeded.
+const MIN_SUCCESS_RATE: f64 = 0.5;
+/// How long a demoted route stays at the back of the order.
+const DEMOTION_MINS: i64 = 5;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Sample {
+    ok: bool,
+    latency_ms: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RouteStats {
+    samples: VecDeque<Sample>,
+    last_at: Option<DateTime<Local>>,
+    /// Failure class of the latest call; `None` when it succeeded.
+    last_failure: Option<String>,
+    demoted_until: Option<DateTime<Local>>,
+}
+
+impl RouteStats {
+    fn success_rate(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let ok = self.samples.iter().filter(|s| s.ok).count();
+        Some(ok as f64 / self.samples.len() as f64)
+    }
+
+    fn mean_latency_ms(&self) -> Option<u64> {
+        let ok: Vec<u64> = self
+            .samples
+            .iter()
+            .filter(|s| s.ok)
+            .map(|s| s.latency_ms)
+            .collect();
+        (!ok.is_empty()).then(|| ok.iter().sum::<u64>() / ok.len() as u64)
+    }
+
+    fn is_failing(&self) -> bool {
+        let streak = self.samples.iter().rev().take_while(|s| !s.ok).count();
+        streak >= FAILURE_STREAK
+            || (self.samples.len() >= FAILURE_STREAK
+                && self
+                    .success_rate()
+                    .is_some_and(|rate| rate < MIN_SUCCESS_RATE))
+    }
+}
+
+pub struct RouteHealth {
+    /// `provider/model` of each route, by index.
+    keys: Vec<String>,
+    path: PathBuf,
+    adaptive: bool,
+    stats: Mutex<HashMap<String, RouteStats>>,
+}
+
+impl RouteHealth {
+    /// Health of `keys` (route keys in route order), with the history an
+    /// earlier run left in `data_dir`.
+    pub fn load(data_dir: &Path, keys: Vec<String>, adaptive: bool) -> Self {
+        let path = data_dir.join("route_health.json");
+        let stats = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|err| {
+                warn!("ignoring unreadable {}: {err}", path.display());
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self {
+            keys,
+            path,
+            adaptive,
+            stats: Mutex::new(stats),
+        }
+    }
+
+    pub fn record_ok(&self, route: usize, latency: Duration) {
+        self.record(route, None, latency);
+    }
+
+    /// Record a failed attempt; `class` is the failure classification.
+    pub fn record_failure(&self, route: usize, class: &str, latency: Duration) {
+        self.record(route, Some(class.to_string()), latency);
+    }
+
+    fn record(&self, route: usize, failure: Option<String>, latency: Duration) {
+        let Some(key) = self.keys.get(route) else {
+            return;
+        };
+        let Ok(mut all) = self.stats.lock() else {
+            return;
+        };
+        let now = Local::now();
+        let stats = all.entry(key.clone()).or_default();
+        stats.samples.push_back(Sample {
+            ok: failure.is_none(),
+            latency_ms: latency.as_millis() as u64,
+        });
+        while stats.samples.len() > WINDOW {
+            stats.samples.pop_front();
+        }
+        stats.last_at = Some(now);
+        if failure.is_none() {
+            stats.demoted_until = None;
+        } else if self.adaptive && stats.is_failing() {
+            stats.demoted_until = Some(now + chrono::Duration::minutes(DEMOTION_MINS));
+        }
+        stats.last_failure = failure;
+        if let Err(err) = save(&self.path, &all) {
+            warn!("could not save route health: {err}");
+        }
+    }
+
+    fn is_demoted(&self, route: usize) -> bool {
+        let Some(key) = self.keys.get(route) else {
+            return false;
+        };
+        let Ok(all) = self.stats.lock() else {
+            return false;
+        };
+        all.get(key)
+            .and_then(|stats| stats.demoted_until)
+            .is_some_and(|until| until > Local::now())
+    }
+
+    /// Move currently demoted routes behind the others, keeping the
+    /// configured order within each group. A no-op unless adaptive fallback
+    /// is enabled.
+    pub fn reorder(&self, order: &mut [usize]) {
+        if self.adaptive {
+            order.sort_by_key(|&route| self.is_demoted(route));
+        }
+    }
+
+    /// One line for route `index`, e.g. "ok at 14:02; 19/20 recent calls ok,
+    /// avg 2.4s" or "failed (rate_limit) at 14:05; ...; tried last until 14:10".
+    pub fn describe(&self, index: usize) -> String {
+        let Some(key) = self.keys.get(index) else {
+            return "unknown".to_string();
+        };
+        let all = match self.stats.lock() {
+            Ok(all) => all,
+            Err(_) => return "unknown".to_string(),
+        };
+        let Some(stats) = all.get(key) else {
+            return "not used yet".to_string();
+        };
+        let mut out = match (&stats.last_at, &stats.last_failure) {
+            (None, _) => "not used yet".to_string(),
+            (Some(at), None) => format!("ok at {}", at.format("%H:%M")),
+            (Some(at), Some(class)) => format!("failed ({class}) at {}", at.format("%H:%M")),
+        };
+        let ok = stats.samples.iter().filter(|s| s.ok).count();
+        out.push_str(&format!("; {ok}/{} recent calls ok", stats.samples.len()));
+        if let Some(ms) = stats.mean_latency_ms() {
+            out.push_str(&format!(", avg {:.1}s", ms as f64 / 1000.0));
+        }
+        if let Some(until) = stats.demoted_until.filter(|until| *until > Local::now()) {
+            out.push_str(&format!("; tried last until {}", until.format("%H:%M")));
+        }
+        out
+    }
+}
+
+fn save(path: &Path, stats: &HashMap<String, RouteStats>) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(stats)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RouteHealth;
+    use std::time::Duration;
+
+    #[test]
+    fn failing_route_moves_back_and_recovers_on_success() {
+        let dir = std::env::temp_dir().join(format!("femtobot-health-{}", std::process::id()));
+        let keys = vec!["openrouter/a".to_string(), "openrouter/b".to_string()];
+        let health = RouteHealth::load(&dir, keys.clone(), true);
+        let latency = Duration::from_millis(800);
+        health.record_ok(0, latency);
+        for _ in 0..3 {
+            health.record_failure(0, "server_error", latency);
+        }
+        let mut order = vec![0, 1];
+        health.reorder(&mut order);
+        assert_eq!(order, vec![1, 0]);
+        assert!(health.describe(0).contains("tried last until"));
+
+        // The window and demotion survive a restart.
+        let reloaded = RouteHealth::load(&dir, keys, true);
+        let mut order = vec![0, 1];
+        reloaded.reorder(&mut order);
+        assert_eq!(order, vec![1, 0]);
+        reloaded.record_ok(0, latency);
+        let mut order = vec![0, 1];
+        reloaded.reorder(&mut order);
+        assert_eq!(order, vec![0, 1]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,141 @@
+//! Publishes what a turn has written so far as [`PartialOutput`]s, so a
+//! channel can show the reply while tools are still running. Each model
+//! response in the turn's tool loop adds its text; streamed turns also show
+//! the response being written.
+
+use crate::bus::{MessageBus, PartialOutput};
+use rig::agent::{HookAction, PromptHook};
+use rig::completion::message::AssistantContent;
+use rig::completion::{CompletionModel, CompletionResponse, Message};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Streamed text is published at most this often; the final text of each
+/// response always is.
+const STREAM_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Default)]
+pub struct LiveReply {
+    target: Option<Arc<Target>>,
+}
+
+struct Target {
+    bus: MessageBus,
+    channel: String,
+    chat_id: String,
+    correlation_id: String,
+    reply_to: Option<String>,
+    text: Mutex<String>,
+    /// When streamed text was last published.
+    shown_at: Mutex<Option<Instant>>,
+}
+
+impl LiveReply {
+    pub fn new(
+        bus: MessageBus,
+        channel: &str,
+        chat_id: &str,
+        correlation_id: &str,
+        reply_to: Option<String>,
+    ) -> Self {
+        Self {
+            target: Some(Arc::new(Target {
+                bus,
+                channel: channel.to_string(),
+                chat_id: chat_id.to_string(),
+                correlation_id: correlation_id.to_string(),
+                reply_to,
+                text: Mutex::new(String::new()),
+                shown_at: Mutex::new(None),
+            })),
+        }
+    }
+
+    /// Announce the turn, or start over after a failed attempt.
+    pub fn begin(&self) {
+        if let Some(target) = &self.target {
+            if let Ok(mut text) = target.text.lock() {
+                text.clear();
+            }
+            target.publish(String::new());
+        }
+    }
+
+    /// Show `current`, the model response being streamed, after the text
+    /// so far without keeping it; [`LiveReply::append`] keeps it once done.
+    pub fn show(&self, current: &str) {
+        let Some(target) = &self.target else {
+            return;
+        };
+        let current = current.trim();
+        if current.is_empty() {
+            return;
+        }
+        if let Ok(mut shown_at) = target.shown_at.lock() {
+            if shown_at.is_some_and(|at| at.elapsed() < STREAM_INTERVAL) {
+                return;
+            }
+            *shown_at = Some(Instant::now());
+        }
+        let Ok(text) = target.text.lock().map(|text| text.clone()) else {
+            return;
+        };
+        target.publish(if text.is_empty() {
+            current.to_string()
+        } else {
+            format!("{text}\n\n{current}")
+        });
+    }
+
+    pub fn append(&self, addition: &str) {
+        let Some(target) = &self.target else {
+            return;
+        };
+        let addition = addition.trim();
+        if addition.is_empty() {
+            return;
+        }
+        let text = {
+            let Ok(mut text) = target.text.lock() else {
+                return;
+            };
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+            text.push_str(addition);
+            text.clone()
+        };
+        target.publish(text);
+    }
+}
+
+impl Target {
+    fn publish(&self, text: String) {
+        self.bus.publish_partial(PartialOutput {
+            channel: self.channel.clone(),
+            chat_id: self.chat_id.clone(),
+            correlation_id: self.correlation_id.clone(),
+            reply_to: self.reply_to.clone(),
+            text,
+        });
+    }
+}
+
+impl<M: CompletionModel> PromptHook<M> for LiveReply {
+    async fn on_completion_response(
+        &self,
+        _prompt: &Message,
+        response: &CompletionResponse<M::Response>,
+    ) -> HookAction {
+        let text: Vec<&str> = response
+            .choice
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect();
+        self.append(&text.join("\n"));
+        HookAction::cont()
+    }
+}
@@ -0,0 +1,297 @@
+//! Built-in `mock` provider for tests and local development. Completions
+//! come from a fixture file (`providers.mock.fixture`, env
+//! `FEMTOBOT_MOCK_FIXTURE`) instead of a model API, so the bus, agent loop,
+//! tools, cron and compaction run without keys or network. The fixture is a
+//! JSON array of steps; each completion is answered by the first unused step
+//! whose `when` text appears in the newest request message (the user's text
+//! without the turn context, or a tool result), else by the first unused
+//! step without `when`:
+//!
+//! ```json
+//! [
+//!   {"when": "note", "tool_calls": [{"name": "write_file", "arguments": {"path": "notes.md", "content": "milk"}}]},
+//!   {"text": "Saved."},
+//!   {"when": "flaky", "error": "{\"error\": {\"code\": 429}}", "repeat": true}
+//! ]
+//! ```
+//!
+//! `error` fails the completion with that provider body, classified like a
+//! real one. `repeat` keeps a step available after it is used. Requests no
+//! step matches are answered by echoing the newest message.
+
+use crate::config::AppConfig;
+use rig::client::FinalCompletionResponse;
+use rig::completion::message::{AssistantContent, Message, ToolResultContent, UserContent};
+use rig::completion::{
+    CompletionError, CompletionModel, CompletionRequest, CompletionResponse, Usage,
+};
+use rig::one_or_many::OneOrMany;
+use rig::streaming::{RawStreamingChoice, RawStreamingToolCall, StreamingCompletionResponse};
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+const CHARS_PER_TOKEN: u64 = 4;
+/// Header the agent puts before the user's text, after the turn context.
+const USER_MESSAGE_MARKER: &str = "[User message]\n";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MockStep {
+    /// Text (case-insensitive) the newest request message must contain.
+    #[serde(default)]
+    pub when: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<MockToolCall>,
+    /// Provider error body to fail the completion with.
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Serve this step again instead of using it up.
+    #[serde(default)]
+    pub repeat: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MockToolCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+/// Mock completion model; clones (one per route) share the script.
+#[derive(Clone)]
+pub struct MockModel {
+    script: Arc<Mutex<Vec<MockStep>>>,
+}
+
+impl MockModel {
+    pub fn new(steps: Vec<MockStep>) -> Self {
+        Self {
+            script: Arc::new(Mutex::new(steps)),
+        }
+    }
+
+    /// Model playing the configured fixture; with none (or an unreadable
+    /// one) every request is echoed.
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        let steps = match &cfg.mock_fixture {
+            Some(path) => load_fixture(path).unwrap_or_else(|err| {
+                warn!("mock fixture {} ignored: {err}", path.display());
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        info!("mock provider with {} scripted step(s)", steps.len());
+        Self::new(steps)
+    }
+
+    /// Next step for a request whose newest message is `newest`.
+    fn next_step(&self, newest: &str) -> Option<MockStep> {
+        let newest = newest.to_lowercase();
+        let mut steps = self.script.lock().ok()?;
+        let index = steps
+            .iter()
+            .position(|step| {
+                step.when
+                    .as_deref()
+                    .is_some_and(|when| newest.contains(&when.to_lowercase()))
+            })
+            .or_else(|| steps.iter().position(|step| step.when.is_none()))?;
+        if steps[index].repeat {
+            Some(steps[index].clone())
+        } else {
+            Some(steps.remove(index))
+        }
+    }
+
+    /// Answer to `request`: the text and tool calls, or the scripted error.
+    fn answer(
+        &self,
+        request: &CompletionRequest,
+    ) -> Result<(Vec<AssistantContent>, Usage), CompletionError> {
+        let newest = request
+            .chat_history
+            .iter()
+            .last()
+            .map(message_text)
+            .unwrap_or_default();
+        let newest = match newest.rsplit_once(USER_MESSAGE_MARKER) {
+            Some((_, user_text)) => user_text.to_string(),
+            None => newest,
+        };
+        let step = self.next_step(&newest).unwrap_or_else(|| MockStep {
+            when: None,
+            text: Some(format!("(mock) {newest}")),
+            tool_calls: Vec::new(),
+            error: None,
+            repeat: false,
+        });
+        if let Some(body) = step.error {
+            return Err(CompletionError::ProviderError(body));
+        }
+        let mut content = Vec::new();
+        if let Some(text) = step.text.filter(|text| !text.is_empty()) {
+            content.push(AssistantContent::text(text));
+        }
+        for call in step.tool_calls {
+            let id = format!("mock-{}", uuid::Uuid::new_v4());
+            content.push(AssistantContent::tool_call(id, call.name, call.arguments));
+        }
+        if content.is_empty() {
+            content.push(AssistantContent::text(""));
+        }
+        let sent: usize = request
+            .chat_history
+            .iter()
+            .map(|message| message_text(message).len())
+            .sum::<usize>()
+            + request.preamble.as_deref().map_or(0, str::len);
+        let written = serde_json::to_string(&content).map_or(0, |json| json.len());
+        let mut usage = Usage::new();
+        usage.input_tokens = sent as u64 / CHARS_PER_TOKEN;
+        usage.output_tokens = written as u64 / CHARS_PER_TOKEN;
+        usage.total_tokens = usage.input_tokens + usage.output_tokens;
+        Ok((content, usage))
+    }
+}
+
+impl CompletionModel for MockModel {
+    type Response = FinalCompletionResponse;
+    type StreamingResponse = FinalCompletionResponse;
+    type Client = ();
+
+    fn make(_client: &Self::Client, _model: impl Into<String>) -> Self {
+        Self::new(Vec::new())
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        let (content, usage) = self.answer(&request)?;
+        let choice = OneOrMany::many(content)
+            .map_err(|_| CompletionError::ResponseError("empty mock answer".to_string()))?;
+        Ok(CompletionResponse {
+            choice,
+            usage,
+            raw_response: FinalCompletionResponse { usage: Some(usage) },
+        })
+    }
+
+    async fn stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        let (content, usage) = self.answer(&request)?;
+        let mut chunks: Vec<Result<RawStreamingChoice<FinalCompletionResponse>, CompletionError>> =
+            Vec::new();
+        for item in content {
+            match item {
+                AssistantContent::Text(text) => {
+                    chunks.push(Ok(RawStreamingChoice::Message(text.text)))
+                }
+                AssistantContent::ToolCall(call) => chunks.push(Ok(RawStreamingChoice::ToolCall(
+                    RawStreamingToolCall::new(call.id, call.function.name, call.function.arguments),
+                ))),
+                _ => {}
+            }
+        }
+        chunks.push(Ok(RawStreamingChoice::FinalResponse(
+            FinalCompletionResponse { usage: Some(usage) },
+        )));
+        Ok(StreamingCompletionResponse::stream(Box::pin(
+            futures_util::stream::iter(chunks),
+        )))
+    }
+}
+
+fn load_fixture(path: &Path) -> anyhow::Result<Vec<MockStep>> {
+    let raw = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&raw)?)
+}
+
+/// Text of a message: what the user wrote, tool results, or the reply.
+fn message_text(message: &Message) -> String {
+    let mut parts = Vec::new();
+    match message {
+        Message::User { content } => {
+            for item in content.iter() {
+                match item {
+                    UserContent::Text(text) => parts.push(text.text.clone()),
+                    UserContent::ToolResult(result) => {
+                        for item in result.content.iter() {
+                            if let ToolResultContent::Text(text) = item {
+                                parts.push(text.text.clone());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Message::Assistant { content, .. } => {
+            for item in content.iter() {
+                if let AssistantContent::Text(text) = item {
+                    parts.push(text.text.clone());
+                }
+            }
+        }
+    }
+    parts.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockModel;
+    use rig::completion::message::{AssistantContent, Message};
+    use rig::completion::{CompletionModel, CompletionRequest};
+    use rig::one_or_many::OneOrMany;
+
+    fn request(text: &str) -> CompletionRequest {
+        CompletionRequest {
+            preamble: None,
+            chat_history: OneOrMany::one(Message::user(text)),
+            documents: Vec::new(),
+            tools: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            tool_choice: None,
+            additional_params: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn steps_are_served_by_match_then_echo() {
+        let steps = serde_json::from_str(
+            r#"[
+                {"when": "NOTE", "tool_calls": [{"name": "write_file", "arguments": {"path": "a.md"}}]},
+                {"text": "Saved."},
+                {"when": "flaky", "error": "{\"error\": {\"code\": 429}}", "repeat": true}
+            ]"#,
+        )
+        .unwrap();
+        let model = MockModel::new(steps);
+
+        let call = model
+            .completion(request(
+                "[Current time]\n...\n\n[User message]\ntake a note",
+            ))
+            .await
+            .unwrap();
+        assert!(matches!(
+            call.choice.first(),
+            AssistantContent::ToolCall(call) if call.function.name == "write_file"
+        ));
+        for _ in 0..2 {
+            assert!(model.completion(request("flaky")).await.is_err());
+        }
+        let saved = model.completion(request("done?")).await.unwrap();
+        assert_eq!(saved.choice.first(), AssistantContent::text("Saved."));
+        let echo = model.completion(request("hello")).await.unwrap();
+        assert_eq!(echo.choice.first(), AssistantContent::text("(mock) hello"));
+        assert!(echo.usage.input_tokens > 0);
+    }
+}
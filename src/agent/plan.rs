@@ -0,0 +1,254 @@
+//! Plan/confirm workflow: multi-step destructive requests are answered with a
+//! numbered plan first and only executed after the user approves it.
+
+use crate::memory::client::{ChatMessage, OpenRouterClient};
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+const PLANNER_PROMPT: &str = "You are planning, not executing. Break the user's request into a short numbered list of concrete steps (at most 10) that an assistant with file, shell, web and messaging tools would carry out. Call out any destructive or irreversible step explicitly. Reply with the numbered list only.";
+
+/// Heuristics deciding which requests need an approved plan before execution.
+#[derive(Clone, Debug)]
+pub struct PlanPolicy {
+    pub enabled: bool,
+    pub destructive_keywords: Vec<String>,
+    pub multi_step_markers: Vec<String>,
+}
+
+impl PlanPolicy {
+    /// A request needs a plan when it mentions a destructive action and also
+    /// looks multi-step (explicit sequencing markers or several sentences).
+    pub fn requires_plan(&self, text: &str) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let lower = format!(" {} ", text.to_lowercase());
+        let destructive = self
+            .destructive_keywords
+            .iter()
+            .any(|kw| contains_word(&lower, kw));
+        if !destructive {
+            return false;
+        }
+        let marked = self
+            .multi_step_markers
+            .iter()
+            .any(|m| lower.contains(&m.to_lowercase()));
+        let sentences = text
+            .split(['.', '!', '?', '\n'])
+            .filter(|s| s.split_whitespace().count() >= 3)
+            .count();
+        marked || sentences >= 3
+    }
+}
+
+fn contains_word(haystack: &str, word: &str) -> bool {
+    let word = word.trim().to_lowercase();
+    if word.is_empty() {
+        return false;
+    }
+    haystack.match_indices(&word).any(|(idx, _)| {
+        let before = haystack[..idx].chars().next_back();
+        let after = haystack[idx + word.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct PendingPlan {
+    pub request: String,
+    pub steps: Vec<String>,
+    pub checklist: PathBuf,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PlanReply {
+    Approve,
+    Reject,
+    Other,
+}
+
+pub fn classify_reply(text: &str) -> PlanReply {
+    let normalized = text.trim().trim_end_matches(['.', '!']).to_lowercase();
+    match normalized.as_str() {
+        "yes" | "y" | "ok" | "okay" | "go" | "go ahead" | "proceed" | "approve" | "approved"
+        | "confirm" | "do it" | "sure" => PlanReply::Approve,
+        "no" | "n" | "cancel" | "stop" | "abort" | "reject" | "don't" | "dont" => PlanReply::Reject,
+        _ => PlanReply::Other,
+    }
+}
+
+/// Tracks plans awaiting confirmation, one per session.
+pub struct PlanTracker {
+    plans_dir: PathBuf,
+    pending: Mutex<HashMap<String, PendingPlan>>,
+}
+
+impl PlanTracker {
+    pub fn new(workspace_dir: &Path) -> Self {
+        Self {
+            plans_dir: workspace_dir.join("plans"),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn take(&self, session_key: &str) -> Option<PendingPlan> {
+        self.pending.lock().await.remove(session_key)
+    }
+
+    /// Record a new plan for `session_key` and write its checklist file.
+    pub async fn propose(
+        &self,
+        session_key: &str,
+        request: &str,
+        steps: Vec<String>,
+    ) -> Result<PendingPlan> {
+        fs::create_dir_all(&self.plans_dir)?;
+        let stem = crate::history::sanitize_file_stem(session_key);
+        let checklist = self.plans_dir.join(format!(
+            "{}-{}.md",
+            Local::now().format("%Y%m%d-%H%M%S"),
+            stem
+        ));
+        let mut body = format!(
+            "# Plan\n\nRequest: {}\n\nStatus: awaiting confirmation\n\n",
+            request.trim()
+        );
+        for (idx, step) in steps.iter().enumerate() {
+            body.push_str(&format!("- [ ] {}. {}\n", idx + 1, step));
+        }
+        fs::write(&checklist, body)?;
+
+        let plan = PendingPlan {
+            request: request.to_string(),
+            steps,
+            checklist,
+        };
+        self.pending
+            .lock()
+            .await
+            .insert(session_key.to_string(), plan.clone());
+        Ok(plan)
+    }
+}
+
+pub fn set_checklist_status(plan: &PendingPlan, status: &str) {
+    if let Ok(content) = fs::read_to_string(&plan.checklist) {
+        let updated = content.replacen(
+            "Status: awaiting confirmation",
+            &format!("Status: {status}"),
+            1,
+        );
+        let _ = fs::write(&plan.checklist, updated);
+    }
+}
+
+/// Ask the model (without tools) for a numbered plan.
+pub async fn draft_plan(
+    client: &OpenRouterClient,
+    model: &str,
+    request: &str,
+) -> Result<Vec<String>> {
+    let response = client
+        .chat_completion(
+            model,
+            vec![
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: PLANNER_PROMPT.to_string(),
+                },
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: request.to_string(),
+                },
+            ],
+            600,
+            0.2,
+            None,
+        )
+        .await?;
+    let steps = parse_plan_steps(&response);
+    if steps.is_empty() {
+        return Err(anyhow!("planner returned no steps"));
+    }
+    Ok(steps)
+}
+
+pub fn render_plan_for_confirmation(plan: &PendingPlan) -> String {
+    let mut out = String::from(
+        "This request has several steps, some of them destructive. Here is my plan:\n\n",
+    );
+    for (idx, step) in plan.steps.iter().enumerate() {
+        out.push_str(&format!("{}. {}\n", idx + 1, step));
+    }
+    out.push_str("\nReply **yes** to execute it or **no** to cancel.");
+    out
+}
+
+pub fn execution_prompt(plan: &PendingPlan) -> String {
+    let mut out = format!("{}\n\n[Approved plan - execute now]\n", plan.request.trim());
+    for (idx, step) in plan.steps.iter().enumerate() {
+        out.push_str(&format!("{}. {}\n", idx + 1, step));
+    }
+    out.push_str(&format!(
+        "\nThe user approved this plan. Execute it step by step. After each completed step, mark it as done (change `- [ ]` to `- [x]`) in the checklist at {} using edit_file. Stop and report if a step fails.",
+        plan.checklist.display()
+    ));
+    out
+}
+
+/// Extract steps from a numbered or bulleted list, ignoring any preamble.
+fn parse_plan_steps(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| {
+            let trimmed = line.trim();
+            let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+            let rest = if digits > 0 {
+                trimmed[digits..].strip_prefix(['.', ')'])?
+            } else {
+                trimmed.strip_prefix(['-', '*'])?
+            };
+            let step = rest.trim();
+            (!step.is_empty()).then(|| step.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify_reply, parse_plan_steps, PlanPolicy, PlanReply};
+
+    fn policy() -> PlanPolicy {
+        PlanPolicy {
+            enabled: true,
+            destructive_keywords: vec!["delete".to_string(), "rm".to_string()],
+            multi_step_markers: vec![" then ".to_string()],
+        }
+    }
+
+    #[test]
+    fn requires_plan_for_multi_step_destructive_requests() {
+        let p = policy();
+        assert!(p.requires_plan("archive the logs then delete the old folder"));
+        assert!(!p.requires_plan("delete the old folder"));
+        assert!(!p.requires_plan("copy the logs then zip them"));
+        assert!(!p.requires_plan("confirm the form then submit it"));
+    }
+
+    #[test]
+    fn parses_numbered_steps() {
+        let steps = parse_plan_steps("Plan:\n1. Back up files\n2) Delete tmp\n\n- Notify user");
+        assert_eq!(steps, vec!["Back up files", "Delete tmp", "Notify user"]);
+    }
+
+    #[test]
+    fn classifies_replies() {
+        assert_eq!(classify_reply("Yes!"), PlanReply::Approve);
+        assert_eq!(classify_reply("cancel"), PlanReply::Reject);
+        assert_eq!(classify_reply("what about step 2?"), PlanReply::Other);
+    }
+}
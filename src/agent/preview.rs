@@ -0,0 +1,154 @@
+//! Short previews of long replies. With `/preview <chars>` a chat gets the
+//! start of a long answer plus a "Show full answer" button; the full text is
+//! held here for a while and sent when the button is pressed. Delivery
+//! reports tell which channel messages carried which preview, so a press on
+//! an older preview expands that one.
+
+use crate::bus::{DeliveryReport, MessageBus};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+
+/// Label of the button under a preview; a press comes back as this text.
+pub const SHOW_FULL: &str = "Show full answer";
+/// How long full answers are kept for expanding.
+const TTL: Duration = Duration::from_secs(10 * 60);
+/// Fewest characters a preview may be set to.
+pub const MIN_CHARS: usize = 200;
+
+struct Held {
+    session_key: String,
+    correlation_id: String,
+    message_ids: Vec<String>,
+    text: String,
+    at: Instant,
+}
+
+#[derive(Clone, Default)]
+pub struct Previews {
+    held: Arc<Mutex<Vec<Held>>>,
+}
+
+impl Previews {
+    /// Follow delivery reports on `bus` in the background.
+    pub fn watch(bus: &MessageBus) -> Self {
+        let previews = Self::default();
+        let watcher = previews.clone();
+        let mut reports = bus.subscribe_deliveries();
+        tokio::spawn(async move {
+            loop {
+                match reports.recv().await {
+                    Ok(report) => watcher.delivered(&report),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+        previews
+    }
+
+    /// Keep `text`, the full reply of the turn `correlation_id`, for expanding.
+    pub fn hold(&self, session_key: &str, correlation_id: &str, text: String) {
+        let Ok(mut held) = self.held.lock() else {
+            return;
+        };
+        held.retain(|h| h.at.elapsed() < TTL);
+        held.push(Held {
+            session_key: session_key.to_string(),
+            correlation_id: correlation_id.to_string(),
+            message_ids: Vec::new(),
+            text,
+            at: Instant::now(),
+        });
+    }
+
+    fn delivered(&self, report: &DeliveryReport) {
+        let (Some(correlation_id), true) = (&report.correlation_id, report.delivered) else {
+            return;
+        };
+        let Ok(mut held) = self.held.lock() else {
+            return;
+        };
+        if let Some(entry) = held
+            .iter_mut()
+            .find(|h| &h.correlation_id == correlation_id)
+        {
+            entry.message_ids.extend(report.message_ids.iter().cloned());
+        }
+    }
+
+    /// Full text for a "Show full answer" press in `session_key`: the preview
+    /// whose message `reply_to` names, else the chat's latest one.
+    pub fn expand(&self, session_key: &str, reply_to: Option<&str>) -> Option<String> {
+        let mut held = self.held.lock().ok()?;
+        held.retain(|h| h.at.elapsed() < TTL);
+        let in_chat = || held.iter().rev().filter(|h| h.session_key == session_key);
+        reply_to
+            .and_then(|id| in_chat().find(|h| h.message_ids.iter().any(|m| m == id)))
+            .or_else(|| in_chat().next())
+            .map(|h| h.text.clone())
+    }
+}
+
+/// The start of `text` within `max_chars`, cut after a paragraph, sentence
+/// or word, or `None` when it already fits.
+pub fn shorten(text: &str, max_chars: usize) -> Option<String> {
+    if text.chars().count() <= max_chars {
+        return None;
+    }
+    let end = text
+        .char_indices()
+        .nth(max_chars)
+        .map_or(text.len(), |(i, _)| i);
+    let head = &text[..end];
+    // Prefer a boundary in the second half so the preview is not tiny.
+    let floor = head.len() / 2;
+    let cut = head
+        .rfind("\n\n")
+        .filter(|&i| i >= floor)
+        .or_else(|| {
+            [". ", "! ", "? ", ".\n", "\n"]
+                .iter()
+                .filter_map(|sep| head.rfind(sep).map(|i| i + 1))
+                .filter(|&i| i >= floor)
+                .max()
+        })
+        .or_else(|| head.rfind(char::is_whitespace))
+        .unwrap_or(head.len());
+    Some(format!("{} …", head[..cut].trim_end()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shorten_cuts_at_paragraph_or_sentence() {
+        assert_eq!(shorten("short", 10), None);
+        let text = format!("{}\n\nSecond paragraph goes on and on.", "a".repeat(30));
+        assert_eq!(shorten(&text, 45).unwrap(), format!("{} …", "a".repeat(30)));
+        let text = "First sentence here. Second one is much longer than that.";
+        assert_eq!(shorten(text, 30).unwrap(), "First sentence here. …");
+    }
+
+    #[test]
+    fn expand_prefers_the_pressed_preview() {
+        let previews = Previews::default();
+        previews.hold("telegram:1", "a", "first full".to_string());
+        previews.hold("telegram:1", "b", "second full".to_string());
+        previews.delivered(&DeliveryReport {
+            channel: "telegram".to_string(),
+            chat_id: "1".to_string(),
+            content: "first".to_string(),
+            correlation_id: Some("a".to_string()),
+            delivered: true,
+            error: None,
+            message_ids: vec!["10".to_string()],
+        });
+
+        let expand = |id| previews.expand("telegram:1", id);
+        assert_eq!(expand(Some("10")).as_deref(), Some("first full"));
+        assert_eq!(expand(None).as_deref(), Some("second full"));
+        assert_eq!(previews.expand("telegram:2", None), None);
+    }
+}
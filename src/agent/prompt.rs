@@ -0,0 +1,174 @@
+//! Operator-editable system prompt. `prompt.md` in the workspace
+//! (`agents.defaults.system_prompt_file`), or else the inline
+//! `agents.defaults.system_prompt`, is read at the start of every turn, so
+//! edits apply to the next message without a restart. Placeholders such as
+//! `{workspace}`, `{date}` and `{channel}` are filled in per turn, and
+//! `{{name}}` session variables work as in messages.
+//!
+//! With `system_prompt_mode = "extend"` (the default) the text follows the
+//! built-in rules; with `"replace"` it stands in for them.
+
+use crate::bus::InboundMessage;
+use crate::config::{AppConfig, Persona};
+use crate::variables::{self, Variables};
+use std::path::PathBuf;
+use tracing::warn;
+
+/// Longer prompts are cut so a stray file cannot flood every request.
+const MAX_PROMPT_CHARS: usize = 16_000;
+
+/// Where the prompt file is looked for.
+fn path(cfg: &AppConfig) -> PathBuf {
+    if cfg.system_prompt_file.is_absolute() {
+        cfg.system_prompt_file.clone()
+    } else {
+        cfg.workspace_dir.join(&cfg.system_prompt_file)
+    }
+}
+
+/// The raw custom prompt: the prompt file when it exists and is not empty,
+/// else the configured text.
+pub fn load(cfg: &AppConfig) -> Option<String> {
+    let path = path(cfg);
+    let from_file = match std::fs::read_to_string(&path) {
+        Ok(text) => Some(text),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => {
+            warn!("cannot read system prompt {}: {err}", path.display());
+            None
+        }
+    };
+    let text = from_file
+        .filter(|text| !text.trim().is_empty())
+        .or_else(|| cfg.system_prompt.clone())?;
+    let text = text.trim();
+    if text.chars().count() > MAX_PROMPT_CHARS {
+        warn!("system prompt is longer than {MAX_PROMPT_CHARS} characters; cutting it");
+        return Some(text.chars().take(MAX_PROMPT_CHARS).collect());
+    }
+    Some(text.to_string())
+}
+
+/// Values for the single-brace placeholders of a turn in `msg`'s chat;
+/// `session_vars` are the chat's resolved `{{name}}` variables.
+pub fn turn_vars(cfg: &AppConfig, msg: &InboundMessage, session_vars: &Variables) -> Variables {
+    let computed = |name: &str| session_vars.get(name).cloned().unwrap_or_default();
+    Variables::from([
+        (
+            "workspace".to_string(),
+            cfg.workspace_dir.display().to_string(),
+        ),
+        ("date".to_string(), computed("today")),
+        ("weekday".to_string(), computed("weekday")),
+        ("time".to_string(), computed("time")),
+        ("channel".to_string(), msg.channel.clone()),
+        ("chat_id".to_string(), msg.chat_id.clone()),
+        ("sender_id".to_string(), msg.sender_id.clone()),
+    ])
+}
+
+/// `template` with `{{name}}` session variables and then `{name}`
+/// placeholders filled in. Unknown names are left as written, so braces in
+/// examples or JSON survive.
+pub fn render(template: &str, session_vars: &Variables, turn_vars: &Variables) -> String {
+    let template = variables::render(template, session_vars);
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(double) = after.strip_prefix('{') {
+            out.push_str("{{");
+            rest = double;
+            continue;
+        }
+        let value = after
+            .find('}')
+            .and_then(|end| turn_vars.get(after[..end].trim()).map(|value| (end, value)));
+        match value {
+            Some((end, value)) => {
+                out.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The `[Persona]` block of a turn: the persona's instructions and which
+/// tools it leaves out.
+pub fn persona_section(
+    name: &str,
+    persona: &Persona,
+    session_vars: &Variables,
+    turn_vars: &Variables,
+) -> String {
+    let mut section = format!("[Persona: {name}]");
+    if !persona.prompt.is_empty() {
+        section.push('\n');
+        section.push_str(&render(&persona.prompt, session_vars, turn_vars));
+    }
+    if !persona.tools.is_empty() {
+        section.push_str(&format!(
+            "\nOnly these tools are available: {}.",
+            persona.tools.join(", ")
+        ));
+    }
+    if !persona.deny_tools.is_empty() {
+        section.push_str(&format!(
+            "\nDo not call these tools; they are not available here: {}.",
+            persona.deny_tools.join(", ")
+        ));
+    }
+    section.push_str("\n\n");
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load, render, turn_vars};
+    use crate::bus::InboundMessage;
+    use crate::config::AppConfig;
+    use crate::variables::Variables;
+
+    #[test]
+    fn prompt_file_wins_and_placeholders_are_filled() {
+        let dir = std::env::temp_dir().join(format!("femtobot-prompt-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut cfg = AppConfig::defaults();
+        cfg.workspace_dir = dir.clone();
+        assert_eq!(load(&cfg), None);
+        cfg.system_prompt = Some("Be brief.".to_string());
+        assert_eq!(load(&cfg).as_deref(), Some("Be brief."));
+
+        std::fs::write(
+            dir.join("prompt.md"),
+            "Files live in {workspace}. Today is {date} on {channel}, {{user_name}}.\nReply as {\"ok\": true} or {unknown}.\n",
+        )
+        .unwrap();
+        let template = load(&cfg).unwrap();
+        let session_vars = Variables::from([
+            ("today".to_string(), "2026-10-18".to_string()),
+            ("user_name".to_string(), "Sam".to_string()),
+        ]);
+        let msg = InboundMessage {
+            channel: "telegram".to_string(),
+            chat_id: "42".to_string(),
+            ..Default::default()
+        };
+        let vars = turn_vars(&cfg, &msg, &session_vars);
+        assert_eq!(
+            render(&template, &session_vars, &vars),
+            format!(
+                "Files live in {}. Today is 2026-10-18 on telegram, Sam.\nReply as {{\"ok\": true}} or {{unknown}}.",
+                dir.display()
+            )
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
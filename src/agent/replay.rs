@@ -0,0 +1,332 @@
+//! Provider record/replay. In `record` mode every completion request and
+//! response is written (with configured secrets redacted) as a numbered JSON
+//! exchange in the cassette directory; in `replay` mode those exchanges are
+//! served back instead of calling the provider, so agent changes can be
+//! tested deterministically and odd model behaviour debugged offline.
+
+use crate::config::AppConfig;
+use crate::tools::middleware;
+use anyhow::{anyhow, Result};
+use rig::completion::message::{AssistantContent, Message};
+use rig::completion::{
+    CompletionError, CompletionModel, CompletionRequest, CompletionResponse, Usage,
+};
+use rig::one_or_many::OneOrMany;
+use rig::streaming::StreamingCompletionResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+const REDACTED: &str = "[REDACTED]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Record,
+    Replay,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Exchange {
+    version: i32,
+    model: String,
+    /// Hash of the newest request message, used to line replays up with
+    /// the request that produced them.
+    fingerprint: String,
+    request: Value,
+    choice: Vec<AssistantContent>,
+    usage: Usage,
+    raw_response: Value,
+}
+
+struct CassetteInner {
+    mode: Mode,
+    dir: PathBuf,
+    secrets: Vec<String>,
+    next_seq: AtomicUsize,
+    /// Recorded exchanges not yet served, in recording order.
+    pending: Mutex<Option<Vec<(PathBuf, Exchange)>>>,
+}
+
+/// Shared recorder/replayer for every model route.
+#[derive(Clone)]
+pub struct Cassette {
+    inner: Arc<CassetteInner>,
+}
+
+impl Cassette {
+    /// Cassette for `provider_mode` = "record" or "replay"; `None` when off.
+    pub fn from_config(cfg: &AppConfig) -> Option<Self> {
+        let mode = match cfg.provider_mode.as_str() {
+            "record" => Mode::Record,
+            "replay" => Mode::Replay,
+            _ => return None,
+        };
+        let dir = cfg.cassette_dir.clone();
+        let next_seq = fs::read_dir(&dir).map(|e| e.count()).unwrap_or(0) + 1;
+        let secrets = cfg.secret_values();
+        info!(
+            "provider {} mode, cassette dir {}",
+            if mode == Mode::Record {
+                "record"
+            } else {
+                "replay"
+            },
+            dir.display()
+        );
+        Some(Self {
+            inner: Arc::new(CassetteInner {
+                mode,
+                dir,
+                secrets,
+                next_seq: AtomicUsize::new(next_seq),
+                pending: Mutex::new(None),
+            }),
+        })
+    }
+
+    pub fn is_replay(&self) -> bool {
+        self.inner.mode == Mode::Replay
+    }
+
+    fn record(
+        &self,
+        model: &str,
+        request: &CompletionRequest,
+        choice: Vec<AssistantContent>,
+        usage: Usage,
+        raw: Value,
+    ) -> Result<()> {
+        let exchange = Exchange {
+            version: 1,
+            model: model.to_string(),
+            fingerprint: fingerprint(request),
+            request: request_json(request),
+            choice,
+            usage,
+            raw_response: raw,
+        };
+        let mut text = serde_json::to_string_pretty(&exchange)?;
+        for secret in &self.inner.secrets {
+            text = text.replace(secret.as_str(), REDACTED);
+        }
+        fs::create_dir_all(&self.inner.dir)?;
+        let seq = self.inner.next_seq.fetch_add(1, Ordering::SeqCst);
+        let path = self.inner.dir.join(format!(
+            "{seq:05}-{}.json",
+            crate::history::sanitize_file_stem(model)
+        ));
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Next recorded exchange for `model`: preferably one whose fingerprint
+    /// matches the request, otherwise the oldest unserved one.
+    fn replay(&self, model: &str, request: &CompletionRequest) -> Result<Exchange> {
+        let mut pending = self
+            .inner
+            .pending
+            .lock()
+            .map_err(|_| anyhow!("replay state poisoned"))?;
+        let exchanges = match pending.as_mut() {
+            Some(exchanges) => exchanges,
+            None => pending.insert(self.load()?),
+        };
+        let wanted = fingerprint(request);
+        let position = exchanges
+            .iter()
+            .position(|(_, e)| e.model == model && e.fingerprint == wanted)
+            .or_else(|| {
+                let fallback = exchanges.iter().position(|(_, e)| e.model == model);
+                if let Some(idx) = fallback {
+                    warn!(
+                        "replay: no exchange matches the request, serving {}",
+                        exchanges[idx].0.display()
+                    );
+                }
+                fallback
+            })
+            .ok_or_else(|| anyhow!("replay cassette has no more exchanges for {model}"))?;
+        Ok(exchanges.remove(position).1)
+    }
+
+    fn load(&self) -> Result<Vec<(PathBuf, Exchange)>> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(&self.inner.dir)
+            .map_err(|err| {
+                anyhow!(
+                    "cannot read cassette dir {}: {err}",
+                    self.inner.dir.display()
+                )
+            })?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+        paths
+            .into_iter()
+            .map(|path| {
+                let exchange = serde_json::from_str(&fs::read_to_string(&path)?)
+                    .map_err(|err| anyhow!("invalid exchange {}: {err}", path.display()))?;
+                Ok((path, exchange))
+            })
+            .collect()
+    }
+}
+
+/// Completion model wrapper that offers the model only the tools the turn's
+/// persona allows, and records or replays through a [`Cassette`]; a plain
+/// pass-through otherwise.
+#[derive(Clone)]
+pub struct Recorded<M> {
+    inner: M,
+    model: String,
+    cassette: Option<Cassette>,
+}
+
+impl<M> Recorded<M> {
+    pub fn new(inner: M, model: &str, cassette: Option<Cassette>) -> Self {
+        Self {
+            inner,
+            model: model.to_string(),
+            cassette,
+        }
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for Recorded<M> {
+    type Response = M::Response;
+    type StreamingResponse = M::StreamingResponse;
+    type Client = M::Client;
+
+    fn make(client: &Self::Client, model: impl Into<String>) -> Self {
+        let model = model.into();
+        Self::new(M::make(client, model.clone()), &model, None)
+    }
+
+    async fn completion(
+        &self,
+        mut request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        middleware::offered(&mut request.tools);
+        let Some(cassette) = &self.cassette else {
+            return self.inner.completion(request).await;
+        };
+        if cassette.is_replay() {
+            let exchange = cassette
+                .replay(&self.model, &request)
+                .map_err(|err| CompletionError::ProviderError(err.to_string()))?;
+            let choice = OneOrMany::many(exchange.choice).map_err(|_| {
+                CompletionError::ResponseError("recorded exchange has no content".to_string())
+            })?;
+            return Ok(CompletionResponse {
+                choice,
+                usage: exchange.usage,
+                raw_response: serde_json::from_value(exchange.raw_response)?,
+            });
+        }
+        let snapshot = request.clone();
+        let response = self.inner.completion(request).await?;
+        let raw = serde_json::to_value(&response.raw_response).unwrap_or(Value::Null);
+        let choice = response.choice.iter().cloned().collect();
+        if let Err(err) = cassette.record(&self.model, &snapshot, choice, response.usage, raw) {
+            warn!("failed to record provider exchange: {err}");
+        }
+        Ok(response)
+    }
+
+    async fn stream(
+        &self,
+        mut request: CompletionRequest,
+    ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        middleware::offered(&mut request.tools);
+        if self.cassette.as_ref().is_some_and(Cassette::is_replay) {
+            return Err(CompletionError::ProviderError(
+                "streaming is not supported in replay mode".to_string(),
+            ));
+        }
+        self.inner.stream(request).await
+    }
+}
+
+fn request_json(request: &CompletionRequest) -> Value {
+    let history: Vec<&Message> = request.chat_history.iter().collect();
+    serde_json::json!({
+        "preamble": request.preamble,
+        "chat_history": history,
+        "documents": request.documents,
+        "tools": request.tools.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+        "temperature": request.temperature,
+        "max_tokens": request.max_tokens,
+        "additional_params": request.additional_params,
+    })
+}
+
+fn fingerprint(request: &CompletionRequest) -> String {
+    let last = request.chat_history.iter().last();
+    let text = serde_json::to_string(&last).unwrap_or_default();
+    hex::encode(&Sha256::digest(text.as_bytes())[..8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cassette;
+    use crate::config::AppConfig;
+    use rig::completion::message::{AssistantContent, Message};
+    use rig::completion::{CompletionRequest, Usage};
+    use rig::one_or_many::OneOrMany;
+
+    fn request(text: &str) -> CompletionRequest {
+        CompletionRequest {
+            preamble: Some("sk-test-secret-key in preamble".to_string()),
+            chat_history: OneOrMany::one(Message::user(text)),
+            documents: Vec::new(),
+            tools: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            tool_choice: None,
+            additional_params: None,
+        }
+    }
+
+    #[test]
+    fn replays_recorded_exchanges_with_secrets_redacted() {
+        let dir = std::env::temp_dir().join(format!("femtobot-cassette-{}", std::process::id()));
+        let mut cfg = AppConfig::defaults();
+        cfg.cassette_dir = dir.clone();
+        cfg.openrouter_api_key = "sk-test-secret-key".to_string();
+
+        cfg.provider_mode = "record".to_string();
+        let recorder = Cassette::from_config(&cfg).unwrap();
+        for text in ["first", "second"] {
+            let usage = Usage::new();
+            let choice = vec![AssistantContent::text(format!("reply to {text}"))];
+            recorder
+                .record("m", &request(text), choice, usage, serde_json::Value::Null)
+                .unwrap();
+        }
+        let recorded = std::fs::read_dir(&dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let text = std::fs::read_to_string(recorded).unwrap();
+        assert!(text.contains("[REDACTED] in preamble") && !text.contains("sk-test"));
+
+        cfg.provider_mode = "replay".to_string();
+        let player = Cassette::from_config(&cfg).unwrap();
+        let second = player.replay("m", &request("second")).unwrap();
+        assert_eq!(
+            second.choice,
+            vec![AssistantContent::text("reply to second")]
+        );
+        let first = player.replay("m", &request("unmatched")).unwrap();
+        assert_eq!(first.choice, vec![AssistantContent::text("reply to first")]);
+        assert!(player.replay("m", &request("first")).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
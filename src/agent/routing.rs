@@ -0,0 +1,214 @@
+//! Picks the cheap or the strong model route for a request. Clear cases are
+//! decided by length and keywords; the rest are compared against a few
+//! example requests by embedding similarity when vector memory is on.
+
+use crate::memory::vector_store::{cosine_similarity, EmbeddingService};
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    Cheap,
+    Strong,
+}
+
+/// Per-chat override set with `/route`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteMode {
+    Auto,
+    Cheap,
+    Strong,
+}
+
+impl RouteMode {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_lowercase().as_str() {
+            "" | "auto" => Some(Self::Auto),
+            "cheap" | "fast" => Some(Self::Cheap),
+            "strong" | "best" => Some(Self::Strong),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Cheap => "cheap",
+            Self::Strong => "strong",
+        }
+    }
+}
+
+/// Whole messages that never need the strong model.
+const TRIVIAL: &[&str] = &[
+    "ok",
+    "okay",
+    "k",
+    "thanks",
+    "thank you",
+    "thx",
+    "ty",
+    "cool",
+    "nice",
+    "great",
+    "yes",
+    "no",
+    "yep",
+    "nope",
+    "sure",
+    "hi",
+    "hello",
+    "hey",
+    "good morning",
+    "good night",
+    "lol",
+    "got it",
+    "perfect",
+    "done",
+];
+
+/// Words that signal multi-step work, code or analysis.
+const COMPLEX: &[&str] = &[
+    "analyze",
+    "analyse",
+    "implement",
+    "refactor",
+    "debug",
+    "write",
+    "draft",
+    "plan",
+    "compare",
+    "research",
+    "explain why",
+    "step by step",
+    "summarize",
+    "summarise",
+    "review",
+    "fix",
+    "design",
+    "calculate",
+    "translate",
+    "schedule",
+    "remind",
+    "search",
+    "browse",
+    "http://",
+    "https://",
+    "```",
+];
+
+const CHEAP_EXAMPLES: &[&str] = &[
+    "thanks, that's all",
+    "what time is it in Tokyo?",
+    "how do you spell necessary",
+    "what's the capital of Australia",
+    "sounds good, see you tomorrow",
+];
+
+const STRONG_EXAMPLES: &[&str] = &[
+    "go through my notes and put together a plan for next week",
+    "why does this function deadlock when two requests arrive at once",
+    "write an email to the landlord about the broken heating",
+    "compare these two job offers and tell me which is better",
+    "find the cheapest flight options and track the prices",
+];
+
+/// Cheap similarity must beat strong similarity by this much.
+const EMBEDDING_MARGIN: f32 = 0.03;
+
+pub struct RouteClassifier {
+    max_cheap_chars: usize,
+    embedder: Option<EmbeddingService>,
+}
+
+impl RouteClassifier {
+    pub fn new(max_cheap_chars: usize, embedder: Option<EmbeddingService>) -> Self {
+        Self {
+            max_cheap_chars,
+            embedder,
+        }
+    }
+
+    pub async fn classify(&self, text: &str) -> Tier {
+        if let Some(tier) = classify_by_rules(text, self.max_cheap_chars) {
+            return tier;
+        }
+        let Some(embedder) = &self.embedder else {
+            return Tier::Strong;
+        };
+        match self.closest(embedder, text).await {
+            Ok(tier) => tier,
+            Err(err) => {
+                warn!("route classification failed, using strong route: {err}");
+                Tier::Strong
+            }
+        }
+    }
+
+    async fn closest(&self, embedder: &EmbeddingService, text: &str) -> anyhow::Result<Tier> {
+        let query = embedder.embed(text).await?;
+        let cheap = best_similarity(embedder, &query, CHEAP_EXAMPLES).await?;
+        let strong = best_similarity(embedder, &query, STRONG_EXAMPLES).await?;
+        Ok(if cheap > strong + EMBEDDING_MARGIN {
+            Tier::Cheap
+        } else {
+            Tier::Strong
+        })
+    }
+}
+
+/// Highest similarity of `query` to any example. Example embeddings are
+/// cached by the embedding service after the first request.
+async fn best_similarity(
+    embedder: &EmbeddingService,
+    query: &[f32],
+    examples: &[&str],
+) -> anyhow::Result<f32> {
+    let mut best = f32::MIN;
+    for example in examples {
+        let vector = embedder.embed(example).await?;
+        best = best.max(cosine_similarity(query, &vector));
+    }
+    Ok(best)
+}
+
+/// `Some` when length and keywords settle it.
+fn classify_by_rules(text: &str, max_cheap_chars: usize) -> Option<Tier> {
+    let normalized = text
+        .trim()
+        .trim_end_matches(['.', '!', '?'])
+        .trim()
+        .to_lowercase();
+    if TRIVIAL.contains(&normalized.as_str()) {
+        return Some(Tier::Cheap);
+    }
+    if normalized.chars().count() > max_cheap_chars
+        || normalized.lines().count() > 2
+        || COMPLEX.iter().any(|word| normalized.contains(word))
+    {
+        return Some(Tier::Strong);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RouteClassifier, Tier};
+
+    #[tokio::test]
+    async fn routes_trivial_and_complex_requests() {
+        let classifier = RouteClassifier::new(200, None);
+        assert_eq!(classifier.classify("Thanks!").await, Tier::Cheap);
+        assert_eq!(
+            classifier
+                .classify("refactor the parser in src/lib.rs")
+                .await,
+            Tier::Strong
+        );
+        assert_eq!(classifier.classify(&"word ".repeat(60)).await, Tier::Strong);
+        // Undecided without embeddings: stay on the strong route.
+        assert_eq!(
+            classifier.classify("what's the tallest mountain").await,
+            Tier::Strong
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Streamed turns: the tool loop runs through rig's streaming API so the
+//! reply is published as [`LiveReply`] partials while it is generated, not
+//! only after each model response. The stream does not hand back the turn's
+//! messages, so they are rebuilt here in the shape the non-streaming loop
+//! leaves in the history (validation, tool-call reports and nudges read
+//! them).
+
+use super::live::LiveReply;
+use futures_util::StreamExt;
+use rig::agent::{Agent, MultiTurnStreamItem, PromptResponse, StreamingError};
+use rig::completion::message::{AssistantContent, Message, Reasoning, UserContent};
+use rig::completion::{CompletionModel, GetTokenUsage, PromptError, Usage};
+use rig::one_or_many::OneOrMany;
+use rig::streaming::{StreamedAssistantContent, StreamedUserContent, StreamingPrompt};
+
+/// Run one turn of `agent` on `prompt` streamed, appending the turn's
+/// messages to `history` like `prompt().with_history()` does.
+pub async fn prompt_streamed<M>(
+    agent: &Agent<M>,
+    prompt: Message,
+    history: &mut Vec<Message>,
+    max_turns: usize,
+    live: &LiveReply,
+) -> Result<PromptResponse, PromptError>
+where
+    M: CompletionModel + 'static,
+    M::StreamingResponse: GetTokenUsage + Send,
+{
+    let mut stream = agent
+        .stream_prompt(prompt.clone())
+        .with_history(history.clone())
+        .multi_turn(max_turns)
+        .await;
+    let mut turn = TurnMessages::default();
+    history.push(prompt);
+    let mut usage = Usage::new();
+    let mut output = None;
+    while let Some(item) = stream.next().await {
+        match item.map_err(prompt_error)? {
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(text)) => {
+                turn.text(&text.text);
+                live.show(&turn.segment);
+            }
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Reasoning(
+                reasoning,
+            )) => turn.reasoning = Some(reasoning),
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::ToolCall {
+                tool_call,
+                ..
+            }) => {
+                if !turn.after_call {
+                    live.append(&turn.segment);
+                }
+                turn.tool_call(AssistantContent::ToolCall(tool_call));
+            }
+            MultiTurnStreamItem::StreamUserItem(StreamedUserContent::ToolResult {
+                tool_result,
+                ..
+            }) => turn.messages.push(Message::User {
+                content: OneOrMany::one(UserContent::ToolResult(tool_result)),
+            }),
+            MultiTurnStreamItem::FinalResponse(last) => {
+                usage = last.usage();
+                output = Some(last.response().to_string());
+            }
+            _ => {}
+        }
+    }
+    if !turn.after_call {
+        live.append(&turn.segment);
+    }
+    let output = output.unwrap_or_else(|| turn.segment.clone());
+    history.extend(turn.finish());
+    Ok(PromptResponse::new(output, usage))
+}
+
+fn prompt_error(err: StreamingError) -> PromptError {
+    match err {
+        StreamingError::Completion(err) => PromptError::CompletionError(err),
+        StreamingError::Prompt(err) => *err,
+        StreamingError::Tool(err) => PromptError::ToolError(err),
+    }
+}
+
+/// The messages of a streamed turn as they arrive: each tool call becomes
+/// an assistant message (with the text and reasoning written before it),
+/// followed by its result.
+#[derive(Default)]
+struct TurnMessages {
+    messages: Vec<Message>,
+    /// Text of the model response in progress.
+    segment: String,
+    reasoning: Option<Reasoning>,
+    /// Whether `segment` belongs to a response already ended by a tool call.
+    after_call: bool,
+}
+
+impl TurnMessages {
+    fn text(&mut self, delta: &str) {
+        if self.after_call {
+            self.segment.clear();
+            self.after_call = false;
+        }
+        self.segment.push_str(delta);
+    }
+
+    fn tool_call(&mut self, call: AssistantContent) {
+        let mut content = Vec::new();
+        if let Some(reasoning) = self.reasoning.take() {
+            content.push(AssistantContent::Reasoning(reasoning));
+        }
+        if !self.after_call && !self.segment.trim().is_empty() {
+            content.push(AssistantContent::text(self.segment.clone()));
+        }
+        content.push(call);
+        self.after_call = true;
+        if let Ok(content) = OneOrMany::many(content) {
+            self.messages.push(Message::Assistant { id: None, content });
+        }
+    }
+
+    /// The messages, ending with the final reply text.
+    fn finish(mut self) -> Vec<Message> {
+        if !self.after_call && !self.segment.trim().is_empty() {
+            self.messages.push(Message::Assistant {
+                id: None,
+                content: OneOrMany::one(AssistantContent::text(self.segment)),
+            });
+        }
+        self.messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TurnMessages;
+    use rig::completion::message::{AssistantContent, Message, ToolCall, ToolFunction};
+    use serde_json::json;
+
+    #[test]
+    fn rebuilds_tool_calls_and_the_final_text() {
+        let call = |id: &str| {
+            AssistantContent::ToolCall(ToolCall::new(
+                id.to_string(),
+                ToolFunction::new("read_file".to_string(), json!({"path": "a.md"})),
+            ))
+        };
+        let mut turn = TurnMessages::default();
+        turn.text("Let me ");
+        turn.text("check.");
+        turn.tool_call(call("1"));
+        turn.tool_call(call("2"));
+        turn.text("Done: ");
+        turn.text("it says hi.");
+        assert_eq!(turn.segment, "Done: it says hi.");
+
+        let messages = turn.finish();
+        assert_eq!(messages.len(), 3);
+        let Message::Assistant { content, .. } = &messages[0] else {
+            panic!("expected an assistant message");
+        };
+        assert_eq!(content.len(), 2);
+        let Message::Assistant { content, .. } = &messages[2] else {
+            panic!("expected an assistant message");
+        };
+        assert!(
+            matches!(content.first(), AssistantContent::Text(t) if t.text == "Done: it says hi.")
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::config::{AppConfig, ModelPrice};
+use rig::completion::message::{AssistantContent, Message, ToolResultContent, UserContent};
+use rig::completion::Usage;
+use std::time::Duration;
+
+/// Characters of tool arguments/results shown per call by `/lastrun`.
+const TOOL_PREVIEW_CHARS: usize = 200;
+
+/// Built-in USD prices per million input/output tokens, matched by model
+/// name prefix (without the provider part); `pricing` in the config
+/// overrides or extends them.
+const DEFAULT_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("o4-mini", 1.10, 4.40),
+    ("claude-3.5-haiku", 0.80, 4.00),
+    ("claude-3.5-sonnet", 3.00, 15.00),
+    ("claude-sonnet-4", 3.00, 15.00),
+    ("claude-opus-4", 15.00, 75.00),
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.5-pro", 1.25, 10.00),
+];
+
+/// Price of `model`: an exact `pricing` entry first, then the longest
+/// matching prefix among configured and built-in prices.
+pub fn model_price(cfg: &AppConfig, model: &str) -> Option<ModelPrice> {
+    if let Some(price) = cfg.model_prices.get(model) {
+        return Some(*price);
+    }
+    let name = model.rsplit('/').next().unwrap_or(model);
+    let configured = cfg
+        .model_prices
+        .iter()
+        .map(|(key, price)| (key.rsplit('/').next().unwrap_or(key), *price));
+    let builtin = DEFAULT_PRICES.iter().map(|(key, input, output)| {
+        (
+            *key,
+            ModelPrice {
+                input_per_mtok: *input,
+                output_per_mtok: *output,
+            },
+        )
+    });
+    configured
+        .chain(builtin)
+        .filter(|(key, _)| name.starts_with(key))
+        .max_by_key(|(key, _)| key.len())
+        .map(|(_, price)| price)
+}
+
+/// Cost in USD of `input`/`output` tokens at `price`.
+pub fn cost_usd(price: ModelPrice, input: u64, output: u64) -> f64 {
+    (input as f64 * price.input_per_mtok + output as f64 * price.output_per_mtok) / 1_000_000.0
+}
+
+#[derive(Debug, Clone)]
+pub struct ToolCallRecord {
+    pub name: String,
+    pub arguments: String,
+    pub result: Option<String>,
+}
+
+/// What happened during one completed turn, for the reply footer and `/lastrun`.
+#[derive(Debug, Clone)]
+pub struct RunReport {
+    pub provider: String,
+    pub model: String,
+    /// Position of the answering route: 0 is the primary, 1.. are fallbacks.
+    pub route_index: usize,
+    pub usage: Usage,
+    /// Cost of `usage` when the model's price is known.
+    pub cost_usd: Option<f64>,
+    pub duration: Duration,
+    pub tool_calls: Vec<ToolCallRecord>,
+}
+
+impl RunReport {
+    pub fn footer(&self) -> String {
+        let mut parts = vec![format!("{}/{}", self.provider, self.model)];
+        if self.route_index > 0 {
+            parts.push(format!("fallback #{}", self.route_index));
+        }
+        parts.push(format!(
+            "{} tokens ({} in / {} out)",
+            self.usage.total_tokens, self.usage.input_tokens, self.usage.output_tokens
+        ));
+        if let Some(cost) = self.cost_usd {
+            parts.push(format!("${cost:.4}"));
+        }
+        parts.push(format!("{:.1}s", self.duration.as_secs_f64()));
+        if !self.tool_calls.is_empty() {
+            parts.push(format!("{} tool calls", self.tool_calls.len()));
+        }
+        format!("\n\n— {}", parts.join(" · "))
+    }
+
+    /// Compact list of the tools this turn called, e.g. `🛠 exec, web_search ×2`,
+    /// in order of first use; empty when none were.
+    pub fn tools_footer(&self) -> String {
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for call in &self.tool_calls {
+            match counts.iter_mut().find(|(name, _)| *name == call.name) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((&call.name, 1)),
+            }
+        }
+        if counts.is_empty() {
+            return String::new();
+        }
+        let tools: Vec<String> = counts
+            .into_iter()
+            .map(|(name, count)| match count {
+                1 => name.to_string(),
+                n => format!("{name} ×{n}"),
+            })
+            .collect();
+        format!("\n\n🛠 {}", tools.join(", "))
+    }
+
+    pub fn describe(&self) -> String {
+        let mut out = format!("Last run:{}", self.footer().trim_start_matches("\n\n—"));
+        if self.tool_calls.is_empty() {
+            out.push_str("\nNo tool calls.");
+            return out;
+        }
+        for (idx, call) in self.tool_calls.iter().enumerate() {
+            out.push_str(&format!(
+                "\n{}. {}({})",
+                idx + 1,
+                call.name,
+                preview(&call.arguments)
+            ));
+            match &call.result {
+                Some(result) => {
+                    out.push_str(&format!("\n   → {}", preview(&result.replace('\n', " "))))
+                }
+                None => out.push_str("\n   → (no result)"),
+            }
+        }
+        out
+    }
+}
+
+fn preview(text: &str) -> String {
+    if text.chars().count() <= TOOL_PREVIEW_CHARS {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(TOOL_PREVIEW_CHARS).collect();
+    format!("{kept}…")
+}
+
+/// Tool calls made in `messages` (the new messages of a turn), paired with
+/// their results by call id.
+pub fn collect_tool_calls(messages: &[Message]) -> Vec<ToolCallRecord> {
+    let mut calls: Vec<(String, ToolCallRecord)> = Vec::new();
+    for message in messages {
+        match message {
+            Message::Assistant { content, .. } => {
+                for item in content.iter() {
+                    if let AssistantContent::ToolCall(call) = item {
+                        calls.push((
+                            call.id.clone(),
+                            ToolCallRecord {
+                                name: call.function.name.clone(),
+                                arguments: call.function.arguments.to_string(),
+                                result: None,
+                            },
+                        ));
+                    }
+                }
+            }
+            Message::User { content } => {
+                for item in content.iter() {
+                    let UserContent::ToolResult(result) = item else {
+                        continue;
+                    };
+                    let text = result
+                        .content
+                        .iter()
+                        .filter_map(|c| match c {
+                            ToolResultContent::Text(t) => Some(t.text.as_str()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    if let Some((_, record)) = calls
+                        .iter_mut()
+                        .find(|(id, record)| *id == result.id && record.result.is_none())
+                    {
+                        record.result = Some(text);
+                    }
+                }
+            }
+        }
+    }
+    calls.into_iter().map(|(_, record)| record).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RunReport, ToolCallRecord};
+    use rig::completion::Usage;
+    use std::time::Duration;
+
+    #[test]
+    fn tools_footer_counts_calls_in_order_of_first_use() {
+        let call = |name: &str| ToolCallRecord {
+            name: name.to_string(),
+            arguments: "{}".to_string(),
+            result: None,
+        };
+        let mut report = RunReport {
+            provider: "openrouter".to_string(),
+            model: "openai/gpt-4o".to_string(),
+            route_index: 0,
+            usage: Usage::new(),
+            cost_usd: None,
+            duration: Duration::from_secs(1),
+            tool_calls: Vec::new(),
+        };
+        assert_eq!(report.tools_footer(), "");
+        report.tool_calls = vec![call("exec"), call("web_search"), call("web_search")];
+        assert_eq!(report.tools_footer(), "\n\n🛠 exec, web_search ×2");
+    }
+}
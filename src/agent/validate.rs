@@ -0,0 +1,148 @@
+//! Post-completion checks on the final reply. A completion can succeed at
+//! the HTTP level and still be useless to the user: blank, a bare refusal
+//! after tools already ran, or the tool output pasted back verbatim.
+
+use super::usage;
+use rig::completion::message::{AssistantContent, Message};
+
+/// Replies shorter than this are checked for bare refusals.
+const MAX_REFUSAL_CHARS: usize = 160;
+/// Replies shorter than this are never treated as echoed tool output.
+const MIN_ECHO_CHARS: usize = 200;
+
+const REFUSALS: &[&str] = &[
+    "i can't help with that",
+    "i cannot help with that",
+    "i can't assist with that",
+    "i cannot assist with that",
+    "i'm unable to help with that",
+    "i am unable to help with that",
+    "i can't comply",
+    "i cannot comply",
+    "i'm sorry, but i can't",
+    "i'm sorry, but i cannot",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplyIssue {
+    Empty,
+    Refusal,
+    EchoedToolOutput,
+}
+
+impl ReplyIssue {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Empty => "empty_reply",
+            Self::Refusal => "refusal",
+            Self::EchoedToolOutput => "echoed_tool_output",
+        }
+    }
+
+    /// Follow-up prompt asking the model to fix the reply.
+    pub fn nudge(self) -> &'static str {
+        match self {
+            Self::Empty => {
+                "Your last reply was empty. Answer my previous message now, using the tool results above if there are any."
+            }
+            Self::Refusal => {
+                "The tools above ran successfully at my request. Please answer my previous message using their results."
+            }
+            Self::EchoedToolOutput => {
+                "You repeated the raw tool output. Answer my previous message in your own words, keeping only what is relevant."
+            }
+        }
+    }
+}
+
+/// Why `reply` should not be delivered, given the messages of its turn.
+pub fn check_reply(reply: &str, turn_messages: &[Message]) -> Option<ReplyIssue> {
+    let reply = normalize(reply);
+    if reply.is_empty() {
+        return Some(ReplyIssue::Empty);
+    }
+    let results: Vec<String> = usage::collect_tool_calls(turn_messages)
+        .into_iter()
+        .filter_map(|call| call.result)
+        .map(|result| normalize(&result))
+        .filter(|result| !result.is_empty())
+        .collect();
+    if results.is_empty() {
+        return None;
+    }
+    let lower = reply.to_lowercase();
+    if reply.chars().count() <= MAX_REFUSAL_CHARS && REFUSALS.iter().any(|r| lower.contains(r)) {
+        return Some(ReplyIssue::Refusal);
+    }
+    if reply.chars().count() >= MIN_ECHO_CHARS
+        && results
+            .iter()
+            .any(|result| *result == reply || result.contains(&reply))
+    {
+        return Some(ReplyIssue::EchoedToolOutput);
+    }
+    None
+}
+
+/// Drop a trailing assistant message that carries no text and no tool call,
+/// which some providers reject when the history is sent back.
+pub fn trim_blank_reply(history: &mut Vec<Message>) {
+    let blank = matches!(history.last(), Some(Message::Assistant { content, .. })
+    if content.iter().all(|item| match item {
+        AssistantContent::Text(text) => text.text.trim().is_empty(),
+        AssistantContent::ToolCall(_) => false,
+        _ => true,
+    }));
+    if blank {
+        history.pop();
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_reply, ReplyIssue};
+    use rig::completion::message::{AssistantContent, Message, ToolCall, ToolFunction};
+    use rig::one_or_many::OneOrMany;
+    use serde_json::json;
+
+    fn tool_turn(result: &str) -> Vec<Message> {
+        vec![
+            Message::Assistant {
+                id: None,
+                content: OneOrMany::one(AssistantContent::ToolCall(ToolCall::new(
+                    "call_1".to_string(),
+                    ToolFunction {
+                        name: "web_fetch".to_string(),
+                        arguments: json!({"url": "https://example.com"}),
+                    },
+                ))),
+            },
+            Message::tool_result("call_1", result),
+        ]
+    }
+
+    #[test]
+    fn flags_blank_refusing_and_echoing_replies() {
+        assert_eq!(check_reply(" \n ", &[]), Some(ReplyIssue::Empty));
+        assert_eq!(check_reply("I can't help with that.", &[]), None);
+
+        let page = "Example Domain. ".repeat(20);
+        let turn = tool_turn(&page);
+        assert_eq!(
+            check_reply("I'm sorry, but I can't help with that.", &turn),
+            Some(ReplyIssue::Refusal)
+        );
+        assert_eq!(
+            check_reply(&page, &turn),
+            Some(ReplyIssue::EchoedToolOutput)
+        );
+        assert_eq!(
+            check_reply("The page is the IANA example domain placeholder.", &turn),
+            None
+        );
+    }
+}
@@ -0,0 +1,352 @@
+//! Per-week conversation statistics computed from the session transcripts
+//! (`HistoryLog`): message counts, frequent topics, tool usage and reply
+//! latency. Results are kept in `<data_dir>/analytics.db` and never leave
+//! the machine; `femtobot analytics` prints them and the `analytics` report
+//! section turns them into a weekly digest.
+
+use crate::history::{HistoryLog, LoggedMessage};
+use anyhow::{anyhow, Result};
+use chrono::Datelike;
+use chrono_tz::Tz;
+use rusqlite::{params, Connection};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Topics and tools listed per week.
+const TOP_N: usize = 5;
+/// Words shorter than this never count as topics.
+const MIN_TOPIC_CHARS: usize = 4;
+
+const STOPWORDS: &[&str] = &[
+    "about", "after", "again", "also", "been", "before", "being", "could", "does", "doing", "done",
+    "from", "have", "having", "here", "into", "just", "know", "like", "make", "more", "much",
+    "need", "only", "other", "please", "really", "should", "some", "than", "thank", "thanks",
+    "that", "their", "them", "then", "there", "these", "they", "thing", "think", "this", "those",
+    "today", "tomorrow", "want", "what", "when", "where", "which", "while", "will", "with",
+    "would", "your", "yours", "okay",
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WeekStats {
+    /// ISO week in the configured timezone, e.g. `2026-W41`.
+    pub week: String,
+    pub sessions: u64,
+    pub user_messages: u64,
+    pub assistant_messages: u64,
+    pub avg_latency_ms: Option<u64>,
+    pub p90_latency_ms: Option<u64>,
+    /// Most called tools, most frequent first.
+    pub tools: Vec<(String, u64)>,
+    /// Most frequent words in user messages, most frequent first.
+    pub topics: Vec<(String, u64)>,
+}
+
+/// SQLite store of the computed weekly statistics.
+#[derive(Clone)]
+pub struct Analytics {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Analytics {
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(data_dir)?;
+        let conn = Connection::open(data_dir.join("analytics.db"))?;
+        init_db(&conn)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    async fn with_conn<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().map_err(|e| anyhow!("mutex poisoned: {e}"))?;
+            f(&mut conn)
+        })
+        .await
+        .map_err(|e| anyhow!("blocking task failed: {e}"))?
+    }
+
+    /// Recompute every week from the transcripts and replace the stored
+    /// statistics. Returns the number of weeks written.
+    pub async fn refresh(&self, history: &HistoryLog, tz: Tz) -> Result<usize> {
+        let weeks = summarize(&history.entries(), tz);
+        let count = weeks.len();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM weeks", [])?;
+            tx.execute("DELETE FROM week_tools", [])?;
+            tx.execute("DELETE FROM week_topics", [])?;
+            for week in &weeks {
+                tx.execute(
+                    "INSERT INTO weeks (week, sessions, user_messages, assistant_messages, avg_latency_ms, p90_latency_ms) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        week.week,
+                        week.sessions,
+                        week.user_messages,
+                        week.assistant_messages,
+                        week.avg_latency_ms,
+                        week.p90_latency_ms
+                    ],
+                )?;
+                for (tool, calls) in &week.tools {
+                    tx.execute(
+                        "INSERT INTO week_tools (week, tool, calls) VALUES (?1, ?2, ?3)",
+                        params![week.week, tool, calls],
+                    )?;
+                }
+                for (topic, mentions) in &week.topics {
+                    tx.execute(
+                        "INSERT INTO week_topics (week, topic, mentions) VALUES (?1, ?2, ?3)",
+                        params![week.week, topic, mentions],
+                    )?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await?;
+        Ok(count)
+    }
+
+    /// The newest `limit` weeks, oldest first.
+    pub async fn recent(&self, limit: usize) -> Result<Vec<WeekStats>> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT week, sessions, user_messages, assistant_messages, avg_latency_ms, p90_latency_ms FROM weeks ORDER BY week DESC LIMIT ?1",
+            )?;
+            let mut weeks = stmt
+                .query_map(params![limit as i64], |row| {
+                    Ok(WeekStats {
+                        week: row.get(0)?,
+                        sessions: row.get(1)?,
+                        user_messages: row.get(2)?,
+                        assistant_messages: row.get(3)?,
+                        avg_latency_ms: row.get(4)?,
+                        p90_latency_ms: row.get(5)?,
+                        ..Default::default()
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            weeks.reverse();
+            let mut tools = conn.prepare(
+                "SELECT tool, calls FROM week_tools WHERE week = ?1 ORDER BY calls DESC, tool",
+            )?;
+            let mut topics = conn.prepare(
+                "SELECT topic, mentions FROM week_topics WHERE week = ?1 ORDER BY mentions DESC, topic",
+            )?;
+            for week in &mut weeks {
+                week.tools = tools
+                    .query_map(params![week.week], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<rusqlite::Result<_>>()?;
+                week.topics = topics
+                    .query_map(params![week.week], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<rusqlite::Result<_>>()?;
+            }
+            Ok(weeks)
+        })
+        .await
+    }
+}
+
+fn init_db(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS weeks (\
+            week TEXT PRIMARY KEY,\
+            sessions INTEGER NOT NULL,\
+            user_messages INTEGER NOT NULL,\
+            assistant_messages INTEGER NOT NULL,\
+            avg_latency_ms INTEGER,\
+            p90_latency_ms INTEGER\
+        );\
+        CREATE TABLE IF NOT EXISTS week_tools (\
+            week TEXT NOT NULL,\
+            tool TEXT NOT NULL,\
+            calls INTEGER NOT NULL,\
+            PRIMARY KEY (week, tool)\
+        );\
+        CREATE TABLE IF NOT EXISTS week_topics (\
+            week TEXT NOT NULL,\
+            topic TEXT NOT NULL,\
+            mentions INTEGER NOT NULL,\
+            PRIMARY KEY (week, topic)\
+        );",
+    )?;
+    Ok(())
+}
+
+/// Group `entries` by ISO week in `tz` and compute each week's statistics.
+pub fn summarize(entries: &[LoggedMessage], tz: Tz) -> Vec<WeekStats> {
+    #[derive(Default)]
+    struct Acc<'a> {
+        sessions: HashSet<&'a str>,
+        user: u64,
+        assistant: u64,
+        latencies: Vec<u64>,
+        tools: HashMap<&'a str, u64>,
+        words: HashMap<String, u64>,
+    }
+
+    let mut weeks: BTreeMap<String, Acc> = BTreeMap::new();
+    for entry in entries {
+        let week = entry.ts.with_timezone(&tz).iso_week();
+        let acc = weeks
+            .entry(format!("{}-W{:02}", week.year(), week.week()))
+            .or_default();
+        acc.sessions.insert(&entry.session);
+        match entry.role.as_str() {
+            "user" => {
+                acc.user += 1;
+                for word in topic_words(&entry.content) {
+                    *acc.words.entry(word).or_default() += 1;
+                }
+            }
+            "assistant" => {
+                acc.assistant += 1;
+                acc.latencies.extend(entry.latency_ms);
+                for tool in &entry.tools {
+                    *acc.tools.entry(tool).or_default() += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    weeks
+        .into_iter()
+        .map(|(week, mut acc)| {
+            acc.latencies.sort_unstable();
+            let avg = (!acc.latencies.is_empty())
+                .then(|| acc.latencies.iter().sum::<u64>() / acc.latencies.len() as u64);
+            let p90 = acc
+                .latencies
+                .get((acc.latencies.len() * 9).div_ceil(10).saturating_sub(1))
+                .copied();
+            WeekStats {
+                week,
+                sessions: acc.sessions.len() as u64,
+                user_messages: acc.user,
+                assistant_messages: acc.assistant,
+                avg_latency_ms: avg,
+                p90_latency_ms: p90,
+                tools: top(acc.tools.into_iter().map(|(k, v)| (k.to_string(), v))),
+                // A topic needs more than one mention to stand out.
+                topics: top(acc.words.into_iter().filter(|(_, count)| *count > 1)),
+            }
+        })
+        .collect()
+}
+
+fn topic_words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|word| {
+            word.chars().count() >= MIN_TOPIC_CHARS
+                && !word.chars().all(|c| c.is_ascii_digit())
+                && !STOPWORDS.contains(&word.as_str())
+        })
+        .collect()
+}
+
+fn top(counts: impl Iterator<Item = (String, u64)>) -> Vec<(String, u64)> {
+    let mut counts: Vec<_> = counts.collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(TOP_N);
+    counts
+}
+
+/// Markdown table of `weeks` followed by each week's topics and tools.
+pub fn render(weeks: &[WeekStats]) -> String {
+    if weeks.is_empty() {
+        return "No conversations recorded yet.\n".to_string();
+    }
+    let mut out = String::from(
+        "| Week | Sessions | Messages in | Replies | Avg latency | p90 latency |\n|---|---|---|---|---|---|\n",
+    );
+    for week in weeks {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            week.week,
+            week.sessions,
+            week.user_messages,
+            week.assistant_messages,
+            seconds(week.avg_latency_ms),
+            seconds(week.p90_latency_ms)
+        ));
+    }
+    for week in weeks {
+        if week.topics.is_empty() && week.tools.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n{}:\n", week.week));
+        if !week.topics.is_empty() {
+            out.push_str(&format!("- Topics: {}\n", counted(&week.topics)));
+        }
+        if !week.tools.is_empty() {
+            out.push_str(&format!("- Tools: {}\n", counted(&week.tools)));
+        }
+    }
+    out
+}
+
+fn seconds(ms: Option<u64>) -> String {
+    ms.map(|ms| format!("{:.1}s", ms as f64 / 1000.0))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn counted(items: &[(String, u64)]) -> String {
+    items
+        .iter()
+        .map(|(name, count)| format!("{name} ({count})"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::summarize;
+    use crate::history::LoggedMessage;
+    use chrono::{TimeZone, Utc};
+
+    fn entry(day: u32, role: &str, content: &str, latency_ms: Option<u64>) -> LoggedMessage {
+        LoggedMessage {
+            ts: Utc.with_ymd_and_hms(2026, 10, day, 12, 0, 0).unwrap(),
+            session: "telegram:1".to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            latency_ms,
+            tools: if role == "assistant" {
+                vec!["web_fetch".to_string()]
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    #[test]
+    fn groups_by_iso_week() {
+        let entries = vec![
+            entry(12, "user", "What is the weather in Lisbon?", None),
+            entry(12, "assistant", "Sunny.", Some(1_000)),
+            entry(13, "user", "And Lisbon tomorrow?", None),
+            entry(13, "assistant", "Rain.", Some(3_000)),
+            entry(19, "user", "hello", None),
+        ];
+        let weeks = summarize(&entries, chrono_tz::UTC);
+        assert_eq!(weeks.len(), 2);
+        let first = &weeks[0];
+        assert_eq!(first.week, "2026-W42");
+        assert_eq!((first.user_messages, first.assistant_messages), (2, 2));
+        assert_eq!(first.avg_latency_ms, Some(2_000));
+        assert_eq!(first.p90_latency_ms, Some(3_000));
+        assert_eq!(first.tools, vec![("web_fetch".to_string(), 2)]);
+        assert_eq!(first.topics, vec![("lisbon".to_string(), 2)]);
+        assert_eq!(weeks[1].week, "2026-W43");
+        assert!(weeks[1].topics.is_empty());
+    }
+}
@@ -0,0 +1,425 @@
+use crate::config::AppConfig;
+use crate::remote::RemoteStore;
+use age::secrecy::SecretString;
+use anyhow::{anyhow, Context, Result};
+use chrono::Local;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+const FILE_PREFIX: &str = "femtobot-";
+/// Remote directory (below the configured prefix) holding uploaded backups.
+const REMOTE_DIR: &str = "backups";
+/// Workspace directories that are regenerated or too bulky to be worth keeping.
+const SKIPPED_WORKSPACE_DIRS: &[&str] = &["artifacts"];
+const AGE_MAGIC: &[u8] = b"age-encryption.org/v1";
+
+pub struct BackupOutcome {
+    pub path: PathBuf,
+    pub uploaded_to: Option<String>,
+    pub pruned: usize,
+}
+
+/// Snapshot the data dir (cron.json, notification and chat state) and the
+/// workspace (memory files, vectors.db, sessions, stores) into a timestamped
+/// `.tar.gz`, encrypted with age when a passphrase is configured. Then
+/// upload it if enabled and apply the retention policy.
+pub async fn run_backup(cfg: &AppConfig) -> Result<BackupOutcome> {
+    let snapshot_cfg = cfg.clone();
+    let path = tokio::task::spawn_blocking(move || create_backup(&snapshot_cfg, None))
+        .await
+        .map_err(|e| anyhow!("backup task failed: {e}"))??;
+    let mut pruned = prune_local(&cfg.backup_dir, cfg.backup_keep)?;
+
+    let mut uploaded_to = None;
+    if cfg.backup_upload {
+        let remote = cfg.remote.clone().map(RemoteStore::new).ok_or_else(|| {
+            anyhow!("backup.upload is enabled but storage.remote is not configured")
+        })?;
+        let name = file_name(&path);
+        remote
+            .put(&format!("{REMOTE_DIR}/{name}"), fs::read(&path)?)
+            .await?;
+        uploaded_to = Some(format!("{}/{REMOTE_DIR}/{name}", remote.describe()));
+        pruned += prune_remote(&remote, cfg.backup_keep).await?;
+    }
+
+    Ok(BackupOutcome {
+        path,
+        uploaded_to,
+        pruned,
+    })
+}
+
+/// Local backups, newest first.
+pub fn list_backups(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| is_backup_name(&file_name(p)))
+        .collect();
+    // Names embed the timestamp, so lexical order is chronological.
+    files.sort_by_key(|p| std::cmp::Reverse(file_name(p)));
+    files
+}
+
+/// Restore a backup over the current data dir and workspace. `source` is a
+/// path or a file name in the backup dir (or, with `from_remote`, a name in
+/// the remote backups folder). The current state is snapshotted first.
+pub async fn restore(cfg: &AppConfig, source: &str, from_remote: bool) -> Result<RestoreOutcome> {
+    let bytes = if from_remote {
+        let remote = cfg
+            .remote
+            .clone()
+            .map(RemoteStore::new)
+            .ok_or_else(|| anyhow!("storage.remote is not configured"))?;
+        remote
+            .get(&format!("{REMOTE_DIR}/{source}"))
+            .await?
+            .ok_or_else(|| anyhow!("remote backup '{source}' not found"))?
+    } else {
+        let path = if Path::new(source).exists() {
+            PathBuf::from(source)
+        } else {
+            cfg.backup_dir.join(source)
+        };
+        fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?
+    };
+
+    let cfg = cfg.clone();
+    tokio::task::spawn_blocking(move || {
+        let safety = create_backup(&cfg, Some("pre-restore"))?;
+        let files = unpack(&cfg, &bytes)?;
+        Ok(RestoreOutcome { safety, files })
+    })
+    .await
+    .map_err(|e| anyhow!("restore task failed: {e}"))?
+}
+
+pub struct RestoreOutcome {
+    /// Snapshot of the state that was overwritten.
+    pub safety: PathBuf,
+    pub files: usize,
+}
+
+/// Run scheduled backups every `backup_interval_hours`, counting from the
+/// newest existing backup so restarts do not trigger extra snapshots.
+pub fn start(cfg: AppConfig) {
+    tokio::spawn(async move {
+        let interval = Duration::from_secs(cfg.backup_interval_hours.max(1) * 3600);
+        loop {
+            let last = list_backups(&cfg.backup_dir)
+                .first()
+                .and_then(|p| fs::metadata(p).and_then(|m| m.modified()).ok());
+            let wait = last
+                .and_then(|t| SystemTime::now().duration_since(t).ok())
+                .map(|age| interval.saturating_sub(age))
+                .unwrap_or(Duration::ZERO);
+            tokio::time::sleep(wait.max(Duration::from_secs(60))).await;
+            match run_backup(&cfg).await {
+                Ok(outcome) => info!(
+                    "backup written to {} (uploaded: {}, pruned: {})",
+                    outcome.path.display(),
+                    outcome.uploaded_to.as_deref().unwrap_or("no"),
+                    outcome.pruned
+                ),
+                Err(err) => {
+                    warn!("scheduled backup failed: {err:#}");
+                    tokio::time::sleep(interval.min(Duration::from_secs(3600))).await;
+                }
+            }
+        }
+    });
+}
+
+fn create_backup(cfg: &AppConfig, label: Option<&str>) -> Result<PathBuf> {
+    fs::create_dir_all(&cfg.backup_dir)?;
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    let mut name = match label {
+        Some(label) => format!("{FILE_PREFIX}{stamp}-{label}.tar.gz"),
+        None => format!("{FILE_PREFIX}{stamp}.tar.gz"),
+    };
+    if cfg.backup_passphrase.is_some() {
+        name.push_str(".age");
+    }
+    let path = cfg.backup_dir.join(&name);
+    let tmp = cfg.backup_dir.join(format!(".{name}.partial"));
+
+    let file = File::create(&tmp)?;
+    let result = match &cfg.backup_passphrase {
+        Some(passphrase) => {
+            let encryptor =
+                age::Encryptor::with_user_passphrase(SecretString::from(passphrase.clone()));
+            encryptor
+                .wrap_output(file)
+                .map_err(anyhow::Error::from)
+                .and_then(|w| write_archive(w, cfg))
+                .and_then(|w| w.finish().map_err(anyhow::Error::from))
+                .map(|_| ())
+        }
+        None => write_archive(file, cfg).map(|_| ()),
+    };
+    if let Err(err) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(err);
+    }
+    fs::rename(&tmp, &path)?;
+    Ok(path)
+}
+
+fn write_archive<W: Write>(writer: W, cfg: &AppConfig) -> Result<W> {
+    let mut tar = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+    let manifest = serde_json::to_vec_pretty(&json!({
+        "version": 1,
+        "createdAt": Local::now().to_rfc3339(),
+        "dataDir": cfg.data_dir,
+        "workspaceDir": cfg.workspace_dir,
+    }))?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    tar.append_data(&mut header, "manifest.json", manifest.as_slice())?;
+
+    let skip: Vec<PathBuf> = SKIPPED_WORKSPACE_DIRS
+        .iter()
+        .map(|d| cfg.workspace_dir.join(d))
+        .chain([
+            cfg.backup_dir.clone(),
+            crate::models::models_dir(&cfg.data_dir),
+        ])
+        .collect();
+    add_tree(&mut tar, &cfg.data_dir, Path::new("data"), &skip)?;
+    add_tree(&mut tar, &cfg.workspace_dir, Path::new("workspace"), &skip)?;
+    Ok(tar.into_inner()?.finish()?)
+}
+
+fn add_tree<W: Write>(
+    tar: &mut tar::Builder<W>,
+    dir: &Path,
+    archive_dir: &Path,
+    skip: &[PathBuf],
+) -> Result<()> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if skip.iter().any(|s| s == &path) {
+            continue;
+        }
+        let name = entry.file_name();
+        let name_str = name.to_string_lossy();
+        let archive_path = archive_dir.join(&name);
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            add_tree(tar, &path, &archive_path, skip)?;
+        } else if file_type.is_file() {
+            if name_str.ends_with("-wal")
+                || name_str.ends_with("-shm")
+                || name_str.ends_with("-journal")
+            {
+                continue;
+            }
+            if name_str.ends_with(".db") {
+                add_sqlite(tar, &path, &archive_path)?;
+            } else {
+                tar.append_path_with_name(&path, &archive_path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Copy a SQLite database consistently (it may be open by the running bot)
+/// via `VACUUM INTO`, falling back to a plain file copy.
+fn add_sqlite<W: Write>(tar: &mut tar::Builder<W>, path: &Path, archive_path: &Path) -> Result<()> {
+    let tmp = std::env::temp_dir().join(format!("femtobot-backup-{}.db", uuid::Uuid::new_v4()));
+    let snapshot = rusqlite::Connection::open(path).and_then(|conn| {
+        conn.execute("VACUUM INTO ?1", [tmp.to_string_lossy().as_ref()])
+            .map(|_| ())
+    });
+    let result = match snapshot {
+        Ok(()) => tar.append_path_with_name(&tmp, archive_path),
+        Err(err) => {
+            warn!(
+                "VACUUM INTO failed for {}: {err}; copying file",
+                path.display()
+            );
+            tar.append_path_with_name(path, archive_path)
+        }
+    };
+    let _ = fs::remove_file(&tmp);
+    Ok(result?)
+}
+
+fn unpack(cfg: &AppConfig, bytes: &[u8]) -> Result<usize> {
+    let reader: Box<dyn Read> = if bytes.starts_with(AGE_MAGIC) {
+        let passphrase = cfg.backup_passphrase.clone().ok_or_else(|| {
+            anyhow!("backup is encrypted; set backup.passphrase or FEMTOBOT_BACKUP_PASSPHRASE")
+        })?;
+        let identity = age::scrypt::Identity::new(SecretString::from(passphrase));
+        let decryptor = age::Decryptor::new(bytes)?;
+        Box::new(decryptor.decrypt(std::iter::once(&identity as &dyn age::Identity))?)
+    } else {
+        Box::new(bytes)
+    };
+
+    let mut archive = tar::Archive::new(GzDecoder::new(reader));
+    let mut files = 0usize;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(anyhow!(
+                "refusing unsafe path in backup: {}",
+                path.display()
+            ));
+        }
+        // Backups only ever hold files and directories; a link could point
+        // a later entry outside the data and workspace directories.
+        let kind = entry.header().entry_type();
+        if !(kind.is_file() || kind.is_dir()) {
+            return Err(anyhow!(
+                "refusing {kind:?} entry in backup: {}",
+                path.display()
+            ));
+        }
+        let dest = if let Ok(rest) = path.strip_prefix("data") {
+            cfg.data_dir.join(rest)
+        } else if let Ok(rest) = path.strip_prefix("workspace") {
+            cfg.workspace_dir.join(rest)
+        } else {
+            continue;
+        };
+        if kind.is_dir() {
+            fs::create_dir_all(&dest)?;
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&dest)?;
+        files += 1;
+    }
+    Ok(files)
+}
+
+fn prune_local(dir: &Path, keep: usize) -> Result<usize> {
+    let mut pruned = 0;
+    for old in list_backups(dir).into_iter().skip(keep.max(1)) {
+        fs::remove_file(&old)?;
+        pruned += 1;
+    }
+    Ok(pruned)
+}
+
+async fn prune_remote(remote: &RemoteStore, keep: usize) -> Result<usize> {
+    let mut names: Vec<String> = remote
+        .list(REMOTE_DIR)
+        .await?
+        .into_iter()
+        .filter_map(|o| o.key.rsplit('/').next().map(str::to_string))
+        .filter(|name| is_backup_name(name))
+        .collect();
+    names.sort_by(|a, b| b.cmp(a));
+    let mut pruned = 0;
+    for name in names.into_iter().skip(keep.max(1)) {
+        remote.delete(&format!("{REMOTE_DIR}/{name}")).await?;
+        pruned += 1;
+    }
+    Ok(pruned)
+}
+
+fn is_backup_name(name: &str) -> bool {
+    name.starts_with(FILE_PREFIX) && (name.ends_with(".tar.gz") || name.ends_with(".tar.gz.age"))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create_backup, list_backups, unpack};
+    use crate::config::AppConfig;
+
+    #[test]
+    fn encrypted_backup_round_trips() {
+        let root =
+            std::env::temp_dir().join(format!("femtobot-backup-test-{}", std::process::id()));
+        let mut cfg = AppConfig::defaults();
+        cfg.data_dir = root.join("data");
+        cfg.workspace_dir = root.join("workspace");
+        cfg.backup_dir = root.join("backups");
+        cfg.backup_passphrase = Some("correct horse".to_string());
+        std::fs::create_dir_all(cfg.workspace_dir.join("memory")).unwrap();
+        std::fs::create_dir_all(&cfg.data_dir).unwrap();
+        std::fs::write(cfg.workspace_dir.join("memory/MEMORY.md"), "likes tea").unwrap();
+        std::fs::write(cfg.data_dir.join("cron.json"), "{}").unwrap();
+
+        let path = create_backup(&cfg, None).unwrap();
+        assert_eq!(list_backups(&cfg.backup_dir), vec![path.clone()]);
+        std::fs::write(cfg.workspace_dir.join("memory/MEMORY.md"), "changed").unwrap();
+
+        let files = unpack(&cfg, &std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(files, 2);
+        assert_eq!(
+            std::fs::read_to_string(cfg.workspace_dir.join("memory/MEMORY.md")).unwrap(),
+            "likes tea"
+        );
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn links_in_a_backup_are_refused() {
+        let root =
+            std::env::temp_dir().join(format!("femtobot-backup-link-{}", uuid::Uuid::new_v4()));
+        let mut cfg = AppConfig::defaults();
+        cfg.data_dir = root.join("data");
+        cfg.workspace_dir = root.join("workspace");
+        let outside = root.join("outside.txt");
+
+        for kind in [tar::EntryType::Symlink, tar::EntryType::Link] {
+            let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+                Vec::new(),
+                flate2::Compression::default(),
+            ));
+            let mut link = tar::Header::new_gnu();
+            link.set_entry_type(kind);
+            link.set_size(0);
+            link.set_mode(0o644);
+            link.set_link_name(&outside).unwrap();
+            link.set_cksum();
+            tar.append_data(&mut link, "workspace/notes.md", std::io::empty())
+                .unwrap();
+            let mut file = tar::Header::new_gnu();
+            file.set_size(6);
+            file.set_mode(0o644);
+            file.set_cksum();
+            tar.append_data(&mut file, "workspace/notes.md", "pwned!".as_bytes())
+                .unwrap();
+            let bytes = tar.into_inner().unwrap().finish().unwrap();
+
+            let err = unpack(&cfg, &bytes).unwrap_err();
+            assert!(err.to_string().contains("refusing"), "{err}");
+            assert!(!outside.exists());
+            assert!(!cfg.workspace_dir.join("notes.md").exists());
+        }
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,158 @@
+//! Admin broadcasts: one message to every allowlisted chat, or to the chats
+//! carrying a tag. Every chat is implicitly tagged with its channel name;
+//! `broadcast.targets` adds chats and custom tags.
+
+use crate::config::AppConfig;
+use crate::cron::CronService;
+use anyhow::{anyhow, Result};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    pub channel: String,
+    pub chat_id: String,
+    pub tags: Vec<String>,
+}
+
+impl Target {
+    pub fn label(&self) -> String {
+        let custom: Vec<&str> = self
+            .tags
+            .iter()
+            .map(String::as_str)
+            .filter(|tag| *tag != self.channel)
+            .collect();
+        if custom.is_empty() {
+            format!("{}:{}", self.channel, self.chat_id)
+        } else {
+            format!("{}:{} [{}]", self.channel, self.chat_id, custom.join(", "))
+        }
+    }
+}
+
+/// Chats a broadcast can reach: allowlisted chats of the enabled channels
+/// plus `broadcast.targets`.
+pub fn all_targets(cfg: &AppConfig) -> Vec<Target> {
+    let mut chats: Vec<(&str, &String)> = Vec::new();
+    if cfg.telegram_enabled() {
+        // Private chat ids equal user ids; `@username` entries cannot be addressed.
+        chats.extend(
+            cfg.telegram_allow_from
+                .iter()
+                .filter(|id| id.parse::<i64>().is_ok())
+                .map(|id| ("telegram", id)),
+        );
+    }
+    if cfg.discord_enabled() {
+        chats.extend(
+            cfg.discord_allowed_channels
+                .iter()
+                .map(|id| ("discord", id)),
+        );
+    }
+    if cfg.matrix_enabled() {
+        chats.extend(cfg.matrix_allowed_rooms.iter().map(|id| ("matrix", id)));
+    }
+    if cfg.mattermost_enabled() {
+        chats.extend(
+            cfg.mattermost_allowed_channels
+                .iter()
+                .map(|id| ("mattermost", id)),
+        );
+    }
+    if cfg.irc_enabled() {
+        chats.extend(cfg.irc_channels.iter().map(|id| ("irc", id)));
+    }
+    if cfg.xmpp_enabled() {
+        chats.extend(cfg.xmpp_allow_from.iter().map(|id| ("xmpp", id)));
+    }
+
+    let mut targets: Vec<Target> = Vec::new();
+    let configured = cfg
+        .broadcast_targets
+        .iter()
+        .map(|t| (t.channel.as_str(), &t.chat_id, t.tags.as_slice()));
+    for (channel, chat_id, tags) in chats
+        .into_iter()
+        .map(|(channel, chat_id)| (channel, chat_id, &[][..]))
+        .chain(configured)
+    {
+        let chat_id = chat_id.trim();
+        if chat_id.is_empty() {
+            continue;
+        }
+        let index = match targets
+            .iter()
+            .position(|t| t.channel == channel && t.chat_id == chat_id)
+        {
+            Some(index) => index,
+            None => {
+                targets.push(Target {
+                    channel: channel.to_string(),
+                    chat_id: chat_id.to_string(),
+                    tags: vec![channel.to_string()],
+                });
+                targets.len() - 1
+            }
+        };
+        for tag in tags {
+            if !targets[index].tags.contains(tag) {
+                targets[index].tags.push(tag.clone());
+            }
+        }
+    }
+    targets
+}
+
+/// Targets carrying any of `tags`; all targets when `tags` is empty.
+pub fn select(cfg: &AppConfig, tags: &[String]) -> Vec<Target> {
+    all_targets(cfg)
+        .into_iter()
+        .filter(|target| tags.is_empty() || tags.iter().any(|tag| target.tags.contains(tag)))
+        .collect()
+}
+
+/// Queue `message` for every target; a running instance delivers it,
+/// paced by each channel's rate limit.
+pub async fn queue(cron: &CronService, targets: &[Target], message: &str) -> Result<()> {
+    if message.trim().is_empty() {
+        return Err(anyhow!("broadcast message is empty"));
+    }
+    let chats: Vec<(String, String)> = targets
+        .iter()
+        .map(|t| (t.channel.clone(), t.chat_id.clone()))
+        .collect();
+    cron.add_broadcast(message.trim(), &chats).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::select;
+    use crate::config::{AppConfig, BroadcastTarget};
+
+    #[test]
+    fn selects_allowlisted_and_tagged_chats() {
+        let mut cfg = AppConfig::defaults();
+        cfg.telegram_bot_token = "token".to_string();
+        cfg.telegram_allow_from = vec!["42".to_string(), "@anna".to_string()];
+        cfg.broadcast_targets = vec![
+            BroadcastTarget {
+                channel: "telegram".to_string(),
+                chat_id: "42".to_string(),
+                tags: vec!["family".to_string()],
+            },
+            BroadcastTarget {
+                channel: "discord".to_string(),
+                chat_id: "900".to_string(),
+                tags: vec!["ops".to_string()],
+            },
+        ];
+
+        let all = select(&cfg, &[]);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].tags, vec!["telegram", "family"]);
+        let family = select(&cfg, &["family".to_string()]);
+        assert_eq!(family.len(), 1);
+        assert_eq!(family[0].label(), "telegram:42 [family]");
+        assert_eq!(select(&cfg, &["discord".to_string()])[0].chat_id, "900");
+    }
+}
@@ -1,19 +1,124 @@
-use std::sync::Arc;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
 use tokio::sync::{broadcast, mpsc, Mutex};
 
-#[derive(Clone, Debug)]
+/// Last look at every published outbound message before any channel sees
+/// it; `None` drops the message.
+pub type OutboundFilter =
+    Arc<dyn Fn(OutboundMessage) -> BoxFuture<'static, Option<OutboundMessage>> + Send + Sync>;
+
+#[derive(Clone, Debug, Default)]
 pub struct InboundMessage {
     pub channel: String,
     pub chat_id: String,
     pub sender_id: String,
+    /// Display name of the sender, when the channel provides one.
+    pub sender_name: Option<String>,
+    /// Whether the chat has several human participants.
+    pub is_group: bool,
     pub content: String,
+    pub meta: MessageMeta,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct OutboundMessage {
     pub channel: String,
     pub chat_id: String,
     pub content: String,
+    pub meta: MessageMeta,
+}
+
+/// Optional envelope fields. Everything defaults to empty, so producers only
+/// set what they know and channels ignore what they cannot use.
+#[derive(Clone, Debug, Default)]
+pub struct MessageMeta {
+    /// Channel-native id of this message (e.g. Telegram message id).
+    pub message_id: Option<String>,
+    /// Channel-native id of the message this one replies to.
+    pub reply_to: Option<String>,
+    /// The replied-to message itself, when the channel includes it.
+    pub quoted: Option<Quoted>,
+    pub attachments: Vec<Attachment>,
+    pub priority: Priority,
+    /// Ties related messages together (a turn, an alert and its re-sends).
+    pub correlation_id: Option<String>,
+    /// Preferred model for the turn this message starts.
+    pub model: Option<String>,
+    /// Sender's language tag, e.g. "de" or "pt-BR".
+    pub locale: Option<String>,
+    /// Quick-reply options offered with an outbound message. Pressing one
+    /// comes back as an inbound message whose content is the label.
+    pub buttons: Vec<String>,
+}
+
+/// Earlier message a user replied to.
+#[derive(Clone, Debug)]
+pub struct Quoted {
+    /// Display name of its author.
+    pub author: String,
+    /// Whether the bot wrote it.
+    pub from_bot: bool,
+    /// Its text, or only the part the user quoted.
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Attachment {
+    /// "voice", "audio", "photo", "document", ...
+    pub kind: String,
+    pub name: String,
+    pub mime: Option<String>,
+    /// Local path once downloaded.
+    pub path: Option<std::path::PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Priority {
+    /// Delivered without a notification sound where the channel allows it.
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Reported by channel forwarders after attempting to send an outbound message.
+#[derive(Clone, Debug)]
+pub struct DeliveryReport {
+    pub channel: String,
+    pub chat_id: String,
+    pub content: String,
+    pub correlation_id: Option<String>,
+    pub delivered: bool,
+    /// Why delivery failed, when the channel knows.
+    pub error: Option<String>,
+    /// Channel-native ids of the messages that carried it, where reported.
+    pub message_ids: Vec<String>,
+}
+
+/// Reply text of a turn that is still running, for channels that show a
+/// reply while it is being written. `text` is everything produced so far;
+/// an empty `text` marks the start of the turn.
+#[derive(Clone, Debug)]
+pub struct PartialOutput {
+    pub channel: String,
+    pub chat_id: String,
+    /// Equal to the `correlation_id` of the turn's final reply.
+    pub correlation_id: String,
+    pub reply_to: Option<String>,
+    pub text: String,
+}
+
+/// A user reacting to a message with an emoji, on channels that have
+/// reactions. Removing a reaction is not reported.
+#[derive(Clone, Debug)]
+pub struct Reaction {
+    pub channel: String,
+    pub chat_id: String,
+    /// Channel-native id of the message reacted to.
+    pub message_id: String,
+    pub sender_id: String,
+    pub emoji: String,
 }
 
 #[derive(Clone)]
@@ -22,6 +127,10 @@ pub struct MessageBus {
     outbound_tx: mpsc::Sender<OutboundMessage>,
     inbound_rx: Arc<Mutex<mpsc::Receiver<InboundMessage>>>,
     outbound_broadcast_tx: broadcast::Sender<OutboundMessage>,
+    outbound_filter: Arc<OnceLock<OutboundFilter>>,
+    delivery_tx: broadcast::Sender<DeliveryReport>,
+    partial_tx: broadcast::Sender<PartialOutput>,
+    reaction_tx: broadcast::Sender<Reaction>,
 }
 
 impl MessageBus {
@@ -29,18 +138,35 @@ impl MessageBus {
         let (inbound_tx, inbound_rx) = mpsc::channel(100);
         let (outbound_tx, mut outbound_rx) = mpsc::channel(100);
         let (outbound_broadcast_tx, _) = broadcast::channel(100);
+        let (delivery_tx, _) = broadcast::channel(100);
+        let (partial_tx, _) = broadcast::channel(100);
+        let (reaction_tx, _) = broadcast::channel(100);
 
         let inbound_rx = Arc::new(Mutex::new(inbound_rx));
+        let outbound_filter: Arc<OnceLock<OutboundFilter>> = Arc::new(OnceLock::new());
 
         let bus = MessageBus {
             inbound_tx,
             outbound_tx,
             inbound_rx: inbound_rx.clone(),
             outbound_broadcast_tx: outbound_broadcast_tx.clone(),
+            outbound_filter: outbound_filter.clone(),
+            delivery_tx,
+            partial_tx,
+            reaction_tx,
         };
 
         tokio::spawn(async move {
+            // Filtered in publish order, so one slow check delays later
+            // messages instead of reordering them.
             while let Some(msg) = outbound_rx.recv().await {
+                let msg = match outbound_filter.get() {
+                    Some(filter) => match filter(msg).await {
+                        Some(msg) => msg,
+                        None => continue,
+                    },
+                    None => msg,
+                };
                 let _ = outbound_broadcast_tx.send(msg);
             }
         });
@@ -56,6 +182,20 @@ impl MessageBus {
         let _ = self.outbound_tx.send(msg).await;
     }
 
+    /// Deliver `msg` without the outbound filter; only for the filter's own
+    /// reports, which would otherwise be checked again.
+    pub fn publish_outbound_unfiltered(&self, msg: OutboundMessage) {
+        let _ = self.outbound_broadcast_tx.send(msg);
+    }
+
+    /// Run every later outbound message through `filter`. Only the first
+    /// filter set on a bus takes effect.
+    pub fn set_outbound_filter(&self, filter: OutboundFilter) {
+        if self.outbound_filter.set(filter).is_err() {
+            tracing::warn!("outbound filter already set; ignoring another");
+        }
+    }
+
     pub async fn consume_inbound(&self) -> Option<InboundMessage> {
         let mut rx = self.inbound_rx.lock().await;
         rx.recv().await
@@ -64,4 +204,83 @@ impl MessageBus {
     pub fn subscribe_outbound(&self) -> broadcast::Receiver<OutboundMessage> {
         self.outbound_broadcast_tx.subscribe()
     }
+
+    /// Outbound messages for `channel`, buffered without bound so a
+    /// forwarder that waits on rate limits never lags the broadcast.
+    pub fn outbound_queue(&self, channel: &str) -> mpsc::UnboundedReceiver<OutboundMessage> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut outbound_rx = self.subscribe_outbound();
+        let channel = channel.to_string();
+        tokio::spawn(async move {
+            loop {
+                match outbound_rx.recv().await {
+                    Ok(msg) if msg.channel == channel => {
+                        if tx.send(msg).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("{channel} outbound lagged, skipped {skipped} message(s)");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        rx
+    }
+
+    pub fn report_delivery(&self, msg: &OutboundMessage, delivered: bool) {
+        self.send_report(msg, delivered, None, Vec::new());
+    }
+
+    /// Report a delivered message along with the ids the channel gave it.
+    pub fn report_sent(&self, msg: &OutboundMessage, message_ids: Vec<String>) {
+        self.send_report(msg, true, None, message_ids);
+    }
+
+    /// Report a failed delivery along with the channel's error.
+    pub fn report_failure(&self, msg: &OutboundMessage, error: String) {
+        self.send_report(msg, false, Some(error), Vec::new());
+    }
+
+    fn send_report(
+        &self,
+        msg: &OutboundMessage,
+        delivered: bool,
+        error: Option<String>,
+        message_ids: Vec<String>,
+    ) {
+        let _ = self.delivery_tx.send(DeliveryReport {
+            channel: msg.channel.clone(),
+            chat_id: msg.chat_id.clone(),
+            content: msg.content.clone(),
+            correlation_id: msg.meta.correlation_id.clone(),
+            delivered,
+            error,
+            message_ids,
+        });
+    }
+
+    pub fn subscribe_deliveries(&self) -> broadcast::Receiver<DeliveryReport> {
+        self.delivery_tx.subscribe()
+    }
+
+    /// Partials are best effort: nobody listening, or a listener lagging
+    /// behind, only costs the in-progress view.
+    pub fn publish_partial(&self, partial: PartialOutput) {
+        let _ = self.partial_tx.send(partial);
+    }
+
+    pub fn subscribe_partials(&self) -> broadcast::Receiver<PartialOutput> {
+        self.partial_tx.subscribe()
+    }
+
+    pub fn publish_reaction(&self, reaction: Reaction) {
+        let _ = self.reaction_tx.send(reaction);
+    }
+
+    pub fn subscribe_reactions(&self) -> broadcast::Receiver<Reaction> {
+        self.reaction_tx.subscribe()
+    }
 }
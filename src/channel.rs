@@ -0,0 +1,108 @@
+//! Chat integrations behind one interface. `main::run` starts whatever
+//! `configured` returns; a channel publishes inbound messages on the bus and
+//! delivers the outbound messages addressed to its name, so adding one
+//! touches neither the bus nor the agent loop.
+
+use crate::bus::{MessageBus, OutboundMessage};
+use crate::config::AppConfig;
+use crate::discord::DiscordChannel;
+use crate::irc::IrcChannel;
+use crate::matrix::MatrixChannel;
+use crate::mattermost::MattermostChannel;
+use crate::mock::MockChannel;
+use crate::render;
+use crate::telegram::TelegramChannel;
+use crate::websocket::WebSocketChannel;
+use crate::xmpp::XmppChannel;
+use anyhow::Result;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+pub trait Channel: Send + Sync + 'static {
+    /// Channel name used in `OutboundMessage::channel` and in config.
+    fn name(&self) -> &'static str;
+
+    /// Connect and serve in the background until the channel fails.
+    fn start(self: Arc<Self>, bus: MessageBus) -> JoinHandle<()>;
+
+    /// Whether `meta.buttons` are shown as native buttons; otherwise
+    /// `render` lists them as text the user can type back.
+    fn supports_buttons(&self) -> bool {
+        false
+    }
+
+    /// Whether files in `meta.attachments` are uploaded; otherwise `render`
+    /// names them in the text.
+    fn supports_attachments(&self) -> bool {
+        false
+    }
+
+    /// Chunks `msg` is sent as, formatted for this channel.
+    fn render(&self, msg: &OutboundMessage) -> Vec<String> {
+        let mut content = msg.content.clone();
+        if !msg.meta.buttons.is_empty() && !self.supports_buttons() {
+            let options: Vec<String> = msg
+                .meta
+                .buttons
+                .iter()
+                .map(|label| format!("- {label}"))
+                .collect();
+            content = format!("{content}\n\nReply with one of:\n{}", options.join("\n"));
+        }
+        if !self.supports_attachments() {
+            for attachment in &msg.meta.attachments {
+                let location = attachment
+                    .path
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_else(|| attachment.name.clone());
+                content.push_str(&format!("\n\n(Attached {}: {location})", attachment.kind));
+            }
+        }
+        render::render_for(&content, self.name())
+    }
+}
+
+/// Run a channel's main future, logging why it stopped.
+pub fn spawn<F>(name: &'static str, serve: F) -> JoinHandle<()>
+where
+    F: Future<Output = Result<()>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        if let Err(err) = serve.await {
+            warn!("{name} disabled: {err}");
+        }
+    })
+}
+
+/// Channels enabled by `cfg`, in start order.
+pub fn configured(cfg: &AppConfig) -> Vec<Arc<dyn Channel>> {
+    let mut channels: Vec<Arc<dyn Channel>> = Vec::new();
+    if cfg.telegram_enabled() {
+        channels.push(Arc::new(TelegramChannel::new(cfg.clone())));
+    }
+    if cfg.discord_enabled() {
+        channels.push(Arc::new(DiscordChannel::new(cfg.clone())));
+    }
+    if cfg.matrix_enabled() {
+        channels.push(Arc::new(MatrixChannel::new(cfg.clone())));
+    }
+    if cfg.mattermost_enabled() {
+        channels.push(Arc::new(MattermostChannel::new(cfg.clone())));
+    }
+    if cfg.irc_enabled() {
+        channels.push(Arc::new(IrcChannel::new(cfg.clone())));
+    }
+    if cfg.xmpp_enabled() {
+        channels.push(Arc::new(XmppChannel::new(cfg.clone())));
+    }
+    if cfg.websocket_enabled() {
+        channels.push(Arc::new(WebSocketChannel::new(cfg.clone())));
+    }
+    if cfg.mock_channel_enabled() {
+        channels.push(Arc::new(MockChannel::new(cfg.clone())));
+    }
+    channels
+}
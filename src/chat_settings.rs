@@ -0,0 +1,99 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Per-chat preferences toggled with chat commands.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatSettings {
+    /// Append a model/tokens/duration footer to replies.
+    #[serde(default, rename = "usageFooter")]
+    pub usage_footer: bool,
+    /// List the tools a turn called under replies; unset follows
+    /// `agents.defaults.tool_footer`.
+    #[serde(
+        default,
+        rename = "toolFooter",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub tool_footer: Option<bool>,
+    /// Model route override: "cheap" or "strong"; unset means automatic.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<String>,
+    /// Model set with `/model`; its route is tried first in this chat.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Exec profile chosen with `/exec`; unset defers to the sender's role.
+    #[serde(
+        default,
+        rename = "execProfile",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub exec_profile: Option<String>,
+    /// Persona chosen with `/persona`; unset defers to `agents.personas`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona: Option<String>,
+    /// Replies longer than this many characters arrive shortened, with a
+    /// button for the full text; unset sends them whole.
+    #[serde(
+        default,
+        rename = "previewChars",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub preview_chars: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChatSettingsData {
+    version: i32,
+    /// Settings per session key (`channel:chat_id`).
+    #[serde(default)]
+    chats: HashMap<String, ChatSettings>,
+}
+
+/// Per-chat settings persisted to `<data_dir>/chat_settings.json`.
+pub struct ChatSettingsStore {
+    path: PathBuf,
+    chats: HashMap<String, ChatSettings>,
+}
+
+impl ChatSettingsStore {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join("chat_settings.json"),
+            chats: HashMap::new(),
+        }
+    }
+
+    pub fn load(&mut self) -> Result<()> {
+        self.chats = if self.path.exists() {
+            let content = fs::read_to_string(&self.path)?;
+            serde_json::from_str::<ChatSettingsData>(&content)?.chats
+        } else {
+            HashMap::new()
+        };
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let data = ChatSettingsData {
+            version: 1,
+            chats: self.chats.clone(),
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&data)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, session_key: &str) -> ChatSettings {
+        self.chats.get(session_key).cloned().unwrap_or_default()
+    }
+
+    pub fn update(&mut self, session_key: &str, f: impl FnOnce(&mut ChatSettings)) -> Result<()> {
+        f(self.chats.entry(session_key.to_string()).or_default());
+        self.save()
+    }
+}
@@ -0,0 +1,59 @@
+use chrono::{DateTime, Local, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// The user's timezone: the configured IANA name if valid, otherwise the
+/// system zone (when it can be identified).
+pub fn resolve_timezone(configured: &str) -> Option<Tz> {
+    let configured = configured.trim();
+    if !configured.is_empty() {
+        if let Ok(tz) = configured.parse::<Tz>() {
+            return Some(tz);
+        }
+        tracing::warn!("unknown timezone '{configured}', falling back to system timezone");
+    }
+    iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|name| name.parse::<Tz>().ok())
+}
+
+/// One-line description of the current time, e.g.
+/// `2025-06-01 14:03 (Sunday), timezone Europe/Berlin (UTC+02:00)`.
+pub fn describe_now(tz: Option<Tz>) -> String {
+    describe_at(Utc::now(), tz)
+}
+
+pub fn describe_at(now: DateTime<Utc>, tz: Option<Tz>) -> String {
+    match tz {
+        Some(tz) => format_in(&now.with_timezone(&tz), tz.name()),
+        None => format_in(&now.with_timezone(&Local), "system local"),
+    }
+}
+
+fn format_in<T: TimeZone>(time: &DateTime<T>, zone: &str) -> String
+where
+    T::Offset: std::fmt::Display,
+{
+    let offset = time.offset().fix();
+    format!(
+        "{}, timezone {} (UTC{})",
+        time.format("%Y-%m-%d %H:%M (%A)"),
+        zone,
+        offset
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::describe_at;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn describes_time_in_configured_zone() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 3, 0).unwrap();
+        let tz = "Europe/Berlin".parse().ok();
+        assert_eq!(
+            describe_at(now, tz),
+            "2025-06-01 14:03 (Sunday), timezone Europe/Berlin (UTC+02:00)"
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -10,6 +11,8 @@ pub enum ProviderKind {
     OpenRouter,
     OpenAI,
     Ollama,
+    /// Scripted answers from a fixture file, for tests and local development.
+    Mock,
 }
 
 impl ProviderKind {
@@ -18,6 +21,7 @@ impl ProviderKind {
             "openrouter" => Some(Self::OpenRouter),
             "openai" => Some(Self::OpenAI),
             "ollama" => Some(Self::Ollama),
+            "mock" => Some(Self::Mock),
             _ => None,
         }
     }
@@ -27,6 +31,7 @@ impl ProviderKind {
             Self::OpenRouter => "openrouter",
             Self::OpenAI => "openai",
             Self::Ollama => "ollama",
+            Self::Mock => "mock",
         }
     }
 }
@@ -47,17 +52,71 @@ pub struct AppConfig {
     pub ollama_api_key: String,
     pub ollama_base_url: String,
     pub ollama_extra_headers: Vec<(String, String)>,
+    /// Script played by the `mock` provider (`providers.mock.fixture`).
+    pub mock_fixture: Option<PathBuf>,
     pub mistral_api_key: String,
     pub mistral_base_url: String,
 
     pub model: String,
     pub model_fallbacks: Vec<String>,
+    /// Try routes that keep failing last for a few minutes.
+    pub adaptive_fallback: bool,
+    /// Models that accept image input; empty guesses from the model name.
+    pub vision_models: Vec<String>,
+    pub routing_enabled: bool,
+    pub routing_cheap_model: String,
+    pub routing_max_cheap_chars: usize,
     pub brave_api_key: Option<String>,
     pub telegram_bot_token: String,
     pub telegram_allow_from: Vec<String>,
+    /// In groups, answer only messages that mention the bot or reply to it.
+    pub telegram_mention_only: bool,
+    /// Show replies in Telegram as they are written by editing a placeholder.
+    pub telegram_live_edit: bool,
     pub discord_bot_token: String,
     pub discord_allow_from: Vec<String>,
     pub discord_allowed_channels: Vec<String>,
+    pub matrix_homeserver: String,
+    pub matrix_user_id: String,
+    pub matrix_access_token: String,
+    pub matrix_allow_from: Vec<String>,
+    pub matrix_allowed_rooms: Vec<String>,
+    /// Server base URL, e.g. `https://chat.example.com`.
+    pub mattermost_url: String,
+    pub mattermost_token: String,
+    pub mattermost_allow_from: Vec<String>,
+    pub mattermost_allowed_channels: Vec<String>,
+    /// IRC server as `host` or `host:port` (6697 with TLS, 6667 without).
+    pub irc_server: String,
+    pub irc_tls: bool,
+    pub irc_nick: String,
+    pub irc_password: Option<String>,
+    pub irc_channels: Vec<String>,
+    pub irc_allow_from: Vec<String>,
+    pub xmpp_jid: String,
+    pub xmpp_password: String,
+    /// `host` or `host:port`; empty connects to the JID's domain on 5222.
+    pub xmpp_server: String,
+    pub xmpp_allow_from: Vec<String>,
+    /// Only answer contacts in the roster (plus `xmpp_allow_from`).
+    pub xmpp_roster_only: bool,
+    /// Address the WebSocket channel listens on, e.g. `127.0.0.1:8765`; empty disables it.
+    pub websocket_listen: String,
+    /// Shared secret clients pass as `?token=` or `Authorization: Bearer`.
+    pub websocket_token: String,
+    /// Browser origins allowed to connect; empty allows any.
+    pub websocket_allowed_origins: Vec<String>,
+    /// In-process `mock` channel for tests and local development.
+    pub mock_channel: bool,
+    /// JSONL file of inbound messages the mock channel plays at start.
+    pub mock_channel_script: Option<PathBuf>,
+    /// JSONL file the mock channel appends delivered messages to; defaults
+    /// to `<data_dir>/mock_channel.jsonl`.
+    pub mock_channel_transcript: Option<PathBuf>,
+    /// Extra broadcast recipients with tags, beyond the allowlisted chats.
+    pub broadcast_targets: Vec<BroadcastTarget>,
+    /// Outbound send limits per channel name; channels without one are unlimited.
+    pub rate_limits: HashMap<String, RateLimit>,
     pub transcription_enabled: bool,
     pub transcription_provider: String,
     pub transcription_model: String,
@@ -68,22 +127,139 @@ pub struct AppConfig {
     pub transcription_mistral_timestamp_granularities: Vec<String>,
     pub data_dir: PathBuf,
     pub workspace_dir: PathBuf,
+    pub admins: Vec<String>,
+    pub moderation_rules: Vec<ModerationRule>,
+    pub moderation_model: Option<String>,
+    pub moderation_command: Option<String>,
+    pub moderation_notify: Option<String>,
     pub exec_timeout_secs: u64,
+    /// Named exec profiles (`tools.exec.profiles`), with safe/normal/yolo built in.
+    pub exec_profiles: HashMap<String, ExecProfile>,
+    /// Profile used when neither the chat nor the sender's role picks one.
+    pub exec_profile: String,
+    /// Profile per sender role: "admin", "cron" or "user".
+    pub exec_role_profiles: HashMap<String, String>,
+    pub form_timeout_secs: u64,
     pub restrict_to_workspace: bool,
+    pub sandbox_chats: bool,
+    pub sandbox_shared_readonly: Vec<PathBuf>,
+    pub backup_enabled: bool,
+    pub backup_interval_hours: u64,
+    pub backup_keep: usize,
+    pub backup_dir: PathBuf,
+    pub backup_passphrase: Option<String>,
+    pub backup_upload: bool,
+    pub remote: Option<RemoteConfig>,
+    pub sync_enabled: bool,
+    pub sync_interval_secs: u64,
+    pub sync_exclude: Vec<String>,
+    pub calendar_export: bool,
+    pub calendar_horizon_days: u64,
     pub max_tool_turns: usize,
+    /// Identical tool calls allowed per turn before more are refused as a
+    /// loop; 0 turns the check off.
+    pub max_repeated_tool_calls: usize,
+    /// Stream completions so replies can be shown while they are written;
+    /// off unless enabled, since not every provider streams well.
+    pub stream_completions: bool,
+    /// List the tools each turn called under its reply, unless the chat
+    /// turned it off with `/footer tools off`.
+    pub tool_footer: bool,
+    /// Instructions that extend (or with `system_prompt_mode = "replace"`
+    /// replace) the built-in system prompt; `prompt.md` in the workspace
+    /// takes precedence when it exists.
+    pub system_prompt: Option<String>,
+    /// Custom prompt file; relative paths resolve against the workspace.
+    pub system_prompt_file: PathBuf,
+    /// `extend` or `replace`.
+    pub system_prompt_mode: String,
+    /// Named personas (`agents.personas`) a chat can be switched to.
+    pub personas: HashMap<String, Persona>,
+    /// Persona for chats that neither picked one nor are listed by one;
+    /// empty means none.
+    pub persona: String,
+    pub provider_mode: String,
+    pub cassette_dir: PathBuf,
     pub memory_enabled: bool,
     pub memory_vector_enabled: bool,
     pub memory_embedding_model: String,
     pub memory_extraction_model: String,
     pub memory_max_memories: usize,
     pub memory_extraction_interval: usize,
+    pub memory_layout: String,
+    pub memory_rescore_interval_hours: u64,
+    /// Sessions idle this many days are archived; 0 keeps them all live.
+    pub archive_after_days: u64,
+    /// Workspace files `femtobot index rebuild` embeds into the `docs` namespace.
+    pub index_globs: Vec<String>,
+    /// Workspace directories the index skips.
+    pub index_exclude: Vec<String>,
+    /// Most chunks kept in the `docs` namespace.
+    pub index_max_chunks: usize,
+    /// Minimum similarity for an existing memory to be offered to the
+    /// consolidator as a merge candidate for a new fact.
+    pub memory_candidate_threshold: f32,
+    /// Share of a memory's priority that comes from recent recalls.
+    pub memory_recency_weight: f32,
+    /// Dynamic-context recall for ordinary turns.
+    pub memory_recall: RecallPolicy,
+    /// Named collections extracted memories are sorted into; empty disables
+    /// tagging.
+    pub memory_collections: Vec<String>,
+    /// Partial policy (`memory.recall.cron`) layered on top for cron turns.
+    pub memory_recall_cron: Value,
+    /// Partial policies layered on top per chat, keyed `channel:chat_id`.
+    pub memory_recall_chats: HashMap<String, Value>,
+    pub expense_default_currency: String,
+    pub summarize_model: String,
+    pub inline_enabled: bool,
+    pub inline_model: String,
+    pub inline_cache_secs: u64,
+    pub plan_confirm_enabled: bool,
+    pub plan_confirm_destructive_keywords: Vec<String>,
+    pub plan_confirm_multi_step_markers: Vec<String>,
+    pub auto_followups: bool,
+    pub escalation_resend_after_mins: u64,
+    pub escalation_max_resends: u32,
+    pub escalation_secondary: Option<String>,
+    pub tool_timeout_secs: u64,
+    pub tool_output_max_chars: usize,
+    pub tool_timeouts: HashMap<String, u64>,
+    pub tool_output_limits: HashMap<String, usize>,
+    /// Schema dialect per route, keyed by `provider/model` or bare model.
+    pub schema_dialects: HashMap<String, SchemaDialect>,
+    /// Tools declared in config (`tools.http`) that call an HTTP API.
+    pub http_tools: Vec<HttpToolSpec>,
+    /// Named credentials (`secrets`) other settings refer to by name.
+    pub secrets: HashMap<String, String>,
+    /// Middleware hook specs per tool name (`"*"` for all tools).
+    pub tool_hooks: HashMap<String, Vec<Value>>,
+    pub model_prices: HashMap<String, ModelPrice>,
+    pub cost_confirm_usd: f64,
+    /// Daily spend limits (`budget`).
+    pub budget: SpendLimits,
+    pub skills_enabled: bool,
+    pub skills_threshold: f32,
+    pub timezone: String,
+    /// `channel:chat_id` that gets a status summary after each start.
+    pub startup_notify: Option<String>,
+    /// Append reactions to the bot's replies to `<workspace>/feedback.jsonl`.
+    pub feedback_log: bool,
+    /// HTTP timeouts and retries for model provider calls (`network.provider`).
+    pub network_provider: NetworkPolicy,
+    /// Same for web_search, web_fetch, summarize_url and HTTP tools (`network.web`).
+    pub network_web: NetworkPolicy,
+    /// Same for speech-to-text requests (`network.transcription`).
+    pub network_transcription: NetworkPolicy,
+    /// Same for the Telegram Bot API (`network.telegram`).
+    pub network_telegram: NetworkPolicy,
 }
 
 impl AppConfig {
     pub fn load() -> Result<Self> {
         let mut cfg = Self::defaults();
 
-        if let Some(femtobot) = load_femtobot_config() {
+        if let Some(femtobot) = load_femtobot_config()? {
             apply_femtobot_config(&mut cfg, &femtobot);
         }
 
@@ -100,7 +276,7 @@ impl AppConfig {
         Ok(cfg)
     }
 
-    fn defaults() -> Self {
+    pub(crate) fn defaults() -> Self {
         Self {
             provider: ProviderKind::OpenRouter,
 
@@ -116,17 +292,53 @@ impl AppConfig {
             ollama_api_key: String::new(),
             ollama_base_url: "http://127.0.0.1:11434/v1".to_string(),
             ollama_extra_headers: Vec::new(),
+            mock_fixture: None,
             mistral_api_key: String::new(),
             mistral_base_url: "https://api.mistral.ai/v1".to_string(),
 
             model: "anthropic/claude-opus-4-5".to_string(),
             model_fallbacks: Vec::new(),
+            adaptive_fallback: false,
+            vision_models: Vec::new(),
+            routing_enabled: false,
+            routing_cheap_model: "gpt-4o-mini".to_string(),
+            routing_max_cheap_chars: 200,
             brave_api_key: None,
             telegram_bot_token: String::new(),
             telegram_allow_from: Vec::new(),
+            telegram_mention_only: false,
+            telegram_live_edit: false,
             discord_bot_token: String::new(),
             discord_allow_from: Vec::new(),
             discord_allowed_channels: Vec::new(),
+            matrix_homeserver: String::new(),
+            matrix_user_id: String::new(),
+            matrix_access_token: String::new(),
+            matrix_allow_from: Vec::new(),
+            matrix_allowed_rooms: Vec::new(),
+            mattermost_url: String::new(),
+            mattermost_token: String::new(),
+            mattermost_allow_from: Vec::new(),
+            mattermost_allowed_channels: Vec::new(),
+            irc_server: String::new(),
+            irc_tls: true,
+            irc_nick: String::new(),
+            irc_password: None,
+            irc_channels: Vec::new(),
+            irc_allow_from: Vec::new(),
+            xmpp_jid: String::new(),
+            xmpp_password: String::new(),
+            xmpp_server: String::new(),
+            xmpp_allow_from: Vec::new(),
+            xmpp_roster_only: true,
+            websocket_listen: String::new(),
+            websocket_token: String::new(),
+            websocket_allowed_origins: Vec::new(),
+            mock_channel: false,
+            mock_channel_script: None,
+            mock_channel_transcript: None,
+            broadcast_targets: Vec::new(),
+            rate_limits: default_rate_limits(),
             transcription_enabled: true,
             transcription_provider: "openai".to_string(),
             transcription_model: "whisper-1".to_string(),
@@ -137,15 +349,110 @@ impl AppConfig {
             transcription_mistral_timestamp_granularities: Vec::new(),
             data_dir: default_data_dir(),
             workspace_dir: default_workspace_dir(),
+            admins: vec!["local".to_string()],
+            moderation_rules: Vec::new(),
+            moderation_model: None,
+            moderation_command: None,
+            moderation_notify: None,
             exec_timeout_secs: 60,
+            exec_profiles: default_exec_profiles(),
+            exec_profile: "normal".to_string(),
+            exec_role_profiles: HashMap::new(),
+            form_timeout_secs: 600,
             restrict_to_workspace: false,
+            sandbox_chats: false,
+            sandbox_shared_readonly: Vec::new(),
+            backup_enabled: false,
+            backup_interval_hours: 24,
+            backup_keep: 7,
+            backup_dir: default_backup_dir(),
+            backup_passphrase: None,
+            backup_upload: false,
+            remote: None,
+            sync_enabled: false,
+            sync_interval_secs: 300,
+            sync_exclude: vec!["artifacts".to_string()],
+            calendar_export: false,
+            calendar_horizon_days: 30,
             max_tool_turns: 20,
+            max_repeated_tool_calls: 3,
+            stream_completions: false,
+            tool_footer: false,
+            system_prompt: None,
+            system_prompt_file: PathBuf::from("prompt.md"),
+            system_prompt_mode: "extend".to_string(),
+            personas: HashMap::new(),
+            persona: String::new(),
+            provider_mode: "off".to_string(),
+            cassette_dir: default_cassette_dir(),
             memory_enabled: true,
             memory_vector_enabled: true,
             memory_embedding_model: "text-embedding-3-small".to_string(),
             memory_extraction_model: "gpt-4o-mini".to_string(),
             memory_max_memories: 1000,
             memory_extraction_interval: 10,
+            memory_layout: "plain".to_string(),
+            memory_rescore_interval_hours: 24,
+            archive_after_days: 0,
+            index_globs: vec!["**/*.md".to_string(), "**/*.txt".to_string()],
+            index_exclude: ["sessions", "archive", "chats", "attachments"]
+                .map(String::from)
+                .to_vec(),
+            index_max_chunks: 20_000,
+            memory_candidate_threshold: 0.5,
+            memory_recency_weight: 0.25,
+            memory_recall: RecallPolicy::default(),
+            memory_collections: Vec::new(),
+            memory_recall_cron: Value::Null,
+            memory_recall_chats: HashMap::new(),
+            expense_default_currency: "USD".to_string(),
+            summarize_model: "gpt-4o-mini".to_string(),
+            inline_enabled: true,
+            inline_model: "gpt-4o-mini".to_string(),
+            inline_cache_secs: 300,
+            plan_confirm_enabled: false,
+            plan_confirm_destructive_keywords: default_destructive_keywords(),
+            plan_confirm_multi_step_markers: default_multi_step_markers(),
+            auto_followups: true,
+            escalation_resend_after_mins: 10,
+            escalation_max_resends: 2,
+            escalation_secondary: None,
+            tool_timeout_secs: 120,
+            tool_output_max_chars: 50_000,
+            tool_timeouts: HashMap::from([("summarize_url".to_string(), 600)]),
+            tool_output_limits: HashMap::from([("exec".to_string(), 10_000)]),
+            schema_dialects: HashMap::new(),
+            http_tools: Vec::new(),
+            secrets: HashMap::new(),
+            tool_hooks: HashMap::new(),
+            model_prices: HashMap::new(),
+            cost_confirm_usd: 0.0,
+            budget: SpendLimits::default(),
+            skills_enabled: true,
+            skills_threshold: 0.45,
+            timezone: String::new(),
+            startup_notify: None,
+            feedback_log: false,
+            network_provider: NetworkPolicy {
+                timeout_secs: 300,
+                retries: 2,
+                backoff_ms: 400,
+                ..Default::default()
+            },
+            network_web: NetworkPolicy {
+                timeout_secs: 60,
+                ..Default::default()
+            },
+            network_transcription: NetworkPolicy {
+                timeout_secs: 120,
+                ..Default::default()
+            },
+            network_telegram: NetworkPolicy {
+                connect_timeout_secs: 5,
+                timeout_secs: 17,
+                retries: 3,
+                ..Default::default()
+            },
         }
     }
 
@@ -154,13 +461,14 @@ impl AppConfig {
             ProviderKind::OpenRouter => &self.openrouter_api_key,
             ProviderKind::OpenAI => &self.openai_api_key,
             ProviderKind::Ollama => &self.ollama_api_key,
+            ProviderKind::Mock => "",
         }
     }
 
     pub fn provider_requires_api_key(&self) -> bool {
         match self.provider {
             ProviderKind::OpenRouter | ProviderKind::OpenAI => true,
-            ProviderKind::Ollama => false,
+            ProviderKind::Ollama | ProviderKind::Mock => false,
         }
     }
 
@@ -172,6 +480,338 @@ impl AppConfig {
         !self.discord_bot_token.trim().is_empty()
     }
 
+    pub fn matrix_enabled(&self) -> bool {
+        !self.matrix_homeserver.trim().is_empty() && !self.matrix_access_token.trim().is_empty()
+    }
+
+    pub fn mattermost_enabled(&self) -> bool {
+        !self.mattermost_url.trim().is_empty() && !self.mattermost_token.trim().is_empty()
+    }
+
+    pub fn irc_enabled(&self) -> bool {
+        !self.irc_server.trim().is_empty() && !self.irc_nick.trim().is_empty()
+    }
+
+    pub fn xmpp_enabled(&self) -> bool {
+        !self.xmpp_jid.trim().is_empty() && !self.xmpp_password.is_empty()
+    }
+
+    pub fn websocket_enabled(&self) -> bool {
+        !self.websocket_listen.trim().is_empty()
+    }
+
+    pub fn mock_channel_enabled(&self) -> bool {
+        self.mock_channel
+    }
+
+    pub fn obsidian_memory(&self) -> bool {
+        self.memory_layout == "obsidian"
+    }
+
+    /// Whether a custom system prompt stands in for the built-in one.
+    pub fn replace_system_prompt(&self) -> bool {
+        self.system_prompt_mode == "replace"
+    }
+
+    /// Settings that load but are probably not what the operator meant.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let channels = [
+            self.telegram_enabled(),
+            self.discord_enabled(),
+            self.matrix_enabled(),
+            self.mattermost_enabled(),
+            self.irc_enabled(),
+            self.xmpp_enabled(),
+            self.websocket_enabled(),
+            self.mock_channel_enabled(),
+        ];
+        if !channels.contains(&true) {
+            warnings.push("no chat channel is configured".to_string());
+        }
+        if self.telegram_enabled() && self.telegram_allow_from.is_empty() {
+            warnings
+                .push("telegram has no allow_from list; anyone can chat with the bot".to_string());
+        }
+        if self.discord_enabled() && self.discord_allow_from.is_empty() {
+            warnings
+                .push("discord has no allow_from list; anyone can chat with the bot".to_string());
+        }
+        if self.websocket_enabled() && self.websocket_token.is_empty() {
+            warnings.push("websocket channel has no token".to_string());
+        }
+        for tool in &self.http_tools {
+            if let Some(auth) = &tool.auth {
+                if self.secret(&auth.secret).is_none() {
+                    warnings.push(format!(
+                        "http tool '{}' uses secret '{}', which is not set",
+                        tool.name, auth.secret
+                    ));
+                }
+            }
+        }
+        if !self.timezone.is_empty() && self.timezone.parse::<chrono_tz::Tz>().is_err() {
+            warnings.push(format!(
+                "unknown timezone '{}'; using the system zone",
+                self.timezone
+            ));
+        }
+        if !matches!(self.system_prompt_mode.as_str(), "extend" | "replace") {
+            warnings.push(format!(
+                "unknown system_prompt_mode '{}'; extending the built-in prompt",
+                self.system_prompt_mode
+            ));
+        }
+        if !self.persona.is_empty() && !self.personas.contains_key(&self.persona) {
+            warnings.push(format!("unknown default persona '{}'", self.persona));
+        }
+        if !self.exec_profiles.contains_key(&self.exec_profile) {
+            warnings.push(format!("unknown exec profile '{}'", self.exec_profile));
+        }
+        for (name, profile) in &self.exec_profiles {
+            if !matches!(profile.guard.as_str(), "strict" | "standard" | "off") {
+                warnings.push(format!(
+                    "exec profile '{name}' has unknown guard '{}'",
+                    profile.guard
+                ));
+            }
+            if !matches!(profile.backend.as_str(), "host" | "docker") {
+                warnings.push(format!(
+                    "exec profile '{name}' has unknown backend '{}'",
+                    profile.backend
+                ));
+            }
+            if !matches!(
+                profile.shell.as_str(),
+                "auto" | "sh" | "cmd" | "powershell" | "pwsh"
+            ) {
+                warnings.push(format!(
+                    "exec profile '{name}' has unknown shell '{}'",
+                    profile.shell
+                ));
+            }
+        }
+        if let Some(target) = &self.escalation_secondary {
+            if !target.contains(':') {
+                warnings.push(format!(
+                    "notifications.escalation.secondary '{target}' is not channel:chat_id"
+                ));
+            }
+        }
+        let overrides = std::iter::once(&self.memory_recall_cron)
+            .chain(self.memory_recall_chats.values())
+            .map(|partial| RecallPolicy::default().overlay(partial));
+        let mut unknown = HashSet::new();
+        for policy in std::iter::once(self.memory_recall.clone()).chain(overrides) {
+            for name in policy
+                .collections
+                .iter()
+                .chain(&policy.exclude_collections)
+                .chain(&policy.boost_collections)
+            {
+                if !self.memory_collections.contains(name) && unknown.insert(name.clone()) {
+                    warnings.push(format!(
+                        "memory recall refers to collection '{name}', which is not in memory.collections"
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Exec profile for a turn: the chat's own choice, then the sender's
+    /// role, then the default. Unknown names fall back to the default.
+    pub fn exec_profile_for(&self, chat_choice: Option<&str>, sender_id: &str) -> String {
+        let role = if sender_id == "cron" {
+            "cron"
+        } else if self.is_admin(sender_id) {
+            "admin"
+        } else {
+            "user"
+        };
+        chat_choice
+            .or(self.exec_role_profiles.get(role).map(String::as_str))
+            .filter(|name| self.exec_profiles.contains_key(*name))
+            .unwrap_or(&self.exec_profile)
+            .to_string()
+    }
+
+    /// Persona for a turn in `session_key`: the chat's own choice, then a
+    /// persona listing the chat, then the default. Unknown names are skipped.
+    pub fn persona_for(
+        &self,
+        chat_choice: Option<&str>,
+        session_key: &str,
+    ) -> Option<(String, Persona)> {
+        let mut names: Vec<&String> = self.personas.keys().collect();
+        names.sort_unstable();
+        let listed = names
+            .into_iter()
+            .find(|name| {
+                self.personas[*name]
+                    .chats
+                    .iter()
+                    .any(|chat| chat == session_key)
+            })
+            .map(String::as_str);
+        [chat_choice, listed, Some(self.persona.as_str())]
+            .into_iter()
+            .flatten()
+            .find_map(|name| {
+                self.personas
+                    .get(name)
+                    .map(|persona| (name.to_string(), persona.clone()))
+            })
+    }
+
+    /// Longest an exec call can take under any profile, approval included.
+    pub fn exec_longest_secs(&self) -> u64 {
+        self.exec_profiles
+            .values()
+            .map(|profile| {
+                let approval = if profile.approval {
+                    self.form_timeout_secs
+                } else {
+                    0
+                };
+                profile.timeout_secs.unwrap_or(self.exec_timeout_secs) + approval
+            })
+            .max()
+            .unwrap_or(self.exec_timeout_secs)
+            .max(self.exec_timeout_secs)
+    }
+
+    fn network_policies_mut(&mut self) -> [(&'static str, &mut NetworkPolicy); 4] {
+        [
+            ("provider", &mut self.network_provider),
+            ("web", &mut self.network_web),
+            ("transcription", &mut self.network_transcription),
+            ("telegram", &mut self.network_telegram),
+        ]
+    }
+
+    /// Whether `model` can be sent images: listed in `vision_models`, or,
+    /// when that is empty, from a family known to accept them.
+    pub fn supports_vision(&self, model: &str) -> bool {
+        if !self.vision_models.is_empty() {
+            return self.vision_models.iter().any(|m| m == model);
+        }
+        const FAMILIES: &[&str] = &[
+            "gpt-4o", "gpt-4.1", "gpt-5", "claude", "gemini", "pixtral", "llava", "vision", "-vl",
+        ];
+        let model = model.to_lowercase();
+        FAMILIES.iter().any(|family| model.contains(family))
+    }
+
+    /// Whether `sender_id` may use admin chat commands.
+    pub fn is_admin(&self, sender_id: &str) -> bool {
+        self.admins.iter().any(|a| a == sender_id)
+    }
+
+    /// Secret `name`: `FEMTOBOT_SECRET_<NAME>` from the environment, else the
+    /// `secrets` section of the config.
+    pub fn secret(&self, name: &str) -> Option<String> {
+        let var = format!(
+            "FEMTOBOT_SECRET_{}",
+            name.to_ascii_uppercase().replace(['-', '.'], "_")
+        );
+        std::env::var(var)
+            .ok()
+            .or_else(|| self.secrets.get(name).cloned())
+            .filter(|secret| !secret.is_empty())
+    }
+
+    /// Every configured credential, for scrubbing files people read or
+    /// share (provider cassettes, debug bundles): provider keys, channel
+    /// tokens and passwords, storage credentials and the named `secrets`,
+    /// including `FEMTOBOT_SECRET_*` overrides. Values shorter than eight
+    /// characters are left out so ordinary words are not redacted.
+    pub fn secret_values(&self) -> Vec<String> {
+        let remote = self.remote.as_ref();
+        let fixed = [
+            Some(&self.openrouter_api_key),
+            Some(&self.openai_api_key),
+            Some(&self.ollama_api_key),
+            Some(&self.mistral_api_key),
+            self.brave_api_key.as_ref(),
+            Some(&self.telegram_bot_token),
+            Some(&self.discord_bot_token),
+            Some(&self.matrix_access_token),
+            Some(&self.mattermost_token),
+            self.irc_password.as_ref(),
+            Some(&self.xmpp_password),
+            Some(&self.websocket_token),
+            self.backup_passphrase.as_ref(),
+            remote.and_then(|r| r.secret_key.as_ref()),
+            remote.and_then(|r| r.password.as_ref()),
+        ]
+        .into_iter()
+        .flatten()
+        .cloned();
+        let overrides = std::env::vars()
+            .filter(|(name, _)| name.starts_with("FEMTOBOT_SECRET_"))
+            .map(|(_, value)| value);
+        let mut values: Vec<String> = fixed
+            .chain(self.secrets.values().cloned())
+            .chain(overrides)
+            .map(|s| s.trim().to_string())
+            .filter(|s| s.len() >= 8)
+            .collect();
+        // Longer first, so a secret containing another is redacted whole.
+        values.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        values.dedup();
+        values
+    }
+
+    /// Schema dialect for `route`: configured by `provider/model`, then by
+    /// model name. Local models default to the simple dialect.
+    pub fn schema_dialect(&self, route: &ModelRoute) -> SchemaDialect {
+        let key = format!("{}/{}", route.provider.as_str(), route.model);
+        self.schema_dialects
+            .get(&key)
+            .or_else(|| self.schema_dialects.get(&route.model))
+            .copied()
+            .unwrap_or(if route.provider == ProviderKind::Ollama {
+                SchemaDialect::Simple
+            } else {
+                SchemaDialect::Full
+            })
+    }
+
+    /// Recall policy for a turn in `channel:chat_id`: the base policy, then
+    /// the cron overrides for cron turns, then the chat's overrides.
+    pub fn recall_policy(&self, channel: &str, chat_id: &str, cron: bool) -> RecallPolicy {
+        let mut policy = self.memory_recall.clone();
+        if cron {
+            policy = policy.overlay(&self.memory_recall_cron);
+        }
+        if let Some(chat) = self
+            .memory_recall_chats
+            .get(&format!("{channel}:{chat_id}"))
+        {
+            policy = policy.overlay(chat);
+        }
+        policy
+    }
+
+    /// Most memories any policy recalls; the agent asks the store for this
+    /// many and each turn's policy trims the result.
+    pub fn recall_max_samples(&self) -> usize {
+        let base = &self.memory_recall;
+        std::iter::once(base.clone().overlay(&self.memory_recall_cron))
+            .chain(self.memory_recall_chats.values().flat_map(|chat| {
+                [
+                    base.clone().overlay(chat),
+                    base.clone().overlay(&self.memory_recall_cron).overlay(chat),
+                ]
+            }))
+            .chain(std::iter::once(base.clone()))
+            .filter(|policy| policy.enabled)
+            .map(|policy| policy.samples)
+            .max()
+            .unwrap_or(0)
+    }
+
     pub fn model_routes(&self) -> Vec<ModelRoute> {
         let mut routes = Vec::new();
         let mut seen = HashSet::new();
@@ -195,8 +835,24 @@ impl AppConfig {
             }
         }
 
+        // The cheap route is also the last fallback.
+        if let Some(route) = self.cheap_route() {
+            let key = format!("{}/{}", route.provider.as_str(), route.model);
+            if seen.insert(key) {
+                routes.push(route);
+            }
+        }
+
         routes
     }
+
+    /// Route trivial requests are sent to, when routing is enabled.
+    pub fn cheap_route(&self) -> Option<ModelRoute> {
+        if !self.routing_enabled {
+            return None;
+        }
+        parse_model_route(&self.routing_cheap_model, &self.provider)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -205,6 +861,508 @@ pub struct ModelRoute {
     pub model: String,
 }
 
+/// How tool parameter schemas are written for a route's model.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaDialect {
+    /// The schemas as generated.
+    #[default]
+    Full,
+    /// No references, unions or nullable types, for models that trip over them.
+    Simple,
+}
+
+impl SchemaDialect {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "full" => Some(Self::Full),
+            "simple" => Some(Self::Simple),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteKind {
+    S3,
+    WebDav,
+}
+
+/// Outbound messages per second a channel accepts, across all chats and
+/// into a single chat.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RateLimit {
+    pub global_per_sec: f64,
+    pub per_chat_per_sec: f64,
+}
+
+/// Telegram allows ~30 msg/s per bot and ~1 msg/s per chat; Discord's
+/// global limit is 50 req/s.
+fn default_rate_limits() -> HashMap<String, RateLimit> {
+    HashMap::from([
+        (
+            "telegram".to_string(),
+            RateLimit {
+                global_per_sec: 30.0,
+                per_chat_per_sec: 1.0,
+            },
+        ),
+        (
+            "discord".to_string(),
+            RateLimit {
+                global_per_sec: 50.0,
+                per_chat_per_sec: 1.0,
+            },
+        ),
+        (
+            "matrix".to_string(),
+            RateLimit {
+                global_per_sec: 10.0,
+                per_chat_per_sec: 1.0,
+            },
+        ),
+        // The server's default API limit is 10 requests/s per token.
+        (
+            "mattermost".to_string(),
+            RateLimit {
+                global_per_sec: 10.0,
+                per_chat_per_sec: 1.0,
+            },
+        ),
+        // Servers start throttling clients that send faster than ~1 line/s.
+        (
+            "irc".to_string(),
+            RateLimit {
+                global_per_sec: 1.0,
+                per_chat_per_sec: 0.0,
+            },
+        ),
+    ])
+}
+
+/// How much vector memory is recalled into a turn's dynamic context.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RecallPolicy {
+    pub enabled: bool,
+    /// Memories injected at most.
+    pub samples: usize,
+    /// Cosine similarity a memory needs to be recalled at all.
+    pub min_similarity: f32,
+    /// Approximate tokens kept per recalled memory; 0 keeps them whole.
+    pub max_item_tokens: usize,
+    /// Only memories in these collections (or in none) are recalled; empty
+    /// allows every collection.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collections: Vec<String>,
+    /// Collections never recalled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_collections: Vec<String>,
+    /// Collections ranked ahead of equally similar memories.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub boost_collections: Vec<String>,
+}
+
+impl Default for RecallPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            samples: 5,
+            min_similarity: 0.0,
+            max_item_tokens: 0,
+            collections: Vec::new(),
+            exclude_collections: Vec::new(),
+            boost_collections: Vec::new(),
+        }
+    }
+}
+
+impl RecallPolicy {
+    /// This policy with the fields present in `partial` replaced.
+    pub fn overlay(mut self, partial: &Value) -> Self {
+        if let Some(enabled) = partial.get("enabled").and_then(Value::as_bool) {
+            self.enabled = enabled;
+        }
+        if let Some(samples) = partial.get("samples").and_then(Value::as_u64) {
+            self.samples = samples as usize;
+        }
+        if let Some(min) = partial.get("min_similarity").and_then(Value::as_f64) {
+            self.min_similarity = min.clamp(-1.0, 1.0) as f32;
+        }
+        if let Some(tokens) = partial.get("max_item_tokens").and_then(Value::as_u64) {
+            self.max_item_tokens = tokens as usize;
+        }
+        for (key, field) in [
+            ("collections", &mut self.collections),
+            ("exclude_collections", &mut self.exclude_collections),
+            ("boost_collections", &mut self.boost_collections),
+        ] {
+            if let Some(list) = partial.get(key).and_then(Value::as_array) {
+                *field = list
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|name| name.trim().to_lowercase())
+                    .filter(|name| !name.is_empty())
+                    .collect();
+            }
+        }
+        self
+    }
+
+    /// Whether a memory in `collection` may be recalled under this policy.
+    pub fn admits(&self, collection: Option<&str>) -> bool {
+        match collection {
+            None => true,
+            Some(name) => {
+                !self.exclude_collections.iter().any(|c| c == name)
+                    && (self.collections.is_empty() || self.collections.iter().any(|c| c == name))
+            }
+        }
+    }
+
+    pub fn boosts(&self, collection: Option<&str>) -> bool {
+        collection.is_some_and(|name| self.boost_collections.iter().any(|c| c == name))
+    }
+}
+
+/// Daily token and dollar budgets, counted from `usage.jsonl` and reset at
+/// local midnight. A limit of 0 is off.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct SpendLimits {
+    pub daily_usd: f64,
+    pub daily_tokens: u64,
+    /// Per chat (session), on top of the global limits.
+    pub chat_daily_usd: f64,
+    pub chat_daily_tokens: u64,
+    /// Refuse turns over budget instead of moving them to the cheapest route.
+    pub refuse: bool,
+    /// `channel:chat_id` told when a limit is reached; defaults to
+    /// `startup.notify`.
+    pub notify: Option<String>,
+}
+
+impl SpendLimits {
+    pub fn is_set(&self) -> bool {
+        self.daily_usd > 0.0
+            || self.daily_tokens > 0
+            || self.chat_daily_usd > 0.0
+            || self.chat_daily_tokens > 0
+    }
+}
+
+/// Timeouts and retries for one area's HTTP requests.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct NetworkPolicy {
+    pub connect_timeout_secs: u64,
+    /// Whole request, including reading the response.
+    pub timeout_secs: u64,
+    /// Extra tries after a transient failure; 0 fails on the first.
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each one after.
+    pub backoff_ms: u64,
+}
+
+impl Default for NetworkPolicy {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            timeout_secs: 30,
+            retries: 1,
+            backoff_ms: 1_000,
+        }
+    }
+}
+
+impl NetworkPolicy {
+    /// This policy with the fields present in `partial` replaced.
+    pub fn overlay(mut self, partial: &Value) -> Self {
+        if let Some(secs) = partial.get("connect_timeout_secs").and_then(Value::as_u64) {
+            self.connect_timeout_secs = secs.max(1);
+        }
+        if let Some(secs) = partial.get("timeout_secs").and_then(Value::as_u64) {
+            self.timeout_secs = secs.max(1);
+        }
+        if let Some(retries) = partial.get("retries").and_then(Value::as_u64) {
+            self.retries = retries.min(10) as u32;
+        }
+        if let Some(ms) = partial.get("backoff_ms").and_then(Value::as_u64) {
+            self.backoff_ms = ms;
+        }
+        self
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    /// Wait before retry number `retry` (0 for the first).
+    pub fn backoff(&self, retry: u32) -> Duration {
+        Duration::from_millis(self.backoff_ms.saturating_mul(1 << retry.min(16)))
+    }
+
+    /// A reqwest client builder with this policy's timeouts.
+    pub fn client_builder(&self) -> reqwest::ClientBuilder {
+        reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs))
+            .timeout(self.timeout())
+    }
+}
+
+/// Settings the exec tool runs with, switchable as a bundle per chat or role.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ExecProfile {
+    /// "strict" (read-only commands only), "standard" (dangerous patterns
+    /// blocked) or "off".
+    pub guard: String,
+    /// "host", or "docker" to run in a throwaway container without network.
+    pub backend: String,
+    /// Image for the docker backend.
+    pub image: String,
+    /// `None` uses `tools.exec.timeout`.
+    pub timeout_secs: Option<u64>,
+    /// Ask the chat before running each command.
+    pub approval: bool,
+    /// Host shell: "sh", "cmd", "powershell" or "pwsh". "auto" is cmd on
+    /// Windows and sh elsewhere.
+    pub shell: String,
+}
+
+impl Default for ExecProfile {
+    fn default() -> Self {
+        Self {
+            guard: "standard".to_string(),
+            backend: "host".to_string(),
+            image: "alpine:3".to_string(),
+            timeout_secs: None,
+            approval: false,
+            shell: "auto".to_string(),
+        }
+    }
+}
+
+impl ExecProfile {
+    /// This profile with the fields present in `partial` replaced.
+    pub fn overlay(mut self, partial: &Value) -> Self {
+        if let Some(guard) = partial.get("guard").and_then(Value::as_str) {
+            self.guard = guard.trim().to_lowercase();
+        }
+        if let Some(backend) = partial.get("backend").and_then(Value::as_str) {
+            self.backend = backend.trim().to_lowercase();
+        }
+        if let Some(image) = partial.get("image").and_then(Value::as_str) {
+            self.image = image.trim().to_string();
+        }
+        if let Some(timeout) = partial.get("timeout_secs").and_then(Value::as_u64) {
+            self.timeout_secs = Some(timeout.max(1));
+        }
+        if let Some(approval) = partial.get("approval").and_then(Value::as_bool) {
+            self.approval = approval;
+        }
+        if let Some(shell) = partial.get("shell").and_then(Value::as_str) {
+            self.shell = shell.trim().to_lowercase();
+        }
+        self
+    }
+}
+
+/// A named assistant profile: extra instructions, a preferred model and the
+/// tools it may use.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Persona {
+    /// Instructions added to each turn; `{{name}}` variables and the
+    /// `prompt.md` placeholders work.
+    pub prompt: String,
+    /// Model tried first, as with `/model`; a chat's `/model` still wins.
+    pub model: Option<String>,
+    /// Tools the persona may call; empty allows all of them.
+    pub tools: Vec<String>,
+    /// Tools withheld even when `tools` is empty.
+    pub deny_tools: Vec<String>,
+    /// Session keys (`channel:chat_id`) using this persona unless they
+    /// picked another with `/persona`.
+    pub chats: Vec<String>,
+}
+
+impl Persona {
+    /// This persona with the fields present in `partial` replaced.
+    pub fn overlay(mut self, partial: &Value) -> Self {
+        let strings = |key: &str| {
+            partial.get(key).and_then(Value::as_array).map(|items| {
+                items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect::<Vec<_>>()
+            })
+        };
+        if let Some(prompt) = partial.get("prompt").and_then(Value::as_str) {
+            self.prompt = prompt.trim().to_string();
+        }
+        if let Some(model) = partial.get("model").and_then(Value::as_str) {
+            self.model = Some(model.trim().to_string()).filter(|m| !m.is_empty());
+        }
+        if let Some(tools) = strings("tools") {
+            self.tools = tools;
+        }
+        if let Some(tools) = strings("deny_tools") {
+            self.deny_tools = tools;
+        }
+        if let Some(chats) = strings("chats") {
+            self.chats = chats;
+        }
+        self
+    }
+
+    pub fn allows_tool(&self, name: &str) -> bool {
+        (self.tools.is_empty() || self.tools.iter().any(|tool| tool == name))
+            && !self.deny_tools.iter().any(|tool| tool == name)
+    }
+}
+
+fn default_exec_profiles() -> HashMap<String, ExecProfile> {
+    HashMap::from([
+        (
+            "safe".to_string(),
+            ExecProfile {
+                guard: "strict".to_string(),
+                timeout_secs: Some(30),
+                approval: true,
+                ..Default::default()
+            },
+        ),
+        ("normal".to_string(), ExecProfile::default()),
+        (
+            "yolo".to_string(),
+            ExecProfile {
+                guard: "off".to_string(),
+                timeout_secs: Some(600),
+                ..Default::default()
+            },
+        ),
+    ])
+}
+
+/// A chat `femtobot broadcast` can reach, with tags for picking subsets.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BroadcastTarget {
+    pub channel: String,
+    pub chat_id: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A tool declared in config that calls an HTTP API. `{param}` in `url`,
+/// header values and `body` is replaced by the argument of that name.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HttpToolSpec {
+    pub name: String,
+    pub description: String,
+    pub url: String,
+    #[serde(default = "default_http_method")]
+    pub method: String,
+    /// Arguments by name: a description, or `{"description", "required"}`.
+    #[serde(default)]
+    pub params: BTreeMap<String, HttpParam>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub auth: Option<HttpAuth>,
+    /// Request body template; without one, POST/PUT/PATCH send the arguments as JSON.
+    #[serde(default)]
+    pub body: Option<String>,
+    /// JQ-style path into a JSON response, e.g. `.current.temp_c` or `.items[].title`.
+    #[serde(default)]
+    pub extract: Option<String>,
+}
+
+fn default_http_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HttpParam {
+    Described(String),
+    Full {
+        description: String,
+        #[serde(default = "default_param_required")]
+        required: bool,
+    },
+}
+
+fn default_param_required() -> bool {
+    true
+}
+
+impl HttpParam {
+    pub fn description(&self) -> &str {
+        match self {
+            Self::Described(description) | Self::Full { description, .. } => description,
+        }
+    }
+
+    pub fn required(&self) -> bool {
+        match self {
+            Self::Described(_) => true,
+            Self::Full { required, .. } => *required,
+        }
+    }
+}
+
+/// Header carrying a secret, e.g. `{"header": "Authorization", "scheme":
+/// "Bearer", "secret": "weather"}`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HttpAuth {
+    #[serde(default = "default_auth_header")]
+    pub header: String,
+    #[serde(default)]
+    pub scheme: Option<String>,
+    pub secret: String,
+}
+
+fn default_auth_header() -> String {
+    "Authorization".to_string()
+}
+
+/// Price of a model in USD per million tokens.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ModelPrice {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+/// Regex rule of the content filter: `action` is "block", "flag" or
+/// "redact"; `applies_to` is "inbound", "outbound" or "both".
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModerationRule {
+    pub pattern: String,
+    pub action: String,
+    #[serde(default = "default_moderation_direction")]
+    pub applies_to: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+fn default_moderation_direction() -> String {
+    "both".to_string()
+}
+
+/// Remote storage used for backup uploads and workspace sync.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoteConfig {
+    pub kind: RemoteKind,
+    /// S3 endpoint (e.g. `https://s3.eu-central-1.amazonaws.com`) or WebDAV base URL.
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub prefix: String,
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
 pub fn config_path() -> PathBuf {
     default_config_path().unwrap_or_else(|| PathBuf::from(".femtobot/config.json"))
 }
@@ -213,6 +1371,20 @@ fn default_config_path() -> Option<PathBuf> {
     dirs::home_dir().map(|p| p.join(".femtobot").join("config.json"))
 }
 
+fn default_backup_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".femtobot")
+        .join("backups")
+}
+
+fn default_cassette_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".femtobot")
+        .join("cassettes")
+}
+
 fn default_data_dir() -> PathBuf {
     dirs::home_dir()
         .unwrap_or_else(|| PathBuf::from("."))
@@ -227,13 +1399,58 @@ fn default_workspace_dir() -> PathBuf {
         .join("workspace")
 }
 
-fn load_femtobot_config() -> Option<Value> {
-    let path = default_config_path()?;
-    if !path.exists() {
-        return None;
-    }
-    let content = std::fs::read_to_string(path).ok()?;
-    serde_json::from_str::<Value>(&content).ok()
+fn default_destructive_keywords() -> Vec<String> {
+    [
+        "delete",
+        "remove",
+        "rm",
+        "erase",
+        "wipe",
+        "drop",
+        "truncate",
+        "overwrite",
+        "reset",
+        "purge",
+        "kill",
+        "uninstall",
+        "format",
+        "move",
+        "rename",
+        "replace",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+fn default_multi_step_markers() -> Vec<String> {
+    [
+        " then ",
+        "after that",
+        "afterwards",
+        "and also",
+        "finally",
+        "next,",
+        "step ",
+        "1.",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+fn load_femtobot_config() -> Result<Option<Value>> {
+    let Some(path) = default_config_path().filter(|path| path.exists()) else {
+        return Ok(None);
+    };
+    let Some(mut value) = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+    else {
+        return Ok(None);
+    };
+    crate::secrets::decrypt_tree(&mut value)?;
+    Ok(Some(value))
 }
 
 fn apply_femtobot_config(cfg: &mut AppConfig, value: &Value) {
@@ -248,6 +1465,7 @@ fn apply_femtobot_config(cfg: &mut AppConfig, value: &Value) {
     apply_provider_config(cfg, value, &["openrouter"], ProviderKind::OpenRouter);
     apply_provider_config(cfg, value, &["openai"], ProviderKind::OpenAI);
     apply_provider_config(cfg, value, &["ollama"], ProviderKind::Ollama);
+    apply_provider_config(cfg, value, &["mock"], ProviderKind::Mock);
     if let Some(obj) = get_provider_object(value, &["mistral"]) {
         if let Some(v) = obj
             .get("apiKey")
@@ -273,12 +1491,46 @@ fn apply_femtobot_config(cfg: &mut AppConfig, value: &Value) {
     {
         cfg.model_fallbacks = fallbacks;
     }
+    if let Some(enabled) = get_bool(value, &["agents", "defaults", "adaptive_fallback"]) {
+        cfg.adaptive_fallback = enabled;
+    }
+    if let Some(models) = get_array(value, &["agents", "defaults", "vision_models"]) {
+        cfg.vision_models = models;
+    }
     if let Some(ws) = get_str(value, &["agents", "defaults", "workspace"]) {
         cfg.workspace_dir = PathBuf::from(ws);
     }
     if let Some(timeout) = get_u64(value, &["tools", "exec", "timeout"]) {
         cfg.exec_timeout_secs = timeout;
     }
+    if let Some(profiles) = value
+        .get("tools")
+        .and_then(|t| t.get("exec"))
+        .and_then(|e| e.get("profiles"))
+        .and_then(Value::as_object)
+    {
+        for (name, partial) in profiles {
+            let base = cfg.exec_profiles.get(name).cloned().unwrap_or_default();
+            cfg.exec_profiles
+                .insert(name.clone(), base.overlay(partial));
+        }
+    }
+    if let Some(profile) = get_str(value, &["tools", "exec", "profile"]) {
+        cfg.exec_profile = profile.trim().to_string();
+    }
+    if let Some(roles) = value
+        .get("tools")
+        .and_then(|t| t.get("exec"))
+        .and_then(|e| e.get("roles"))
+        .and_then(Value::as_object)
+    {
+        for (role, profile) in roles {
+            if let Some(profile) = profile.as_str() {
+                cfg.exec_role_profiles
+                    .insert(role.clone(), profile.trim().to_string());
+            }
+        }
+    }
     if let Some(restrict) = get_bool(value, &["tools", "restrict_to_workspace"]) {
         cfg.restrict_to_workspace = restrict;
     }
@@ -290,17 +1542,146 @@ fn apply_femtobot_config(cfg: &mut AppConfig, value: &Value) {
     if let Some(token) = get_str(value, &["channels", "telegram", "token"]) {
         cfg.telegram_bot_token = token.to_string();
     }
-    if let Some(list) = get_array(value, &["channels", "telegram", "allow_from"]) {
-        cfg.telegram_allow_from = list;
+    if let Some(list) = get_array(value, &["channels", "telegram", "allow_from"]) {
+        cfg.telegram_allow_from = list;
+    }
+    if let Some(enabled) = get_bool(value, &["channels", "telegram", "mention_only"]) {
+        cfg.telegram_mention_only = enabled;
+    }
+    if let Some(enabled) = get_bool(value, &["channels", "telegram", "live_edit"]) {
+        cfg.telegram_live_edit = enabled;
+    }
+    if let Some(token) = get_str(value, &["channels", "discord", "token"]) {
+        cfg.discord_bot_token = token.to_string();
+    }
+    if let Some(list) = get_array(value, &["channels", "discord", "allow_from"]) {
+        cfg.discord_allow_from = list;
+    }
+    if let Some(list) = get_array(value, &["channels", "discord", "allowed_channels"]) {
+        cfg.discord_allowed_channels = list;
+    }
+    if let Some(url) = get_str(value, &["channels", "matrix", "homeserver"]) {
+        cfg.matrix_homeserver = url.trim_end_matches('/').to_string();
+    }
+    if let Some(user) = get_str(value, &["channels", "matrix", "user_id"]) {
+        cfg.matrix_user_id = user.to_string();
+    }
+    if let Some(token) = get_str(value, &["channels", "matrix", "access_token"]) {
+        cfg.matrix_access_token = token.to_string();
+    }
+    if let Some(list) = get_array(value, &["channels", "matrix", "allow_from"]) {
+        cfg.matrix_allow_from = list;
+    }
+    if let Some(list) = get_array(value, &["channels", "matrix", "allowed_rooms"]) {
+        cfg.matrix_allowed_rooms = list;
+    }
+    if let Some(url) = get_str(value, &["channels", "mattermost", "url"]) {
+        cfg.mattermost_url = url.trim().to_string();
+    }
+    if let Some(token) = get_str(value, &["channels", "mattermost", "token"]) {
+        cfg.mattermost_token = token.trim().to_string();
+    }
+    if let Some(list) = get_array(value, &["channels", "mattermost", "allow_from"]) {
+        cfg.mattermost_allow_from = list;
+    }
+    if let Some(list) = get_array(value, &["channels", "mattermost", "allowed_channels"]) {
+        cfg.mattermost_allowed_channels = list;
+    }
+    if let Some(server) = get_str(value, &["channels", "irc", "server"]) {
+        cfg.irc_server = server.trim().to_string();
+    }
+    if let Some(tls) = get_bool(value, &["channels", "irc", "tls"]) {
+        cfg.irc_tls = tls;
+    }
+    if let Some(nick) = get_str(value, &["channels", "irc", "nick"]) {
+        cfg.irc_nick = nick.trim().to_string();
+    }
+    if let Some(password) = get_str(value, &["channels", "irc", "password"]) {
+        cfg.irc_password = Some(password.to_string()).filter(|p| !p.is_empty());
+    }
+    if let Some(list) = get_array(value, &["channels", "irc", "channels"]) {
+        cfg.irc_channels = list;
+    }
+    if let Some(list) = get_array(value, &["channels", "irc", "allow_from"]) {
+        cfg.irc_allow_from = list;
+    }
+    if let Some(jid) = get_str(value, &["channels", "xmpp", "jid"]) {
+        cfg.xmpp_jid = jid.trim().to_string();
+    }
+    if let Some(password) = get_str(value, &["channels", "xmpp", "password"]) {
+        cfg.xmpp_password = password.to_string();
+    }
+    if let Some(server) = get_str(value, &["channels", "xmpp", "server"]) {
+        cfg.xmpp_server = server.trim().to_string();
+    }
+    if let Some(list) = get_array(value, &["channels", "xmpp", "allow_from"]) {
+        cfg.xmpp_allow_from = list;
+    }
+    if let Some(roster_only) = get_bool(value, &["channels", "xmpp", "roster_only"]) {
+        cfg.xmpp_roster_only = roster_only;
+    }
+    if let Some(listen) = get_str(value, &["channels", "websocket", "listen"]) {
+        cfg.websocket_listen = listen.trim().to_string();
+    }
+    if let Some(token) = get_str(value, &["channels", "websocket", "token"]) {
+        cfg.websocket_token = token.trim().to_string();
+    }
+    if let Some(list) = get_array(value, &["channels", "websocket", "allowed_origins"]) {
+        cfg.websocket_allowed_origins = list;
+    }
+    if let Some(enabled) = get_bool(value, &["channels", "mock", "enabled"]) {
+        cfg.mock_channel = enabled;
+    }
+    if let Some(path) = get_str(value, &["channels", "mock", "script"]) {
+        cfg.mock_channel_script = Some(PathBuf::from(path.trim()));
+    }
+    if let Some(path) = get_str(value, &["channels", "mock", "transcript"]) {
+        cfg.mock_channel_transcript = Some(PathBuf::from(path.trim()));
     }
-    if let Some(token) = get_str(value, &["channels", "discord", "token"]) {
-        cfg.discord_bot_token = token.to_string();
+    if let Some(tools) = value.get("tools").and_then(|t| t.get("http")) {
+        match serde_json::from_value(tools.clone()) {
+            Ok(tools) => cfg.http_tools = tools,
+            Err(err) => tracing::warn!("invalid tools.http: {err}"),
+        }
     }
-    if let Some(list) = get_array(value, &["channels", "discord", "allow_from"]) {
-        cfg.discord_allow_from = list;
+    if let Some(secrets) = value.get("secrets").and_then(Value::as_object) {
+        for (name, secret) in secrets {
+            if let Some(secret) = secret.as_str() {
+                cfg.secrets.insert(name.clone(), secret.to_string());
+            }
+        }
     }
-    if let Some(list) = get_array(value, &["channels", "discord", "allowed_channels"]) {
-        cfg.discord_allowed_channels = list;
+    if let Some(targets) = value.get("broadcast").and_then(|b| b.get("targets")) {
+        match serde_json::from_value(targets.clone()) {
+            Ok(targets) => cfg.broadcast_targets = targets,
+            Err(err) => tracing::warn!("invalid broadcast.targets: {err}"),
+        }
+    }
+    if let Some(channels) = value.get("channels").and_then(Value::as_object) {
+        for (channel, channel_cfg) in channels {
+            let Some(limit) = channel_cfg.get("rate_limit") else {
+                continue;
+            };
+            let current = cfg.rate_limits.get(channel).copied();
+            let field = |name: &str, fallback: Option<f64>| {
+                limit
+                    .get(name)
+                    .and_then(Value::as_f64)
+                    .or(fallback)
+                    .unwrap_or(0.0)
+                    .max(0.0)
+            };
+            cfg.rate_limits.insert(
+                channel.clone(),
+                RateLimit {
+                    global_per_sec: field("global_per_sec", current.map(|c| c.global_per_sec)),
+                    per_chat_per_sec: field(
+                        "per_chat_per_sec",
+                        current.map(|c| c.per_chat_per_sec),
+                    ),
+                },
+            );
+        }
     }
     if let Some(enabled) = get_bool(value, &["channels", "telegram", "transcription", "enabled"]) {
         cfg.transcription_enabled = enabled;
@@ -361,6 +1742,38 @@ fn apply_femtobot_config(cfg: &mut AppConfig, value: &Value) {
     if let Some(turns) = get_u64(value, &["agents", "defaults", "max_tool_iterations"]) {
         cfg.max_tool_turns = turns as usize;
     }
+    if let Some(repeats) = get_u64(value, &["agents", "defaults", "max_repeated_tool_calls"]) {
+        cfg.max_repeated_tool_calls = repeats as usize;
+    }
+    if let Some(stream) = get_bool(value, &["agents", "defaults", "stream"]) {
+        cfg.stream_completions = stream;
+    }
+    if let Some(footer) = get_bool(value, &["agents", "defaults", "tool_footer"]) {
+        cfg.tool_footer = footer;
+    }
+    if let Some(prompt) = get_str(value, &["agents", "defaults", "system_prompt"]) {
+        cfg.system_prompt = Some(prompt.to_string()).filter(|p| !p.trim().is_empty());
+    }
+    if let Some(path) = get_str(value, &["agents", "defaults", "system_prompt_file"]) {
+        cfg.system_prompt_file = PathBuf::from(path.trim());
+    }
+    if let Some(mode) = get_str(value, &["agents", "defaults", "system_prompt_mode"]) {
+        cfg.system_prompt_mode = mode.trim().to_ascii_lowercase();
+    }
+    if let Some(personas) = value
+        .get("agents")
+        .and_then(|a| a.get("personas"))
+        .and_then(Value::as_object)
+    {
+        for (name, partial) in personas {
+            let base = cfg.personas.get(name).cloned().unwrap_or_default();
+            cfg.personas
+                .insert(name.trim().to_string(), base.overlay(partial));
+        }
+    }
+    if let Some(persona) = get_str(value, &["agents", "defaults", "persona"]) {
+        cfg.persona = persona.trim().to_string();
+    }
     if let Some(enabled) = get_bool(value, &["memory", "enabled"]) {
         cfg.memory_enabled = enabled;
     }
@@ -379,6 +1792,312 @@ fn apply_femtobot_config(cfg: &mut AppConfig, value: &Value) {
     if let Some(interval) = get_u64(value, &["memory", "extraction_interval"]) {
         cfg.memory_extraction_interval = interval as usize;
     }
+    if let Some(currency) = get_str(value, &["tools", "expenses", "default_currency"]) {
+        if !currency.trim().is_empty() {
+            cfg.expense_default_currency = currency.trim().to_uppercase();
+        }
+    }
+    if let Some(model) = get_str(value, &["tools", "summarize", "model"]) {
+        if !model.trim().is_empty() {
+            cfg.summarize_model = model.to_string();
+        }
+    }
+    if let Some(enabled) = get_bool(value, &["agents", "defaults", "plan_confirm", "enabled"]) {
+        cfg.plan_confirm_enabled = enabled;
+    }
+    if let Some(list) = get_array(
+        value,
+        &["agents", "defaults", "plan_confirm", "destructive_keywords"],
+    ) {
+        cfg.plan_confirm_destructive_keywords = list;
+    }
+    if let Some(list) = get_array(
+        value,
+        &["agents", "defaults", "plan_confirm", "multi_step_markers"],
+    ) {
+        cfg.plan_confirm_multi_step_markers = list;
+    }
+    if let Some(enabled) = get_bool(value, &["agents", "defaults", "auto_followups"]) {
+        cfg.auto_followups = enabled;
+    }
+    if let Some(mins) = get_u64(value, &["notifications", "escalation", "resend_after_mins"]) {
+        cfg.escalation_resend_after_mins = mins;
+    }
+    if let Some(max) = get_u64(value, &["notifications", "escalation", "max_resends"]) {
+        cfg.escalation_max_resends = max as u32;
+    }
+    if let Some(target) = get_str(value, &["notifications", "escalation", "secondary"]) {
+        cfg.escalation_secondary = (!target.trim().is_empty()).then(|| target.trim().to_string());
+    }
+    if let Some(limits) = value
+        .get("tools")
+        .and_then(|v| v.get("limits"))
+        .and_then(Value::as_object)
+    {
+        for (name, limit) in limits {
+            let timeout = limit.get("timeout_secs").and_then(Value::as_u64);
+            let max_chars = limit.get("max_output_chars").and_then(Value::as_u64);
+            if name == "default" {
+                if let Some(secs) = timeout {
+                    cfg.tool_timeout_secs = secs;
+                }
+                if let Some(chars) = max_chars {
+                    cfg.tool_output_max_chars = chars as usize;
+                }
+                continue;
+            }
+            if let Some(secs) = timeout {
+                cfg.tool_timeouts.insert(name.clone(), secs);
+            }
+            if let Some(chars) = max_chars {
+                cfg.tool_output_limits.insert(name.clone(), chars as usize);
+            }
+        }
+    }
+    if let Some(dialects) = value
+        .get("tools")
+        .and_then(|v| v.get("schema_dialects"))
+        .and_then(Value::as_object)
+    {
+        for (route, dialect) in dialects {
+            match dialect.as_str().and_then(SchemaDialect::parse) {
+                Some(dialect) => {
+                    cfg.schema_dialects.insert(route.clone(), dialect);
+                }
+                None => tracing::warn!(
+                    "unknown schema dialect for {route}: {dialect} (use full or simple)"
+                ),
+            }
+        }
+    }
+    if let Some(hooks) = value
+        .get("tools")
+        .and_then(|v| v.get("hooks"))
+        .and_then(Value::as_object)
+    {
+        for (name, specs) in hooks {
+            let specs = match specs {
+                Value::Array(items) => items.clone(),
+                single => vec![single.clone()],
+            };
+            cfg.tool_hooks.insert(name.clone(), specs);
+        }
+    }
+    if let Some(tz) = get_str(value, &["agents", "defaults", "timezone"]) {
+        cfg.timezone = tz.trim().to_string();
+    }
+    if let Some(target) = get_str(value, &["startup", "notify"]) {
+        cfg.startup_notify = Some(target.trim().to_string()).filter(|t| t.contains(':'));
+    }
+    if let Some(enabled) = get_bool(value, &["feedback", "log"]) {
+        cfg.feedback_log = enabled;
+    }
+    if let Some(enabled) = get_bool(value, &["tools", "sandbox", "enabled"]) {
+        cfg.sandbox_chats = enabled;
+    }
+    if let Some(shared) = get_array(value, &["tools", "sandbox", "shared_readonly"]) {
+        cfg.sandbox_shared_readonly = shared.into_iter().map(PathBuf::from).collect();
+    }
+    if let Some(enabled) = get_bool(value, &["backup", "enabled"]) {
+        cfg.backup_enabled = enabled;
+    }
+    if let Some(hours) = get_u64(value, &["backup", "interval_hours"]) {
+        cfg.backup_interval_hours = hours.max(1);
+    }
+    if let Some(keep) = get_u64(value, &["backup", "keep"]) {
+        cfg.backup_keep = keep as usize;
+    }
+    if let Some(dir) = get_str(value, &["backup", "dir"]) {
+        cfg.backup_dir = PathBuf::from(dir);
+    }
+    if let Some(passphrase) = get_str(value, &["backup", "passphrase"]) {
+        cfg.backup_passphrase = Some(passphrase.to_string()).filter(|p| !p.is_empty());
+    }
+    if let Some(upload) = get_bool(value, &["backup", "upload"]) {
+        cfg.backup_upload = upload;
+    }
+    if let Some(remote) = get_str(value, &["storage", "remote", "kind"]) {
+        cfg.remote = parse_remote_config(remote, value);
+    }
+    if let Some(enabled) = get_bool(value, &["storage", "sync", "enabled"]) {
+        cfg.sync_enabled = enabled;
+    }
+    if let Some(secs) = get_u64(value, &["storage", "sync", "interval_secs"]) {
+        cfg.sync_interval_secs = secs.max(30);
+    }
+    if let Some(exclude) = get_array(value, &["storage", "sync", "exclude"]) {
+        cfg.sync_exclude = exclude;
+    }
+    if let Some(layout) = get_str(value, &["memory", "layout"]) {
+        cfg.memory_layout = layout.trim().to_ascii_lowercase();
+    }
+    if let Some(enabled) = get_bool(value, &["cron", "calendar", "enabled"]) {
+        cfg.calendar_export = enabled;
+    }
+    if let Some(days) = get_u64(value, &["cron", "calendar", "horizon_days"]) {
+        cfg.calendar_horizon_days = days.max(1);
+    }
+    if let Some(secs) = get_u64(value, &["tools", "form", "timeout_secs"]) {
+        cfg.form_timeout_secs = secs.max(10);
+    }
+    if let Some(admins) = get_array(value, &["admins"]) {
+        cfg.admins = admins;
+    }
+    if let Some(admins) = get_array(value, &["tools", "sandbox", "admins"]) {
+        tracing::warn!("tools.sandbox.admins is deprecated; its senders are added to admins");
+        merge_admins(cfg, admins);
+    }
+    if let Some(mode) = get_str(value, &["recording", "mode"]) {
+        cfg.provider_mode = mode.trim().to_ascii_lowercase();
+    }
+    if let Some(dir) = get_str(value, &["recording", "dir"]) {
+        cfg.cassette_dir = PathBuf::from(dir);
+    }
+    if let Some(rules) = value.get("moderation").and_then(|m| m.get("rules")) {
+        match serde_json::from_value::<Vec<ModerationRule>>(rules.clone()) {
+            Ok(rules) => cfg.moderation_rules = rules,
+            Err(err) => tracing::warn!("invalid moderation.rules: {err}"),
+        }
+    }
+    if let Some(model) = get_str(value, &["moderation", "model"]) {
+        cfg.moderation_model = Some(model.trim().to_string()).filter(|m| !m.is_empty());
+    }
+    if let Some(command) = get_str(value, &["moderation", "command"]) {
+        cfg.moderation_command = Some(command.trim().to_string()).filter(|c| !c.is_empty());
+    }
+    if let Some(target) = get_str(value, &["moderation", "notify"]) {
+        cfg.moderation_notify = Some(target.trim().to_string()).filter(|t| t.contains(':'));
+    }
+    if let Some(prices) = value.get("pricing").and_then(Value::as_object) {
+        for (model, price) in prices {
+            let input = price.get("input").and_then(Value::as_f64);
+            let output = price.get("output").and_then(Value::as_f64);
+            if let (Some(input), Some(output)) = (input, output) {
+                cfg.model_prices.insert(
+                    model.clone(),
+                    ModelPrice {
+                        input_per_mtok: input,
+                        output_per_mtok: output,
+                    },
+                );
+            }
+        }
+    }
+    if let Some(limit) = value
+        .get("cost")
+        .and_then(|c| c.get("confirm_above_usd"))
+        .and_then(Value::as_f64)
+    {
+        cfg.cost_confirm_usd = limit.max(0.0);
+    }
+    if let Some(budget) = value.get("budget") {
+        let usd = |key: &str| {
+            budget
+                .get(key)
+                .and_then(Value::as_f64)
+                .unwrap_or(0.0)
+                .max(0.0)
+        };
+        cfg.budget.daily_usd = usd("daily_usd");
+        cfg.budget.chat_daily_usd = usd("chat_daily_usd");
+        cfg.budget.daily_tokens = get_u64(budget, &["daily_tokens"]).unwrap_or(0);
+        cfg.budget.chat_daily_tokens = get_u64(budget, &["chat_daily_tokens"]).unwrap_or(0);
+        match get_str(budget, &["on_exceed"]).map(str::trim) {
+            None | Some("downgrade") => cfg.budget.refuse = false,
+            Some("refuse") => cfg.budget.refuse = true,
+            Some(other) => {
+                tracing::warn!("unknown budget.on_exceed '{other}', expected downgrade or refuse")
+            }
+        }
+        if let Some(target) = get_str(budget, &["notify"]) {
+            cfg.budget.notify = Some(target.trim().to_string()).filter(|t| t.contains(':'));
+        }
+    }
+    if let Some(enabled) = get_bool(value, &["skills", "enabled"]) {
+        cfg.skills_enabled = enabled;
+    }
+    if let Some(threshold) = value
+        .get("skills")
+        .and_then(|s| s.get("threshold"))
+        .and_then(Value::as_f64)
+    {
+        cfg.skills_threshold = threshold.clamp(0.0, 1.0) as f32;
+    }
+    if let Some(hours) = get_u64(value, &["memory", "rescore_interval_hours"]) {
+        cfg.memory_rescore_interval_hours = hours;
+    }
+    if let Some(days) = get_u64(value, &["sessions", "archive_after_days"]) {
+        cfg.archive_after_days = days;
+    }
+    if let Some(list) = get_array(value, &["index", "globs"]) {
+        cfg.index_globs = list;
+    }
+    if let Some(list) = get_array(value, &["index", "exclude"]) {
+        cfg.index_exclude = list;
+    }
+    if let Some(max) = get_u64(value, &["index", "max_chunks"]) {
+        cfg.index_max_chunks = max as usize;
+    }
+    if let Some(threshold) = value
+        .get("memory")
+        .and_then(|m| m.get("candidate_threshold"))
+        .and_then(Value::as_f64)
+    {
+        cfg.memory_candidate_threshold = threshold.clamp(0.0, 1.0) as f32;
+    }
+    if let Some(weight) = value
+        .get("memory")
+        .and_then(|m| m.get("recency_weight"))
+        .and_then(Value::as_f64)
+    {
+        cfg.memory_recency_weight = weight.clamp(0.0, 1.0) as f32;
+    }
+    if let Some(list) = get_array(value, &["memory", "collections"]) {
+        cfg.memory_collections = list
+            .iter()
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+    }
+    if let Some(recall) = value.get("memory").and_then(|m| m.get("recall")) {
+        cfg.memory_recall = cfg.memory_recall.clone().overlay(recall);
+        if let Some(cron) = recall.get("cron") {
+            cfg.memory_recall_cron = cron.clone();
+        }
+        if let Some(chats) = recall.get("chats").and_then(Value::as_object) {
+            cfg.memory_recall_chats = chats
+                .iter()
+                .map(|(chat, policy)| (chat.clone(), policy.clone()))
+                .collect();
+        }
+    }
+    if let Some(network) = value.get("network") {
+        // Top-level keys apply to every area; `network.<area>` wins over them.
+        for (area, policy) in cfg.network_policies_mut() {
+            *policy = policy.clone().overlay(network);
+            if let Some(own) = network.get(area) {
+                *policy = policy.clone().overlay(own);
+            }
+        }
+    }
+    if let Some(enabled) = get_bool(value, &["channels", "telegram", "inline", "enabled"]) {
+        cfg.inline_enabled = enabled;
+    }
+    if let Some(model) = get_str(value, &["channels", "telegram", "inline", "model"]) {
+        cfg.inline_model = model.to_string();
+    }
+    if let Some(secs) = get_u64(value, &["channels", "telegram", "inline", "cache_secs"]) {
+        cfg.inline_cache_secs = secs;
+    }
+    if let Some(enabled) = get_bool(value, &["agents", "defaults", "routing", "enabled"]) {
+        cfg.routing_enabled = enabled;
+    }
+    if let Some(model) = get_str(value, &["agents", "defaults", "routing", "cheap_model"]) {
+        cfg.routing_cheap_model = model.to_string();
+    }
+    if let Some(chars) = get_u64(value, &["agents", "defaults", "routing", "max_cheap_chars"]) {
+        cfg.routing_max_cheap_chars = chars as usize;
+    }
 }
 
 fn apply_provider_config(
@@ -438,6 +2157,12 @@ fn apply_provider_config(
                 cfg.ollama_extra_headers = v;
             }
         }
+        ProviderKind::Mock => {
+            if let Some(v) = provider_obj.get("fixture").and_then(Value::as_str) {
+                cfg.mock_fixture =
+                    Some(PathBuf::from(v.trim())).filter(|p| !p.as_os_str().is_empty());
+            }
+        }
     }
 }
 
@@ -460,6 +2185,15 @@ fn get_provider_object<'a>(
     None
 }
 
+/// Add senders from the old `tools.sandbox.admins` list to `admins`.
+fn merge_admins(cfg: &mut AppConfig, admins: Vec<String>) {
+    for admin in admins {
+        if !cfg.admins.contains(&admin) {
+            cfg.admins.push(admin);
+        }
+    }
+}
+
 fn apply_env_overrides(cfg: &mut AppConfig) {
     if let Ok(provider) =
         std::env::var("FEMTOBOT_PROVIDER").or_else(|_| std::env::var("LLM_PROVIDER"))
@@ -498,6 +2232,9 @@ fn apply_env_overrides(cfg: &mut AppConfig) {
     if let Ok(base) = std::env::var("OLLAMA_BASE_URL") {
         cfg.ollama_base_url = base;
     }
+    if let Ok(path) = std::env::var("FEMTOBOT_MOCK_FIXTURE") {
+        cfg.mock_fixture = Some(PathBuf::from(path.trim())).filter(|p| !p.as_os_str().is_empty());
+    }
     if let Ok(key) = std::env::var("MISTRAL_API_KEY") {
         cfg.mistral_api_key = key;
     }
@@ -510,6 +2247,12 @@ fn apply_env_overrides(cfg: &mut AppConfig) {
     {
         cfg.telegram_bot_token = token;
     }
+    if let Ok(val) = std::env::var("FEMTOBOT_TELEGRAM_MENTION_ONLY") {
+        cfg.telegram_mention_only = parse_bool(&val).unwrap_or(cfg.telegram_mention_only);
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_TELEGRAM_LIVE_EDIT") {
+        cfg.telegram_live_edit = parse_bool(&val).unwrap_or(cfg.telegram_live_edit);
+    }
     if let Ok(token) = std::env::var("DISCORD_BOT_TOKEN") {
         cfg.discord_bot_token = token;
     }
@@ -529,6 +2272,61 @@ fn apply_env_overrides(cfg: &mut AppConfig) {
             .map(|s| s.to_string())
             .collect();
     }
+    if let Ok(url) = std::env::var("MATRIX_HOMESERVER") {
+        cfg.matrix_homeserver = url.trim_end_matches('/').to_string();
+    }
+    if let Ok(user) = std::env::var("MATRIX_USER_ID") {
+        cfg.matrix_user_id = user;
+    }
+    if let Ok(token) = std::env::var("MATRIX_ACCESS_TOKEN") {
+        cfg.matrix_access_token = token;
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_MATRIX_ALLOWED_ROOMS") {
+        cfg.matrix_allowed_rooms = val
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+    }
+    if let Ok(url) = std::env::var("MATTERMOST_URL") {
+        cfg.mattermost_url = url.trim().to_string();
+    }
+    if let Ok(token) = std::env::var("MATTERMOST_TOKEN") {
+        cfg.mattermost_token = token.trim().to_string();
+    }
+    if let Ok(listen) = std::env::var("WEBSOCKET_LISTEN") {
+        cfg.websocket_listen = listen.trim().to_string();
+    }
+    if let Ok(token) = std::env::var("WEBSOCKET_TOKEN") {
+        cfg.websocket_token = token.trim().to_string();
+    }
+    if let Ok(server) = std::env::var("IRC_SERVER") {
+        cfg.irc_server = server.trim().to_string();
+    }
+    if let Ok(nick) = std::env::var("IRC_NICK") {
+        cfg.irc_nick = nick.trim().to_string();
+    }
+    if let Ok(password) = std::env::var("IRC_PASSWORD") {
+        cfg.irc_password = Some(password).filter(|p| !p.is_empty());
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_IRC_CHANNELS") {
+        cfg.irc_channels = val
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+    }
+    if let Ok(jid) = std::env::var("XMPP_JID") {
+        cfg.xmpp_jid = jid.trim().to_string();
+    }
+    if let Ok(password) = std::env::var("XMPP_PASSWORD") {
+        cfg.xmpp_password = password;
+    }
+    if let Ok(server) = std::env::var("XMPP_SERVER") {
+        cfg.xmpp_server = server.trim().to_string();
+    }
     if let Ok(brave) = std::env::var("BRAVE_API_KEY") {
         cfg.brave_api_key = Some(brave);
     }
@@ -602,6 +2400,9 @@ fn apply_env_overrides(cfg: &mut AppConfig) {
             cfg.exec_timeout_secs = num;
         }
     }
+    if let Ok(val) = std::env::var("FEMTOBOT_EXEC_PROFILE") {
+        cfg.exec_profile = val.trim().to_string();
+    }
     if let Ok(val) = std::env::var("FEMTOBOT_MAX_TOOL_TURNS")
         .or_else(|_| std::env::var("RUSTBOT_MAX_TOOL_TURNS"))
     {
@@ -609,6 +2410,24 @@ fn apply_env_overrides(cfg: &mut AppConfig) {
             cfg.max_tool_turns = num;
         }
     }
+    if let Ok(val) = std::env::var("FEMTOBOT_MAX_REPEATED_TOOL_CALLS") {
+        if let Ok(num) = val.parse::<usize>() {
+            cfg.max_repeated_tool_calls = num;
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_STREAM") {
+        if let Some(flag) = parse_bool(&val) {
+            cfg.stream_completions = flag;
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_TOOL_FOOTER") {
+        if let Some(flag) = parse_bool(&val) {
+            cfg.tool_footer = flag;
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_SYSTEM_PROMPT_FILE") {
+        cfg.system_prompt_file = PathBuf::from(val.trim());
+    }
     if let Ok(val) = std::env::var("FEMTOBOT_MEMORY_ENABLED") {
         if let Some(flag) = parse_bool(&val) {
             cfg.memory_enabled = flag;
@@ -650,6 +2469,150 @@ fn apply_env_overrides(cfg: &mut AppConfig) {
             cfg.model_fallbacks = parsed;
         }
     }
+    if let Ok(val) = std::env::var("FEMTOBOT_ADAPTIVE_FALLBACK") {
+        cfg.adaptive_fallback = parse_bool(&val).unwrap_or(cfg.adaptive_fallback);
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_EXPENSE_CURRENCY") {
+        if !val.trim().is_empty() {
+            cfg.expense_default_currency = val.trim().to_uppercase();
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_SUMMARIZE_MODEL") {
+        if !val.trim().is_empty() {
+            cfg.summarize_model = val;
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_PLAN_CONFIRM") {
+        if let Some(flag) = parse_bool(&val) {
+            cfg.plan_confirm_enabled = flag;
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_AUTO_FOLLOWUPS") {
+        if let Some(flag) = parse_bool(&val) {
+            cfg.auto_followups = flag;
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_ESCALATION_SECONDARY") {
+        cfg.escalation_secondary = (!val.trim().is_empty()).then(|| val.trim().to_string());
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_TOOL_TIMEOUT_SECS") {
+        if let Ok(num) = val.parse::<u64>() {
+            cfg.tool_timeout_secs = num;
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_TOOL_OUTPUT_MAX_CHARS") {
+        if let Ok(num) = val.parse::<usize>() {
+            cfg.tool_output_max_chars = num;
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_TIMEZONE") {
+        cfg.timezone = val.trim().to_string();
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_STARTUP_NOTIFY") {
+        cfg.startup_notify = Some(val.trim().to_string()).filter(|t| t.contains(':'));
+    }
+    for (var, key) in [
+        ("FEMTOBOT_NETWORK_TIMEOUT_SECS", "timeout_secs"),
+        ("FEMTOBOT_NETWORK_RETRIES", "retries"),
+    ] {
+        if let Some(num) = std::env::var(var)
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+        {
+            let partial = serde_json::json!({ key: num });
+            for (_, policy) in cfg.network_policies_mut() {
+                *policy = policy.clone().overlay(&partial);
+            }
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_FEEDBACK_LOG") {
+        cfg.feedback_log = parse_bool(&val).unwrap_or(cfg.feedback_log);
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_SANDBOX_CHATS") {
+        cfg.sandbox_chats = parse_bool(&val).unwrap_or(cfg.sandbox_chats);
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_BACKUP_ENABLED") {
+        cfg.backup_enabled = parse_bool(&val).unwrap_or(cfg.backup_enabled);
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_BACKUP_PASSPHRASE") {
+        cfg.backup_passphrase = Some(val).filter(|p| !p.is_empty());
+    }
+    if let Some(remote) = cfg.remote.as_mut() {
+        if let Ok(val) = std::env::var("FEMTOBOT_REMOTE_ACCESS_KEY") {
+            remote.access_key = Some(val);
+        }
+        if let Ok(val) = std::env::var("FEMTOBOT_REMOTE_SECRET_KEY") {
+            remote.secret_key = Some(val);
+        }
+        if let Ok(val) = std::env::var("FEMTOBOT_REMOTE_PASSWORD") {
+            remote.password = Some(val);
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_SYNC_ENABLED") {
+        cfg.sync_enabled = parse_bool(&val).unwrap_or(cfg.sync_enabled);
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_MEMORY_LAYOUT") {
+        cfg.memory_layout = val.trim().to_ascii_lowercase();
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_CALENDAR_EXPORT") {
+        cfg.calendar_export = parse_bool(&val).unwrap_or(cfg.calendar_export);
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_ADMINS") {
+        cfg.admins = val
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_SANDBOX_ADMINS") {
+        tracing::warn!("FEMTOBOT_SANDBOX_ADMINS is deprecated; use FEMTOBOT_ADMINS");
+        let admins = val
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+        merge_admins(cfg, admins);
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_PROVIDER_MODE") {
+        cfg.provider_mode = val.trim().to_ascii_lowercase();
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_CASSETTE_DIR") {
+        cfg.cassette_dir = PathBuf::from(val);
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_COST_CONFIRM_USD") {
+        if let Ok(limit) = val.trim().parse::<f64>() {
+            cfg.cost_confirm_usd = limit.max(0.0);
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_BUDGET_DAILY_USD") {
+        if let Ok(limit) = val.trim().parse::<f64>() {
+            cfg.budget.daily_usd = limit.max(0.0);
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_SKILLS_ENABLED") {
+        cfg.skills_enabled = parse_bool(&val).unwrap_or(cfg.skills_enabled);
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_MEMORY_RESCORE_HOURS") {
+        if let Ok(hours) = val.trim().parse::<u64>() {
+            cfg.memory_rescore_interval_hours = hours;
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_ARCHIVE_AFTER_DAYS") {
+        if let Ok(days) = val.trim().parse::<u64>() {
+            cfg.archive_after_days = days;
+        }
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_INLINE_ENABLED") {
+        cfg.inline_enabled = parse_bool(&val).unwrap_or(cfg.inline_enabled);
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_ROUTING_ENABLED") {
+        cfg.routing_enabled = parse_bool(&val).unwrap_or(cfg.routing_enabled);
+    }
+    if let Ok(val) = std::env::var("FEMTOBOT_ROUTING_CHEAP_MODEL") {
+        cfg.routing_cheap_model = val;
+    }
 }
 
 fn get_str<'a>(value: &'a Value, path: &[&str]) -> Option<&'a str> {
@@ -691,6 +2654,35 @@ fn get_array(value: &Value, path: &[&str]) -> Option<Vec<String>> {
     Some(out)
 }
 
+fn parse_remote_config(kind: &str, value: &Value) -> Option<RemoteConfig> {
+    let kind = match kind.trim().to_ascii_lowercase().as_str() {
+        "s3" => RemoteKind::S3,
+        "webdav" => RemoteKind::WebDav,
+        other => {
+            tracing::warn!("unknown storage.remote.kind '{other}' (use s3 or webdav)");
+            return None;
+        }
+    };
+    let field = |name: &str| {
+        get_str(value, &["storage", "remote", name])
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    let endpoint = field("endpoint")?;
+    Some(RemoteConfig {
+        kind,
+        endpoint,
+        bucket: field("bucket").unwrap_or_default(),
+        region: field("region").unwrap_or_else(|| "us-east-1".to_string()),
+        prefix: field("prefix").unwrap_or_else(|| "femtobot".to_string()),
+        access_key: field("access_key"),
+        secret_key: field("secret_key"),
+        username: field("username"),
+        password: field("password"),
+    })
+}
+
 fn parse_bool(value: &str) -> Option<bool> {
     match value.trim().to_ascii_lowercase().as_str() {
         "1" | "true" | "yes" | "y" => Some(true),
@@ -723,3 +2715,139 @@ fn parse_model_route(raw: &str, default_provider: &ProviderKind) -> Option<Model
         model: trimmed.to_string(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::AppConfig;
+    use serde_json::json;
+
+    #[test]
+    fn recall_policy_layers_cron_and_chat_overrides() {
+        let mut cfg = AppConfig::defaults();
+        cfg.memory_recall = cfg.memory_recall.clone().overlay(&json!({"samples": 3}));
+        cfg.memory_recall_cron = json!({"enabled": false});
+        cfg.memory_recall_chats.insert(
+            "telegram:42".to_string(),
+            json!({"samples": 8, "min_similarity": 0.4, "exclude_collections": ["Health"], "boost_collections": ["work"]}),
+        );
+
+        let base = cfg.recall_policy("discord", "1", false);
+        assert_eq!((base.samples, base.enabled), (3, true));
+        assert!(!cfg.recall_policy("discord", "1", true).enabled);
+        let chat = cfg.recall_policy("telegram", "42", false);
+        assert_eq!(chat.samples, 8);
+        assert!((chat.min_similarity - 0.4).abs() < 1e-6);
+        assert!(!chat.admits(Some("health")) && chat.admits(Some("work")) && chat.admits(None));
+        assert!(chat.boosts(Some("work")) && !base.boosts(Some("work")));
+        assert_eq!(cfg.recall_max_samples(), 8);
+    }
+
+    #[test]
+    fn exec_profile_follows_chat_then_role_then_default() {
+        let mut cfg = AppConfig::defaults();
+        cfg.admins = vec!["1".to_string()];
+        cfg.exec_role_profiles
+            .insert("user".to_string(), "safe".to_string());
+        let custom = cfg.exec_profiles["yolo"]
+            .clone()
+            .overlay(&json!({"backend": "docker", "timeout_secs": 900}));
+        cfg.exec_profiles.insert("vm".to_string(), custom);
+
+        assert_eq!(cfg.exec_profile_for(None, "1"), "normal");
+        assert_eq!(cfg.exec_profile_for(None, "2"), "safe");
+        assert_eq!(cfg.exec_profile_for(Some("vm"), "2"), "vm");
+        assert_eq!(cfg.exec_profile_for(Some("missing"), "1"), "normal");
+        assert_eq!(cfg.exec_profiles["vm"].guard, "off");
+        assert!(cfg.exec_longest_secs() >= 900);
+    }
+
+    #[test]
+    fn persona_follows_chat_then_listing_then_default() {
+        let mut cfg = AppConfig::defaults();
+        super::apply_femtobot_config(
+            &mut cfg,
+            &json!({"agents": {
+                "defaults": {"persona": "home"},
+                "personas": {
+                    "home": {"prompt": "Be warm."},
+                    "work": {
+                        "prompt": "Be terse.",
+                        "model": "openai/gpt-4o-mini",
+                        "deny_tools": ["web_search", "web_fetch"],
+                        "chats": ["telegram:-100"]
+                    }
+                }
+            }}),
+        );
+
+        let (name, work) = cfg.persona_for(None, "telegram:-100").unwrap();
+        assert_eq!(name, "work");
+        assert_eq!(work.model.as_deref(), Some("openai/gpt-4o-mini"));
+        assert!(!work.allows_tool("web_search"));
+        assert!(work.allows_tool("exec"));
+        assert_eq!(
+            cfg.persona_for(Some("home"), "telegram:-100").unwrap().0,
+            "home"
+        );
+        assert_eq!(
+            cfg.persona_for(Some("missing"), "telegram:1").unwrap().0,
+            "home"
+        );
+        cfg.persona.clear();
+        assert!(cfg.persona_for(None, "telegram:1").is_none());
+    }
+
+    #[test]
+    fn network_section_applies_to_all_areas_then_per_area() {
+        let mut cfg = AppConfig::defaults();
+        super::apply_femtobot_config(
+            &mut cfg,
+            &json!({"network": {"timeout_secs": 90, "retries": 0, "telegram": {"retries": 5}}}),
+        );
+        assert_eq!(cfg.network_provider.timeout_secs, 90);
+        assert_eq!(cfg.network_web.retries, 0);
+        assert_eq!(cfg.network_telegram.retries, 5);
+        assert_eq!(cfg.network_telegram.connect_timeout_secs, 5);
+        let backoff = cfg.network_telegram.backoff(2);
+        assert_eq!(backoff, std::time::Duration::from_secs(4));
+    }
+
+    #[test]
+    fn vision_support_uses_list_or_model_family() {
+        let mut cfg = AppConfig::defaults();
+        assert!(cfg.supports_vision("openai/gpt-4o-mini"));
+        assert!(cfg.supports_vision("anthropic/claude-opus-4-5"));
+        assert!(!cfg.supports_vision("deepseek/deepseek-chat"));
+        cfg.vision_models = vec!["deepseek/deepseek-chat".to_string()];
+        assert!(cfg.supports_vision("deepseek/deepseek-chat"));
+        assert!(!cfg.supports_vision("openai/gpt-4o-mini"));
+    }
+
+    #[test]
+    fn secret_values_cover_channel_tokens_and_named_secrets() {
+        let mut cfg = AppConfig::defaults();
+        cfg.mistral_api_key = "mistral-key-123".to_string();
+        cfg.matrix_access_token = "syt_matrix_token".to_string();
+        cfg.mattermost_token = "mattermost-token".to_string();
+        cfg.xmpp_password = "xmpp-password".to_string();
+        cfg.irc_password = Some("irc-password".to_string());
+        cfg.websocket_token = "websocket-token".to_string();
+        cfg.secrets
+            .insert("github".to_string(), "ghp_named_secret".to_string());
+        cfg.secrets.insert("pin".to_string(), "1234".to_string());
+
+        let values = cfg.secret_values();
+        for secret in [
+            "mistral-key-123",
+            "syt_matrix_token",
+            "mattermost-token",
+            "xmpp-password",
+            "irc-password",
+            "websocket-token",
+            "ghp_named_secret",
+        ] {
+            assert!(values.iter().any(|v| v == secret), "{secret} missing");
+        }
+        assert!(!values.iter().any(|v| v == "1234"));
+    }
+}
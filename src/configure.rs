@@ -471,7 +471,17 @@ fn configure_transcription(root: &mut Value) -> Result<bool> {
     Ok(root != &before)
 }
 
-fn load_config_value(path: &PathBuf) -> Result<Value> {
+/// Write `new_value` at `path` in the config file, keeping everything else,
+/// and return the file's location.
+pub fn update_config_file(path: &[&str], new_value: Value) -> Result<PathBuf> {
+    let file = crate::config::config_path();
+    let mut root = load_config_value(&file)?;
+    set_path(&mut root, path, new_value)?;
+    save_config_value(&file, &root)?;
+    Ok(file)
+}
+
+pub(crate) fn load_config_value(path: &PathBuf) -> Result<Value> {
     if path.exists() {
         let content = fs::read_to_string(path)?;
         let parsed: Value = serde_json::from_str(&content)
@@ -488,7 +498,7 @@ fn load_config_value(path: &PathBuf) -> Result<Value> {
     }
 }
 
-fn save_config_value(path: &PathBuf, value: &Value) -> Result<()> {
+pub(crate) fn save_config_value(path: &PathBuf, value: &Value) -> Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
@@ -0,0 +1,164 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Contact {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(rename = "chatId", default, skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl Contact {
+    pub fn summary(&self) -> String {
+        let mut parts = vec![self.name.clone()];
+        if let (Some(channel), Some(chat_id)) = (&self.channel, &self.chat_id) {
+            parts.push(format!("{channel}:{chat_id}"));
+        }
+        if let Some(email) = &self.email {
+            parts.push(format!("email: {email}"));
+        }
+        if let Some(phone) = &self.phone {
+            parts.push(format!("phone: {phone}"));
+        }
+        if let Some(notes) = &self.notes {
+            parts.push(format!("notes: {notes}"));
+        }
+        parts.join(" | ")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ContactStoreData {
+    version: i32,
+    contacts: Vec<Contact>,
+}
+
+/// JSON-backed contact book stored at `<workspace>/contacts.json`.
+pub struct ContactBook {
+    path: PathBuf,
+    pub contacts: Vec<Contact>,
+}
+
+impl ContactBook {
+    pub fn new(workspace_dir: PathBuf) -> Self {
+        Self {
+            path: workspace_dir.join("contacts.json"),
+            contacts: Vec::new(),
+        }
+    }
+
+    pub fn load(&mut self) -> Result<()> {
+        if self.path.exists() {
+            let content = fs::read_to_string(&self.path)?;
+            let data: ContactStoreData = serde_json::from_str(&content)?;
+            self.contacts = data.contacts;
+        } else {
+            self.contacts = Vec::new();
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let data = ContactStoreData {
+            version: 1,
+            contacts: self.contacts.clone(),
+        };
+        let content = serde_json::to_string_pretty(&data)?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    /// Insert a new contact or merge non-empty fields into an existing one
+    /// with the same (case-insensitive) name. Returns true when created.
+    pub fn upsert(&mut self, contact: Contact) -> Result<bool> {
+        let name = contact.name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("contact name cannot be empty"));
+        }
+        let key = name.to_lowercase();
+        let created = match self
+            .contacts
+            .iter_mut()
+            .find(|c| c.name.to_lowercase() == key)
+        {
+            Some(existing) => {
+                merge_field(&mut existing.channel, contact.channel);
+                merge_field(&mut existing.chat_id, contact.chat_id);
+                merge_field(&mut existing.email, contact.email);
+                merge_field(&mut existing.phone, contact.phone);
+                merge_field(&mut existing.notes, contact.notes);
+                false
+            }
+            None => {
+                self.contacts.push(Contact {
+                    name: name.to_string(),
+                    ..contact
+                });
+                true
+            }
+        };
+        self.contacts.sort_by_key(|c| c.name.to_lowercase());
+        self.save()?;
+        Ok(created)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        let key = name.trim().to_lowercase();
+        let len_before = self.contacts.len();
+        self.contacts.retain(|c| c.name.to_lowercase() != key);
+        let removed = self.contacts.len() < len_before;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Find contacts whose name matches `query` exactly, falling back to a
+    /// case-insensitive substring match across name, email and notes.
+    pub fn find(&self, query: &str) -> Vec<Contact> {
+        let key = query.trim().to_lowercase();
+        if key.is_empty() {
+            return Vec::new();
+        }
+        if let Some(exact) = self.contacts.iter().find(|c| c.name.to_lowercase() == key) {
+            return vec![exact.clone()];
+        }
+        self.contacts
+            .iter()
+            .filter(|c| {
+                c.name.to_lowercase().contains(&key)
+                    || c.email
+                        .as_deref()
+                        .is_some_and(|e| e.to_lowercase().contains(&key))
+                    || c.notes
+                        .as_deref()
+                        .is_some_and(|n| n.to_lowercase().contains(&key))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+fn merge_field(target: &mut Option<String>, incoming: Option<String>) {
+    if let Some(value) = incoming {
+        let value = value.trim();
+        if value.is_empty() {
+            *target = None;
+        } else {
+            *target = Some(value.to_string());
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::cron::types::CronJob;
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Upper bound on events per job so an every-minute schedule does not turn
+/// the feed into tens of thousands of entries.
+const MAX_OCCURRENCES_PER_JOB: usize = 100;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Render enabled jobs as an iCalendar feed: one `VEVENT` per upcoming
+/// occurrence between `now_ms` and `horizon_days` ahead. One-shot reminders
+/// appear once; recurring jobs are expanded into individual occurrences.
+pub fn render(jobs: &[CronJob], now_ms: i64, horizon_days: u64) -> String {
+    let until_ms = now_ms + horizon_days as i64 * DAY_MS;
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//femtobot//scheduled jobs//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:femtobot".to_string(),
+    ];
+    for job in jobs.iter().filter(|j| j.enabled) {
+        let kind = if job.payload.kind == "reminder" {
+            "Reminder"
+        } else {
+            "Agent turn"
+        };
+        let mut description = job.payload.message.clone();
+        if let (Some(channel), Some(to)) = (&job.payload.channel, &job.payload.to) {
+            description.push_str(&format!("\n\nDelivered to {channel}:{to}"));
+        }
+        description.push_str(&format!("\nJob id: {}", job.id));
+        for start_ms in occurrences(job, now_ms, until_ms) {
+            lines.push("BEGIN:VEVENT".to_string());
+            lines.push(format!("UID:{}-{start_ms}@femtobot", job.id));
+            lines.push(format!("DTSTAMP:{}", format_utc(job.updated_at_ms)));
+            lines.push(format!("DTSTART:{}", format_utc(start_ms)));
+            lines.push("DURATION:PT15M".to_string());
+            lines.push(format!(
+                "SUMMARY:{}",
+                escape(&format!("{kind}: {}", job.name))
+            ));
+            lines.push(format!("DESCRIPTION:{}", escape(&description)));
+            lines.push(format!("CATEGORIES:{}", escape(&job.payload.kind)));
+            lines.push("END:VEVENT".to_string());
+        }
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// Location of the exported feed inside the workspace.
+pub fn calendar_path(workspace: &Path) -> PathBuf {
+    workspace.join("calendar").join("femtobot.ics")
+}
+
+pub fn write(path: &Path, ics: &str) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, ics)?;
+    Ok(())
+}
+
+fn occurrences(job: &CronJob, now_ms: i64, until_ms: i64) -> Vec<i64> {
+    let in_range = |ms: &i64| *ms >= now_ms && *ms <= until_ms;
+    match job.schedule.kind.as_str() {
+        "at" => job.schedule.at_ms.filter(in_range).into_iter().collect(),
+        "every" => {
+            let Some(every) = job.schedule.every_ms.filter(|ms| *ms > 0) else {
+                return Vec::new();
+            };
+            let mut next = job.state.next_run_at_ms.unwrap_or(now_ms + every);
+            if next < now_ms {
+                next += (now_ms - next + every - 1) / every * every;
+            }
+            std::iter::successors(Some(next), |ms| Some(ms + every))
+                .take_while(|ms| *ms <= until_ms)
+                .take(MAX_OCCURRENCES_PER_JOB)
+                .collect()
+        }
+        "cron" => crate::cron::cron_runs(&job.schedule, now_ms, MAX_OCCURRENCES_PER_JOB)
+            .into_iter()
+            .take_while(|ms| *ms <= until_ms)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn format_utc(ms: i64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(ms)
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// Escape a TEXT value (RFC 5545 §3.3.11).
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold content lines longer than 75 octets without splitting a character.
+fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / 70 * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fold, render};
+    use crate::cron::types::{CronJob, CronPayload, CronSchedule, CronState};
+
+    fn job(id: &str, kind: &str, at_ms: Option<i64>, every_ms: Option<i64>) -> CronJob {
+        CronJob {
+            id: id.to_string(),
+            name: format!("job {id}"),
+            enabled: true,
+            schedule: CronSchedule {
+                kind: kind.to_string(),
+                at_ms,
+                every_ms,
+                expr: None,
+                tz: None,
+            },
+            payload: CronPayload {
+                kind: "reminder".to_string(),
+                message: "Call mom, then; relax".to_string(),
+                deliver: true,
+                channel: None,
+                to: None,
+                model: None,
+                origin: None,
+            },
+            state: CronState::default(),
+            created_at_ms: 0,
+            updated_at_ms: 0,
+            delete_after_run: false,
+        }
+    }
+
+    #[test]
+    fn expands_occurrences_within_horizon() {
+        let now = 1_700_000_000_000;
+        let hour = 60 * 60 * 1000;
+        let jobs = vec![
+            job("once", "at", Some(now + hour), None),
+            job("past", "at", Some(now - hour), None),
+            job("daily", "every", None, Some(24 * hour)),
+        ];
+        let ics = render(&jobs, now, 3);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1 + 3);
+        assert!(!ics.contains("UID:past-"));
+        assert!(ics.contains("DESCRIPTION:Call mom\\, then\\; relax\\nJob id: once"));
+        assert!(fold(&"x".repeat(160))
+            .split("\r\n")
+            .all(|line| line.len() <= 75));
+    }
+}
@@ -1,22 +1,53 @@
+pub mod ical;
+pub mod natural;
 pub mod store;
 pub mod types;
 
-use crate::bus::{InboundMessage, MessageBus};
+use crate::bus::{InboundMessage, MessageBus, MessageMeta, OutboundMessage};
 use crate::config::AppConfig;
-use anyhow::Result;
+use crate::notify::Notifier;
+use crate::variables;
+use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use cron::Schedule;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::{Mutex, Notify};
 use tokio::time::{self, Duration};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use types::{CronJob, CronSchedule};
 
+/// Appended to delivered reminders.
+pub const SNOOZE_HINT: &str =
+    "(Reply \"snooze 10m\", \"snooze 1h\" or \"snooze tomorrow\" to be reminded again, or \"repeat every day at 8am\" to make it recurring.)";
+/// How long after delivery a reminder can still be snoozed or repeated.
+const FOLLOW_UP_WINDOW_MS: i64 = 12 * 60 * 60 * 1000;
+
+/// A reminder recently delivered to a chat, kept so a "snooze" or "repeat"
+/// reply can reschedule it.
+#[derive(Clone, Debug)]
+pub struct FiredReminder {
+    pub name: String,
+    pub message: String,
+    pub fired_at_ms: i64,
+}
+
 struct CronInner {
     store: Mutex<store::CronStore>,
     bus: MessageBus,
     notify: Notify,
+    /// Where the iCal feed is kept up to date, when calendar export is on.
+    calendar_path: Option<PathBuf>,
+    calendar_horizon_days: u64,
+    /// Zone clock times in natural-language schedules are read in.
+    timezone: Tz,
+    /// Last reminder delivered per `channel:chat_id`.
+    fired: std::sync::Mutex<HashMap<String, FiredReminder>>,
+    /// Where session variables for delivered messages are read from.
+    data_dir: PathBuf,
 }
 
 #[derive(Clone)]
@@ -28,6 +59,9 @@ pub struct CronStatus {
     pub jobs: usize,
     pub enabled_jobs: usize,
     pub next_wake_at_ms: Option<i64>,
+    /// Enabled jobs whose run is more than a minute late, a sign the
+    /// scheduler has stalled.
+    pub overdue_jobs: usize,
 }
 
 impl CronService {
@@ -38,11 +72,20 @@ impl CronService {
                 store: Mutex::new(store),
                 bus,
                 notify: Notify::new(),
+                calendar_path: cfg
+                    .calendar_export
+                    .then(|| ical::calendar_path(&cfg.workspace_dir)),
+                calendar_horizon_days: cfg.calendar_horizon_days,
+                timezone: crate::clock::resolve_timezone(&cfg.timezone).unwrap_or(Tz::UTC),
+                fired: std::sync::Mutex::new(HashMap::new()),
+                data_dir: cfg.data_dir.clone(),
             }),
         }
     }
 
-    pub async fn start(&self) {
+    /// Run due jobs in the background. Every message a job delivers goes
+    /// through `notifier`, so the chat's quiet hours and digest apply.
+    pub async fn start(&self, notifier: Notifier) {
         // Load initial state
         {
             let mut store = self.inner.store.lock().await;
@@ -68,6 +111,7 @@ impl CronService {
             // Poll frequently so tool/CLI mutations are picked up quickly even
             // when they happen in another CronService instance.
             const MAX_SLEEP: Duration = Duration::from_secs(1);
+            let mut last_calendar = String::new();
             loop {
                 // Reload persisted store so tool/CLI changes from other CronService
                 // instances are picked up by the running scheduler.
@@ -76,6 +120,19 @@ impl CronService {
                     if let Err(e) = store.load() {
                         error!("Failed to reload cron jobs: {}", e);
                     }
+                    if let Some(path) = &loop_service.inner.calendar_path {
+                        let ics = ical::render(
+                            &store.jobs,
+                            Utc::now().timestamp_millis(),
+                            loop_service.inner.calendar_horizon_days,
+                        );
+                        if ics != last_calendar {
+                            match ical::write(path, &ics) {
+                                Ok(()) => last_calendar = ics,
+                                Err(e) => warn!("Failed to write calendar feed: {}", e),
+                            }
+                        }
+                    }
                 }
 
                 // 1. Calculate time to next job
@@ -119,7 +176,7 @@ impl CronService {
                     _ = time::sleep(sleep_duration) => {
                          // Time to run jobs?
                          if next_wake_ms.is_some() {
-                             loop_service.process_due_jobs().await;
+                             loop_service.process_due_jobs(&notifier).await;
                          }
                     }
                 }
@@ -127,7 +184,7 @@ impl CronService {
         });
     }
 
-    async fn process_due_jobs(&self) {
+    async fn process_due_jobs(&self, notifier: &Notifier) {
         let mut store = self.inner.store.lock().await;
         // Reload right before execution to avoid running stale jobs and
         // overwriting newer tool/CLI changes with in-memory state.
@@ -138,6 +195,7 @@ impl CronService {
         let now = Utc::now().timestamp_millis();
 
         let mut jobs_to_run = Vec::new();
+        let mut finished = Vec::new();
 
         for (i, job) in store.jobs.iter().enumerate() {
             if job.enabled {
@@ -153,24 +211,57 @@ impl CronService {
             let job = &mut store.jobs[idx];
             info!("Executing cron job: {} ({})", job.name, job.id);
 
-            // Send message to bus
-            let msg = InboundMessage {
-                channel: job
-                    .payload
-                    .channel
-                    .clone()
-                    .unwrap_or_else(|| "cron".to_string()),
-                chat_id: job
-                    .payload
-                    .to
-                    .clone()
-                    .unwrap_or_else(|| "direct".to_string()),
-                sender_id: "cron".to_string(),
-                content: job.payload.message.clone(),
-                // TODO: Propagate job.payload.model when InboundMessage supports it
-                // For now, we just ensure the field exists in CronPayload
-            };
-            self.inner.bus.publish_inbound(msg).await;
+            if job.payload.deliver {
+                // Reminder-style jobs deliver their text directly instead of
+                // triggering an agent turn.
+                if let (Some(channel), Some(to)) = (&job.payload.channel, &job.payload.to) {
+                    let vars = variables::resolve(
+                        &self.inner.data_dir,
+                        &format!("{channel}:{to}"),
+                        Some(self.inner.timezone),
+                    );
+                    let mut content = variables::render(&job.payload.message, &vars);
+                    if job.payload.kind == "reminder" {
+                        self.note_fired(channel, to, &job.name, &job.payload.message);
+                        content = format!("{content}\n\n{SNOOZE_HINT}");
+                    }
+                    let msg = OutboundMessage {
+                        channel: channel.clone(),
+                        chat_id: to.clone(),
+                        content,
+                        ..Default::default()
+                    };
+                    if notifier.deliver(msg, false).await {
+                        info!("Cron job {} queued for quiet hours or digest", job.id);
+                    }
+                } else {
+                    error!("Cron job {} has no delivery target", job.id);
+                }
+            } else {
+                // Send message to bus
+                let msg = InboundMessage {
+                    channel: job
+                        .payload
+                        .channel
+                        .clone()
+                        .unwrap_or_else(|| "cron".to_string()),
+                    chat_id: job
+                        .payload
+                        .to
+                        .clone()
+                        .unwrap_or_else(|| "direct".to_string()),
+                    sender_id: "cron".to_string(),
+                    sender_name: None,
+                    is_group: false,
+                    content: job.payload.message.clone(),
+                    meta: MessageMeta {
+                        model: job.payload.model.clone(),
+                        correlation_id: Some(format!("cron:{}", job.id)),
+                        ..Default::default()
+                    },
+                };
+                self.inner.bus.publish_inbound(msg).await;
+            }
 
             // Update state
             job.state.last_run_at_ms = Some(now);
@@ -179,18 +270,20 @@ impl CronService {
 
             // Handle one-off vs recurring
             if job.schedule.kind == "at" {
+                job.enabled = false;
+                job.state.next_run_at_ms = None;
                 if job.delete_after_run {
-                    job.enabled = false;
-                    job.state.next_run_at_ms = None;
-                } else {
-                    job.enabled = false;
-                    job.state.next_run_at_ms = None;
+                    finished.push(job.id.clone());
                 }
             } else {
                 job.state.next_run_at_ms = compute_next_run(&job.schedule, now);
             }
         }
 
+        if !finished.is_empty() {
+            store.jobs.retain(|j| !finished.contains(&j.id));
+        }
+
         // Save state
         if let Err(e) = store.save() {
             error!("Failed to save cron store: {}", e);
@@ -205,33 +298,25 @@ impl CronService {
         message: String,
         channel: Option<String>,
         to: Option<String>,
-    ) -> Result<()> {
+    ) -> Result<CronJob> {
+        let sched = self.parse_schedule(&schedule)?;
+        self.add_turn_job(name, sched, message, channel, to).await
+    }
+
+    /// Persist a job that runs an agent turn with `message` on `sched`.
+    pub async fn add_turn_job(
+        &self,
+        name: String,
+        sched: CronSchedule,
+        message: String,
+        channel: Option<String>,
+        to: Option<String>,
+    ) -> Result<CronJob> {
         let mut store = self.inner.store.lock().await;
         store.load()?;
         let now = Utc::now().timestamp_millis();
-
-        // Determine schedule type
-        let (kind, every_ms, expr) = if schedule.starts_with("@") || schedule.contains(" *") {
-            ("cron", None, Some(schedule))
-        } else if let Ok(secs) = schedule.parse::<u64>() {
-            ("every", Some((secs * 1000) as i64), None)
-        } else {
-            ("every", None, None) // Default/Error fallthrough
-        };
-
-        if kind == "every" && every_ms.is_none() {
-            return Err(anyhow::anyhow!("Invalid schedule format"));
-        }
-
-        let sched = CronSchedule {
-            kind: kind.to_string(),
-            at_ms: None,
-            every_ms,
-            expr,
-            tz: None,
-        };
-
         let next = compute_next_run(&sched, now);
+        let one_shot = sched.kind == "at";
 
         let job = CronJob {
             id: uuid::Uuid::new_v4().to_string()[..8].to_string(),
@@ -245,6 +330,7 @@ impl CronService {
                 channel,
                 to,
                 model: None, // Default
+                origin: None,
             },
             state: types::CronState {
                 next_run_at_ms: next,
@@ -252,7 +338,7 @@ impl CronService {
             },
             created_at_ms: now,
             updated_at_ms: now,
-            delete_after_run: false,
+            delete_after_run: one_shot,
         };
 
         store.add(job.clone())?;
@@ -261,15 +347,309 @@ impl CronService {
         // Notify the loop to pick up the new job immediately
         self.inner.notify.notify_one();
 
+        Ok(job)
+    }
+
+    /// Turn a schedule argument into a [`CronSchedule`]: a cron expression
+    /// (`@daily`, `0 0 9 * * *`), an interval in seconds, or an English
+    /// phrase understood by [`natural::parse`].
+    pub fn parse_schedule(&self, schedule: &str) -> Result<CronSchedule> {
+        let schedule = schedule.trim();
+        if schedule.starts_with('@') || schedule.contains(" *") {
+            return Ok(CronSchedule {
+                kind: "cron".to_string(),
+                at_ms: None,
+                every_ms: None,
+                expr: Some(schedule.to_string()),
+                tz: None,
+            });
+        }
+        if let Ok(secs) = schedule.parse::<u64>() {
+            return Ok(CronSchedule {
+                kind: "every".to_string(),
+                at_ms: None,
+                every_ms: Some((secs * 1000) as i64),
+                expr: None,
+                tz: None,
+            });
+        }
+        natural::parse(schedule, Utc::now(), self.inner.timezone)
+    }
+
+    /// Persist a one-shot reminder that is delivered verbatim to `channel`/`to`
+    /// at `at_ms` and then removed from the store.
+    pub async fn add_reminder(
+        &self,
+        name: String,
+        at_ms: i64,
+        message: String,
+        channel: String,
+        to: String,
+    ) -> Result<String> {
+        let payload = types::CronPayload {
+            kind: "reminder".to_string(),
+            message,
+            deliver: true,
+            channel: Some(channel),
+            to: Some(to),
+            model: None,
+            origin: None,
+        };
+        let id = self.add_one_shot(name, at_ms, payload).await?;
+        info!("Added reminder: {}", id);
+        Ok(id)
+    }
+
+    /// Persist a reminder delivered verbatim on a recurring `schedule`.
+    pub async fn add_recurring_reminder(
+        &self,
+        name: String,
+        schedule: CronSchedule,
+        message: String,
+        channel: String,
+        to: String,
+    ) -> Result<CronJob> {
+        let job = self
+            .add_delivery("reminder", name, schedule, message, channel, to)
+            .await?;
+        info!("Added recurring reminder: {}", job.id);
+        Ok(job)
+    }
+
+    /// Persist a pre-written message delivered to `channel`/`to` on
+    /// `schedule`, as is: no agent turn and no snooze hint when it fires.
+    pub async fn add_message(
+        &self,
+        name: String,
+        schedule: CronSchedule,
+        message: String,
+        channel: String,
+        to: String,
+    ) -> Result<CronJob> {
+        let job = self
+            .add_delivery("message", name, schedule, message, channel, to)
+            .await?;
+        info!("Added scheduled message: {}", job.id);
+        Ok(job)
+    }
+
+    /// Persist `message` for verbatim delivery to `channel`/`to` at `at_ms`
+    /// unless the chat `origin` cancels it first.
+    pub async fn add_pending_send(
+        &self,
+        name: String,
+        at_ms: i64,
+        message: String,
+        channel: String,
+        to: String,
+        origin: String,
+    ) -> Result<String> {
+        let payload = types::CronPayload {
+            kind: "pending".to_string(),
+            message,
+            deliver: true,
+            channel: Some(channel),
+            to: Some(to),
+            model: None,
+            origin: Some(origin),
+        };
+        let id = self.add_one_shot(name, at_ms, payload).await?;
+        info!("Added pending send: {}", id);
+        Ok(id)
+    }
+
+    /// Pending sends queued by the chat `origin` that have not gone out yet,
+    /// soonest first.
+    pub async fn pending_sends(&self, origin: &str) -> Result<Vec<CronJob>> {
+        let mut jobs: Vec<CronJob> = self
+            .list_jobs()
+            .await?
+            .into_iter()
+            .filter(|j| {
+                j.enabled
+                    && j.payload.kind == "pending"
+                    && j.payload.origin.as_deref() == Some(origin)
+            })
+            .collect();
+        jobs.sort_by_key(|j| j.state.next_run_at_ms);
+        Ok(jobs)
+    }
+
+    async fn add_delivery(
+        &self,
+        kind: &str,
+        name: String,
+        schedule: CronSchedule,
+        message: String,
+        channel: String,
+        to: String,
+    ) -> Result<CronJob> {
+        let mut store = self.inner.store.lock().await;
+        store.load()?;
+        let now = Utc::now().timestamp_millis();
+        let next_run_at_ms = compute_next_run(&schedule, now);
+        if next_run_at_ms.is_none() {
+            return Err(anyhow!("That time has already passed."));
+        }
+        let job = CronJob {
+            id: uuid::Uuid::new_v4().to_string()[..8].to_string(),
+            name,
+            enabled: true,
+            state: types::CronState {
+                next_run_at_ms,
+                ..Default::default()
+            },
+            delete_after_run: schedule.kind == "at",
+            schedule,
+            payload: types::CronPayload {
+                kind: kind.to_string(),
+                message,
+                deliver: true,
+                channel: Some(channel),
+                to: Some(to),
+                model: None,
+                origin: None,
+            },
+            created_at_ms: now,
+            updated_at_ms: now,
+        };
+        store.add(job.clone())?;
+        self.inner.notify.notify_one();
+        Ok(job)
+    }
+
+    /// Queue `message` for immediate verbatim delivery to each
+    /// `(channel, chat_id)`, one one-shot job per chat, so a running instance
+    /// delivers it even when the broadcast comes from the CLI.
+    pub async fn add_broadcast(&self, message: &str, targets: &[(String, String)]) -> Result<()> {
+        // "at" schedules only fire for times still ahead of the scheduler.
+        let at_ms = Utc::now().timestamp_millis() + 1_000;
+        for (channel, to) in targets {
+            let payload = types::CronPayload {
+                kind: "broadcast".to_string(),
+                message: message.to_string(),
+                deliver: true,
+                channel: Some(channel.clone()),
+                to: Some(to.clone()),
+                model: None,
+                origin: None,
+            };
+            self.add_one_shot(format!("broadcast to {channel}:{to}"), at_ms, payload)
+                .await?;
+        }
+        info!("Queued broadcast to {} chats", targets.len());
         Ok(())
     }
 
+    /// Remember that a reminder was just delivered to `channel:chat_id`.
+    pub fn note_fired(&self, channel: &str, chat_id: &str, name: &str, message: &str) {
+        if let Ok(mut fired) = self.inner.fired.lock() {
+            fired.insert(
+                format!("{channel}:{chat_id}"),
+                FiredReminder {
+                    name: name.to_string(),
+                    message: message.to_string(),
+                    fired_at_ms: Utc::now().timestamp_millis(),
+                },
+            );
+        }
+    }
+
+    /// The reminder most recently delivered to `channel:chat_id`, if it is
+    /// still recent enough to snooze or repeat.
+    pub fn last_fired(&self, channel: &str, chat_id: &str) -> Option<FiredReminder> {
+        let fired = self.inner.fired.lock().ok()?;
+        fired
+            .get(&format!("{channel}:{chat_id}"))
+            .filter(|r| Utc::now().timestamp_millis() - r.fired_at_ms < FOLLOW_UP_WINDOW_MS)
+            .cloned()
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.inner.timezone
+    }
+
+    /// Persist a one-shot agent turn that runs `message` as a prompt at
+    /// `at_ms` in the context of `channel`/`to`, then removes itself.
+    pub async fn add_follow_up(
+        &self,
+        name: String,
+        at_ms: i64,
+        message: String,
+        channel: String,
+        to: String,
+    ) -> Result<String> {
+        let payload = types::CronPayload {
+            kind: "agent_turn".to_string(),
+            message,
+            deliver: false,
+            channel: Some(channel),
+            to: Some(to),
+            model: None,
+            origin: None,
+        };
+        let id = self.add_one_shot(name, at_ms, payload).await?;
+        info!("Added follow-up: {}", id);
+        Ok(id)
+    }
+
+    async fn add_one_shot(
+        &self,
+        name: String,
+        at_ms: i64,
+        payload: types::CronPayload,
+    ) -> Result<String> {
+        let mut store = self.inner.store.lock().await;
+        store.load()?;
+        let now = Utc::now().timestamp_millis();
+
+        let sched = CronSchedule {
+            kind: "at".to_string(),
+            at_ms: Some(at_ms),
+            every_ms: None,
+            expr: None,
+            tz: None,
+        };
+        let next = compute_next_run(&sched, now);
+
+        let job = CronJob {
+            id: uuid::Uuid::new_v4().to_string()[..8].to_string(),
+            name,
+            enabled: true,
+            schedule: sched,
+            payload,
+            state: types::CronState {
+                next_run_at_ms: next,
+                ..Default::default()
+            },
+            created_at_ms: now,
+            updated_at_ms: now,
+            delete_after_run: true,
+        };
+
+        let id = job.id.clone();
+        store.add(job)?;
+        self.inner.notify.notify_one();
+
+        Ok(id)
+    }
+
     pub async fn list_jobs(&self) -> Result<Vec<CronJob>> {
         let mut store = self.inner.store.lock().await;
         store.load()?;
         Ok(store.jobs.clone())
     }
 
+    /// iCalendar feed of upcoming occurrences of all enabled jobs.
+    pub async fn calendar(&self) -> Result<String> {
+        let jobs = self.list_jobs().await?;
+        Ok(ical::render(
+            &jobs,
+            Utc::now().timestamp_millis(),
+            self.inner.calendar_horizon_days,
+        ))
+    }
+
     pub async fn remove_job(&self, id: &str) -> Result<bool> {
         let mut store = self.inner.store.lock().await;
         store.load()?;
@@ -290,10 +670,16 @@ impl CronService {
             .filter(|j| j.enabled && j.state.next_run_at_ms.is_some())
             .map(|j| j.state.next_run_at_ms.unwrap_or_default())
             .min();
+        let late_before = Utc::now().timestamp_millis() - 60_000;
         Ok(CronStatus {
             jobs: store.jobs.len(),
             enabled_jobs: store.jobs.iter().filter(|j| j.enabled).count(),
             next_wake_at_ms,
+            overdue_jobs: store
+                .jobs
+                .iter()
+                .filter(|j| j.enabled && j.state.next_run_at_ms.is_some_and(|at| at < late_before))
+                .count(),
         })
     }
 }
@@ -315,19 +701,34 @@ fn compute_next_run(schedule: &CronSchedule, now_ms: i64) -> Option<i64> {
                 None
             }
         }
-        "cron" => {
-            if let Some(expr) = &schedule.expr {
-                if let Ok(schedule) = Schedule::from_str(expr) {
-                    let dt = DateTime::<Utc>::from(
-                        std::time::UNIX_EPOCH + std::time::Duration::from_millis(now_ms as u64),
-                    );
-                    if let Some(next) = schedule.after(&dt).next() {
-                        return Some(next.timestamp_millis());
-                    }
-                }
-            }
-            None
-        }
+        "cron" => cron_runs(schedule, now_ms, 1).first().copied(),
         _ => None,
     }
 }
+
+/// Up to `limit` runs of a cron schedule after `after_ms`, evaluated in the
+/// schedule's timezone (UTC when unset).
+pub(crate) fn cron_runs(schedule: &CronSchedule, after_ms: i64, limit: usize) -> Vec<i64> {
+    let Some(parsed) = schedule
+        .expr
+        .as_deref()
+        .and_then(|expr| Schedule::from_str(expr).ok())
+    else {
+        return Vec::new();
+    };
+    let Some(after) = DateTime::<Utc>::from_timestamp_millis(after_ms) else {
+        return Vec::new();
+    };
+    match schedule.tz.as_deref().and_then(|tz| tz.parse::<Tz>().ok()) {
+        Some(tz) => parsed
+            .after(&after.with_timezone(&tz))
+            .take(limit)
+            .map(|dt| dt.timestamp_millis())
+            .collect(),
+        None => parsed
+            .after(&after)
+            .take(limit)
+            .map(|dt| dt.timestamp_millis())
+            .collect(),
+    }
+}
@@ -0,0 +1,434 @@
+//! Deterministic English schedule phrases → [`CronSchedule`], so scheduling
+//! does not depend on the model writing valid cron expressions.
+//!
+//! Understood forms (case-insensitive, times like `8am`, `18:30`, `noon`):
+//! - one-shot: `in 2 hours`, `in half an hour`, `tomorrow at 9am`,
+//!   `at 5pm`, `next monday at 10`, `2025-06-01 at 14:00`
+//! - intervals: `every 15 minutes`, `every other day`, `hourly`
+//! - recurring: `every weekday at 8am`, `every monday and thursday at 18:30`,
+//!   `weekends at 10`, `daily at 7:15`
+//! - monthly: `every month on the 15th at 9am`, `the 1st of every month`,
+//!   `first monday of the month at 9`
+//!
+//! Day-level schedules without a time run at 09:00. Cron expressions carry
+//! the timezone they were parsed in.
+
+use crate::cron::types::CronSchedule;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use regex::Regex;
+use std::sync::LazyLock;
+
+const DEFAULT_HOUR: u32 = 9;
+const TIME: &str = r"(noon|midnight|\d{1,2}(?::\d{2})?\s*(?:am|pm)?)";
+const DAYS: &str = r"((?:mon|tue|wed|thu|fri|sat|sun)[a-z]*(?:(?:\s*,\s*|\s+and\s+|\s*&\s*)(?:mon|tue|wed|thu|fri|sat|sun)[a-z]*)*)";
+
+static IN_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^in (.+)$").unwrap());
+static INTERVAL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:every|each) (\d+ |other )?(second|minute|min|hour|day|week)s?$").unwrap()
+});
+static WEEKLY_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"^(every |each |on )?(day|daily|weekdays?|weekends?|{DAYS})(?: (?:at )?{TIME})?$"
+    ))
+    .unwrap()
+});
+static MONTHLY_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"^(?:(?:every|each) month|monthly)(?: on the (\d{{1,2}})(?:st|nd|rd|th)?)?(?: (?:at )?{TIME})?$"
+    ))
+    .unwrap()
+});
+static DAY_OF_MONTH_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"^(?:on )?the (\d{{1,2}})(?:st|nd|rd|th)? of (?:every|each|the) month(?: (?:at )?{TIME})?$"
+    ))
+    .unwrap()
+});
+static NTH_WEEKDAY_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"^(?:(?:on|every) )?(?:the )?(first|second|third|fourth|last|1st|2nd|3rd|4th) ((?:mon|tue|wed|thu|fri|sat|sun)[a-z]*) of (?:the|every|each) month(?: (?:at )?{TIME})?$"
+    ))
+    .unwrap()
+});
+static ONE_SHOT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"^(?:(today|tonight|tomorrow|(?:next |on )?(?:mon|tue|wed|thu|fri|sat|sun)[a-z]*|(?:on )?\d{{4}}-\d{{2}}-\d{{2}})(?: |$))?(?:at )?{TIME}?$"
+    ))
+    .unwrap()
+});
+
+/// When a snoozed reminder should fire again: empty means ten minutes,
+/// otherwise a compact duration (`10m`, `1h30m`), `tomorrow`, `until 5pm`
+/// or any one-shot phrase [`parse`] understands.
+pub fn snooze_until(spec: &str, now: DateTime<Utc>, tz: Tz) -> Result<i64> {
+    let spec = normalize(spec);
+    let spec = spec
+        .strip_prefix("until ")
+        .or_else(|| spec.strip_prefix("for "))
+        .unwrap_or(&spec);
+    if spec.is_empty() {
+        return Ok((now + Duration::minutes(10)).timestamp_millis());
+    }
+    if let Some(secs) = crate::tools::timer::parse_duration_secs(spec) {
+        return Ok(now.timestamp_millis() + secs as i64 * 1000);
+    }
+    let schedule = parse(spec, now, tz).or_else(|_| parse(&format!("in {spec}"), now, tz))?;
+    schedule
+        .at_ms
+        .filter(|_| schedule.kind == "at")
+        .ok_or_else(|| {
+            anyhow!("Snooze needs a single time, e.g. 'snooze 1h' or 'snooze tomorrow'.")
+        })
+}
+
+/// Parse `phrase` relative to `now`, reading clock times in `tz`.
+pub fn parse(phrase: &str, now: DateTime<Utc>, tz: Tz) -> Result<CronSchedule> {
+    let text = normalize(phrase);
+    let unknown = || {
+        anyhow!(
+            "Could not understand schedule '{}'. Try e.g. 'in 2 hours', 'every weekday at 8am', 'first monday of the month at 9'.",
+            phrase.trim()
+        )
+    };
+    if text.is_empty() {
+        return Err(unknown());
+    }
+
+    if let Some(caps) = IN_RE.captures(&text) {
+        let delay = parse_delay(&caps[1]).ok_or_else(unknown)?;
+        return Ok(at(now + delay));
+    }
+    if matches!(text.as_str(), "hourly" | "every hour") {
+        return Ok(every(Duration::hours(1)));
+    }
+    if let Some(caps) = INTERVAL_RE.captures(&text) {
+        let count = match caps.get(1).map(|m| m.as_str().trim()) {
+            None => 1,
+            Some("other") => 2,
+            Some(n) => n.parse::<i64>().map_err(|_| unknown())?,
+        };
+        if count <= 0 {
+            return Err(unknown());
+        }
+        let unit = match &caps[2] {
+            "second" => Duration::seconds(1),
+            "minute" | "min" => Duration::minutes(1),
+            "hour" => Duration::hours(1),
+            "day" => Duration::days(1),
+            _ => Duration::weeks(1),
+        };
+        return Ok(every(unit * count as i32));
+    }
+    // "every monday" and "on mondays" recur; "on monday" is a one-shot.
+    if let Some(caps) = WEEKLY_RE.captures(&text).filter(|caps| {
+        matches!(caps.get(1).map(|m| m.as_str()), Some("every " | "each "))
+            || caps.get(3).is_none()
+            || caps[2].ends_with('s')
+    }) {
+        let (hour, minute) =
+            time_or_default(caps.get(4).map(|m| m.as_str())).ok_or_else(unknown)?;
+        let days = match caps[2].trim_end_matches('s') {
+            "day" | "daily" => "*".to_string(),
+            "weekday" => "Mon-Fri".to_string(),
+            "weekend" => "Sat,Sun".to_string(),
+            _ => parse_day_list(&caps[2]).ok_or_else(unknown)?,
+        };
+        return Ok(cron(format!("0 {minute} {hour} * * {days}"), tz));
+    }
+    if let Some(caps) = MONTHLY_RE
+        .captures(&text)
+        .or_else(|| DAY_OF_MONTH_RE.captures(&text))
+    {
+        let day = caps
+            .get(1)
+            .map_or(Some(1), |m| m.as_str().parse::<u32>().ok());
+        let day = day.filter(|d| (1..=31).contains(d)).ok_or_else(unknown)?;
+        let (hour, minute) =
+            time_or_default(caps.get(2).map(|m| m.as_str())).ok_or_else(unknown)?;
+        return Ok(cron(format!("0 {minute} {hour} {day} * *"), tz));
+    }
+    if let Some(caps) = NTH_WEEKDAY_RE.captures(&text) {
+        let first_day = match &caps[1] {
+            "first" | "1st" => 1,
+            "second" | "2nd" => 8,
+            "third" | "3rd" => 15,
+            "fourth" | "4th" => 22,
+            _ => {
+                return Err(anyhow!(
+                    "'last <weekday> of the month' cannot be scheduled yet; use a specific week instead."
+                ))
+            }
+        };
+        let weekday = parse_weekday(&caps[2]).ok_or_else(unknown)?;
+        let (hour, minute) =
+            time_or_default(caps.get(3).map(|m| m.as_str())).ok_or_else(unknown)?;
+        // The cron crate requires both day-of-month and day-of-week to match.
+        return Ok(cron(
+            format!(
+                "0 {minute} {hour} {first_day}-{} * {}",
+                first_day + 6,
+                cron_day(weekday)
+            ),
+            tz,
+        ));
+    }
+    if let Some(caps) = ONE_SHOT_RE.captures(&text) {
+        let day = caps.get(1).map(|m| m.as_str());
+        let time = caps.get(2).map(|m| m.as_str());
+        if day.is_none() && time.is_none() {
+            return Err(unknown());
+        }
+        let local_now = now.with_timezone(&tz);
+        let (hour, minute) = match (day, time) {
+            (Some("tonight"), None) => (20, 0),
+            _ => time_or_default(time).ok_or_else(unknown)?,
+        };
+        let clock = NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(unknown)?;
+        let today = local_now.date_naive();
+        let date = match day {
+            None | Some("today") | Some("tonight") => today,
+            Some("tomorrow") => today + Duration::days(1),
+            Some(day) => {
+                let day = day.trim_start_matches("on ");
+                if let Ok(date) = NaiveDate::parse_from_str(day, "%Y-%m-%d") {
+                    date
+                } else {
+                    let next = day.starts_with("next ");
+                    let weekday =
+                        parse_weekday(day.trim_start_matches("next ")).ok_or_else(unknown)?;
+                    let mut ahead = (weekday.num_days_from_monday() + 7
+                        - today.weekday().num_days_from_monday())
+                        % 7;
+                    if ahead == 0 && (next || clock <= local_now.time()) {
+                        ahead = 7;
+                    }
+                    today + Duration::days(ahead as i64)
+                }
+            }
+        };
+        let mut when = local_to_utc(tz, date, clock).ok_or_else(unknown)?;
+        if when <= now {
+            if day.is_some() {
+                return Err(anyhow!("'{}' is in the past.", phrase.trim()));
+            }
+            // A bare time that already passed today means tomorrow.
+            when = local_to_utc(tz, date + Duration::days(1), clock).ok_or_else(unknown)?;
+        }
+        return Ok(at(when));
+    }
+    Err(unknown())
+}
+
+fn normalize(phrase: &str) -> String {
+    let text = phrase
+        .to_lowercase()
+        .replace("a.m.", "am")
+        .replace("p.m.", "pm")
+        .replace("o'clock", "");
+    let text = text.trim().trim_end_matches(['.', '!']);
+    text.strip_prefix("remind me ")
+        .unwrap_or(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// "2 hours", "an hour and 30 minutes", "half an hour", "90 min".
+fn parse_delay(text: &str) -> Option<Duration> {
+    if matches!(text, "half an hour" | "a half hour") {
+        return Some(Duration::minutes(30));
+    }
+    let words: Vec<&str> = text
+        .split([' ', ','])
+        .filter(|w| !w.is_empty() && *w != "and")
+        .collect();
+    if words.is_empty() || !words.len().is_multiple_of(2) {
+        return None;
+    }
+    let mut total = Duration::zero();
+    for pair in words.chunks(2) {
+        let count: i64 = match pair[0] {
+            "a" | "an" | "one" => 1,
+            "two" => 2,
+            "three" => 3,
+            n => n.parse().ok()?,
+        };
+        let unit = match pair[1].trim_end_matches('s') {
+            "second" | "sec" => Duration::seconds(1),
+            "minute" | "min" => Duration::minutes(1),
+            "hour" | "hr" => Duration::hours(1),
+            "day" => Duration::days(1),
+            "week" => Duration::weeks(1),
+            _ => return None,
+        };
+        total += unit * i32::try_from(count).ok()?;
+    }
+    (total > Duration::zero()).then_some(total)
+}
+
+/// "8am", "8:30 pm", "18:30", "noon" → (hour, minute) on a 24h clock.
+fn parse_time(text: &str) -> Option<(u32, u32)> {
+    match text {
+        "noon" => return Some((12, 0)),
+        "midnight" => return Some((0, 0)),
+        _ => {}
+    }
+    let (clock, meridiem) = if let Some(rest) = text.strip_suffix("am") {
+        (rest.trim(), Some(false))
+    } else if let Some(rest) = text.strip_suffix("pm") {
+        (rest.trim(), Some(true))
+    } else {
+        (text, None)
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((h, m)) => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    let hour = match meridiem {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(false) => hour % 12,
+        Some(true) => hour % 12 + 12,
+        None => hour,
+    };
+    (hour < 24 && minute < 60).then_some((hour, minute))
+}
+
+fn time_or_default(text: Option<&str>) -> Option<(u32, u32)> {
+    match text {
+        Some(text) => parse_time(text),
+        None => Some((DEFAULT_HOUR, 0)),
+    }
+}
+
+fn parse_weekday(text: &str) -> Option<Weekday> {
+    let day = match text.get(..3)? {
+        "mon" => Weekday::Mon,
+        "tue" => Weekday::Tue,
+        "wed" => Weekday::Wed,
+        "thu" => Weekday::Thu,
+        "fri" => Weekday::Fri,
+        "sat" => Weekday::Sat,
+        "sun" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(day)
+}
+
+fn parse_day_list(text: &str) -> Option<String> {
+    let mut days = Vec::new();
+    for word in text
+        .split([',', '&', ' '])
+        .filter(|w| !w.is_empty() && *w != "and")
+    {
+        let day = parse_weekday(word)?;
+        if !days.contains(&day) {
+            days.push(day);
+        }
+    }
+    days.sort_by_key(|d| d.num_days_from_monday());
+    Some(days.into_iter().map(cron_day).collect::<Vec<_>>().join(","))
+}
+
+fn cron_day(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Mon",
+        Weekday::Tue => "Tue",
+        Weekday::Wed => "Wed",
+        Weekday::Thu => "Thu",
+        Weekday::Fri => "Fri",
+        Weekday::Sat => "Sat",
+        Weekday::Sun => "Sun",
+    }
+}
+
+fn local_to_utc(tz: Tz, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+fn at(when: DateTime<Utc>) -> CronSchedule {
+    CronSchedule {
+        kind: "at".to_string(),
+        at_ms: Some(when.timestamp_millis()),
+        every_ms: None,
+        expr: None,
+        tz: None,
+    }
+}
+
+fn every(interval: Duration) -> CronSchedule {
+    CronSchedule {
+        kind: "every".to_string(),
+        at_ms: None,
+        every_ms: Some(interval.num_milliseconds()),
+        expr: None,
+        tz: None,
+    }
+}
+
+fn cron(expr: String, tz: Tz) -> CronSchedule {
+    CronSchedule {
+        kind: "cron".to_string(),
+        at_ms: None,
+        every_ms: None,
+        expr: Some(expr),
+        tz: Some(tz.name().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, snooze_until};
+    use chrono::{TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    #[test]
+    fn parses_common_phrases() {
+        // Wednesday 2025-06-04 10:00 in Berlin (08:00 UTC).
+        let now = Utc.with_ymd_and_hms(2025, 6, 4, 8, 0, 0).unwrap();
+        let tz: Tz = "Europe/Berlin".parse().unwrap();
+        let expr = |phrase: &str| parse(phrase, now, tz).unwrap().expr.unwrap();
+        let at = |phrase: &str| {
+            let ms = parse(phrase, now, tz).unwrap().at_ms.unwrap();
+            Utc.timestamp_millis_opt(ms).unwrap().to_rfc3339()
+        };
+
+        assert_eq!(expr("Every weekday at 8am"), "0 0 8 * * Mon-Fri");
+        assert_eq!(
+            expr("every monday and thursday at 18:30"),
+            "0 30 18 * * Mon,Thu"
+        );
+        assert_eq!(expr("daily at 7:15pm"), "0 15 19 * * *");
+        assert_eq!(expr("first Monday of the month"), "0 0 9 1-7 * Mon");
+        assert_eq!(expr("the 15th of every month at noon"), "0 0 12 15 * *");
+        assert_eq!(
+            parse("every 2 hours", now, tz).unwrap().every_ms,
+            Some(7_200_000)
+        );
+        assert_eq!(at("in 2 hours"), "2025-06-04T10:00:00+00:00");
+        assert_eq!(at("tomorrow at 9am"), "2025-06-05T07:00:00+00:00");
+        assert_eq!(at("at 9am"), "2025-06-05T07:00:00+00:00");
+        assert_eq!(at("next wednesday at 10"), "2025-06-11T08:00:00+00:00");
+        assert!(parse("last friday of the month", now, tz).is_err());
+        assert!(parse("whenever you like", now, tz).is_err());
+    }
+
+    #[test]
+    fn snooze_targets() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 4, 8, 0, 0).unwrap();
+        let tz: Tz = "Europe/Berlin".parse().unwrap();
+        let at = |spec: &str| {
+            let ms = snooze_until(spec, now, tz).unwrap();
+            Utc.timestamp_millis_opt(ms).unwrap().to_rfc3339()
+        };
+        assert_eq!(at(""), "2025-06-04T08:10:00+00:00");
+        assert_eq!(at("1h"), "2025-06-04T09:00:00+00:00");
+        assert_eq!(at("30 minutes"), "2025-06-04T08:30:00+00:00");
+        assert_eq!(at("tomorrow"), "2025-06-05T07:00:00+00:00");
+        assert_eq!(at("until 5pm"), "2025-06-04T15:00:00+00:00");
+        assert!(snooze_until("every day", now, tz).is_err());
+    }
+}
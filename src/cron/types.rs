@@ -28,15 +28,43 @@ pub struct CronSchedule {
     pub tz: Option<String>,
 }
 
+impl CronSchedule {
+    /// Short human-readable form, e.g. `cron 0 0 8 * * Mon-Fri (Europe/Berlin)`.
+    pub fn describe(&self) -> String {
+        match self.kind.as_str() {
+            "at" => {
+                let when = self
+                    .at_ms
+                    .and_then(chrono::DateTime::<chrono::Utc>::from_timestamp_millis)
+                    .map(|dt| dt.to_rfc3339())
+                    .unwrap_or_else(|| "?".to_string());
+                format!("once at {when}")
+            }
+            "every" => format!("every {}s", self.every_ms.unwrap_or(0) / 1000),
+            _ => {
+                let expr = self.expr.as_deref().unwrap_or("?");
+                match &self.tz {
+                    Some(tz) => format!("cron {expr} ({tz})"),
+                    None => format!("cron {expr}"),
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CronPayload {
-    pub kind: String, // "agent_turn"
+    pub kind: String, // "agent_turn", "reminder", "message", "pending"
     pub message: String,
     #[serde(default)]
     pub deliver: bool,
     pub channel: Option<String>,
     pub to: Option<String>,
     pub model: Option<String>,
+    /// `channel:chat_id` of the chat that queued a pending send, which may
+    /// cancel it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -1,5 +1,7 @@
-use crate::bus::{InboundMessage, MessageBus, OutboundMessage};
+use crate::bus::{InboundMessage, MessageBus};
+use crate::channel::{self, Channel};
 use crate::config::AppConfig;
+use crate::ratelimit::RateLimiter;
 use anyhow::{anyhow, Result};
 use serenity::async_trait;
 use serenity::http::Http;
@@ -9,11 +11,31 @@ use serenity::model::id::ChannelId;
 use serenity::prelude::*;
 use std::collections::HashSet;
 use std::sync::Arc;
+use tokio::task::JoinHandle;
 use tracing::{info, warn};
 
-const DISCORD_MESSAGE_LIMIT: usize = 2000;
+pub struct DiscordChannel {
+    cfg: AppConfig,
+}
+
+impl DiscordChannel {
+    pub fn new(cfg: AppConfig) -> Self {
+        Self { cfg }
+    }
+}
+
+impl Channel for DiscordChannel {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
+
+    fn start(self: Arc<Self>, bus: MessageBus) -> JoinHandle<()> {
+        channel::spawn(self.name(), serve(self, bus))
+    }
+}
 
-pub async fn start(cfg: AppConfig, bus: MessageBus) -> Result<()> {
+async fn serve(channel: Arc<DiscordChannel>, bus: MessageBus) -> Result<()> {
+    let cfg = &channel.cfg;
     let token = cfg.discord_bot_token.trim().to_string();
     if token.is_empty() {
         return Err(anyhow!("discord token is missing"));
@@ -23,13 +45,18 @@ pub async fn start(cfg: AppConfig, bus: MessageBus) -> Result<()> {
         | GatewayIntents::DIRECT_MESSAGES
         | GatewayIntents::MESSAGE_CONTENT;
 
-    let handler = DiscordHandler::new(&cfg, bus.clone());
+    let handler = DiscordHandler::new(cfg, bus.clone());
     let mut client = Client::builder(token, intents)
         .event_handler(handler)
         .await
         .map_err(|err| anyhow!("discord client initialization failed: {err}"))?;
 
-    spawn_outbound_forwarder(client.http.clone(), bus.subscribe_outbound());
+    spawn_outbound_forwarder(
+        channel.clone(),
+        client.http.clone(),
+        bus.clone(),
+        RateLimiter::for_channel(cfg, "discord"),
+    );
 
     client
         .start()
@@ -117,7 +144,15 @@ impl EventHandler for DiscordHandler {
                 channel: "discord".to_string(),
                 chat_id: msg.channel_id.get().to_string(),
                 sender_id: msg.author.id.get().to_string(),
+                sender_name: Some(
+                    msg.author
+                        .global_name
+                        .clone()
+                        .unwrap_or_else(|| msg.author.name.clone()),
+                ),
+                is_group: msg.guild_id.is_some(),
                 content: text,
+                ..Default::default()
             })
             .await;
     }
@@ -128,66 +163,33 @@ impl EventHandler for DiscordHandler {
 }
 
 fn spawn_outbound_forwarder(
+    channel: Arc<DiscordChannel>,
     http: Arc<Http>,
-    mut rx: tokio::sync::broadcast::Receiver<OutboundMessage>,
+    bus: MessageBus,
+    mut limiter: RateLimiter,
 ) {
+    let mut rx = bus.outbound_queue(channel.name());
     tokio::spawn(async move {
-        loop {
-            let msg = match rx.recv().await {
-                Ok(msg) => msg,
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                    info!("outbound channel closed, discord forwarder shutting down");
-                    break;
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
-                    warn!("discord outbound lagged, skipped {skipped} message(s)");
-                    continue;
-                }
-            };
-
-            if msg.channel != "discord" {
-                continue;
-            }
-
+        while let Some(msg) = rx.recv().await {
             let Ok(raw_channel_id) = msg.chat_id.parse::<u64>() else {
                 warn!("invalid discord chat_id: {}", msg.chat_id);
                 continue;
             };
 
-            if let Err(err) =
-                send_discord_message(&http, ChannelId::new(raw_channel_id), &msg.content).await
-            {
-                warn!("discord send failed for channel {}: {err}", msg.chat_id);
+            let mut result = Ok(());
+            for chunk in channel.render(&msg) {
+                limiter.acquire(&msg.chat_id).await;
+                result = ChannelId::new(raw_channel_id)
+                    .say(&http, chunk)
+                    .await
+                    .map(|_| ());
+                if let Err(err) = &result {
+                    warn!("discord send failed for channel {}: {err}", msg.chat_id);
+                    break;
+                }
             }
+            bus.report_delivery(&msg, result.is_ok());
         }
+        info!("outbound channel closed, discord forwarder shutting down");
     });
 }
-
-async fn send_discord_message(
-    http: &Http,
-    channel_id: ChannelId,
-    text: &str,
-) -> serenity::Result<()> {
-    if text.len() <= DISCORD_MESSAGE_LIMIT {
-        channel_id.say(http, text).await?;
-        return Ok(());
-    }
-
-    let mut remaining = text;
-    while !remaining.is_empty() {
-        let chunk_len = if remaining.len() <= DISCORD_MESSAGE_LIMIT {
-            remaining.len()
-        } else {
-            remaining[..DISCORD_MESSAGE_LIMIT]
-                .rfind('\n')
-                .unwrap_or(DISCORD_MESSAGE_LIMIT)
-        };
-        let chunk = &remaining[..chunk_len];
-        channel_id.say(http, chunk).await?;
-        remaining = &remaining[chunk_len..];
-        if remaining.starts_with('\n') {
-            remaining = &remaining[1..];
-        }
-    }
-    Ok(())
-}
@@ -0,0 +1,321 @@
+//! Structured errors for tools and agent turns. Every failure carries an
+//! [`ErrorKind`] with a stable code (used in logs, `/status`, debug captures
+//! and `usage.jsonl`) and a retry decision, so callers branch on the kind
+//! instead of matching error text. Provider failures are classified from
+//! rig's error variants and the provider's JSON error body; only bodies
+//! that are not JSON (gateway pages, plain text) fall back to looking for a
+//! status code or a known phrase as a whole word.
+
+use rig::completion::{CompletionError, PromptError};
+use rig::http_client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The provider is throttling requests (HTTP 429).
+    RateLimit,
+    /// The call took too long, locally or at the provider.
+    Timeout,
+    /// The provider failed or is overloaded (5xx).
+    Upstream,
+    /// The provider could not be reached at all.
+    Network,
+    /// The API key was rejected or lacks permission.
+    Auth,
+    /// The account is out of credit or quota.
+    Quota,
+    /// The conversation does not fit the model's context window.
+    ContextLength,
+    /// The provider rejected the request itself (bad model, bad parameters).
+    Request,
+    /// The provider answered with something that could not be parsed.
+    Response,
+    /// The model called tools for more turns than allowed.
+    MaxTurns,
+    /// A tool call did not match the tool's schema.
+    InvalidArgs,
+    /// A tool call was refused before running (loops, hooks, permissions).
+    Refused,
+    /// A tool ran and failed.
+    Tool,
+    /// The turn was cancelled.
+    Cancelled,
+    Unknown,
+}
+
+impl ErrorKind {
+    /// Stable code for logs and stored records.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::RateLimit => "rate_limit",
+            Self::Timeout => "timeout",
+            Self::Upstream => "upstream",
+            Self::Network => "network",
+            Self::Auth => "auth",
+            Self::Quota => "quota",
+            Self::ContextLength => "context_length",
+            Self::Request => "request",
+            Self::Response => "response",
+            Self::MaxTurns => "max_turns",
+            Self::InvalidArgs => "invalid_args",
+            Self::Refused => "refused",
+            Self::Tool => "tool",
+            Self::Cancelled => "cancelled",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// Whether trying the same call again after a pause may succeed.
+    pub fn retryable(self) -> bool {
+        matches!(
+            self,
+            Self::RateLimit | Self::Timeout | Self::Upstream | Self::Network
+        )
+    }
+
+    /// What the user is told when a turn ends with this kind of failure.
+    pub fn user_message(self) -> &'static str {
+        match self {
+            Self::RateLimit => "The model provider is rate-limiting requests right now. Please try again in a minute.",
+            Self::Timeout => "The model took too long to answer. Please try again.",
+            Self::Upstream | Self::Response => {
+                "The model provider is having problems right now. Please try again later."
+            }
+            Self::Network => "I couldn't reach the model provider. Please try again later.",
+            Self::Auth => "The model provider rejected my credentials; the bot's API key needs checking.",
+            Self::Quota => "The model provider account is out of credit or quota.",
+            Self::ContextLength => "This conversation is too long for the model. Send /reset to start over.",
+            Self::Request => "The model provider rejected the request; the bot's model settings may need checking.",
+            Self::MaxTurns => "I used too many tool steps without finishing. Try a smaller request.",
+            Self::InvalidArgs | Self::Refused | Self::Tool => {
+                "A tool failed while I was working on this. Please try again."
+            }
+            Self::Cancelled => "The request was cancelled.",
+            Self::Unknown => "Something went wrong while answering.",
+        }
+    }
+
+    /// Kind of an HTTP status returned by a provider.
+    pub fn from_status(status: u16) -> Option<Self> {
+        Some(match status {
+            429 => Self::RateLimit,
+            408 | 504 | 524 => Self::Timeout,
+            402 => Self::Quota,
+            401 | 403 => Self::Auth,
+            413 => Self::ContextLength,
+            400..=499 => Self::Request,
+            500..=599 => Self::Upstream,
+            _ => return None,
+        })
+    }
+
+    /// Classify a failed prompt from rig's error variants.
+    pub fn of_prompt(err: &PromptError) -> Self {
+        match err {
+            PromptError::CompletionError(err) => Self::of_completion(err),
+            PromptError::ToolError(_) | PromptError::ToolServerError(_) => Self::Tool,
+            PromptError::MaxTurnsError { .. } => Self::MaxTurns,
+            PromptError::PromptCancelled { .. } => Self::Cancelled,
+        }
+    }
+
+    fn of_completion(err: &CompletionError) -> Self {
+        match err {
+            CompletionError::HttpError(err) => Self::of_http(err),
+            CompletionError::JsonError(_) | CompletionError::ResponseError(_) => Self::Response,
+            CompletionError::UrlError(_) | CompletionError::RequestError(_) => Self::Request,
+            CompletionError::ProviderError(body) => Self::of_provider_body(body),
+        }
+    }
+
+    fn of_http(err: &http_client::Error) -> Self {
+        match err {
+            http_client::Error::InvalidStatusCode(status)
+            | http_client::Error::InvalidStatusCodeWithMessage(status, _) => {
+                Self::from_status(status.as_u16()).unwrap_or(Self::Unknown)
+            }
+            http_client::Error::StreamEnded | http_client::Error::InvalidContentType(_) => {
+                Self::Upstream
+            }
+            http_client::Error::Instance(err) => err
+                .downcast_ref::<reqwest::Error>()
+                .map(Self::of_reqwest)
+                .unwrap_or(Self::Network),
+            _ => Self::Network,
+        }
+    }
+
+    /// Kind of a failed outbound request, for the provider and web tools alike.
+    pub fn of_reqwest(err: &reqwest::Error) -> Self {
+        if err.is_timeout() {
+            return Self::Timeout;
+        }
+        err.status()
+            .and_then(|status| Self::from_status(status.as_u16()))
+            .unwrap_or(Self::Network)
+    }
+
+    /// Classify the error body a provider returned: its JSON error code,
+    /// type or status first, then a status or phrase in plain text.
+    pub fn of_provider_body(body: &str) -> Self {
+        if let Ok(json) = serde_json::from_str::<Value>(body.trim()) {
+            if let Some(kind) = Self::of_error_json(&json) {
+                return kind;
+            }
+        }
+        let lower = body.to_ascii_lowercase();
+        let words: Vec<&str> = lower
+            .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .filter(|w| !w.is_empty())
+            .collect();
+        if let Some(kind) = words
+            .iter()
+            .filter(|w| w.len() == 3)
+            .filter_map(|w| w.parse::<u16>().ok())
+            .find_map(Self::from_status)
+        {
+            return kind;
+        }
+        let phrase = |p: &str| {
+            let p: Vec<&str> = p.split(' ').collect();
+            words.windows(p.len()).any(|w| w == p.as_slice())
+        };
+        if phrase("rate limit") || phrase("too many requests") {
+            Self::RateLimit
+        } else if phrase("timed out") || phrase("timeout") {
+            Self::Timeout
+        } else if phrase("overloaded") || phrase("temporarily unavailable") {
+            Self::Upstream
+        } else if phrase("context length") || phrase("context window") {
+            Self::ContextLength
+        } else if phrase("unauthorized") || phrase("invalid api key") {
+            Self::Auth
+        } else {
+            Self::Unknown
+        }
+    }
+
+    /// Kind named by an error object such as `{"error": {"code": 429}}`,
+    /// `{"error": {"type": "overloaded_error"}}` or
+    /// `{"error": {"code": "context_length_exceeded"}}`.
+    fn of_error_json(json: &Value) -> Option<Self> {
+        let error = json.get("error").filter(|e| e.is_object()).unwrap_or(json);
+        let labels = ["code", "type", "status"]
+            .iter()
+            .filter_map(|key| error.get(*key))
+            .filter_map(|v| match v {
+                Value::Number(n) => n
+                    .as_u64()
+                    .and_then(|n| u16::try_from(n).ok())
+                    .and_then(Self::from_status),
+                Value::String(s) => s
+                    .parse::<u16>()
+                    .ok()
+                    .and_then(Self::from_status)
+                    .or_else(|| Self::from_label(s)),
+                _ => None,
+            });
+        // A specific label ("context_length_exceeded") beats a generic status.
+        let mut found: Vec<Self> = labels.collect();
+        found.sort_by_key(|kind| matches!(kind, Self::Request | Self::Upstream));
+        found.into_iter().next()
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        Some(match label.to_ascii_lowercase().as_str() {
+            "rate_limit_exceeded" | "rate_limit_error" | "resource_exhausted" => Self::RateLimit,
+            "insufficient_quota" | "billing_error" | "payment_required" => Self::Quota,
+            "context_length_exceeded" | "string_above_max_length" => Self::ContextLength,
+            "overloaded_error" | "server_error" | "api_error" | "unavailable" => Self::Upstream,
+            "timeout" | "timeout_error" | "deadline_exceeded" => Self::Timeout,
+            "authentication_error"
+            | "invalid_api_key"
+            | "permission_error"
+            | "unauthenticated"
+            | "permission_denied" => Self::Auth,
+            "invalid_request_error"
+            | "not_found_error"
+            | "model_not_found"
+            | "invalid_argument" => Self::Request,
+            _ => return None,
+        })
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// A failed agent turn: what kind of failure it was, what the user is told
+/// and the full detail for logs.
+#[derive(Clone, Debug)]
+pub struct AgentError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub detail: String,
+}
+
+impl AgentError {
+    pub fn new(kind: ErrorKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: kind.user_message().to_string(),
+            detail: detail.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for AgentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.kind, self.detail)
+    }
+}
+
+impl std::error::Error for AgentError {}
+
+#[cfg(test)]
+mod tests {
+    use super::ErrorKind;
+
+    #[test]
+    fn provider_bodies_are_classified_by_code_not_substring() {
+        let cases = [
+            (
+                r#"{"error":{"message":"Rate limit exceeded","code":429}}"#,
+                ErrorKind::RateLimit,
+            ),
+            (
+                r#"{"error":{"message":"This model's maximum context length is 4000 tokens","type":"invalid_request_error","code":"context_length_exceeded"}}"#,
+                ErrorKind::ContextLength,
+            ),
+            (
+                r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+                ErrorKind::Upstream,
+            ),
+            (
+                r#"{"error":{"message":"No auth credentials found","code":401}}"#,
+                ErrorKind::Auth,
+            ),
+            (
+                "<html><title>502 Bad Gateway</title></html>",
+                ErrorKind::Upstream,
+            ),
+            // Numbers inside other numbers or ids are not statuses.
+            (
+                "max_tokens must be at most 4000 for model x-4290",
+                ErrorKind::Unknown,
+            ),
+            ("Request timed out", ErrorKind::Timeout),
+        ];
+        for (body, kind) in cases {
+            assert_eq!(ErrorKind::of_provider_body(body), kind, "{body}");
+        }
+        assert!(ErrorKind::RateLimit.retryable());
+        assert!(!ErrorKind::ContextLength.retryable());
+        assert_eq!(ErrorKind::ContextLength.code(), "context_length");
+    }
+}
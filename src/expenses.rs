@@ -0,0 +1,227 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection};
+
+#[derive(Clone, Debug)]
+pub struct Expense {
+    pub id: i64,
+    pub amount: f64,
+    pub currency: String,
+    pub category: String,
+    pub note: Option<String>,
+    pub date: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct ExpenseTotal {
+    pub group: String,
+    pub currency: String,
+    pub total: f64,
+    pub count: i64,
+}
+
+/// Grouping key for expense summaries.
+#[derive(Clone, Copy, Debug)]
+pub enum SummaryGroup {
+    Category,
+    Month,
+}
+
+/// SQLite-backed expense ledger stored at `<workspace>/expenses.db`.
+#[derive(Clone)]
+pub struct ExpenseStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl ExpenseStore {
+    pub fn new(db_path: PathBuf) -> Result<Self> {
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(db_path)?;
+        init_db(&conn)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Run a blocking closure against the database connection on Tokio's
+    /// blocking thread pool.
+    async fn with_conn<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().map_err(|e| anyhow!("mutex poisoned: {e}"))?;
+            f(&conn)
+        })
+        .await
+        .map_err(|e| anyhow!("blocking task failed: {e}"))?
+    }
+
+    pub async fn add(
+        &self,
+        amount: f64,
+        currency: &str,
+        category: &str,
+        note: Option<String>,
+        date: &str,
+    ) -> Result<Expense> {
+        if !amount.is_finite() {
+            return Err(anyhow!("amount must be a finite number"));
+        }
+        let currency = currency.trim().to_uppercase();
+        let category = category.trim().to_lowercase();
+        if category.is_empty() {
+            return Err(anyhow!("category cannot be empty"));
+        }
+        let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+            .map_err(|_| anyhow!("date must be YYYY-MM-DD"))?
+            .format("%Y-%m-%d")
+            .to_string();
+        let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+        let created_at = Utc::now().to_rfc3339();
+
+        let (cur, cat, nt, dt) = (
+            currency.clone(),
+            category.clone(),
+            note.clone(),
+            date.clone(),
+        );
+        let id = self
+            .with_conn(move |conn| {
+                conn.execute(
+                    "INSERT INTO expenses (amount, currency, category, note, date, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![amount, cur, cat, nt, dt, created_at],
+                )?;
+                Ok(conn.last_insert_rowid())
+            })
+            .await?;
+
+        Ok(Expense {
+            id,
+            amount,
+            currency,
+            category,
+            note,
+            date,
+        })
+    }
+
+    pub async fn remove(&self, id: i64) -> Result<bool> {
+        self.with_conn(move |conn| {
+            let rows = conn.execute("DELETE FROM expenses WHERE id = ?1", params![id])?;
+            Ok(rows > 0)
+        })
+        .await
+    }
+
+    /// List expenses, optionally restricted to a `YYYY-MM` month and category.
+    pub async fn list(
+        &self,
+        month: Option<String>,
+        category: Option<String>,
+        limit: usize,
+    ) -> Result<Vec<Expense>> {
+        let month_prefix = month.map(|m| format!("{}%", m.trim()));
+        let category = category.map(|c| c.trim().to_lowercase());
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, amount, currency, category, note, date FROM expenses \
+                 WHERE (?1 IS NULL OR date LIKE ?1) AND (?2 IS NULL OR category = ?2) \
+                 ORDER BY date DESC, id DESC LIMIT ?3",
+            )?;
+            let rows = stmt.query_map(params![month_prefix, category, limit as i64], |row| {
+                Ok(Expense {
+                    id: row.get(0)?,
+                    amount: row.get(1)?,
+                    currency: row.get(2)?,
+                    category: row.get(3)?,
+                    note: row.get(4)?,
+                    date: row.get(5)?,
+                })
+            })?;
+            Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+        })
+        .await
+    }
+
+    /// Expenses dated from `from` to `to`, both inclusive, oldest first.
+    pub async fn between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Expense>> {
+        let (from, to) = (from.to_string(), to.to_string());
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, amount, currency, category, note, date FROM expenses \
+                 WHERE date >= ?1 AND date <= ?2 ORDER BY date ASC, id ASC",
+            )?;
+            let rows = stmt.query_map(params![from, to], |row| {
+                Ok(Expense {
+                    id: row.get(0)?,
+                    amount: row.get(1)?,
+                    currency: row.get(2)?,
+                    category: row.get(3)?,
+                    note: row.get(4)?,
+                    date: row.get(5)?,
+                })
+            })?;
+            Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+        })
+        .await
+    }
+
+    /// Sum expenses per category (within `month`, if given) or per month.
+    pub async fn totals(
+        &self,
+        group: SummaryGroup,
+        month: Option<String>,
+    ) -> Result<Vec<ExpenseTotal>> {
+        let month_prefix = month.map(|m| format!("{}%", m.trim()));
+        let group_expr = match group {
+            SummaryGroup::Category => "category",
+            SummaryGroup::Month => "substr(date, 1, 7)",
+        };
+        let sql = format!(
+            "SELECT {group_expr} AS grp, currency, SUM(amount), COUNT(*) FROM expenses \
+             WHERE (?1 IS NULL OR date LIKE ?1) \
+             GROUP BY grp, currency ORDER BY grp ASC, currency ASC"
+        );
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt.query_map(params![month_prefix], |row| {
+                Ok(ExpenseTotal {
+                    group: row.get(0)?,
+                    currency: row.get(1)?,
+                    total: row.get(2)?,
+                    count: row.get(3)?,
+                })
+            })?;
+            Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+        })
+        .await
+    }
+}
+
+fn init_db(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS expenses (\
+            id INTEGER PRIMARY KEY AUTOINCREMENT,\
+            amount REAL NOT NULL,\
+            currency TEXT NOT NULL,\
+            category TEXT NOT NULL,\
+            note TEXT,\
+            date TEXT NOT NULL,\
+            created_at TEXT NOT NULL\
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_expenses_date ON expenses(date DESC)",
+        [],
+    )?;
+    Ok(())
+}
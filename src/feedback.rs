@@ -0,0 +1,227 @@
+//! Reactions to the bot's replies as feedback. Delivery reports tell which
+//! channel message carried which reply; a 👍 or 👎 on one of them becomes a
+//! note on the chat's next turn, and with `feedback.log` a line in
+//! `<workspace>/feedback.jsonl` for reviewing which answers went wrong.
+
+use crate::bus::{DeliveryReport, MessageBus, Reaction};
+use crate::config::AppConfig;
+use chrono::Local;
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+/// Replies remembered for matching reactions; older ones are only logged.
+const MAX_REMEMBERED: usize = 500;
+/// Characters of the reply quoted in notes and the log.
+const PREVIEW_CHARS: usize = 200;
+
+const POSITIVE: &[&str] = &[
+    "👍", "👌", "❤", "❤️", "🔥", "👏", "🎉", "🤩", "💯", "🏆", "🙏", "😍", "🥰", "🤝",
+];
+const NEGATIVE: &[&str] = &[
+    "👎", "💩", "🤮", "🤬", "😡", "🤡", "🥱", "💔", "🤨", "😐", "🙈",
+];
+
+/// Whether `emoji` approves (`Some(true)`) or disapproves of a reply.
+fn rating(emoji: &str) -> Option<bool> {
+    if POSITIVE.contains(&emoji) {
+        Some(true)
+    } else if NEGATIVE.contains(&emoji) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// A delivered reply and the channel messages that carried it.
+struct SentReply {
+    channel: String,
+    chat_id: String,
+    message_ids: Vec<String>,
+    content: String,
+}
+
+#[derive(Default)]
+struct FeedbackState {
+    sent: VecDeque<SentReply>,
+    /// Feedback notes per `channel:chat_id`, shown on its next turn.
+    notes: HashMap<String, Vec<String>>,
+}
+
+#[derive(Clone)]
+pub struct Feedback {
+    state: Arc<Mutex<FeedbackState>>,
+    log: Option<PathBuf>,
+}
+
+impl Feedback {
+    /// Follow delivery reports and reactions on `bus` in the background.
+    pub fn watch(cfg: &AppConfig, bus: &MessageBus) -> Self {
+        let feedback = Self {
+            state: Arc::default(),
+            log: cfg
+                .feedback_log
+                .then(|| cfg.workspace_dir.join("feedback.jsonl")),
+        };
+        let watcher = feedback.clone();
+        let mut reports = bus.subscribe_deliveries();
+        let mut reactions = bus.subscribe_reactions();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    report = reports.recv() => match report {
+                        Ok(report) => watcher.remember(report),
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    },
+                    reaction = reactions.recv() => match reaction {
+                        Ok(reaction) => watcher.record(&reaction),
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    },
+                }
+            }
+        });
+        feedback
+    }
+
+    fn remember(&self, report: DeliveryReport) {
+        if !report.delivered || report.message_ids.is_empty() {
+            return;
+        }
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.sent.push_back(SentReply {
+            channel: report.channel,
+            chat_id: report.chat_id,
+            message_ids: report.message_ids,
+            content: report.content,
+        });
+        while state.sent.len() > MAX_REMEMBERED {
+            state.sent.pop_front();
+        }
+    }
+
+    fn record(&self, reaction: &Reaction) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        // A reaction names the chat without its topic; replies may have one.
+        let reply = state
+            .sent
+            .iter()
+            .rev()
+            .find(|sent| {
+                sent.channel == reaction.channel
+                    && (sent.chat_id == reaction.chat_id
+                        || sent.chat_id.starts_with(&format!("{}/", reaction.chat_id)))
+                    && sent.message_ids.contains(&reaction.message_id)
+            })
+            .map(|sent| (sent.chat_id.clone(), preview(&sent.content)));
+        let rating = rating(&reaction.emoji);
+        if let (Some((chat_id, text)), Some(good)) = (&reply, rating) {
+            let note = if good {
+                format!(
+                    "The user reacted {} to your earlier reply (\"{text}\"): it was helpful.",
+                    reaction.emoji
+                )
+            } else {
+                format!(
+                    "The user reacted {} to your earlier reply (\"{text}\"): it missed the mark. If it is unclear why, ask briefly; otherwise do better in similar answers.",
+                    reaction.emoji
+                )
+            };
+            state
+                .notes
+                .entry(format!("{}:{chat_id}", reaction.channel))
+                .or_default()
+                .push(note);
+        }
+        drop(state);
+        if let Some(path) = &self.log {
+            let entry = json!({
+                "at": Local::now().to_rfc3339(),
+                "channel": reaction.channel,
+                "chat_id": reply.as_ref().map_or(&reaction.chat_id, |(chat_id, _)| chat_id),
+                "message_id": reaction.message_id,
+                "sender_id": reaction.sender_id,
+                "reaction": reaction.emoji,
+                "rating": rating.map(|good| if good { "good" } else { "bad" }),
+                "reply": reply.as_ref().map(|(_, text)| text),
+            });
+            if let Err(err) = append_line(path, &entry.to_string()) {
+                warn!("could not write {}: {err}", path.display());
+            }
+        }
+    }
+
+    /// Take the feedback notes for turns in `channel:chat_id`.
+    pub fn take(&self, channel: &str, chat_id: &str) -> Vec<String> {
+        self.state
+            .lock()
+            .ok()
+            .and_then(|mut state| state.notes.remove(&format!("{channel}:{chat_id}")))
+            .unwrap_or_default()
+    }
+}
+
+fn preview(content: &str) -> String {
+    let flat = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut text: String = flat.chars().take(PREVIEW_CHARS).collect();
+    if text.len() < flat.len() {
+        text.push('…');
+    }
+    text
+}
+
+fn append_line(path: &PathBuf, line: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{line}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Feedback;
+    use crate::bus::{DeliveryReport, Reaction};
+
+    #[test]
+    fn reactions_to_replies_become_notes_for_the_chat() {
+        let feedback = Feedback {
+            state: Default::default(),
+            log: None,
+        };
+        feedback.remember(DeliveryReport {
+            channel: "telegram".to_string(),
+            chat_id: "42/7".to_string(),
+            content: "Paris is the capital of Germany.".to_string(),
+            correlation_id: None,
+            delivered: true,
+            error: None,
+            message_ids: vec!["100".to_string()],
+        });
+        let reaction = |message_id: &str, emoji: &str| Reaction {
+            channel: "telegram".to_string(),
+            chat_id: "42".to_string(),
+            message_id: message_id.to_string(),
+            sender_id: "1".to_string(),
+            emoji: emoji.to_string(),
+        };
+        feedback.record(&reaction("100", "👎"));
+        feedback.record(&reaction("100", "🐳"));
+        feedback.record(&reaction("99", "👍"));
+
+        let notes = feedback.take("telegram", "42/7");
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].contains("👎") && notes[0].contains("capital of Germany"));
+        assert!(feedback.take("telegram", "42/7").is_empty());
+    }
+}
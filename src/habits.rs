@@ -0,0 +1,218 @@
+use anyhow::{anyhow, Result};
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+
+/// Window used when reporting completion rates.
+pub const COMPLETION_WINDOW_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Habit {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "createdOn")]
+    pub created_on: NaiveDate,
+    #[serde(rename = "checkIns", default)]
+    pub check_ins: BTreeSet<NaiveDate>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HabitStats {
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    pub completed_in_window: u32,
+    pub window_days: u32,
+    pub done_today: bool,
+}
+
+impl Habit {
+    pub fn stats(&self, today: NaiveDate) -> HabitStats {
+        let window_start = std::cmp::max(
+            self.created_on,
+            today - Duration::days(COMPLETION_WINDOW_DAYS - 1),
+        );
+        let window_days = ((today - window_start).num_days() + 1).max(1) as u32;
+        let completed_in_window = self.check_ins.range(window_start..=today).count() as u32;
+        HabitStats {
+            current_streak: current_streak(&self.check_ins, today),
+            longest_streak: longest_streak(&self.check_ins),
+            completed_in_window,
+            window_days,
+            done_today: self.check_ins.contains(&today),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HabitStoreData {
+    version: i32,
+    habits: Vec<Habit>,
+}
+
+/// JSON-backed habit store at `<workspace>/habits.json`.
+pub struct HabitStore {
+    path: PathBuf,
+    pub habits: Vec<Habit>,
+}
+
+impl HabitStore {
+    pub fn new(workspace_dir: PathBuf) -> Self {
+        Self {
+            path: workspace_dir.join("habits.json"),
+            habits: Vec::new(),
+        }
+    }
+
+    pub fn load(&mut self) -> Result<()> {
+        if self.path.exists() {
+            let content = fs::read_to_string(&self.path)?;
+            let data: HabitStoreData = serde_json::from_str(&content)?;
+            self.habits = data.habits;
+        } else {
+            self.habits = Vec::new();
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let data = HabitStoreData {
+            version: 1,
+            habits: self.habits.clone(),
+        };
+        let content = serde_json::to_string_pretty(&data)?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    pub fn define(
+        &mut self,
+        name: &str,
+        description: Option<String>,
+        today: NaiveDate,
+    ) -> Result<bool> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("habit name cannot be empty"));
+        }
+        if self.find_mut(name).is_some() {
+            return Ok(false);
+        }
+        self.habits.push(Habit {
+            name: name.to_string(),
+            description: description.filter(|d| !d.trim().is_empty()),
+            created_on: today,
+            check_ins: BTreeSet::new(),
+        });
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Record a check-in for `date`. Returns false if it was already recorded.
+    pub fn check_in(&mut self, name: &str, date: NaiveDate) -> Result<bool> {
+        let habit = self
+            .find_mut(name)
+            .ok_or_else(|| anyhow!("habit '{}' not found", name.trim()))?;
+        let inserted = habit.check_ins.insert(date);
+        if date < habit.created_on {
+            habit.created_on = date;
+        }
+        if inserted {
+            self.save()?;
+        }
+        Ok(inserted)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        let key = name.trim().to_lowercase();
+        let len_before = self.habits.len();
+        self.habits.retain(|h| h.name.to_lowercase() != key);
+        let removed = self.habits.len() < len_before;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Habit> {
+        let key = name.trim().to_lowercase();
+        self.habits.iter().find(|h| h.name.to_lowercase() == key)
+    }
+
+    fn find_mut(&mut self, name: &str) -> Option<&mut Habit> {
+        let key = name.trim().to_lowercase();
+        self.habits
+            .iter_mut()
+            .find(|h| h.name.to_lowercase() == key)
+    }
+}
+
+/// Consecutive check-in days ending today, or yesterday if today is not yet
+/// checked in (the streak is still alive until the day ends).
+fn current_streak(check_ins: &BTreeSet<NaiveDate>, today: NaiveDate) -> u32 {
+    let mut day = if check_ins.contains(&today) {
+        today
+    } else {
+        today - Duration::days(1)
+    };
+    let mut streak = 0;
+    while check_ins.contains(&day) {
+        streak += 1;
+        day -= Duration::days(1);
+    }
+    streak
+}
+
+fn longest_streak(check_ins: &BTreeSet<NaiveDate>) -> u32 {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut prev: Option<NaiveDate> = None;
+    for day in check_ins {
+        run = match prev {
+            Some(p) if *day - p == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        prev = Some(*day);
+    }
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{current_streak, longest_streak};
+    use chrono::NaiveDate;
+    use std::collections::BTreeSet;
+
+    fn days(raw: &[&str]) -> BTreeSet<NaiveDate> {
+        raw.iter()
+            .map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn streak_survives_until_end_of_day() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let set = days(&["2025-03-07", "2025-03-08", "2025-03-09"]);
+        assert_eq!(current_streak(&set, today), 3);
+        let set = days(&["2025-03-07", "2025-03-08"]);
+        assert_eq!(current_streak(&set, today), 0);
+    }
+
+    #[test]
+    fn longest_streak_spans_gaps() {
+        let set = days(&[
+            "2025-01-01",
+            "2025-01-02",
+            "2025-01-05",
+            "2025-01-06",
+            "2025-01-07",
+        ]);
+        assert_eq!(longest_streak(&set), 3);
+    }
+}
@@ -0,0 +1,233 @@
+//! Conversation handoff between frontends: a session started in one chat
+//! can be continued in another, either by linking the two (both chats share
+//! one history and memory namespace) or by copying the history and memories
+//! over once. Handoffs asked for by non-admins wait until the target chat
+//! accepts them with `/handoff accept`, so nobody can attach a chat they do
+//! not control.
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Unanswered handoff requests expire after this.
+const REQUEST_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HandoffMode {
+    /// The target chat shares the source session from now on.
+    Link,
+    /// The target chat gets a one-time copy of the source session.
+    Copy,
+}
+
+impl HandoffMode {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_lowercase().as_str() {
+            "" | "link" | "share" => Some(Self::Link),
+            "copy" => Some(Self::Copy),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Handoff {
+    /// Session key (`channel:chat_id`) the conversation comes from.
+    pub source: String,
+    pub mode: HandoffMode,
+    #[serde(rename = "createdAtMs")]
+    pub created_at_ms: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HandoffData {
+    version: i32,
+    /// Handoffs keyed by target session key.
+    #[serde(default)]
+    handoffs: HashMap<String, Handoff>,
+}
+
+/// Handoffs persisted to `<data_dir>/handoffs.json`, shared between the
+/// `continue_on` tool and the agent loop.
+#[derive(Clone)]
+pub struct Handoffs {
+    path: PathBuf,
+    handoffs: Arc<Mutex<HashMap<String, Handoff>>>,
+    /// Requests awaiting `/handoff accept`, keyed by target session key.
+    requests: Arc<Mutex<HashMap<String, Handoff>>>,
+}
+
+impl Handoffs {
+    pub fn new(data_dir: &Path) -> Self {
+        let path = data_dir.join("handoffs.json");
+        let handoffs = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<HandoffData>(&content).ok())
+            .map(|data| data.handoffs)
+            .unwrap_or_default();
+        Self {
+            path,
+            handoffs: Arc::new(Mutex::new(handoffs)),
+            requests: Arc::default(),
+        }
+    }
+
+    fn save(&self, handoffs: &HashMap<String, Handoff>) -> Result<()> {
+        let data = HandoffData {
+            version: 1,
+            handoffs: handoffs.clone(),
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&data)?)?;
+        Ok(())
+    }
+
+    /// Hand `source` over to `target`. Linking follows an existing link on
+    /// the source so chains collapse onto the original session.
+    pub async fn start(&self, source: &str, target: &str, mode: HandoffMode) -> Result<String> {
+        let mut handoffs = self.handoffs.lock().await;
+        let source = match handoffs.get(source) {
+            Some(link) if link.mode == HandoffMode::Link => link.source.clone(),
+            _ => source.to_string(),
+        };
+        if source == target {
+            anyhow::bail!("That chat already continues this conversation.");
+        }
+        handoffs.insert(
+            target.to_string(),
+            Handoff {
+                source: source.clone(),
+                mode,
+                created_at_ms: Utc::now().timestamp_millis(),
+            },
+        );
+        self.save(&handoffs)?;
+        Ok(source)
+    }
+
+    /// Ask `target` to take over `source`; nothing changes until the target
+    /// chat calls [`Handoffs::accept`].
+    pub async fn request(&self, source: &str, target: &str, mode: HandoffMode) -> Result<()> {
+        if source == target {
+            anyhow::bail!("That chat already continues this conversation.");
+        }
+        self.requests.lock().await.insert(
+            target.to_string(),
+            Handoff {
+                source: source.to_string(),
+                mode,
+                created_at_ms: Utc::now().timestamp_millis(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Carry out the request waiting for `target`, returning its source and
+    /// mode, or `None` when there is none (or it expired).
+    pub async fn accept(&self, target: &str) -> Result<Option<(String, HandoffMode)>> {
+        let Some(request) = self.requests.lock().await.remove(target) else {
+            return Ok(None);
+        };
+        let age_ms = Utc::now().timestamp_millis() - request.created_at_ms;
+        if age_ms > REQUEST_TTL.as_millis() as i64 {
+            return Ok(None);
+        }
+        let source = self.start(&request.source, target, request.mode).await?;
+        Ok(Some((source, request.mode)))
+    }
+
+    /// The session key whose history and memory `session_key` uses.
+    pub async fn resolve(&self, session_key: &str) -> String {
+        match self.handoffs.lock().await.get(session_key) {
+            Some(link) if link.mode == HandoffMode::Link => link.source.clone(),
+            _ => session_key.to_string(),
+        }
+    }
+
+    /// Take a pending copy into `target`, returning the source session key.
+    pub async fn take_copy(&self, target: &str) -> Option<String> {
+        let mut handoffs = self.handoffs.lock().await;
+        if handoffs.get(target)?.mode != HandoffMode::Copy {
+            return None;
+        }
+        let handoff = handoffs.remove(target)?;
+        if let Err(err) = self.save(&handoffs) {
+            tracing::warn!("failed to save handoffs: {err}");
+        }
+        Some(handoff.source)
+    }
+
+    /// Detach `target` from its source. Returns the source it was linked to.
+    pub async fn unlink(&self, target: &str) -> Result<Option<String>> {
+        let mut handoffs = self.handoffs.lock().await;
+        let removed = handoffs.remove(target).map(|h| h.source);
+        if removed.is_some() {
+            self.save(&handoffs)?;
+        }
+        Ok(removed)
+    }
+
+    pub async fn get(&self, target: &str) -> Option<Handoff> {
+        self.handoffs.lock().await.get(target).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HandoffMode, Handoffs};
+
+    #[tokio::test]
+    async fn links_resolve_and_copies_apply_once() {
+        let dir = std::env::temp_dir().join(format!("femtobot-handoff-{}", std::process::id()));
+        let handoffs = Handoffs::new(&dir);
+        handoffs
+            .start("telegram:1", "webhook:desk", HandoffMode::Link)
+            .await
+            .unwrap();
+        assert_eq!(handoffs.resolve("webhook:desk").await, "telegram:1");
+        // A link from a linked chat points at the original session.
+        let source = handoffs
+            .start("webhook:desk", "discord:9", HandoffMode::Link)
+            .await
+            .unwrap();
+        assert_eq!(source, "telegram:1");
+        assert!(handoffs
+            .start("webhook:desk", "telegram:1", HandoffMode::Link)
+            .await
+            .is_err());
+
+        handoffs
+            .start("telegram:1", "tui:local", HandoffMode::Copy)
+            .await
+            .unwrap();
+        assert_eq!(handoffs.resolve("tui:local").await, "tui:local");
+        assert_eq!(Handoffs::new(&dir).resolve("discord:9").await, "telegram:1");
+        assert_eq!(
+            handoffs.take_copy("tui:local").await.as_deref(),
+            Some("telegram:1")
+        );
+        assert_eq!(handoffs.take_copy("tui:local").await, None);
+
+        handoffs
+            .request("telegram:2", "telegram:3", HandoffMode::Link)
+            .await
+            .unwrap();
+        assert_eq!(handoffs.resolve("telegram:3").await, "telegram:3");
+        assert_eq!(handoffs.accept("telegram:2").await.unwrap(), None);
+        assert_eq!(
+            handoffs.accept("telegram:3").await.unwrap(),
+            Some(("telegram:2".to_string(), HandoffMode::Link))
+        );
+        assert_eq!(handoffs.resolve("telegram:3").await, "telegram:2");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
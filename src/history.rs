@@ -0,0 +1,377 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+const SNIPPET_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedMessage {
+    pub ts: DateTime<Utc>,
+    pub session: String,
+    pub role: String,
+    pub content: String,
+    /// Milliseconds from the user's message to this reply (assistant entries).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Tools called while producing this reply (assistant entries).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    /// `YYYY-MM-DD` for notes, `YYYY-MM-DD HH:MM` (local) for session entries.
+    pub when: String,
+    /// Where the hit came from, e.g. `telegram:123` or `notes 2025-01-02.md`.
+    pub source: String,
+    pub snippet: String,
+}
+
+/// Append-only JSONL transcripts of every session under
+/// `<workspace>/sessions/`, searchable alongside daily notes. Sessions idle
+/// for long move to `<workspace>/archive/` as a markdown transcript plus a
+/// gzipped copy of the log, which [`HistoryLog::rehydrate`] restores.
+#[derive(Clone)]
+pub struct HistoryLog {
+    sessions_dir: PathBuf,
+    archive_dir: PathBuf,
+    notes_dir: PathBuf,
+}
+
+impl HistoryLog {
+    pub fn new(workspace_dir: &Path) -> Self {
+        Self {
+            sessions_dir: workspace_dir.join("sessions"),
+            archive_dir: workspace_dir.join("archive"),
+            notes_dir: workspace_dir.join("memory"),
+        }
+    }
+
+    /// Append with an explicit timestamp (used when importing old chats).
+    pub fn append_at(
+        &self,
+        session: &str,
+        role: &str,
+        content: &str,
+        ts: DateTime<Utc>,
+    ) -> Result<()> {
+        self.append_entry(&LoggedMessage {
+            ts,
+            session: session.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            latency_ms: None,
+            tools: Vec::new(),
+        })
+    }
+
+    pub fn append_entry(&self, entry: &LoggedMessage) -> Result<()> {
+        if entry.content.trim().is_empty() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.sessions_dir)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.session_file(&entry.session))?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    /// Every logged message of every session, archived ones included, in
+    /// file order.
+    pub fn entries(&self) -> Vec<LoggedMessage> {
+        self.sessions().into_iter().flatten().collect()
+    }
+
+    /// Logged messages per session: live sessions, then archived ones.
+    fn sessions(&self) -> Vec<Vec<LoggedMessage>> {
+        let live = list_files(&self.sessions_dir, "jsonl")
+            .into_iter()
+            .map(|path| read_jsonl(&path));
+        let archived = list_files(&self.archive_dir, "gz")
+            .into_iter()
+            .map(|path| read_gz(&path).unwrap_or_default());
+        live.chain(archived).collect()
+    }
+
+    /// Transcripts of live sessions whose last message is older than `cutoff`.
+    pub fn inactive_sessions(&self, cutoff: DateTime<Utc>) -> Vec<Vec<LoggedMessage>> {
+        list_files(&self.sessions_dir, "jsonl")
+            .into_iter()
+            .map(|path| read_jsonl(&path))
+            .filter(|entries| entries.last().is_some_and(|last| last.ts < cutoff))
+            .collect()
+    }
+
+    /// Move a session's transcript to the archive: a readable markdown file
+    /// headed by `summary`, and the gzipped log for rehydration. Returns the
+    /// markdown path.
+    pub fn archive(&self, entries: &[LoggedMessage], summary: &str) -> Result<PathBuf> {
+        let Some(session) = entries.first().map(|e| e.session.clone()) else {
+            return Err(anyhow::anyhow!("nothing to archive"));
+        };
+        fs::create_dir_all(&self.archive_dir)?;
+        let (gz_path, md_path) = self.archive_files(&session);
+        let mut encoder = GzEncoder::new(fs::File::create(&gz_path)?, Compression::default());
+        for entry in entries {
+            writeln!(encoder, "{}", serde_json::to_string(entry)?)?;
+        }
+        encoder.finish()?;
+        fs::write(&md_path, archive_markdown(&session, entries, summary))?;
+        fs::remove_file(self.session_file(&session))?;
+        Ok(md_path)
+    }
+
+    pub fn is_archived(&self, session: &str) -> bool {
+        self.archive_files(session).0.exists()
+    }
+
+    /// Bring an archived session back to `sessions/`, ahead of anything
+    /// logged since, and return its transcript.
+    pub fn rehydrate(&self, session: &str) -> Result<Vec<LoggedMessage>> {
+        let (gz_path, md_path) = self.archive_files(session);
+        let mut entries = read_gz(&gz_path)?;
+        let live = self.session_file(session);
+        entries.extend(read_jsonl(&live));
+        fs::create_dir_all(&self.sessions_dir)?;
+        let mut content = String::new();
+        for entry in &entries {
+            content.push_str(&serde_json::to_string(entry)?);
+            content.push('\n');
+        }
+        fs::write(&live, content)?;
+        fs::remove_file(&gz_path)?;
+        let _ = fs::remove_file(&md_path);
+        Ok(entries)
+    }
+
+    fn archive_files(&self, session: &str) -> (PathBuf, PathBuf) {
+        let stem = sanitize_file_stem(session);
+        (
+            self.archive_dir.join(format!("{stem}.jsonl.gz")),
+            self.archive_dir.join(format!("{stem}.md")),
+        )
+    }
+
+    /// Case-insensitive full-text search across session transcripts and
+    /// daily notes. Every whitespace-separated term must appear. Newest first.
+    /// With `session`, only that session's transcript is searched (daily
+    /// notes are shared, so they are left out).
+    pub fn search(&self, query: &str, limit: usize, session: Option<&str>) -> Vec<SearchHit> {
+        let terms: Vec<String> = query.split_whitespace().map(|t| t.to_lowercase()).collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let mut hits: Vec<(String, SearchHit)> = Vec::new();
+
+        for transcript in self.sessions() {
+            for entry in transcript {
+                if session.is_some_and(|session| session != entry.session) {
+                    continue;
+                }
+                if matches_all(&entry.content, &terms) {
+                    let local = entry.ts.with_timezone(&Local);
+                    hits.push((
+                        entry.ts.to_rfc3339(),
+                        SearchHit {
+                            when: local.format("%Y-%m-%d %H:%M").to_string(),
+                            source: format!("{} ({})", entry.session, entry.role),
+                            snippet: snippet(&entry.content, &terms[0]),
+                        },
+                    ));
+                }
+            }
+        }
+
+        let notes = if session.is_some() {
+            Vec::new()
+        } else {
+            list_files(&self.notes_dir, "md")
+        };
+        for path in notes {
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let date = name.trim_end_matches(".md").to_string();
+            for paragraph in content.split("\n\n") {
+                if matches_all(paragraph, &terms) {
+                    hits.push((
+                        date.clone(),
+                        SearchHit {
+                            when: date.clone(),
+                            source: format!("notes {name}"),
+                            snippet: snippet(paragraph, &terms[0]),
+                        },
+                    ));
+                }
+            }
+        }
+
+        hits.sort_by(|a, b| b.0.cmp(&a.0));
+        hits.into_iter().take(limit).map(|(_, hit)| hit).collect()
+    }
+
+    fn session_file(&self, session: &str) -> PathBuf {
+        self.sessions_dir
+            .join(format!("{}.jsonl", sanitize_file_stem(session)))
+    }
+}
+
+fn read_jsonl(path: &Path) -> Vec<LoggedMessage> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<LoggedMessage>(line).ok())
+        .collect()
+}
+
+fn read_gz(path: &Path) -> Result<Vec<LoggedMessage>> {
+    let mut content = String::new();
+    GzDecoder::new(fs::File::open(path)?).read_to_string(&mut content)?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<LoggedMessage>(line).ok())
+        .collect())
+}
+
+fn archive_markdown(session: &str, entries: &[LoggedMessage], summary: &str) -> String {
+    let mut out = format!(
+        "# Archived chat {session}\n\n## Summary\n\n{}\n",
+        summary.trim()
+    );
+    let mut day = String::new();
+    for entry in entries {
+        let local = entry.ts.with_timezone(&Local);
+        let date = local.format("%Y-%m-%d").to_string();
+        if date != day {
+            out.push_str(&format!("\n## {date}\n"));
+            day = date;
+        }
+        out.push_str(&format!(
+            "\n**{}** ({}): {}\n",
+            entry.role,
+            local.format("%H:%M"),
+            entry.content.trim()
+        ));
+    }
+    out
+}
+
+fn list_files(dir: &Path, ext: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == ext))
+        .collect()
+}
+
+fn matches_all(text: &str, terms: &[String]) -> bool {
+    let lower = text.to_lowercase();
+    terms.iter().all(|t| lower.contains(t.as_str()))
+}
+
+/// Return up to `SNIPPET_CHARS` characters of `text` centred on `term`.
+fn snippet(text: &str, term: &str) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let chars: Vec<char> = flat.chars().collect();
+    if chars.len() <= SNIPPET_CHARS {
+        return flat;
+    }
+    let lower: Vec<char> = flat.to_lowercase().chars().collect();
+    let needle: Vec<char> = term.chars().collect();
+    let pos = if needle.is_empty() || lower.len() != chars.len() {
+        0
+    } else {
+        lower
+            .windows(needle.len())
+            .position(|w| w == needle.as_slice())
+            .unwrap_or(0)
+    };
+    let start = pos.saturating_sub(SNIPPET_CHARS / 3);
+    let end = (start + SNIPPET_CHARS).min(chars.len());
+    let mut out: String = chars[start..end].iter().collect();
+    if start > 0 {
+        out.insert(0, '…');
+    }
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+pub fn sanitize_file_stem(raw: &str) -> String {
+    raw.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HistoryLog;
+    use chrono::{Duration, Utc};
+
+    #[test]
+    fn archives_idle_sessions_and_restores_them() {
+        let dir = std::env::temp_dir().join(format!("femtobot-archive-{}", std::process::id()));
+        let log = HistoryLog::new(&dir);
+        let old = Utc::now() - Duration::days(40);
+        log.append_at("telegram:1", "user", "plan the trip", old)
+            .unwrap();
+        log.append_at("telegram:2", "user", "still here", Utc::now())
+            .unwrap();
+
+        let idle = log.inactive_sessions(Utc::now() - Duration::days(30));
+        assert_eq!(idle.len(), 1);
+        let md = log.archive(&idle[0], "Trip planning.").unwrap();
+        assert!(std::fs::read_to_string(md)
+            .unwrap()
+            .contains("plan the trip"));
+        assert!(log.is_archived("telegram:1"));
+        assert_eq!(log.entries().len(), 2);
+
+        let restored = log.rehydrate("telegram:1").unwrap();
+        assert_eq!(restored[0].content, "plan the trip");
+        assert!(!log.is_archived("telegram:1"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn scoped_search_cannot_see_other_chats() {
+        let dir = std::env::temp_dir().join(format!("femtobot-search-{}", uuid::Uuid::new_v4()));
+        let log = HistoryLog::new(&dir);
+        log.append_at("telegram:1", "user", "my locker code is 1234", Utc::now())
+            .unwrap();
+        log.append_at("telegram:2", "user", "the locker is broken", Utc::now())
+            .unwrap();
+
+        let own = log.search("locker", 10, Some("telegram:2"));
+        assert_eq!(own.len(), 1);
+        assert!(own[0].source.starts_with("telegram:2"));
+        assert!(log.search("code 1234", 10, Some("telegram:2")).is_empty());
+        assert_eq!(log.search("locker", 10, None).len(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,516 @@
+//! `femtobot import`: seed session history and memory from another
+//! assistant's export. Each imported conversation gets a short summary,
+//! logged as a `summary` entry in the `import-<format>` session transcript
+//! (dated like the original) and stored as a vector memory; facts are
+//! extracted and consolidated the same way as after live turns.
+
+use crate::config::AppConfig;
+use crate::history::HistoryLog;
+use crate::memory::client::{ChatMessage, OpenRouterClient};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Messages handed to the fact extractor at a time.
+const EXTRACTION_WINDOW: usize = 30;
+/// Transcript characters sent to the summarizer.
+const SUMMARY_INPUT_CHARS: usize = 12_000;
+
+const SUMMARY_PROMPT: &str = "Summarize this conversation between a user and an assistant in two or three sentences. Focus on what it shows about the user: goals, decisions, preferences, people and ongoing projects. Write in the third person (\"The user ...\") and reply with the summary only.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// `conversations.json` from a ChatGPT data export.
+    ChatGpt,
+    /// `result.json` from Telegram Desktop (one chat or a full export).
+    Telegram,
+    /// A markdown file or a directory of them.
+    Markdown,
+}
+
+impl ImportFormat {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_lowercase().as_str() {
+            "chatgpt" | "openai" => Some(Self::ChatGpt),
+            "telegram" | "tg" => Some(Self::Telegram),
+            "markdown" | "md" | "notes" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ChatGpt => "chatgpt",
+            Self::Telegram => "telegram",
+            Self::Markdown => "markdown",
+        }
+    }
+
+    /// Guess the format from the path and, for JSON, its shape.
+    fn detect(path: &Path) -> Result<Self> {
+        if path.is_dir() || path.extension().is_some_and(|ext| ext == "md") {
+            return Ok(Self::Markdown);
+        }
+        let value = read_json(path)?;
+        if value
+            .as_array()
+            .and_then(|items| items.first())
+            .is_some_and(|first| first.get("mapping").is_some())
+        {
+            return Ok(Self::ChatGpt);
+        }
+        if value.get("messages").is_some() || value.pointer("/chats/list").is_some() {
+            return Ok(Self::Telegram);
+        }
+        Err(anyhow!(
+            "could not detect the format of {}; pass --format chatgpt|telegram|markdown",
+            path.display()
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    pub title: String,
+    pub started: Option<DateTime<Utc>>,
+    pub messages: Vec<ChatMessage>,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub conversations: usize,
+    pub messages: usize,
+    pub summaries: usize,
+    pub facts: usize,
+}
+
+pub struct ImportOptions {
+    pub format: Option<ImportFormat>,
+    pub dry_run: bool,
+    /// Only the newest N conversations.
+    pub limit: Option<usize>,
+}
+
+pub async fn run(cfg: &AppConfig, path: &Path, opts: ImportOptions) -> Result<ImportReport> {
+    let format = match opts.format {
+        Some(format) => format,
+        None => ImportFormat::detect(path)?,
+    };
+    let mut conversations = load(path, format)?;
+    conversations.retain(|conv| !conv.messages.is_empty());
+    conversations.sort_by_key(|conv| conv.started);
+    if let Some(limit) = opts.limit {
+        let skip = conversations.len().saturating_sub(limit);
+        conversations.drain(..skip);
+    }
+
+    let mut report = ImportReport {
+        conversations: conversations.len(),
+        messages: conversations.iter().map(|c| c.messages.len()).sum(),
+        ..Default::default()
+    };
+    if opts.dry_run {
+        for conv in &conversations {
+            println!(
+                "{}  {} ({} messages)",
+                conv.started
+                    .map(|ts| ts.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| "----------".to_string()),
+                conv.title,
+                conv.messages.len()
+            );
+        }
+        return Ok(report);
+    }
+
+    let client = OpenRouterClient::from_config(cfg).ok();
+    if client.is_none() {
+        println!("No provider configured: summaries fall back to excerpts.");
+    }
+    let (vector, extractor, consolidator) = crate::agent::init_vector_memory(cfg);
+    if vector.is_none() {
+        println!("Vector memory is disabled: importing history summaries only.");
+    }
+    let history = HistoryLog::new(&cfg.workspace_dir);
+    let session = format!("import-{}", format.as_str());
+
+    for (idx, conv) in conversations.iter().enumerate() {
+        println!("[{}/{}] {}", idx + 1, conversations.len(), conv.title);
+        let summary = match &client {
+            Some(client) => summarize(client, &cfg.memory_extraction_model, conv)
+                .await
+                .unwrap_or_else(|_| excerpt(conv)),
+            None => excerpt(conv),
+        };
+        let when = conv.started.unwrap_or_else(Utc::now);
+        history.append_at(
+            &session,
+            "summary",
+            &format!("{}: {summary}", conv.title),
+            when,
+        )?;
+        report.summaries += 1;
+
+        let Some(vector) = &vector else {
+            continue;
+        };
+        let metadata = HashMap::from([
+            ("source".to_string(), json!("import")),
+            ("imported_from".to_string(), json!(format.as_str())),
+            ("kind".to_string(), json!("conversation_summary")),
+        ]);
+        let memory = format!(
+            "Past conversation \"{}\" ({}): {summary}",
+            conv.title,
+            when.format("%Y-%m-%d")
+        );
+        if let Err(err) = vector.add(&memory, metadata, None).await {
+            println!("  summary not stored in memory: {err}");
+        }
+        if let (Some(extractor), Some(consolidator)) = (&extractor, &consolidator) {
+            for window in conv.messages.chunks(EXTRACTION_WINDOW) {
+                let facts = extractor.extract(window).await;
+                report.facts += facts.len();
+                let _ = consolidator.consolidate(facts, "default").await;
+            }
+        }
+    }
+    Ok(report)
+}
+
+pub fn load(path: &Path, format: ImportFormat) -> Result<Vec<Conversation>> {
+    match format {
+        ImportFormat::ChatGpt => Ok(parse_chatgpt(&read_json(path)?)),
+        ImportFormat::Telegram => Ok(parse_telegram(&read_json(path)?)),
+        ImportFormat::Markdown => load_markdown(path),
+    }
+}
+
+fn read_json(path: &Path) -> Result<Value> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&raw).with_context(|| format!("{} is not valid JSON", path.display()))
+}
+
+async fn summarize(client: &OpenRouterClient, model: &str, conv: &Conversation) -> Result<String> {
+    let mut transcript = String::new();
+    for msg in &conv.messages {
+        transcript.push_str(&format!("{}: {}\n", msg.role, msg.content));
+        if transcript.len() > SUMMARY_INPUT_CHARS {
+            break;
+        }
+    }
+    let mut cut = transcript.len().min(SUMMARY_INPUT_CHARS);
+    while !transcript.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    transcript.truncate(cut);
+    let summary = client
+        .chat_completion(
+            model,
+            vec![
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: SUMMARY_PROMPT.to_string(),
+                },
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: format!("Title: {}\n\n{transcript}", conv.title),
+                },
+            ],
+            200,
+            0.2,
+            None,
+        )
+        .await?;
+    let summary = summary.trim();
+    if summary.is_empty() {
+        return Err(anyhow!("empty summary"));
+    }
+    Ok(summary.to_string())
+}
+
+/// Fallback summary: the opening user message.
+fn excerpt(conv: &Conversation) -> String {
+    let first = conv
+        .messages
+        .iter()
+        .find(|m| m.role == "user")
+        .unwrap_or(&conv.messages[0]);
+    let text: String = first
+        .content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let short: String = text.chars().take(200).collect();
+    if short.len() < text.len() {
+        format!("Started with: {short}…")
+    } else {
+        format!("Started with: {short}")
+    }
+}
+
+fn message(role: &str, content: &str) -> Option<ChatMessage> {
+    let content = content.trim();
+    (!content.is_empty()).then(|| ChatMessage {
+        role: role.to_string(),
+        content: content.to_string(),
+    })
+}
+
+fn unix_time(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    let secs = value?.as_f64()?;
+    Utc.timestamp_opt(secs as i64, 0).single()
+}
+
+/// ChatGPT stores each conversation as a tree of message nodes; the visible
+/// thread is the path from `current_node` back to the root.
+fn parse_chatgpt(value: &Value) -> Vec<Conversation> {
+    let Some(items) = value.as_array() else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for item in items {
+        let Some(mapping) = item.get("mapping").and_then(Value::as_object) else {
+            continue;
+        };
+        let mut thread = Vec::new();
+        let mut node_id = item.get("current_node").and_then(Value::as_str);
+        while let Some(id) = node_id {
+            let Some(node) = mapping.get(id) else {
+                break;
+            };
+            thread.push(node);
+            node_id = node.get("parent").and_then(Value::as_str);
+            if thread.len() > mapping.len() {
+                break;
+            }
+        }
+        thread.reverse();
+        let messages = thread
+            .into_iter()
+            .filter_map(|node| {
+                let msg = node.get("message")?;
+                let role = msg.pointer("/author/role").and_then(Value::as_str)?;
+                if !matches!(role, "user" | "assistant") {
+                    return None;
+                }
+                let text = msg
+                    .pointer("/content/parts")
+                    .and_then(Value::as_array)?
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                message(role, &text)
+            })
+            .collect();
+        out.push(Conversation {
+            title: item
+                .get("title")
+                .and_then(Value::as_str)
+                .filter(|t| !t.trim().is_empty())
+                .unwrap_or("Untitled conversation")
+                .to_string(),
+            started: unix_time(item.get("create_time")),
+            messages,
+        });
+    }
+    out
+}
+
+/// A single-chat export, or a full account export with `chats.list`.
+fn parse_telegram(value: &Value) -> Vec<Conversation> {
+    let owner = value
+        .pointer("/personal_information/user_id")
+        .and_then(Value::as_i64)
+        .map(|id| format!("user{id}"));
+    match value.pointer("/chats/list").and_then(Value::as_array) {
+        Some(chats) => chats
+            .iter()
+            .filter_map(|chat| telegram_chat(chat, owner.as_deref()))
+            .collect(),
+        None => telegram_chat(value, owner.as_deref()).into_iter().collect(),
+    }
+}
+
+fn telegram_chat(chat: &Value, owner: Option<&str>) -> Option<Conversation> {
+    let entries: Vec<&Value> = chat
+        .get("messages")?
+        .as_array()?
+        .iter()
+        .filter(|m| m.get("type").and_then(Value::as_str) == Some("message"))
+        .collect();
+    let sender = |m: &Value| {
+        m.get("from_id")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    // Without account info, the most active sender is taken to be the user.
+    let owner = match owner {
+        Some(owner) => owner.to_string(),
+        None => {
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for entry in &entries {
+                *counts.entry(sender(entry)).or_default() += 1;
+            }
+            counts.into_iter().max_by_key(|(_, n)| *n)?.0
+        }
+    };
+    let is_group = !matches!(
+        chat.get("type").and_then(Value::as_str),
+        Some("personal_chat" | "bot_chat" | "saved_messages") | None
+    );
+    let messages = entries
+        .iter()
+        .filter_map(|entry| {
+            let text = telegram_text(entry.get("text")?);
+            if is_group {
+                let name = entry
+                    .get("from")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Someone");
+                return message("user", &format!("[{name}]: {text}"));
+            }
+            let role = if sender(entry) == owner {
+                "user"
+            } else {
+                "assistant"
+            };
+            message(role, &text)
+        })
+        .collect();
+    let started = entries
+        .first()
+        .and_then(|entry| entry.get("date").and_then(Value::as_str))
+        .and_then(|date| NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S").ok())
+        .map(|naive| naive.and_utc());
+    Some(Conversation {
+        title: chat
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or("Telegram chat")
+            .to_string(),
+        started,
+        messages,
+    })
+}
+
+/// Text is a string, or an array of strings and `{type, text}` entities.
+fn telegram_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                Value::String(text) => Some(text.as_str()),
+                other => other.get("text").and_then(Value::as_str),
+            })
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+/// Every note becomes a conversation of its paragraphs, all from the user.
+fn load_markdown(path: &Path) -> Result<Vec<Conversation>> {
+    let mut files = Vec::new();
+    collect_markdown(path, &mut files)?;
+    let mut out = Vec::new();
+    for file in files {
+        let raw = std::fs::read_to_string(&file)
+            .with_context(|| format!("failed to read {}", file.display()))?;
+        let body = crate::memory::vault::strip_frontmatter(&raw);
+        let messages = body
+            .split("\n\n")
+            .filter_map(|para| message("user", para))
+            .collect();
+        let started = std::fs::metadata(&file)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .map(DateTime::<Utc>::from);
+        out.push(Conversation {
+            title: file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            started,
+            messages,
+        });
+    }
+    Ok(out)
+}
+
+fn collect_markdown(path: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    if path.is_file() {
+        out.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(path)
+        .with_context(|| format!("failed to read {}", path.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect::<Vec<_>>();
+    entries.sort();
+    for entry in entries {
+        let hidden = entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if entry.is_dir() {
+            collect_markdown(&entry, out)?;
+        } else if entry.extension().is_some_and(|ext| ext == "md") {
+            out.push(entry);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_chatgpt, parse_telegram};
+    use serde_json::json;
+
+    #[test]
+    fn parses_chatgpt_and_telegram_exports() {
+        let chatgpt = json!([{
+            "title": "Trip planning",
+            "create_time": 1700000000.5,
+            "current_node": "c",
+            "mapping": {
+                "root": { "message": null, "parent": null },
+                "a": { "parent": "root", "message": { "author": { "role": "user" }, "content": { "parts": ["I'm vegetarian, find restaurants in Lisbon"] } } },
+                "stale": { "parent": "a", "message": { "author": { "role": "assistant" }, "content": { "parts": ["regenerated away"] } } },
+                "c": { "parent": "a", "message": { "author": { "role": "assistant" }, "content": { "parts": ["Here are three."] } } }
+            }
+        }]);
+        let convs = parse_chatgpt(&chatgpt);
+        assert_eq!(convs[0].title, "Trip planning");
+        let roles: Vec<&str> = convs[0].messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["user", "assistant"]);
+        assert_eq!(convs[0].messages[1].content, "Here are three.");
+
+        let telegram = json!({
+            "name": "Old bot",
+            "type": "bot_chat",
+            "messages": [
+                { "type": "message", "date": "2023-05-01T10:00:00", "from_id": "user1", "text": "remind me about the dentist" },
+                { "type": "message", "date": "2023-05-01T10:00:05", "from_id": "user9", "text": ["Done: ", { "type": "bold", "text": "dentist" }] },
+                { "type": "service", "action": "pin_message" },
+                { "type": "message", "date": "2023-05-02T08:00:00", "from_id": "user1", "text": "thanks" }
+            ]
+        });
+        let convs = parse_telegram(&telegram);
+        assert_eq!(convs[0].messages.len(), 3);
+        assert_eq!(convs[0].messages[0].role, "user");
+        assert_eq!(convs[0].messages[1].role, "assistant");
+        assert_eq!(convs[0].messages[1].content, "Done: dentist");
+        assert_eq!(
+            convs[0].started.unwrap().to_rfc3339(),
+            "2023-05-01T10:00:00+00:00"
+        );
+    }
+}
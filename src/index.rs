@@ -0,0 +1,224 @@
+//! `femtobot index rebuild`: chunk and embed the workspace files matching
+//! `index.globs` into the `docs` memory namespace, so notes written outside
+//! chat are searchable next to extracted facts. Every chunk records its
+//! file's path and content hash; later runs re-embed only changed files and
+//! drop the chunks of deleted ones.
+
+use crate::config::AppConfig;
+use crate::memory::vector_store::{MemorySelector, VectorMemoryStore};
+use crate::tools::summarize::chunk_text;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+pub const DOCS_NAMESPACE: &str = "docs";
+/// Characters per embedded chunk.
+const CHUNK_CHARS: usize = 1_500;
+/// Files larger than this are skipped; they are rarely notes.
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+#[derive(Debug, Default)]
+pub struct IndexStats {
+    pub files: usize,
+    pub unchanged: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub chunks_added: usize,
+    pub chunks_removed: usize,
+    /// Files that could not be read or embedded, with the reason.
+    pub failed: Vec<String>,
+}
+
+impl IndexStats {
+    pub fn summary(&self) -> String {
+        let mut out = format!(
+            "{} file(s) matched: {} unchanged, {} (re)indexed, {} removed; {} chunk(s) added, {} removed",
+            self.files,
+            self.unchanged,
+            self.updated,
+            self.removed,
+            self.chunks_added,
+            self.chunks_removed
+        );
+        for failure in &self.failed {
+            out.push_str(&format!("\nfailed: {failure}"));
+        }
+        out
+    }
+}
+
+/// Bring the `docs` namespace in line with the workspace. `full` re-embeds
+/// every file even when its hash is unchanged.
+pub async fn rebuild(cfg: &AppConfig, store: &VectorMemoryStore, full: bool) -> Result<IndexStats> {
+    let globs = cfg
+        .index_globs
+        .iter()
+        .map(|glob| glob_regex(glob))
+        .collect::<Result<Vec<_>>>()?;
+    let files = scan(&cfg.workspace_dir, &globs, &cfg.index_exclude)?;
+
+    // Chunks already stored, grouped by file.
+    let mut indexed: HashMap<String, (String, Vec<String>)> = HashMap::new();
+    let selector = MemorySelector {
+        namespace: Some(DOCS_NAMESPACE.to_string()),
+        ..Default::default()
+    };
+    for item in store.select(&selector).await? {
+        let field = |key: &str| {
+            item.metadata
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let entry = indexed.entry(field("path")).or_default();
+        entry.0 = field("hash");
+        entry.1.push(item.id.clone());
+    }
+
+    let mut stats = IndexStats {
+        files: files.len(),
+        ..Default::default()
+    };
+    for (rel, text) in &files {
+        let hash = hex::encode(Sha256::digest(text.as_bytes()));
+        let previous = indexed.remove(rel);
+        if !full && previous.as_ref().is_some_and(|(old, _)| *old == hash) {
+            stats.unchanged += 1;
+            continue;
+        }
+        if let Some((_, ids)) = previous {
+            stats.chunks_removed += store.delete_many(ids).await?;
+        }
+        match embed_file(store, rel, &hash, text).await {
+            Ok(added) => {
+                stats.updated += 1;
+                stats.chunks_added += added;
+            }
+            Err(err) => stats.failed.push(format!("{rel}: {err}")),
+        }
+    }
+    for (_, (_, ids)) in indexed {
+        stats.removed += 1;
+        stats.chunks_removed += store.delete_many(ids).await?;
+    }
+    Ok(stats)
+}
+
+/// Store `text` as chunks of `rel`. On failure the chunks added so far are
+/// removed again, so the file is retried on the next run.
+async fn embed_file(store: &VectorMemoryStore, rel: &str, hash: &str, text: &str) -> Result<usize> {
+    let mut added = Vec::new();
+    for (index, chunk) in chunk_text(text, CHUNK_CHARS).iter().enumerate() {
+        let metadata = HashMap::from([
+            ("kind".to_string(), json!("doc")),
+            ("path".to_string(), json!(rel)),
+            ("hash".to_string(), json!(hash)),
+            ("chunk".to_string(), json!(index)),
+        ]);
+        let content = format!("[{rel}]\n{chunk}");
+        match store.add(&content, metadata, Some(DOCS_NAMESPACE)).await {
+            Ok(item) => added.push(item.id),
+            Err(err) => {
+                store.delete_many(added).await?;
+                return Err(err);
+            }
+        }
+    }
+    Ok(added.len())
+}
+
+/// Workspace-relative `/`-separated paths of matching text files, with
+/// their contents.
+fn scan(workspace: &Path, globs: &[Regex], exclude: &[String]) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![workspace.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let Ok(rel) = path.strip_prefix(workspace) else {
+                continue;
+            };
+            let rel = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if rel.split('/').any(|part| part.starts_with('.')) || is_excluded(&rel, exclude) {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file()
+                && globs.iter().any(|glob| glob.is_match(&rel))
+                && entry.metadata()?.len() <= MAX_FILE_BYTES
+            {
+                // Binary files that happen to match are skipped.
+                if let Ok(text) = fs::read_to_string(&path) {
+                    if !text.trim().is_empty() {
+                        files.insert(rel, text);
+                    }
+                }
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn is_excluded(rel: &str, exclude: &[String]) -> bool {
+    exclude.iter().any(|prefix| {
+        let prefix = prefix.trim_matches('/');
+        rel == prefix || rel.starts_with(&format!("{prefix}/"))
+    })
+}
+
+/// `*` matches within a path segment, `**/` any number of directories and
+/// `?` one character.
+fn glob_regex(glob: &str) -> Result<Regex> {
+    let mut pattern = String::from("^");
+    let mut rest = glob.trim().trim_start_matches("./");
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("**/") {
+            pattern.push_str("(?:.*/)?");
+            rest = after;
+            continue;
+        }
+        if let Some(after) = rest.strip_prefix("**") {
+            pattern.push_str(".*");
+            rest = after;
+            continue;
+        }
+        match c {
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    pattern.push('$');
+    Regex::new(&pattern).map_err(|err| anyhow!("invalid index glob '{glob}': {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_regex;
+
+    #[test]
+    fn globs_match_segments_and_nested_dirs() {
+        let md = glob_regex("**/*.md").unwrap();
+        assert!(md.is_match("MEMORY.md"));
+        assert!(md.is_match("notes/2024/trip.md"));
+        assert!(!md.is_match("notes/trip.md.bak"));
+        let top = glob_regex("notes/*.txt").unwrap();
+        assert!(top.is_match("notes/a.txt"));
+        assert!(!top.is_match("notes/sub/a.txt"));
+    }
+}
@@ -0,0 +1,410 @@
+//! IRC channel over a plain or TLS socket. Private messages and channel
+//! messages addressed to the bot's nick (`femtobot: hi`) become inbound
+//! turns; replies are sent as PRIVMSG lines wrapped to fit IRC's 512-byte
+//! line limit. Private chats use the sender's nick as `chat_id`, channels
+//! their name.
+//!
+//! Nicks are not an identity: anyone can take any free nick. Senders are
+//! identified by their services account (IRCv3 `account-tag`) as
+//! `irc:<account>`; senders who are not logged in get `irc:~<nick>`, which
+//! no admin or `allow_from` entry can match.
+
+use crate::bus::{InboundMessage, MessageBus, MessageMeta};
+use crate::channel::{self, Channel};
+use crate::config::AppConfig;
+use crate::ratelimit::RateLimiter;
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+use tracing::{info, warn};
+
+const RETRY_DELAY: Duration = Duration::from_secs(15);
+/// Payload bytes per PRIVMSG; the server prepends our prefix and the
+/// command to the 512-byte line, so stay well below it.
+const MAX_PAYLOAD_BYTES: usize = 400;
+/// Reply lines sent per message; longer replies are cut with a note.
+const MAX_LINES_PER_REPLY: usize = 15;
+
+trait IrcStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> IrcStream for T {}
+
+pub struct IrcChannel {
+    cfg: AppConfig,
+}
+
+impl IrcChannel {
+    pub fn new(cfg: AppConfig) -> Self {
+        Self { cfg }
+    }
+}
+
+impl Channel for IrcChannel {
+    fn name(&self) -> &'static str {
+        "irc"
+    }
+
+    fn start(self: Arc<Self>, bus: MessageBus) -> JoinHandle<()> {
+        channel::spawn(self.name(), serve(self, bus))
+    }
+}
+
+async fn serve(channel: Arc<IrcChannel>, bus: MessageBus) -> Result<()> {
+    let cfg = &channel.cfg;
+    let (line_tx, mut line_rx) = unbounded_channel::<String>();
+    spawn_outbound_forwarder(
+        channel.clone(),
+        line_tx,
+        bus.clone(),
+        RateLimiter::for_channel(cfg, "irc"),
+    );
+    loop {
+        match run_session(cfg, &bus, &mut line_rx).await {
+            Ok(()) => warn!("irc connection closed by server"),
+            Err(err) => warn!("irc connection failed: {err}"),
+        }
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+async fn connect(cfg: &AppConfig) -> Result<Box<dyn IrcStream>> {
+    let server = cfg.irc_server.trim();
+    let (host, port) = match server.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse::<u16>()
+                .map_err(|_| anyhow!("invalid irc port in {server}"))?,
+        ),
+        None => (server, if cfg.irc_tls { 6697 } else { 6667 }),
+    };
+    let tcp = TcpStream::connect((host, port)).await?;
+    if !cfg.irc_tls {
+        return Ok(Box::new(tcp));
+    }
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let tls =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    let name = ServerName::try_from(host.to_string())?;
+    let stream = TlsConnector::from(Arc::new(tls)).connect(name, tcp).await?;
+    Ok(Box::new(stream))
+}
+
+async fn run_session(
+    cfg: &AppConfig,
+    bus: &MessageBus,
+    outbound: &mut UnboundedReceiver<String>,
+) -> Result<()> {
+    let stream = connect(cfg).await?;
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    let mut nick = cfg.irc_nick.trim().to_string();
+    if let Some(password) = &cfg.irc_password {
+        send_line(&mut writer, &format!("PASS {password}")).await?;
+    }
+    send_line(&mut writer, "CAP REQ :account-tag").await?;
+    send_line(&mut writer, &format!("NICK {nick}")).await?;
+    send_line(&mut writer, &format!("USER {nick} 0 * :femtobot")).await?;
+    let filter = SenderFilter::new(&cfg.irc_allow_from);
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    return Ok(());
+                };
+                let Some(msg) = parse_line(&line) else {
+                    continue;
+                };
+                match msg.command {
+                    "PING" => {
+                        let token = msg.params.last().copied().unwrap_or_default();
+                        send_line(&mut writer, &format!("PONG :{token}")).await?;
+                    }
+                    // Account tags granted or refused: finish registration.
+                    "CAP" if msg.params.iter().any(|p| matches!(*p, "ACK" | "NAK")) => {
+                        send_line(&mut writer, "CAP END").await?;
+                    }
+                    // Registered: join the configured channels.
+                    "001" => {
+                        info!("irc connected to {} as {nick}", cfg.irc_server);
+                        for channel in &cfg.irc_channels {
+                            send_line(&mut writer, &format!("JOIN {}", channel.trim())).await?;
+                        }
+                    }
+                    // Nick in use.
+                    "433" => {
+                        nick.push('_');
+                        send_line(&mut writer, &format!("NICK {nick}")).await?;
+                    }
+                    "PRIVMSG" => {
+                        let Some(inbound) = to_inbound(&msg, &nick) else {
+                            continue;
+                        };
+                        if filter.allows(&inbound) {
+                            bus.publish_inbound(inbound).await;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Some(line) = outbound.recv() => {
+                send_line(&mut writer, &line).await?;
+            }
+        }
+    }
+}
+
+async fn send_line<W: AsyncWrite + Unpin>(writer: &mut W, line: &str) -> Result<()> {
+    writer.write_all(line.as_bytes()).await?;
+    writer.write_all(b"\r\n").await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+#[derive(Debug)]
+struct IrcMessage<'a> {
+    /// Services account from the `account` tag, when the sender is logged in.
+    account: Option<&'a str>,
+    /// Nick part of the `nick!user@host` prefix.
+    nick: Option<&'a str>,
+    command: &'a str,
+    params: Vec<&'a str>,
+}
+
+fn parse_line(line: &str) -> Option<IrcMessage<'_>> {
+    let mut rest = line.trim_end_matches(['\r', '\n']);
+    // Of the IRCv3 message tags only `account` is used.
+    let mut account = None;
+    if let Some(tagged) = rest.strip_prefix('@') {
+        let (tags, tail) = tagged.split_once(' ')?;
+        account = tags
+            .split(';')
+            .find_map(|tag| tag.strip_prefix("account="))
+            .filter(|name| !name.is_empty() && *name != "*");
+        rest = tail;
+    }
+    let mut nick = None;
+    if let Some(stripped) = rest.strip_prefix(':') {
+        let (prefix, tail) = stripped.split_once(' ')?;
+        nick = Some(prefix.split('!').next().unwrap_or(prefix));
+        rest = tail;
+    }
+    let (head, trailing) = match rest.split_once(" :") {
+        Some((head, trailing)) => (head, Some(trailing)),
+        None => (rest, None),
+    };
+    let mut words = head.split_whitespace();
+    let command = words.next()?;
+    let mut params: Vec<&str> = words.collect();
+    params.extend(trailing);
+    Some(IrcMessage {
+        account,
+        nick,
+        command,
+        params,
+    })
+}
+
+/// Private messages, and channel messages starting with `nick:` or `nick,`.
+fn to_inbound(msg: &IrcMessage<'_>, own_nick: &str) -> Option<InboundMessage> {
+    let sender = msg.nick?;
+    let [target, text] = msg.params.as_slice() else {
+        return None;
+    };
+    // CTCP (ACTION, VERSION, ...) is not conversation.
+    if text.starts_with('\u{1}') || sender.eq_ignore_ascii_case(own_nick) {
+        return None;
+    }
+    let is_group = target.starts_with(['#', '&', '+', '!']);
+    let content = if is_group {
+        let addressed = text
+            .get(..own_nick.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(own_nick));
+        let rest = text.get(own_nick.len()..).filter(|_| addressed)?;
+        rest.strip_prefix([':', ','])?.trim()
+    } else {
+        text.trim()
+    };
+    if content.is_empty() {
+        return None;
+    }
+    Some(InboundMessage {
+        channel: "irc".to_string(),
+        chat_id: if is_group { target } else { sender }.to_string(),
+        sender_id: match msg.account {
+            Some(account) => format!("irc:{account}"),
+            None => format!("irc:~{sender}"),
+        },
+        sender_name: is_group.then(|| sender.to_string()),
+        is_group,
+        content: content.to_string(),
+        meta: MessageMeta::default(),
+    })
+}
+
+/// `allow_from` lists services accounts; senders who are not logged in are
+/// only let through when the list is empty.
+struct SenderFilter {
+    allow_from: Vec<String>,
+}
+
+impl SenderFilter {
+    fn new(list: &[String]) -> Self {
+        Self {
+            allow_from: list
+                .iter()
+                .map(|entry| entry.trim().to_ascii_lowercase())
+                .filter(|entry| !entry.is_empty())
+                .collect(),
+        }
+    }
+
+    fn allows(&self, msg: &InboundMessage) -> bool {
+        let account = msg
+            .sender_id
+            .strip_prefix("irc:")
+            .filter(|id| !id.starts_with('~'))
+            .map(str::to_ascii_lowercase);
+        self.allow_from.is_empty() || account.is_some_and(|a| self.allow_from.contains(&a))
+    }
+}
+
+/// Split `text` into IRC-sized lines: one per non-empty line of the reply,
+/// wrapped at word boundaries (or char boundaries for long words).
+fn wrap_lines(text: &str, max_bytes: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    for line in text
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| !l.trim().is_empty())
+    {
+        let mut current = String::new();
+        for word in line.split(' ') {
+            let sep = usize::from(!current.is_empty());
+            if current.len() + sep + word.len() <= max_bytes {
+                if sep == 1 {
+                    current.push(' ');
+                }
+                current.push_str(word);
+                continue;
+            }
+            if !current.is_empty() {
+                out.push(std::mem::take(&mut current));
+            }
+            let mut word = word;
+            while word.len() > max_bytes {
+                let mut cut = max_bytes;
+                while !word.is_char_boundary(cut) {
+                    cut -= 1;
+                }
+                out.push(word[..cut].to_string());
+                word = &word[cut..];
+            }
+            current.push_str(word);
+        }
+        if !current.is_empty() {
+            out.push(current);
+        }
+    }
+    out
+}
+
+fn spawn_outbound_forwarder(
+    channel: Arc<IrcChannel>,
+    lines: UnboundedSender<String>,
+    bus: MessageBus,
+    mut limiter: RateLimiter,
+) {
+    let mut rx = bus.outbound_queue(channel.name());
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let target = msg.chat_id.trim();
+            if target.is_empty() || target.contains([' ', '\r', '\n']) {
+                warn!("invalid irc target: {}", msg.chat_id);
+                bus.report_delivery(&msg, false);
+                continue;
+            }
+            let mut wrapped = Vec::new();
+            for chunk in channel.render(&msg) {
+                wrapped.extend(wrap_lines(&chunk, MAX_PAYLOAD_BYTES));
+            }
+            if wrapped.len() > MAX_LINES_PER_REPLY {
+                wrapped.truncate(MAX_LINES_PER_REPLY - 1);
+                wrapped.push("[reply truncated]".to_string());
+            }
+            let mut ok = true;
+            for line in wrapped {
+                limiter.acquire(target).await;
+                if lines.send(format!("PRIVMSG {target} :{line}")).is_err() {
+                    ok = false;
+                    break;
+                }
+            }
+            bus.report_delivery(&msg, ok);
+        }
+        info!("outbound channel closed, irc forwarder shutting down");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_line, to_inbound, wrap_lines, SenderFilter};
+
+    #[test]
+    fn addressed_channel_messages_and_queries_become_inbound() {
+        let line = ":anna!a@host PRIVMSG #rust :Femtobot: what's up?";
+        let msg = to_inbound(&parse_line(line).unwrap(), "femtobot").unwrap();
+        assert_eq!(msg.chat_id, "#rust");
+        assert_eq!(msg.content, "what's up?");
+        assert!(msg.is_group);
+
+        let unaddressed = parse_line(":anna!a@host PRIVMSG #rust :hello all").unwrap();
+        assert!(to_inbound(&unaddressed, "femtobot").is_none());
+
+        let query = parse_line(":anna!a@host PRIVMSG femtobot :hi").unwrap();
+        let msg = to_inbound(&query, "femtobot").unwrap();
+        assert_eq!(msg.chat_id, "anna");
+        assert!(!msg.is_group);
+    }
+
+    #[test]
+    fn senders_are_identified_by_account_not_nick() {
+        let filter = SenderFilter::new(&["Anna".to_string()]);
+        let logged_in =
+            parse_line("@time=x;account=anna :anna!a@host PRIVMSG femtobot :hi").unwrap();
+        let msg = to_inbound(&logged_in, "femtobot").unwrap();
+        assert_eq!(msg.sender_id, "irc:anna");
+        assert!(filter.allows(&msg));
+
+        for line in [
+            ":anna!a@host PRIVMSG femtobot :hi",
+            "@account=* :anna!a@host PRIVMSG femtobot :hi",
+            ":local!a@host PRIVMSG femtobot :hi",
+        ] {
+            let msg = to_inbound(&parse_line(line).unwrap(), "femtobot").unwrap();
+            assert!(msg.sender_id.starts_with("irc:~"), "{}", msg.sender_id);
+            assert!(!filter.allows(&msg));
+        }
+    }
+
+    #[test]
+    fn wraps_long_replies() {
+        let lines = wrap_lines("one two three\n\nfour", 9);
+        assert_eq!(lines, vec!["one two", "three", "four"]);
+        let lines = wrap_lines(&"é".repeat(10), 5);
+        assert!(lines.iter().all(|l| l.len() <= 5));
+        assert_eq!(lines.concat(), "é".repeat(10));
+    }
+}
@@ -1,18 +1,54 @@
 mod agent;
+mod analytics;
+mod backup;
+mod broadcast;
 mod bus;
+mod channel;
+mod chat_settings;
+mod clock;
 mod config;
 mod configure;
+mod contacts;
 mod cron;
 mod discord;
+mod error;
+mod expenses;
+mod feedback;
+mod habits;
+mod handoff;
+mod history;
+mod import;
+mod index;
+mod irc;
+mod matrix;
+mod mattermost;
 mod memory;
+mod mock;
+mod models;
+mod moderation;
+mod notify;
+mod outbox;
+mod projects;
+mod quick;
+mod ratelimit;
+mod remote;
+mod render;
+mod repl;
+mod reports;
+mod scratchpad;
+mod secrets;
 mod session_compaction;
+mod skills;
+mod sync;
 mod telegram;
 mod tools;
 mod transcription;
+mod variables;
+mod websocket;
+mod xmpp;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use tokio::io::{self, AsyncBufReadExt};
 use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
@@ -27,12 +63,157 @@ struct Cli {
 enum Commands {
     Run,
     Tui,
+    /// Chat with the agent in the terminal (no bot token needed)
+    Chat {
+        /// Session to use; each name keeps its own history
+        #[arg(long, default_value = "local")]
+        session: String,
+        /// Sender id the agent sees (for allowlist and sandbox testing)
+        #[arg(long, default_value = "local")]
+        user: String,
+    },
     Configure,
     Cron {
         /// Admin cron operations (tool-driven scheduling is preferred)
         #[command(subcommand)]
         command: CronCommands,
     },
+    Backup {
+        /// Snapshot or restore memory, sessions and schedules
+        #[command(subcommand)]
+        command: BackupCommands,
+    },
+    Memory {
+        /// Inspect long-term memory
+        #[command(subcommand)]
+        command: MemoryCommands,
+    },
+    Models {
+        /// Download and manage model files for offline components
+        #[command(subcommand)]
+        command: ModelCommands,
+    },
+    /// Sync the workspace with the configured remote storage once
+    Sync,
+    Secrets {
+        /// Encrypt or decrypt the secret values in config.json
+        #[command(subcommand)]
+        command: SecretsCommands,
+    },
+    Index {
+        /// Semantic index of workspace files (the `docs` memory namespace)
+        #[command(subcommand)]
+        command: IndexCommands,
+    },
+    /// Seed history and memory from another assistant's export
+    Import {
+        /// ChatGPT conversations.json, Telegram result.json, or a markdown file/directory
+        path: std::path::PathBuf,
+        /// chatgpt, telegram or markdown (detected when omitted)
+        #[arg(long)]
+        format: Option<String>,
+        /// Only import the newest N conversations
+        #[arg(long)]
+        limit: Option<usize>,
+        /// List what would be imported without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Weekly conversation statistics computed locally from the session transcripts
+    Analytics {
+        /// How many recent weeks to show
+        #[arg(long, default_value_t = 4)]
+        weeks: usize,
+        /// Also schedule a weekly digest, e.g. "every monday at 9am"
+        #[arg(long)]
+        digest: Option<String>,
+        /// Channel the digest is sent to
+        #[arg(long, requires = "digest")]
+        channel: Option<String>,
+        /// Chat id the digest is sent to
+        #[arg(long, requires = "digest")]
+        to: Option<String>,
+    },
+    /// Send a message to all allowlisted chats (or the tagged ones) via the running bot
+    Broadcast {
+        message: String,
+        /// Only chats with this tag (channel name or a broadcast.targets tag); repeatable
+        #[arg(long)]
+        tag: Vec<String>,
+        /// Skip the confirmation prompt
+        #[arg(long, short)]
+        yes: bool,
+        /// Only list the recipients
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupCommands {
+    Now,
+    List,
+    Restore {
+        /// Backup file name (in the backup dir) or path
+        name: String,
+        /// Download the backup from the configured remote storage
+        #[arg(long)]
+        remote: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MemoryCommands {
+    /// Search memories and past conversations
+    Search {
+        query: String,
+        /// Maximum number of results
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+        /// Show each memory's cosine score, recency, importance and namespace
+        #[arg(long)]
+        explain: bool,
+        /// Only score memories in this namespace (with --explain)
+        #[arg(long, requires = "explain")]
+        namespace: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ModelCommands {
+    /// Download a model file into the data dir
+    Pull {
+        url: String,
+        /// File name to keep it under (default: the last part of the URL)
+        #[arg(long)]
+        name: Option<String>,
+        /// Expected SHA-256 of the file; the download is discarded on a mismatch
+        #[arg(long)]
+        sha256: Option<String>,
+    },
+    /// Show downloaded models and the disk space they use
+    List,
+    Remove {
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SecretsCommands {
+    /// Encrypt API keys, tokens, passwords and `secrets` with a master passphrase
+    Encrypt,
+    /// Write the secrets back in plain text
+    Decrypt,
+}
+
+#[derive(Subcommand)]
+enum IndexCommands {
+    /// Embed new and changed files matching `index.globs`, drop deleted ones
+    Rebuild {
+        /// Re-embed every file, not only changed ones
+        #[arg(long)]
+        full: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -43,69 +224,156 @@ enum CronCommands {
         #[arg(long)]
         id: String,
     },
+    /// Print upcoming job occurrences as an iCalendar (.ics) feed
+    Ics {
+        /// Write the feed to this file instead of stdout
+        #[arg(long)]
+        out: Option<std::path::PathBuf>,
+    },
+    /// Add a job; the schedule may be English ("every weekday at 8am")
+    Add {
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        message: String,
+        #[arg(long)]
+        schedule: String,
+        #[arg(long)]
+        channel: Option<String>,
+        #[arg(long)]
+        to: Option<String>,
+    },
+    /// Show how a schedule phrase is understood and its next runs
+    Parse {
+        /// e.g. "first monday of the month at 9"
+        phrase: String,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    init_logging();
-
     let cli = Cli::parse();
-    match cli.command.unwrap_or(Commands::Run) {
+    let command = cli.command.unwrap_or(Commands::Run);
+    // Interactive modes keep the terminal for the conversation.
+    let interactive = matches!(command, Commands::Tui | Commands::Chat { .. });
+    init_logging(if interactive { "warn" } else { "info" });
+
+    match command {
         Commands::Run => run().await,
-        Commands::Tui => run_tui().await,
+        Commands::Tui => {
+            repl::run(
+                config::AppConfig::load()?,
+                repl::ReplOptions {
+                    channel: "tui".to_string(),
+                    session: "local".to_string(),
+                    user: "local".to_string(),
+                },
+            )
+            .await
+        }
+        Commands::Chat { session, user } => {
+            repl::run(
+                config::AppConfig::load()?,
+                repl::ReplOptions {
+                    channel: "cli".to_string(),
+                    session,
+                    user,
+                },
+            )
+            .await
+        }
         Commands::Configure => configure::run(),
         Commands::Cron { command } => handle_cron(command).await,
+        Commands::Backup { command } => handle_backup(command).await,
+        Commands::Memory { command } => handle_memory(command).await,
+        Commands::Models { command } => handle_models(command).await,
+        Commands::Sync => {
+            let cfg = config::AppConfig::load()?;
+            let report = sync::sync_once(&cfg).await?;
+            println!("Workspace sync: {}", report.summary());
+            Ok(())
+        }
+        Commands::Secrets { command } => {
+            let encrypt = matches!(command, SecretsCommands::Encrypt);
+            let (path, count) = secrets::rewrite_config_file(encrypt)?;
+            let done = if encrypt { "encrypted" } else { "decrypted" };
+            println!("{count} value(s) {done} in {}", path.display());
+            Ok(())
+        }
+        Commands::Index { command } => handle_index(command).await,
+        Commands::Import {
+            path,
+            format,
+            limit,
+            dry_run,
+        } => handle_import(path, format, limit, dry_run).await,
+        Commands::Analytics {
+            weeks,
+            digest,
+            channel,
+            to,
+        } => handle_analytics(weeks, digest, channel, to).await,
+        Commands::Broadcast {
+            message,
+            tag,
+            yes,
+            dry_run,
+        } => handle_broadcast(message, tag, yes, dry_run).await,
     }
 }
 
 async fn run() -> Result<()> {
     let cfg = config::AppConfig::load()?;
+    for warning in cfg.warnings() {
+        warn!("config: {warning}");
+    }
 
     let bus = bus::MessageBus::new();
 
+    let notifier = notify::Notifier::new(&cfg, bus.clone());
+    notifier.start();
+
     // Start Cron Service
     let cron_service = cron::CronService::new(&cfg, bus.clone());
-    cron_service.start().await;
+    cron_service.start(notifier.clone()).await;
+
+    if cfg.backup_enabled {
+        backup::start(cfg.clone());
+    }
+    if cfg.sync_enabled {
+        sync::start(cfg.clone());
+    }
 
-    let agent = agent::AgentLoop::new(cfg.clone(), bus.clone(), cron_service.clone());
+    let agent = agent::AgentLoop::new(cfg.clone(), bus.clone(), cron_service.clone(), notifier);
     tokio::spawn(async move {
         agent.run().await;
     });
 
-    let mut enabled_channels = 0usize;
-
-    if cfg.telegram_enabled() {
-        enabled_channels += 1;
-        let telegram_cfg = cfg.clone();
-        let telegram_bus = bus.clone();
-        tokio::spawn(async move {
-            if let Err(err) = telegram::start(telegram_cfg, telegram_bus).await {
-                warn!("telegram disabled: {err}");
-            }
-        });
-    } else {
+    if !cfg.telegram_enabled() {
         info!("Telegram token not configured; running without Telegram input/output");
         info!("Set TELOXIDE_TOKEN or channels.telegram.token to enable Telegram");
     }
-
-    if cfg.discord_enabled() {
-        enabled_channels += 1;
-        let discord_cfg = cfg.clone();
-        let discord_bus = bus.clone();
-        tokio::spawn(async move {
-            if let Err(err) = discord::start(discord_cfg, discord_bus).await {
-                warn!("discord disabled: {err}");
-            }
-        });
-    } else {
+    if !cfg.discord_enabled() {
         info!("Discord token not configured; running without Discord input/output");
         info!("Set DISCORD_BOT_TOKEN or channels.discord.token to enable Discord");
     }
 
-    if enabled_channels == 0 {
+    let channels = channel::configured(&cfg);
+    let handles: Vec<_> = channels
+        .iter()
+        .map(|channel| {
+            info!("starting {} channel", channel.name());
+            channel.clone().start(bus.clone())
+        })
+        .collect();
+
+    if channels.is_empty() {
         warn!("femtobot is running without chat input/output; press Ctrl+C to exit");
     }
     wait_for_shutdown().await?;
+    for handle in handles {
+        handle.abort();
+    }
 
     Ok(())
 }
@@ -115,6 +383,142 @@ async fn wait_for_shutdown() -> Result<()> {
     Ok(())
 }
 
+async fn handle_index(cmd: IndexCommands) -> Result<()> {
+    let cfg = config::AppConfig::load()?;
+    let (Some(store), _, _) = agent::init_vector_memory(&cfg) else {
+        anyhow::bail!("vector memory is disabled or has no embedding provider");
+    };
+    let store = store.with_max_memories(cfg.index_max_chunks);
+    match cmd {
+        IndexCommands::Rebuild { full } => {
+            let started = std::time::Instant::now();
+            let stats = index::rebuild(&cfg, &store, full).await?;
+            println!("{}", stats.summary());
+            println!("took {:.1}s", started.elapsed().as_secs_f64());
+        }
+    }
+    Ok(())
+}
+
+async fn handle_models(cmd: ModelCommands) -> Result<()> {
+    let cfg = config::AppConfig::load()?;
+    match cmd {
+        ModelCommands::Pull { url, name, sha256 } => {
+            println!("Downloading {url} ...");
+            let entry = models::pull(&cfg, &url, name.as_deref(), sha256.as_deref()).await?;
+            println!(
+                "Saved {} ({}) to {}",
+                entry.name,
+                models::format_size(entry.size),
+                entry.path(&cfg.data_dir).display()
+            );
+            if entry.verified {
+                println!("Checksum verified.");
+            } else {
+                println!(
+                    "SHA-256 {} (not verified; pass --sha256 to check it)",
+                    entry.sha256
+                );
+            }
+        }
+        ModelCommands::List => {
+            let entries = models::list(&cfg.data_dir)?;
+            if entries.is_empty() {
+                println!(
+                    "No models in {}.",
+                    models::models_dir(&cfg.data_dir).display()
+                );
+            }
+            for entry in &entries {
+                let present = entry.path(&cfg.data_dir).exists();
+                println!(
+                    "{:<40} {:>10}  {}  {}{}",
+                    entry.name,
+                    models::format_size(entry.size),
+                    entry.pulled_at.format("%Y-%m-%d"),
+                    &entry.sha256[..12.min(entry.sha256.len())],
+                    if present { "" } else { "  (file missing)" }
+                );
+            }
+            println!(
+                "Disk usage: {}",
+                models::format_size(models::disk_usage(&cfg.data_dir))
+            );
+        }
+        ModelCommands::Remove { name } => {
+            let freed = models::remove(&cfg.data_dir, &name)?;
+            println!("Removed {name}, freed {}.", models::format_size(freed));
+        }
+    }
+    Ok(())
+}
+
+async fn handle_memory(cmd: MemoryCommands) -> Result<()> {
+    let cfg = config::AppConfig::load()?;
+    let (vector_memory, _, _) = agent::init_vector_memory(&cfg);
+    match cmd {
+        MemoryCommands::Search {
+            query,
+            limit,
+            explain,
+            namespace,
+        } => {
+            let out = match (&vector_memory, namespace) {
+                (Some(store), Some(namespace)) => {
+                    tools::history::explain_memories(store, &query, limit, Some(&namespace)).await
+                }
+                _ => {
+                    tools::history::search_history(
+                        &history::HistoryLog::new(&cfg.workspace_dir),
+                        vector_memory.as_ref(),
+                        &query,
+                        limit,
+                        explain,
+                        None,
+                    )
+                    .await
+                }
+            };
+            print!("{out}");
+        }
+    }
+    Ok(())
+}
+
+async fn handle_analytics(
+    weeks: usize,
+    digest: Option<String>,
+    channel: Option<String>,
+    to: Option<String>,
+) -> Result<()> {
+    let cfg = config::AppConfig::load()?;
+    let tz = clock::resolve_timezone(&cfg.timezone).unwrap_or(chrono_tz::Tz::UTC);
+    let analytics = analytics::Analytics::open(&cfg.data_dir)?;
+    analytics
+        .refresh(&history::HistoryLog::new(&cfg.workspace_dir), tz)
+        .await?;
+    print!("{}", analytics::render(&analytics.recent(weeks).await?));
+
+    if let Some(schedule) = digest {
+        let service = cron::CronService::new(&cfg, bus::MessageBus::new());
+        let job = service
+            .add_job(
+                "weekly analytics digest".to_string(),
+                schedule,
+                "/report weekly analytics".to_string(),
+                channel,
+                to,
+            )
+            .await?;
+        println!(
+            "\nDigest scheduled as cron job {} ({}).",
+            job.id,
+            job.schedule.describe()
+        );
+    }
+    Ok(())
+}
+
 async fn handle_cron(cmd: CronCommands) -> Result<()> {
     let cfg = config::AppConfig::load()?;
     // We don't need a real bus for CLI operations acting on the store
@@ -176,70 +580,171 @@ async fn handle_cron(cmd: CronCommands) -> Result<()> {
             println!("Jobs: {}", status.jobs);
             println!("Enabled jobs: {}", status.enabled_jobs);
             println!("Next wake: {}", next);
+            if status.overdue_jobs > 0 {
+                println!("Overdue jobs: {}", status.overdue_jobs);
+            }
         }
         CronCommands::Remove { id } => match service.remove_job(&id).await {
             Ok(true) => println!("Job removed."),
             Ok(false) => println!("Job not found."),
             Err(e) => println!("Error removing job: {}", e),
         },
+        CronCommands::Add {
+            name,
+            message,
+            schedule,
+            channel,
+            to,
+        } => {
+            let job = service
+                .add_job(name, schedule, message, channel, to)
+                .await?;
+            println!("Added job {} ({})", job.id, job.schedule.describe());
+        }
+        CronCommands::Parse { phrase } => {
+            let schedule = service.parse_schedule(&phrase)?;
+            println!("{}", schedule.describe());
+            let now = chrono::Utc::now().timestamp_millis();
+            let runs = match schedule.kind.as_str() {
+                "cron" => cron::cron_runs(&schedule, now, 3),
+                "every" => (1..=3)
+                    .map(|n| now + n * schedule.every_ms.unwrap_or(0))
+                    .collect(),
+                _ => schedule.at_ms.into_iter().collect(),
+            };
+            for ms in runs {
+                if let Some(dt) = chrono::DateTime::<chrono::Utc>::from_timestamp_millis(ms) {
+                    println!("  next: {}", dt.with_timezone(&chrono::Local).to_rfc3339());
+                }
+            }
+        }
+        CronCommands::Ics { out } => {
+            let ics = service.calendar().await?;
+            match out {
+                Some(path) => {
+                    cron::ical::write(&path, &ics)?;
+                    println!("Calendar written to {}", path.display());
+                }
+                None => print!("{ics}"),
+            }
+        }
     }
     Ok(())
 }
 
-async fn run_tui() -> Result<()> {
+async fn handle_backup(cmd: BackupCommands) -> Result<()> {
     let cfg = config::AppConfig::load()?;
-    let bus = bus::MessageBus::new();
-
-    let cron_service = cron::CronService::new(&cfg, bus.clone());
-    cron_service.start().await;
-
-    let agent = agent::AgentLoop::new(cfg, bus.clone(), cron_service);
-    tokio::spawn(async move {
-        agent.run().await;
-    });
-
-    let bus_for_outbound = bus.clone();
-    tokio::spawn(async move {
-        let mut outbound_rx = bus_for_outbound.subscribe_outbound();
-        loop {
-            let msg = match outbound_rx.recv().await {
-                Ok(msg) => msg,
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
-            };
-            if msg.channel != "tui" {
-                continue;
+    match cmd {
+        BackupCommands::Now => {
+            let outcome = backup::run_backup(&cfg).await?;
+            println!("Backup written to {}", outcome.path.display());
+            if let Some(remote) = outcome.uploaded_to {
+                println!("Uploaded to {remote}");
+            }
+            if outcome.pruned > 0 {
+                println!("Pruned {} old backup(s).", outcome.pruned);
             }
-            println!("\nassistant> {}\n", msg.content.trim());
         }
-    });
-
-    println!("femtobot TUI mode");
-    println!("Type messages and press Enter. Type /exit to quit.\n");
-
-    let mut lines = io::BufReader::new(io::stdin()).lines();
-    while let Some(line) = lines.next_line().await? {
-        let content = line.trim().to_string();
-        if content.is_empty() {
-            continue;
+        BackupCommands::List => {
+            let backups = backup::list_backups(&cfg.backup_dir);
+            if backups.is_empty() {
+                println!("No backups in {}.", cfg.backup_dir.display());
+            }
+            for path in backups {
+                let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                println!(
+                    "{:<50} {:>10} KB",
+                    path.file_name().unwrap_or_default().to_string_lossy(),
+                    size / 1024
+                );
+            }
         }
-        if content == "/exit" {
-            break;
+        BackupCommands::Restore { name, remote } => {
+            let outcome = backup::restore(&cfg, &name, remote).await?;
+            println!("Restored {} file(s) from {name}.", outcome.files);
+            println!(
+                "Previous state saved to {}. Restart femtobot to load the restored data.",
+                outcome.safety.display()
+            );
         }
-        bus.publish_inbound(bus::InboundMessage {
-            channel: "tui".to_string(),
-            chat_id: "local".to_string(),
-            sender_id: "local".to_string(),
-            content,
-        })
-        .await;
     }
+    Ok(())
+}
+
+async fn handle_import(
+    path: std::path::PathBuf,
+    format: Option<String>,
+    limit: Option<usize>,
+    dry_run: bool,
+) -> Result<()> {
+    let cfg = config::AppConfig::load()?;
+    let format = match format.as_deref() {
+        Some(raw) => Some(import::ImportFormat::parse(raw).ok_or_else(|| {
+            anyhow::anyhow!("unknown format '{raw}'; use chatgpt, telegram or markdown")
+        })?),
+        None => None,
+    };
+    let opts = import::ImportOptions {
+        format,
+        dry_run,
+        limit,
+    };
+    let report = import::run(&cfg, &path, opts).await?;
+    if dry_run {
+        println!(
+            "{} conversation(s), {} message(s) would be imported.",
+            report.conversations, report.messages
+        );
+    } else {
+        println!(
+            "Imported {} conversation(s) ({} messages): {} summaries, {} facts extracted.",
+            report.conversations, report.messages, report.summaries, report.facts
+        );
+    }
+    Ok(())
+}
 
+async fn handle_broadcast(
+    message: String,
+    tags: Vec<String>,
+    yes: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let cfg = config::AppConfig::load()?;
+    let targets = broadcast::select(&cfg, &tags);
+    if targets.is_empty() {
+        println!("No chats match. Broadcasts reach allowlisted chats of enabled channels and broadcast.targets.");
+        return Ok(());
+    }
+    println!("Recipients ({}):", targets.len());
+    for target in &targets {
+        println!("  {}", target.label());
+    }
+    if dry_run {
+        return Ok(());
+    }
+    if !yes {
+        print!("Send this message to {} chats? [y/N] ", targets.len());
+        std::io::Write::flush(&mut std::io::stdout())?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            println!("Cancelled.");
+            return Ok(());
+        }
+    }
+    let service = cron::CronService::new(&cfg, bus::MessageBus::new());
+    broadcast::queue(&service, &targets, &message).await?;
+    println!(
+        "Queued for {} chats; the running femtobot delivers them within a few seconds.",
+        targets.len()
+    );
     Ok(())
 }
 
-fn init_logging() {
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+fn init_logging(default_level: &str) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level));
     tracing_subscriber::fmt()
         .with_env_filter(filter)
         .with_target(false)
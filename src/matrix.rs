@@ -0,0 +1,384 @@
+//! Matrix channel over the client-server HTTP API: long-polls `/sync` for
+//! room messages and sends replies as `m.room.message` events. Room ids are
+//! used as `chat_id`. For end-to-end encrypted rooms, point `homeserver` at
+//! an E2EE-aware proxy such as pantalaimon.
+
+use crate::bus::{InboundMessage, MessageBus, MessageMeta};
+use crate::channel::{self, Channel};
+use crate::config::AppConfig;
+use crate::ratelimit::RateLimiter;
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use url::Url;
+
+/// Long-poll timeout passed to `/sync`.
+const SYNC_TIMEOUT_MS: u64 = 30_000;
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+struct MatrixClient {
+    http: Client,
+    homeserver: Url,
+    token: String,
+}
+
+impl MatrixClient {
+    fn endpoint(&self, segments: &[&str]) -> Result<Url> {
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("invalid matrix homeserver url"))?
+            .pop_if_empty()
+            .extend(["_matrix", "client", "v3"])
+            .extend(segments);
+        Ok(url)
+    }
+
+    async fn get(&self, url: Url) -> Result<Value> {
+        let resp = self.http.get(url).bearer_auth(&self.token).send().await?;
+        let status = resp.status();
+        let body: Value = resp.json().await?;
+        if !status.is_success() {
+            return Err(anyhow!("matrix request failed ({status}): {body}"));
+        }
+        Ok(body)
+    }
+
+    async fn send(&self, method: reqwest::Method, url: Url, body: Value) -> Result<Value> {
+        let resp = self
+            .http
+            .request(method, url)
+            .bearer_auth(&self.token)
+            .json(&body)
+            .send()
+            .await?;
+        let status = resp.status();
+        let body: Value = resp.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(anyhow!("matrix request failed ({status}): {body}"));
+        }
+        Ok(body)
+    }
+
+    async fn sync(&self, since: Option<&str>, timeout_ms: u64) -> Result<Value> {
+        let mut url = self.endpoint(&["sync"])?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("timeout", &timeout_ms.to_string());
+            match since {
+                Some(since) => {
+                    query.append_pair("since", since);
+                }
+                // The first sync only establishes a position; skip the backlog.
+                None => {
+                    query.append_pair("filter", r#"{"room":{"timeline":{"limit":0}}}"#);
+                }
+            }
+        }
+        self.get(url).await
+    }
+
+    async fn send_text(&self, room_id: &str, text: &str, reply_to: Option<&str>) -> Result<()> {
+        let txn = uuid::Uuid::new_v4().to_string();
+        let url = self.endpoint(&["rooms", room_id, "send", "m.room.message", &txn])?;
+        let mut body = json!({ "msgtype": "m.text", "body": text });
+        if let Some(event_id) = reply_to {
+            body["m.relates_to"] = json!({ "m.in_reply_to": { "event_id": event_id } });
+        }
+        self.send(reqwest::Method::PUT, url, body).await?;
+        Ok(())
+    }
+
+    async fn set_typing(&self, room_id: &str, user_id: &str) {
+        let result = match self.endpoint(&["rooms", room_id, "typing", user_id]) {
+            Ok(url) => self
+                .send(
+                    reqwest::Method::PUT,
+                    url,
+                    json!({ "typing": true, "timeout": SYNC_TIMEOUT_MS }),
+                )
+                .await
+                .map(|_| ()),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            warn!("matrix typing notice failed for {room_id}: {err}");
+        }
+    }
+
+    async fn join(&self, room_id: &str) -> Result<()> {
+        let url = self.endpoint(&["rooms", room_id, "join"])?;
+        self.send(reqwest::Method::POST, url, json!({})).await?;
+        Ok(())
+    }
+}
+
+pub struct MatrixChannel {
+    cfg: AppConfig,
+}
+
+impl MatrixChannel {
+    pub fn new(cfg: AppConfig) -> Self {
+        Self { cfg }
+    }
+}
+
+impl Channel for MatrixChannel {
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    fn start(self: Arc<Self>, bus: MessageBus) -> JoinHandle<()> {
+        channel::spawn(self.name(), serve(self, bus))
+    }
+}
+
+async fn serve(channel: Arc<MatrixChannel>, bus: MessageBus) -> Result<()> {
+    let cfg = &channel.cfg;
+    let homeserver = Url::parse(cfg.matrix_homeserver.trim())
+        .map_err(|err| anyhow!("invalid matrix homeserver: {err}"))?;
+    let client = MatrixClient {
+        http: Client::builder()
+            .timeout(Duration::from_millis(SYNC_TIMEOUT_MS) + Duration::from_secs(30))
+            .build()?,
+        homeserver,
+        token: cfg.matrix_access_token.trim().to_string(),
+    };
+    let user_id = match cfg.matrix_user_id.trim() {
+        "" => client
+            .get(client.endpoint(&["account", "whoami"])?)
+            .await
+            .map_err(|err| anyhow!("matrix authentication failed: {err}"))?
+            .get("user_id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("matrix whoami returned no user_id"))?
+            .to_string(),
+        user => user.to_string(),
+    };
+    info!("matrix connected as {user_id}");
+
+    spawn_outbound_forwarder(
+        channel.clone(),
+        client.clone(),
+        bus.clone(),
+        RateLimiter::for_channel(cfg, "matrix"),
+    );
+
+    let filter = RoomFilter::new(cfg);
+    let mut since: Option<String> = None;
+    loop {
+        let timeout = if since.is_some() { SYNC_TIMEOUT_MS } else { 0 };
+        let sync = match client.sync(since.as_deref(), timeout).await {
+            Ok(sync) => sync,
+            Err(err) => {
+                warn!("matrix sync failed: {err}");
+                tokio::time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+        };
+        let first = since.is_none();
+        since = sync
+            .get("next_batch")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        if let Some(invites) = sync.pointer("/rooms/invite").and_then(Value::as_object) {
+            for room_id in invites.keys() {
+                if !filter.room_allowed(room_id) {
+                    continue;
+                }
+                match client.join(room_id).await {
+                    Ok(()) => info!("matrix joined invited room {room_id}"),
+                    Err(err) => warn!("matrix join failed for {room_id}: {err}"),
+                }
+            }
+        }
+        if first {
+            continue;
+        }
+
+        let Some(rooms) = sync.pointer("/rooms/join").and_then(Value::as_object) else {
+            continue;
+        };
+        for (room_id, room) in rooms {
+            if !filter.room_allowed(room_id) {
+                continue;
+            }
+            let members = room
+                .pointer("/summary/m.joined_member_count")
+                .and_then(Value::as_u64);
+            let events = room
+                .pointer("/timeline/events")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+            for event in events {
+                let Some(inbound) = to_inbound(room_id, &event, &user_id, members) else {
+                    continue;
+                };
+                if !filter.sender_allowed(&inbound.sender_id) {
+                    continue;
+                }
+                client.set_typing(room_id, &user_id).await;
+                bus.publish_inbound(inbound).await;
+            }
+        }
+    }
+}
+
+/// Text message events from others become inbound messages.
+fn to_inbound(
+    room_id: &str,
+    event: &Value,
+    own_user_id: &str,
+    members: Option<u64>,
+) -> Option<InboundMessage> {
+    if event.get("type").and_then(Value::as_str) != Some("m.room.message") {
+        return None;
+    }
+    let sender = event.get("sender").and_then(Value::as_str)?;
+    if sender == own_user_id {
+        return None;
+    }
+    let content = event.get("content")?;
+    if !matches!(
+        content.get("msgtype").and_then(Value::as_str),
+        Some("m.text" | "m.notice")
+    ) {
+        return None;
+    }
+    let body = strip_reply_fallback(content.get("body").and_then(Value::as_str)?);
+    if body.is_empty() {
+        return None;
+    }
+    let is_group = members.is_some_and(|n| n > 2);
+    Some(InboundMessage {
+        channel: "matrix".to_string(),
+        chat_id: room_id.to_string(),
+        sender_id: sender.to_string(),
+        sender_name: is_group.then(|| display_name(sender)),
+        is_group,
+        content: body.to_string(),
+        meta: MessageMeta {
+            message_id: event
+                .get("event_id")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            reply_to: content
+                .pointer("/m.relates_to/m.in_reply_to/event_id")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            ..Default::default()
+        },
+    })
+}
+
+/// Replies quote the original as "> " lines followed by a blank line.
+fn strip_reply_fallback(body: &str) -> &str {
+    if !body.starts_with("> ") {
+        return body.trim();
+    }
+    match body.find("\n\n") {
+        Some(idx) => body[idx + 2..].trim(),
+        None => body.trim(),
+    }
+}
+
+/// "@anna:example.org" -> "anna".
+fn display_name(user_id: &str) -> String {
+    user_id
+        .trim_start_matches('@')
+        .split(':')
+        .next()
+        .unwrap_or(user_id)
+        .to_string()
+}
+
+struct RoomFilter {
+    allowed_rooms: Vec<String>,
+    allow_from: Vec<String>,
+}
+
+impl RoomFilter {
+    fn new(cfg: &AppConfig) -> Self {
+        let clean = |list: &[String]| {
+            list.iter()
+                .map(|entry| entry.trim().to_string())
+                .filter(|entry| !entry.is_empty())
+                .collect::<Vec<_>>()
+        };
+        Self {
+            allowed_rooms: clean(&cfg.matrix_allowed_rooms),
+            allow_from: clean(&cfg.matrix_allow_from),
+        }
+    }
+
+    fn room_allowed(&self, room_id: &str) -> bool {
+        self.allowed_rooms.is_empty() || self.allowed_rooms.iter().any(|r| r == room_id)
+    }
+
+    fn sender_allowed(&self, user_id: &str) -> bool {
+        self.allow_from.is_empty()
+            || self
+                .allow_from
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(user_id))
+    }
+}
+
+fn spawn_outbound_forwarder(
+    channel: Arc<MatrixChannel>,
+    client: MatrixClient,
+    bus: MessageBus,
+    mut limiter: RateLimiter,
+) {
+    let mut rx = bus.outbound_queue(channel.name());
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let mut result = Ok(());
+            let mut reply_to = msg.meta.reply_to.as_deref();
+            for chunk in channel.render(&msg) {
+                limiter.acquire(&msg.chat_id).await;
+                result = client.send_text(&msg.chat_id, &chunk, reply_to).await;
+                if let Err(err) = &result {
+                    warn!("matrix send failed for room {}: {err}", msg.chat_id);
+                    break;
+                }
+                reply_to = None;
+            }
+            bus.report_delivery(&msg, result.is_ok());
+        }
+        info!("outbound channel closed, matrix forwarder shutting down");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_inbound;
+    use serde_json::json;
+
+    #[test]
+    fn converts_text_events_and_skips_own() {
+        let event = json!({
+            "type": "m.room.message",
+            "sender": "@anna:example.org",
+            "event_id": "$abc",
+            "content": {
+                "msgtype": "m.text",
+                "body": "> <@bot:example.org> earlier\n\nwhat's next?",
+                "m.relates_to": { "m.in_reply_to": { "event_id": "$prev" } }
+            }
+        });
+        let msg = to_inbound("!room:example.org", &event, "@bot:example.org", Some(3)).unwrap();
+        assert_eq!(msg.chat_id, "!room:example.org");
+        assert_eq!(msg.content, "what's next?");
+        assert_eq!(msg.sender_name.as_deref(), Some("anna"));
+        assert!(msg.is_group);
+        assert_eq!(msg.meta.reply_to.as_deref(), Some("$prev"));
+        assert!(to_inbound("!room:example.org", &event, "@anna:example.org", None).is_none());
+    }
+}
@@ -0,0 +1,360 @@
+//! Mattermost channel: listens on the WebSocket events API for `posted`
+//! events and replies through the REST API. Channel ids are used as
+//! `chat_id`, so each channel and each direct message has its own session.
+//! Outside direct messages the bot only answers posts that mention it.
+
+use crate::bus::{InboundMessage, MessageBus, MessageMeta};
+use crate::channel::{self, Channel};
+use crate::config::AppConfig;
+use crate::ratelimit::RateLimiter;
+use anyhow::{anyhow, Result};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tracing::{info, warn};
+use url::Url;
+
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+struct MattermostClient {
+    http: Client,
+    base: Url,
+    token: String,
+}
+
+impl MattermostClient {
+    fn endpoint(&self, segments: &[&str]) -> Result<Url> {
+        let mut url = self.base.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("invalid mattermost url"))?
+            .pop_if_empty()
+            .extend(["api", "v4"])
+            .extend(segments);
+        Ok(url)
+    }
+
+    fn websocket_url(&self) -> Result<Url> {
+        let mut url = self.endpoint(&["websocket"])?;
+        let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+        url.set_scheme(scheme)
+            .map_err(|_| anyhow!("invalid mattermost url"))?;
+        Ok(url)
+    }
+
+    async fn me(&self) -> Result<BotUser> {
+        let resp = self
+            .http
+            .get(self.endpoint(&["users", "me"])?)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let status = resp.status();
+        let body: Value = resp.json().await?;
+        if !status.is_success() {
+            return Err(anyhow!("mattermost request failed ({status}): {body}"));
+        }
+        let field = |name: &str| {
+            body.get(name)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("mattermost users/me returned no {name}"))
+        };
+        Ok(BotUser {
+            id: field("id")?,
+            username: field("username")?,
+        })
+    }
+
+    async fn create_post(&self, channel_id: &str, message: &str) -> Result<()> {
+        let resp = self
+            .http
+            .post(self.endpoint(&["posts"])?)
+            .bearer_auth(&self.token)
+            .json(&json!({ "channel_id": channel_id, "message": message }))
+            .send()
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(anyhow!("mattermost post failed ({status}): {body}"));
+        }
+        Ok(())
+    }
+}
+
+struct BotUser {
+    id: String,
+    username: String,
+}
+
+pub struct MattermostChannel {
+    cfg: AppConfig,
+}
+
+impl MattermostChannel {
+    pub fn new(cfg: AppConfig) -> Self {
+        Self { cfg }
+    }
+}
+
+impl Channel for MattermostChannel {
+    fn name(&self) -> &'static str {
+        "mattermost"
+    }
+
+    fn start(self: Arc<Self>, bus: MessageBus) -> JoinHandle<()> {
+        channel::spawn(self.name(), serve(self, bus))
+    }
+}
+
+async fn serve(channel: Arc<MattermostChannel>, bus: MessageBus) -> Result<()> {
+    let cfg = &channel.cfg;
+    let base = Url::parse(cfg.mattermost_url.trim())
+        .map_err(|err| anyhow!("invalid mattermost url: {err}"))?;
+    let client = MattermostClient {
+        http: Client::builder().timeout(Duration::from_secs(30)).build()?,
+        base,
+        token: cfg.mattermost_token.trim().to_string(),
+    };
+    let me = client
+        .me()
+        .await
+        .map_err(|err| anyhow!("mattermost authentication failed: {err}"))?;
+    info!("mattermost connected as @{}", me.username);
+
+    spawn_outbound_forwarder(
+        channel.clone(),
+        client.clone(),
+        bus.clone(),
+        RateLimiter::for_channel(cfg, "mattermost"),
+    );
+
+    let filter = PostFilter::new(cfg);
+    loop {
+        if let Err(err) = listen(&client, &me, &filter, &bus).await {
+            warn!("mattermost websocket failed: {err}");
+        }
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+async fn listen(
+    client: &MattermostClient,
+    me: &BotUser,
+    filter: &PostFilter,
+    bus: &MessageBus,
+) -> Result<()> {
+    let (mut ws, _) = tokio_tungstenite::connect_async(client.websocket_url()?.as_str()).await?;
+    let mut seq = 1u64;
+    ws.send(WsMessage::Text(
+        json!({
+            "seq": seq,
+            "action": "authentication_challenge",
+            "data": { "token": client.token },
+        })
+        .to_string(),
+    ))
+    .await?;
+
+    while let Some(frame) = ws.next().await {
+        let text = match frame? {
+            WsMessage::Text(text) => text,
+            WsMessage::Ping(payload) => {
+                ws.send(WsMessage::Pong(payload)).await?;
+                continue;
+            }
+            WsMessage::Close(_) => break,
+            _ => continue,
+        };
+        let Ok(event) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        let Some(inbound) = to_inbound(&event, me) else {
+            continue;
+        };
+        if !filter.allows(&inbound) {
+            continue;
+        }
+        seq += 1;
+        ws.send(WsMessage::Text(
+            json!({
+                "seq": seq,
+                "action": "user_typing",
+                "data": { "channel_id": inbound.chat_id },
+            })
+            .to_string(),
+        ))
+        .await?;
+        bus.publish_inbound(inbound).await;
+    }
+    Ok(())
+}
+
+/// `posted` events from others become inbound messages; outside direct
+/// messages only when they mention the bot.
+fn to_inbound(event: &Value, me: &BotUser) -> Option<InboundMessage> {
+    if event.get("event").and_then(Value::as_str) != Some("posted") {
+        return None;
+    }
+    let data = event.get("data")?;
+    // The post and the mention list arrive as JSON-encoded strings.
+    let post: Value = serde_json::from_str(data.get("post")?.as_str()?).ok()?;
+    let sender = post.get("user_id").and_then(Value::as_str)?;
+    // System posts (joins, header changes) carry a type.
+    let is_system = post
+        .get("type")
+        .and_then(Value::as_str)
+        .is_some_and(|kind| !kind.is_empty());
+    if sender == me.id || is_system {
+        return None;
+    }
+    let is_group = data.get("channel_type").and_then(Value::as_str) != Some("D");
+    let mut text = post
+        .get("message")
+        .and_then(Value::as_str)?
+        .trim()
+        .to_string();
+    if is_group {
+        let mentions: Vec<String> = data
+            .get("mentions")
+            .and_then(Value::as_str)
+            .and_then(|raw| serde_json::from_str(raw).ok())
+            .unwrap_or_default();
+        if !mentions.iter().any(|id| id == &me.id) {
+            return None;
+        }
+        text = text
+            .replace(&format!("@{}", me.username), "")
+            .trim()
+            .to_string();
+    }
+    if text.is_empty() {
+        return None;
+    }
+    let sender_name = data
+        .get("sender_name")
+        .and_then(Value::as_str)
+        .map(|name| name.trim_start_matches('@').to_string());
+    Some(InboundMessage {
+        channel: "mattermost".to_string(),
+        chat_id: post.get("channel_id").and_then(Value::as_str)?.to_string(),
+        sender_id: sender.to_string(),
+        sender_name,
+        is_group,
+        content: text,
+        meta: MessageMeta {
+            message_id: post.get("id").and_then(Value::as_str).map(str::to_string),
+            ..Default::default()
+        },
+    })
+}
+
+struct PostFilter {
+    allowed_channels: Vec<String>,
+    allow_from: Vec<String>,
+}
+
+impl PostFilter {
+    fn new(cfg: &AppConfig) -> Self {
+        let clean = |list: &[String]| {
+            list.iter()
+                .map(|entry| entry.trim().trim_start_matches('@').to_ascii_lowercase())
+                .filter(|entry| !entry.is_empty())
+                .collect::<Vec<_>>()
+        };
+        Self {
+            allowed_channels: clean(&cfg.mattermost_allowed_channels),
+            allow_from: clean(&cfg.mattermost_allow_from),
+        }
+    }
+
+    /// Senders match by user id or username; the channel list applies to
+    /// everything but direct messages.
+    fn allows(&self, msg: &InboundMessage) -> bool {
+        let channel_ok = !msg.is_group
+            || self.allowed_channels.is_empty()
+            || self
+                .allowed_channels
+                .contains(&msg.chat_id.to_ascii_lowercase());
+        let username = msg.sender_name.as_deref().unwrap_or_default();
+        let sender_ok = self.allow_from.is_empty()
+            || self.allow_from.iter().any(|allowed| {
+                allowed.eq_ignore_ascii_case(&msg.sender_id)
+                    || allowed.eq_ignore_ascii_case(username)
+            });
+        channel_ok && sender_ok
+    }
+}
+
+fn spawn_outbound_forwarder(
+    channel: Arc<MattermostChannel>,
+    client: MattermostClient,
+    bus: MessageBus,
+    mut limiter: RateLimiter,
+) {
+    let mut rx = bus.outbound_queue(channel.name());
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let mut result = Ok(());
+            for chunk in channel.render(&msg) {
+                limiter.acquire(&msg.chat_id).await;
+                result = client.create_post(&msg.chat_id, &chunk).await;
+                if let Err(err) = &result {
+                    warn!("mattermost send failed for channel {}: {err}", msg.chat_id);
+                    break;
+                }
+            }
+            bus.report_delivery(&msg, result.is_ok());
+        }
+        info!("outbound channel closed, mattermost forwarder shutting down");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_inbound, BotUser};
+    use serde_json::json;
+
+    #[test]
+    fn converts_direct_and_mentioning_posts() {
+        let me = BotUser {
+            id: "bot1".to_string(),
+            username: "femtobot".to_string(),
+        };
+        let post = |message: &str| {
+            json!({ "id": "p1", "user_id": "u1", "channel_id": "c1", "message": message, "type": "" })
+                .to_string()
+        };
+        let dm = json!({
+            "event": "posted",
+            "data": { "channel_type": "D", "sender_name": "@anna", "post": post("hi") }
+        });
+        let msg = to_inbound(&dm, &me).unwrap();
+        assert_eq!(msg.chat_id, "c1");
+        assert!(!msg.is_group);
+        assert_eq!(msg.sender_name.as_deref(), Some("anna"));
+
+        let channel = json!({
+            "event": "posted",
+            "data": {
+                "channel_type": "O",
+                "post": post("@femtobot what's the status?"),
+                "mentions": "[\"bot1\"]"
+            }
+        });
+        let msg = to_inbound(&channel, &me).unwrap();
+        assert_eq!(msg.content, "what's the status?");
+        assert!(msg.is_group);
+
+        let unaddressed = json!({
+            "event": "posted",
+            "data": { "channel_type": "O", "post": post("lunch?") }
+        });
+        assert!(to_inbound(&unaddressed, &me).is_none());
+    }
+}
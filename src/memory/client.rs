@@ -78,6 +78,7 @@ impl OpenRouterClient {
                 None,
                 cfg.ollama_extra_headers.clone(),
             ),
+            ProviderKind::Mock => Err(anyhow!("the mock provider has no chat completions API")),
         }
     }
 
@@ -7,6 +7,7 @@ use tracing::warn;
 
 use crate::memory::client::{ChatMessage, OpenRouterClient, ResponseFormat};
 use crate::memory::extractor::ExtractedFact;
+use crate::memory::vault::ObsidianVault;
 use crate::memory::vector_store::{MemoryItem, VectorMemoryStore};
 
 #[derive(Clone, Debug)]
@@ -38,12 +39,20 @@ struct ConsolidationDecision {
     reason: Option<String>,
 }
 
+/// Metadata labels an extracted fact carries into storage.
+struct FactLabels<'a> {
+    topic: Option<&'a str>,
+    speaker: Option<&'a str>,
+    collection: Option<&'a str>,
+}
+
 #[derive(Clone)]
 pub struct MemoryConsolidator {
     store: VectorMemoryStore,
     model: String,
     candidate_threshold: f32,
     client: OpenRouterClient,
+    vault: Option<ObsidianVault>,
 }
 
 impl MemoryConsolidator {
@@ -58,9 +67,16 @@ impl MemoryConsolidator {
             model,
             client,
             candidate_threshold,
+            vault: None,
         }
     }
 
+    /// Mirror applied operations into topic notes of an Obsidian vault.
+    pub fn with_vault(mut self, vault: Option<ObsidianVault>) -> Self {
+        self.vault = vault;
+        self
+    }
+
     pub async fn consolidate(
         &self,
         facts: Vec<ExtractedFact>,
@@ -72,6 +88,9 @@ impl MemoryConsolidator {
                 continue;
             }
             let fact_source = fact.source.clone();
+            let topic = fact.topic.clone();
+            let speaker = fact.speaker.clone();
+            let collection = fact.collection.clone();
             let (result, valid_ids) = self
                 .consolidate_single(fact.content.trim(), namespace)
                 .await
@@ -98,7 +117,17 @@ impl MemoryConsolidator {
             };
 
             if let Err(err) = self
-                .execute_operation(&result, namespace, importance, &valid_ids)
+                .execute_operation(
+                    &result,
+                    namespace,
+                    importance,
+                    FactLabels {
+                        topic: topic.as_deref(),
+                        speaker: speaker.as_deref(),
+                        collection: collection.as_deref(),
+                    },
+                    &valid_ids,
+                )
                 .await
             {
                 warn!("Failed to execute operation: {}", err);
@@ -225,15 +254,26 @@ impl MemoryConsolidator {
         result: &ConsolidationResult,
         namespace: &str,
         importance: f32,
+        labels: FactLabels<'_>,
         valid_ids: &[String],
     ) -> Result<()> {
+        let topic = labels.topic;
         let mut base_metadata = HashMap::new();
         base_metadata.insert("importance".to_string(), Value::from(importance));
+        for (key, value) in [
+            ("topic", topic),
+            ("speaker", labels.speaker),
+            ("collection", labels.collection),
+        ] {
+            if let Some(value) = value {
+                base_metadata.insert(key.to_string(), Value::from(value));
+            }
+        }
 
         match result.operation {
             Operation::Add => {
                 if let Some(content) = &result.new_content {
-                    let _ = self
+                    let item = self
                         .store
                         .add(
                             &sanitize_storage_content(content),
@@ -241,6 +281,7 @@ impl MemoryConsolidator {
                             Some(namespace),
                         )
                         .await?;
+                    self.mirror_to_vault(&item, topic);
                 }
             }
             Operation::Update => {
@@ -257,16 +298,19 @@ impl MemoryConsolidator {
                             Some(namespace),
                         )
                         .await?;
-                    if updated.is_none() {
-                        let _ = self
-                            .store
-                            .add(
-                                &sanitize_storage_content(content),
-                                base_metadata.clone(),
-                                Some(namespace),
-                            )
-                            .await?;
-                    }
+                    let item = match updated {
+                        Some(item) => item,
+                        None => {
+                            self.store
+                                .add(
+                                    &sanitize_storage_content(content),
+                                    base_metadata.clone(),
+                                    Some(namespace),
+                                )
+                                .await?
+                        }
+                    };
+                    self.mirror_to_vault(&item, topic);
                 }
             }
             Operation::Delete => {
@@ -275,6 +319,11 @@ impl MemoryConsolidator {
                         return Ok(());
                     }
                     let _ = self.store.delete(id, Some(namespace)).await?;
+                    if let Some(vault) = &self.vault {
+                        if let Err(err) = vault.remove_fact(id) {
+                            warn!("failed to remove fact from vault: {err}");
+                        }
+                    }
                 }
                 if let Some(content) = &result.new_content {
                     if let Some(old) = &result.old_content {
@@ -282,7 +331,7 @@ impl MemoryConsolidator {
                             return Ok(());
                         }
                     }
-                    let _ = self
+                    let item = self
                         .store
                         .add(
                             &sanitize_storage_content(content),
@@ -290,12 +339,24 @@ impl MemoryConsolidator {
                             Some(namespace),
                         )
                         .await?;
+                    self.mirror_to_vault(&item, topic);
                 }
             }
             Operation::Noop => {}
         }
         Ok(())
     }
+
+    fn mirror_to_vault(&self, item: &MemoryItem, topic: Option<&str>) {
+        let Some(vault) = &self.vault else {
+            return;
+        };
+        // Fall back to the topic stored with the memory when re-filing updates.
+        let topic = topic.or_else(|| item.metadata.get("topic").and_then(Value::as_str));
+        if let Err(err) = vault.record_fact(topic, &item.content, &item.id) {
+            warn!("failed to write fact to vault: {err}");
+        }
+    }
 }
 
 fn sanitize_content(text: &str) -> String {
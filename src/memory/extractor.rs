@@ -41,9 +41,11 @@ Rules:
 - Facts only, no opinions or temporary context
 - Self-contained statements
 - Skip greetings and small talk
+- In group chats user messages start with the speaker's name, e.g. "[Anna]: ...". Attribute each fact to that person by name ("Anna prefers window seats"), never to "User", and set "speaker" to the name
 
-Return JSON array: [{"fact": "...", "importance": "high|medium|low"}]
-Example: [{"fact": "User's name is John", "importance": "high"}]
+Return JSON array: [{"fact": "...", "importance": "high|medium|low", "topic": "...", "speaker": "..."}]
+"topic" is a short, reusable category for the fact (e.g. "Work", "Family", "Health", "Preferences").
+Example: [{"fact": "User's name is John", "importance": "high", "topic": "Personal"}]
 
 Facts:"#;
 
@@ -52,6 +54,11 @@ pub struct ExtractedFact {
     pub content: String,
     pub importance: f32,
     pub source: String,
+    pub topic: Option<String>,
+    /// Group-chat participant the fact is about, if known.
+    pub speaker: Option<String>,
+    /// One of the configured memory collections, if the fact fits one.
+    pub collection: Option<String>,
 }
 
 #[derive(Clone)]
@@ -60,6 +67,7 @@ pub struct MemoryExtractor {
     max_facts: usize,
     client: OpenRouterClient,
     trivial_patterns: Vec<Regex>,
+    collections: Vec<String>,
 }
 
 impl MemoryExtractor {
@@ -74,9 +82,16 @@ impl MemoryExtractor {
             max_facts,
             client,
             trivial_patterns,
+            collections: Vec::new(),
         }
     }
 
+    /// Have the model sort each fact into one of `collections`.
+    pub fn with_collections(mut self, collections: Vec<String>) -> Self {
+        self.collections = collections;
+        self
+    }
+
     pub async fn extract(&self, messages: &[ChatMessage]) -> Vec<ExtractedFact> {
         if messages.is_empty() {
             return Vec::new();
@@ -113,8 +128,17 @@ impl MemoryExtractor {
     }
 
     async fn llm_extract(&self, conversation: &str) -> Result<Vec<ExtractedFact>> {
-        let prompt =
+        let mut prompt =
             EXTRACTION_PROMPT.replace("{conversation}", &sanitize_for_prompt(conversation));
+        if !self.collections.is_empty() {
+            prompt = prompt.replace(
+                "\n\nFacts:",
+                &format!(
+                    "\nAlso set \"collection\" to exactly one of: {}. Omit it when none fits.\n\nFacts:",
+                    self.collections.join(", ")
+                ),
+            );
+        }
         let response = self
             .client
             .chat_completion(
@@ -144,6 +168,12 @@ impl MemoryExtractor {
                 content,
                 importance,
                 source: "llm".to_string(),
+                topic: item.topic.filter(|t| !t.trim().is_empty()),
+                speaker: item.speaker.filter(|s| !s.trim().is_empty()),
+                collection: item
+                    .collection
+                    .map(|c| c.trim().to_lowercase())
+                    .filter(|c| self.collections.contains(c)),
             });
         }
         Ok(extracted)
@@ -155,6 +185,12 @@ struct ExtractedFactSchema {
     fact: String,
     #[serde(default = "default_importance")]
     importance: String,
+    #[serde(default)]
+    topic: Option<String>,
+    #[serde(default)]
+    speaker: Option<String>,
+    #[serde(default)]
+    collection: Option<String>,
 }
 
 fn default_importance() -> String {
@@ -210,7 +246,8 @@ fn heuristic_extract(messages: &[ChatMessage]) -> Vec<ExtractedFact> {
         if msg.role != "user" {
             continue;
         }
-        let content = msg.content.to_lowercase();
+        let (speaker, text) = split_speaker(&msg.content);
+        let content = text.to_lowercase();
         for (indicator, importance) in patterns.iter() {
             if let Some(start) = content.find(indicator) {
                 let end = [".", "!", "?", "\n"]
@@ -221,6 +258,9 @@ fn heuristic_extract(messages: &[ChatMessage]) -> Vec<ExtractedFact> {
                 let fact_text = content[start..end].trim();
                 if fact_text.len() > 5 {
                     let mut fact = to_third_person(fact_text);
+                    if let Some(name) = speaker {
+                        fact = fact.replace("User", name);
+                    }
                     if let Some(first) = fact.get_mut(0..1) {
                         first.make_ascii_uppercase();
                     }
@@ -229,6 +269,9 @@ fn heuristic_extract(messages: &[ChatMessage]) -> Vec<ExtractedFact> {
                             content: fact,
                             importance: *importance,
                             source: "heuristic".to_string(),
+                            topic: None,
+                            speaker: speaker.map(str::to_string),
+                            collection: None,
                         });
                     }
                 }
@@ -252,6 +295,16 @@ static THIRD_PERSON_RULES: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(
     ]
 });
 
+/// Split a group-chat message of the form "[Name]: text" into its speaker
+/// and text.
+pub fn split_speaker(content: &str) -> (Option<&str>, &str) {
+    content
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("]: "))
+        .filter(|(name, _)| !name.is_empty() && name.len() <= 64 && !name.contains('\n'))
+        .map_or((None, content), |(name, text)| (Some(name), text))
+}
+
 fn to_third_person(text: &str) -> String {
     let mut result = text.to_string();
     for (re, replacement) in THIRD_PERSON_RULES.iter() {
@@ -302,3 +355,29 @@ fn strip_code_fences(content: &str) -> String {
     }
     trimmed.to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{heuristic_extract, split_speaker};
+    use crate::memory::client::ChatMessage;
+
+    #[test]
+    fn attributes_group_facts_to_the_speaker() {
+        assert_eq!(
+            split_speaker("[Anna]: hi there"),
+            (Some("Anna"), "hi there")
+        );
+        assert_eq!(
+            split_speaker("[not a name] text"),
+            (None, "[not a name] text")
+        );
+
+        let facts = heuristic_extract(&[ChatMessage {
+            role: "user".to_string(),
+            content: "[Anna]: I prefer window seats.".to_string(),
+        }]);
+        assert_eq!(facts.len(), 1);
+        assert_eq!(facts[0].speaker.as_deref(), Some("Anna"));
+        assert!(facts[0].content.starts_with("Anna"), "{}", facts[0].content);
+    }
+}
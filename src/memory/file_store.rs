@@ -1,3 +1,4 @@
+use crate::memory::vault::{strip_frontmatter, ObsidianVault};
 use chrono::{Datelike, Local};
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -11,6 +12,7 @@ pub struct MemoryStore {
     workspace: PathBuf,
     memory_dir: PathBuf,
     memory_file: PathBuf,
+    vault: Option<ObsidianVault>,
 }
 
 impl MemoryStore {
@@ -21,20 +23,33 @@ impl MemoryStore {
             workspace,
             memory_dir,
             memory_file,
+            vault: None,
         }
     }
 
+    /// Keep the memory directory as an Obsidian vault (see [`ObsidianVault`]).
+    pub fn with_obsidian_vault(mut self) -> Self {
+        self.vault = Some(ObsidianVault::new(self.memory_dir.clone()));
+        self
+    }
+
+    pub fn vault(&self) -> Option<&ObsidianVault> {
+        self.vault.as_ref()
+    }
+
     pub fn get_today_file(&self) -> PathBuf {
         self.memory_dir.join(format!("{}.md", today_date()))
     }
 
     pub fn read_today(&self) -> String {
         let today_file = self.get_today_file();
-        fs::read_to_string(today_file).unwrap_or_default()
+        let text = fs::read_to_string(today_file).unwrap_or_default();
+        strip_frontmatter(&text).to_string()
     }
 
     pub fn read_long_term(&self) -> String {
-        fs::read_to_string(&self.memory_file).unwrap_or_default()
+        let text = fs::read_to_string(&self.memory_file).unwrap_or_default();
+        strip_frontmatter(&text).to_string()
     }
 
     pub fn get_memory_context(&self, max_chars: usize) -> String {
@@ -80,7 +95,7 @@ fn today_date() -> String {
     format!("{:04}-{:02}-{:02}", now.year(), now.month(), now.day())
 }
 
-fn truncate(content: &str, max_chars: usize) -> String {
+pub(crate) fn truncate(content: &str, max_chars: usize) -> String {
     if content.len() <= max_chars {
         return content.to_string();
     }
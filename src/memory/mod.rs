@@ -2,4 +2,5 @@ pub mod client;
 pub mod consolidator;
 pub mod extractor;
 pub mod file_store;
+pub mod vault;
 pub mod vector_store;
@@ -0,0 +1,249 @@
+use anyhow::Result;
+use chrono::Local;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TOPICS_DIR: &str = "topics";
+const DEFAULT_TOPIC: &str = "General";
+const INDEX_START: &str = "<!-- femtobot:topics -->";
+const INDEX_END: &str = "<!-- /femtobot:topics -->";
+
+/// Writes the memory directory as an Obsidian vault: one note per topic
+/// under `topics/` holding its facts (each with a `^block` id tied to the
+/// vector memory id), daily notes with frontmatter that link the topics
+/// learned that day, and a managed topic index in `MEMORY.md`.
+#[derive(Clone)]
+pub struct ObsidianVault {
+    dir: PathBuf,
+}
+
+impl ObsidianVault {
+    pub fn new(memory_dir: PathBuf) -> Self {
+        Self { dir: memory_dir }
+    }
+
+    /// Add or replace the fact with `memory_id` in its topic note and link
+    /// it from today's daily note.
+    pub fn record_fact(&self, topic: Option<&str>, content: &str, memory_id: &str) -> Result<()> {
+        let topic = topic_title(topic.unwrap_or(DEFAULT_TOPIC));
+        let content = content
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace('\n', " ");
+        let today = today();
+        self.remove_fact(memory_id)?;
+
+        let topics = self.dir.join(TOPICS_DIR);
+        fs::create_dir_all(&topics)?;
+        let path = topics.join(format!("{topic}.md"));
+        let mut note = match fs::read_to_string(&path) {
+            Ok(existing) => set_frontmatter_field(&existing, "updated", &today),
+            Err(_) => format!(
+                "---\ntype: topic\ncreated: {today}\nupdated: {today}\ntags: [femtobot/memory]\n---\n# {topic}\n\nPart of [[MEMORY]].\n\n"
+            ),
+        };
+        if !note.ends_with('\n') {
+            note.push('\n');
+        }
+        note.push_str(&format!(
+            "- {content} (learned [[{today}]]) ^{}\n",
+            block_id(memory_id)
+        ));
+        fs::write(&path, note)?;
+
+        let daily = self.ensure_daily_note()?;
+        let mut text = fs::read_to_string(&daily).unwrap_or_default();
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&format!("- Learned ([[{topic}]]): {content}\n"));
+        fs::write(&daily, text)?;
+
+        self.update_index()
+    }
+
+    /// Remove the fact with `memory_id` from whichever topic note holds it.
+    pub fn remove_fact(&self, memory_id: &str) -> Result<bool> {
+        let marker = format!(" ^{}", block_id(memory_id));
+        let Ok(entries) = fs::read_dir(self.dir.join(TOPICS_DIR)) else {
+            return Ok(false);
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let Ok(text) = fs::read_to_string(&path) else {
+                continue;
+            };
+            if !text.lines().any(|l| l.trim_end().ends_with(&marker)) {
+                continue;
+            }
+            let kept: Vec<&str> = text
+                .lines()
+                .filter(|l| !l.trim_end().ends_with(&marker))
+                .collect();
+            fs::write(&path, format!("{}\n", kept.join("\n")))?;
+            self.update_index()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Today's daily note, created with frontmatter if missing.
+    pub fn ensure_daily_note(&self) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let today = today();
+        let path = self.dir.join(format!("{today}.md"));
+        if !path.exists() {
+            fs::write(
+                &path,
+                format!(
+                    "---\ntype: daily\ndate: {today}\ntags: [femtobot/daily]\n---\n# {today}\n\nSee [[MEMORY]].\n\n"
+                ),
+            )?;
+        }
+        Ok(path)
+    }
+
+    /// Rewrite the managed topic list in `MEMORY.md`, leaving the rest of
+    /// the note untouched.
+    fn update_index(&self) -> Result<()> {
+        let mut topics: Vec<(String, usize)> = fs::read_dir(self.dir.join(TOPICS_DIR))
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .filter_map(|e| {
+                        let path = e.path();
+                        let name = path.file_stem()?.to_string_lossy().into_owned();
+                        (path.extension()? == "md").then(|| (name, count_facts(&path)))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        topics.sort();
+        let list = topics
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(name, count)| format!("- [[{name}]] ({count})"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let block = format!("{INDEX_START}\n## Topics\n{list}\n{INDEX_END}");
+
+        let path = self.dir.join("MEMORY.md");
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        let updated = match (existing.find(INDEX_START), existing.find(INDEX_END)) {
+            (Some(start), Some(end)) if end > start => format!(
+                "{}{block}{}",
+                &existing[..start],
+                &existing[end + INDEX_END.len()..]
+            ),
+            _ if existing.trim().is_empty() => {
+                format!("---\ntype: index\ntags: [femtobot/memory]\n---\n# Memory\n\n{block}\n")
+            }
+            _ => format!("{}\n\n{block}\n", existing.trim_end()),
+        };
+        fs::write(&path, updated)?;
+        Ok(())
+    }
+}
+
+/// Note body without its YAML frontmatter.
+pub fn strip_frontmatter(text: &str) -> &str {
+    let Some(rest) = text.strip_prefix("---\n") else {
+        return text;
+    };
+    match rest.find("\n---\n") {
+        Some(end) => rest[end + 5..].trim_start_matches('\n'),
+        None => text,
+    }
+}
+
+fn set_frontmatter_field(text: &str, field: &str, value: &str) -> String {
+    if !text.starts_with("---\n") {
+        return text.to_string();
+    }
+    let prefix = format!("{field}:");
+    let mut in_frontmatter = true;
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            if i > 0 && line == "---" {
+                in_frontmatter = false;
+            }
+            if in_frontmatter && line.starts_with(&prefix) {
+                format!("{prefix} {value}")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
+
+fn count_facts(path: &Path) -> usize {
+    fs::read_to_string(path)
+        .map(|t| t.lines().filter(|l| l.starts_with("- ")).count())
+        .unwrap_or(0)
+}
+
+/// Topic names become file names and wiki-link targets, so drop characters
+/// Obsidian does not allow in either.
+fn topic_title(raw: &str) -> String {
+    let cleaned: String = raw
+        .chars()
+        .filter(|c| {
+            !matches!(
+                c,
+                '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']'
+            )
+        })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut chars = cleaned.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).take(60).collect(),
+        None => DEFAULT_TOPIC.to_string(),
+    }
+}
+
+fn block_id(memory_id: &str) -> String {
+    let id: String = memory_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(12)
+        .collect();
+    format!("m-{id}")
+}
+
+fn today() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_frontmatter, ObsidianVault};
+
+    #[test]
+    fn records_and_moves_facts_between_topics() {
+        let dir = std::env::temp_dir().join(format!("femtobot-vault-{}", std::process::id()));
+        let vault = ObsidianVault::new(dir.clone());
+        vault
+            .record_fact(Some("work"), "Works at Acme", "abc-123")
+            .unwrap();
+        let work = std::fs::read_to_string(dir.join("topics/Work.md")).unwrap();
+        assert!(work.starts_with("---\ntype: topic"));
+        assert!(work.contains("- Works at Acme (learned [["));
+        assert!(work.trim_end().ends_with("^m-abc123"));
+        let index = std::fs::read_to_string(dir.join("MEMORY.md")).unwrap();
+        assert!(index.contains("- [[Work]] (1)"));
+
+        vault
+            .record_fact(Some("Career"), "Works at Initech", "abc-123")
+            .unwrap();
+        let work = std::fs::read_to_string(dir.join("topics/Work.md")).unwrap();
+        assert!(!work.contains("Acme"));
+        let index = std::fs::read_to_string(dir.join("MEMORY.md")).unwrap();
+        assert!(index.contains("- [[Career]] (1)") && !index.contains("[[Work]]"));
+        assert!(strip_frontmatter(&index).starts_with("# Memory"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
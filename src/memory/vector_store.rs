@@ -1,6 +1,9 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::future::Future;
 use std::path::PathBuf;
 use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
@@ -10,10 +13,12 @@ use rig::vector_store::{VectorStoreError, VectorStoreIndex};
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tracing::warn;
+use tracing::{info, warn};
 use uuid::Uuid;
 
+use crate::config::RecallPolicy;
 use crate::memory::client::OpenRouterClient;
+use crate::memory::file_store::CHARS_PER_TOKEN;
 use tokio::sync::Mutex as AsyncMutex;
 
 const MAX_CONTENT_LENGTH: usize = 8192;
@@ -36,10 +41,141 @@ pub struct MemoryItem {
     pub namespace: String,
 }
 
+impl MemoryItem {
+    /// Named collection the memory was sorted into, if any.
+    pub fn collection(&self) -> Option<&str> {
+        self.metadata.get("collection").and_then(Value::as_str)
+    }
+}
+
 /// Default priority weight used when blending similarity with priority score.
 const DEFAULT_PRIORITY_WEIGHT: f32 = 0.3;
 /// Default similarity threshold for vector search.
 const DEFAULT_THRESHOLD: f32 = 0.0;
+/// Days without a recall after which a memory's freshness has fallen to ~37%.
+const IDLE_DECAY_DAYS: f64 = 60.0;
+/// Added to the ranking score of memories in a collection the turn's recall
+/// policy boosts.
+const COLLECTION_BOOST: f32 = 0.15;
+/// Default share of priority that comes from freshness (`memory.recency_weight`).
+pub const DEFAULT_RECENCY_WEIGHT: f64 = 0.25;
+
+tokio::task_local! {
+    static RECALLED: RefCell<Vec<String>>;
+    static RECALL_POLICY: RecallPolicy;
+}
+
+/// Run `fut` (an agent turn) with its dynamic-context recall limited by
+/// `policy`.
+pub async fn with_recall_policy<F: Future>(policy: RecallPolicy, fut: F) -> F::Output {
+    RECALL_POLICY.scope(policy, fut).await
+}
+
+/// `text` cut to about `max_tokens` tokens; 0 leaves it whole.
+fn clip_to_tokens(text: &str, max_tokens: usize) -> String {
+    let max_chars = max_tokens.saturating_mul(CHARS_PER_TOKEN);
+    if max_tokens == 0 || text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// Run `fut` (an agent turn) and return the ids of the memories recalled
+/// into its dynamic context alongside its output.
+pub async fn track_recalls<F: Future>(fut: F) -> (F::Output, Vec<String>) {
+    RECALLED
+        .scope(RefCell::new(Vec::new()), async {
+            let out = fut.await;
+            let ids = RECALLED.with(|ids| ids.take());
+            (out, ids)
+        })
+        .await
+}
+
+/// Priority from stated importance and observed usefulness: memories that
+/// are recalled often into turns that succeed rise, and memories that are
+/// never recalled decay with idle time.
+/// `recency_weight` is the share of the result that comes from freshness;
+/// importance and usefulness split the rest 7:8.
+fn rescored_priority(
+    importance: f64,
+    recalls: i64,
+    useful: i64,
+    idle_days: f64,
+    recency_weight: f64,
+) -> f32 {
+    let recall_score = 1.0 - (-(recalls.max(0) as f64) / 5.0).exp();
+    let success_rate = (useful.max(0) as f64 + 1.0) / (recalls.max(0) as f64 + 2.0);
+    let rest = (importance * 7.0 + recall_score * success_rate * 8.0) / 15.0;
+    (rest * (1.0 - recency_weight) + freshness(idle_days) * recency_weight).clamp(0.0, 1.0) as f32
+}
+
+fn freshness(idle_days: f64) -> f64 {
+    (-idle_days.max(0.0) / IDLE_DECAY_DAYS).exp()
+}
+
+/// How a search scored one memory, for `explain` output.
+#[derive(Debug, Clone)]
+pub struct ScoreBreakdown {
+    pub item: MemoryItem,
+    /// Raw cosine similarity between the query and the memory.
+    pub similarity: f32,
+    /// Stated importance from the memory's metadata (0.5 when unset).
+    pub importance: f64,
+    /// Days since the memory was last recalled (or created).
+    pub idle_days: f64,
+    /// What freshness adds to the priority: `recency_weight * freshness`.
+    pub recency: f64,
+    /// Ranking score: similarity blended with the stored priority.
+    pub combined: f32,
+}
+
+impl ScoreBreakdown {
+    pub fn describe(&self) -> String {
+        format!(
+            "cosine {:.3} · combined {:.3} · priority {:.2} (importance {:.2}, recency +{:.3} after {:.0}d idle, {} recalls) · ns {} · id {}",
+            self.similarity,
+            self.combined,
+            self.item.priority,
+            self.importance,
+            self.recency,
+            self.idle_days,
+            self.item.access_count,
+            self.item.namespace,
+            self.item.id
+        )
+    }
+}
+
+/// Which memories a bulk operation applies to. Empty fields match everything.
+#[derive(Debug, Default, Clone)]
+pub struct MemorySelector {
+    pub namespace: Option<String>,
+    /// Metadata fields that must be equal; for array fields (e.g. `tags`),
+    /// the value must be one of the elements.
+    pub metadata: HashMap<String, Value>,
+    /// Case-insensitive substring of the content.
+    pub contains: Option<String>,
+}
+
+impl MemorySelector {
+    pub fn matches(&self, item: &MemoryItem) -> bool {
+        let metadata_ok = self
+            .metadata
+            .iter()
+            .all(|(key, wanted)| match item.metadata.get(key) {
+                Some(Value::Array(values)) => values.contains(wanted),
+                Some(value) => value == wanted,
+                None => false,
+            });
+        let content_ok = self
+            .contains
+            .as_deref()
+            .is_none_or(|needle| item.content.to_lowercase().contains(&needle.to_lowercase()));
+        metadata_ok && content_ok
+    }
+}
 
 #[derive(Clone)]
 pub struct EmbeddingService {
@@ -82,6 +218,7 @@ pub struct VectorMemoryStore {
     embedder: EmbeddingService,
     max_memories: usize,
     namespace: String,
+    recency_weight: f64,
 }
 
 impl VectorMemoryStore {
@@ -101,7 +238,208 @@ impl VectorMemoryStore {
             embedder,
             max_memories,
             namespace: validate_namespace(&namespace)?,
+            recency_weight: DEFAULT_RECENCY_WEIGHT,
+        })
+    }
+
+    /// Cap each namespace at `max` memories instead of `memory.max_memories`.
+    pub fn with_max_memories(mut self, max: usize) -> Self {
+        self.max_memories = max;
+        self
+    }
+
+    /// Set the share of priority that comes from recent recalls.
+    pub fn with_recency_weight(mut self, weight: f64) -> Self {
+        self.recency_weight = weight.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Count a recall of `ids` into a prompt.
+    async fn record_recalls(&self, ids: Vec<String>) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let _ = RECALLED.try_with(|recalled| recalled.borrow_mut().extend(ids.iter().cloned()));
+        let now = Utc::now().to_rfc3339();
+        self.with_conn(move |conn| {
+            for id in ids {
+                conn.execute(
+                    "UPDATE memories SET access_count = access_count + 1, last_recalled_at = ?1 WHERE id = ?2",
+                    params![now, id],
+                )?;
+            }
+            Ok(())
         })
+        .await
+    }
+
+    /// Credit memories recalled into a turn that produced a reply.
+    pub async fn record_outcome(&self, ids: Vec<String>, succeeded: bool) -> Result<()> {
+        if ids.is_empty() || !succeeded {
+            return Ok(());
+        }
+        self.with_conn(move |conn| {
+            for id in ids {
+                conn.execute(
+                    "UPDATE memories SET useful_count = useful_count + 1 WHERE id = ?1",
+                    params![id],
+                )?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Recompute every memory's priority from its importance and recall
+    /// history. Returns the number of memories re-scored.
+    pub async fn rescore(&self) -> Result<usize> {
+        let now = Utc::now();
+        let recency_weight = self.recency_weight;
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, metadata, created_at, access_count, useful_count, last_recalled_at FROM memories",
+            )?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, i64>(3)?,
+                        row.get::<_, i64>(4)?,
+                        row.get::<_, Option<String>>(5)?,
+                    ))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let tx = conn.unchecked_transaction()?;
+            for (id, metadata, created_at, recalls, useful, last_recalled) in &rows {
+                let importance = serde_json::from_str::<HashMap<String, Value>>(metadata)
+                    .ok()
+                    .and_then(|m| m.get("importance").and_then(Value::as_f64))
+                    .unwrap_or(0.5);
+                let idle_since = last_recalled
+                    .as_deref()
+                    .unwrap_or(created_at)
+                    .parse::<DateTime<Utc>>()
+                    .unwrap_or(now);
+                let idle_days = (now - idle_since).num_seconds() as f64 / 86400.0;
+                let priority =
+                    rescored_priority(importance, *recalls, *useful, idle_days, recency_weight);
+                tx.execute(
+                    "UPDATE memories SET priority = ?1 WHERE id = ?2",
+                    params![priority, id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(rows.len())
+        })
+        .await
+    }
+
+    /// Every memory matching `selector`, oldest first.
+    pub async fn select(&self, selector: &MemorySelector) -> Result<Vec<MemoryItem>> {
+        let namespace = selector
+            .namespace
+            .as_deref()
+            .map(validate_namespace)
+            .transpose()?;
+        let items = self
+            .with_conn(move |conn| {
+                let sql = "SELECT id, content, embedding, metadata, created_at, updated_at, access_count, priority, namespace FROM memories";
+                let items = match namespace {
+                    Some(ns) => conn
+                        .prepare(&format!("{sql} WHERE namespace = ?1 ORDER BY created_at"))?
+                        .query_map(params![ns], parse_memory_row)?
+                        .collect::<std::result::Result<Vec<_>, _>>()?,
+                    None => conn
+                        .prepare(&format!("{sql} ORDER BY created_at"))?
+                        .query_map([], parse_memory_row)?
+                        .collect::<std::result::Result<Vec<_>, _>>()?,
+                };
+                Ok(items)
+            })
+            .await?;
+        Ok(items
+            .into_iter()
+            .filter(|item| selector.matches(item))
+            .collect())
+    }
+
+    /// Delete memories by id in any namespace. Returns how many were removed.
+    pub async fn delete_many(&self, ids: Vec<String>) -> Result<usize> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            let mut removed = 0;
+            for id in ids {
+                removed += tx.execute("DELETE FROM memories WHERE id = ?1", params![id])?;
+            }
+            tx.commit()?;
+            Ok(removed)
+        })
+        .await
+    }
+
+    /// Merge `patch` into the metadata of `items` (a null value removes the
+    /// field) and optionally move them to another namespace.
+    pub async fn retag(
+        &self,
+        items: Vec<MemoryItem>,
+        patch: HashMap<String, Value>,
+        to_namespace: Option<&str>,
+    ) -> Result<usize> {
+        let to_namespace = to_namespace.map(validate_namespace).transpose()?;
+        let now = Utc::now().to_rfc3339();
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            let mut changed = 0;
+            for mut item in items {
+                for (key, value) in &patch {
+                    if value.is_null() {
+                        item.metadata.remove(key);
+                    } else {
+                        item.metadata.insert(key.clone(), value.clone());
+                    }
+                }
+                let namespace = to_namespace.clone().unwrap_or(item.namespace);
+                changed += tx.execute(
+                    "UPDATE memories SET metadata = ?1, namespace = ?2, updated_at = ?3 WHERE id = ?4",
+                    params![serde_json::to_string(&item.metadata)?, namespace, now, item.id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(changed)
+        })
+        .await
+    }
+
+    /// Copy every memory of namespace `from` into `to`, skipping facts `to`
+    /// already holds. Returns how many were copied.
+    pub async fn copy_namespace(&self, from: &str, to: &str) -> Result<usize> {
+        let from = validate_namespace(from)?;
+        let to = validate_namespace(to)?;
+        self.with_conn(move |conn| {
+            let ids = conn
+                .prepare("SELECT id FROM memories WHERE namespace = ?1 ORDER BY created_at")?
+                .query_map(params![from], |row| row.get::<_, String>(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let tx = conn.unchecked_transaction()?;
+            let mut copied = 0;
+            for id in ids {
+                copied += tx.execute(
+                    "INSERT INTO memories (id, content, embedding, metadata, created_at, updated_at, access_count, priority, namespace)
+                     SELECT ?1, content, embedding, metadata, created_at, updated_at, 0, priority, ?2 FROM memories src
+                     WHERE src.id = ?3 AND NOT EXISTS (SELECT 1 FROM memories dst WHERE dst.namespace = ?2 AND dst.content = src.content)",
+                    params![Uuid::new_v4().to_string(), to, id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(copied)
+        })
+        .await
+    }
+
+    pub fn embedder(&self) -> &EmbeddingService {
+        &self.embedder
     }
 
     /// Run a blocking closure against the database connection on Tokio's
@@ -310,6 +648,96 @@ impl VectorMemoryStore {
             Ok(trimmed)
         }).await
     }
+
+    /// Score every memory in `namespace` (all namespaces when `None`)
+    /// against `query` without a similarity threshold, best first, with the
+    /// parts each score is made of.
+    pub async fn explain(
+        &self,
+        query: &str,
+        top_k: usize,
+        namespace: Option<&str>,
+    ) -> Result<Vec<ScoreBreakdown>> {
+        let namespace = namespace.map(validate_namespace).transpose()?;
+        let query_embedding = self.embedder.embed(query).await?;
+        let recency_weight = self.recency_weight;
+        let now = Utc::now();
+
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, content, embedding, metadata, created_at, updated_at, access_count, priority, namespace, last_recalled_at FROM memories WHERE ?1 IS NULL OR namespace = ?1",
+            )?;
+            let rows = stmt.query_map(params![namespace], |row| {
+                Ok((parse_memory_row(row)?, row.get::<_, Option<String>>(9)?))
+            })?;
+
+            let mut results = Vec::new();
+            for row in rows {
+                let (item, last_recalled) = row?;
+                let similarity = cosine_similarity(&query_embedding, &item.embedding);
+                let importance = item
+                    .metadata
+                    .get("importance")
+                    .and_then(Value::as_f64)
+                    .unwrap_or(0.5);
+                let idle_since = last_recalled
+                    .and_then(|at| at.parse::<DateTime<Utc>>().ok())
+                    .unwrap_or(item.created_at);
+                let idle_days = ((now - idle_since).num_seconds() as f64 / 86400.0).max(0.0);
+                let combined = similarity * (1.0 - DEFAULT_PRIORITY_WEIGHT)
+                    + item.priority * DEFAULT_PRIORITY_WEIGHT;
+                results.push(ScoreBreakdown {
+                    item,
+                    similarity,
+                    importance,
+                    idle_days,
+                    recency: recency_weight * freshness(idle_days),
+                    combined,
+                });
+            }
+
+            results.sort_by(|a, b| {
+                b.combined
+                    .partial_cmp(&a.combined)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            results.truncate(top_k);
+            Ok(results)
+        })
+        .await
+    }
+
+    /// Semantic search across every namespace, used for history lookups that
+    /// are not tied to a single session.
+    pub async fn search_all_namespaces(
+        &self,
+        query: &str,
+        top_k: usize,
+        threshold: f32,
+    ) -> Result<Vec<(MemoryItem, f32)>> {
+        let query_embedding = self.embedder.embed(query).await?;
+
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, content, embedding, metadata, created_at, updated_at, access_count, priority, namespace FROM memories",
+            )?;
+            let rows = stmt.query_map([], parse_memory_row)?;
+
+            let mut results: Vec<(MemoryItem, f32)> = Vec::new();
+            for row in rows {
+                let item = row?;
+                let similarity = cosine_similarity(&query_embedding, &item.embedding);
+                if similarity >= threshold {
+                    results.push((item, similarity));
+                }
+            }
+
+            results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            results.truncate(top_k);
+            Ok(results)
+        })
+        .await
+    }
 }
 
 fn parse_memory_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<MemoryItem> {
@@ -371,9 +799,35 @@ fn init_db(conn: &Connection) -> Result<()> {
         "CREATE INDEX IF NOT EXISTS idx_memories_namespace ON memories(namespace)",
         [],
     )?;
+    let columns = conn
+        .prepare("PRAGMA table_info(memories)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    if !columns.iter().any(|c| c == "useful_count") {
+        conn.execute(
+            "ALTER TABLE memories ADD COLUMN useful_count INTEGER DEFAULT 0",
+            [],
+        )?;
+    }
+    if !columns.iter().any(|c| c == "last_recalled_at") {
+        conn.execute("ALTER TABLE memories ADD COLUMN last_recalled_at TEXT", [])?;
+    }
     Ok(())
 }
 
+/// Re-score memory priorities every `every` in the background.
+pub fn start_rescoring(store: VectorMemoryStore, every: Duration) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(every).await;
+            match store.rescore().await {
+                Ok(count) => info!("re-scored {count} memories"),
+                Err(err) => warn!("memory re-scoring failed: {err}"),
+            }
+        }
+    });
+}
+
 fn validate_namespace(namespace: &str) -> Result<String> {
     if NAMESPACE_RE.is_match(namespace) {
         return Ok(namespace.to_string());
@@ -438,7 +892,7 @@ fn bytes_to_f32s(bytes: &[u8]) -> Vec<f32> {
     out
 }
 
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     if a.is_empty() || b.is_empty() {
         return 0.0;
     }
@@ -537,11 +991,23 @@ impl VectorStoreIndex for VectorMemoryStore {
     {
         async move {
             let query_text = req.query().to_string();
-            let samples = req.samples() as usize;
+            let policy = RECALL_POLICY.try_with(Clone::clone).ok();
+            if policy
+                .as_ref()
+                .is_some_and(|policy| !policy.enabled || policy.samples == 0)
+            {
+                return Ok(Vec::new());
+            }
+            let samples = policy.as_ref().map_or(req.samples() as usize, |policy| {
+                policy.samples.min(req.samples() as usize)
+            });
             let threshold = req
                 .threshold()
                 .map(|t| t as f32)
+                .or(policy.as_ref().map(|policy| policy.min_similarity))
                 .unwrap_or(DEFAULT_THRESHOLD);
+            let max_item_tokens = policy.as_ref().map_or(0, |policy| policy.max_item_tokens);
+            let policy = policy.unwrap_or_default();
             let (filter_ns, priority_weight) = match req.filter() {
                 Some(f) => (
                     f.namespace.clone(),
@@ -577,10 +1043,17 @@ impl VectorStoreIndex for VectorMemoryStore {
                     let mut results: Vec<(MemoryItem, f32)> = Vec::new();
                     for row in rows {
                         let item = row?;
+                        let collection = item.collection();
+                        if !policy.admits(collection) {
+                            continue;
+                        }
                         let similarity = cosine_similarity(&query_embedding, &item.embedding);
                         if similarity >= threshold {
-                            let combined = similarity * (1.0 - priority_weight)
+                            let mut combined = similarity * (1.0 - priority_weight)
                                 + item.priority * priority_weight;
+                            if policy.boosts(collection) {
+                                combined += COLLECTION_BOOST;
+                            }
                             results.push((item, combined));
                         }
                     }
@@ -603,10 +1076,19 @@ impl VectorStoreIndex for VectorMemoryStore {
                 }
             };
 
+            let recalled = scored_items
+                .iter()
+                .map(|(item, _)| item.id.clone())
+                .collect();
+            if let Err(err) = self.record_recalls(recalled).await {
+                warn!("failed to record memory recalls: {err}");
+            }
+
             // Convert each MemoryItem to (score, id, T) by serializing to JSON
             // then deserializing to the caller's expected type.
             let mut out = Vec::with_capacity(scored_items.len());
-            for (item, score) in scored_items {
+            for (mut item, score) in scored_items {
+                item.content = clip_to_tokens(&item.content, max_item_tokens);
                 let id = item.id.clone();
                 let json_value = serde_json::to_value(&item)?;
                 let doc: T = serde_json::from_value(json_value)?;
@@ -630,3 +1112,53 @@ impl VectorStoreIndex for VectorMemoryStore {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{rescored_priority, MemoryItem, MemorySelector, DEFAULT_RECENCY_WEIGHT};
+    use chrono::Utc;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn selector_matches_metadata_tags_and_text() {
+        let item = MemoryItem {
+            id: "m1".to_string(),
+            content: "Flight to Tokyo leaves at 9".to_string(),
+            embedding: Vec::new(),
+            metadata: HashMap::from([
+                ("topic".to_string(), json!("Travel")),
+                ("tags".to_string(), json!(["project:japan", "flights"])),
+            ]),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            access_count: 0,
+            priority: 0.5,
+            namespace: "default".to_string(),
+        };
+        let mut selector = MemorySelector {
+            metadata: HashMap::from([("tags".to_string(), json!("project:japan"))]),
+            contains: Some("tokyo".to_string()),
+            ..Default::default()
+        };
+        assert!(selector.matches(&item));
+        selector.metadata.insert("topic".to_string(), json!("Work"));
+        assert!(!selector.matches(&item));
+    }
+
+    #[test]
+    fn useful_memories_rise_and_idle_ones_decay() {
+        let w = DEFAULT_RECENCY_WEIGHT;
+        let fresh = rescored_priority(0.5, 0, 0, 0.0, w);
+        let useful = rescored_priority(0.5, 20, 18, 1.0, w);
+        let unhelpful = rescored_priority(0.5, 20, 0, 1.0, w);
+        let forgotten = rescored_priority(0.5, 0, 0, 180.0, w);
+        assert!(useful > fresh && useful > unhelpful);
+        assert!(forgotten < fresh && forgotten < unhelpful);
+        assert!((0.0..=1.0).contains(&useful));
+        assert_eq!(
+            rescored_priority(0.5, 0, 0, 180.0, 0.0),
+            rescored_priority(0.5, 0, 0, 0.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! In-process `mock` channel for tests and local development. At start it
+//! plays `channels.mock.script`, a JSONL file of inbound messages, waiting
+//! for the bot's reply to each before sending the next:
+//!
+//! ```json
+//! {"content": "remember to buy milk"}
+//! {"chat_id": "group", "sender_id": "anna", "is_group": true, "content": "hi"}
+//! ```
+//!
+//! Every message addressed to the channel is delivered to
+//! `channels.mock.transcript` (JSONL, default `<data_dir>/mock_channel.jsonl`)
+//! and kept in memory for [`MockChannel::sent`]. Together with the `mock`
+//! provider this runs the whole pipeline without any chat service or API key.
+
+use crate::bus::{InboundMessage, MessageBus, OutboundMessage};
+use crate::channel::{self, Channel};
+use crate::config::AppConfig;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// How long a scripted message waits for its reply before the next is sent.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Deserialize)]
+struct ScriptedMessage {
+    #[serde(default = "default_id")]
+    chat_id: String,
+    #[serde(default = "default_id")]
+    sender_id: String,
+    #[serde(default)]
+    is_group: bool,
+    content: String,
+}
+
+fn default_id() -> String {
+    "mock".to_string()
+}
+
+pub struct MockChannel {
+    cfg: AppConfig,
+    sent: Arc<Mutex<Vec<OutboundMessage>>>,
+}
+
+impl MockChannel {
+    pub fn new(cfg: AppConfig) -> Self {
+        Self {
+            cfg,
+            sent: Arc::default(),
+        }
+    }
+
+    /// Messages delivered so far, oldest first.
+    pub fn sent(&self) -> Vec<OutboundMessage> {
+        self.sent
+            .lock()
+            .map(|sent| sent.clone())
+            .unwrap_or_default()
+    }
+
+    fn transcript_path(&self) -> PathBuf {
+        self.cfg
+            .mock_channel_transcript
+            .clone()
+            .unwrap_or_else(|| self.cfg.data_dir.join("mock_channel.jsonl"))
+    }
+
+    fn deliver(&self, msg: &OutboundMessage) -> Result<()> {
+        if let Ok(mut sent) = self.sent.lock() {
+            sent.push(msg.clone());
+        }
+        let path = self.transcript_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let attachments: Vec<&str> = msg
+            .meta
+            .attachments
+            .iter()
+            .map(|attachment| attachment.name.as_str())
+            .collect();
+        let line = json!({
+            "ts": chrono::Utc::now().to_rfc3339(),
+            "chat_id": msg.chat_id,
+            "content": msg.content,
+            "buttons": msg.meta.buttons,
+            "attachments": attachments,
+            "correlation_id": msg.meta.correlation_id,
+        });
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{line}")?;
+        Ok(())
+    }
+}
+
+impl Channel for MockChannel {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn start(self: Arc<Self>, bus: MessageBus) -> JoinHandle<()> {
+        channel::spawn(self.name(), serve(self, bus))
+    }
+
+    fn supports_buttons(&self) -> bool {
+        true
+    }
+
+    fn supports_attachments(&self) -> bool {
+        true
+    }
+}
+
+async fn serve(channel: Arc<MockChannel>, bus: MessageBus) -> Result<()> {
+    let mut outbound = bus.outbound_queue(channel.name());
+    let forwarder = channel.clone();
+    let delivery_bus = bus.clone();
+    tokio::spawn(async move {
+        while let Some(msg) = outbound.recv().await {
+            match forwarder.deliver(&msg) {
+                Ok(()) => delivery_bus.report_delivery(&msg, true),
+                Err(err) => delivery_bus.report_failure(&msg, err.to_string()),
+            }
+        }
+    });
+
+    let Some(path) = channel.cfg.mock_channel_script.clone() else {
+        return Ok(());
+    };
+    let raw = std::fs::read_to_string(&path)
+        .map_err(|err| anyhow!("cannot read script {}: {err}", path.display()))?;
+    let script = raw
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str::<ScriptedMessage>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| anyhow!("invalid script {}: {err}", path.display()))?;
+    info!(
+        "mock channel playing {} message(s) from {}",
+        script.len(),
+        path.display()
+    );
+    for scripted in script {
+        let mut replies = bus.subscribe_outbound();
+        let chat_id = scripted.chat_id.clone();
+        bus.publish_inbound(InboundMessage {
+            channel: channel.name().to_string(),
+            chat_id: scripted.chat_id,
+            sender_id: scripted.sender_id,
+            is_group: scripted.is_group,
+            content: scripted.content,
+            ..Default::default()
+        })
+        .await;
+        let replied = tokio::time::timeout(REPLY_TIMEOUT, async {
+            loop {
+                match replies.recv().await {
+                    Ok(msg) if msg.channel == "mock" && msg.chat_id == chat_id => break,
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+        .await;
+        if replied.is_err() {
+            warn!("mock channel: no reply in chat {chat_id} within {REPLY_TIMEOUT:?}");
+        }
+    }
+    info!(
+        "mock channel script done; {} message(s) delivered",
+        channel.sent().len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockChannel;
+    use crate::agent::AgentLoop;
+    use crate::bus::MessageBus;
+    use crate::channel::Channel;
+    use crate::config::{AppConfig, ProviderKind};
+    use crate::cron::CronService;
+    use crate::notify::Notifier;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn scripted_chat_runs_through_the_agent_and_tools() {
+        let dir = std::env::temp_dir().join(format!("femtobot-mock-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let notes = dir.join("notes.md");
+        let fixture = serde_json::json!([
+            {"when": "remember", "tool_calls": [
+                {"name": "write_file", "arguments": {"path": notes, "content": "buy milk"}}
+            ]},
+            {"text": "Noted."}
+        ]);
+        std::fs::write(dir.join("fixture.json"), fixture.to_string()).unwrap();
+        std::fs::write(
+            dir.join("script.jsonl"),
+            "{\"content\": \"remember to buy milk\"}\n{\"content\": \"ping\"}\n",
+        )
+        .unwrap();
+        let mut cfg = AppConfig::defaults();
+        cfg.data_dir = dir.join("data");
+        cfg.workspace_dir = dir.join("workspace");
+        cfg.provider = ProviderKind::Mock;
+        cfg.model = "scripted".to_string();
+        cfg.model_fallbacks = Vec::new();
+        cfg.mock_fixture = Some(dir.join("fixture.json"));
+        cfg.mock_channel = true;
+        cfg.mock_channel_script = Some(dir.join("script.jsonl"));
+
+        let bus = MessageBus::new();
+        let cron = CronService::new(&cfg, bus.clone());
+        let notifier = Notifier::new(&cfg, bus.clone());
+        let agent = AgentLoop::new(cfg.clone(), bus.clone(), cron, notifier);
+        tokio::spawn(agent.run());
+        let channel = Arc::new(MockChannel::new(cfg.clone()));
+        channel.clone().start(bus);
+
+        let mut waited = 0;
+        while channel.sent().len() < 2 && waited < 300 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            waited += 1;
+        }
+        let sent = channel.sent();
+        assert_eq!(sent.len(), 2, "{sent:?}");
+        assert!(sent[0].content.starts_with("Noted."), "{}", sent[0].content);
+        assert!(
+            sent[1].content.starts_with("(mock) ping"),
+            "{}",
+            sent[1].content
+        );
+        assert_eq!(std::fs::read_to_string(notes).unwrap(), "buy milk");
+        let transcript = std::fs::read_to_string(cfg.data_dir.join("mock_channel.jsonl")).unwrap();
+        assert_eq!(transcript.lines().count(), 2);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
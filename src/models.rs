@@ -0,0 +1,255 @@
+//! Model files for offline components, kept in `<data_dir>/models`.
+//! `femtobot models pull` downloads a file, checks its SHA-256 and records
+//! it in `models.json` next to the files; `list` shows what is there and how
+//! much disk it takes, `remove` deletes a model again. Backups skip this
+//! directory since everything in it can be downloaded again.
+
+use crate::config::AppConfig;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const MANIFEST: &str = "models.json";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelEntry {
+    pub name: String,
+    pub url: String,
+    pub sha256: String,
+    pub size: u64,
+    /// Whether the checksum was given up front and matched, rather than
+    /// just recorded from the download.
+    pub verified: bool,
+    pub pulled_at: DateTime<Local>,
+}
+
+impl ModelEntry {
+    pub fn path(&self, data_dir: &Path) -> PathBuf {
+        models_dir(data_dir).join(&self.name)
+    }
+}
+
+pub fn models_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("models")
+}
+
+/// The recorded models, by name.
+pub fn list(data_dir: &Path) -> Result<Vec<ModelEntry>> {
+    let path = models_dir(data_dir).join(MANIFEST);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut entries: Vec<ModelEntry> = serde_json::from_str(&fs::read_to_string(&path)?)
+        .with_context(|| format!("unreadable {}", path.display()))?;
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+fn save(data_dir: &Path, entries: &[ModelEntry]) -> Result<()> {
+    let dir = models_dir(data_dir);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(MANIFEST);
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(entries)?)?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+/// Download `url` into the models directory as `name` (the last URL path
+/// segment when omitted). With `sha256` the download must match it; a
+/// mismatch leaves nothing behind. Pulling an existing name replaces it.
+pub async fn pull(
+    cfg: &AppConfig,
+    url: &str,
+    name: Option<&str>,
+    sha256: Option<&str>,
+) -> Result<ModelEntry> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => file_name_of(url).ok_or_else(|| anyhow!("cannot name {url}; pass --name"))?,
+    };
+    if !valid_name(&name) {
+        bail!("'{name}' is not a valid model name (letters, digits, '.', '-' and '_')");
+    }
+    let expected = sha256.map(|s| s.trim().to_ascii_lowercase());
+    if let Some(expected) = &expected {
+        if expected.len() != 64 || !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("--sha256 must be 64 hex digits");
+        }
+    }
+
+    let dir = models_dir(&cfg.data_dir);
+    fs::create_dir_all(&dir)?;
+    let part = dir.join(format!("{name}.part"));
+    let downloaded = download(cfg, url, &part).await;
+    let (digest, size) = match downloaded {
+        Ok(done) => done,
+        Err(err) => {
+            let _ = fs::remove_file(&part);
+            return Err(err);
+        }
+    };
+    if let Some(expected) = &expected {
+        if &digest != expected {
+            let _ = fs::remove_file(&part);
+            bail!("checksum mismatch for {name}: expected {expected}, got {digest}");
+        }
+    }
+
+    let entry = ModelEntry {
+        name: name.clone(),
+        url: url.to_string(),
+        sha256: digest,
+        size,
+        verified: expected.is_some(),
+        pulled_at: Local::now(),
+    };
+    fs::rename(&part, entry.path(&cfg.data_dir))?;
+    let mut entries = list(&cfg.data_dir)?;
+    entries.retain(|e| e.name != name);
+    entries.push(entry.clone());
+    save(&cfg.data_dir, &entries)?;
+    Ok(entry)
+}
+
+/// Stream `url` into `path`, hashing on the way; returns the hex digest and
+/// the number of bytes written.
+async fn download(cfg: &AppConfig, url: &str, path: &Path) -> Result<(String, u64)> {
+    // Model files can take longer than any request timeout; only the
+    // connect timeout applies.
+    let client = reqwest::Client::builder()
+        .connect_timeout(std::time::Duration::from_secs(
+            cfg.network_web.connect_timeout_secs,
+        ))
+        .build()?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("downloading {url}"))?;
+    if !response.status().is_success() {
+        bail!("downloading {url} failed: HTTP {}", response.status());
+    }
+    let mut file = fs::File::create(path)?;
+    let mut hasher = Sha256::new();
+    let mut size = 0u64;
+    while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
+        file.write_all(&chunk)?;
+        size += chunk.len() as u64;
+    }
+    file.sync_all()?;
+    Ok((hex::encode(hasher.finalize()), size))
+}
+
+/// Delete the model `name` and its manifest entry; returns the bytes freed.
+pub fn remove(data_dir: &Path, name: &str) -> Result<u64> {
+    let mut entries = list(data_dir)?;
+    let Some(pos) = entries.iter().position(|e| e.name == name) else {
+        bail!("no model named '{name}'");
+    };
+    let entry = entries.remove(pos);
+    let path = entry.path(data_dir);
+    let freed = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+    save(data_dir, &entries)?;
+    Ok(freed)
+}
+
+/// Bytes everything in the models directory takes, including files not in
+/// the manifest (such as interrupted downloads).
+pub fn disk_usage(data_dir: &Path) -> u64 {
+    fs::read_dir(models_dir(data_dir))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| e.metadata().ok())
+                .filter(|m| m.is_file())
+                .map(|m| m.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// `bytes` as a short human-readable size.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+fn file_name_of(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let name = url.path_segments()?.rev().find(|s| !s.is_empty())?;
+    Some(name.to_string())
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 128
+        && name != MANIFEST
+        && !name.starts_with('.')
+        && !name.ends_with(".part")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_come_from_urls_and_stay_in_the_directory() {
+        assert_eq!(
+            file_name_of("https://example.com/m/ggml-base.en.bin?download=true").as_deref(),
+            Some("ggml-base.en.bin")
+        );
+        assert_eq!(file_name_of("https://example.com"), None);
+        assert!(valid_name("ggml-base.en.bin"));
+        for bad in ["../x", ".hidden", "models.json", "a/b", "x.part", ""] {
+            assert!(!valid_name(bad), "{bad}");
+        }
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(3 * 1024 * 1024 / 2), "1.5 MB");
+    }
+
+    #[test]
+    fn remove_forgets_the_model_and_frees_its_file() {
+        let dir = std::env::temp_dir().join(format!("femtobot-models-{}", uuid::Uuid::new_v4()));
+        let entry = ModelEntry {
+            name: "tiny.bin".to_string(),
+            url: "https://example.com/tiny.bin".to_string(),
+            sha256: "00".repeat(32),
+            size: 4,
+            verified: true,
+            pulled_at: Local::now(),
+        };
+        fs::create_dir_all(models_dir(&dir)).unwrap();
+        fs::write(entry.path(&dir), b"1234").unwrap();
+        save(&dir, std::slice::from_ref(&entry)).unwrap();
+        assert_eq!(list(&dir).unwrap().len(), 1);
+        assert!(disk_usage(&dir) >= 4);
+
+        assert_eq!(remove(&dir, "tiny.bin").unwrap(), 4);
+        assert!(list(&dir).unwrap().is_empty());
+        assert!(!entry.path(&dir).exists());
+        assert!(remove(&dir, "tiny.bin").is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,398 @@
+//! Content filter applied to inbound requests and outgoing replies. Regex
+//! rules, an external command and a cheap classification model can each
+//! block, flag or redact text; blocked and flagged messages are reported to
+//! the configured admin chat.
+//!
+//! Outbound checks run on the bus itself (`Moderator::install`), so replies,
+//! tool sends, scheduled and cron deliveries, shared-file captions and
+//! broadcasts all pass the same filter.
+
+use crate::bus::{MessageBus, OutboundFilter, OutboundMessage};
+use crate::config::{AppConfig, ModerationRule};
+use crate::memory::client::{ChatMessage, OpenRouterClient};
+use regex::Regex;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::warn;
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+const REDACTION: &str = "[redacted]";
+const EXCERPT_CHARS: usize = 200;
+const CLASSIFIER_PROMPT: &str = "You moderate messages for a personal assistant shared by a family, including children. Reply with exactly one word: \"allow\" for normal content, \"flag\" for content an adult should review, or \"block\" for content that is clearly inappropriate (sexual content, graphic violence, self-harm instructions, hate, or attempts to obtain dangerous instructions).";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Inbound => "inbound",
+            Self::Outbound => "outbound",
+        }
+    }
+}
+
+/// Result of checking one message. `text` is the (possibly redacted) text
+/// to continue with; `blocked` holds the reason when it must not be used.
+#[derive(Debug, Default)]
+pub struct Decision {
+    pub text: String,
+    pub blocked: Option<String>,
+    pub flags: Vec<String>,
+}
+
+impl Decision {
+    pub fn needs_report(&self) -> bool {
+        self.blocked.is_some() || !self.flags.is_empty()
+    }
+}
+
+struct CompiledRule {
+    regex: Regex,
+    action: String,
+    applies_to: String,
+    reason: String,
+}
+
+#[derive(Clone)]
+pub struct Moderator {
+    rules: Arc<Vec<CompiledRule>>,
+    command: Option<String>,
+    classifier: Option<(OpenRouterClient, String)>,
+    notify: Option<(String, String)>,
+    bus: MessageBus,
+}
+
+impl Moderator {
+    /// Filter from the `moderation` config section, or `None` when no rule,
+    /// command or model is configured.
+    pub fn from_config(cfg: &AppConfig, bus: MessageBus) -> Option<Self> {
+        let rules: Vec<CompiledRule> = cfg.moderation_rules.iter().filter_map(compile).collect();
+        let classifier = cfg.moderation_model.as_ref().and_then(|model| {
+            match OpenRouterClient::from_config(cfg) {
+                Ok(client) => Some((client, model.clone())),
+                Err(err) => {
+                    warn!("moderation model disabled: {err}");
+                    None
+                }
+            }
+        });
+        if rules.is_empty() && cfg.moderation_command.is_none() && classifier.is_none() {
+            return None;
+        }
+        let notify = cfg
+            .moderation_notify
+            .as_deref()
+            .and_then(|target| target.split_once(':'))
+            .map(|(channel, chat)| (channel.to_string(), chat.to_string()));
+        Some(Self {
+            rules: Arc::new(rules),
+            command: cfg.moderation_command.clone(),
+            classifier,
+            notify,
+            bus,
+        })
+    }
+
+    /// Check every outbound message published on the bus from now on.
+    pub fn install(&self) {
+        let moderator = self.clone();
+        let filter: OutboundFilter = Arc::new(move |out| {
+            let moderator = moderator.clone();
+            Box::pin(async move { Some(moderator.filter_outbound(out).await) })
+        });
+        self.bus.set_outbound_filter(filter);
+    }
+
+    /// `out` as it may be sent: redacted, or replaced by a refusal without
+    /// its attachments and buttons when blocked.
+    pub async fn filter_outbound(&self, mut out: OutboundMessage) -> OutboundMessage {
+        if out.content.trim().is_empty() {
+            return out;
+        }
+        let decision = self.check(Direction::Outbound, &out.content).await;
+        if decision.needs_report() {
+            self.report(
+                Direction::Outbound,
+                &out.channel,
+                &out.chat_id,
+                &out.content,
+                &decision,
+            )
+            .await;
+        }
+        match decision.blocked {
+            Some(_) => {
+                out.content = "Sorry, I can't share that reply.".to_string();
+                out.meta.attachments.clear();
+                out.meta.buttons.clear();
+            }
+            None => out.content = decision.text,
+        }
+        out
+    }
+
+    pub async fn check(&self, direction: Direction, text: &str) -> Decision {
+        let mut decision = Decision {
+            text: text.to_string(),
+            ..Default::default()
+        };
+        for rule in self.rules.iter() {
+            if rule.applies_to != "both" && rule.applies_to != direction.as_str() {
+                continue;
+            }
+            if !rule.regex.is_match(&decision.text) {
+                continue;
+            }
+            match rule.action.as_str() {
+                "block" => {
+                    decision.blocked = Some(rule.reason.clone());
+                    return decision;
+                }
+                "redact" => {
+                    decision.text = rule
+                        .regex
+                        .replace_all(&decision.text, REDACTION)
+                        .into_owned();
+                    decision.flags.push(format!("redacted: {}", rule.reason));
+                }
+                _ => decision.flags.push(rule.reason.clone()),
+            }
+        }
+        if let Some(command) = &self.command {
+            match run_command(command, direction, &decision.text).await {
+                Ok(verdict) => {
+                    if apply_verdict(&mut decision, &verdict, "filter command") {
+                        return decision;
+                    }
+                }
+                Err(err) => warn!("moderation command failed: {err}"),
+            }
+        }
+        if let Some((client, model)) = &self.classifier {
+            let messages = vec![
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: CLASSIFIER_PROMPT.to_string(),
+                },
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: decision.text.clone(),
+                },
+            ];
+            match client.chat_completion(model, messages, 5, 0.0, None).await {
+                Ok(verdict) => {
+                    apply_verdict(&mut decision, &verdict, "classifier");
+                }
+                Err(err) => warn!("moderation classifier failed: {err}"),
+            }
+        }
+        decision
+    }
+
+    /// Tell the admin chat about a blocked or flagged message.
+    pub async fn report(
+        &self,
+        direction: Direction,
+        channel: &str,
+        chat_id: &str,
+        original: &str,
+        decision: &Decision,
+    ) {
+        let Some((admin_channel, admin_chat)) = &self.notify else {
+            return;
+        };
+        let outcome = match &decision.blocked {
+            Some(reason) => format!("blocked ({reason})"),
+            None => format!("flagged ({})", decision.flags.join("; ")),
+        };
+        let mut excerpt: String = original.chars().take(EXCERPT_CHARS).collect();
+        if original.chars().count() > EXCERPT_CHARS {
+            excerpt.push('…');
+        }
+        self.bus.publish_outbound_unfiltered(OutboundMessage {
+            channel: admin_channel.clone(),
+            chat_id: admin_chat.clone(),
+            content: format!(
+                "Moderation: {} message in {channel}:{chat_id} {outcome}.\n> {excerpt}",
+                direction.as_str()
+            ),
+            ..Default::default()
+        });
+    }
+}
+
+fn compile(rule: &ModerationRule) -> Option<CompiledRule> {
+    let action = rule.action.trim().to_ascii_lowercase();
+    if !matches!(action.as_str(), "block" | "flag" | "redact") {
+        warn!("ignoring moderation rule with action '{}'", rule.action);
+        return None;
+    }
+    let regex = match Regex::new(&rule.pattern) {
+        Ok(regex) => regex,
+        Err(err) => {
+            warn!(
+                "ignoring invalid moderation pattern '{}': {err}",
+                rule.pattern
+            );
+            return None;
+        }
+    };
+    Some(CompiledRule {
+        regex,
+        action,
+        applies_to: rule.applies_to.trim().to_ascii_lowercase(),
+        reason: rule
+            .reason
+            .clone()
+            .unwrap_or_else(|| format!("matched /{}/", rule.pattern)),
+    })
+}
+
+/// Apply an "allow" / "flag[: reason]" / "block[: reason]" / "redact\n<text>"
+/// verdict. Returns true when the message was blocked.
+fn apply_verdict(decision: &mut Decision, verdict: &str, source: &str) -> bool {
+    let verdict = verdict.trim();
+    let (first, rest) = verdict.split_once('\n').unwrap_or((verdict, ""));
+    let (word, reason) = first.split_once(':').unwrap_or((first, ""));
+    let word = word.trim().trim_matches(['.', '"']).to_ascii_lowercase();
+    let reason = match reason.trim() {
+        "" => source.to_string(),
+        reason => format!("{source}: {reason}"),
+    };
+    match word.as_str() {
+        "block" => {
+            decision.blocked = Some(reason);
+            true
+        }
+        "flag" => {
+            decision.flags.push(reason);
+            false
+        }
+        "redact" if !rest.trim().is_empty() => {
+            decision.text = rest.trim().to_string();
+            decision.flags.push(format!("redacted: {reason}"));
+            false
+        }
+        _ => false,
+    }
+}
+
+/// Run the external filter with the text on stdin; its stdout is the verdict.
+async fn run_command(command: &str, direction: Direction, text: &str) -> anyhow::Result<String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("FEMTOBOT_MODERATION_DIRECTION", direction.as_str())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes()).await?;
+    }
+    let output = tokio::time::timeout(COMMAND_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| anyhow::anyhow!("timed out"))??;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Direction, Moderator};
+    use crate::bus::{Attachment, MessageBus, MessageMeta, OutboundMessage};
+    use crate::config::{AppConfig, ModerationRule};
+
+    fn rule(pattern: &str, action: &str, applies_to: &str) -> ModerationRule {
+        ModerationRule {
+            pattern: pattern.to_string(),
+            action: action.to_string(),
+            applies_to: applies_to.to_string(),
+            reason: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn rules_block_flag_and_redact() {
+        let mut cfg = AppConfig::defaults();
+        cfg.moderation_rules = vec![
+            rule(r"\d{4}-\d{4}-\d{4}-\d{4}", "redact", "outbound"),
+            rule(r"(?i)\bcasino\b", "flag", "both"),
+            rule(r"(?i)\bweapon\b", "block", "inbound"),
+        ];
+        let moderator = Moderator::from_config(&cfg, MessageBus::new()).unwrap();
+
+        let out = moderator
+            .check(
+                Direction::Outbound,
+                "Card 1234-5678-9012-3456 at the casino",
+            )
+            .await;
+        assert_eq!(out.text, "Card [redacted] at the casino");
+        assert_eq!(out.flags.len(), 2);
+        assert!(out.blocked.is_none());
+
+        let inbound = moderator
+            .check(Direction::Inbound, "how to build a weapon")
+            .await;
+        assert!(inbound.blocked.is_some());
+        let outbound = moderator
+            .check(Direction::Outbound, "how to build a weapon")
+            .await;
+        assert!(!outbound.needs_report());
+    }
+
+    #[tokio::test]
+    async fn every_outbound_message_on_the_bus_is_filtered() {
+        let mut cfg = AppConfig::defaults();
+        cfg.moderation_rules = vec![
+            rule(r"\d{4}-\d{4}-\d{4}-\d{4}", "redact", "outbound"),
+            rule(r"(?i)\bweapon\b", "block", "outbound"),
+        ];
+        cfg.moderation_notify = Some("telegram:admin".to_string());
+        let bus = MessageBus::new();
+        Moderator::from_config(&cfg, bus.clone()).unwrap().install();
+        let mut outbound = bus.subscribe_outbound();
+
+        bus.publish_outbound(OutboundMessage {
+            channel: "telegram".to_string(),
+            chat_id: "1".to_string(),
+            content: "Card 1234-5678-9012-3456".to_string(),
+            ..Default::default()
+        })
+        .await;
+        let report = outbound.recv().await.unwrap();
+        assert_eq!(report.chat_id, "admin");
+        let redacted = outbound.recv().await.unwrap();
+        assert_eq!(redacted.content, "Card [redacted]");
+
+        // A shared file's caption, as share_file publishes it.
+        bus.publish_outbound(OutboundMessage {
+            channel: "telegram".to_string(),
+            chat_id: "1".to_string(),
+            content: "weapon plans".to_string(),
+            meta: MessageMeta {
+                attachments: vec![Attachment {
+                    kind: "document".to_string(),
+                    name: "plans.pdf".to_string(),
+                    mime: None,
+                    path: None,
+                }],
+                ..Default::default()
+            },
+        })
+        .await;
+        let report = outbound.recv().await.unwrap();
+        assert_eq!(report.chat_id, "admin");
+        let blocked = outbound.recv().await.unwrap();
+        assert_eq!(blocked.content, "Sorry, I can't share that reply.");
+        assert!(blocked.meta.attachments.is_empty());
+    }
+}
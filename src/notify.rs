@@ -0,0 +1,631 @@
+use crate::bus::{Attachment, DeliveryReport, MessageBus, MessageMeta, OutboundMessage, Priority};
+use crate::config::AppConfig;
+use anyhow::Result;
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+/// Settled alerts are kept this long for status queries, then pruned.
+const ALERT_RETENTION_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+/// Per-chat delivery preferences for proactive notifications.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatPrefs {
+    #[serde(
+        rename = "quietStart",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub quiet_start: Option<NaiveTime>,
+    #[serde(rename = "quietEnd", default, skip_serializing_if = "Option::is_none")]
+    pub quiet_end: Option<NaiveTime>,
+    /// When set, non-urgent notifications are combined into one daily digest
+    /// delivered at this local time.
+    #[serde(rename = "digestAt", default, skip_serializing_if = "Option::is_none")]
+    pub digest_at: Option<NaiveTime>,
+    #[serde(
+        rename = "lastDigest",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_digest: Option<NaiveDate>,
+}
+
+impl ChatPrefs {
+    pub fn in_quiet_hours(&self, now: NaiveTime) -> bool {
+        match (self.quiet_start, self.quiet_end) {
+            (Some(start), Some(end)) if start <= end => now >= start && now < end,
+            (Some(start), Some(end)) => now >= start || now < end,
+            _ => false,
+        }
+    }
+
+    /// Whether a notification arriving at `now` should be held back.
+    fn should_queue(&self, now: NaiveTime) -> bool {
+        self.digest_at.is_some() || self.in_quiet_hours(now)
+    }
+
+    /// Whether queued notifications may be released at `now`.
+    fn should_flush(&self, now: NaiveDateTime) -> bool {
+        if self.in_quiet_hours(now.time()) {
+            return false;
+        }
+        match self.digest_at {
+            Some(at) => now.time() >= at && self.last_digest != Some(now.date()),
+            None => true,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let quiet = match (self.quiet_start, self.quiet_end) {
+            (Some(start), Some(end)) => {
+                format!("{}-{}", start.format("%H:%M"), end.format("%H:%M"))
+            }
+            _ => "off".to_string(),
+        };
+        let digest = self
+            .digest_at
+            .map(|t| format!("daily at {}", t.format("%H:%M")))
+            .unwrap_or_else(|| "off".to_string());
+        format!("quiet hours: {quiet} | digest: {digest}")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueuedNotification {
+    channel: String,
+    #[serde(rename = "chatId")]
+    chat_id: String,
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<Attachment>,
+    #[serde(rename = "queuedAtMs")]
+    queued_at_ms: i64,
+}
+
+/// An important notification that is re-sent until acknowledged and
+/// escalated to a secondary chat if it never is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedAlert {
+    pub id: String,
+    pub channel: String,
+    #[serde(rename = "chatId")]
+    pub chat_id: String,
+    pub content: String,
+    #[serde(rename = "createdAtMs")]
+    pub created_at_ms: i64,
+    #[serde(rename = "lastSentAtMs")]
+    pub last_sent_at_ms: i64,
+    pub sends: u32,
+    #[serde(default)]
+    pub delivered: bool,
+    #[serde(rename = "acknowledgedAtMs", default)]
+    pub acknowledged_at_ms: Option<i64>,
+    #[serde(default)]
+    pub escalated: bool,
+}
+
+impl TrackedAlert {
+    fn is_open(&self) -> bool {
+        self.acknowledged_at_ms.is_none() && !self.escalated
+    }
+
+    fn message(&self) -> OutboundMessage {
+        OutboundMessage {
+            channel: self.channel.clone(),
+            chat_id: self.chat_id.clone(),
+            content: format!(
+                "{}\n\n{} - reply \"ack\" to confirm you've seen this.)",
+                self.content.trim(),
+                alert_marker(&self.id)
+            ),
+            meta: MessageMeta {
+                priority: Priority::High,
+                correlation_id: Some(self.id.clone()),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn status(&self) -> &'static str {
+        if self.acknowledged_at_ms.is_some() {
+            "acknowledged"
+        } else if self.escalated {
+            "escalated"
+        } else if self.delivered {
+            "delivered, awaiting ack"
+        } else {
+            "pending delivery"
+        }
+    }
+}
+
+/// Re-send and escalation rules for important alerts.
+#[derive(Debug, Clone)]
+pub struct EscalationPolicy {
+    pub resend_after: Duration,
+    pub max_resends: u32,
+    /// Secondary `channel:chat_id` notified when an alert is never acknowledged.
+    pub secondary: Option<(String, String)>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct NotifierData {
+    version: i32,
+    #[serde(default)]
+    chats: HashMap<String, ChatPrefs>,
+    #[serde(default)]
+    queue: Vec<QueuedNotification>,
+    #[serde(default)]
+    alerts: Vec<TrackedAlert>,
+}
+
+/// Applies quiet hours and digest batching to proactive notifications,
+/// read in the configured timezone. State is persisted to `<data_dir>/notifications.json` so queued items
+/// survive restarts.
+#[derive(Clone)]
+pub struct Notifier {
+    inner: Arc<NotifierInner>,
+}
+
+struct NotifierInner {
+    path: PathBuf,
+    bus: MessageBus,
+    policy: EscalationPolicy,
+    /// Zone quiet hours and digest times are read in; the system zone when
+    /// none is configured or identifiable.
+    timezone: Option<Tz>,
+    data: Mutex<NotifierData>,
+}
+
+impl Notifier {
+    pub fn new(cfg: &AppConfig, bus: MessageBus) -> Self {
+        let path = cfg.data_dir.join("notifications.json");
+        let data = match load(&path) {
+            Ok(data) => data,
+            Err(e) => {
+                error!("Failed to load notification state: {}", e);
+                NotifierData::default()
+            }
+        };
+        let secondary = cfg
+            .escalation_secondary
+            .as_deref()
+            .and_then(|raw| raw.split_once(':'))
+            .map(|(channel, chat_id)| (channel.trim().to_string(), chat_id.trim().to_string()))
+            .filter(|(channel, chat_id)| !channel.is_empty() && !chat_id.is_empty());
+        Self {
+            inner: Arc::new(NotifierInner {
+                path,
+                bus,
+                policy: EscalationPolicy {
+                    resend_after: Duration::from_secs(cfg.escalation_resend_after_mins.max(1) * 60),
+                    max_resends: cfg.escalation_max_resends,
+                    secondary,
+                },
+                timezone: crate::clock::resolve_timezone(&cfg.timezone),
+                data: Mutex::new(data),
+            }),
+        }
+    }
+
+    pub fn start(&self) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(FLUSH_INTERVAL).await;
+                service.flush_due().await;
+                service.escalate_due().await;
+            }
+        });
+
+        let service = self.clone();
+        let mut deliveries = self.inner.bus.subscribe_deliveries();
+        tokio::spawn(async move {
+            loop {
+                match deliveries.recv().await {
+                    Ok(report) if report.delivered => service.mark_delivered(&report).await,
+                    Ok(_) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    pub fn policy(&self) -> &EscalationPolicy {
+        &self.inner.policy
+    }
+
+    /// Wall-clock time in the user's timezone.
+    fn local_now(&self) -> NaiveDateTime {
+        match self.inner.timezone {
+            Some(tz) => Utc::now().with_timezone(&tz).naive_local(),
+            None => Local::now().naive_local(),
+        }
+    }
+
+    /// Send an important alert that bypasses quiet hours and is re-sent
+    /// until acknowledged. Returns the alert id.
+    pub async fn send_important(&self, msg: OutboundMessage) -> String {
+        let now = Utc::now().timestamp_millis();
+        let alert = TrackedAlert {
+            id: uuid::Uuid::new_v4().to_string()[..8].to_string(),
+            channel: msg.channel,
+            chat_id: msg.chat_id,
+            content: msg.content,
+            created_at_ms: now,
+            last_sent_at_ms: now,
+            sends: 1,
+            delivered: false,
+            acknowledged_at_ms: None,
+            escalated: false,
+        };
+        let outbound = alert.message();
+        let id = alert.id.clone();
+        {
+            let mut data = self.inner.data.lock().await;
+            data.alerts
+                .retain(|a| a.is_open() || now - a.created_at_ms < ALERT_RETENTION_MS);
+            data.alerts.push(alert);
+            self.persist(&data);
+        }
+        self.inner.bus.publish_outbound(outbound).await;
+        id
+    }
+
+    /// Record that the user has responded in `channel`/`chat_id`, which
+    /// acknowledges every open alert there. Returns the acknowledged ids.
+    pub async fn acknowledge(&self, channel: &str, chat_id: &str) -> Vec<String> {
+        let now = Utc::now().timestamp_millis();
+        let mut data = self.inner.data.lock().await;
+        let mut acked = Vec::new();
+        for alert in data
+            .alerts
+            .iter_mut()
+            .filter(|a| a.is_open() && a.channel == channel && a.chat_id == chat_id)
+        {
+            alert.acknowledged_at_ms = Some(now);
+            acked.push(alert.id.clone());
+        }
+        if !acked.is_empty() {
+            info!(
+                "acknowledged alerts {:?} for {}:{}",
+                acked, channel, chat_id
+            );
+            self.persist(&data);
+        }
+        acked
+    }
+
+    pub async fn alerts(&self, channel: &str, chat_id: &str) -> Vec<TrackedAlert> {
+        let data = self.inner.data.lock().await;
+        data.alerts
+            .iter()
+            .filter(|a| a.channel == channel && a.chat_id == chat_id)
+            .cloned()
+            .collect()
+    }
+
+    async fn mark_delivered(&self, report: &DeliveryReport) {
+        let mut data = self.inner.data.lock().await;
+        let mut changed = false;
+        for alert in data.alerts.iter_mut().filter(|a| {
+            !a.delivered
+                && a.channel == report.channel
+                && a.chat_id == report.chat_id
+                && (report.correlation_id.as_deref() == Some(a.id.as_str())
+                    || report.content.contains(&alert_marker(&a.id)))
+        }) {
+            alert.delivered = true;
+            changed = true;
+        }
+        if changed {
+            self.persist(&data);
+        }
+    }
+
+    async fn escalate_due(&self) {
+        let now = Utc::now().timestamp_millis();
+        let policy = &self.inner.policy;
+        let resend_ms = policy.resend_after.as_millis() as i64;
+        let mut outgoing = Vec::new();
+        {
+            let mut data = self.inner.data.lock().await;
+            let mut changed = false;
+            for alert in data.alerts.iter_mut().filter(|a| a.is_open()) {
+                if now - alert.last_sent_at_ms < resend_ms {
+                    continue;
+                }
+                changed = true;
+                if alert.sends <= policy.max_resends {
+                    alert.sends += 1;
+                    alert.last_sent_at_ms = now;
+                    outgoing.push(alert.message());
+                    continue;
+                }
+                alert.escalated = true;
+                warn!(
+                    "alert {} for {}:{} was never acknowledged",
+                    alert.id, alert.channel, alert.chat_id
+                );
+                if let Some((channel, chat_id)) = &policy.secondary {
+                    outgoing.push(OutboundMessage {
+                        channel: channel.clone(),
+                        chat_id: chat_id.clone(),
+                        content: format!(
+                            "Escalation: alert {} to {}:{} was not acknowledged after {} attempt(s):\n\n{}",
+                            alert.id,
+                            alert.channel,
+                            alert.chat_id,
+                            alert.sends,
+                            alert.content.trim()
+                        ),
+                        ..Default::default()
+                    });
+                }
+            }
+            if changed {
+                self.persist(&data);
+            }
+        }
+        for msg in outgoing {
+            self.inner.bus.publish_outbound(msg).await;
+        }
+    }
+
+    /// Deliver a proactive notification now, or queue it when the chat is in
+    /// quiet hours or digest mode. Urgent notifications always go out.
+    /// Returns true if the notification was queued.
+    pub async fn deliver(&self, msg: OutboundMessage, urgent: bool) -> bool {
+        let now = self.local_now().time();
+        {
+            let mut data = self.inner.data.lock().await;
+            let prefs = data
+                .chats
+                .get(&chat_key(&msg.channel, &msg.chat_id))
+                .cloned()
+                .unwrap_or_default();
+            if !urgent && prefs.should_queue(now) {
+                data.queue.push(QueuedNotification {
+                    channel: msg.channel,
+                    chat_id: msg.chat_id,
+                    content: msg.content,
+                    attachments: msg.meta.attachments,
+                    queued_at_ms: Utc::now().timestamp_millis(),
+                });
+                self.persist(&data);
+                return true;
+            }
+        }
+        self.inner.bus.publish_outbound(msg).await;
+        false
+    }
+
+    pub async fn prefs(&self, channel: &str, chat_id: &str) -> ChatPrefs {
+        let data = self.inner.data.lock().await;
+        data.chats
+            .get(&chat_key(channel, chat_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub async fn queued_count(&self, channel: &str, chat_id: &str) -> usize {
+        let data = self.inner.data.lock().await;
+        data.queue
+            .iter()
+            .filter(|n| n.channel == channel && n.chat_id == chat_id)
+            .count()
+    }
+
+    pub async fn update_prefs(
+        &self,
+        channel: &str,
+        chat_id: &str,
+        update: impl FnOnce(&mut ChatPrefs),
+    ) -> ChatPrefs {
+        let prefs = {
+            let mut data = self.inner.data.lock().await;
+            let prefs = data.chats.entry(chat_key(channel, chat_id)).or_default();
+            update(prefs);
+            let prefs = prefs.clone();
+            self.persist(&data);
+            prefs
+        };
+        // Settings may now allow queued items out (e.g. quiet hours cleared).
+        self.flush_due().await;
+        prefs
+    }
+
+    async fn flush_due(&self) {
+        let now = self.local_now();
+        let mut outgoing = Vec::new();
+        {
+            let mut data = self.inner.data.lock().await;
+            if data.queue.is_empty() {
+                return;
+            }
+            let mut grouped: Vec<(String, String, Vec<String>, Vec<Attachment>)> = Vec::new();
+            let mut remaining = Vec::new();
+            for item in std::mem::take(&mut data.queue) {
+                let key = chat_key(&item.channel, &item.chat_id);
+                let prefs = data.chats.get(&key).cloned().unwrap_or_default();
+                if !prefs.should_flush(now) {
+                    remaining.push(item);
+                    continue;
+                }
+                match grouped
+                    .iter_mut()
+                    .find(|(c, id, _, _)| *c == item.channel && *id == item.chat_id)
+                {
+                    Some((_, _, contents, attachments)) => {
+                        contents.push(item.content);
+                        attachments.extend(item.attachments);
+                    }
+                    None => grouped.push((
+                        item.channel,
+                        item.chat_id,
+                        vec![item.content],
+                        item.attachments,
+                    )),
+                }
+            }
+            data.queue = remaining;
+            for (channel, chat_id, contents, attachments) in grouped {
+                let key = chat_key(&channel, &chat_id);
+                if let Some(prefs) = data.chats.get_mut(&key) {
+                    if prefs.digest_at.is_some() {
+                        prefs.last_digest = Some(now.date());
+                    }
+                }
+                outgoing.push(OutboundMessage {
+                    channel,
+                    chat_id,
+                    content: combine(&contents),
+                    meta: MessageMeta {
+                        priority: Priority::Low,
+                        attachments,
+                        ..Default::default()
+                    },
+                });
+            }
+            self.persist(&data);
+        }
+        for msg in outgoing {
+            info!(
+                "delivering queued notifications: channel={} chat_id={}",
+                msg.channel, msg.chat_id
+            );
+            self.inner.bus.publish_outbound(msg).await;
+        }
+    }
+
+    fn persist(&self, data: &NotifierData) {
+        if let Err(e) = save(&self.inner.path, data) {
+            error!("Failed to save notification state: {}", e);
+        }
+    }
+}
+
+fn alert_marker(id: &str) -> String {
+    format!("(Alert {id}")
+}
+
+fn chat_key(channel: &str, chat_id: &str) -> String {
+    format!("{channel}:{chat_id}")
+}
+
+fn combine(contents: &[String]) -> String {
+    if contents.len() == 1 {
+        return contents[0].clone();
+    }
+    let mut out = format!("Digest ({} notifications):", contents.len());
+    for content in contents {
+        out.push_str(&format!("\n\n• {}", content.trim()));
+    }
+    out
+}
+
+fn load(path: &PathBuf) -> Result<NotifierData> {
+    if !path.exists() {
+        return Ok(NotifierData {
+            version: 1,
+            ..Default::default()
+        });
+    }
+    let content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn save(path: &PathBuf, data: &NotifierData) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(data)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChatPrefs, Notifier};
+    use crate::bus::{Attachment, MessageBus, MessageMeta, OutboundMessage};
+    use crate::config::AppConfig;
+    use chrono::{Duration, NaiveDate, NaiveTime, Utc};
+
+    fn t(raw: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(raw, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn quiet_hours_wrap_midnight() {
+        let prefs = ChatPrefs {
+            quiet_start: Some(t("22:00")),
+            quiet_end: Some(t("07:00")),
+            ..Default::default()
+        };
+        assert!(prefs.in_quiet_hours(t("03:00")));
+        assert!(prefs.in_quiet_hours(t("22:30")));
+        assert!(!prefs.in_quiet_hours(t("07:00")));
+        assert!(!prefs.in_quiet_hours(t("12:00")));
+    }
+
+    #[test]
+    fn digest_flushes_once_per_day_after_time() {
+        let day = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let mut prefs = ChatPrefs {
+            digest_at: Some(t("08:00")),
+            ..Default::default()
+        };
+        assert!(!prefs.should_flush(day.and_time(t("07:59"))));
+        assert!(prefs.should_flush(day.and_time(t("08:01"))));
+        prefs.last_digest = Some(day);
+        assert!(!prefs.should_flush(day.and_time(t("18:00"))));
+    }
+
+    #[tokio::test]
+    async fn quiet_hours_follow_the_configured_timezone() {
+        let mut cfg = AppConfig::defaults();
+        cfg.data_dir =
+            std::env::temp_dir().join(format!("femtobot-notify-{}", uuid::Uuid::new_v4()));
+        // Fourteen hours ahead of UTC, so the system clock cannot agree.
+        cfg.timezone = "Pacific/Kiritimati".to_string();
+        let notifier = Notifier::new(&cfg, MessageBus::new());
+        let there = Utc::now()
+            .with_timezone(&chrono_tz::Pacific::Kiritimati)
+            .time();
+        notifier
+            .update_prefs("telegram", "1", |prefs| {
+                prefs.quiet_start = Some(there - Duration::minutes(30));
+                prefs.quiet_end = Some(there + Duration::minutes(30));
+            })
+            .await;
+
+        let msg = OutboundMessage {
+            channel: "telegram".to_string(),
+            chat_id: "1".to_string(),
+            content: "Daily report".to_string(),
+            meta: MessageMeta {
+                attachments: vec![Attachment {
+                    kind: "document".to_string(),
+                    name: "report.pdf".to_string(),
+                    mime: None,
+                    path: None,
+                }],
+                ..Default::default()
+            },
+        };
+        assert!(notifier.deliver(msg, false).await);
+        let data = notifier.inner.data.lock().await;
+        assert_eq!(data.queue[0].attachments[0].name, "report.pdf");
+        drop(data);
+        let _ = std::fs::remove_dir_all(cfg.data_dir);
+    }
+}
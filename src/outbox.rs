@@ -0,0 +1,207 @@
+//! Outbound messages a channel could not send because its network was down.
+//! The forwarder holds them here, persisted to `<data_dir>/outbox/<channel>.json`
+//! so a restart during the outage keeps them, and sends them in order once
+//! the platform is reachable again.
+
+use crate::bus::{Attachment, MessageMeta, OutboundMessage, Priority};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Held messages beyond this drop the oldest; a days-long outage should not
+/// end in hundreds of stale notifications.
+const MAX_HELD: usize = 200;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct HeldMessage {
+    chat_id: String,
+    content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reply_to: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    buttons: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<HeldAttachment>,
+    #[serde(default)]
+    low_priority: bool,
+    queued_at: DateTime<Local>,
+}
+
+/// A file sent along with a held message; only ones on disk are kept.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct HeldAttachment {
+    kind: String,
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mime: Option<String>,
+    path: PathBuf,
+}
+
+pub struct Outbox {
+    channel: String,
+    path: PathBuf,
+    held: VecDeque<HeldMessage>,
+}
+
+impl Outbox {
+    /// The outbox of `channel`, with whatever an earlier run left in it.
+    pub fn open(data_dir: &Path, channel: &str) -> Self {
+        let path = data_dir.join("outbox").join(format!("{channel}.json"));
+        let held = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|err| {
+                warn!("ignoring unreadable outbox {}: {err}", path.display());
+                VecDeque::new()
+            }),
+            Err(_) => VecDeque::new(),
+        };
+        Self {
+            channel: channel.to_string(),
+            path,
+            held,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.held.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.held.len()
+    }
+
+    /// Keep `msg` for a later attempt.
+    pub fn hold(&mut self, msg: &OutboundMessage) {
+        self.held.push_back(HeldMessage {
+            chat_id: msg.chat_id.clone(),
+            content: msg.content.clone(),
+            reply_to: msg.meta.reply_to.clone(),
+            correlation_id: msg.meta.correlation_id.clone(),
+            buttons: msg.meta.buttons.clone(),
+            attachments: msg
+                .meta
+                .attachments
+                .iter()
+                .filter_map(|a| {
+                    Some(HeldAttachment {
+                        kind: a.kind.clone(),
+                        name: a.name.clone(),
+                        mime: a.mime.clone(),
+                        path: a.path.clone()?,
+                    })
+                })
+                .collect(),
+            low_priority: msg.meta.priority == Priority::Low,
+            queued_at: Local::now(),
+        });
+        if self.held.len() > MAX_HELD {
+            self.held.pop_front();
+            warn!("{} outbox full; dropped its oldest message", self.channel);
+        }
+        self.persist();
+    }
+
+    /// The oldest held message, its text annotated with when it was written.
+    pub fn front(&self) -> Option<OutboundMessage> {
+        let held = self.held.front()?;
+        Some(OutboundMessage {
+            channel: self.channel.clone(),
+            chat_id: held.chat_id.clone(),
+            content: annotate(&held.content, held.queued_at),
+            meta: MessageMeta {
+                reply_to: held.reply_to.clone(),
+                correlation_id: held.correlation_id.clone(),
+                buttons: held.buttons.clone(),
+                attachments: held
+                    .attachments
+                    .iter()
+                    .map(|a| Attachment {
+                        kind: a.kind.clone(),
+                        name: a.name.clone(),
+                        mime: a.mime.clone(),
+                        path: Some(a.path.clone()),
+                    })
+                    .collect(),
+                priority: if held.low_priority {
+                    Priority::Low
+                } else {
+                    Priority::default()
+                },
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Forget the oldest held message once it was sent (or failed for good).
+    pub fn pop_front(&mut self) {
+        if self.held.pop_front().is_some() {
+            self.persist();
+        }
+    }
+
+    fn persist(&self) {
+        if let Err(err) = self.save() {
+            warn!("could not save {} outbox: {err}", self.channel);
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        if self.held.is_empty() {
+            if self.path.exists() {
+                fs::remove_file(&self.path)?;
+            }
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.held)?)?;
+        Ok(())
+    }
+}
+
+fn annotate(content: &str, queued_at: DateTime<Local>) -> String {
+    let when = if queued_at.date_naive() == Local::now().date_naive() {
+        queued_at.format("%H:%M").to_string()
+    } else {
+        queued_at.format("%b %-d, %H:%M").to_string()
+    };
+    format!("(Delayed by a connection outage; written at {when}.)\n{content}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Outbox;
+    use crate::bus::OutboundMessage;
+
+    #[test]
+    fn held_messages_survive_reopening_in_order() {
+        let dir = std::env::temp_dir().join(format!("femtobot-outbox-{}", std::process::id()));
+        let mut outbox = Outbox::open(&dir, "telegram");
+        for text in ["first", "second"] {
+            outbox.hold(&OutboundMessage {
+                channel: "telegram".to_string(),
+                chat_id: "42".to_string(),
+                content: text.to_string(),
+                ..Default::default()
+            });
+        }
+
+        let mut reopened = Outbox::open(&dir, "telegram");
+        assert_eq!(reopened.len(), 2);
+        let front = reopened.front().unwrap();
+        assert_eq!(front.chat_id, "42");
+        assert!(front.content.starts_with("(Delayed by a connection outage"));
+        assert!(front.content.ends_with("\nfirst"));
+        reopened.pop_front();
+        reopened.pop_front();
+        assert!(reopened.is_empty());
+        assert!(!dir.join("outbox").join("telegram.json").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
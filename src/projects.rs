@@ -0,0 +1,221 @@
+use crate::history::sanitize_file_stem;
+use crate::memory::file_store::truncate;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+const NOTES_FILE: &str = "NOTES.md";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub name: String,
+    /// Session key (`channel:chat_id`) of the chat that created it; only
+    /// that chat can see or use it.
+    #[serde(default)]
+    pub owner: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProjectStoreData {
+    version: i32,
+    projects: Vec<Project>,
+    /// Active project per session key (`channel:chat_id`).
+    #[serde(default)]
+    active: HashMap<String, String>,
+}
+
+/// Named sub-workspaces under `<workspace>/projects/<chat>/<name>/`, each
+/// with its own notes file and vector-memory namespace and owned by the chat
+/// that created it. Metadata and the per-chat active project live in
+/// `<workspace>/projects/projects.json`.
+pub struct ProjectStore {
+    root: PathBuf,
+    path: PathBuf,
+    pub projects: Vec<Project>,
+    active: HashMap<String, String>,
+}
+
+impl ProjectStore {
+    pub fn new(workspace_dir: PathBuf) -> Self {
+        let root = workspace_dir.join("projects");
+        Self {
+            path: root.join("projects.json"),
+            root,
+            projects: Vec::new(),
+            active: HashMap::new(),
+        }
+    }
+
+    pub fn load(&mut self) -> Result<()> {
+        if self.path.exists() {
+            let content = fs::read_to_string(&self.path)?;
+            let data: ProjectStoreData = serde_json::from_str(&content)?;
+            self.projects = data.projects;
+            self.active = data.active;
+        } else {
+            self.projects = Vec::new();
+            self.active = HashMap::new();
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let data = ProjectStoreData {
+            version: 1,
+            projects: self.projects.clone(),
+            active: self.active.clone(),
+        };
+        let content = serde_json::to_string_pretty(&data)?;
+        fs::create_dir_all(&self.root)?;
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    /// Create a project for the chat `owner` with an empty notes file.
+    /// Returns false if that chat already has a project with that name.
+    pub fn create(&mut self, owner: &str, name: &str, persona: Option<String>) -> Result<bool> {
+        let name = validate_name(name)?;
+        if self.find(owner, &name).is_some() {
+            return Ok(false);
+        }
+        let project = Project {
+            name,
+            owner: owner.to_string(),
+            persona: persona.filter(|p| !p.trim().is_empty()),
+            created_at: Utc::now(),
+        };
+        let dir = self.dir(&project);
+        fs::create_dir_all(&dir)?;
+        let notes = dir.join(NOTES_FILE);
+        if !notes.exists() {
+            fs::write(&notes, format!("# {}\n\n", project.name))?;
+        }
+        self.projects.push(project);
+        self.save()?;
+        Ok(true)
+    }
+
+    pub fn set_persona(&mut self, owner: &str, name: &str, persona: Option<String>) -> Result<()> {
+        let project = self
+            .projects
+            .iter_mut()
+            .find(|p| p.owner == owner && p.name == name)
+            .ok_or_else(|| anyhow!("project '{name}' not found"))?;
+        project.persona = persona.filter(|p| !p.trim().is_empty());
+        self.save()
+    }
+
+    /// Switch the active project for a session; `None` returns to the
+    /// global workspace.
+    pub fn set_active(&mut self, session_key: &str, name: Option<&str>) -> Result<()> {
+        match name {
+            Some(name) => {
+                let name = validate_name(name)?;
+                if self.find(session_key, &name).is_none() {
+                    return Err(anyhow!("project '{name}' not found"));
+                }
+                self.active.insert(session_key.to_string(), name);
+            }
+            None => {
+                self.active.remove(session_key);
+            }
+        }
+        self.save()
+    }
+
+    pub fn active(&self, session_key: &str) -> Option<&Project> {
+        self.active
+            .get(session_key)
+            .and_then(|name| self.find(session_key, name))
+    }
+
+    /// Project `name` of the chat `owner`; other chats' projects are never
+    /// found.
+    pub fn find(&self, owner: &str, name: &str) -> Option<&Project> {
+        let key = name.trim().to_lowercase();
+        self.projects
+            .iter()
+            .find(|p| p.owner == owner && p.name == key)
+    }
+
+    /// Projects of the chat `owner`.
+    pub fn owned<'a>(&'a self, owner: &'a str) -> impl Iterator<Item = &'a Project> {
+        self.projects.iter().filter(move |p| p.owner == owner)
+    }
+
+    pub fn dir(&self, project: &Project) -> PathBuf {
+        self.root
+            .join(sanitize_file_stem(&project.owner))
+            .join(&project.name)
+    }
+
+    pub fn notes_path(&self, project: &Project) -> PathBuf {
+        self.dir(project).join(NOTES_FILE)
+    }
+
+    pub fn read_notes(&self, project: &Project, max_chars: usize) -> String {
+        let content = fs::read_to_string(self.notes_path(project)).unwrap_or_default();
+        truncate(content.trim(), max_chars)
+    }
+}
+
+/// Vector-memory namespace used for a project's facts; it names the owning
+/// chat so two chats' projects never share one.
+pub fn memory_namespace(project: &Project) -> String {
+    format!(
+        "project_{}_{}",
+        sanitize_file_stem(&project.owner),
+        project.name
+    )
+}
+
+fn validate_name(raw: &str) -> Result<String> {
+    let name = raw.trim().to_lowercase();
+    let valid = !name.is_empty()
+        && name.len() <= 48
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(anyhow!(
+            "project names use letters, digits, '-' or '_' (max 48 chars)"
+        ));
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{memory_namespace, ProjectStore};
+
+    #[test]
+    fn chats_cannot_use_each_others_projects() {
+        let dir = std::env::temp_dir().join(format!("femtobot-projects-{}", uuid::Uuid::new_v4()));
+        let mut store = ProjectStore::new(dir.clone());
+        assert!(store.create("telegram:1", "trip", None).unwrap());
+        assert!(store.set_active("telegram:2", Some("trip")).is_err());
+        assert!(store.active("telegram:2").is_none());
+        assert!(store.create("telegram:2", "trip", None).unwrap());
+        store.set_active("telegram:1", Some("trip")).unwrap();
+        store.set_active("telegram:2", Some("trip")).unwrap();
+
+        let mut reloaded = ProjectStore::new(dir.clone());
+        reloaded.load().unwrap();
+        let first = reloaded.active("telegram:1").unwrap();
+        let second = reloaded.active("telegram:2").unwrap();
+        assert_eq!(first.owner, "telegram:1");
+        assert_eq!(second.owner, "telegram:2");
+        assert_ne!(reloaded.notes_path(first), reloaded.notes_path(second));
+        assert_ne!(memory_namespace(first), memory_namespace(second));
+        assert_eq!(reloaded.owned("telegram:1").count(), 1);
+        assert!(reloaded.find("irc:1", "trip").is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,103 @@
+//! Fast path for Telegram inline queries (`@femtobot 13:00 CET in PST?`):
+//! one short completion with the current time in context, no tools, no
+//! history or memory, and a short cache so repeated lookups are free.
+
+use crate::config::AppConfig;
+use crate::memory::client::{ChatMessage, OpenRouterClient};
+use anyhow::Result;
+use chrono_tz::Tz;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+const MAX_ANSWER_TOKENS: u32 = 300;
+/// Cached answers kept at most; the oldest are dropped first.
+const MAX_CACHED: usize = 256;
+
+const SYSTEM_PROMPT: &str = "You answer quick lookups typed into a chat's inline bar: unit and timezone conversions, short facts, definitions, quick math. Reply with the answer only, in one or two short sentences, no preamble or markdown. If the question needs browsing, files or a longer conversation, say so in one sentence and suggest messaging the bot directly.";
+
+#[derive(Clone)]
+pub struct QuickAnswerer {
+    client: OpenRouterClient,
+    model: String,
+    timezone: Option<Tz>,
+    ttl: Duration,
+    cache: Arc<Mutex<HashMap<String, (Instant, String)>>>,
+}
+
+impl QuickAnswerer {
+    pub fn from_config(cfg: &AppConfig) -> Option<Self> {
+        if !cfg.inline_enabled || cfg.inline_model.trim().is_empty() {
+            return None;
+        }
+        let client = OpenRouterClient::from_config(cfg).ok()?;
+        Some(Self {
+            client,
+            model: cfg.inline_model.clone(),
+            timezone: crate::clock::resolve_timezone(&cfg.timezone),
+            ttl: Duration::from_secs(cfg.inline_cache_secs),
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    pub fn cache_secs(&self) -> u32 {
+        self.ttl.as_secs() as u32
+    }
+
+    pub async fn answer(&self, query: &str) -> Result<String> {
+        let key = normalize(query);
+        if let Some((at, answer)) = self.cache.lock().await.get(&key) {
+            if at.elapsed() < self.ttl {
+                return Ok(answer.clone());
+            }
+        }
+        let system = format!(
+            "{SYSTEM_PROMPT}\nCurrent time: {}",
+            crate::clock::describe_now(self.timezone)
+        );
+        let answer = self
+            .client
+            .chat_completion(
+                &self.model,
+                vec![
+                    ChatMessage {
+                        role: "system".to_string(),
+                        content: system,
+                    },
+                    ChatMessage {
+                        role: "user".to_string(),
+                        content: query.trim().to_string(),
+                    },
+                ],
+                MAX_ANSWER_TOKENS,
+                0.0,
+                None,
+            )
+            .await?
+            .trim()
+            .to_string();
+        let mut cache = self.cache.lock().await;
+        cache.retain(|_, (at, _)| at.elapsed() < self.ttl);
+        if cache.len() >= MAX_CACHED {
+            if let Some(oldest) = cache
+                .iter()
+                .min_by_key(|(_, (at, _))| *at)
+                .map(|(k, _)| k.clone())
+            {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(key, (Instant::now(), answer.clone()));
+        Ok(answer)
+    }
+}
+
+/// Cache key: case and whitespace do not change the question.
+fn normalize(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
@@ -0,0 +1,181 @@
+//! Outbound send pacing. Each channel forwarder owns a [`RateLimiter`] and
+//! waits on it before every API send, so bursts (digests, multi-part replies)
+//! are queued instead of tripping the platform's 429s. When a platform asks
+//! for a pause anyway (Telegram's `retry_after`), [`RateLimiter::pause`] holds
+//! every send to that chat until it is over.
+
+use crate::config::{AppConfig, RateLimit};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Idle per-chat buckets are dropped once this many chats are tracked.
+const MAX_TRACKED_CHATS: usize = 1_000;
+
+/// Token bucket refilled at `rate` per second, holding at most `capacity`.
+#[derive(Debug, Clone)]
+struct Bucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(rate: f64, now: Instant) -> Self {
+        let capacity = rate.max(1.0);
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            last: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last = now;
+    }
+
+    /// Time until a token is available.
+    fn wait(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.rate)
+        }
+    }
+
+    fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+}
+
+pub struct RateLimiter {
+    limit: Option<RateLimit>,
+    global: Option<Bucket>,
+    chats: HashMap<String, Bucket>,
+    /// Chats the platform asked us to leave alone until the given time.
+    paused: HashMap<String, Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: Option<RateLimit>) -> Self {
+        let now = Instant::now();
+        Self {
+            limit,
+            global: limit
+                .filter(|l| l.global_per_sec > 0.0)
+                .map(|l| Bucket::new(l.global_per_sec, now)),
+            chats: HashMap::new(),
+            paused: HashMap::new(),
+        }
+    }
+
+    pub fn for_channel(cfg: &AppConfig, channel: &str) -> Self {
+        Self::new(cfg.rate_limits.get(channel).copied())
+    }
+
+    /// Hold sends to `chat_id` for `wait`, as the platform asked.
+    pub fn pause(&mut self, chat_id: &str, wait: Duration) {
+        let until = Instant::now() + wait;
+        let entry = self.paused.entry(chat_id.to_string()).or_insert(until);
+        *entry = (*entry).max(until);
+    }
+
+    /// Time left of a pause on `chat_id`; zero when it may be sent to.
+    pub fn paused_for(&mut self, chat_id: &str) -> Duration {
+        self.pause_left(chat_id, Instant::now())
+    }
+
+    fn pause_left(&mut self, chat_id: &str, now: Instant) -> Duration {
+        match self.paused.get(chat_id) {
+            Some(until) if *until > now => *until - now,
+            Some(_) => {
+                self.paused.remove(chat_id);
+                Duration::ZERO
+            }
+            None => Duration::ZERO,
+        }
+    }
+
+    /// How long a send to `chat_id` must wait at `now`; zero means it may go
+    /// and its tokens have been taken.
+    fn reserve(&mut self, chat_id: &str, now: Instant) -> Duration {
+        let paused = self.pause_left(chat_id, now);
+        if !paused.is_zero() {
+            return paused;
+        }
+        let per_chat = self.limit.map(|l| l.per_chat_per_sec).unwrap_or(0.0);
+        if per_chat > 0.0 && !self.chats.contains_key(chat_id) {
+            if self.chats.len() >= MAX_TRACKED_CHATS {
+                self.chats.retain(|_, bucket| !bucket.is_full(now));
+            }
+            self.chats
+                .insert(chat_id.to_string(), Bucket::new(per_chat, now));
+        }
+        let global_wait = self.global.as_mut().map_or(Duration::ZERO, |b| b.wait(now));
+        let chat_wait = self
+            .chats
+            .get_mut(chat_id)
+            .map_or(Duration::ZERO, |b| b.wait(now));
+        let wait = global_wait.max(chat_wait);
+        if wait.is_zero() {
+            if let Some(bucket) = self.global.as_mut() {
+                bucket.tokens -= 1.0;
+            }
+            if let Some(bucket) = self.chats.get_mut(chat_id) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        wait
+    }
+
+    /// Wait until a message may be sent to `chat_id`.
+    pub async fn acquire(&mut self, chat_id: &str) {
+        loop {
+            let wait = self.reserve(chat_id, Instant::now());
+            if wait.is_zero() {
+                return;
+            }
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use crate::config::RateLimit;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn paces_per_chat_and_globally() {
+        let mut limiter = RateLimiter::new(Some(RateLimit {
+            global_per_sec: 2.0,
+            per_chat_per_sec: 1.0,
+        }));
+        let now = Instant::now();
+        assert!(limiter.reserve("a", now).is_zero());
+        // Same chat must wait a second; another chat may go right away.
+        assert_eq!(limiter.reserve("a", now), Duration::from_secs(1));
+        assert!(limiter.reserve("b", now).is_zero());
+        // Global budget (2/s) is now spent.
+        assert!(!limiter.reserve("c", now).is_zero());
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.reserve("a", later).is_zero());
+
+        let mut unlimited = RateLimiter::new(None);
+        for _ in 0..100 {
+            assert!(unlimited.reserve("a", now).is_zero());
+        }
+        unlimited.pause("a", Duration::from_secs(30));
+        assert!(!unlimited.reserve("a", Instant::now()).is_zero());
+        assert!(unlimited.reserve("b", Instant::now()).is_zero());
+        assert!(unlimited
+            .reserve("a", Instant::now() + Duration::from_secs(31))
+            .is_zero());
+    }
+}
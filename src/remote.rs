@@ -0,0 +1,476 @@
+use crate::config::{RemoteConfig, RemoteKind};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, KeyInit, Mac};
+use regex::Regex;
+use reqwest::{Client, Method, StatusCode};
+use sha2::{Digest, Sha256};
+
+/// An object stored remotely, keyed by its path relative to the prefix.
+#[derive(Debug, Clone)]
+pub struct RemoteObject {
+    pub key: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    pub etag: Option<String>,
+}
+
+impl RemoteObject {
+    /// Value that changes whenever the object content changes.
+    pub fn fingerprint(&self) -> String {
+        match &self.etag {
+            Some(etag) => etag.trim_matches('"').to_string(),
+            None => format!(
+                "{}:{}",
+                self.size,
+                self.modified.map(|m| m.timestamp()).unwrap_or(0)
+            ),
+        }
+    }
+}
+
+/// Minimal client for S3-compatible object storage (SigV4, path-style
+/// addressing) and WebDAV servers. Keys are `/`-separated and relative to
+/// the configured prefix.
+#[derive(Clone)]
+pub struct RemoteStore {
+    cfg: RemoteConfig,
+    http: Client,
+}
+
+impl RemoteStore {
+    pub fn new(cfg: RemoteConfig) -> Self {
+        Self {
+            cfg,
+            http: Client::new(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.cfg.kind {
+            RemoteKind::S3 => format!(
+                "s3://{}/{}",
+                self.cfg.bucket,
+                self.cfg.prefix.trim_matches('/')
+            ),
+            RemoteKind::WebDav => self.object_url(""),
+        }
+    }
+
+    /// Upload an object. Returns its new fingerprint (see
+    /// [`RemoteObject::fingerprint`]) when the server reports an ETag.
+    pub async fn put(&self, key: &str, body: Vec<u8>) -> Result<Option<String>> {
+        if self.cfg.kind == RemoteKind::WebDav {
+            self.webdav_mkcols(key).await?;
+        }
+        let resp = self.send(Method::PUT, key, &[], body).await?;
+        check(resp.status(), "upload", key)?;
+        Ok(resp
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(|etag| etag.trim_matches('"').to_string()))
+    }
+
+    /// Download an object, or `None` if it does not exist.
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let resp = self.send(Method::GET, key, &[], Vec::new()).await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        check(resp.status(), "download", key)?;
+        Ok(Some(resp.bytes().await?.to_vec()))
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        let resp = self.send(Method::DELETE, key, &[], Vec::new()).await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        check(resp.status(), "delete", key)
+    }
+
+    /// All objects below `dir` (recursively).
+    pub async fn list(&self, dir: &str) -> Result<Vec<RemoteObject>> {
+        match self.cfg.kind {
+            RemoteKind::S3 => self.s3_list(dir).await,
+            RemoteKind::WebDav => self.webdav_list(dir).await,
+        }
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        let prefix = self.cfg.prefix.trim_matches('/');
+        let key = key.trim_start_matches('/');
+        match (prefix.is_empty(), key.is_empty()) {
+            (true, _) => key.to_string(),
+            (false, true) => format!("{prefix}/"),
+            (false, false) => format!("{prefix}/{key}"),
+        }
+    }
+
+    fn object_path(&self, key: &str) -> String {
+        let full = self.full_key(key);
+        match self.cfg.kind {
+            RemoteKind::S3 => format!("/{}/{}", self.cfg.bucket, uri_encode(&full, false)),
+            RemoteKind::WebDav => format!("/{}", uri_encode(&full, false)),
+        }
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        format!(
+            "{}{}",
+            self.cfg.endpoint.trim_end_matches('/'),
+            self.object_path(key)
+        )
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response> {
+        let mut url = self.object_url(key);
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(&canonical_query(query));
+        }
+        let mut req = self.http.request(method.clone(), &url);
+        match self.cfg.kind {
+            RemoteKind::S3 => {
+                for (name, value) in
+                    self.s3_auth_headers(&method, &self.object_path(key), query, &body)?
+                {
+                    req = req.header(name, value);
+                }
+            }
+            RemoteKind::WebDav => {
+                if let Some(user) = &self.cfg.username {
+                    req = req.basic_auth(user, self.cfg.password.as_deref());
+                }
+            }
+        }
+        req.body(body)
+            .send()
+            .await
+            .with_context(|| format!("{method} {url} failed"))
+    }
+
+    fn s3_auth_headers(
+        &self,
+        method: &Method,
+        path: &str,
+        query: &[(&str, String)],
+        body: &[u8],
+    ) -> Result<Vec<(&'static str, String)>> {
+        let access_key = self
+            .cfg
+            .access_key
+            .as_deref()
+            .ok_or_else(|| anyhow!("remote.access_key is not set"))?;
+        let secret_key = self
+            .cfg
+            .secret_key
+            .as_deref()
+            .ok_or_else(|| anyhow!("remote.secret_key is not set"))?;
+        let endpoint = url::Url::parse(&self.cfg.endpoint)?;
+        let host = endpoint
+            .host_str()
+            .ok_or_else(|| anyhow!("remote.endpoint has no host"))?;
+        let host = match endpoint.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        };
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(body));
+        let canonical_request = format!(
+            "{method}\n{path}\n{}\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\nhost;x-amz-content-sha256;x-amz-date\n{payload_hash}",
+            canonical_query(query)
+        );
+        let scope = format!("{date}/{}/s3/aws4_request", self.cfg.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut key = hmac_sha256(format!("AWS4{secret_key}").as_bytes(), date.as_bytes());
+        for part in [self.cfg.region.as_str(), "s3", "aws4_request"] {
+            key = hmac_sha256(&key, part.as_bytes());
+        }
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+        Ok(vec![
+            ("x-amz-date", amz_date),
+            ("x-amz-content-sha256", payload_hash),
+            (
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={access_key}/{scope}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={signature}"
+                ),
+            ),
+        ])
+    }
+
+    async fn s3_list(&self, dir: &str) -> Result<Vec<RemoteObject>> {
+        let list_prefix = self.full_key(dir);
+        let strip = self.full_key("");
+        let mut objects = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("list-type", "2".to_string()),
+                ("prefix", list_prefix.clone()),
+            ];
+            if let Some(token) = &token {
+                query.push(("continuation-token", token.clone()));
+            }
+            // Listing is a bucket-level request: sign `/<bucket>` rather than an object path.
+            let resp = self.send_bucket(&query).await?;
+            check(resp.status(), "list", dir)?;
+            let xml = resp.text().await?;
+            for block in xml_blocks(&xml, "Contents") {
+                let Some(key) = xml_value(block, "Key") else {
+                    continue;
+                };
+                let key = xml_unescape(&key);
+                objects.push(RemoteObject {
+                    key: key.strip_prefix(&strip).unwrap_or(&key).to_string(),
+                    size: xml_value(block, "Size")
+                        .and_then(|s| s.parse().ok())
+                        .unwrap_or(0),
+                    modified: xml_value(block, "LastModified")
+                        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                        .map(|d| d.with_timezone(&Utc)),
+                    etag: xml_value(block, "ETag").map(|e| xml_unescape(&e)),
+                });
+            }
+            token = xml_value(&xml, "NextContinuationToken").map(|t| xml_unescape(&t));
+            if token.is_none() {
+                break;
+            }
+        }
+        Ok(objects)
+    }
+
+    async fn send_bucket(&self, query: &[(&str, String)]) -> Result<reqwest::Response> {
+        let path = format!("/{}", self.cfg.bucket);
+        let url = format!(
+            "{}{}?{}",
+            self.cfg.endpoint.trim_end_matches('/'),
+            path,
+            canonical_query(query)
+        );
+        let mut req = self.http.get(&url);
+        for (name, value) in self.s3_auth_headers(&Method::GET, &path, query, &[])? {
+            req = req.header(name, value);
+        }
+        req.send()
+            .await
+            .with_context(|| format!("GET {url} failed"))
+    }
+
+    async fn webdav_list(&self, dir: &str) -> Result<Vec<RemoteObject>> {
+        let base_path = percent_decode(&self.object_path(""));
+        let mut objects = Vec::new();
+        let mut pending = vec![dir.trim_matches('/').to_string()];
+        while let Some(current) = pending.pop() {
+            let key = if current.is_empty() {
+                String::new()
+            } else {
+                format!("{current}/")
+            };
+            let resp = self
+                .http
+                .request(Method::from_bytes(b"PROPFIND")?, self.object_url(&key))
+                .header("Depth", "1")
+                .basic_auth(
+                    self.cfg.username.clone().unwrap_or_default(),
+                    self.cfg.password.as_deref(),
+                )
+                .send()
+                .await?;
+            if resp.status() == StatusCode::NOT_FOUND {
+                continue;
+            }
+            check(resp.status(), "list", &key)?;
+            let xml = resp.text().await?;
+            for block in xml_blocks(&xml, "response") {
+                let Some(href) = xml_value(block, "href") else {
+                    continue;
+                };
+                let href = percent_decode(&xml_unescape(&href));
+                let path = url::Url::parse(&href)
+                    .map(|u| percent_decode(u.path()))
+                    .unwrap_or(href);
+                let Some(pos) = path.find(&base_path) else {
+                    continue;
+                };
+                let rel = path[pos + base_path.len()..].trim_matches('/').to_string();
+                if rel.is_empty() || rel == current {
+                    continue;
+                }
+                if block.contains("collection") {
+                    pending.push(rel);
+                    continue;
+                }
+                objects.push(RemoteObject {
+                    key: rel,
+                    size: xml_value(block, "getcontentlength")
+                        .and_then(|s| s.parse().ok())
+                        .unwrap_or(0),
+                    modified: xml_value(block, "getlastmodified")
+                        .and_then(|s| DateTime::parse_from_rfc2822(&s).ok())
+                        .map(|d| d.with_timezone(&Utc)),
+                    etag: xml_value(block, "getetag").map(|e| xml_unescape(&e)),
+                });
+            }
+        }
+        Ok(objects)
+    }
+
+    /// WebDAV needs parent collections to exist before a PUT.
+    async fn webdav_mkcols(&self, key: &str) -> Result<()> {
+        let full = self.full_key(key);
+        let parts: Vec<&str> = full.split('/').filter(|p| !p.is_empty()).collect();
+        let mut path = String::new();
+        for part in parts.iter().take(parts.len().saturating_sub(1)) {
+            path.push('/');
+            path.push_str(&uri_encode(part, true));
+            let url = format!("{}{}/", self.cfg.endpoint.trim_end_matches('/'), path);
+            let resp = self
+                .http
+                .request(Method::from_bytes(b"MKCOL")?, &url)
+                .basic_auth(
+                    self.cfg.username.clone().unwrap_or_default(),
+                    self.cfg.password.as_deref(),
+                )
+                .send()
+                .await?;
+            // 405 means the collection already exists.
+            if !resp.status().is_success() && resp.status() != StatusCode::METHOD_NOT_ALLOWED {
+                return Err(anyhow!(
+                    "failed to create remote folder {path}: {}",
+                    resp.status()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check(status: StatusCode, action: &str, key: &str) -> Result<()> {
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(anyhow!("remote {action} of '{key}' failed: HTTP {status}"))
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// SigV4 URI encoding; `/` is kept in paths and encoded in query values.
+fn uri_encode(raw: &str, encode_slash: bool) -> String {
+    let mut out = String::new();
+    for byte in raw.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+fn canonical_query(query: &[(&str, String)]) -> String {
+    let mut pairs: Vec<(String, String)> = query
+        .iter()
+        .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+        .collect();
+    pairs.sort();
+    pairs
+        .into_iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn percent_decode(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Ok(byte) = u8::from_str_radix(&raw[i + 1..i + 3], 16) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Inner text of every `<tag>` element, ignoring namespace prefixes.
+fn xml_blocks<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let re = Regex::new(&format!(
+        r"(?s)<(?:[\w-]+:)?{tag}(?:\s[^>]*)?>(.*?)</(?:[\w-]+:)?{tag}>"
+    ))
+    .expect("valid tag regex");
+    re.captures_iter(xml)
+        .filter_map(|c| c.get(1))
+        .map(|m| m.as_str())
+        .collect()
+}
+
+fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    xml_blocks(xml, tag)
+        .first()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn xml_unescape(raw: &str) -> String {
+    raw.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonical_query, percent_decode, uri_encode, xml_blocks, xml_value};
+
+    #[test]
+    fn encodes_like_sigv4() {
+        assert_eq!(
+            uri_encode("memory/My Notes.md", false),
+            "memory/My%20Notes.md"
+        );
+        assert_eq!(
+            canonical_query(&[
+                ("prefix", "a/b".to_string()),
+                ("list-type", "2".to_string())
+            ]),
+            "list-type=2&prefix=a%2Fb"
+        );
+        assert_eq!(percent_decode("/dav/My%20Notes.md"), "/dav/My Notes.md");
+    }
+
+    #[test]
+    fn parses_namespaced_xml() {
+        let xml = "<d:multistatus><d:response><d:href>/a</d:href></d:response><D:response><D:href>/b</D:href></D:response></d:multistatus>";
+        let blocks = xml_blocks(xml, "response");
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(xml_value(blocks[1], "href").as_deref(), Some("/b"));
+    }
+}
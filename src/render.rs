@@ -0,0 +1,708 @@
+//! Outbound rendering: the agent writes markdown, and each channel gets its
+//! native formatting (Telegram MarkdownV2, Slack mrkdwn, plain text) cut to
+//! the channel's message length limit.
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Passed through unchanged (Discord, webhooks, terminals).
+    Markdown,
+    TelegramMarkdownV2,
+    SlackMrkdwn,
+    /// Formatting stripped, links spelled out (SMS and similar).
+    Plain,
+}
+
+impl Format {
+    pub fn for_channel(channel: &str) -> Self {
+        match channel {
+            "telegram" => Self::TelegramMarkdownV2,
+            "slack" => Self::SlackMrkdwn,
+            "sms" | "irc" | "xmpp" => Self::Plain,
+            _ => Self::Markdown,
+        }
+    }
+}
+
+/// Longest single message `channel` accepts, in bytes.
+pub fn max_len(channel: &str) -> usize {
+    match channel {
+        "telegram" => 4096,
+        "discord" => 2000,
+        "matrix" => 32_000,
+        "mattermost" => 16_000,
+        "slack" => 40_000,
+        "sms" => 1600,
+        _ => usize::MAX,
+    }
+}
+
+/// Render `markdown` in `format`.
+pub fn render(markdown: &str, format: Format) -> String {
+    match format {
+        Format::Markdown => markdown.trim_end().to_string(),
+        Format::TelegramMarkdownV2 => to_telegram_markdown_v2(markdown),
+        Format::SlackMrkdwn => to_simple(markdown, true),
+        Format::Plain => to_simple(markdown, false),
+    }
+}
+
+/// Render `markdown` for `channel` and cut it into messages the channel
+/// accepts. The markdown is split before rendering, on paragraph and code
+/// block boundaries, so every message carries complete formatting.
+pub fn render_for(markdown: &str, channel: &str) -> Vec<String> {
+    let limit = max_len(channel);
+    let mut out = Vec::new();
+    render_chunks(
+        markdown,
+        Format::for_channel(channel),
+        limit,
+        limit,
+        &mut out,
+    );
+    out
+}
+
+/// Smallest markdown budget tried before falling back to cutting rendered
+/// text, which may split an entity.
+const MIN_CHUNK_BUDGET: usize = 256;
+
+fn render_chunks(
+    markdown: &str,
+    format: Format,
+    limit: usize,
+    budget: usize,
+    out: &mut Vec<String>,
+) {
+    for chunk in chunk_markdown(markdown, budget) {
+        let rendered = render(&chunk, format);
+        if rendered.len() <= limit {
+            if !rendered.trim().is_empty() {
+                out.push(rendered);
+            }
+        } else if budget > MIN_CHUNK_BUDGET {
+            // Escaping grew the chunk past the limit; retry it in smaller pieces.
+            let smaller = (budget / 2).max(MIN_CHUNK_BUDGET);
+            render_chunks(&chunk, format, limit, smaller, out);
+        } else {
+            out.extend(split_text(&rendered, limit).into_iter().map(str::to_string));
+        }
+    }
+}
+
+/// Split `markdown` into pieces of at most `limit` bytes, packing whole
+/// paragraphs and fenced code blocks. A paragraph that is too long on its
+/// own is cut at line breaks; a code block is cut the same way with its
+/// fence closed and reopened around each piece.
+pub fn chunk_markdown(markdown: &str, limit: usize) -> Vec<String> {
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for block in markdown_blocks(markdown) {
+        let pieces = if block.len() <= limit {
+            vec![block]
+        } else {
+            split_block(&block, limit)
+        };
+        for piece in pieces {
+            if !current.is_empty() && current.len() + 2 + piece.len() > limit {
+                chunks.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(&piece);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Paragraphs and fenced code blocks of `markdown`, without the blank lines
+/// between them.
+fn markdown_blocks(markdown: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in markdown.lines() {
+        let marker = fence_marker(line);
+        match fence {
+            Some(open) => {
+                current.push(line);
+                if marker.is_some_and(|m| m.starts_with(open) && line.trim() == m) {
+                    fence = None;
+                }
+            }
+            None if line.trim().is_empty() => {
+                if !current.is_empty() {
+                    blocks.push(current.join("\n"));
+                    current.clear();
+                }
+            }
+            None => {
+                current.push(line);
+                fence = marker;
+            }
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current.join("\n"));
+    }
+    blocks
+}
+
+/// The run of backticks or tildes opening a code fence on `line`, if any.
+fn fence_marker(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let ch = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.len() - trimmed.trim_start_matches(ch).len();
+    (len >= 3).then(|| &trimmed[..len])
+}
+
+fn split_block(block: &str, limit: usize) -> Vec<String> {
+    let mut lines = block.lines();
+    let first = lines.next().unwrap_or_default();
+    let Some(marker) = fence_marker(first) else {
+        return split_text(block, limit)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+    };
+    let body: Vec<&str> = lines.collect();
+    let body = match body.split_last() {
+        Some((last, rest)) if last.trim() == marker => rest.join("\n"),
+        _ => body.join("\n"),
+    };
+    // Room for the opening line, the closing fence and two line breaks.
+    let room = limit.saturating_sub(first.len() + marker.len() + 2).max(1);
+    split_text(&body, room)
+        .into_iter()
+        .map(|piece| format!("{first}\n{piece}\n{marker}"))
+        .collect()
+}
+
+/// Split `text` into chunks of at most `limit` bytes, preferring line breaks
+/// and never cutting inside a UTF-8 character. Blank chunks are dropped.
+pub fn split_text(text: &str, limit: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut remaining = text;
+    while !remaining.is_empty() {
+        let chunk_len = if remaining.len() <= limit {
+            remaining.len()
+        } else {
+            let mut end = limit;
+            while !remaining.is_char_boundary(end) {
+                end -= 1;
+            }
+            match remaining[..end].rfind('\n') {
+                Some(idx) if idx > 0 => idx,
+                _ => end,
+            }
+        };
+        let chunk = &remaining[..chunk_len];
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk);
+        }
+        remaining = &remaining[chunk_len..];
+        if let Some(rest) = remaining.strip_prefix('\n') {
+            remaining = rest;
+        }
+    }
+    chunks
+}
+
+fn parser_options() -> Options {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options
+}
+
+fn ensure_line_break(out: &mut String) {
+    if !out.ends_with('\n') && !out.is_empty() {
+        out.push('\n');
+    }
+}
+
+fn to_telegram_markdown_v2(input: &str) -> String {
+    #[derive(Clone, Copy)]
+    enum ListKind {
+        Unordered,
+        Ordered,
+    }
+
+    #[derive(Clone, Copy)]
+    struct ListState {
+        kind: ListKind,
+        next: u64,
+    }
+
+    fn push_blockquote_prefix(out: &mut String, depth: usize) {
+        for _ in 0..depth {
+            out.push_str("\\> ");
+        }
+    }
+
+    let parser = Parser::new_ext(input, parser_options());
+    let mut out = String::with_capacity(input.len() + 16);
+    let mut list_stack: Vec<ListState> = Vec::new();
+    let mut in_code_block = false;
+    let mut item_open = false;
+    let mut link_urls: Vec<String> = Vec::new();
+    let mut blockquote_depth = 0usize;
+
+    for event in parser {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph => {}
+                Tag::Heading { .. } => {
+                    ensure_line_break(&mut out);
+                    out.push('*');
+                }
+                Tag::List(start) => {
+                    list_stack.push(ListState {
+                        kind: if start.is_some() {
+                            ListKind::Ordered
+                        } else {
+                            ListKind::Unordered
+                        },
+                        next: start.unwrap_or(1),
+                    });
+                    ensure_line_break(&mut out);
+                }
+                Tag::Item => {
+                    ensure_line_break(&mut out);
+                    if let Some(last) = list_stack.last_mut() {
+                        match last.kind {
+                            ListKind::Unordered => out.push_str("• "),
+                            ListKind::Ordered => {
+                                out.push_str(&last.next.to_string());
+                                out.push_str("\\. ");
+                                last.next += 1;
+                            }
+                        }
+                    }
+                    item_open = true;
+                }
+                Tag::Emphasis => out.push('_'),
+                Tag::Strong => out.push('*'),
+                Tag::Strikethrough => out.push('~'),
+                Tag::BlockQuote(_) => {
+                    ensure_line_break(&mut out);
+                    blockquote_depth += 1;
+                    push_blockquote_prefix(&mut out, blockquote_depth);
+                }
+                Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => {
+                    out.push('[');
+                    link_urls.push(dest_url.to_string());
+                }
+                Tag::CodeBlock(kind) => {
+                    ensure_line_break(&mut out);
+                    out.push_str("```");
+                    if let CodeBlockKind::Fenced(lang) = kind {
+                        let lang = lang.trim();
+                        if !lang.is_empty() {
+                            out.push_str(&escape_markdown_v2_code(lang));
+                        }
+                    }
+                    out.push('\n');
+                    in_code_block = true;
+                }
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Paragraph => {
+                    ensure_line_break(&mut out);
+                }
+                TagEnd::Heading(_) => {
+                    out.push('*');
+                    ensure_line_break(&mut out);
+                }
+                TagEnd::List(_) => {
+                    let _ = list_stack.pop();
+                    ensure_line_break(&mut out);
+                }
+                TagEnd::Item => {
+                    if item_open {
+                        ensure_line_break(&mut out);
+                    }
+                    item_open = false;
+                }
+                TagEnd::Emphasis => out.push('_'),
+                TagEnd::Strong => out.push('*'),
+                TagEnd::Strikethrough => out.push('~'),
+                TagEnd::Link | TagEnd::Image => {
+                    let url = link_urls.pop().unwrap_or_default();
+                    out.push(']');
+                    out.push('(');
+                    out.push_str(&escape_markdown_v2_url(&url));
+                    out.push(')');
+                }
+                TagEnd::CodeBlock => {
+                    if !out.ends_with('\n') {
+                        out.push('\n');
+                    }
+                    out.push_str("```");
+                    ensure_line_break(&mut out);
+                    in_code_block = false;
+                }
+                TagEnd::BlockQuote(_) => {
+                    ensure_line_break(&mut out);
+                    blockquote_depth = blockquote_depth.saturating_sub(1);
+                }
+                _ => {}
+            },
+            Event::Text(text) => {
+                if in_code_block {
+                    out.push_str(&escape_markdown_v2_code(&text));
+                } else {
+                    out.push_str(&escape_markdown_v2_text(&text));
+                }
+            }
+            Event::Code(code) => {
+                out.push('`');
+                out.push_str(&escape_markdown_v2_code(&code));
+                out.push('`');
+            }
+            Event::InlineHtml(html) | Event::Html(html) => {
+                out.push_str(&escape_markdown_v2_text(&html));
+            }
+            Event::InlineMath(math) | Event::DisplayMath(math) => {
+                out.push_str(&escape_markdown_v2_text(&math));
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                out.push('\n');
+                if blockquote_depth > 0 {
+                    push_blockquote_prefix(&mut out, blockquote_depth);
+                }
+            }
+            Event::Rule => {
+                ensure_line_break(&mut out);
+                out.push_str("\\-\\-\\-");
+                ensure_line_break(&mut out);
+            }
+            Event::FootnoteReference(label) => {
+                out.push('[');
+                out.push_str(&escape_markdown_v2_text(&label));
+                out.push(']');
+            }
+            Event::TaskListMarker(checked) => {
+                if checked {
+                    out.push_str("\\[x\\] ");
+                } else {
+                    out.push_str("\\[ \\] ");
+                }
+            }
+        }
+    }
+
+    out.trim_end().to_string()
+}
+
+/// Plain text of a MarkdownV2 message: escapes resolved, entity markers
+/// dropped and links spelled out. Used to resend a chunk Telegram refused
+/// to parse.
+pub fn plain_from_markdown_v2(input: &str) -> String {
+    let chars: Vec<char> = input.chars().collect();
+    let mut out = String::with_capacity(input.len());
+    let mut in_pre = false;
+    let mut in_code = false;
+    let mut link_text_start: Option<usize> = None;
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        let at_line_start = i == 0 || chars[i - 1] == '\n';
+        match ch {
+            '\\' if i + 1 < chars.len() => {
+                out.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            '`' if chars[i..].starts_with(&['`', '`', '`']) => {
+                i += 3;
+                if !in_pre {
+                    // Skip the language tag; the code starts on the next line.
+                    while i < chars.len() && chars[i] != '\n' {
+                        i += 1;
+                    }
+                    i += 1;
+                } else if out.ends_with('\n') {
+                    out.pop();
+                }
+                in_pre = !in_pre;
+                continue;
+            }
+            _ if in_pre => out.push(ch),
+            '`' => in_code = !in_code,
+            _ if in_code => out.push(ch),
+            '*' | '_' | '~' | '|' => {}
+            '>' if at_line_start => {}
+            '[' => link_text_start = Some(out.len()),
+            ']' if link_text_start.is_some() && chars.get(i + 1) == Some(&'(') => {
+                let text_start = link_text_start.take().unwrap_or(out.len());
+                let mut url = String::new();
+                i += 2;
+                while i < chars.len() && chars[i] != ')' {
+                    if chars[i] == '\\' && i + 1 < chars.len() {
+                        i += 1;
+                    }
+                    url.push(chars[i]);
+                    i += 1;
+                }
+                if out[text_start..] != url {
+                    out.push_str(&format!(" ({url})"));
+                }
+            }
+            _ => out.push(ch),
+        }
+        i += 1;
+    }
+    out
+}
+
+fn escape_markdown_v2_text(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for ch in input.chars() {
+        push_escaped_markdown_v2_char(&mut out, ch);
+    }
+    out
+}
+
+fn escape_markdown_v2_code(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for ch in input.chars() {
+        match ch {
+            '`' | '\\' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+fn escape_markdown_v2_url(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for ch in input.chars() {
+        match ch {
+            ')' | '\\' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+fn push_escaped_markdown_v2_char(out: &mut String, ch: char) {
+    match ch {
+        '_' | '*' | '[' | ']' | '(' | ')' | '~' | '`' | '>' | '#' | '+' | '-' | '=' | '|' | '{'
+        | '}' | '.' | '!' | '\\' => {
+            out.push('\\');
+            out.push(ch);
+        }
+        _ => out.push(ch),
+    }
+}
+
+/// Slack mrkdwn (`slack`) or plain text: lists become bullets, headings bold
+/// (or plain lines), links `<url|text>` (or `text (url)`).
+fn to_simple(input: &str, slack: bool) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut list_stack: Vec<Option<u64>> = Vec::new();
+    let mut links: Vec<(String, usize)> = Vec::new();
+    let mut blockquote_depth = 0usize;
+    let mut in_code_block = false;
+    let mark = |out: &mut String, slack_mark: &str| {
+        if slack {
+            out.push_str(slack_mark);
+        }
+    };
+
+    for event in Parser::new_ext(input, parser_options()) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Heading { .. } => {
+                    ensure_line_break(&mut out);
+                    mark(&mut out, "*");
+                }
+                Tag::List(start) => {
+                    list_stack.push(start);
+                    ensure_line_break(&mut out);
+                }
+                Tag::Item => {
+                    ensure_line_break(&mut out);
+                    out.push_str(&"  ".repeat(list_stack.len().saturating_sub(1)));
+                    match list_stack.last_mut() {
+                        Some(Some(next)) => {
+                            out.push_str(&format!("{next}. "));
+                            *next += 1;
+                        }
+                        _ => out.push_str("• "),
+                    }
+                }
+                Tag::Emphasis => mark(&mut out, "_"),
+                Tag::Strong => mark(&mut out, "*"),
+                Tag::Strikethrough => mark(&mut out, "~"),
+                Tag::BlockQuote(_) => {
+                    ensure_line_break(&mut out);
+                    blockquote_depth += 1;
+                    out.push_str(&"> ".repeat(blockquote_depth));
+                }
+                Tag::Link { dest_url, .. } => {
+                    links.push((dest_url.to_string(), out.len()));
+                }
+                Tag::CodeBlock(_) => {
+                    ensure_line_break(&mut out);
+                    if slack {
+                        out.push_str("```\n");
+                    }
+                    in_code_block = true;
+                }
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Paragraph | TagEnd::List(_) | TagEnd::Item => {
+                    if matches!(tag, TagEnd::List(_)) {
+                        list_stack.pop();
+                    }
+                    ensure_line_break(&mut out);
+                }
+                TagEnd::Heading(_) => {
+                    mark(&mut out, "*");
+                    ensure_line_break(&mut out);
+                }
+                TagEnd::Emphasis => mark(&mut out, "_"),
+                TagEnd::Strong => mark(&mut out, "*"),
+                TagEnd::Strikethrough => mark(&mut out, "~"),
+                TagEnd::Link => {
+                    let (url, start) = links.pop().unwrap_or_default();
+                    let text = out.split_off(start);
+                    if slack {
+                        out.push_str(&format!("<{url}|{text}>"));
+                    } else if text == url || text.is_empty() {
+                        out.push_str(&url);
+                    } else {
+                        out.push_str(&format!("{text} ({url})"));
+                    }
+                }
+                TagEnd::CodeBlock => {
+                    ensure_line_break(&mut out);
+                    if slack {
+                        out.push_str("```");
+                    }
+                    ensure_line_break(&mut out);
+                    in_code_block = false;
+                }
+                TagEnd::BlockQuote(_) => {
+                    ensure_line_break(&mut out);
+                    blockquote_depth = blockquote_depth.saturating_sub(1);
+                }
+                _ => {}
+            },
+            Event::Text(text) if slack && !in_code_block => out.push_str(&escape_slack(&text)),
+            Event::Text(text)
+            | Event::InlineHtml(text)
+            | Event::Html(text)
+            | Event::InlineMath(text)
+            | Event::DisplayMath(text)
+            | Event::FootnoteReference(text) => out.push_str(&text),
+            Event::Code(code) => {
+                mark(&mut out, "`");
+                out.push_str(&code);
+                mark(&mut out, "`");
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                out.push('\n');
+                out.push_str(&"> ".repeat(blockquote_depth));
+            }
+            Event::Rule => {
+                ensure_line_break(&mut out);
+                out.push_str("---");
+                ensure_line_break(&mut out);
+            }
+            Event::TaskListMarker(checked) => {
+                out.push_str(if checked { "[x] " } else { "[ ] " });
+            }
+        }
+    }
+    out.trim_end().to_string()
+}
+
+/// Slack treats `&`, `<` and `>` as control characters in mrkdwn.
+fn escape_slack(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk_markdown, plain_from_markdown_v2, render, render_for, split_text, Format};
+
+    #[test]
+    fn renders_multiline_blockquote_lines() {
+        let input = "> first line\n> second line";
+        let rendered = render(input, Format::TelegramMarkdownV2);
+        assert_eq!(rendered, "\\> first line\n\\> second line");
+    }
+
+    #[test]
+    fn strips_markdown_v2_to_plain_text() {
+        let input = "# Plan\n**Pack** the [list](https://x.io/a_(b)) & `tent_1`, 2.5*2\n\n```sh\nls *.md\n```";
+        let rendered = render(input, Format::TelegramMarkdownV2);
+        assert_eq!(
+            plain_from_markdown_v2(&rendered),
+            "Plan\nPack the list (https://x.io/a_(b)) & tent_1, 2.5*2\nls *.md"
+        );
+    }
+
+    #[test]
+    fn renders_slack_and_plain() {
+        let input = "# Plan\n**Pack** the [list](https://x.io) & go:\n\n- socks\n- `tent`";
+        assert_eq!(
+            render(input, Format::SlackMrkdwn),
+            "*Plan*\n*Pack* the <https://x.io|list> &amp; go:\n• socks\n• `tent`"
+        );
+        assert_eq!(
+            render(input, Format::Plain),
+            "Plan\nPack the list (https://x.io) & go:\n• socks\n• tent"
+        );
+    }
+
+    #[test]
+    fn chunks_on_paragraphs_and_reopens_code_fences() {
+        let text = format!(
+            "intro\n\n```rust\n{}\n```\n\noutro",
+            "let x = 1;\n".repeat(10)
+        );
+        let chunks = chunk_markdown(&text, 60);
+        assert!(chunks.len() > 2);
+        assert!(chunks[0].starts_with("intro"));
+        assert!(chunks[chunks.len() - 1].ends_with("outro"));
+        assert!(chunks.iter().all(|c| c.len() <= 60));
+        assert!(chunks.iter().all(|c| c.matches("```").count() % 2 == 0));
+        assert_eq!(chunks.concat().matches("let x = 1;").count(), 10);
+        assert_eq!(chunk_markdown("a\n\nb", 100), vec!["a\n\nb"]);
+
+        let long = format!("*{}*\n\n{}", "bold ".repeat(300), "x.".repeat(2000));
+        let rendered = render_for(&long, "telegram");
+        assert!(rendered.len() > 1);
+        assert!(rendered.iter().all(|m| m.len() <= 4096));
+    }
+
+    #[test]
+    fn splits_on_lines_and_char_boundaries() {
+        assert_eq!(split_text("short", 2000), vec!["short"]);
+        assert_eq!(split_text("aaa\nbbb", 5), vec!["aaa", "bbb"]);
+        let text = "é".repeat(5);
+        let chunks = split_text(&text, 3);
+        assert!(chunks.iter().all(|c| c.len() <= 3));
+        assert_eq!(chunks.concat(), text);
+    }
+}
@@ -0,0 +1,135 @@
+//! Terminal channel: runs the agent loop against stdin/stdout so prompts,
+//! tools and memory can be tried locally without any bot token.
+
+use crate::bus::{InboundMessage, MessageBus, OutboundMessage};
+use crate::config::AppConfig;
+use crate::{agent, cron, notify};
+use anyhow::Result;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tokio::io::{self, AsyncBufReadExt};
+use tokio::sync::broadcast::error::RecvError;
+
+pub struct ReplOptions {
+    /// Channel name the agent sees ("cli" for `femtobot chat`).
+    pub channel: String,
+    /// Chat id, i.e. which session history and memory the turns use.
+    pub session: String,
+    /// Sender id, for testing per-user allowlists and sandboxes.
+    pub user: String,
+}
+
+const HELP: &str = "Local commands:
+  /session [name]  show or switch the session (separate history per name)
+  /help            this help
+  /quit            leave (also /exit or Ctrl-D)
+Anything else, including the agent commands listed below, goes to the agent.";
+
+pub async fn run(cfg: AppConfig, opts: ReplOptions) -> Result<()> {
+    let bus = MessageBus::new();
+
+    let notifier = notify::Notifier::new(&cfg, bus.clone());
+    notifier.start();
+
+    let cron_service = cron::CronService::new(&cfg, bus.clone());
+    cron_service.start(notifier.clone()).await;
+
+    let agent = agent::AgentLoop::new(cfg, bus.clone(), cron_service, notifier);
+    tokio::spawn(async move {
+        agent.run().await;
+    });
+
+    let session = Arc::new(Mutex::new(opts.session));
+    let printer_bus = bus.clone();
+    let printer_session = session.clone();
+    let channel = opts.channel.clone();
+    tokio::spawn(async move {
+        let mut outbound_rx = printer_bus.subscribe_outbound();
+        loop {
+            let msg = match outbound_rx.recv().await {
+                Ok(msg) => msg,
+                Err(RecvError::Closed) => break,
+                Err(RecvError::Lagged(_)) => continue,
+            };
+            if msg.channel != channel {
+                continue;
+            }
+            let current = printer_session.lock().unwrap().clone();
+            print_reply(&msg, &current);
+            printer_bus.report_delivery(&msg, true);
+        }
+    });
+
+    println!(
+        "femtobot chat ({} channel, session '{}'). Type /help for commands.\n",
+        opts.channel,
+        session.lock().unwrap()
+    );
+    prompt();
+
+    let sender_name = std::env::var("USER").ok().filter(|name| !name.is_empty());
+    let mut lines = io::BufReader::new(io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        let content = line.trim().to_string();
+        if content.is_empty() {
+            prompt();
+            continue;
+        }
+        let mut words = content.split_whitespace();
+        match words.next().unwrap_or_default() {
+            "/quit" | "/exit" => break,
+            // The agent follows with its own commands.
+            "/help" => println!("{HELP}\n"),
+            "/session" => {
+                let mut current = session.lock().unwrap();
+                if let Some(name) = words.next() {
+                    *current = name.to_string();
+                    println!("Switched to session '{current}'.\n");
+                } else {
+                    println!("Session '{current}'.\n");
+                }
+                drop(current);
+                prompt();
+                continue;
+            }
+            _ => {}
+        }
+        let chat_id = session.lock().unwrap().clone();
+        bus.publish_inbound(InboundMessage {
+            channel: opts.channel.clone(),
+            chat_id,
+            sender_id: opts.user.clone(),
+            sender_name: sender_name.clone(),
+            is_group: false,
+            content,
+            ..Default::default()
+        })
+        .await;
+    }
+
+    Ok(())
+}
+
+fn print_reply(msg: &OutboundMessage, current_session: &str) {
+    let label = if msg.chat_id == current_session {
+        "femtobot".to_string()
+    } else {
+        format!("femtobot [{}]", msg.chat_id)
+    };
+    println!("\r{label}> {}", msg.content.trim());
+    for attachment in &msg.meta.attachments {
+        let location = attachment
+            .path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| attachment.name.clone());
+        println!("  ({} attachment: {location})", attachment.kind);
+    }
+    println!();
+    prompt();
+}
+
+fn prompt() {
+    print!("you> ");
+    let _ = std::io::stdout().flush();
+}
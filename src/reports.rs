@@ -0,0 +1,473 @@
+//! Recurring workspace reports rendered from the structured stores (cron
+//! jobs, expenses, habits, memories, model usage) with fixed templates, so
+//! the numbers come straight from the data rather than from a model.
+//! Reports are written to `<workspace>/reports/` and run on a schedule via
+//! a cron job whose message is `/report <spec>`.
+
+use crate::analytics::{self, Analytics};
+use crate::config::AppConfig;
+use crate::cron::CronService;
+use crate::error::ErrorKind;
+use crate::expenses::ExpenseStore;
+use crate::habits::HabitStore;
+use crate::history::HistoryLog;
+use crate::memory::vector_store::{MemorySelector, VectorMemoryStore};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Memories listed by content in the memory section; the rest are counted.
+const MAX_LISTED_MEMORIES: usize = 10;
+const MEMORY_PREVIEW_CHARS: usize = 120;
+
+/// One model turn, completed or failed, appended to `<data_dir>/usage.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageEntry {
+    pub ts: DateTime<Utc>,
+    pub session: String,
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    #[serde(default)]
+    pub tool_calls: usize,
+    /// Tool loops stopped during the turn.
+    #[serde(default)]
+    pub tool_loops: usize,
+    /// Why the turn failed; failed turns have no provider, model or tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorKind>,
+}
+
+#[derive(Clone)]
+pub struct UsageLog {
+    path: PathBuf,
+}
+
+impl UsageLog {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join("usage.jsonl"),
+        }
+    }
+
+    pub fn append(&self, entry: &UsageEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    /// Entries recorded at or after `since`; unreadable lines are skipped.
+    pub fn since(&self, since: DateTime<Utc>) -> Vec<UsageEntry> {
+        let Ok(raw) = fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        raw.lines()
+            .filter_map(|line| serde_json::from_str::<UsageEntry>(line).ok())
+            .filter(|entry| entry.ts >= since)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportPeriod {
+    Day,
+    Week,
+    Month,
+}
+
+impl ReportPeriod {
+    fn days(self) -> i64 {
+        match self {
+            Self::Day => 1,
+            Self::Week => 7,
+            Self::Month => 30,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Day => "daily",
+            Self::Week => "weekly",
+            Self::Month => "monthly",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportSection {
+    Tasks,
+    Expenses,
+    Habits,
+    Memory,
+    Usage,
+    /// Conversation statistics from `analytics`; only when asked for.
+    Analytics,
+}
+
+impl ReportSection {
+    const ALL: [ReportSection; 5] = [
+        Self::Tasks,
+        Self::Expenses,
+        Self::Habits,
+        Self::Memory,
+        Self::Usage,
+    ];
+}
+
+/// What to report on, parsed from e.g. `weekly`, `daily: tasks, usage` or
+/// `monthly expenses`. No sections means all of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportSpec {
+    pub period: ReportPeriod,
+    pub sections: Vec<ReportSection>,
+}
+
+impl ReportSpec {
+    pub fn parse(raw: &str) -> Result<Self> {
+        let mut period = None;
+        let mut sections = Vec::new();
+        for word in raw
+            .split(|c: char| c.is_whitespace() || c == ',' || c == ':')
+            .filter(|w| !w.is_empty())
+        {
+            match word.to_lowercase().as_str() {
+                "day" | "daily" | "today" => period = Some(ReportPeriod::Day),
+                "week" | "weekly" => period = Some(ReportPeriod::Week),
+                "month" | "monthly" => period = Some(ReportPeriod::Month),
+                "tasks" | "jobs" | "cron" => sections.push(ReportSection::Tasks),
+                "expenses" | "spending" => sections.push(ReportSection::Expenses),
+                "habits" => sections.push(ReportSection::Habits),
+                "memory" | "memories" => sections.push(ReportSection::Memory),
+                "usage" | "cost" | "costs" => sections.push(ReportSection::Usage),
+                "analytics" | "conversations" => sections.push(ReportSection::Analytics),
+                other => {
+                    return Err(anyhow!(
+                        "unknown report word '{other}'. Use a period (daily, weekly, monthly) and sections (tasks, expenses, habits, memory, usage, analytics)"
+                    ))
+                }
+            }
+        }
+        if sections.is_empty() {
+            sections = ReportSection::ALL.to_vec();
+        }
+        let mut seen = HashSet::new();
+        sections.retain(|section| seen.insert(*section));
+        Ok(Self {
+            period: period.unwrap_or(ReportPeriod::Week),
+            sections,
+        })
+    }
+}
+
+pub struct Reporter {
+    workspace_dir: PathBuf,
+    data_dir: PathBuf,
+    cron: CronService,
+    vector_memory: Option<VectorMemoryStore>,
+    usage: UsageLog,
+    timezone: Tz,
+}
+
+impl Reporter {
+    pub fn new(
+        cfg: &AppConfig,
+        cron: CronService,
+        vector_memory: Option<VectorMemoryStore>,
+    ) -> Self {
+        Self {
+            workspace_dir: cfg.workspace_dir.clone(),
+            data_dir: cfg.data_dir.clone(),
+            cron,
+            vector_memory,
+            usage: UsageLog::new(&cfg.data_dir),
+            timezone: crate::clock::resolve_timezone(&cfg.timezone).unwrap_or(Tz::UTC),
+        }
+    }
+
+    /// Render the report for the period ending at `now`, write it under
+    /// `reports/` and return its path and markdown.
+    pub async fn generate(
+        &self,
+        spec: &ReportSpec,
+        now: DateTime<Utc>,
+    ) -> Result<(PathBuf, String)> {
+        let start = now - Duration::days(spec.period.days());
+        let local_start = start.with_timezone(&self.timezone).date_naive();
+        let local_end = now.with_timezone(&self.timezone).date_naive();
+        let mut out = format!(
+            "# {} report: {local_start} to {local_end}\n",
+            capitalize(spec.period.label())
+        );
+        for section in &spec.sections {
+            out.push('\n');
+            out.push_str(&match section {
+                ReportSection::Tasks => self.tasks_section(start, now).await?,
+                ReportSection::Expenses => self.expenses_section(local_start, local_end).await?,
+                ReportSection::Habits => self.habits_section(local_start, local_end)?,
+                ReportSection::Memory => self.memory_section(start).await?,
+                ReportSection::Usage => usage_section(&self.usage.since(start)),
+                ReportSection::Analytics => self.analytics_section(spec.period).await?,
+            });
+        }
+        let dir = self.workspace_dir.join("reports");
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}-{local_end}.md", spec.period.label()));
+        fs::write(&path, &out)?;
+        Ok((path, out))
+    }
+
+    async fn tasks_section(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<String> {
+        let range = start.timestamp_millis()..=end.timestamp_millis();
+        let mut ran: Vec<_> = self
+            .cron
+            .list_jobs()
+            .await?
+            .into_iter()
+            .filter(|job| {
+                job.state
+                    .last_run_at_ms
+                    .is_some_and(|at| range.contains(&at))
+            })
+            .collect();
+        ran.sort_by_key(|job| job.state.last_run_at_ms);
+        let mut out = format!("## Scheduled tasks\n\n{} job(s) ran.\n", ran.len());
+        for job in &ran {
+            let when = job
+                .state
+                .last_run_at_ms
+                .and_then(DateTime::<Utc>::from_timestamp_millis)
+                .map(|at| {
+                    at.with_timezone(&self.timezone)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "- {} ({}): last run {when}, {}\n",
+                job.name,
+                job.payload.kind,
+                job.state.last_status.as_deref().unwrap_or("unknown")
+            ));
+        }
+        Ok(out)
+    }
+
+    async fn expenses_section(&self, from: NaiveDate, to: NaiveDate) -> Result<String> {
+        let store = ExpenseStore::new(self.workspace_dir.join("expenses.db"))?;
+        let expenses = store.between(from, to).await?;
+        let mut by_category: BTreeMap<(String, String), (f64, usize)> = BTreeMap::new();
+        let mut by_currency: BTreeMap<String, f64> = BTreeMap::new();
+        for expense in &expenses {
+            let entry = by_category
+                .entry((expense.category.clone(), expense.currency.clone()))
+                .or_default();
+            entry.0 += expense.amount;
+            entry.1 += 1;
+            *by_currency.entry(expense.currency.clone()).or_default() += expense.amount;
+        }
+        let mut out = format!("## Expenses\n\n{} expense(s).\n", expenses.len());
+        for (currency, total) in &by_currency {
+            out.push_str(&format!("- Total: {total:.2} {currency}\n"));
+        }
+        for ((category, currency), (total, count)) in &by_category {
+            out.push_str(&format!("- {category}: {total:.2} {currency} ({count})\n"));
+        }
+        Ok(out)
+    }
+
+    fn habits_section(&self, from: NaiveDate, to: NaiveDate) -> Result<String> {
+        let mut store = HabitStore::new(self.workspace_dir.clone());
+        store.load()?;
+        let days = (to - from).num_days() + 1;
+        let mut out = format!("## Habits\n\n{} habit(s) tracked.\n", store.habits.len());
+        for habit in &store.habits {
+            let done = habit.check_ins.range(from..=to).count();
+            let stats = habit.stats(to);
+            out.push_str(&format!(
+                "- {}: {done}/{days} days, current streak {}\n",
+                habit.name, stats.current_streak
+            ));
+        }
+        Ok(out)
+    }
+
+    async fn analytics_section(&self, period: ReportPeriod) -> Result<String> {
+        let analytics = Analytics::open(&self.data_dir)?;
+        analytics
+            .refresh(&HistoryLog::new(&self.workspace_dir), self.timezone)
+            .await?;
+        let weeks = match period {
+            ReportPeriod::Month => 5,
+            _ => 2,
+        };
+        let recent = analytics.recent(weeks).await?;
+        Ok(format!(
+            "## Conversations\n\n{}",
+            analytics::render(&recent)
+        ))
+    }
+
+    async fn memory_section(&self, start: DateTime<Utc>) -> Result<String> {
+        let Some(store) = &self.vector_memory else {
+            return Ok("## Memory\n\nVector memory is disabled.\n".to_string());
+        };
+        let items = store.select(&MemorySelector::default()).await?;
+        let added: Vec<_> = items.iter().filter(|m| m.created_at >= start).collect();
+        let updated = items
+            .iter()
+            .filter(|m| m.created_at < start && m.updated_at >= start)
+            .count();
+        let mut out = format!(
+            "## Memory\n\n{} new, {updated} updated, {} total.\n",
+            added.len(),
+            items.len()
+        );
+        for item in added.iter().rev().take(MAX_LISTED_MEMORIES) {
+            out.push_str(&format!("- {}\n", preview(&item.content)));
+        }
+        if added.len() > MAX_LISTED_MEMORIES {
+            out.push_str(&format!(
+                "- …and {} more\n",
+                added.len() - MAX_LISTED_MEMORIES
+            ));
+        }
+        Ok(out)
+    }
+}
+
+fn usage_section(entries: &[UsageEntry]) -> String {
+    let (entries, failed): (Vec<&UsageEntry>, Vec<&UsageEntry>) =
+        entries.iter().partition(|e| e.error.is_none());
+    let mut by_model: BTreeMap<&str, (usize, u64, u64, Option<f64>)> = BTreeMap::new();
+    for entry in &entries {
+        let row = by_model.entry(&entry.model).or_default();
+        row.0 += 1;
+        row.1 += entry.input_tokens;
+        row.2 += entry.output_tokens;
+        if let Some(cost) = entry.cost_usd {
+            row.3 = Some(row.3.unwrap_or_default() + cost);
+        }
+    }
+    let tokens: u64 = entries
+        .iter()
+        .map(|e| e.input_tokens + e.output_tokens)
+        .sum();
+    let cost: f64 = entries.iter().filter_map(|e| e.cost_usd).sum();
+    let mut out = format!(
+        "## Usage\n\n{} turn(s), {tokens} tokens, ${cost:.4} (priced models only).\n",
+        entries.len()
+    );
+    for (model, (turns, input, output, cost)) in &by_model {
+        let cost = cost
+            .map(|c| format!(", ${c:.4}"))
+            .unwrap_or_else(|| ", price unknown".to_string());
+        out.push_str(&format!(
+            "- {model}: {turns} turn(s), {input} in / {output} out{cost}\n"
+        ));
+    }
+    let loops: usize = entries.iter().map(|e| e.tool_loops).sum();
+    if loops > 0 {
+        let calls: usize = entries.iter().map(|e| e.tool_calls).sum();
+        out.push_str(&format!(
+            "- {calls} tool call(s); {loops} tool loop(s) stopped\n"
+        ));
+    }
+    if !failed.is_empty() {
+        let mut by_code: BTreeMap<&str, usize> = BTreeMap::new();
+        for kind in failed.iter().filter_map(|e| e.error) {
+            *by_code.entry(kind.code()).or_default() += 1;
+        }
+        let codes: Vec<String> = by_code
+            .iter()
+            .map(|(code, count)| format!("{code} ×{count}"))
+            .collect();
+        out.push_str(&format!(
+            "- {} failed turn(s): {}\n",
+            failed.len(),
+            codes.join(", ")
+        ));
+    }
+    out
+}
+
+fn preview(text: &str) -> String {
+    let text = text.replace('\n', " ");
+    if text.chars().count() <= MEMORY_PREVIEW_CHARS {
+        return text;
+    }
+    let kept: String = text.chars().take(MEMORY_PREVIEW_CHARS).collect();
+    format!("{kept}…")
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{usage_section, ReportPeriod, ReportSection, ReportSpec, UsageEntry};
+    use crate::error::ErrorKind;
+    use chrono::Utc;
+
+    #[test]
+    fn parses_specs_and_totals_usage() {
+        let spec = ReportSpec::parse("daily: usage, expenses").unwrap();
+        assert_eq!(spec.period, ReportPeriod::Day);
+        assert_eq!(
+            spec.sections,
+            vec![ReportSection::Usage, ReportSection::Expenses]
+        );
+        assert_eq!(ReportSpec::parse("").unwrap().sections.len(), 5);
+        assert!(ReportSpec::parse("weekly gossip").is_err());
+        assert_eq!(
+            ReportSpec::parse("memory,expenses,memory")
+                .unwrap()
+                .sections,
+            vec![ReportSection::Memory, ReportSection::Expenses]
+        );
+
+        let entry = |model: &str, cost| UsageEntry {
+            ts: Utc::now(),
+            session: "cli:direct".to_string(),
+            provider: "openai".to_string(),
+            model: model.to_string(),
+            input_tokens: 1_000,
+            output_tokens: 200,
+            cost_usd: cost,
+            tool_calls: 2,
+            tool_loops: 0,
+            error: None,
+        };
+        let mut failed = entry("", None);
+        (failed.input_tokens, failed.output_tokens) = (0, 0);
+        failed.error = Some(ErrorKind::RateLimit);
+        let out = usage_section(&[
+            entry("gpt-4o", Some(0.01)),
+            entry("gpt-4o", Some(0.02)),
+            entry("local", None),
+            failed,
+        ]);
+        assert!(out.contains("3 turn(s), 3600 tokens, $0.0300"));
+        assert!(out.contains("- gpt-4o: 2 turn(s), 2000 in / 400 out, $0.0300"));
+        assert!(out.contains("- local: 1 turn(s), 1000 in / 200 out, price unknown"));
+        assert!(out.contains("- 1 failed turn(s): rate_limit ×1"));
+    }
+}
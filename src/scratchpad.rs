@@ -0,0 +1,172 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Serialized size limit per value.
+pub const MAX_VALUE_BYTES: usize = 4096;
+/// Keys per namespace.
+pub const MAX_KEYS: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub value: Value,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ScratchpadData {
+    version: i32,
+    #[serde(default)]
+    namespaces: BTreeMap<String, BTreeMap<String, Entry>>,
+}
+
+/// Small structured values for automations (counters, last-seen ids,
+/// thresholds), persisted to `<data_dir>/scratchpad.json`.
+pub struct Scratchpad {
+    path: PathBuf,
+    data: ScratchpadData,
+}
+
+impl Scratchpad {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join("scratchpad.json"),
+            data: ScratchpadData::default(),
+        }
+    }
+
+    pub fn load(&mut self) -> Result<()> {
+        self.data = if self.path.exists() {
+            serde_json::from_str(&fs::read_to_string(&self.path)?)?
+        } else {
+            ScratchpadData::default()
+        };
+        Ok(())
+    }
+
+    fn save(&mut self) -> Result<()> {
+        self.data.version = 1;
+        self.data.namespaces.retain(|_, keys| !keys.is_empty());
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&self.data)?)?;
+        fs::rename(tmp, &self.path)?;
+        Ok(())
+    }
+
+    pub fn get(&self, namespace: &str, key: &str) -> Option<&Entry> {
+        self.data.namespaces.get(namespace)?.get(key)
+    }
+
+    /// Store `value`, returning the previous entry.
+    pub fn set(&mut self, namespace: &str, key: &str, value: Value) -> Result<Option<Entry>> {
+        let size = value.to_string().len();
+        if size > MAX_VALUE_BYTES {
+            return Err(anyhow!(
+                "value is {size} bytes; the scratchpad holds at most {MAX_VALUE_BYTES} per key"
+            ));
+        }
+        let keys = self
+            .data
+            .namespaces
+            .entry(namespace.to_string())
+            .or_default();
+        if !keys.contains_key(key) && keys.len() >= MAX_KEYS {
+            return Err(anyhow!(
+                "namespace '{namespace}' already has {MAX_KEYS} keys; delete some first"
+            ));
+        }
+        let previous = keys.insert(
+            key.to_string(),
+            Entry {
+                value,
+                updated_at: Utc::now(),
+            },
+        );
+        self.save()?;
+        Ok(previous)
+    }
+
+    /// Add `by` to a numeric value (missing keys start at 0).
+    pub fn increment(&mut self, namespace: &str, key: &str, by: f64) -> Result<Value> {
+        let current = match self.get(namespace, key).map(|e| &e.value) {
+            None | Some(Value::Null) => 0.0,
+            Some(Value::Number(n)) => n.as_f64().unwrap_or(0.0),
+            Some(other) => return Err(anyhow!("'{key}' holds {other}, not a number")),
+        };
+        let next = current + by;
+        let value = if next.fract() == 0.0 && next.abs() < i64::MAX as f64 {
+            Value::from(next as i64)
+        } else {
+            Value::from(next)
+        };
+        self.set(namespace, key, value.clone())?;
+        Ok(value)
+    }
+
+    pub fn delete(&mut self, namespace: &str, key: &str) -> Result<bool> {
+        let removed = self
+            .data
+            .namespaces
+            .get_mut(namespace)
+            .and_then(|keys| keys.remove(key))
+            .is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Keys of `namespace` starting with `prefix`, in key order.
+    pub fn list(&self, namespace: &str, prefix: &str) -> Vec<(&String, &Entry)> {
+        self.data
+            .namespaces
+            .get(namespace)
+            .map(|keys| {
+                keys.iter()
+                    .filter(|(key, _)| key.starts_with(prefix))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn namespaces(&self) -> Vec<(&String, usize)> {
+        self.data
+            .namespaces
+            .iter()
+            .map(|(name, keys)| (name, keys.len()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scratchpad;
+    use serde_json::json;
+
+    #[test]
+    fn persists_values_and_counters() {
+        let dir = std::env::temp_dir().join(format!("femtobot-scratchpad-{}", std::process::id()));
+        let mut pad = Scratchpad::new(&dir);
+        assert!(pad.set("prices", "btc", json!(64000)).unwrap().is_none());
+        let previous = pad.set("prices", "btc", json!(65000)).unwrap().unwrap();
+        assert_eq!(previous.value, json!(64000));
+        assert_eq!(pad.increment("stats", "alerts", 1.0).unwrap(), json!(1));
+        assert_eq!(pad.increment("stats", "alerts", 2.0).unwrap(), json!(3));
+
+        let mut reloaded = Scratchpad::new(&dir);
+        reloaded.load().unwrap();
+        assert_eq!(reloaded.get("prices", "btc").unwrap().value, json!(65000));
+        assert!(reloaded.delete("stats", "alerts").unwrap());
+        assert!(reloaded.list("stats", "").is_empty());
+        assert_eq!(reloaded.namespaces().len(), 1);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
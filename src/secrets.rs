@@ -0,0 +1,260 @@
+//! Secret values in config.json (API keys, channel tokens and passwords,
+//! the `secrets` section) can be stored encrypted as `enc:<base64>`: an age
+//! file encrypted with a master passphrase. `femtobot secrets encrypt`
+//! rewrites the config that way; on load the passphrase comes from
+//! `FEMTOBOT_MASTER_PASSPHRASE`, the key file at `FEMTOBOT_MASTER_KEY_FILE`
+//! (default `~/.femtobot/master.key`), or a prompt when stdin is a terminal.
+
+use age::secrecy::SecretString;
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use serde_json::Value;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+
+const PREFIX: &str = "enc:";
+/// scrypt work factor (log2 N) for new values. Lower than age's default
+/// (about a second per value) so a config with a dozen secrets still starts
+/// quickly; a long passphrase matters more than this.
+const WORK_FACTOR: u8 = 15;
+/// Object keys whose string values are secret wherever they appear.
+const SECRET_KEYS: &[&str] = &[
+    "apiKey",
+    "api_key",
+    "token",
+    "access_token",
+    "password",
+    "passphrase",
+    "secret_key",
+];
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+pub fn encrypt_value(plain: &str, passphrase: &SecretString) -> Result<String> {
+    let mut recipient = age::scrypt::Recipient::new(passphrase.clone());
+    recipient.set_work_factor(WORK_FACTOR);
+    let bytes = age::encrypt(&recipient, plain.as_bytes())?;
+    Ok(format!(
+        "{PREFIX}{}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+pub fn decrypt_value(value: &str, passphrase: &SecretString) -> Result<String> {
+    let encoded = value
+        .strip_prefix(PREFIX)
+        .ok_or_else(|| anyhow!("value is not encrypted"))?;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .context("encrypted value is not valid base64")?;
+    let identity = age::scrypt::Identity::new(passphrase.clone());
+    let plain = age::decrypt(&identity, &bytes)
+        .map_err(|err| anyhow!("could not decrypt a config secret (wrong passphrase?): {err}"))?;
+    Ok(String::from_utf8(plain)?)
+}
+
+/// Decrypt every `enc:` string in `value` in place. The passphrase is only
+/// looked up when there is something to decrypt.
+pub fn decrypt_tree(value: &mut Value) -> Result<usize> {
+    if !has_encrypted(value) {
+        return Ok(0);
+    }
+    decrypt_tree_with(value, &master_passphrase(false)?)
+}
+
+/// Encrypt the plain secret values in `value` in place: strings under
+/// [`SECRET_KEYS`] anywhere and every string in the top-level `secrets`.
+pub fn encrypt_tree(value: &mut Value, passphrase: &SecretString) -> Result<usize> {
+    let mut count = 0;
+    let mut encrypt = |text: &mut String| -> Result<()> {
+        if !text.is_empty() && !is_encrypted(text) {
+            *text = encrypt_value(text, passphrase)?;
+            count += 1;
+        }
+        Ok(())
+    };
+    if let Some(secrets) = value.get_mut("secrets") {
+        visit_strings(secrets, &mut encrypt)?;
+    }
+    visit_secret_keys(value, &mut encrypt)?;
+    Ok(count)
+}
+
+/// `femtobot secrets encrypt|decrypt`: rewrite the config file with its
+/// secrets encrypted or back in plain text. Returns the file and how many
+/// values changed.
+pub fn rewrite_config_file(encrypt: bool) -> Result<(PathBuf, usize)> {
+    let path = crate::config::config_path();
+    let mut root = crate::configure::load_config_value(&path)?;
+    let count = if encrypt {
+        let passphrase = master_passphrase(true)?;
+        // Values encrypted earlier must use the same passphrase.
+        let mut check = root.clone();
+        decrypt_tree_with(&mut check, &passphrase)?;
+        encrypt_tree(&mut root, &passphrase)?
+    } else {
+        decrypt_tree(&mut root)?
+    };
+    if count > 0 {
+        crate::configure::save_config_value(&path, &root)?;
+    }
+    Ok((path, count))
+}
+
+fn decrypt_tree_with(value: &mut Value, passphrase: &SecretString) -> Result<usize> {
+    let mut count = 0;
+    visit_strings(value, &mut |text| {
+        if is_encrypted(text) {
+            *text = decrypt_value(text, passphrase)?;
+            count += 1;
+        }
+        Ok(())
+    })?;
+    Ok(count)
+}
+
+/// The master passphrase from the environment, the key file or a prompt.
+/// `confirm` asks twice when prompting, for setting a new passphrase.
+fn master_passphrase(confirm: bool) -> Result<SecretString> {
+    if let Ok(passphrase) = std::env::var("FEMTOBOT_MASTER_PASSPHRASE") {
+        if !passphrase.is_empty() {
+            return Ok(SecretString::from(passphrase));
+        }
+    }
+    let explicit = std::env::var("FEMTOBOT_MASTER_KEY_FILE")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+        .map(PathBuf::from);
+    let key_file = explicit.clone().or_else(default_key_file);
+    if let Some(path) = key_file {
+        match std::fs::read_to_string(&path) {
+            Ok(content) if !content.trim().is_empty() => {
+                return Ok(SecretString::from(content.trim().to_string()));
+            }
+            Ok(_) => return Err(anyhow!("master key file {} is empty", path.display())),
+            Err(err) if explicit.is_some() => {
+                return Err(anyhow!(
+                    "could not read master key file {}: {err}",
+                    path.display()
+                ));
+            }
+            Err(_) => {}
+        }
+    }
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "config.json has encrypted secrets; set FEMTOBOT_MASTER_PASSPHRASE or FEMTOBOT_MASTER_KEY_FILE"
+        ));
+    }
+    let passphrase = prompt_hidden("Master passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(anyhow!("empty master passphrase"));
+    }
+    if confirm && prompt_hidden("Repeat passphrase: ")? != passphrase {
+        return Err(anyhow!("passphrases do not match"));
+    }
+    Ok(SecretString::from(passphrase))
+}
+
+fn default_key_file() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".femtobot").join("master.key"))
+}
+
+/// Read a line from the terminal without echoing it (where `stty` exists).
+fn prompt_hidden(label: &str) -> Result<String> {
+    eprint!("{label}");
+    io::stderr().flush()?;
+    let hidden = set_echo(false);
+    let mut line = String::new();
+    let read = io::stdin().read_line(&mut line);
+    if hidden {
+        set_echo(true);
+        eprintln!();
+    }
+    read?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn set_echo(on: bool) -> bool {
+    std::process::Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(std::process::Stdio::inherit())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn has_encrypted(value: &Value) -> bool {
+    match value {
+        Value::String(text) => is_encrypted(text),
+        Value::Array(items) => items.iter().any(has_encrypted),
+        Value::Object(map) => map.values().any(has_encrypted),
+        _ => false,
+    }
+}
+
+fn visit_strings(
+    value: &mut Value,
+    visit: &mut impl FnMut(&mut String) -> Result<()>,
+) -> Result<()> {
+    match value {
+        Value::String(text) => visit(text),
+        Value::Array(items) => items
+            .iter_mut()
+            .try_for_each(|item| visit_strings(item, visit)),
+        Value::Object(map) => map
+            .values_mut()
+            .try_for_each(|item| visit_strings(item, visit)),
+        _ => Ok(()),
+    }
+}
+
+fn visit_secret_keys(
+    value: &mut Value,
+    visit: &mut impl FnMut(&mut String) -> Result<()>,
+) -> Result<()> {
+    match value {
+        Value::Array(items) => items
+            .iter_mut()
+            .try_for_each(|item| visit_secret_keys(item, visit)),
+        Value::Object(map) => map.iter_mut().try_for_each(|(key, item)| match item {
+            Value::String(text) if SECRET_KEYS.contains(&key.as_str()) => visit(text),
+            _ => visit_secret_keys(item, visit),
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt_tree_with, encrypt_tree};
+    use age::secrecy::SecretString;
+    use serde_json::json;
+
+    #[test]
+    fn encrypts_secret_fields_and_round_trips() {
+        let passphrase = SecretString::from("correct horse battery".to_string());
+        let mut config = json!({
+            "providers": {"openrouter": {"apiKey": "sk-or-123", "apiBase": "https://x.test"}},
+            "channels": {"telegram": {"token": "123:abc", "allow_from": ["42"]}},
+            "secrets": {"weather": "w-key"}
+        });
+        let original = config.clone();
+        assert_eq!(encrypt_tree(&mut config, &passphrase).unwrap(), 3);
+        let key = config["providers"]["openrouter"]["apiKey"]
+            .as_str()
+            .unwrap();
+        assert!(key.starts_with("enc:"));
+        assert_eq!(
+            config["providers"]["openrouter"]["apiBase"],
+            "https://x.test"
+        );
+        assert_eq!(config["channels"]["telegram"]["allow_from"], json!(["42"]));
+        // Already encrypted values are left alone.
+        assert_eq!(encrypt_tree(&mut config, &passphrase).unwrap(), 0);
+
+        decrypt_tree_with(&mut config, &passphrase).unwrap();
+        assert_eq!(config, original);
+    }
+}
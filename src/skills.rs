@@ -0,0 +1,276 @@
+//! Skills: reusable procedures taught in plain markdown instead of code.
+//! Each skill lives in `<workspace>/skills/<name>.md` (or
+//! `skills/<name>/SKILL.md`) with a small frontmatter header:
+//!
+//! ```text
+//! ---
+//! name: file-invoices
+//! description: File incoming invoices into the finance folder
+//! tools: read_file, write_file
+//! triggers: invoice, bill
+//! ---
+//! Step-by-step instructions...
+//!
+//! ## Examples
+//! User: file this invoice ...
+//! ```
+//!
+//! Skills whose description is semantically close to the request, or whose
+//! trigger words appear in it, are added to that turn's prompt.
+
+use crate::memory::vector_store::{cosine_similarity, EmbeddingService};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Skills added to a single prompt at most.
+const MAX_SKILLS_PER_TURN: usize = 2;
+/// Instructions longer than this are cut when injected.
+const MAX_SKILL_CHARS: usize = 4_000;
+const SKILL_FILE: &str = "SKILL.md";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skill {
+    pub name: String,
+    pub description: String,
+    /// Tools the procedure relies on.
+    pub tools: Vec<String>,
+    /// Words or phrases that select the skill without an embedding match.
+    pub triggers: Vec<String>,
+    pub instructions: String,
+    pub examples: String,
+    pub path: PathBuf,
+}
+
+impl Skill {
+    /// Parse a skill file. The name falls back to the file (or directory) name.
+    pub fn parse(path: &Path, text: &str) -> Option<Self> {
+        let fallback = if path.file_name().is_some_and(|n| n == SKILL_FILE) {
+            path.parent().and_then(Path::file_name)
+        } else {
+            path.file_stem()
+        };
+        let mut skill = Skill {
+            name: fallback?.to_string_lossy().into_owned(),
+            description: String::new(),
+            tools: Vec::new(),
+            triggers: Vec::new(),
+            instructions: String::new(),
+            examples: String::new(),
+            path: path.to_path_buf(),
+        };
+        let text = text.replace("\r\n", "\n");
+        let mut body = text.as_str();
+        if let Some(rest) = body.strip_prefix("---\n") {
+            let end = rest.find("\n---")?;
+            for line in rest[..end].lines() {
+                let Some((key, value)) = line.split_once(':') else {
+                    continue;
+                };
+                let value = value.trim();
+                match key.trim() {
+                    "name" if !value.is_empty() => skill.name = value.to_string(),
+                    "description" => skill.description = value.to_string(),
+                    "tools" => skill.tools = parse_list(value),
+                    "triggers" => skill.triggers = parse_list(value),
+                    _ => {}
+                }
+            }
+            body = rest[end + 4..].trim_start_matches(['-', '\n']);
+        }
+        let (instructions, examples) = match body.find("\n## Examples") {
+            Some(idx) => (&body[..idx], &body[idx + "\n## Examples".len()..]),
+            None => (body, ""),
+        };
+        skill.instructions = instructions.trim().to_string();
+        skill.examples = examples.trim().to_string();
+        if skill.description.is_empty() {
+            skill.description = skill
+                .instructions
+                .lines()
+                .find(|l| !l.trim().is_empty())
+                .unwrap_or_default()
+                .trim_start_matches('#')
+                .trim()
+                .to_string();
+        }
+        if skill.instructions.is_empty() {
+            return None;
+        }
+        Some(skill)
+    }
+
+    fn triggered_by(&self, request: &str) -> bool {
+        let request = request.to_lowercase();
+        self.triggers
+            .iter()
+            .any(|t| request.contains(&t.to_lowercase()))
+    }
+
+    /// Prompt section carrying the skill into a turn.
+    pub fn render(&self) -> String {
+        let instructions = crate::memory::file_store::truncate(&self.instructions, MAX_SKILL_CHARS);
+        let mut out = format!(
+            "[Skill: {}]\n{}\nFollow this procedure for the request:\n{instructions}",
+            self.name, self.description
+        );
+        if !self.tools.is_empty() {
+            out.push_str(&format!("\nTools used: {}", self.tools.join(", ")));
+        }
+        if !self.examples.is_empty() {
+            out.push_str(&format!("\nExamples:\n{}", self.examples));
+        }
+        out
+    }
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .trim_matches(['[', ']'])
+        .split(',')
+        .map(|s| s.trim().trim_matches(['"', '\'']).to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// The workspace's skills, selected per request.
+#[derive(Clone)]
+pub struct SkillLibrary {
+    dir: PathBuf,
+    embedder: Option<EmbeddingService>,
+    threshold: f32,
+}
+
+impl SkillLibrary {
+    pub fn new(workspace_dir: &Path, embedder: Option<EmbeddingService>, threshold: f32) -> Self {
+        Self {
+            dir: workspace_dir.join("skills"),
+            embedder,
+            threshold,
+        }
+    }
+
+    /// Every readable skill, sorted by name. Files are re-read on each call
+    /// so edits apply without a restart.
+    pub fn load(&self) -> Vec<Skill> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut skills: Vec<Skill> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter_map(|path| {
+                if path.is_dir() {
+                    Some(path.join(SKILL_FILE)).filter(|p| p.is_file())
+                } else {
+                    Some(path).filter(|p| p.extension().is_some_and(|ext| ext == "md"))
+                }
+            })
+            .filter_map(|path| {
+                let text = fs::read_to_string(&path).ok()?;
+                let skill = Skill::parse(&path, &text);
+                if skill.is_none() {
+                    warn!("ignoring skill without instructions: {}", path.display());
+                }
+                skill
+            })
+            .collect();
+        skills.sort_by(|a, b| a.name.cmp(&b.name));
+        skills
+    }
+
+    /// Skills relevant to `request`, best match first: trigger hits, then
+    /// descriptions whose embedding is close enough to the request.
+    pub async fn select(&self, request: &str) -> Vec<Skill> {
+        let skills = self.load();
+        if skills.is_empty() || request.trim().is_empty() {
+            return Vec::new();
+        }
+        let query = match &self.embedder {
+            Some(embedder) => match embedder.embed(request).await {
+                Ok(embedding) => Some(embedding),
+                Err(err) => {
+                    warn!("skill matching without embeddings: {err}");
+                    None
+                }
+            },
+            None => None,
+        };
+        let mut scored = Vec::new();
+        for skill in skills {
+            let mut score = if skill.triggered_by(request) {
+                1.0
+            } else {
+                0.0
+            };
+            if let (Some(embedder), Some(query)) = (&self.embedder, &query) {
+                let text = format!("{}: {}", skill.name, skill.description);
+                if let Ok(embedding) = embedder.embed(&text).await {
+                    let similarity = cosine_similarity(query, &embedding);
+                    if similarity >= self.threshold {
+                        score = f32::max(score, similarity);
+                    }
+                }
+            }
+            if score > 0.0 {
+                scored.push((score, skill));
+            }
+        }
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored
+            .into_iter()
+            .take(MAX_SKILLS_PER_TURN)
+            .map(|(_, skill)| skill)
+            .collect()
+    }
+
+    /// Listing for the `/skills` command.
+    pub fn describe(&self) -> String {
+        let skills = self.load();
+        if skills.is_empty() {
+            return format!(
+                "No skills yet. Add markdown files to {} to teach me procedures.",
+                self.dir.display()
+            );
+        }
+        let mut out = format!("Skills ({}):", skills.len());
+        for skill in skills {
+            out.push_str(&format!("\n- {}: {}", skill.name, skill.description));
+            if !skill.tools.is_empty() {
+                out.push_str(&format!(" (tools: {})", skill.tools.join(", ")));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Skill, SkillLibrary};
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn parses_frontmatter_and_selects_by_trigger() {
+        let text = "---\nname: file-invoices\ndescription: File incoming invoices\ntools: [read_file, write_file]\ntriggers: invoice, bill\n---\n1. Save the PDF under finance/.\n\n## Examples\nUser: file this invoice\n";
+        let skill = Skill::parse(Path::new("skills/invoices.md"), text).unwrap();
+        assert_eq!(skill.name, "file-invoices");
+        assert_eq!(skill.tools, vec!["read_file", "write_file"]);
+        assert_eq!(skill.instructions, "1. Save the PDF under finance/.");
+        assert_eq!(skill.examples, "User: file this invoice");
+
+        let dir = std::env::temp_dir().join(format!("femtobot-skills-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("skills/weekly-review")).unwrap();
+        std::fs::write(dir.join("skills/invoices.md"), text).unwrap();
+        std::fs::write(
+            dir.join("skills/weekly-review/SKILL.md"),
+            "# Weekly review\nSummarize the week.",
+        )
+        .unwrap();
+        let library = SkillLibrary::new(&dir, None, 0.5);
+        assert_eq!(library.load().len(), 2);
+        let selected = library.select("Please file this Invoice").await;
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].name, "file-invoices");
+        assert!(library.select("what's the weather").await.is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
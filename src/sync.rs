@@ -0,0 +1,530 @@
+use crate::config::AppConfig;
+use crate::remote::{RemoteObject, RemoteStore};
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Remote directory (below the configured prefix) mirroring the workspace.
+const REMOTE_DIR: &str = "workspace";
+/// SQLite files change constantly and cannot be merged; backups cover them.
+const SKIPPED_SUFFIXES: &[&str] = &[".db", "-wal", "-shm", "-journal", ".partial"];
+
+/// What both sides looked like after the last successful sync of a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncedFile {
+    hash: String,
+    remote: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    version: i32,
+    files: HashMap<String, SyncedFile>,
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub uploaded: usize,
+    pub downloaded: usize,
+    pub deleted_local: usize,
+    pub deleted_remote: usize,
+    /// Local paths where the remote copy of a conflicting edit was saved.
+    pub conflicts: Vec<String>,
+}
+
+impl SyncReport {
+    pub fn summary(&self) -> String {
+        let mut out = format!(
+            "uploaded {}, downloaded {}, deleted {} local / {} remote",
+            self.uploaded, self.downloaded, self.deleted_local, self.deleted_remote
+        );
+        if !self.conflicts.is_empty() {
+            out.push_str(&format!(
+                ", {} conflict(s) kept as {}",
+                self.conflicts.len(),
+                self.conflicts.join(", ")
+            ));
+        }
+        out
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    Upload,
+    Download,
+    DeleteLocal,
+    DeleteRemote,
+    Resolve,
+}
+
+/// What to do with a path given its last synced state and the local hash
+/// and remote fingerprint seen now; `None` when nothing changed.
+fn plan(
+    base: Option<&SyncedFile>,
+    local: Option<&String>,
+    remote: Option<&String>,
+) -> Option<Action> {
+    let local_changed = local != base.map(|b| &b.hash);
+    let remote_changed = remote != base.map(|b| &b.remote);
+    let action = match (local.is_some(), remote.is_some()) {
+        (true, true) => match (local_changed, remote_changed) {
+            (false, false) => return None,
+            (true, false) => Action::Upload,
+            (false, true) => Action::Download,
+            (true, true) => Action::Resolve,
+        },
+        (true, false) if base.is_some() && !local_changed => Action::DeleteLocal,
+        (true, false) => Action::Upload,
+        (false, true) if base.is_some() && !remote_changed => Action::DeleteRemote,
+        (false, true) => Action::Download,
+        (false, false) => return None,
+    };
+    Some(action)
+}
+
+/// A path's state once its action succeeded.
+#[derive(Debug)]
+enum Settled {
+    /// The same content, with hash `hash`, is on both sides. `remote` is
+    /// `None` when the store did not report the fingerprint of an upload.
+    Synced {
+        hash: String,
+        remote: Option<String>,
+    },
+    /// Gone from both sides.
+    Removed,
+}
+
+/// Two-way sync of the workspace with `storage.remote`. Changes are
+/// detected against the state recorded after the previous sync: a file
+/// changed on one side is copied to the other, deletions propagate, and when
+/// both sides changed the local copy wins while the remote version is kept
+/// next to it as `<name>.conflict-<timestamp>`.
+///
+/// A path's recorded state only moves once its own action succeeded, and
+/// records what was actually transferred, so failures are retried and edits
+/// made while the sync runs are picked up by the next one.
+pub async fn sync_once(cfg: &AppConfig) -> Result<SyncReport> {
+    let remote = cfg
+        .remote
+        .clone()
+        .map(RemoteStore::new)
+        .ok_or_else(|| anyhow!("storage.remote is not configured"))?;
+    let state_path = cfg.data_dir.join("sync_state.json");
+    let state = load_state(&state_path);
+
+    let local = scan_local(&cfg.workspace_dir, &cfg.sync_exclude)?;
+    let remote_files = list_remote(&remote, &cfg.sync_exclude).await?;
+
+    let paths: BTreeSet<&String> = local
+        .keys()
+        .chain(remote_files.keys())
+        .chain(state.files.keys())
+        .collect();
+    let mut report = SyncReport::default();
+    let mut outcomes = Vec::new();
+    for path in paths {
+        let local_hash = local.get(path);
+        let remote_fp = remote_files.get(path).map(RemoteObject::fingerprint);
+        let outcome = match plan(state.files.get(path), local_hash, remote_fp.as_ref()) {
+            Some(action) => {
+                let seen = Seen {
+                    local: local_hash,
+                    remote: remote_fp.as_ref(),
+                };
+                let outcome = apply(cfg, &remote, path, action, seen, &mut report).await;
+                if let Err(err) = &outcome {
+                    warn!("sync of {path} failed: {err:#}");
+                }
+                outcome
+            }
+            None if local_hash.is_none() && remote_fp.is_none() => Ok(Settled::Removed),
+            None => continue,
+        };
+        outcomes.push((path.clone(), outcome));
+    }
+
+    // Uploads whose fingerprint the store did not report are looked up once.
+    let unreported = outcomes
+        .iter()
+        .any(|(_, outcome)| matches!(outcome, Ok(Settled::Synced { remote: None, .. })));
+    let listing = match unreported {
+        true => match list_remote(&remote, &cfg.sync_exclude).await {
+            Ok(listing) => Some(listing),
+            Err(err) => {
+                warn!("cannot list remote after sync: {err:#}");
+                None
+            }
+        },
+        false => None,
+    };
+    let files = next_state(state.files, outcomes, listing.as_ref());
+    save_state(&state_path, &SyncState { version: 1, files })?;
+    Ok(report)
+}
+
+/// The state to record after a run: paths whose action succeeded move to
+/// what was transferred, failed ones keep their old base.
+fn next_state(
+    mut files: HashMap<String, SyncedFile>,
+    outcomes: Vec<(String, Result<Settled>)>,
+    listing: Option<&HashMap<String, RemoteObject>>,
+) -> HashMap<String, SyncedFile> {
+    for (path, outcome) in outcomes {
+        match outcome {
+            Ok(Settled::Synced { hash, remote }) => {
+                let remote = remote.or_else(|| {
+                    listing
+                        .and_then(|listing| listing.get(&path))
+                        .map(RemoteObject::fingerprint)
+                });
+                if let Some(remote) = remote {
+                    files.insert(path, SyncedFile { hash, remote });
+                }
+            }
+            Ok(Settled::Removed) => {
+                files.remove(&path);
+            }
+            Err(_) => {}
+        }
+    }
+    files
+}
+
+/// A path as the sync saw it before acting.
+struct Seen<'a> {
+    local: Option<&'a String>,
+    remote: Option<&'a String>,
+}
+
+async fn apply(
+    cfg: &AppConfig,
+    remote: &RemoteStore,
+    path: &str,
+    action: Action,
+    seen: Seen<'_>,
+    report: &mut SyncReport,
+) -> Result<Settled> {
+    let file = local_path(&cfg.workspace_dir, path)?;
+    let key = format!("{REMOTE_DIR}/{path}");
+    let remote_fp = || {
+        seen.remote
+            .cloned()
+            .ok_or_else(|| anyhow!("remote file disappeared"))
+    };
+    let settled = match action {
+        Action::Upload => {
+            let bytes = fs::read(&file)?;
+            let hash = hash(&bytes);
+            let remote = remote.put(&key, bytes).await?;
+            report.uploaded += 1;
+            Settled::Synced { hash, remote }
+        }
+        Action::Download => {
+            let bytes = remote
+                .get(&key)
+                .await?
+                .ok_or_else(|| anyhow!("remote file disappeared"))?;
+            let hash = hash(&bytes);
+            replace_local(&file, seen.local, Some(&bytes))?;
+            report.downloaded += 1;
+            Settled::Synced {
+                hash,
+                remote: Some(remote_fp()?),
+            }
+        }
+        Action::DeleteLocal => {
+            replace_local(&file, seen.local, None)?;
+            report.deleted_local += 1;
+            Settled::Removed
+        }
+        Action::DeleteRemote => {
+            remote.delete(&key).await?;
+            report.deleted_remote += 1;
+            Settled::Removed
+        }
+        Action::Resolve => {
+            let bytes = remote
+                .get(&key)
+                .await?
+                .ok_or_else(|| anyhow!("remote file disappeared"))?;
+            let remote_hash = hash(&bytes);
+            // Both sides may have made the same edit.
+            if Some(&remote_hash) == seen.local {
+                Settled::Synced {
+                    hash: remote_hash,
+                    remote: Some(remote_fp()?),
+                }
+            } else {
+                let conflict = conflict_path(path);
+                fs::write(local_path(&cfg.workspace_dir, &conflict)?, bytes)?;
+                report.conflicts.push(conflict);
+                let local = fs::read(&file)?;
+                let hash = hash(&local);
+                let remote = remote.put(&key, local).await?;
+                report.uploaded += 1;
+                Settled::Synced { hash, remote }
+            }
+        }
+    };
+    Ok(settled)
+}
+
+/// Overwrite `file` with `bytes`, or remove it for `None`, unless it changed
+/// since the scan saw `expected`; such an edit is left for the next sync.
+fn replace_local(file: &Path, expected: Option<&String>, bytes: Option<&[u8]>) -> Result<()> {
+    let current = match fs::read(file) {
+        Ok(content) => Some(hash(&content)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err.into()),
+    };
+    if current.as_ref() != expected {
+        return Err(anyhow!("local file changed during the sync"));
+    }
+    match bytes {
+        Some(bytes) => {
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(file, bytes)?;
+        }
+        None => fs::remove_file(file)?,
+    }
+    Ok(())
+}
+
+/// Sync every `sync_interval_secs` in the background.
+pub fn start(cfg: AppConfig) {
+    tokio::spawn(async move {
+        let interval = Duration::from_secs(cfg.sync_interval_secs.max(30));
+        loop {
+            match sync_once(&cfg).await {
+                Ok(report) => info!("workspace sync: {}", report.summary()),
+                Err(err) => warn!("workspace sync failed: {err:#}"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+/// Workspace-relative `/`-separated paths mapped to content hashes.
+fn scan_local(workspace: &Path, exclude: &[String]) -> Result<HashMap<String, String>> {
+    let mut files = HashMap::new();
+    let mut pending = vec![workspace.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let Ok(rel) = path.strip_prefix(workspace) else {
+                continue;
+            };
+            let rel = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if is_skipped(&rel, exclude) {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file() {
+                files.insert(rel, hash(&fs::read(&path)?));
+            }
+        }
+    }
+    Ok(files)
+}
+
+async fn list_remote(
+    remote: &RemoteStore,
+    exclude: &[String],
+) -> Result<HashMap<String, RemoteObject>> {
+    let prefix = format!("{REMOTE_DIR}/");
+    Ok(remote
+        .list(REMOTE_DIR)
+        .await?
+        .into_iter()
+        .filter_map(|object| {
+            let rel = object.key.strip_prefix(&prefix)?.to_string();
+            (!rel.is_empty() && !is_skipped(&rel, exclude)).then_some((rel, object))
+        })
+        .collect())
+}
+
+fn is_skipped(rel: &str, exclude: &[String]) -> bool {
+    let excluded = exclude.iter().any(|prefix| {
+        let prefix = prefix.trim_matches('/');
+        rel == prefix || rel.starts_with(&format!("{prefix}/"))
+    });
+    excluded || SKIPPED_SUFFIXES.iter().any(|suffix| rel.ends_with(suffix))
+}
+
+/// Resolve a remote-supplied relative path inside the workspace.
+fn local_path(workspace: &Path, rel: &str) -> Result<PathBuf> {
+    let rel = Path::new(rel);
+    if rel.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(anyhow!("refusing unsafe sync path {}", rel.display()));
+    }
+    Ok(workspace.join(rel))
+}
+
+fn conflict_path(path: &str) -> String {
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    match path.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.contains('/') => {
+            format!("{stem}.conflict-{stamp}.{ext}")
+        }
+        _ => format!("{path}.conflict-{stamp}"),
+    }
+}
+
+fn hash(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn load_state(path: &Path) -> SyncState {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(path: &Path, state: &SyncState) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(state)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        conflict_path, hash, is_skipped, next_state, plan, replace_local, Action, Settled,
+        SyncedFile,
+    };
+    use anyhow::anyhow;
+    use std::collections::HashMap;
+
+    fn synced(hash: &str, remote: &str) -> SyncedFile {
+        SyncedFile {
+            hash: hash.to_string(),
+            remote: remote.to_string(),
+        }
+    }
+
+    #[test]
+    fn action_table() {
+        let base = synced("h1", "r1");
+        let (h1, h2, r1, r2) = (
+            "h1".to_string(),
+            "h2".to_string(),
+            "r1".to_string(),
+            "r2".to_string(),
+        );
+        let cases = [
+            // Unchanged on both sides.
+            (Some(&base), Some(&h1), Some(&r1), None),
+            (Some(&base), Some(&h2), Some(&r1), Some(Action::Upload)),
+            (None, Some(&h1), None, Some(Action::Upload)),
+            (Some(&base), Some(&h1), Some(&r2), Some(Action::Download)),
+            (None, None, Some(&r1), Some(Action::Download)),
+            (Some(&base), Some(&h1), None, Some(Action::DeleteLocal)),
+            (Some(&base), None, Some(&r1), Some(Action::DeleteRemote)),
+            (Some(&base), Some(&h2), Some(&r2), Some(Action::Resolve)),
+            (None, Some(&h1), Some(&r1), Some(Action::Resolve)),
+            // Deleted on one side after an edit on the other: the edit wins.
+            (Some(&base), Some(&h2), None, Some(Action::Upload)),
+            (Some(&base), None, Some(&r2), Some(Action::Download)),
+            (Some(&base), None, None, None),
+        ];
+        for (base, local, remote, expected) in cases {
+            assert_eq!(
+                plan(base, local, remote),
+                expected,
+                "base={base:?} local={local:?} remote={remote:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn failed_actions_keep_their_old_base() {
+        let files = HashMap::from([
+            ("failed.md".to_string(), synced("h1", "r1")),
+            ("uploaded.md".to_string(), synced("h1", "r1")),
+            ("deleted.md".to_string(), synced("h1", "r1")),
+        ]);
+        let outcomes = vec![
+            ("failed.md".to_string(), Err(anyhow!("HTTP 500"))),
+            (
+                "uploaded.md".to_string(),
+                Ok(Settled::Synced {
+                    hash: "h2".to_string(),
+                    remote: Some("r2".to_string()),
+                }),
+            ),
+            ("deleted.md".to_string(), Ok(Settled::Removed)),
+            // No fingerprint reported and none listed: retried next time.
+            (
+                "unlisted.md".to_string(),
+                Ok(Settled::Synced {
+                    hash: "h3".to_string(),
+                    remote: None,
+                }),
+            ),
+        ];
+        let files = next_state(files, outcomes, Some(&HashMap::new()));
+        assert_eq!(files["failed.md"].hash, "h1");
+        assert_eq!(files["failed.md"].remote, "r1");
+        assert_eq!(files["uploaded.md"].hash, "h2");
+        assert_eq!(files["uploaded.md"].remote, "r2");
+        assert!(!files.contains_key("deleted.md"));
+        assert!(!files.contains_key("unlisted.md"));
+    }
+
+    #[test]
+    fn local_edits_during_a_sync_are_not_overwritten() {
+        let dir = std::env::temp_dir().join(format!("femtobot-sync-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes.md");
+        std::fs::write(&file, "scanned").unwrap();
+        let scanned = hash(b"scanned");
+
+        std::fs::write(&file, "edited meanwhile").unwrap();
+        assert!(replace_local(&file, Some(&scanned), Some(b"remote")).is_err());
+        assert!(replace_local(&file, Some(&scanned), None).is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "edited meanwhile");
+
+        let edited = hash(b"edited meanwhile");
+        replace_local(&file, Some(&edited), Some(b"remote")).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "remote");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn skips_excluded_dirs_and_databases() {
+        let exclude = vec!["artifacts".to_string()];
+        assert!(is_skipped("artifacts/x.txt", &exclude));
+        assert!(is_skipped("memory/vectors.db", &exclude));
+        assert!(!is_skipped("memory/MEMORY.md", &exclude));
+        assert!(!is_skipped("artifacts-old/x.txt", &exclude));
+    }
+
+    #[test]
+    fn conflict_copy_keeps_extension() {
+        let path = conflict_path("memory/MEMORY.md");
+        assert!(path.starts_with("memory/MEMORY.conflict-"));
+        assert!(path.ends_with(".md"));
+    }
+}
@@ -1,49 +1,301 @@
-use crate::bus::{InboundMessage, MessageBus};
-use crate::config::AppConfig;
+use crate::agent::commands;
+use crate::bus::{
+    Attachment, InboundMessage, MessageBus, MessageMeta, OutboundMessage, PartialOutput, Priority,
+    Quoted, Reaction,
+};
+use crate::channel::{self, Channel};
+use crate::config::{AppConfig, NetworkPolicy};
+use crate::configure;
+use crate::history::sanitize_file_stem;
+use crate::outbox::Outbox;
+use crate::quick::QuickAnswerer;
+use crate::ratelimit::RateLimiter;
+use crate::render;
+use crate::tools::sandbox::ChatSandbox;
+use crate::tools::summarize::pdf_to_text;
 use crate::transcription::Transcriber;
 use anyhow::{anyhow, Result};
-use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use teloxide::dispatching::UpdateHandler;
 use teloxide::net::Download;
 use teloxide::prelude::*;
-use teloxide::types::{ChatAction, FileId, ParseMode};
+use teloxide::types::{
+    BotCommand, CallbackQuery, ChatAction, FileId, InlineKeyboardButton, InlineKeyboardMarkup,
+    InlineQuery, InlineQueryResult, InlineQueryResultArticle, InputFile, InputMessageContent,
+    InputMessageContentText, MessageId, MessageReactionUpdated, ParseMode, ReplyParameters,
+    ThreadId, User, UserId,
+};
+use teloxide::{ApiError, RequestError};
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::JoinHandle;
 use tracing::{info, warn};
 
-pub async fn start(cfg: AppConfig, bus: MessageBus) -> Result<()> {
-    let bot = Bot::new(cfg.telegram_bot_token.clone());
-    bot.get_me()
+/// Inline queries arrive per keystroke; only the last one typed within this
+/// window is answered.
+const INLINE_DEBOUNCE: Duration = Duration::from_millis(700);
+const INLINE_MIN_CHARS: usize = 3;
+/// Photos larger than this are refused rather than downloaded.
+const MAX_PHOTO_BYTES: usize = 10 * 1024 * 1024;
+/// Telegram's Bot API serves downloads up to this size.
+const MAX_DOCUMENT_BYTES: usize = 20 * 1024 * 1024;
+/// Characters of a document's text included in the prompt.
+const DOCUMENT_EXCERPT_CHARS: usize = 1_500;
+/// Characters of a replied-to message passed along with the reply.
+const MAX_QUOTED_CHARS: usize = 1_000;
+/// How often held messages are retried while Telegram is unreachable.
+const OUTBOX_RETRY: Duration = Duration::from_secs(30);
+/// Long polling holds `getUpdates` open this long, so the client timeout
+/// must stay above it.
+const MIN_CLIENT_TIMEOUT: Duration = Duration::from_secs(15);
+/// `retry_after` waits up to this are sat out in place; longer ones queue
+/// the chat's messages so other chats are not held up.
+const MAX_INLINE_WAIT: Duration = Duration::from_secs(3);
+/// How often queued messages of throttled chats are checked.
+const WAITING_CHECK: Duration = Duration::from_secs(1);
+/// Minimum gap between edits of a live reply; Telegram throttles bots that
+/// edit one message in quick succession.
+const LIVE_EDIT_INTERVAL: Duration = Duration::from_millis(1_500);
+/// Placeholders whose turn never sent a reply are forgotten after this.
+const LIVE_REPLY_TTL: Duration = Duration::from_secs(15 * 60);
+/// Characters of an in-progress reply shown; Telegram caps messages at 4096.
+const LIVE_MAX_CHARS: usize = 4_000;
+
+/// Agent commands registered with Telegram's command menu, which every user
+/// sees; their descriptions come from the agent's command table.
+const MENU: &[&str] = &["reset", "model", "preview", "persona", "get", "help"];
+
+pub struct TelegramChannel {
+    cfg: AppConfig,
+}
+
+impl TelegramChannel {
+    pub fn new(cfg: AppConfig) -> Self {
+        Self { cfg }
+    }
+}
+
+impl Channel for TelegramChannel {
+    fn name(&self) -> &'static str {
+        "telegram"
+    }
+
+    fn start(self: Arc<Self>, bus: MessageBus) -> JoinHandle<()> {
+        channel::spawn(self.name(), serve(self, bus))
+    }
+
+    fn supports_buttons(&self) -> bool {
+        true
+    }
+
+    fn supports_attachments(&self) -> bool {
+        true
+    }
+}
+
+async fn serve(channel: Arc<TelegramChannel>, bus: MessageBus) -> Result<()> {
+    let cfg = &channel.cfg;
+    let network = &cfg.network_telegram;
+    let http = network
+        .client_builder()
+        .timeout(network.timeout().max(MIN_CLIENT_TIMEOUT))
+        .tcp_nodelay(true)
+        .build()?;
+    let bot = Bot::with_client(cfg.telegram_bot_token.clone(), http);
+    let me = bot
+        .get_me()
         .await
         .map_err(|err| anyhow!("telegram authentication failed: {err}"))?;
+    let commands = commands::COMMANDS
+        .iter()
+        .filter(|spec| MENU.contains(&spec.name))
+        .map(|spec| BotCommand::new(spec.name, spec.help));
+    if let Err(err) = bot.set_my_commands(commands).await {
+        warn!("failed to register telegram commands: {err}");
+    }
+    let bot_username = me.username().to_string();
+    let bot_id = me.id;
+    let mention_only = cfg.telegram_mention_only;
 
-    spawn_outbound_forwarder(bot.clone(), bus.subscribe_outbound());
+    spawn_outbound_forwarder(
+        channel.clone(),
+        bot.clone(),
+        bus.clone(),
+        RateLimiter::for_channel(cfg, "telegram"),
+    );
 
-    let allowlist = cfg.telegram_allow_from.clone();
-    let transcriber = Transcriber::from_config(&cfg);
-    let handler: UpdateHandler<anyhow::Error> =
+    let allowlist = Allowlist::new(cfg.telegram_allow_from.clone());
+    let files_cfg = Arc::new(cfg.clone());
+    let inline_allowlist = allowlist.clone();
+    let button_allowlist = allowlist.clone();
+    let reaction_allowlist = allowlist.clone();
+    let transcriber = Transcriber::from_config(cfg);
+    let quick = QuickAnswerer::from_config(cfg);
+    let pending_inline: Arc<Mutex<HashMap<UserId, String>>> = Arc::default();
+    let messages =
         Update::filter_message().endpoint(move |bot: Bot, msg: Message, bus: MessageBus| {
             let allowlist = allowlist.clone();
             let transcriber = transcriber.clone();
+            let bot_username = bot_username.clone();
+            let files_cfg = files_cfg.clone();
             async move {
-                if !is_allowed(&msg, &allowlist) {
+                if !allowlist.allows(msg.from.as_ref()) {
                     return Ok(());
                 }
 
-                let chat_id = msg.chat.id.0.to_string();
+                let chat_id = chat_key(msg.chat.id, topic_of(&msg));
                 let sender_id = msg
                     .from
                     .as_ref()
                     .map(|u| u.id.0.to_string())
                     .unwrap_or_else(|| "unknown".to_string());
+                let sender_name = msg.from.as_ref().map(|u| u.full_name());
+                let is_group = msg.chat.is_group() || msg.chat.is_supergroup();
+                if is_group && mention_only && !addressed_to_bot(&msg, bot_id, &bot_username) {
+                    return Ok(());
+                }
+                let mut meta = MessageMeta {
+                    message_id: Some(msg.id.0.to_string()),
+                    reply_to: msg.reply_to_message().map(|m| m.id.0.to_string()),
+                    quoted: msg
+                        .reply_to_message()
+                        .and_then(|replied| quoted_message(&msg, replied, bot_id)),
+                    locale: msg.from.as_ref().and_then(|u| u.language_code.clone()),
+                    ..Default::default()
+                };
 
                 if let Some(text) = msg.text() {
+                    let text = strip_bot_mention(text, &bot_username);
+                    if let Some(answer) =
+                        allowlist_command(&text, &sender_id, &files_cfg, &allowlist)
+                    {
+                        reply(&bot, &msg, answer).await?;
+                        return Ok(());
+                    }
                     let inbound = InboundMessage {
                         channel: "telegram".to_string(),
                         chat_id,
                         sender_id,
-                        content: text.to_string(),
+                        sender_name,
+                        is_group,
+                        content: text,
+                        meta,
                     };
                     bus.publish_inbound(inbound).await;
-                    bot.send_chat_action(msg.chat.id, ChatAction::Typing).await?;
+                    typing(&bot, &msg).await?;
+                    return Ok(());
+                }
+
+                if let Some(photo) = msg
+                    .photo()
+                    .and_then(|sizes| sizes.iter().max_by_key(|p| p.width * p.height))
+                {
+                    if photo.file.size as usize > MAX_PHOTO_BYTES {
+                        reply(&bot, &msg, "That photo is too large for me to look at.")
+                            .await?;
+                        return Ok(());
+                    }
+                    typing(&bot, &msg).await?;
+                    let name = format!("photo_{}.jpg", photo.file.unique_id.0);
+                    let saved = match download_telegram_file(&bot, photo.file.id.clone()).await {
+                        Ok(data) => save_attachment(
+                            &attachment_dir(&files_cfg, &chat_id, &sender_id),
+                            &name,
+                            &data,
+                        ),
+                        Err(err) => Err(err),
+                    };
+                    match saved {
+                        Ok(path) => {
+                            meta.attachments.push(Attachment {
+                                kind: "image".to_string(),
+                                name,
+                                mime: Some("image/jpeg".to_string()),
+                                path: Some(path),
+                            });
+                            let content = msg
+                                .caption()
+                                .map(|caption| strip_bot_mention(caption, &bot_username))
+                                .filter(|caption| !caption.trim().is_empty())
+                                .unwrap_or_else(|| "[photo]".to_string());
+                            bus.publish_inbound(InboundMessage {
+                                channel: "telegram".to_string(),
+                                chat_id,
+                                sender_id,
+                                sender_name,
+                                is_group,
+                                content,
+                                meta,
+                            })
+                            .await;
+                        }
+                        Err(err) => {
+                            warn!("photo download failed: {err}");
+                            reply(&bot, &msg, "I couldn't download that photo from Telegram.",
+                            )
+                            .await?;
+                        }
+                    }
+                    return Ok(());
+                }
+
+                if let Some(doc) = msg.document() {
+                    if doc.file.size as usize > MAX_DOCUMENT_BYTES {
+                        reply(&bot, &msg, "That file is too large to download (Telegram allows bots 20 MB).",
+                        )
+                        .await?;
+                        return Ok(());
+                    }
+                    typing(&bot, &msg).await?;
+                    let name = document_name(doc.file_name.as_deref(), &doc.file.unique_id.0);
+                    let mime = doc.mime_type.as_ref().map(ToString::to_string);
+                    let dir = attachment_dir(&files_cfg, &chat_id, &sender_id);
+                    let data = match download_telegram_file(&bot, doc.file.id.clone()).await {
+                        Ok(data) => data,
+                        Err(err) => {
+                            warn!("document download failed: {err}");
+                            reply(&bot, &msg, "I couldn't download that file from Telegram.",
+                            )
+                            .await?;
+                            return Ok(());
+                        }
+                    };
+                    let path = match save_attachment(&dir, &name, &data) {
+                        Ok(path) => path,
+                        Err(err) => {
+                            warn!("could not save document {name}: {err}");
+                            reply(&bot, &msg, "I couldn't save that file.")
+                                .await?;
+                            return Ok(());
+                        }
+                    };
+                    let summary = describe_document(&path, mime.as_deref(), &data).await;
+                    meta.attachments.push(Attachment {
+                        kind: "document".to_string(),
+                        name: name.clone(),
+                        mime,
+                        path: Some(path),
+                    });
+                    let caption = msg
+                        .caption()
+                        .map(|caption| strip_bot_mention(caption, &bot_username))
+                        .filter(|caption| !caption.trim().is_empty());
+                    let content = match caption {
+                        Some(caption) => format!("{caption}\n\n[document {name}] {summary}"),
+                        None => format!("[document {name}] {summary}"),
+                    };
+                    bus.publish_inbound(InboundMessage {
+                        channel: "telegram".to_string(),
+                        chat_id,
+                        sender_id,
+                        sender_name,
+                        is_group,
+                        content,
+                        meta,
+                    })
+                    .await;
                     return Ok(());
                 }
 
@@ -52,30 +304,42 @@ pub async fn start(cfg: AppConfig, bus: MessageBus) -> Result<()> {
                         voice.file.id.clone(),
                         format!("voice_{}.ogg", voice.file.unique_id.0),
                         voice.file.size as usize,
+                        Attachment {
+                            kind: "voice".to_string(),
+                            name: format!("voice_{}.ogg", voice.file.unique_id.0),
+                            mime: voice.mime_type.as_ref().map(ToString::to_string),
+                            path: None,
+                        },
                     ))
                 } else if let Some(audio) = msg.audio() {
                     let filename = audio
                         .file_name
                         .clone()
                         .unwrap_or_else(|| format!("audio_{}.mp3", audio.file.unique_id.0));
-                    Some((audio.file.id.clone(), filename, audio.file.size as usize))
+                    Some((
+                        audio.file.id.clone(),
+                        filename.clone(),
+                        audio.file.size as usize,
+                        Attachment {
+                            kind: "audio".to_string(),
+                            name: filename,
+                            mime: audio.mime_type.as_ref().map(ToString::to_string),
+                            path: None,
+                        },
+                    ))
                 } else {
                     None
                 };
 
-                if let Some((file_id, filename, file_size)) = media {
+                if let Some((file_id, filename, file_size, attachment)) = media {
                     let Some(transcriber) = transcriber.clone() else {
-                        bot.send_message(
-                            msg.chat.id,
-                            "Voice/audio transcription is not configured.",
+                        reply(&bot, &msg, "Voice/audio transcription is not configured.",
                         )
                         .await?;
                         return Ok(());
                     };
                     if file_size > transcriber.max_bytes() {
-                        bot.send_message(
-                            msg.chat.id,
-                            format!(
+                        reply(&bot, &msg, format!(
                                 "Audio file is too large ({} bytes). Max allowed is {} bytes.",
                                 file_size,
                                 transcriber.max_bytes()
@@ -85,39 +349,37 @@ pub async fn start(cfg: AppConfig, bus: MessageBus) -> Result<()> {
                         return Ok(());
                     }
 
-                    bot.send_chat_action(msg.chat.id, ChatAction::Typing).await?;
+                    typing(&bot, &msg).await?;
                     match download_telegram_file(&bot, file_id).await {
                         Ok(data) => match transcriber.transcribe_bytes(filename, data).await {
                             Ok(transcript) if !transcript.is_empty() => {
+                                meta.attachments.push(attachment);
                                 let inbound = InboundMessage {
                                     channel: "telegram".to_string(),
                                     chat_id,
                                     sender_id,
+                                    sender_name,
+                                    is_group,
                                     content: transcript,
+                                    meta,
                                 };
                                 bus.publish_inbound(inbound).await;
                             }
                             Ok(_) => {
-                                bot.send_message(
-                                    msg.chat.id,
-                                    "I couldn't extract text from that audio message.",
+                                reply(&bot, &msg, "I couldn't extract text from that audio message.",
                                 )
                                 .await?;
                             }
                             Err(err) => {
                                 warn!("audio transcription failed: {err}");
-                                bot.send_message(
-                                    msg.chat.id,
-                                    "I couldn't transcribe that audio message. Please retry or send text.",
+                                reply(&bot, &msg, "I couldn't transcribe that audio message. Please retry or send text.",
                                 )
                                 .await?;
                             }
                         },
                         Err(err) => {
                             warn!("audio download failed: {err}");
-                            bot.send_message(
-                                msg.chat.id,
-                                "I couldn't download that audio message from Telegram.",
+                            reply(&bot, &msg, "I couldn't download that audio message from Telegram.",
                             )
                             .await?;
                         }
@@ -127,6 +389,127 @@ pub async fn start(cfg: AppConfig, bus: MessageBus) -> Result<()> {
                 Ok(())
             }
         });
+    let inline = Update::filter_inline_query().endpoint(move |bot: Bot, q: InlineQuery| {
+        let allowlist = inline_allowlist.clone();
+        let quick = quick.clone();
+        let pending = pending_inline.clone();
+        async move {
+            let Some(quick) = quick else {
+                return Ok(());
+            };
+            let query = q.query.trim().to_string();
+            if query.chars().count() < INLINE_MIN_CHARS || !allowlist.allows(Some(&q.from)) {
+                return Ok(());
+            }
+            let query_id = q.id.to_string();
+            pending.lock().await.insert(q.from.id, query_id.clone());
+            tokio::time::sleep(INLINE_DEBOUNCE).await;
+            if pending.lock().await.get(&q.from.id) != Some(&query_id) {
+                return Ok(());
+            }
+            let answer = match quick.answer(&query).await {
+                Ok(answer) if !answer.is_empty() => answer,
+                Ok(_) => return Ok(()),
+                Err(err) => {
+                    warn!("inline query failed: {err}");
+                    return Ok(());
+                }
+            };
+            let result = InlineQueryResultArticle::new(
+                "answer",
+                truncate_title(&query),
+                InputMessageContent::Text(InputMessageContentText::new(format!(
+                    "{query}\n{answer}"
+                ))),
+            )
+            .description(answer);
+            bot.answer_inline_query(q.id, vec![InlineQueryResult::Article(result)])
+                .cache_time(quick.cache_secs())
+                .is_personal(true)
+                .await?;
+            Ok(())
+        }
+    });
+    let buttons = Update::filter_callback_query().endpoint(
+        move |bot: Bot, q: CallbackQuery, bus: MessageBus| {
+            let allowlist = button_allowlist.clone();
+            async move {
+                bot.answer_callback_query(q.id.clone()).await?;
+                let (Some(label), Some(message)) = (q.data.clone(), q.message.as_ref()) else {
+                    return Ok(());
+                };
+                if !allowlist.allows(Some(&q.from)) {
+                    return Ok(());
+                }
+                let chat = message.chat().clone();
+                // Drop the keyboard so a choice cannot be made twice.
+                if let Err(err) = bot.edit_message_reply_markup(chat.id, message.id()).await {
+                    warn!("failed to remove telegram keyboard: {err}");
+                }
+                let topic = message.regular_message().and_then(topic_of);
+                bus.publish_inbound(InboundMessage {
+                    channel: "telegram".to_string(),
+                    chat_id: chat_key(chat.id, topic),
+                    sender_id: q.from.id.0.to_string(),
+                    sender_name: Some(q.from.full_name()),
+                    is_group: chat.is_group() || chat.is_supergroup(),
+                    content: label,
+                    meta: MessageMeta {
+                        reply_to: Some(message.id().0.to_string()),
+                        locale: q.from.language_code.clone(),
+                        ..Default::default()
+                    },
+                })
+                .await;
+                let mut action = bot.send_chat_action(chat.id, ChatAction::Typing);
+                if let Some(topic) = topic {
+                    action = action.message_thread_id(topic);
+                }
+                action.await?;
+                Ok(())
+            }
+        },
+    );
+    // Telegram reports reactions in groups only to bots that are admins.
+    let reactions = Update::filter_message_reaction_updated().endpoint(
+        move |update: MessageReactionUpdated, bus: MessageBus| {
+            let allowlist = reaction_allowlist.clone();
+            async move {
+                let Some(user) = update.actor.user() else {
+                    return Ok(());
+                };
+                if !allowlist.allows(Some(user)) {
+                    return Ok(());
+                }
+                // Only newly added emoji count; taking one back is not feedback.
+                for reaction in &update.new_reaction {
+                    let Some(emoji) = reaction.emoji() else {
+                        continue;
+                    };
+                    if update
+                        .old_reaction
+                        .iter()
+                        .any(|old| old.emoji() == Some(emoji))
+                    {
+                        continue;
+                    }
+                    bus.publish_reaction(Reaction {
+                        channel: "telegram".to_string(),
+                        chat_id: chat_key(update.chat.id, None),
+                        message_id: update.message_id.0.to_string(),
+                        sender_id: user.id.0.to_string(),
+                        emoji: emoji.clone(),
+                    });
+                }
+                Ok(())
+            }
+        },
+    );
+    let handler: UpdateHandler<anyhow::Error> = dptree::entry()
+        .branch(messages)
+        .branch(inline)
+        .branch(buttons)
+        .branch(reactions);
 
     Dispatcher::builder(bot, handler)
         .dependencies(dptree::deps![bus])
@@ -138,11 +521,119 @@ pub async fn start(cfg: AppConfig, bus: MessageBus) -> Result<()> {
     Ok(())
 }
 
-fn is_allowed(msg: &Message, allowlist: &[String]) -> bool {
+/// `channels.telegram.allow_from`, which admins can edit from chat.
+#[derive(Clone)]
+struct Allowlist(Arc<RwLock<Vec<String>>>);
+
+impl Allowlist {
+    fn new(entries: Vec<String>) -> Self {
+        Self(Arc::new(RwLock::new(entries)))
+    }
+
+    fn allows(&self, user: Option<&User>) -> bool {
+        let entries = self.0.read().unwrap_or_else(|e| e.into_inner());
+        is_allowed(user, &entries)
+    }
+
+    fn entries(&self) -> Vec<String> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Answer `/allow <id>`, `/deny <id>` and `/allowlist` from admins, saving
+/// changes to the config file. `None` when `text` is not one of them.
+fn allowlist_command(
+    text: &str,
+    sender_id: &str,
+    cfg: &AppConfig,
+    allowlist: &Allowlist,
+) -> Option<String> {
+    let mut words = text.split_whitespace();
+    // "/allow@femtobot 123" in groups.
+    let command = words.next()?.split('@').next()?;
+    if !matches!(command, "/allow" | "/deny" | "/allowlist") {
+        return None;
+    }
+    if !cfg.is_admin(sender_id) {
+        return Some("Only admins can change who may use this bot.".to_string());
+    }
+    let entries = allowlist.entries();
+    if command == "/allowlist" {
+        return Some(if entries.is_empty() {
+            "The allowlist is empty: anyone can use this bot.".to_string()
+        } else {
+            format!("Allowed users:\n{}", entries.join("\n"))
+        });
+    }
+    let Some(target) = words.next() else {
+        return Some(format!("Usage: {command} <user id or @username>"));
+    };
+    let (updated, note) = match updated_allowlist(command, target, sender_id, entries) {
+        Ok(change) => change,
+        Err(refusal) => return Some(refusal),
+    };
+    *allowlist.0.write().unwrap_or_else(|e| e.into_inner()) = updated.clone();
+    let done = if command == "/allow" {
+        format!("Allowed {target}.")
+    } else {
+        format!("Removed {target}.")
+    };
+    Some(
+        match configure::update_config_file(
+            &["channels", "telegram", "allow_from"],
+            serde_json::json!(updated),
+        ) {
+            Ok(path) => format!("{done}{note} Saved to {}.", path.display()),
+            Err(err) => {
+                warn!("could not save telegram allowlist: {err}");
+                format!("{done}{note} The change lasts until the next restart; saving the config failed: {err}")
+            }
+        },
+    )
+}
+
+/// `entries` after `/allow target` or `/deny target` from `sender_id`, with
+/// a note for the reply, or why the change is refused.
+fn updated_allowlist(
+    command: &str,
+    target: &str,
+    sender_id: &str,
+    mut entries: Vec<String>,
+) -> Result<(Vec<String>, &'static str), String> {
+    let mut note = "";
+    if command == "/allow" {
+        if entries.iter().any(|entry| entry == target) {
+            return Err(format!("{target} is already allowed."));
+        }
+        if entries.is_empty() {
+            // An empty list admits everyone; the first entry closes it, so
+            // keep the admin making the change in.
+            if sender_id != target {
+                entries.push(sender_id.to_string());
+            }
+            note = " The allowlist was empty, so anyone could use the bot; now only listed users can, including you.";
+        }
+        entries.push(target.to_string());
+    } else {
+        if !entries.iter().any(|entry| entry == target) {
+            return Err(format!("{target} is not on the allowlist."));
+        }
+        entries.retain(|entry| entry != target);
+        if entries.is_empty() {
+            return Err(
+                "Removing the last entry would let anyone use the bot. Edit the config file if that is what you want."
+                    .to_string(),
+            );
+        }
+    }
+    Ok((entries, note))
+}
+
+fn is_allowed(user: Option<&User>, allowlist: &[String]) -> bool {
     if allowlist.is_empty() {
         return true;
     }
-    let user = match msg.from.as_ref() {
+    let user = match user {
         Some(u) => u,
         None => return false,
     };
@@ -162,266 +653,821 @@ fn is_allowed(msg: &Message, allowlist: &[String]) -> bool {
     })
 }
 
-fn spawn_outbound_forwarder(
-    bot: Bot,
-    mut outbound_rx: tokio::sync::broadcast::Receiver<crate::bus::OutboundMessage>,
-) {
-    tokio::spawn(async move {
-        loop {
-            let msg = match outbound_rx.recv().await {
-                Ok(msg) => msg,
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                    info!("outbound channel closed, telegram forwarder shutting down");
-                    break;
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
-                    warn!("telegram outbound lagged, skipped {skipped} message(s)");
-                    continue;
+/// Inline result titles are shown on one line; keep the echoed question short.
+fn truncate_title(query: &str) -> String {
+    const MAX_TITLE_CHARS: usize = 64;
+    if query.chars().count() <= MAX_TITLE_CHARS {
+        return query.to_string();
+    }
+    let mut title: String = query.chars().take(MAX_TITLE_CHARS - 1).collect();
+    title.push('…');
+    title
+}
+
+/// Inline keyboard for quick-reply `labels`: short labels share rows of
+/// three, longer ones get a row each. The label is the callback data.
+fn keyboard(labels: &[String]) -> InlineKeyboardMarkup {
+    let per_row = if labels.iter().all(|l| l.chars().count() <= 20) {
+        3
+    } else {
+        1
+    };
+    InlineKeyboardMarkup::new(labels.chunks(per_row).map(|row| {
+        row.iter()
+            .map(|label| InlineKeyboardButton::callback(label.clone(), label.clone()))
+            .collect::<Vec<_>>()
+    }))
+}
+
+/// Send `request`, retrying what Telegram may still accept: a short
+/// `retry_after` is waited out, transient server and network errors are
+/// retried with the backoff from `network.telegram`. A longer `retry_after` pauses the chat in `limiter`
+/// and is returned, so the caller can queue the message and serve other chats.
+async fn send_with_retry<R>(
+    request: R,
+    limiter: &mut RateLimiter,
+    limit_key: &str,
+    chat_id: &str,
+    network: &NetworkPolicy,
+) -> Result<Message, RequestError>
+where
+    R: Request<Err = RequestError> + Clone,
+    R::Payload: teloxide::requests::Payload<Output = Message>,
+{
+    for retry in 0..=network.retries {
+        limiter.acquire(limit_key).await;
+        let err = match request.clone().send().await {
+            Ok(sent) => return Ok(sent),
+            Err(err) => err,
+        };
+        if retry == network.retries {
+            return Err(err);
+        }
+        match &err {
+            RequestError::RetryAfter(wait) => {
+                limiter.pause(limit_key, wait.duration());
+                if wait.duration() > MAX_INLINE_WAIT {
+                    return Err(err);
                 }
-            };
-            if msg.channel != "telegram" {
-                continue;
+                warn!("telegram rate limited chat {chat_id}; retrying in {wait}");
             }
-            if let Ok(chat_id) = msg.chat_id.parse::<i64>() {
-                let rendered = markdown_to_telegram_markdown_v2(&msg.content);
-                let _ = bot
-                    .send_message(ChatId(chat_id), rendered)
-                    .parse_mode(ParseMode::MarkdownV2)
-                    .await;
+            err if is_transient(err) => {
+                let backoff = network.backoff(retry);
+                warn!("telegram send to chat {chat_id} failed ({err}); retrying in {backoff:?}");
+                tokio::time::sleep(backoff).await;
             }
+            _ => return Err(err),
         }
-    });
+    }
+    unreachable!("the last attempt returns")
 }
 
-fn markdown_to_telegram_markdown_v2(input: &str) -> String {
-    #[derive(Clone, Copy)]
-    enum ListKind {
-        Unordered,
-        Ordered,
-    }
+/// Network-level failures (as opposed to the API refusing the message) mean
+/// Telegram is unreachable; those messages wait in the outbox.
+fn is_outage(err: &RequestError) -> bool {
+    matches!(err, RequestError::Network(_) | RequestError::Io(_))
+}
 
-    #[derive(Clone, Copy)]
-    struct ListState {
-        kind: ListKind,
-        next: u64,
+/// Failures worth another try: outages and Telegram's own server errors,
+/// which arrive as unknown API errors or as an HTML error page.
+fn is_transient(err: &RequestError) -> bool {
+    match err {
+        RequestError::Api(ApiError::Unknown(text)) => [
+            "Internal Server Error",
+            "Bad Gateway",
+            "Service Unavailable",
+            "Gateway Timeout",
+        ]
+        .iter()
+        .any(|known| text.contains(known)),
+        RequestError::InvalidJson { .. } => true,
+        err => is_outage(err),
     }
+}
 
-    fn ensure_line_break(out: &mut String) {
-        if !out.ends_with('\n') && !out.is_empty() {
-            out.push('\n');
+/// Send the chunks of `msg` after the first `skip`, then its attachments as
+/// photos or documents, stopping at the first failure, and return the ids of
+/// the sent messages. The error comes with the number of parts sent by then.
+async fn send_outbound(
+    channel: &TelegramChannel,
+    bot: &Bot,
+    limiter: &mut RateLimiter,
+    msg: &OutboundMessage,
+    (chat_id, topic): (ChatId, Option<ThreadId>),
+    skip: usize,
+) -> Result<Vec<String>, (usize, RequestError)> {
+    let reply_to = msg
+        .meta
+        .reply_to
+        .as_deref()
+        .and_then(|id| id.parse::<i32>().ok())
+        .map(|id| ReplyParameters::new(MessageId(id)).allow_sending_without_reply());
+    // Telegram's per-chat limit covers all topics of a forum.
+    let limit_key = chat_id.0.to_string();
+    let chunks = channel.render(msg);
+    let text_parts = chunks.len();
+    let last = text_parts.saturating_sub(1);
+    let mut sent_ids = Vec::new();
+    for (idx, chunk) in chunks.into_iter().enumerate().skip(skip) {
+        let mut request = bot
+            .send_message(chat_id, chunk.clone())
+            .parse_mode(ParseMode::MarkdownV2)
+            .disable_notification(msg.meta.priority == Priority::Low);
+        if let Some(topic) = topic {
+            request = request.message_thread_id(topic);
+        }
+        if let Some(reply_to) = reply_to.clone() {
+            request = request.reply_parameters(reply_to);
+        }
+        if idx == last && !msg.meta.buttons.is_empty() {
+            request = request.reply_markup(keyboard(&msg.meta.buttons));
+        }
+        let mut result = send_with_retry(
+            request.clone(),
+            limiter,
+            &limit_key,
+            &msg.chat_id,
+            &channel.cfg.network_telegram,
+        )
+        .await;
+        if let Err(RequestError::Api(ApiError::CantParseEntities(reason))) = &result {
+            // Resend the chunk unformatted rather than dropping it.
+            warn!(
+                "telegram could not parse formatting for chat {} ({reason}); sending plain text",
+                msg.chat_id
+            );
+            let mut plain = request;
+            plain.text = render::plain_from_markdown_v2(&chunk);
+            plain.parse_mode = None;
+            result = send_with_retry(
+                plain,
+                limiter,
+                &limit_key,
+                &msg.chat_id,
+                &channel.cfg.network_telegram,
+            )
+            .await;
+        }
+        match result {
+            Ok(sent) => sent_ids.push(sent.id.0.to_string()),
+            Err(err) => {
+                if !matches!(err, RequestError::RetryAfter(_)) {
+                    warn!("telegram send failed for chat {}: {err}", msg.chat_id);
+                }
+                return Err((idx, err));
+            }
         }
     }
-
-    fn push_blockquote_prefix(out: &mut String, depth: usize) {
-        for _ in 0..depth {
-            out.push_str("\\> ");
+    let files = msg
+        .meta
+        .attachments
+        .iter()
+        .filter_map(|a| Some((a, a.path.clone()?)));
+    for (idx, (attachment, path)) in files.enumerate().map(|(i, f)| (text_parts + i, f)) {
+        if idx < skip {
+            continue;
+        }
+        let file = InputFile::file(path).file_name(attachment.name.clone());
+        let result = if attachment.kind == "photo" {
+            let mut request = bot
+                .send_photo(chat_id, file)
+                .disable_notification(msg.meta.priority == Priority::Low);
+            if let Some(topic) = topic {
+                request = request.message_thread_id(topic);
+            }
+            send_with_retry(
+                request,
+                limiter,
+                &limit_key,
+                &msg.chat_id,
+                &channel.cfg.network_telegram,
+            )
+            .await
+        } else {
+            let mut request = bot
+                .send_document(chat_id, file)
+                .disable_notification(msg.meta.priority == Priority::Low);
+            if let Some(topic) = topic {
+                request = request.message_thread_id(topic);
+            }
+            send_with_retry(
+                request,
+                limiter,
+                &limit_key,
+                &msg.chat_id,
+                &channel.cfg.network_telegram,
+            )
+            .await
+        };
+        match result {
+            Ok(sent) => sent_ids.push(sent.id.0.to_string()),
+            Err(err) => {
+                if !matches!(err, RequestError::RetryAfter(_)) {
+                    warn!(
+                        "telegram could not send {} to chat {}: {err}",
+                        attachment.name, msg.chat_id
+                    );
+                }
+                return Err((idx, err));
+            }
         }
     }
+    Ok(sent_ids)
+}
 
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    options.insert(Options::ENABLE_TASKLISTS);
-    let parser = Parser::new_ext(input, options);
-    let mut out = String::with_capacity(input.len() + 16);
-    let mut list_stack: Vec<ListState> = Vec::new();
-    let mut in_code_block = false;
-    let mut item_open = false;
-    let mut link_urls: Vec<String> = Vec::new();
-    let mut blockquote_depth = 0usize;
-
-    for event in parser {
-        match event {
-            Event::Start(tag) => match tag {
-                Tag::Paragraph => {}
-                Tag::Heading { .. } => {
-                    ensure_line_break(&mut out);
-                    out.push('*');
-                }
-                Tag::List(start) => {
-                    list_stack.push(ListState {
-                        kind: if start.is_some() {
-                            ListKind::Ordered
-                        } else {
-                            ListKind::Unordered
-                        },
-                        next: start.unwrap_or(1),
-                    });
-                    ensure_line_break(&mut out);
-                }
-                Tag::Item => {
-                    ensure_line_break(&mut out);
-                    if let Some(last) = list_stack.last_mut() {
-                        match last.kind {
-                            ListKind::Unordered => out.push_str("• "),
-                            ListKind::Ordered => {
-                                out.push_str(&last.next.to_string());
-                                out.push_str("\\. ");
-                                last.next += 1;
-                            }
+/// State of the outbound forwarder task.
+struct Forwarder {
+    channel: Arc<TelegramChannel>,
+    bot: Bot,
+    bus: MessageBus,
+    limiter: RateLimiter,
+    outbox: Outbox,
+    live: LiveReplies,
+    live_edit: bool,
+    /// Messages for chats Telegram asked to slow down, in order, with the
+    /// number of chunks already sent.
+    waiting: HashMap<String, VecDeque<(OutboundMessage, usize)>>,
+}
+
+fn spawn_outbound_forwarder(
+    channel: Arc<TelegramChannel>,
+    bot: Bot,
+    bus: MessageBus,
+    limiter: RateLimiter,
+) {
+    let mut outbound_rx = bus.outbound_queue(channel.name());
+    let outbox = Outbox::open(&channel.cfg.data_dir, channel.name());
+    if !outbox.is_empty() {
+        info!(
+            "telegram outbox holds {} message(s) from an earlier outage",
+            outbox.len()
+        );
+    }
+    let mut partial_rx = bus.subscribe_partials();
+    let mut forwarder = Forwarder {
+        live_edit: channel.cfg.telegram_live_edit,
+        channel,
+        bot,
+        bus,
+        limiter,
+        outbox,
+        live: LiveReplies::new(),
+        waiting: HashMap::new(),
+    };
+    tokio::spawn(async move {
+        let mut retry = tokio::time::interval(OUTBOX_RETRY);
+        let mut resume = tokio::time::interval(WAITING_CHECK);
+        loop {
+            let msg = tokio::select! {
+                msg = outbound_rx.recv() => match msg {
+                    Some(msg) => Some(msg),
+                    None => break,
+                },
+                _ = retry.tick(), if !forwarder.outbox.is_empty() => None,
+                _ = resume.tick(), if !forwarder.waiting.is_empty() => None,
+                partial = partial_rx.recv(), if forwarder.live_edit => {
+                    match partial {
+                        Ok(partial)
+                            if partial.channel == forwarder.channel.name()
+                                && forwarder.outbox.is_empty() =>
+                        {
+                            update_live(
+                                &forwarder.bot,
+                                &mut forwarder.limiter,
+                                &mut forwarder.live,
+                                partial,
+                            )
+                            .await;
                         }
-                    }
-                    item_open = true;
-                }
-                Tag::Emphasis => out.push('_'),
-                Tag::Strong => out.push('*'),
-                Tag::Strikethrough => out.push('~'),
-                Tag::BlockQuote(_) => {
-                    ensure_line_break(&mut out);
-                    blockquote_depth += 1;
-                    push_blockquote_prefix(&mut out, blockquote_depth);
-                }
-                Tag::Link { dest_url, .. } => {
-                    out.push('[');
-                    link_urls.push(dest_url.to_string());
-                }
-                Tag::CodeBlock(kind) => {
-                    ensure_line_break(&mut out);
-                    out.push_str("```");
-                    if let CodeBlockKind::Fenced(lang) = kind {
-                        let lang = lang.trim();
-                        if !lang.is_empty() {
-                            out.push_str(&escape_markdown_v2_code(lang));
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("telegram live replies lagged, skipped {skipped} update(s)");
                         }
+                        _ => {}
                     }
-                    out.push('\n');
-                    in_code_block = true;
-                }
-                _ => {}
-            },
-            Event::End(tag) => match tag {
-                TagEnd::Paragraph => {
-                    ensure_line_break(&mut out);
-                }
-                TagEnd::Heading(_) => {
-                    out.push('*');
-                    ensure_line_break(&mut out);
-                }
-                TagEnd::List(_) => {
-                    let _ = list_stack.pop();
-                    ensure_line_break(&mut out);
-                }
-                TagEnd::Item => {
-                    if item_open {
-                        ensure_line_break(&mut out);
-                    }
-                    item_open = false;
+                    continue;
                 }
-                TagEnd::Emphasis => out.push('_'),
-                TagEnd::Strong => out.push('*'),
-                TagEnd::Strikethrough => out.push('~'),
-                TagEnd::Link => {
-                    let url = link_urls.pop().unwrap_or_default();
-                    out.push(']');
-                    out.push('(');
-                    out.push_str(&escape_markdown_v2_url(&url));
-                    out.push(')');
+            };
+            forwarder.flush_outbox().await;
+            forwarder.resume_waiting().await;
+            let Some(msg) = msg else { continue };
+            let Some((chat, _)) = parse_chat_key(&msg.chat_id) else {
+                continue;
+            };
+            if !forwarder.outbox.is_empty() {
+                forwarder.outbox.hold(&msg);
+                continue;
+            }
+            // Later messages queue behind earlier ones for a throttled chat.
+            let paused = !forwarder.limiter.paused_for(&chat.0.to_string()).is_zero();
+            if paused || forwarder.waiting.contains_key(&msg.chat_id) {
+                forwarder
+                    .waiting
+                    .entry(msg.chat_id.clone())
+                    .or_default()
+                    .push_back((msg, 0));
+                continue;
+            }
+            if let Err(sent) = forwarder.deliver(&msg, 0).await {
+                forwarder
+                    .waiting
+                    .entry(msg.chat_id.clone())
+                    .or_default()
+                    .push_back((msg, sent));
+            }
+        }
+        info!("outbound channel closed, telegram forwarder shutting down");
+    });
+}
+
+impl Forwarder {
+    /// Send held messages first so the chat sees them in order.
+    async fn flush_outbox(&mut self) {
+        while let Some(held) = self.outbox.front() {
+            let Some(chat_id) = parse_chat_key(&held.chat_id) else {
+                self.outbox.pop_front();
+                continue;
+            };
+            match send_outbound(
+                &self.channel,
+                &self.bot,
+                &mut self.limiter,
+                &held,
+                chat_id,
+                0,
+            )
+            .await
+            {
+                Err((_, err)) if is_outage(&err) || matches!(err, RequestError::RetryAfter(_)) => {
+                    break
                 }
-                TagEnd::CodeBlock => {
-                    if !out.ends_with('\n') {
-                        out.push('\n');
+                Err((_, err)) => self.bus.report_failure(&held, err.to_string()),
+                Ok(ids) => self.bus.report_sent(&held, ids),
+            }
+            self.outbox.pop_front();
+            if self.outbox.is_empty() {
+                info!("telegram reachable again; outbox flushed");
+            }
+        }
+    }
+
+    /// Send what waits for chats whose pause is over, oldest first.
+    async fn resume_waiting(&mut self) {
+        let keys: Vec<String> = self.waiting.keys().cloned().collect();
+        for key in keys {
+            loop {
+                let paused = parse_chat_key(&key).is_some_and(|(chat, _)| {
+                    !self.limiter.paused_for(&chat.0.to_string()).is_zero()
+                });
+                let next = match self.waiting.get_mut(&key) {
+                    Some(queue) if !paused => queue.pop_front(),
+                    _ => None,
+                };
+                let Some((msg, skip)) = next else { break };
+                if let Err(sent) = self.deliver(&msg, skip).await {
+                    if let Some(queue) = self.waiting.get_mut(&key) {
+                        queue.push_front((msg, sent));
                     }
-                    out.push_str("```");
-                    ensure_line_break(&mut out);
-                    in_code_block = false;
-                }
-                TagEnd::BlockQuote(_) => {
-                    ensure_line_break(&mut out);
-                    blockquote_depth = blockquote_depth.saturating_sub(1);
-                }
-                _ => {}
-            },
-            Event::Text(text) => {
-                if in_code_block {
-                    out.push_str(&escape_markdown_v2_code(&text));
-                } else {
-                    out.push_str(&escape_markdown_v2_text(&text));
+                    break;
                 }
             }
-            Event::Code(code) => {
-                out.push('`');
-                out.push_str(&escape_markdown_v2_code(&code));
-                out.push('`');
+        }
+        self.waiting.retain(|_, queue| !queue.is_empty());
+    }
+
+    /// Send `msg` from chunk `skip` on, finishing its live reply if one is
+    /// showing. Returns `Err` with the chunks sent so far when Telegram asked
+    /// for a longer pause; the message should wait and be resumed then.
+    async fn deliver(&mut self, msg: &OutboundMessage, skip: usize) -> Result<(), usize> {
+        let Some(chat_id) = parse_chat_key(&msg.chat_id) else {
+            return Ok(());
+        };
+        let placeholder = match &msg.meta.correlation_id {
+            Some(id) if self.live_edit && skip == 0 => finish_turn(&mut self.live, id),
+            _ => None,
+        };
+        if let Some(placeholder) = placeholder {
+            if finish_live(
+                &self.channel,
+                &self.bot,
+                &mut self.limiter,
+                msg,
+                placeholder,
+            )
+            .await
+            {
+                self.bus
+                    .report_sent(msg, vec![placeholder.1 .0.to_string()]);
+                return Ok(());
             }
-            Event::InlineHtml(html) | Event::Html(html) => {
-                out.push_str(&escape_markdown_v2_text(&html));
+        }
+        match send_outbound(
+            &self.channel,
+            &self.bot,
+            &mut self.limiter,
+            msg,
+            chat_id,
+            skip,
+        )
+        .await
+        {
+            Ok(ids) => self.bus.report_sent(msg, ids),
+            Err((sent, RequestError::RetryAfter(wait))) => {
+                info!(
+                    "telegram asked to wait {wait} before writing to chat {}; queued",
+                    msg.chat_id
+                );
+                return Err(sent);
             }
-            Event::InlineMath(math) | Event::DisplayMath(math) => {
-                out.push_str(&escape_markdown_v2_text(&math));
+            Err((_, err)) if is_outage(&err) => {
+                warn!("telegram unreachable; holding messages until it is back");
+                self.outbox.hold(msg);
             }
-            Event::SoftBreak | Event::HardBreak => {
-                out.push('\n');
-                if blockquote_depth > 0 {
-                    push_blockquote_prefix(&mut out, blockquote_depth);
-                }
+            Err((_, err)) => self.bus.report_failure(msg, err.to_string()),
+        }
+        Ok(())
+    }
+}
+
+/// A turn's live reply (`channels.telegram.live_edit`), keyed by correlation id.
+enum LiveState {
+    /// Placeholder edited while the turn runs.
+    Showing {
+        chat: ChatId,
+        message_id: MessageId,
+        last_edit: Instant,
+    },
+    /// The final reply went out; partials arriving late are ignored.
+    Finished,
+}
+
+type LiveReplies = HashMap<String, (Instant, LiveState)>;
+
+/// Post a placeholder when a turn starts, then edit it as text arrives, at
+/// most once per [`LIVE_EDIT_INTERVAL`]. Skipped updates are not lost for
+/// good: the final reply replaces the placeholder either way.
+async fn update_live(
+    bot: &Bot,
+    limiter: &mut RateLimiter,
+    live: &mut LiveReplies,
+    partial: PartialOutput,
+) {
+    let now = Instant::now();
+    live.retain(|_, (started, _)| now.duration_since(*started) < LIVE_REPLY_TTL);
+    // Progress is not worth waiting out a flood-control pause for.
+    if parse_chat_key(&partial.chat_id)
+        .is_some_and(|(chat, _)| !limiter.paused_for(&chat.0.to_string()).is_zero())
+    {
+        return;
+    }
+    match live.get_mut(&partial.correlation_id) {
+        Some((_, LiveState::Finished)) => {}
+        Some((
+            _,
+            LiveState::Showing {
+                chat,
+                message_id,
+                last_edit,
+            },
+        )) => {
+            if partial.text.is_empty() || now.duration_since(*last_edit) < LIVE_EDIT_INTERVAL {
+                return;
             }
-            Event::Rule => {
-                ensure_line_break(&mut out);
-                out.push_str("\\-\\-\\-");
-                ensure_line_break(&mut out);
+            limiter.acquire(&chat.0.to_string()).await;
+            let request = bot.edit_message_text(*chat, *message_id, live_text(&partial.text));
+            match request.await {
+                Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => {}
+                Err(err) => warn!(
+                    "telegram live edit failed for chat {}: {err}",
+                    partial.chat_id
+                ),
             }
-            Event::FootnoteReference(label) => {
-                out.push('[');
-                out.push_str(&escape_markdown_v2_text(&label));
-                out.push(']');
+            *last_edit = Instant::now();
+        }
+        None => {
+            let Some((chat, topic)) = parse_chat_key(&partial.chat_id) else {
+                return;
+            };
+            let mut request = bot.send_message(chat, live_text(&partial.text));
+            if let Some(topic) = topic {
+                request = request.message_thread_id(topic);
             }
-            Event::TaskListMarker(checked) => {
-                if checked {
-                    out.push_str("\\[x\\] ");
-                } else {
-                    out.push_str("\\[ \\] ");
+            if let Some(id) = partial
+                .reply_to
+                .as_deref()
+                .and_then(|id| id.parse::<i32>().ok())
+            {
+                request = request.reply_parameters(
+                    ReplyParameters::new(MessageId(id)).allow_sending_without_reply(),
+                );
+            }
+            limiter.acquire(&chat.0.to_string()).await;
+            match request.await {
+                Ok(sent) => {
+                    let state = LiveState::Showing {
+                        chat,
+                        message_id: sent.id,
+                        last_edit: Instant::now(),
+                    };
+                    live.insert(partial.correlation_id, (now, state));
                 }
+                Err(err) => warn!(
+                    "telegram live placeholder failed for chat {}: {err}",
+                    partial.chat_id
+                ),
             }
         }
     }
+}
 
-    out.trim_end().to_string()
+/// Mark the turn behind `correlation_id` as replied, returning its
+/// placeholder if one is showing.
+fn finish_turn(live: &mut LiveReplies, correlation_id: &str) -> Option<(ChatId, MessageId)> {
+    let previous = live.insert(
+        correlation_id.to_string(),
+        (Instant::now(), LiveState::Finished),
+    );
+    match previous {
+        Some((
+            _,
+            LiveState::Showing {
+                chat, message_id, ..
+            },
+        )) => Some((chat, message_id)),
+        _ => None,
+    }
 }
 
-fn escape_markdown_v2_text(input: &str) -> String {
-    let mut out = String::with_capacity(input.len());
-    for ch in input.chars() {
-        push_escaped_markdown_v2_char(&mut out, ch);
+/// In-progress text as plain text, marked as unfinished.
+fn live_text(text: &str) -> String {
+    let mut shown: String = text.chars().take(LIVE_MAX_CHARS).collect();
+    if !shown.is_empty() {
+        shown.push(' ');
     }
-    out
+    shown.push('…');
+    shown
 }
 
-fn escape_markdown_v2_code(input: &str) -> String {
-    let mut out = String::with_capacity(input.len());
-    for ch in input.chars() {
-        match ch {
-            '`' | '\\' => {
-                out.push('\\');
-                out.push(ch);
-            }
-            _ => out.push(ch),
+/// Turn a live placeholder into the final reply. Replies that need several
+/// messages are sent normally instead and the placeholder is deleted; returns
+/// whether `msg` was delivered by the edit.
+async fn finish_live(
+    channel: &TelegramChannel,
+    bot: &Bot,
+    limiter: &mut RateLimiter,
+    msg: &OutboundMessage,
+    (chat, message_id): (ChatId, MessageId),
+) -> bool {
+    let limit_key = chat.0.to_string();
+    let chunks = channel.render(msg);
+    // Attachments need messages of their own, so those replies go out anew.
+    if let ([chunk], true) = (chunks.as_slice(), msg.meta.attachments.is_empty()) {
+        let mut request = bot
+            .edit_message_text(chat, message_id, chunk.clone())
+            .parse_mode(ParseMode::MarkdownV2);
+        if !msg.meta.buttons.is_empty() {
+            request = request.reply_markup(keyboard(&msg.meta.buttons));
+        }
+        limiter.acquire(&limit_key).await;
+        let mut result = request.clone().await;
+        if let Err(RequestError::Api(ApiError::CantParseEntities(_))) = &result {
+            let mut plain = request;
+            plain.text = render::plain_from_markdown_v2(chunk);
+            plain.parse_mode = None;
+            limiter.acquire(&limit_key).await;
+            result = plain.await;
         }
+        match result {
+            Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => return true,
+            Err(err) => warn!(
+                "telegram could not finish live reply in chat {}: {err}",
+                msg.chat_id
+            ),
+        }
+    }
+    limiter.acquire(&limit_key).await;
+    if let Err(err) = bot.delete_message(chat, message_id).await {
+        warn!(
+            "telegram could not delete live placeholder in chat {}: {err}",
+            msg.chat_id
+        );
     }
-    out
+    false
 }
 
-fn escape_markdown_v2_url(input: &str) -> String {
-    let mut out = String::with_capacity(input.len());
-    for ch in input.chars() {
-        match ch {
-            ')' | '\\' => {
-                out.push('\\');
-                out.push(ch);
-            }
-            _ => out.push(ch),
-        }
+/// The message `msg` replies to, or only the part the user quoted from it.
+/// Service messages (such as a forum topic's opening) have no text and give
+/// `None`.
+fn quoted_message(msg: &Message, replied: &Message, bot_id: UserId) -> Option<Quoted> {
+    let text = msg
+        .quote()
+        .map(|quote| quote.text.as_str())
+        .or_else(|| replied.text())
+        .or_else(|| replied.caption())?;
+    Some(Quoted {
+        author: replied
+            .from
+            .as_ref()
+            .map(|user| user.full_name())
+            .unwrap_or_else(|| "unknown".to_string()),
+        from_bot: replied.from.as_ref().is_some_and(|user| user.id == bot_id),
+        text: text.chars().take(MAX_QUOTED_CHARS).collect(),
+    })
+}
+
+/// Forum topic `msg` was posted in, if any.
+fn topic_of(msg: &Message) -> Option<ThreadId> {
+    msg.thread_id.filter(|_| msg.is_topic_message)
+}
+
+/// Chat id used on the bus: the chat, plus `/<topic>` for forum topics so
+/// each topic is a conversation of its own.
+fn chat_key(chat: ChatId, topic: Option<ThreadId>) -> String {
+    match topic {
+        Some(ThreadId(MessageId(thread))) => format!("{}/{thread}", chat.0),
+        None => chat.0.to_string(),
+    }
+}
+
+fn parse_chat_key(key: &str) -> Option<(ChatId, Option<ThreadId>)> {
+    match key.split_once('/') {
+        Some((chat, thread)) => Some((
+            ChatId(chat.parse().ok()?),
+            Some(ThreadId(MessageId(thread.parse().ok()?))),
+        )),
+        None => Some((ChatId(key.parse().ok()?), None)),
+    }
+}
+
+/// A message to the chat (and topic) `msg` came from.
+fn reply(
+    bot: &Bot,
+    msg: &Message,
+    text: impl Into<String>,
+) -> teloxide::requests::JsonRequest<teloxide::payloads::SendMessage> {
+    let request = bot.send_message(msg.chat.id, text);
+    match topic_of(msg) {
+        Some(topic) => request.message_thread_id(topic),
+        None => request,
+    }
+}
+
+fn typing(
+    bot: &Bot,
+    msg: &Message,
+) -> teloxide::requests::JsonRequest<teloxide::payloads::SendChatAction> {
+    let request = bot.send_chat_action(msg.chat.id, ChatAction::Typing);
+    match topic_of(msg) {
+        Some(topic) => request.message_thread_id(topic),
+        None => request,
+    }
+}
+
+/// In groups, commands arrive as `/status@SomeBot` and questions as
+/// "@SomeBot what's up"; drop our own name so the agent sees the plain text.
+fn strip_bot_mention(text: &str, username: &str) -> String {
+    let Some((command, rest)) = text
+        .strip_prefix('/')
+        .map(|body| body.split_at(body.find(char::is_whitespace).unwrap_or(body.len())))
+    else {
+        let mention = format!("@{username}");
+        let words: Vec<&str> = text
+            .split(' ')
+            .filter(|word| {
+                !word
+                    .trim_end_matches([',', ':', '!', '?', '.'])
+                    .eq_ignore_ascii_case(&mention)
+            })
+            .collect();
+        let stripped = words.join(" ").trim().to_string();
+        return if stripped.is_empty() {
+            text.to_string()
+        } else {
+            stripped
+        };
+    };
+    match command.split_once('@') {
+        Some((name, bot)) if bot.eq_ignore_ascii_case(username) => format!("/{name}{rest}"),
+        _ => text.to_string(),
+    }
+}
+
+/// Whether a group message is meant for the bot: it mentions `@username`,
+/// replies to one of the bot's messages, or is a command.
+fn addressed_to_bot(msg: &Message, bot_id: UserId, username: &str) -> bool {
+    let replies_to_bot = msg
+        .reply_to_message()
+        .and_then(|replied| replied.from.as_ref())
+        .is_some_and(|user| user.id == bot_id);
+    let text = msg.text().or(msg.caption()).unwrap_or_default();
+    let mention = format!("@{}", username.to_lowercase());
+    replies_to_bot || text.starts_with('/') || text.to_lowercase().contains(&mention)
+}
+
+/// Keep an inbound file under `dir/<chat>/` so the agent can refer back to it.
+/// Where files a user sends are saved: inside the chat's sandbox when it has
+/// one, so the file tools can open them, else under the workspace.
+fn attachment_dir(cfg: &AppConfig, chat_id: &str, sender_id: &str) -> PathBuf {
+    match ChatSandbox::for_sender(cfg, "telegram", chat_id, sender_id) {
+        Some(sandbox) => sandbox.root.join("attachments"),
+        None => cfg
+            .workspace_dir
+            .join("attachments")
+            .join(sanitize_file_stem(chat_id)),
+    }
+}
+
+fn save_attachment(dir: &Path, name: &str, data: &[u8]) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(name);
+    std::fs::write(&path, data)?;
+    Ok(path)
+}
+
+/// The sender's file name reduced to something safe to save, keeping the
+/// extension.
+fn document_name(file_name: Option<&str>, unique_id: &str) -> String {
+    let raw = file_name.unwrap_or_default();
+    let raw = raw.rsplit(['/', '\\']).next().unwrap_or_default();
+    let (stem, ext) = match raw.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+        _ => (raw, None),
+    };
+    let stem: String = sanitize_file_stem(stem).chars().take(80).collect();
+    let stem = if stem.is_empty() {
+        format!("document_{unique_id}")
+    } else {
+        stem
+    };
+    match ext.map(sanitize_file_stem).filter(|ext| !ext.is_empty()) {
+        Some(ext) => format!("{stem}.{}", ext.to_ascii_lowercase()),
+        None => stem,
     }
-    out
 }
 
-fn push_escaped_markdown_v2_char(out: &mut String, ch: char) {
-    match ch {
-        '_' | '*' | '[' | ']' | '(' | ')' | '~' | '`' | '>' | '#' | '+' | '-' | '=' | '|' | '{'
-        | '}' | '.' | '!' | '\\' => {
-            out.push('\\');
-            out.push(ch);
+/// One-paragraph description of a saved document for the prompt: its size,
+/// an excerpt of its text, and where to read the rest. PDFs are converted
+/// and the text saved next to the original.
+async fn describe_document(path: &Path, mime: Option<&str>, data: &[u8]) -> String {
+    let size = format!("{} KB", data.len().div_ceil(1024));
+    let is_pdf = mime == Some("application/pdf") || data.starts_with(b"%PDF");
+    let (text, text_path) = if is_pdf {
+        match pdf_to_text(data).await {
+            Ok(text) => {
+                let text_path = path.with_extension("pdf.txt");
+                match std::fs::write(&text_path, &text) {
+                    Ok(()) => (text, text_path),
+                    Err(err) => {
+                        warn!("could not save extracted text: {err}");
+                        (text, path.to_path_buf())
+                    }
+                }
+            }
+            Err(err) => {
+                return format!(
+                    "PDF, {size}, saved at {}. Its text could not be extracted: {err}",
+                    path.display()
+                )
+            }
         }
-        _ => out.push(ch),
+    } else if is_text_document(path, mime, data) {
+        (
+            String::from_utf8_lossy(data).to_string(),
+            path.to_path_buf(),
+        )
+    } else {
+        return format!(
+            "{size}, saved at {}. Not a text format, so no excerpt is included.",
+            path.display()
+        );
+    };
+    let text = text.trim();
+    if text.is_empty() {
+        return format!("{size}, saved at {}. It contains no text.", path.display());
     }
+    let lines = text.lines().count();
+    let mut excerpt: String = text.chars().take(DOCUMENT_EXCERPT_CHARS).collect();
+    let more = if text.chars().count() > DOCUMENT_EXCERPT_CHARS {
+        excerpt.push_str("\n...");
+        format!(" Read {} with read_file for the rest.", text_path.display())
+    } else {
+        String::new()
+    };
+    format!(
+        "{size}, {lines} lines, saved at {}.{more}\nExcerpt:\n{excerpt}",
+        path.display()
+    )
+}
+
+fn is_text_document(path: &Path, mime: Option<&str>, data: &[u8]) -> bool {
+    const TEXT_EXTENSIONS: &[&str] = &[
+        "txt", "csv", "tsv", "md", "json", "log", "xml", "yaml", "yml", "toml", "ini", "html",
+    ];
+    let by_type = mime.is_some_and(|mime| mime.starts_with("text/") || mime.ends_with("json"));
+    let by_extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| TEXT_EXTENSIONS.contains(&ext));
+    (by_type || by_extension) && std::str::from_utf8(&data[..data.len().min(4096)]).is_ok()
 }
 
 async fn download_telegram_file(bot: &Bot, file_id: FileId) -> Result<Vec<u8>> {
@@ -433,12 +1479,54 @@ async fn download_telegram_file(bot: &Bot, file_id: FileId) -> Result<Vec<u8>> {
 
 #[cfg(test)]
 mod tests {
-    use super::markdown_to_telegram_markdown_v2;
+    use super::{allowlist_command, updated_allowlist, Allowlist};
+    use crate::config::AppConfig;
+
+    fn admin_config() -> AppConfig {
+        let mut cfg = AppConfig::defaults();
+        cfg.admins = vec!["1".to_string()];
+        cfg
+    }
+
+    #[test]
+    fn only_admins_change_the_allowlist() {
+        let cfg = admin_config();
+        let allowlist = Allowlist::new(vec!["2".to_string()]);
+        for text in ["/allow 3", "/deny 2", "/allowlist", "/allow@femtobot 3"] {
+            let reply = allowlist_command(text, "2", &cfg, &allowlist).unwrap();
+            assert!(reply.starts_with("Only admins"), "{text}: {reply}");
+        }
+        assert_eq!(allowlist.entries(), ["2"]);
+        assert!(allowlist_command("/start", "2", &cfg, &allowlist).is_none());
+    }
 
     #[test]
-    fn renders_multiline_blockquote_lines() {
-        let input = "> first line\n> second line";
-        let rendered = markdown_to_telegram_markdown_v2(input);
-        assert_eq!(rendered, "\\> first line\n\\> second line");
+    fn first_entry_keeps_the_admin_in() {
+        let (entries, note) = updated_allowlist("/allow", "3", "1", Vec::new()).unwrap();
+        assert_eq!(entries, ["1", "3"]);
+        assert!(note.contains("including you"));
+
+        let (entries, _) = updated_allowlist("/allow", "1", "1", Vec::new()).unwrap();
+        assert_eq!(entries, ["1"]);
+
+        let (entries, note) = updated_allowlist("/allow", "3", "1", vec!["2".to_string()]).unwrap();
+        assert_eq!(entries, ["2", "3"]);
+        assert!(note.is_empty());
+    }
+
+    #[test]
+    fn the_last_entry_is_not_removed() {
+        let cfg = admin_config();
+        let allowlist = Allowlist::new(vec!["1".to_string()]);
+        let reply = allowlist_command("/deny 1", "1", &cfg, &allowlist).unwrap();
+        assert!(reply.starts_with("Removing the last entry"), "{reply}");
+        assert_eq!(allowlist.entries(), ["1"]);
+
+        let reply = allowlist_command("/deny 9", "1", &cfg, &allowlist).unwrap();
+        assert_eq!(reply, "9 is not on the allowlist.");
+
+        let (entries, _) =
+            updated_allowlist("/deny", "2", "1", vec!["1".to_string(), "2".to_string()]).unwrap();
+        assert_eq!(entries, ["1"]);
     }
 }
@@ -0,0 +1,94 @@
+use crate::broadcast;
+use crate::config::AppConfig;
+use crate::cron::CronService;
+use crate::tools::middleware;
+use crate::tools::ToolError;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+
+#[derive(Clone)]
+pub struct BroadcastTool {
+    cfg: AppConfig,
+    cron: CronService,
+}
+
+impl BroadcastTool {
+    pub fn new(cfg: AppConfig, cron: CronService) -> Self {
+        Self { cfg, cron }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct BroadcastArgs {
+    /// Text to send, verbatim, to every selected chat
+    pub message: String,
+    /// Only chats with any of these tags (a channel name like "telegram" or a configured tag); omit for all
+    pub tags: Option<Vec<String>>,
+    /// Set true only after showing the user the recipient list and getting their go-ahead
+    pub confirm: Option<bool>,
+}
+
+impl Tool for BroadcastTool {
+    const NAME: &'static str = "broadcast";
+    type Args = BroadcastArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Admin only: send one message to all allowlisted chats, or to those with given tags (e.g. a restart notice). Without confirm=true it only returns the recipients; show them to the user and call again with confirm=true once they agree. Delivery is queued and paced per channel.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(BroadcastArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let cfg = self.cfg.clone();
+        let cron = self.cron.clone();
+        async move {
+            let admin =
+                middleware::current_chat().is_some_and(|chat| cfg.is_admin(&chat.sender_id));
+            if !admin {
+                return Err(ToolError::msg(
+                    "broadcast is restricted to admins. Tell the user you cannot do this for them.",
+                ));
+            }
+            let message = args.message.trim();
+            if message.is_empty() {
+                return Err(ToolError::msg("Missing required field: message"));
+            }
+            let tags = args.tags.unwrap_or_default();
+            let targets = broadcast::select(&cfg, &tags);
+            if targets.is_empty() {
+                return Err(ToolError::msg(
+                    "No chats match. Broadcasts reach allowlisted chats of enabled channels and broadcast.targets.",
+                ));
+            }
+            let recipients: Vec<String> = targets.iter().map(|t| t.label()).collect();
+            if !args.confirm.unwrap_or(false) {
+                return Ok(format!(
+                    "Not sent yet. Would send to {} chats:\n{}\nAsk the user to confirm, then call again with confirm=true.",
+                    targets.len(),
+                    recipients.join("\n")
+                ));
+            }
+            broadcast::queue(&cron, &targets, message)
+                .await
+                .map_err(|e| ToolError::msg(e.to_string()))?;
+            Ok(format!(
+                "Broadcast queued for {} chats: {}",
+                targets.len(),
+                recipients.join(", ")
+            ))
+        }
+    }
+}
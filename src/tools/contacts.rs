@@ -0,0 +1,133 @@
+use crate::contacts::{Contact, ContactBook};
+use crate::tools::ToolError;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Clone)]
+pub struct ContactsTool {
+    book: Arc<Mutex<ContactBook>>,
+}
+
+impl ContactsTool {
+    pub fn new(workspace_dir: PathBuf) -> Self {
+        Self {
+            book: Arc::new(Mutex::new(ContactBook::new(workspace_dir))),
+        }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct ContactsArgs {
+    /// One of: add, find, list, remove
+    pub action: String,
+    /// Contact name (required for add/remove; search query for find)
+    pub name: Option<String>,
+    /// Channel used to reach the contact (e.g. "telegram")
+    pub channel: Option<String>,
+    /// Chat id on that channel (e.g. Telegram chat id)
+    pub chat_id: Option<String>,
+    /// Email address
+    pub email: Option<String>,
+    /// Phone number
+    pub phone: Option<String>,
+    /// Free-form notes about the contact
+    pub notes: Option<String>,
+}
+
+impl Tool for ContactsTool {
+    const NAME: &'static str = "contacts";
+    type Args = ContactsArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Manage the contact book. Use action=find to resolve a person's name to their channel/chat_id before calling send_message; never guess chat ids. action=add creates a contact or updates the given fields of an existing one (pass an empty string to clear a field); list shows all contacts; remove deletes by name.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(ContactsArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let book = self.book.clone();
+        async move {
+            let action = args.action.trim().to_lowercase();
+            let mut book = book.lock().await;
+            book.load().map_err(|e| ToolError::msg(e.to_string()))?;
+
+            match action.as_str() {
+                "add" => {
+                    let name = args
+                        .name
+                        .ok_or_else(|| ToolError::msg("Missing required field: name"))?;
+                    let created = book
+                        .upsert(Contact {
+                            name,
+                            channel: args.channel,
+                            chat_id: args.chat_id,
+                            email: args.email,
+                            phone: args.phone,
+                            notes: args.notes,
+                        })
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    if created {
+                        Ok("Contact added.".to_string())
+                    } else {
+                        Ok("Contact updated.".to_string())
+                    }
+                }
+                "find" => {
+                    let query = args
+                        .name
+                        .ok_or_else(|| ToolError::msg("Missing required field: name"))?;
+                    let matches = book.find(&query);
+                    if matches.is_empty() {
+                        return Ok(format!("No contacts matching '{query}'."));
+                    }
+                    Ok(matches
+                        .iter()
+                        .map(Contact::summary)
+                        .collect::<Vec<_>>()
+                        .join("\n"))
+                }
+                "list" => {
+                    if book.contacts.is_empty() {
+                        return Ok("No contacts found.".to_string());
+                    }
+                    Ok(book
+                        .contacts
+                        .iter()
+                        .map(Contact::summary)
+                        .collect::<Vec<_>>()
+                        .join("\n"))
+                }
+                "remove" => {
+                    let name = args
+                        .name
+                        .ok_or_else(|| ToolError::msg("Missing required field: name"))?;
+                    let removed = book
+                        .remove(&name)
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    if removed {
+                        Ok("Contact removed.".to_string())
+                    } else {
+                        Ok("Contact not found.".to_string())
+                    }
+                }
+                _ => Ok("Invalid action. Use: add, find, list, remove.".to_string()),
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::cron::CronService;
+use crate::reports::ReportSpec;
 use crate::tools::ToolError;
 use rig::completion::request::ToolDefinition;
 use rig::tool::Tool;
@@ -21,9 +22,11 @@ pub struct CronArgs {
     pub action: String,
     /// Job name (required for add)
     pub name: Option<String>,
-    /// Prompt/message to send when the job runs (required for add)
+    /// Prompt/message to send when the job runs (required for add unless report is set)
     pub message: Option<String>,
-    /// Schedule for add: cron expression, interval in seconds, or @-style cron
+    /// For add: make this a report job instead of a prompt, e.g. "weekly", "daily: tasks, usage" or "monthly expenses". Sections: tasks, expenses, habits, memory, usage
+    pub report: Option<String>,
+    /// Schedule for add: an English phrase ("every weekday at 8am", "in 2 hours", "first monday of the month at 9"), a 6-field cron expression with seconds, an interval in seconds, or @-style cron
     pub schedule: Option<String>,
     /// Delivery channel for add (e.g. "telegram")
     pub channel: Option<String>,
@@ -46,7 +49,7 @@ impl Tool for CronTool {
         async {
             ToolDefinition {
                 name: Self::NAME.to_string(),
-                description: "Manage scheduled tasks. Use action=add for new schedules, list to inspect jobs, remove to delete by id, status for scheduler summary. For add: use schedule as cron expression (e.g. '0 9 * * *'), seconds interval (e.g. '14400' for every 4h), or @-style cron. The message field is the inbound text injected when the job fires. Set channel/to to route the cron turn to a destination context (typically current channel/chat), then use send_message if that turn should notify the user.".to_string(),
+                description: "Manage scheduled tasks. Use action=add for new schedules, list to inspect jobs, remove to delete by id, status for scheduler summary. For add: prefer a plain English schedule such as 'every weekday at 8am', 'every monday and thursday at 18:30', 'in 2 hours', 'tomorrow at 9am' or 'first monday of the month'; it is parsed deterministically in the user's timezone. Raw cron expressions (with a leading seconds field, e.g. '0 0 9 * * *'), seconds intervals (e.g. '14400') and @-style cron also work. The message field is the inbound text injected when the job fires. For recurring summaries of tasks, expenses, habits, memory or usage, set report instead of message: it renders a report with exact numbers from the stored data into reports/ and sends it to channel/to. Set channel/to to route the cron turn to a destination context (typically current channel/chat), then use send_message if that turn should notify the user.".to_string(),
                 parameters: serde_json::to_value(schemars::schema_for!(CronArgs)).unwrap(),
             }
         }
@@ -65,17 +68,33 @@ impl Tool for CronTool {
                     let name = args
                         .name
                         .ok_or_else(|| ToolError::msg("Missing required field: name"))?;
-                    let message = args
-                        .message
-                        .ok_or_else(|| ToolError::msg("Missing required field: message"))?;
+                    let message = match args.report.as_deref().map(str::trim) {
+                        Some(report) => {
+                            ReportSpec::parse(report).map_err(|e| ToolError::msg(e.to_string()))?;
+                            format!("/report {report}")
+                        }
+                        None => args
+                            .message
+                            .ok_or_else(|| ToolError::msg("Missing required field: message"))?,
+                    };
                     let schedule = args
                         .schedule
                         .ok_or_else(|| ToolError::msg("Missing required field: schedule"))?;
-                    service
+                    let job = service
                         .add_job(name, schedule, message, args.channel, args.to)
                         .await
                         .map_err(|e| ToolError::msg(e.to_string()))?;
-                    Ok("Cron job added.".to_string())
+                    let next = job
+                        .state
+                        .next_run_at_ms
+                        .and_then(chrono::DateTime::<chrono::Utc>::from_timestamp_millis)
+                        .map(|dt| dt.to_rfc3339())
+                        .unwrap_or_else(|| "N/A".to_string());
+                    Ok(format!(
+                        "Cron job {} added ({}), next run: {next}.",
+                        job.id,
+                        job.schedule.describe()
+                    ))
                 }
                 "list" => {
                     let jobs = service
@@ -0,0 +1,188 @@
+use crate::expenses::{ExpenseStore, SummaryGroup};
+use crate::tools::ToolError;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use std::path::PathBuf;
+use tracing::warn;
+
+const DEFAULT_LIST_LIMIT: usize = 50;
+
+#[derive(Clone)]
+pub struct ExpenseTool {
+    store: Option<ExpenseStore>,
+    default_currency: String,
+}
+
+impl ExpenseTool {
+    pub fn new(workspace_dir: PathBuf, default_currency: String) -> Self {
+        let store = match ExpenseStore::new(workspace_dir.join("expenses.db")) {
+            Ok(store) => Some(store),
+            Err(err) => {
+                warn!("expense tracking disabled: failed to open store: {err}");
+                None
+            }
+        };
+        Self {
+            store,
+            default_currency,
+        }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct ExpenseArgs {
+    /// One of: add, list, summary, remove
+    pub action: String,
+    /// Amount spent (required for add)
+    pub amount: Option<f64>,
+    /// ISO currency code (defaults to the configured currency)
+    pub currency: Option<String>,
+    /// Category such as "groceries" or "transport" (required for add; filter for list)
+    pub category: Option<String>,
+    /// Optional free-form note for add
+    pub note: Option<String>,
+    /// Date of the expense as YYYY-MM-DD (defaults to today)
+    pub date: Option<String>,
+    /// Month filter as YYYY-MM for list/summary
+    pub month: Option<String>,
+    /// Summary grouping: "category" (default) or "month"
+    pub group_by: Option<String>,
+    /// Expense id (required for remove)
+    pub id: Option<i64>,
+}
+
+impl Tool for ExpenseTool {
+    const NAME: &'static str = "track_expense";
+    type Args = ExpenseArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Track personal expenses. action=add records amount/category (optional currency, note, date); list shows recent entries (filter by month YYYY-MM and/or category); summary totals spending by category for a month, or by month when group_by=month; remove deletes an entry by id.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(ExpenseArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let store = self.store.clone();
+        let default_currency = self.default_currency.clone();
+        async move {
+            let Some(store) = store else {
+                return Ok("Error: expense store is unavailable".to_string());
+            };
+            let action = args.action.trim().to_lowercase();
+
+            match action.as_str() {
+                "add" => {
+                    let amount = args
+                        .amount
+                        .ok_or_else(|| ToolError::msg("Missing required field: amount"))?;
+                    let category = args
+                        .category
+                        .ok_or_else(|| ToolError::msg("Missing required field: category"))?;
+                    let currency = args
+                        .currency
+                        .filter(|c| !c.trim().is_empty())
+                        .unwrap_or(default_currency);
+                    let date = args
+                        .date
+                        .filter(|d| !d.trim().is_empty())
+                        .unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+                    let expense = store
+                        .add(amount, &currency, &category, args.note, &date)
+                        .await
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    Ok(format!(
+                        "Recorded expense #{}: {:.2} {} ({}) on {}.",
+                        expense.id,
+                        expense.amount,
+                        expense.currency,
+                        expense.category,
+                        expense.date
+                    ))
+                }
+                "list" => {
+                    let entries = store
+                        .list(args.month, args.category, DEFAULT_LIST_LIMIT)
+                        .await
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    if entries.is_empty() {
+                        return Ok("No expenses found.".to_string());
+                    }
+                    let mut out = String::new();
+                    for e in entries {
+                        out.push_str(&format!(
+                            "#{} | {} | {:.2} {} | {}{}\n",
+                            e.id,
+                            e.date,
+                            e.amount,
+                            e.currency,
+                            e.category,
+                            e.note.map(|n| format!(" | {n}")).unwrap_or_default()
+                        ));
+                    }
+                    Ok(out)
+                }
+                "summary" => {
+                    let group = match args.group_by.as_deref().map(str::trim) {
+                        Some("month") => SummaryGroup::Month,
+                        _ => SummaryGroup::Category,
+                    };
+                    let month =
+                        match group {
+                            SummaryGroup::Category => Some(args.month.unwrap_or_else(|| {
+                                chrono::Local::now().format("%Y-%m").to_string()
+                            })),
+                            SummaryGroup::Month => args.month,
+                        };
+                    let totals = store
+                        .totals(group, month.clone())
+                        .await
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    if totals.is_empty() {
+                        return Ok("No expenses found.".to_string());
+                    }
+                    let mut out = match (&group, &month) {
+                        (SummaryGroup::Category, Some(m)) => {
+                            format!("Spending by category for {m}:\n")
+                        }
+                        _ => "Spending by month:\n".to_string(),
+                    };
+                    for t in totals {
+                        out.push_str(&format!(
+                            "{}: {:.2} {} ({} entries)\n",
+                            t.group, t.total, t.currency, t.count
+                        ));
+                    }
+                    Ok(out)
+                }
+                "remove" => {
+                    let id = args
+                        .id
+                        .ok_or_else(|| ToolError::msg("Missing required field: id"))?;
+                    let removed = store
+                        .remove(id)
+                        .await
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    if removed {
+                        Ok("Expense removed.".to_string())
+                    } else {
+                        Ok("Expense not found.".to_string())
+                    }
+                }
+                _ => Ok("Invalid action. Use: add, list, summary, remove.".to_string()),
+            }
+        }
+    }
+}
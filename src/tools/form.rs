@@ -0,0 +1,349 @@
+use crate::bus::{MessageBus, OutboundMessage};
+use crate::tools::ToolError;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+
+/// Invalid answers tolerated per field before the form is abandoned.
+const MAX_ATTEMPTS: usize = 3;
+
+/// Routes chat replies to forms waiting for input. While a form is open in a
+/// chat, the agent loop hands that chat's messages here instead of starting
+/// a new turn.
+#[derive(Clone, Default)]
+pub struct FormBroker {
+    open: Arc<Mutex<HashMap<String, mpsc::UnboundedSender<String>>>>,
+}
+
+impl FormBroker {
+    /// Deliver `text` to the form open in `channel:chat_id`. Returns false
+    /// when no form is waiting there.
+    pub async fn answer(&self, channel: &str, chat_id: &str, text: &str) -> bool {
+        let key = format!("{channel}:{chat_id}");
+        let mut open = self.open.lock().await;
+        match open.get(&key) {
+            Some(tx) if tx.send(text.to_string()).is_ok() => true,
+            Some(_) => {
+                open.remove(&key);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Send `question` to a chat and wait for the next reply there. `None`
+    /// if another form is open in that chat or nobody answered in time.
+    pub async fn ask(
+        &self,
+        bus: &MessageBus,
+        channel: &str,
+        chat_id: &str,
+        question: &str,
+        timeout: Duration,
+    ) -> Option<String> {
+        let key = format!("{channel}:{chat_id}");
+        let mut replies = self.open(&key).await?;
+        bus.publish_outbound(OutboundMessage {
+            channel: channel.to_string(),
+            chat_id: chat_id.to_string(),
+            content: question.to_string(),
+            ..Default::default()
+        })
+        .await;
+        let answer = tokio::time::timeout(timeout, replies.recv())
+            .await
+            .ok()
+            .flatten();
+        self.close(&key).await;
+        answer
+    }
+
+    async fn open(&self, key: &str) -> Option<mpsc::UnboundedReceiver<String>> {
+        let mut open = self.open.lock().await;
+        if open.get(key).is_some_and(|tx| !tx.is_closed()) {
+            return None;
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        open.insert(key.to_string(), tx);
+        Some(rx)
+    }
+
+    async fn close(&self, key: &str) {
+        self.open.lock().await.remove(key);
+    }
+}
+
+#[derive(Clone)]
+pub struct AskFormTool {
+    bus: MessageBus,
+    broker: FormBroker,
+    /// Time the user has to finish the whole form.
+    timeout: Duration,
+}
+
+impl AskFormTool {
+    pub fn new(bus: MessageBus, broker: FormBroker, timeout_secs: u64) -> Self {
+        Self {
+            bus,
+            broker,
+            timeout: Duration::from_secs(timeout_secs.max(10)),
+        }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct FormField {
+    /// Key of the value in the returned object (e.g. "amount")
+    pub name: String,
+    /// Question shown to the user (e.g. "How much was it?")
+    pub prompt: String,
+    /// "text" (default), "number", "boolean", "date" (YYYY-MM-DD) or "choice"
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// Allowed answers for "choice" fields
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// Set to false to let the user skip the field
+    pub required: Option<bool>,
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct AskFormArgs {
+    /// Channel of the chat to ask in (e.g. "telegram")
+    pub channel: String,
+    /// Chat id to ask in
+    pub chat_id: String,
+    /// Short intro sent before the first question
+    pub title: Option<String>,
+    /// Fields to collect, asked one at a time in this order
+    pub fields: Vec<FormField>,
+}
+
+impl Tool for AskFormTool {
+    const NAME: &'static str = "ask_form";
+    type Args = AskFormArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Collect several values from the user in the current chat by asking one question per field and validating each answer. Returns a JSON object {status, values}. Use this when an action needs multiple parameters the user has not given, instead of asking for everything in one free-form message. status is \"cancelled\" or \"timeout\" when the user did not finish the form.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(AskFormArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let tool = self.clone();
+        async move {
+            let channel = args.channel.trim().to_string();
+            let chat_id = args.chat_id.trim().to_string();
+            if channel.is_empty() {
+                return Err(ToolError::msg("Missing required field: channel"));
+            }
+            if chat_id.is_empty() {
+                return Err(ToolError::msg("Missing required field: chat_id"));
+            }
+            if args.fields.is_empty() {
+                return Err(ToolError::msg("Missing required field: fields"));
+            }
+            for field in &args.fields {
+                if field.name.trim().is_empty() || field.prompt.trim().is_empty() {
+                    return Err(ToolError::msg("Every field needs a name and a prompt"));
+                }
+                let kind = field.kind.as_deref().unwrap_or("text");
+                if !matches!(kind, "text" | "number" | "boolean" | "date" | "choice") {
+                    return Err(ToolError::msg(format!(
+                        "Invalid type '{kind}' for field '{}'. Use: text, number, boolean, date, choice",
+                        field.name
+                    )));
+                }
+                if kind == "choice" && field.options.is_empty() {
+                    return Err(ToolError::msg(format!(
+                        "Field '{}' has type choice but no options",
+                        field.name
+                    )));
+                }
+            }
+
+            let key = format!("{channel}:{chat_id}");
+            let Some(mut replies) = tool.broker.open(&key).await else {
+                return Err(ToolError::msg("Another form is already open in this chat"));
+            };
+            let result = tool.run_form(&channel, &chat_id, &args, &mut replies).await;
+            tool.broker.close(&key).await;
+            Ok(result.to_string())
+        }
+    }
+}
+
+impl AskFormTool {
+    async fn run_form(
+        &self,
+        channel: &str,
+        chat_id: &str,
+        args: &AskFormArgs,
+        replies: &mut mpsc::UnboundedReceiver<String>,
+    ) -> Value {
+        let send = |content: String| {
+            self.bus.publish_outbound(OutboundMessage {
+                channel: channel.to_string(),
+                chat_id: chat_id.to_string(),
+                content,
+                ..Default::default()
+            })
+        };
+        if let Some(title) = args.title.as_deref().filter(|t| !t.trim().is_empty()) {
+            send(format!(
+                "{}\n(Reply \"cancel\" at any time to stop.)",
+                title.trim()
+            ))
+            .await;
+        }
+
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        let mut values = Map::new();
+        let total = args.fields.len();
+        for (idx, field) in args.fields.iter().enumerate() {
+            let mut question = format!("({}/{total}) {}", idx + 1, field.prompt.trim());
+            if let Some(hint) = field_hint(field) {
+                question.push_str(&format!(" [{hint}]"));
+            }
+            let mut attempts = 0;
+            let value = loop {
+                send(question.clone()).await;
+                let answer = match tokio::time::timeout_at(deadline, replies.recv()).await {
+                    Ok(Some(answer)) => answer,
+                    Ok(None) | Err(_) => {
+                        send("The form timed out.".to_string()).await;
+                        return json!({ "status": "timeout", "values": values });
+                    }
+                };
+                let answer = answer.trim();
+                if answer.eq_ignore_ascii_case("cancel") {
+                    send("Okay, form cancelled.".to_string()).await;
+                    return json!({ "status": "cancelled", "values": values });
+                }
+                match parse_answer(field, answer) {
+                    Ok(value) => break value,
+                    Err(problem) => {
+                        attempts += 1;
+                        if attempts >= MAX_ATTEMPTS {
+                            send("Too many invalid answers; form cancelled.".to_string()).await;
+                            return json!({
+                                "status": "cancelled",
+                                "values": values,
+                                "error": format!("invalid answer for {}: {problem}", field.name),
+                            });
+                        }
+                        question = format!("{problem} {}", field.prompt.trim());
+                    }
+                }
+            };
+            values.insert(field.name.trim().to_string(), value);
+        }
+        json!({ "status": "completed", "values": values })
+    }
+}
+
+fn field_hint(field: &FormField) -> Option<String> {
+    let mut hints = Vec::new();
+    match field.kind.as_deref().unwrap_or("text") {
+        "number" => hints.push("number".to_string()),
+        "boolean" => hints.push("yes/no".to_string()),
+        "date" => hints.push("YYYY-MM-DD".to_string()),
+        "choice" => hints.push(field.options.join(" / ")),
+        _ => {}
+    }
+    if field.required == Some(false) {
+        hints.push("or \"skip\"".to_string());
+    }
+    (!hints.is_empty()).then(|| hints.join(", "))
+}
+
+/// Validate one answer against its field, returning the JSON value or a
+/// short explanation to show before asking again.
+fn parse_answer(field: &FormField, answer: &str) -> Result<Value, String> {
+    if field.required == Some(false) && (answer.is_empty() || answer.eq_ignore_ascii_case("skip")) {
+        return Ok(Value::Null);
+    }
+    if answer.is_empty() {
+        return Err("An answer is required.".to_string());
+    }
+    match field.kind.as_deref().unwrap_or("text") {
+        "number" => answer
+            .replace(',', ".")
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .map(|n| json!(n))
+            .ok_or_else(|| "Please reply with a number.".to_string()),
+        "boolean" => match answer.to_lowercase().as_str() {
+            "yes" | "y" | "true" | "1" | "ok" => Ok(json!(true)),
+            "no" | "n" | "false" | "0" => Ok(json!(false)),
+            _ => Err("Please reply yes or no.".to_string()),
+        },
+        "date" => chrono::NaiveDate::parse_from_str(answer, "%Y-%m-%d")
+            .map(|d| json!(d.to_string()))
+            .map_err(|_| "Please reply with a date like 2025-06-01.".to_string()),
+        "choice" => field
+            .options
+            .iter()
+            .find(|o| o.eq_ignore_ascii_case(answer))
+            .map(|o| json!(o))
+            .ok_or_else(|| format!("Please pick one of: {}.", field.options.join(", "))),
+        _ => Ok(json!(answer)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_answer, FormField};
+    use serde_json::json;
+
+    fn field(kind: &str, required: Option<bool>) -> FormField {
+        FormField {
+            name: "f".to_string(),
+            prompt: "?".to_string(),
+            kind: Some(kind.to_string()),
+            options: vec!["Small".to_string(), "Large".to_string()],
+            required,
+        }
+    }
+
+    #[test]
+    fn validates_answers_by_type() {
+        assert_eq!(
+            parse_answer(&field("number", None), "12,5"),
+            Ok(json!(12.5))
+        );
+        assert!(parse_answer(&field("number", None), "twelve").is_err());
+        assert_eq!(
+            parse_answer(&field("boolean", None), "Yes"),
+            Ok(json!(true))
+        );
+        assert_eq!(
+            parse_answer(&field("choice", None), "large"),
+            Ok(json!("Large"))
+        );
+        assert!(parse_answer(&field("date", None), "tomorrow").is_err());
+        assert_eq!(
+            parse_answer(&field("text", Some(false)), "skip"),
+            Ok(serde_json::Value::Null)
+        );
+        assert!(parse_answer(&field("text", None), "").is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::tools::sandbox;
 use crate::tools::ToolError;
 use rig::completion::request::ToolDefinition;
 use rig::tool::Tool;
@@ -18,39 +19,90 @@ fn resolve_path(
     path: &str,
     allowed_dir: Option<&Path>,
     allow_missing: bool,
+    write: bool,
 ) -> Result<PathBuf, String> {
     let expanded = expand_path(path);
+    let sandbox = sandbox::current();
+    let base = match &sandbox {
+        Some(sb) => {
+            std::fs::create_dir_all(&sb.root)
+                .map_err(|e| format!("failed to create chat directory: {e}"))?;
+            sb.root.clone()
+        }
+        None => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+    };
     let abs = if expanded.is_absolute() {
         expanded
     } else {
-        std::env::current_dir()
-            .unwrap_or_else(|_| PathBuf::from("."))
-            .join(expanded)
+        base.join(expanded)
     };
 
-    let resolved = if allow_missing {
-        if abs.exists() {
-            abs.canonicalize().map_err(|e| e.to_string())?
-        } else {
-            abs
-        }
+    let resolved = if allow_missing && !abs.exists() {
+        resolve_missing(&abs)?
     } else {
         abs.canonicalize().map_err(|e| e.to_string())?
     };
 
-    if let Some(allowed) = allowed_dir {
-        let allowed = allowed
+    let allowed: Vec<&Path> = match &sandbox {
+        Some(sb) => sb.allowed_dirs(write),
+        None => allowed_dir.into_iter().collect(),
+    };
+    if allowed.is_empty() {
+        return Ok(resolved);
+    }
+    for dir in &allowed {
+        let dir = dir
             .canonicalize()
             .map_err(|e| format!("failed to resolve allowed dir: {e}"))?;
-        if !resolved.starts_with(&allowed) {
-            return Err(format!(
-                "path {} is outside allowed directory {}",
-                resolved.display(),
-                allowed.display()
-            ));
+        if resolved.starts_with(&dir) {
+            return Ok(resolved);
         }
     }
+    let allowed = allowed
+        .iter()
+        .map(|d| d.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(match &sandbox {
+        Some(_) if write => format!(
+            "path {} is outside this chat's directory {allowed}",
+            resolved.display()
+        ),
+        Some(_) => format!(
+            "path {} is outside this chat's directory and shared areas ({allowed})",
+            resolved.display()
+        ),
+        None => format!(
+            "path {} is outside allowed directory {allowed}",
+            resolved.display()
+        ),
+    })
+}
+
+/// An existing file the current turn may read, resolved the way `read_file`
+/// resolves paths.
+pub(crate) fn resolve_readable(path: &str, allowed_dir: Option<&Path>) -> Result<PathBuf, String> {
+    resolve_path(path, allowed_dir, false, false)
+}
 
+/// Canonicalize the nearest existing ancestor of a path that does not exist
+/// yet, so `..` segments cannot slip past the allowed-dir check.
+fn resolve_missing(abs: &Path) -> Result<PathBuf, String> {
+    let mut existing = abs;
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        let name = existing
+            .file_name()
+            .ok_or_else(|| format!("invalid path {}", abs.display()))?;
+        rest.push(name);
+        existing = existing
+            .parent()
+            .ok_or_else(|| format!("invalid path {}", abs.display()))?;
+    }
+    let mut resolved = existing.canonicalize().map_err(|e| e.to_string())?;
+    for name in rest.into_iter().rev() {
+        resolved.push(name);
+    }
     Ok(resolved)
 }
 
@@ -69,6 +121,10 @@ impl ReadFileTool {
 pub struct ReadFileArgs {
     /// The file path to read
     pub path: String,
+    /// Optional 1-based line to start reading from
+    pub offset: Option<usize>,
+    /// Optional maximum number of lines to return
+    pub limit: Option<usize>,
 }
 
 impl Tool for ReadFileTool {
@@ -84,7 +140,7 @@ impl Tool for ReadFileTool {
         async {
             ToolDefinition {
                 name: Self::NAME.to_string(),
-                description: "Read the contents of a file at the given path.".to_string(),
+                description: "Read the contents of a file at the given path. Use offset/limit (lines) to read part of a large file.".to_string(),
                 parameters: serde_json::to_value(schemars::schema_for!(ReadFileArgs)).unwrap(),
             }
         }
@@ -95,7 +151,7 @@ impl Tool for ReadFileTool {
         args: Self::Args,
     ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
         async move {
-            let path = resolve_path(&args.path, self.allowed_dir.as_deref(), false)
+            let path = resolve_path(&args.path, self.allowed_dir.as_deref(), false, false)
                 .map_err(ToolError::msg)?;
             if !path.exists() {
                 return Ok(format!("Error: File not found: {}", args.path));
@@ -104,7 +160,16 @@ impl Tool for ReadFileTool {
                 return Ok(format!("Error: Not a file: {}", args.path));
             }
             match std::fs::read_to_string(&path) {
-                Ok(content) => Ok(content),
+                Ok(content) if args.offset.is_none() && args.limit.is_none() => Ok(content),
+                Ok(content) => {
+                    let start = args.offset.unwrap_or(1).max(1) - 1;
+                    let lines: Vec<&str> = content
+                        .lines()
+                        .skip(start)
+                        .take(args.limit.unwrap_or(usize::MAX))
+                        .collect();
+                    Ok(lines.join("\n"))
+                }
                 Err(e) => Ok(format!("Error reading file: {e}")),
             }
         }
@@ -154,7 +219,7 @@ impl Tool for WriteFileTool {
         args: Self::Args,
     ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
         async move {
-            let path = resolve_path(&args.path, self.allowed_dir.as_deref(), true)
+            let path = resolve_path(&args.path, self.allowed_dir.as_deref(), true, true)
                 .map_err(ToolError::msg)?;
             if let Some(parent) = path.parent() {
                 if let Err(e) = std::fs::create_dir_all(parent) {
@@ -218,7 +283,7 @@ impl Tool for EditFileTool {
         args: Self::Args,
     ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
         async move {
-            let path = resolve_path(&args.path, self.allowed_dir.as_deref(), false)
+            let path = resolve_path(&args.path, self.allowed_dir.as_deref(), false, true)
                 .map_err(ToolError::msg)?;
             if !path.exists() {
                 return Ok(format!("Error: File not found: {}", args.path));
@@ -288,7 +353,7 @@ impl Tool for ListDirTool {
         args: Self::Args,
     ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
         async move {
-            let path = resolve_path(&args.path, self.allowed_dir.as_deref(), false)
+            let path = resolve_path(&args.path, self.allowed_dir.as_deref(), false, false)
                 .map_err(ToolError::msg)?;
             if !path.exists() {
                 return Ok(format!("Error: Directory not found: {}", args.path));
@@ -315,3 +380,29 @@ impl Tool for ListDirTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_path;
+    use crate::tools::sandbox::{scoped, ChatSandbox};
+
+    #[tokio::test]
+    async fn sandbox_confines_writes_to_chat_dir() {
+        let workspace =
+            std::env::temp_dir().join(format!("femtobot-sandbox-{}", std::process::id()));
+        let shared = workspace.join("shared");
+        std::fs::create_dir_all(&shared).unwrap();
+        let sandbox = ChatSandbox {
+            root: workspace.join("chats").join("42"),
+            shared_readonly: vec![shared.clone()],
+        };
+        scoped(Some(sandbox), async {
+            assert!(resolve_path("notes/todo.md", None, true, true).is_ok());
+            assert!(resolve_path("../../escape.txt", None, true, true).is_err());
+            assert!(resolve_path(shared.to_str().unwrap(), None, false, false).is_ok());
+            assert!(resolve_path(shared.to_str().unwrap(), None, true, true).is_err());
+        })
+        .await;
+        let _ = std::fs::remove_dir_all(workspace);
+    }
+}
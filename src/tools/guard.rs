@@ -0,0 +1,397 @@
+use crate::config::{AppConfig, SchemaDialect};
+use crate::error::ErrorKind;
+use crate::history::sanitize_file_stem;
+use crate::tools::middleware::{self, ToolHooks};
+use crate::tools::sandbox;
+use crate::tools::schema;
+use crate::tools::transcript;
+use crate::tools::ToolError;
+use chrono::Local;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tracing::warn;
+
+/// Artifacts older than this are pruned whenever a new one is written.
+const ARTIFACT_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Execution limits applied to every registered tool.
+#[derive(Clone, Debug)]
+pub struct ToolLimits {
+    pub timeout: Duration,
+    pub max_output_chars: usize,
+    /// Where full copies of oversized outputs are stored, one directory per
+    /// chat; sandboxed chats keep theirs in the sandbox instead.
+    pub artifacts_dir: PathBuf,
+    /// Identical calls allowed per turn before further ones are refused.
+    pub max_repeats: usize,
+}
+
+impl ToolLimits {
+    /// Resolve limits for `name`: per-tool overrides first, then the global
+    /// defaults. `exec` (under its slowest profile) and `ask_form` always get
+    /// a little longer than their own timeouts so those tools can report the
+    /// timeout themselves.
+    pub fn for_tool(cfg: &AppConfig, name: &str) -> Self {
+        let mut timeout_secs = cfg
+            .tool_timeouts
+            .get(name)
+            .copied()
+            .unwrap_or(cfg.tool_timeout_secs);
+        if name == "exec" {
+            timeout_secs = timeout_secs.max(cfg.exec_longest_secs() + 5);
+        }
+        if name == "ask_form" {
+            timeout_secs = timeout_secs.max(cfg.form_timeout_secs + 5);
+        }
+        let max_output_chars = cfg
+            .tool_output_limits
+            .get(name)
+            .copied()
+            .unwrap_or(cfg.tool_output_max_chars);
+        Self {
+            timeout: Duration::from_secs(timeout_secs.max(1)),
+            max_output_chars: max_output_chars.max(1),
+            artifacts_dir: cfg.workspace_dir.join("artifacts"),
+            max_repeats: cfg.max_repeated_tool_calls,
+        }
+    }
+}
+
+/// Counts of tool calls whose arguments failed to deserialize, keyed by
+/// (tool, model), shared by every runtime agent.
+#[derive(Clone, Default)]
+pub struct SchemaFailures {
+    counts: Arc<Mutex<HashMap<(String, String), u64>>>,
+}
+
+impl SchemaFailures {
+    fn record(&self, tool: &str, model: &str) -> u64 {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        let count = counts
+            .entry((tool.to_string(), model.to_string()))
+            .or_insert(0);
+        *count += 1;
+        *count
+    }
+
+    /// `(tool, model, failures)` sorted by most failures first.
+    pub fn snapshot(&self) -> Vec<(String, String, u64)> {
+        let counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        let mut rows: Vec<_> = counts
+            .iter()
+            .map(|((tool, model), count)| (tool.clone(), model.clone(), *count))
+            .collect();
+        rows.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        rows
+    }
+}
+
+/// Wraps a tool with argument validation, configured middleware hooks, a
+/// timeout and an output budget so a slow, chatty or mis-called tool cannot
+/// stall or flood a whole turn.
+#[derive(Clone)]
+pub struct Guarded<T> {
+    inner: T,
+    /// The tool's name; config-defined tools share one type but not a name.
+    name: String,
+    limits: ToolLimits,
+    hooks: ToolHooks,
+    model: String,
+    failures: SchemaFailures,
+    dialect: SchemaDialect,
+}
+
+pub fn guarded<T: Tool>(tool: T, cfg: &AppConfig) -> Guarded<T> {
+    let name = tool.name();
+    Guarded {
+        limits: ToolLimits::for_tool(cfg, &name),
+        hooks: ToolHooks::for_tool(cfg, &name),
+        inner: tool,
+        name,
+        model: String::new(),
+        failures: SchemaFailures::default(),
+        dialect: SchemaDialect::default(),
+    }
+}
+
+impl<T: Clone> Guarded<T> {
+    /// Copy of this tool that attributes schema failures to `model` and
+    /// describes itself in `dialect`.
+    pub fn for_model(
+        &self,
+        model: &str,
+        failures: &SchemaFailures,
+        dialect: SchemaDialect,
+    ) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            name: self.name.clone(),
+            limits: self.limits.clone(),
+            hooks: self.hooks.clone(),
+            model: model.to_string(),
+            failures: failures.clone(),
+            dialect,
+        }
+    }
+}
+
+impl<T> Tool for Guarded<T>
+where
+    T: Tool<Output = String, Error = ToolError>,
+{
+    const NAME: &'static str = T::NAME;
+    // Arguments are validated here rather than by rig so the model gets the
+    // schema and the exact failure back instead of a bare JSON error.
+    type Args = Value;
+    type Output = String;
+    type Error = ToolError;
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn definition(
+        &self,
+        prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send + Sync {
+        let definition = self.inner.definition(prompt);
+        let dialect = self.dialect;
+        async move { schema::adapt(definition.await, dialect) }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let limits = self.limits.clone();
+        let hooks = self.hooks.clone();
+        let inner = &self.inner;
+        let model = self.model.clone();
+        let failures = self.failures.clone();
+        let dialect = self.dialect;
+        let name = self.name.clone();
+        async move {
+            let name = name.as_str();
+            let recorded_args = args.clone();
+            let transcript = (name != transcript::RecentToolResultsTool::NAME)
+                .then(middleware::current_chat)
+                .flatten()
+                .map(|chat| chat.transcript);
+            let result = async {
+                let mut args = args;
+                if let Some(reason) = middleware::withheld(name) {
+                    return Err(ToolError::new(ErrorKind::Refused, reason));
+                }
+                if let Some(transcript) = &transcript {
+                    if let Err(reason) = transcript.check_loop(name, &args, limits.max_repeats) {
+                        warn!("tool {name} refused as a loop (model={model})");
+                        return Err(ToolError::new(ErrorKind::Refused, reason));
+                    }
+                }
+                if let Err(reason) = middleware::resolve_current_target(name, &mut args) {
+                    return Err(ToolError::new(ErrorKind::InvalidArgs, reason));
+                }
+                if let Err(reason) = hooks.before(name, &mut args).await {
+                    return Err(ToolError::new(ErrorKind::Refused, reason));
+                }
+                let args = match serde_json::from_value::<T::Args>(args) {
+                    Ok(args) => args,
+                    Err(err) => {
+                        let count = failures.record(name, &model);
+                        warn!(
+                            "invalid arguments for tool {} (model={}, failures={}): {err}",
+                            name,
+                            model,
+                            count
+                        );
+                        let schema =
+                            schema::adapt(inner.definition(String::new()).await, dialect).parameters;
+                        return Err(ToolError::new(ErrorKind::InvalidArgs, format!(
+                            "invalid arguments for {}: {err}. Expected arguments matching this JSON schema: {}. Fix the arguments and call {} again.",
+                            name,
+                            schema,
+                            name
+                        )));
+                    }
+                };
+                match tokio::time::timeout(limits.timeout, inner.call(args)).await {
+                    Ok(Ok(output)) => Ok(limit_output(name, hooks.after(name, output), &limits)),
+                    Ok(Err(err)) => Err(ToolError::new(
+                        err.kind(),
+                        format!("{} failed: {err}", name),
+                    )),
+                    Err(_) => {
+                        warn!(
+                            "tool {} timed out after {}s",
+                            name,
+                            limits.timeout.as_secs()
+                        );
+                        Err(ToolError::new(ErrorKind::Timeout, format!(
+                            "{} timed out after {}s",
+                            name,
+                            limits.timeout.as_secs()
+                        )))
+                    }
+                }
+            }
+            .await;
+            if let Some(transcript) = &transcript {
+                let outcome = result.as_ref().map(String::as_str);
+                transcript.record(name, &recorded_args, outcome);
+            }
+            result
+        }
+    }
+}
+
+/// Return `output` unchanged if it fits the budget. Otherwise persist the
+/// full text as an artifact of the current chat and return a preview with
+/// its path, so later turns can read specific parts with read_file
+/// (offset/limit).
+fn limit_output(tool: &str, output: String, limits: &ToolLimits) -> String {
+    let total = output.chars().count();
+    if total <= limits.max_output_chars {
+        return output;
+    }
+    match write_artifact(&chat_artifacts_dir(&limits.artifacts_dir), tool, &output) {
+        Ok(path) => {
+            let preview: String = output.chars().take(limits.max_output_chars).collect();
+            format!(
+                "{preview}\n... (output truncated: showing {} of {} chars, {} lines total. Full output saved to {}; use read_file with offset/limit to view the rest.)",
+                limits.max_output_chars,
+                total,
+                output.lines().count(),
+                path.display()
+            )
+        }
+        Err(err) => {
+            warn!("failed to persist {tool} output artifact: {err}");
+            truncate_output(output, limits.max_output_chars)
+        }
+    }
+}
+
+/// The sandbox's `artifacts/` for a sandboxed turn, so its file tools can
+/// read the output back; otherwise the chat's own directory below `base`.
+/// Calls outside any chat turn use `base` itself.
+fn chat_artifacts_dir(base: &Path) -> PathBuf {
+    if let Some(sandbox) = sandbox::current() {
+        return sandbox.root.join("artifacts");
+    }
+    match middleware::current_chat() {
+        Some(turn) => base.join(sanitize_file_stem(&turn.session)),
+        None => base.to_path_buf(),
+    }
+}
+
+fn write_artifact(dir: &Path, tool: &str, output: &str) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    prune_artifacts(dir);
+    let path = dir.join(format!(
+        "{}-{}-{}.txt",
+        Local::now().format("%Y%m%d-%H%M%S"),
+        tool,
+        &uuid::Uuid::new_v4().to_string()[..8]
+    ));
+    fs::write(&path, output)?;
+    Ok(path)
+}
+
+fn prune_artifacts(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.filter_map(Result::ok) {
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > ARTIFACT_RETENTION);
+        if expired {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn truncate_output(output: String, max_chars: usize) -> String {
+    let total = output.chars().count();
+    if total <= max_chars {
+        return output;
+    }
+    let kept: String = output.chars().take(max_chars).collect();
+    format!(
+        "{kept}\n... (output truncated, {} more chars)",
+        total - max_chars
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{limit_output, truncate_output, ToolLimits};
+    use crate::tools::sandbox::{self, ChatSandbox};
+    use std::time::Duration;
+
+    #[test]
+    fn truncates_on_char_boundaries() {
+        assert_eq!(truncate_output("short".to_string(), 10), "short");
+        let out = truncate_output("ééééé".to_string(), 2);
+        assert!(out.starts_with("éé\n"));
+        assert!(out.ends_with("3 more chars)"));
+    }
+
+    #[test]
+    fn oversized_output_is_saved_as_artifact() {
+        let dir = std::env::temp_dir().join(format!("femtobot-artifacts-{}", std::process::id()));
+        let limits = ToolLimits {
+            timeout: Duration::from_secs(1),
+            max_output_chars: 5,
+            artifacts_dir: dir.clone(),
+            max_repeats: 3,
+        };
+        let out = limit_output("exec", "line one\nline two".to_string(), &limits);
+        assert!(out.starts_with("line "));
+        let path = out
+            .split("saved to ")
+            .nth(1)
+            .and_then(|rest| rest.split(';').next())
+            .expect("artifact path");
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "line one\nline two");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn sandboxed_artifacts_stay_in_the_chat_root() {
+        let dir = std::env::temp_dir().join(format!("femtobot-artifacts-{}", uuid::Uuid::new_v4()));
+        let root = dir.join("chats").join("7");
+        let limits = ToolLimits {
+            timeout: Duration::from_secs(1),
+            max_output_chars: 5,
+            artifacts_dir: dir.join("artifacts"),
+            max_repeats: 3,
+        };
+        let sandbox = ChatSandbox {
+            root: root.clone(),
+            shared_readonly: Vec::new(),
+        };
+        let out = sandbox::scoped(Some(sandbox), async {
+            limit_output("exec", "line one\nline two".to_string(), &limits)
+        })
+        .await;
+        let path = out
+            .split("saved to ")
+            .nth(1)
+            .and_then(|rest| rest.split(';').next())
+            .expect("artifact path");
+        assert!(std::path::Path::new(path).starts_with(root.join("artifacts")));
+        assert!(!dir.join("artifacts").exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
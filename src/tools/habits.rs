@@ -0,0 +1,270 @@
+use crate::cron::types::CronSchedule;
+use crate::cron::CronService;
+use crate::habits::{Habit, HabitStore};
+use crate::tools::ToolError;
+use chrono::{NaiveDate, NaiveTime, Timelike, Utc};
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+const DEFAULT_NAG_TIME: &str = "21:00";
+
+#[derive(Clone)]
+pub struct HabitsTool {
+    store: Arc<Mutex<HabitStore>>,
+    cron: CronService,
+}
+
+impl HabitsTool {
+    pub fn new(workspace_dir: PathBuf, cron: CronService) -> Self {
+        Self {
+            store: Arc::new(Mutex::new(HabitStore::new(workspace_dir))),
+            cron,
+        }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct HabitsArgs {
+    /// One of: define, check_in, status, list, remove, unchecked, nag
+    pub action: String,
+    /// Habit name (required for define, check_in, remove; optional filter for status)
+    pub name: Option<String>,
+    /// Optional description for define
+    pub description: Option<String>,
+    /// Date for check_in as YYYY-MM-DD (defaults to today)
+    pub date: Option<String>,
+    /// Time of day for nag as HH:MM in the user's timezone (defaults to 21:00)
+    pub time: Option<String>,
+    /// Channel to nag on (required for nag)
+    pub channel: Option<String>,
+    /// Chat id to nag (required for nag)
+    pub chat_id: Option<String>,
+}
+
+impl Tool for HabitsTool {
+    const NAME: &'static str = "habits";
+    type Args = HabitsArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Track habits and streaks. action=define creates a habit; check_in marks it done (today or date); status reports current/longest streak and 30-day completion rate; list shows habits; remove deletes one; unchecked lists habits not yet done today; nag schedules a daily cron check (default 21:00) that reminds channel/chat_id about unchecked habits.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(HabitsArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let store = self.store.clone();
+        let cron = self.cron.clone();
+        async move {
+            let action = args.action.trim().to_lowercase();
+            let today = Utc::now().with_timezone(&cron.timezone()).date_naive();
+
+            if action == "nag" {
+                return schedule_nag(&cron, args).await;
+            }
+
+            let mut store = store.lock().await;
+            store.load().map_err(|e| ToolError::msg(e.to_string()))?;
+
+            match action.as_str() {
+                "define" => {
+                    let name = args
+                        .name
+                        .ok_or_else(|| ToolError::msg("Missing required field: name"))?;
+                    let created = store
+                        .define(&name, args.description, today)
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    if created {
+                        Ok(format!("Habit '{}' defined.", name.trim()))
+                    } else {
+                        Ok(format!("Habit '{}' already exists.", name.trim()))
+                    }
+                }
+                "check_in" | "checkin" => {
+                    let name = args
+                        .name
+                        .ok_or_else(|| ToolError::msg("Missing required field: name"))?;
+                    let date = match args.date.as_deref().map(str::trim) {
+                        Some(raw) if !raw.is_empty() => NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                            .map_err(|_| ToolError::msg("date must be YYYY-MM-DD"))?,
+                        _ => today,
+                    };
+                    let inserted = store
+                        .check_in(&name, date)
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    let streak = store
+                        .find(&name)
+                        .map(|h| h.stats(today).current_streak)
+                        .unwrap_or(0);
+                    if inserted {
+                        Ok(format!(
+                            "Checked in '{}' for {}. Current streak: {} day(s).",
+                            name.trim(),
+                            date,
+                            streak
+                        ))
+                    } else {
+                        Ok(format!(
+                            "'{}' was already checked in for {}.",
+                            name.trim(),
+                            date
+                        ))
+                    }
+                }
+                "status" => {
+                    let habits: Vec<&Habit> = match &args.name {
+                        Some(name) => store.find(name).into_iter().collect(),
+                        None => store.habits.iter().collect(),
+                    };
+                    if habits.is_empty() {
+                        return Ok("No habits found.".to_string());
+                    }
+                    let mut out = String::new();
+                    for habit in habits {
+                        let stats = habit.stats(today);
+                        out.push_str(&format!(
+                            "{} | streak: {} (best {}) | {}/{} days ({:.0}%) | today: {}\n",
+                            habit.name,
+                            stats.current_streak,
+                            stats.longest_streak,
+                            stats.completed_in_window,
+                            stats.window_days,
+                            stats.completed_in_window as f64 * 100.0 / stats.window_days as f64,
+                            if stats.done_today { "done" } else { "pending" }
+                        ));
+                    }
+                    Ok(out)
+                }
+                "list" => {
+                    if store.habits.is_empty() {
+                        return Ok("No habits found.".to_string());
+                    }
+                    Ok(store
+                        .habits
+                        .iter()
+                        .map(|h| match &h.description {
+                            Some(d) => format!("{} - {}", h.name, d),
+                            None => h.name.clone(),
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"))
+                }
+                "remove" => {
+                    let name = args
+                        .name
+                        .ok_or_else(|| ToolError::msg("Missing required field: name"))?;
+                    let removed = store
+                        .remove(&name)
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    if removed {
+                        Ok("Habit removed.".to_string())
+                    } else {
+                        Ok("Habit not found.".to_string())
+                    }
+                }
+                "unchecked" => {
+                    let pending: Vec<String> = store
+                        .habits
+                        .iter()
+                        .filter(|h| !h.check_ins.contains(&today))
+                        .map(|h| h.name.clone())
+                        .collect();
+                    if pending.is_empty() {
+                        Ok("All habits are checked in for today.".to_string())
+                    } else {
+                        Ok(format!("Unchecked today: {}", pending.join(", ")))
+                    }
+                }
+                _ => Ok(
+                    "Invalid action. Use: define, check_in, status, list, remove, unchecked, nag."
+                        .to_string(),
+                ),
+            }
+        }
+    }
+}
+
+async fn schedule_nag(cron: &CronService, args: HabitsArgs) -> Result<String, ToolError> {
+    let channel = args
+        .channel
+        .filter(|c| !c.trim().is_empty())
+        .ok_or_else(|| ToolError::msg("Missing required field: channel"))?;
+    let chat_id = args
+        .chat_id
+        .filter(|c| !c.trim().is_empty())
+        .ok_or_else(|| ToolError::msg("Missing required field: chat_id"))?;
+    let raw_time = args.time.unwrap_or_else(|| DEFAULT_NAG_TIME.to_string());
+    let time = NaiveTime::parse_from_str(raw_time.trim(), "%H:%M")
+        .map_err(|_| ToolError::msg("time must be HH:MM"))?;
+
+    // Evaluated in the configured zone, so the nag keeps its wall-clock time
+    // across DST changes. The cron crate expects a leading seconds field.
+    let schedule = CronSchedule {
+        kind: "cron".to_string(),
+        at_ms: None,
+        every_ms: None,
+        expr: Some(format!("0 {} {} * * *", time.minute(), time.hour())),
+        tz: Some(cron.timezone().name().to_string()),
+    };
+    let message = "Daily habit check: call the habits tool with action=unchecked. If any habits remain unchecked, use send_message to send a short, friendly nudge listing them; otherwise do not send anything.".to_string();
+    cron.add_turn_job(
+        "habit nag".to_string(),
+        schedule,
+        message,
+        Some(channel.trim().to_string()),
+        Some(chat_id.trim().to_string()),
+    )
+    .await
+    .map_err(|e| ToolError::msg(e.to_string()))?;
+    Ok(format!(
+        "Daily habit nag scheduled at {}.",
+        time.format("%H:%M")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{schedule_nag, HabitsArgs};
+    use crate::bus::MessageBus;
+    use crate::config::AppConfig;
+    use crate::cron::CronService;
+
+    #[tokio::test]
+    async fn nag_keeps_its_time_in_the_configured_timezone() {
+        let mut cfg = AppConfig::defaults();
+        cfg.data_dir =
+            std::env::temp_dir().join(format!("femtobot-habits-{}", uuid::Uuid::new_v4()));
+        cfg.timezone = "America/New_York".to_string();
+        let cron = CronService::new(&cfg, MessageBus::new());
+        let args = HabitsArgs {
+            action: "nag".to_string(),
+            name: None,
+            description: None,
+            date: None,
+            time: Some("21:30".to_string()),
+            channel: Some("telegram".to_string()),
+            chat_id: Some("1".to_string()),
+        };
+        schedule_nag(&cron, args).await.unwrap();
+
+        let jobs = cron.list_jobs().await.unwrap();
+        assert_eq!(jobs[0].schedule.expr.as_deref(), Some("0 30 21 * * *"));
+        assert_eq!(jobs[0].schedule.tz.as_deref(), Some("America/New_York"));
+        let _ = std::fs::remove_dir_all(cfg.data_dir);
+    }
+}
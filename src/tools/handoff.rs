@@ -0,0 +1,212 @@
+use crate::bus::{MessageBus, OutboundMessage};
+use crate::config::AppConfig;
+use crate::error::ErrorKind;
+use crate::handoff::{HandoffMode, Handoffs};
+use crate::tools::middleware;
+use crate::tools::ToolError;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use tracing::info;
+
+#[derive(Clone)]
+pub struct ContinueOnTool {
+    cfg: AppConfig,
+    bus: MessageBus,
+    handoffs: Handoffs,
+}
+
+impl ContinueOnTool {
+    pub fn new(cfg: AppConfig, bus: MessageBus, handoffs: Handoffs) -> Self {
+        Self { cfg, bus, handoffs }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct ContinueOnArgs {
+    /// Channel to continue on (e.g. "webhook", "discord", "tui")
+    pub to_channel: String,
+    /// Chat id on that channel
+    pub to_chat_id: String,
+    /// "link" (default): both chats share this conversation from now on; "copy": the other chat starts from a copy of it
+    pub mode: Option<String>,
+    /// Optional message posted in the other chat, e.g. a recap of where things stand
+    pub note: Option<String>,
+}
+
+impl Tool for ContinueOnTool {
+    const NAME: &'static str = "continue_on";
+    type Args = ContinueOnArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Continue this conversation in another chat or channel (e.g. \"continue this on my desktop webhook session\"). link shares history and memories between both chats; copy gives the other chat a one-time copy. Only use chats the user named.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(ContinueOnArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let tool = self.clone();
+        async move {
+            let field = |value: &str, name: &str| {
+                let value = value.trim();
+                if value.is_empty() {
+                    Err(ToolError::msg(format!("Missing required field: {name}")))
+                } else {
+                    Ok(value.to_string())
+                }
+            };
+            // The conversation handed over is always the current one; a
+            // model-supplied source would let any chat pull in another's.
+            let Some(turn) = middleware::current_chat().filter(|turn| turn.channel != "cron")
+            else {
+                return Err(ToolError::new(
+                    ErrorKind::Refused,
+                    "continue_on only works in a chat turn.",
+                ));
+            };
+            let channel = turn.channel;
+            let to_channel = field(&args.to_channel, "to_channel")?;
+            let to_chat_id = field(&args.to_chat_id, "to_chat_id")?;
+            let Some(mode) = HandoffMode::parse(args.mode.as_deref().unwrap_or_default()) else {
+                return Err(ToolError::msg("Invalid mode. Use: link, copy."));
+            };
+            let source = format!("{channel}:{}", turn.chat_id);
+            let target = format!("{to_channel}:{to_chat_id}");
+            if !tool.cfg.is_admin(&turn.sender_id) {
+                tool.handoffs
+                    .request(&source, &target, mode)
+                    .await
+                    .map_err(|e| ToolError::msg(e.to_string()))?;
+                info!("handoff {source} -> {target} ({mode:?}) awaiting acceptance");
+                tool.bus
+                    .publish_outbound(OutboundMessage {
+                        channel: to_channel,
+                        chat_id: to_chat_id,
+                        content: format!(
+                            "A chat on {channel} asks to continue its conversation here ({}). Send /handoff accept within an hour to agree; ignore this otherwise.",
+                            match mode {
+                                HandoffMode::Link => "shared from now on",
+                                HandoffMode::Copy => "as a one-time copy",
+                            }
+                        ),
+                        ..Default::default()
+                    })
+                    .await;
+                return Ok(format!(
+                    "Asked {target} to confirm; the handoff happens once someone there sends /handoff accept."
+                ));
+            }
+            let session = tool
+                .handoffs
+                .start(&source, &target, mode)
+                .await
+                .map_err(|e| ToolError::msg(e.to_string()))?;
+            info!("handoff {source} -> {target} ({mode:?}, session {session})");
+
+            let mut greeting = match mode {
+                HandoffMode::Link => format!(
+                    "Continuing our conversation from {channel}. Both chats now share it; send /handoff off here to detach."
+                ),
+                HandoffMode::Copy => format!(
+                    "Picking up a copy of our conversation from {channel}; it will be loaded with your next message."
+                ),
+            };
+            if let Some(note) = args
+                .note
+                .as_deref()
+                .map(str::trim)
+                .filter(|n| !n.is_empty())
+            {
+                greeting.push_str(&format!("\n\n{note}"));
+            }
+            tool.bus
+                .publish_outbound(OutboundMessage {
+                    channel: to_channel,
+                    chat_id: to_chat_id,
+                    content: greeting,
+                    ..Default::default()
+                })
+                .await;
+            Ok(match mode {
+                HandoffMode::Link => format!("Linked {target} to this conversation."),
+                HandoffMode::Copy => {
+                    format!("{target} will continue from a copy of this conversation.")
+                }
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ContinueOnArgs, ContinueOnTool};
+    use crate::bus::MessageBus;
+    use crate::config::AppConfig;
+    use crate::handoff::Handoffs;
+    use crate::tools::form::FormBroker;
+    use crate::tools::middleware::{scoped, TurnChat};
+    use crate::tools::transcript::ToolTranscript;
+    use rig::tool::Tool;
+
+    fn turn(bus: &MessageBus, chat_id: &str, sender_id: &str) -> Option<TurnChat> {
+        Some(TurnChat {
+            bus: bus.clone(),
+            forms: FormBroker::default(),
+            channel: "telegram".to_string(),
+            chat_id: chat_id.to_string(),
+            sender_id: sender_id.to_string(),
+            session: format!("telegram:{chat_id}"),
+            exec_profile: "normal".to_string(),
+            persona: None,
+            transcript: ToolTranscript::default(),
+        })
+    }
+
+    fn to(chat_id: &str) -> ContinueOnArgs {
+        ContinueOnArgs {
+            to_channel: "telegram".to_string(),
+            to_chat_id: chat_id.to_string(),
+            mode: None,
+            note: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn foreign_targets_need_acceptance_from_the_target_chat() {
+        let dir = std::env::temp_dir().join(format!("femtobot-continue-{}", uuid::Uuid::new_v4()));
+        let mut cfg = AppConfig::defaults();
+        cfg.admins = vec!["admin".to_string()];
+        let bus = MessageBus::new();
+        let handoffs = Handoffs::new(&dir);
+        let tool = ContinueOnTool::new(cfg, bus.clone(), handoffs.clone());
+
+        assert!(tool.call(to("victim")).await.is_err());
+        let reply = scoped(turn(&bus, "1", "mallory"), tool.call(to("victim")))
+            .await
+            .unwrap();
+        assert!(reply.contains("confirm"), "{reply}");
+        assert_eq!(handoffs.resolve("telegram:victim").await, "telegram:victim");
+        assert_eq!(
+            handoffs.accept("telegram:victim").await.unwrap().unwrap().0,
+            "telegram:1"
+        );
+
+        scoped(turn(&bus, "2", "admin"), tool.call(to("desk")))
+            .await
+            .unwrap();
+        assert_eq!(handoffs.resolve("telegram:desk").await, "telegram:2");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
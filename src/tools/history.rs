@@ -0,0 +1,208 @@
+use crate::config::AppConfig;
+use crate::error::ErrorKind;
+use crate::history::HistoryLog;
+use crate::memory::vector_store::VectorMemoryStore;
+use crate::tools::middleware;
+use crate::tools::ToolError;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use tracing::warn;
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 30;
+/// Minimum cosine similarity for semantic matches to be reported.
+const SEMANTIC_THRESHOLD: f32 = 0.35;
+
+#[derive(Clone)]
+pub struct SearchHistoryTool {
+    cfg: AppConfig,
+    history: HistoryLog,
+    vector_memory: Option<VectorMemoryStore>,
+}
+
+impl SearchHistoryTool {
+    pub fn new(
+        cfg: AppConfig,
+        history: HistoryLog,
+        vector_memory: Option<VectorMemoryStore>,
+    ) -> Self {
+        Self {
+            cfg,
+            history,
+            vector_memory,
+        }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct SearchHistoryArgs {
+    /// Words or phrase to look for
+    pub query: String,
+    /// Maximum number of results per search mode (1-30, default 10)
+    pub limit: Option<usize>,
+    /// Show how each memory was scored (cosine, recency, importance, namespace), including those below the threshold
+    pub explain: Option<bool>,
+}
+
+impl Tool for SearchHistoryTool {
+    const NAME: &'static str = "search_history";
+    type Args = SearchHistoryArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Search past conversations and daily notes. Combines full-text matches (all words must appear) with semantic matches from long-term memory, and returns dated snippets. Use for questions like 'when did we discuss X?'.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(SearchHistoryArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let history = self.history.clone();
+        let vector_memory = self.vector_memory.clone();
+        let cfg = self.cfg.clone();
+        async move {
+            if args.query.trim().is_empty() {
+                return Err(ToolError::msg("Missing required field: query"));
+            }
+            // Other chats' conversations are visible to admins only.
+            let Some(turn) = middleware::current_chat() else {
+                return Err(ToolError::new(
+                    ErrorKind::Refused,
+                    "search_history only works in a chat turn.",
+                ));
+            };
+            let scope = (!cfg.is_admin(&turn.sender_id)).then_some(turn.session);
+            let limit = args.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+            let explain = args.explain.unwrap_or(false);
+            Ok(search_history(
+                &history,
+                vector_memory.as_ref(),
+                &args.query,
+                limit,
+                explain,
+                scope.as_deref(),
+            )
+            .await)
+        }
+    }
+}
+
+/// Run a combined full-text + semantic search and render the results. Shared
+/// by the `search_history` tool and the `/search` chat command. With
+/// `explain`, memories are listed with their score breakdown instead. With
+/// `session`, only that session's transcript and memory namespace are
+/// searched; `None` searches everything and is for admins.
+pub async fn search_history(
+    history: &HistoryLog,
+    vector_memory: Option<&VectorMemoryStore>,
+    query: &str,
+    limit: usize,
+    explain: bool,
+    session: Option<&str>,
+) -> String {
+    let query = query.trim();
+    let history_for_scan = history.clone();
+    let owned_query = query.to_string();
+    let owned_session = session.map(str::to_string);
+    let text_hits = tokio::task::spawn_blocking(move || {
+        history_for_scan.search(&owned_query, limit, owned_session.as_deref())
+    })
+    .await
+    .unwrap_or_default();
+
+    if explain {
+        let mut out = format!("Results for: {query}\n");
+        match vector_memory {
+            Some(store) => out.push_str(&explain_memories(store, query, limit, session).await),
+            None => out.push_str("\nVector memory is disabled.\n"),
+        }
+        return out;
+    }
+
+    let semantic_hits = match (vector_memory, session) {
+        (Some(store), Some(namespace)) => {
+            store
+                .search(query, limit, SEMANTIC_THRESHOLD, Some(namespace), 0.0)
+                .await
+        }
+        (Some(store), None) => {
+            store
+                .search_all_namespaces(query, limit, SEMANTIC_THRESHOLD)
+                .await
+        }
+        (None, _) => Ok(Vec::new()),
+    };
+    let semantic_hits = semantic_hits.unwrap_or_else(|err| {
+        warn!("search_history: semantic search failed: {err}");
+        Vec::new()
+    });
+
+    if text_hits.is_empty() && semantic_hits.is_empty() {
+        return format!("No matches for: {query}");
+    }
+
+    let mut out = format!("Results for: {query}\n");
+    if !text_hits.is_empty() {
+        out.push_str("\nText matches:\n");
+        for hit in text_hits {
+            out.push_str(&format!(
+                "- [{}] {}: {}\n",
+                hit.when, hit.source, hit.snippet
+            ));
+        }
+    }
+    if !semantic_hits.is_empty() {
+        out.push_str("\nRelated memories:\n");
+        for (item, score) in semantic_hits {
+            out.push_str(&format!(
+                "- [{}] {} (similarity {:.2})\n",
+                item.created_at.format("%Y-%m-%d"),
+                item.content,
+                score
+            ));
+        }
+    }
+    out
+}
+
+/// Memories ranked for `query` with how each was scored, marking those
+/// below the threshold `search_history` reports.
+pub async fn explain_memories(
+    store: &VectorMemoryStore,
+    query: &str,
+    limit: usize,
+    namespace: Option<&str>,
+) -> String {
+    let hits = match store.explain(query, limit, namespace).await {
+        Ok(hits) => hits,
+        Err(err) => return format!("\nMemory search failed: {err}\n"),
+    };
+    if hits.is_empty() {
+        return "\nNo memories stored.\n".to_string();
+    }
+    let mut out = format!("\nMemory scores (threshold {SEMANTIC_THRESHOLD:.2}):\n");
+    for hit in hits {
+        let marker = if hit.similarity >= SEMANTIC_THRESHOLD {
+            "+"
+        } else {
+            "-"
+        };
+        out.push_str(&format!(
+            "{marker} {}\n  {}\n",
+            hit.item.content.replace('\n', " "),
+            hit.describe()
+        ));
+    }
+    out
+}
@@ -0,0 +1,272 @@
+//! Tools declared in config (`tools.http`): each calls one HTTP endpoint
+//! with the model's arguments filled into a URL, header and body template,
+//! and returns the response or the part picked out by `extract`.
+
+use crate::config::{AppConfig, HttpToolSpec, NetworkPolicy};
+use crate::error::ErrorKind;
+use crate::tools::ToolError;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde_json::{json, Map, Value};
+
+#[derive(Clone)]
+pub struct HttpTool {
+    spec: HttpToolSpec,
+    /// Value of the auth header, resolved from the secret at startup.
+    auth: Option<(String, String)>,
+    /// Timeouts only; calls may not be safe to repeat, so none are retried.
+    network: NetworkPolicy,
+}
+
+impl HttpTool {
+    pub fn new(spec: HttpToolSpec, cfg: &AppConfig) -> Self {
+        let auth = spec.auth.as_ref().and_then(|auth| {
+            let secret = cfg.secret(&auth.secret)?;
+            let value = match &auth.scheme {
+                Some(scheme) => format!("{scheme} {secret}"),
+                None => secret,
+            };
+            Some((auth.header.clone(), value))
+        });
+        Self {
+            spec,
+            auth,
+            network: cfg.network_web.clone(),
+        }
+    }
+
+    /// Whether `name` is usable as a tool name by providers.
+    pub fn valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name.len() <= 64
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+
+    fn parameters(&self) -> Value {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for (name, param) in &self.spec.params {
+            properties.insert(
+                name.clone(),
+                json!({ "type": "string", "description": param.description() }),
+            );
+            if param.required() {
+                required.push(name.clone());
+            }
+        }
+        json!({ "type": "object", "properties": properties, "required": required })
+    }
+
+    fn request(&self, args: &Map<String, Value>) -> Result<reqwest::RequestBuilder, ToolError> {
+        for (name, param) in &self.spec.params {
+            if param.required() && arg(args, name).is_empty() {
+                return Err(ToolError::msg(format!("missing argument '{name}'")));
+            }
+        }
+        let url = fill(&self.spec.url, args, |value| {
+            url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+        });
+        let method =
+            reqwest::Method::from_bytes(self.spec.method.trim().to_ascii_uppercase().as_bytes())
+                .map_err(|_| {
+                    ToolError::msg(format!("invalid HTTP method '{}'", self.spec.method))
+                })?;
+        let client = self
+            .network
+            .client_builder()
+            .build()
+            .map_err(|err| ToolError::msg(err.to_string()))?;
+        let sends_body = matches!(
+            method,
+            reqwest::Method::POST | reqwest::Method::PUT | reqwest::Method::PATCH
+        );
+        let mut request = client.request(method, url);
+        for (header, template) in &self.spec.headers {
+            request = request.header(header, fill(template, args, str::to_string));
+        }
+        if let Some((header, value)) = &self.auth {
+            request = request.header(header, value);
+        }
+        if let Some(body) = &self.spec.body {
+            // Values land inside JSON strings in most templates.
+            request = request.body(fill(body, args, |value| {
+                let quoted = Value::String(value.to_string()).to_string();
+                quoted[1..quoted.len() - 1].to_string()
+            }));
+        } else if sends_body {
+            request = request.json(args);
+        }
+        Ok(request)
+    }
+}
+
+impl Tool for HttpTool {
+    // Every config-defined tool shares this type; `name()` tells them apart.
+    const NAME: &'static str = "http_tool";
+    type Args = Map<String, Value>;
+    type Output = String;
+    type Error = ToolError;
+
+    fn name(&self) -> String {
+        self.spec.name.clone()
+    }
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send + Sync {
+        let definition = ToolDefinition {
+            name: self.spec.name.clone(),
+            description: self.spec.description.clone(),
+            parameters: self.parameters(),
+        };
+        async move { definition }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        async move {
+            if self.spec.auth.is_some() && self.auth.is_none() {
+                return Err(ToolError::msg(format!(
+                    "the secret for {} is not configured",
+                    self.spec.name
+                )));
+            }
+            let response = self
+                .request(&args)?
+                .send()
+                .await
+                .map_err(|err| ToolError::new(ErrorKind::of_reqwest(&err), err.to_string()))?;
+            let status = response.status();
+            let text = response
+                .text()
+                .await
+                .map_err(|err| ToolError::msg(err.to_string()))?;
+            if !status.is_success() {
+                let excerpt: String = text.chars().take(500).collect();
+                let kind = ErrorKind::from_status(status.as_u16()).unwrap_or(ErrorKind::Tool);
+                return Err(ToolError::new(kind, format!("HTTP {status}: {excerpt}")));
+            }
+            let Some(path) = self.spec.extract.as_deref() else {
+                return Ok(text);
+            };
+            let json: Value = serde_json::from_str(&text).map_err(|_| {
+                ToolError::msg("the response is not JSON, so `extract` cannot apply".to_string())
+            })?;
+            let picked = extract(&json, path).map_err(ToolError::msg)?;
+            Ok(match picked.as_slice() {
+                [Value::String(text)] => text.clone(),
+                [single] => serde_json::to_string_pretty(single).unwrap_or_default(),
+                many => serde_json::to_string_pretty(many).unwrap_or_default(),
+            })
+        }
+    }
+}
+
+/// Argument `name` as text; missing arguments are empty.
+fn arg(args: &Map<String, Value>, name: &str) -> String {
+    match args.get(name) {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Replace every `{name}` in `template` with the escaped argument.
+fn fill(template: &str, args: &Map<String, Value>, escape: impl Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) if HttpTool::valid_name(&after[..end]) => {
+                out.push_str(&escape(&arg(args, &after[..end])));
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Values at a JQ-style `path`: `.key` steps into objects, `[n]` picks an
+/// array element and `[]` continues with every element.
+fn extract(json: &Value, path: &str) -> Result<Vec<Value>, String> {
+    let mut current = vec![json.clone()];
+    let mut rest = path.trim();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("[]") {
+            current = current
+                .into_iter()
+                .flat_map(|value| match value {
+                    Value::Array(items) => items,
+                    _ => Vec::new(),
+                })
+                .collect();
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after
+                .find(']')
+                .ok_or_else(|| format!("unclosed '[' in extract path '{path}'"))?;
+            let index: usize = after[..end]
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid index in extract path '{path}'"))?;
+            current = current
+                .into_iter()
+                .filter_map(|value| value.get(index).cloned())
+                .collect();
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            let key = &after[..end];
+            if !key.is_empty() {
+                current = current
+                    .into_iter()
+                    .filter_map(|value| value.get(key).cloned())
+                    .collect();
+            }
+            rest = &after[end..];
+        } else {
+            return Err(format!("invalid extract path '{path}'"));
+        }
+    }
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract, fill};
+    use serde_json::json;
+
+    #[test]
+    fn fills_templates_and_extracts_paths() {
+        let args = json!({"city": "São Paulo", "units": "metric"});
+        let args = args.as_object().unwrap();
+        let url = fill("https://x.test/w?q={city}&u={units}&{raw", args, |v| {
+            url::form_urlencoded::byte_serialize(v.as_bytes()).collect()
+        });
+        assert_eq!(url, "https://x.test/w?q=S%C3%A3o+Paulo&u=metric&{raw");
+
+        let body = json!({"current": {"temp_c": 21.5}, "items": [{"title": "a"}, {"title": "b"}]});
+        assert_eq!(
+            extract(&body, ".current.temp_c").unwrap(),
+            vec![json!(21.5)]
+        );
+        assert_eq!(
+            extract(&body, ".items[].title").unwrap(),
+            vec![json!("a"), json!("b")]
+        );
+        assert_eq!(extract(&body, ".items[1].title").unwrap(), vec![json!("b")]);
+        assert!(extract(&body, "items").is_err());
+    }
+}
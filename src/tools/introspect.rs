@@ -0,0 +1,170 @@
+use crate::channel;
+use crate::config::{AppConfig, ModelRoute};
+use crate::tools::guard::ToolLimits;
+use crate::tools::{ToolError, ToolRegistry};
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Reports what this agent can actually do: its tools, the model route it
+/// runs on, memory status, workspace paths and limits. One instance exists
+/// per runtime agent, so `model` is the route answering the current turn.
+#[derive(Clone)]
+pub struct IntrospectTool {
+    cfg: AppConfig,
+    tools: ToolRegistry,
+    route: ModelRoute,
+}
+
+impl IntrospectTool {
+    pub fn new(cfg: AppConfig, tools: ToolRegistry, route: ModelRoute) -> Self {
+        Self { cfg, tools, route }
+    }
+
+    async fn tools_section(&self, with_schemas: bool) -> Value {
+        let mut definitions = self.tools.definitions().await;
+        definitions.push(self.definition(String::new()).await);
+        let entries = definitions
+            .into_iter()
+            .map(|def| {
+                let mut entry = json!({ "name": def.name, "description": def.description });
+                if with_schemas {
+                    entry["parameters"] = def.parameters;
+                }
+                entry
+            })
+            .collect::<Vec<_>>();
+        Value::Array(entries)
+    }
+
+    fn model_section(&self) -> Value {
+        let routes = self
+            .cfg
+            .model_routes()
+            .into_iter()
+            .map(|r| format!("{}/{}", r.provider.as_str(), r.model))
+            .collect::<Vec<_>>();
+        json!({
+            "active": format!("{}/{}", self.route.provider.as_str(), self.route.model),
+            "routes": routes,
+            "cheap_route": self
+                .cfg
+                .cheap_route()
+                .map(|r| format!("{}/{}", r.provider.as_str(), r.model)),
+        })
+    }
+
+    fn memory_section(&self) -> Value {
+        let cfg = &self.cfg;
+        json!({
+            "enabled": cfg.memory_enabled,
+            "vector_memory": cfg.memory_enabled && cfg.memory_vector_enabled,
+            "embedding_model": cfg.memory_embedding_model,
+            "extraction_model": cfg.memory_extraction_model,
+            "max_memories": cfg.memory_max_memories,
+            "recall": cfg.memory_recall,
+            "layout": cfg.memory_layout,
+            "long_term_file": cfg.workspace_dir.join("memory").join("MEMORY.md"),
+        })
+    }
+
+    fn paths_section(&self) -> Value {
+        json!({
+            "workspace": self.cfg.workspace_dir,
+            "data": self.cfg.data_dir,
+            "restricted_to_workspace": self.cfg.restrict_to_workspace,
+            "sandboxed_chats": self.cfg.sandbox_chats,
+        })
+    }
+
+    fn limits_section(&self) -> Value {
+        let defaults = ToolLimits::for_tool(&self.cfg, "");
+        let exec = ToolLimits::for_tool(&self.cfg, "exec");
+        json!({
+            "max_tool_turns": self.cfg.max_tool_turns,
+            "max_repeated_tool_calls": self.cfg.max_repeated_tool_calls,
+            "stream": self.cfg.stream_completions,
+            "tool_timeout_secs": defaults.timeout.as_secs(),
+            "tool_output_max_chars": defaults.max_output_chars,
+            "exec_timeout_secs": self.cfg.exec_timeout_secs,
+            "exec_tool_timeout_secs": exec.timeout.as_secs(),
+            "tool_timeouts": self.cfg.tool_timeouts,
+            "tool_output_limits": self.cfg.tool_output_limits,
+            "network": {
+                "provider": self.cfg.network_provider,
+                "web": self.cfg.network_web,
+                "transcription": self.cfg.network_transcription,
+                "telegram": self.cfg.network_telegram,
+            },
+        })
+    }
+
+    fn channels_section(&self) -> Value {
+        let mut channels = vec!["cli"];
+        channels.extend(
+            channel::configured(&self.cfg)
+                .iter()
+                .map(|channel| channel.name()),
+        );
+        json!(channels)
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct IntrospectArgs {
+    /// One of: tools, model, memory, paths, limits, channels. Omit for an overview of everything (tools without schemas)
+    pub section: Option<String>,
+}
+
+impl Tool for IntrospectTool {
+    const NAME: &'static str = "introspect";
+    type Args = IntrospectArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Describe your own configuration: enabled tools (section=tools includes their schemas), the model route answering now, memory status, workspace paths, limits and connected channels. Call this before answering questions like \"what can you do?\" or \"which model are you?\" and describe only what it reports.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(IntrospectArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let tool = self.clone();
+        async move {
+            let section = args.section.unwrap_or_default().trim().to_lowercase();
+            let value = match section.as_str() {
+                "" | "all" => json!({
+                    "tools": tool.tools_section(false).await,
+                    "model": tool.model_section(),
+                    "memory": tool.memory_section(),
+                    "paths": tool.paths_section(),
+                    "limits": tool.limits_section(),
+                    "channels": tool.channels_section(),
+                }),
+                "tools" => tool.tools_section(true).await,
+                "model" => tool.model_section(),
+                "memory" => tool.memory_section(),
+                "paths" => tool.paths_section(),
+                "limits" => tool.limits_section(),
+                "channels" => tool.channels_section(),
+                other => {
+                    return Err(ToolError::msg(format!(
+                        "Unknown section '{other}'. Use: tools, model, memory, paths, limits, channels."
+                    )))
+                }
+            };
+            serde_json::to_string_pretty(&value).map_err(|e| ToolError::msg(e.to_string()))
+        }
+    }
+}
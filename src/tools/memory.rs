@@ -0,0 +1,221 @@
+use crate::memory::vector_store::{MemorySelector, VectorMemoryStore};
+use crate::tools::ToolError;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Memories shown by action=list.
+const LIST_LIMIT: usize = 50;
+
+#[derive(Clone)]
+pub struct MemoryTool {
+    store: Option<VectorMemoryStore>,
+    workspace_dir: PathBuf,
+}
+
+impl MemoryTool {
+    pub fn new(store: Option<VectorMemoryStore>, workspace_dir: PathBuf) -> Self {
+        Self {
+            store,
+            workspace_dir,
+        }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct MemoryArgs {
+    /// One of: list, count, delete, retag, export
+    pub action: String,
+    /// Namespace to operate on (e.g. "project_trip-japan" or "telegram_12345"); omit for all namespaces
+    pub namespace: Option<String>,
+    /// Metadata fields that must match, e.g. {"topic": "Travel"} or {"tags": "project:X"}
+    pub filter: Option<HashMap<String, Value>>,
+    /// Only memories whose text contains this phrase (case-insensitive)
+    pub contains: Option<String>,
+    /// Single memory id (delete/retag just that memory)
+    pub id: Option<String>,
+    /// For retag: metadata fields to set; null removes a field
+    pub set: Option<HashMap<String, Value>>,
+    /// For retag: move the memories to this namespace
+    pub to_namespace: Option<String>,
+    /// Must be true to delete more than one memory
+    pub confirm: Option<bool>,
+}
+
+impl Tool for MemoryTool {
+    const NAME: &'static str = "memory";
+    type Args = MemoryArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Inspect and clean up long-term memory in bulk. Select memories by namespace, metadata filter, text (contains) or id, then: list/count them, delete them (confirm=true is required when more than one matches; call count or list first and show the user what will go), retag them (merge metadata from `set`, optionally move to `to_namespace`), or export them to a JSON file in the workspace. Project memories live in namespace \"project_<name>\".".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(MemoryArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let tool = self.clone();
+        async move {
+            let Some(store) = &tool.store else {
+                return Err(ToolError::msg("Vector memory is disabled"));
+            };
+            let action = args.action.trim().to_lowercase();
+            if !matches!(
+                action.as_str(),
+                "list" | "count" | "delete" | "retag" | "export"
+            ) {
+                return Ok("Invalid action. Use: list, count, delete, retag, export.".to_string());
+            }
+            let selector = MemorySelector {
+                namespace: args
+                    .namespace
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|ns| !ns.is_empty())
+                    .map(str::to_string),
+                metadata: args.filter.clone().unwrap_or_default(),
+                contains: args
+                    .contains
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+                    .map(str::to_string),
+            };
+            let mut items = store
+                .select(&selector)
+                .await
+                .map_err(|e| ToolError::msg(e.to_string()))?;
+            if let Some(id) = args
+                .id
+                .as_deref()
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+            {
+                items.retain(|item| item.id == id);
+            }
+            let scoped = selector.namespace.is_some()
+                || !selector.metadata.is_empty()
+                || selector.contains.is_some()
+                || args.id.is_some();
+
+            match action.as_str() {
+                "count" => Ok(format!("{} matching memories.", items.len())),
+                "list" => {
+                    if items.is_empty() {
+                        return Ok("No matching memories.".to_string());
+                    }
+                    let mut out = format!("{} matching memories:\n", items.len());
+                    for item in items.iter().take(LIST_LIMIT) {
+                        out.push_str(&format!(
+                            "- {} [{}] {} {}\n",
+                            item.id,
+                            item.namespace,
+                            item.content,
+                            serde_json::to_string(&item.metadata).unwrap_or_default()
+                        ));
+                    }
+                    if items.len() > LIST_LIMIT {
+                        out.push_str(&format!(
+                            "... {} more (use export for the full set)\n",
+                            items.len() - LIST_LIMIT
+                        ));
+                    }
+                    Ok(out)
+                }
+                "delete" => {
+                    if !scoped {
+                        return Err(ToolError::msg(
+                            "Refusing to delete everything: give a namespace, filter, contains or id",
+                        ));
+                    }
+                    if items.is_empty() {
+                        return Ok("No matching memories.".to_string());
+                    }
+                    if items.len() > 1 && !args.confirm.unwrap_or(false) {
+                        return Ok(format!(
+                            "{} memories match. Call again with confirm=true to delete them.",
+                            items.len()
+                        ));
+                    }
+                    let ids = items.into_iter().map(|item| item.id).collect();
+                    let removed = store
+                        .delete_many(ids)
+                        .await
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    Ok(format!("Deleted {removed} memories."))
+                }
+                "retag" => {
+                    let patch = args.set.unwrap_or_default();
+                    let to_namespace = args
+                        .to_namespace
+                        .as_deref()
+                        .map(str::trim)
+                        .filter(|ns| !ns.is_empty());
+                    if patch.is_empty() && to_namespace.is_none() {
+                        return Err(ToolError::msg(
+                            "Missing required field: set or to_namespace",
+                        ));
+                    }
+                    if !scoped {
+                        return Err(ToolError::msg(
+                            "Refusing to retag everything: give a namespace, filter, contains or id",
+                        ));
+                    }
+                    let changed = store
+                        .retag(items, patch, to_namespace)
+                        .await
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    Ok(format!("Updated {changed} memories."))
+                }
+                _ => {
+                    let export: Vec<Value> = items
+                        .iter()
+                        .map(|item| {
+                            json!({
+                                "id": item.id,
+                                "namespace": item.namespace,
+                                "content": item.content,
+                                "metadata": item.metadata,
+                                "created_at": item.created_at,
+                                "updated_at": item.updated_at,
+                                "priority": item.priority,
+                            })
+                        })
+                        .collect();
+                    let dir = tool.workspace_dir.join("exports");
+                    let path = dir.join(format!(
+                        "memories-{}.json",
+                        chrono::Local::now().format("%Y%m%d-%H%M%S")
+                    ));
+                    let body = serde_json::to_string_pretty(&json!({
+                        "version": 1,
+                        "memories": export,
+                    }))
+                    .map_err(|e| ToolError::msg(e.to_string()))?;
+                    std::fs::create_dir_all(&dir)
+                        .and_then(|_| std::fs::write(&path, body))
+                        .map_err(|e| ToolError::msg(format!("Failed to write export: {e}")))?;
+                    Ok(format!(
+                        "Exported {} memories to {}.",
+                        items.len(),
+                        path.display()
+                    ))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,493 @@
+//! Hooks run around guarded tool calls, configured per tool under
+//! `tools.hooks` (`"*"` applies to every tool):
+//!
+//! ```json
+//! "hooks": {
+//!   "*": ["log"],
+//!   "exec": ["approve", {"hook": "deny", "pattern": "rm\\s+-rf"}],
+//!   "web_fetch": ["strip_html"],
+//!   "web_search": [{"hook": "default_args", "values": {"count": 3}}]
+//! }
+//! ```
+//!
+//! `before` hooks run in order on the raw JSON arguments and may rewrite or
+//! reject them; `after` hooks post-process the output before the output
+//! budget is applied.
+
+use crate::bus::MessageBus;
+use crate::config::{AppConfig, Persona};
+use crate::tools::form::FormBroker;
+use crate::tools::transcript::ToolTranscript;
+use regex::Regex;
+use rig::completion::ToolDefinition;
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub type HookFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+pub trait ToolHook: Send + Sync {
+    /// Inspect or rewrite the arguments; `Err` rejects the call and is
+    /// returned to the model as the tool error.
+    fn before<'a>(&'a self, _tool: &'a str, _args: &'a mut Value) -> HookFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn after(&self, _tool: &str, output: String) -> String {
+        output
+    }
+}
+
+/// The hooks configured for one tool, in order.
+#[derive(Clone, Default)]
+pub struct ToolHooks {
+    hooks: Vec<Arc<dyn ToolHook>>,
+}
+
+impl ToolHooks {
+    pub fn for_tool(cfg: &AppConfig, tool: &str) -> Self {
+        let hooks = ["*", tool]
+            .iter()
+            .filter_map(|key| cfg.tool_hooks.get(*key))
+            .flatten()
+            .filter_map(|spec| match build_hook(spec, cfg) {
+                Ok(hook) => Some(hook),
+                Err(err) => {
+                    warn!("ignoring tool hook for {tool}: {err}");
+                    None
+                }
+            })
+            .collect();
+        Self { hooks }
+    }
+
+    pub async fn before(&self, tool: &str, args: &mut Value) -> Result<(), String> {
+        for hook in &self.hooks {
+            hook.before(tool, args).await?;
+        }
+        Ok(())
+    }
+
+    pub fn after(&self, tool: &str, mut output: String) -> String {
+        for hook in &self.hooks {
+            output = hook.after(tool, output);
+        }
+        output
+    }
+}
+
+/// Built-in hooks by name: a string, or an object with `hook` and options.
+fn build_hook(spec: &Value, cfg: &AppConfig) -> Result<Arc<dyn ToolHook>, String> {
+    let name = spec
+        .as_str()
+        .or_else(|| spec.get("hook").and_then(Value::as_str))
+        .ok_or_else(|| format!("hook spec needs a name: {spec}"))?;
+    let values = || {
+        spec.get("values")
+            .and_then(Value::as_object)
+            .cloned()
+            .ok_or_else(|| format!("{name} needs a \"values\" object"))
+    };
+    Ok(match name {
+        "log" => Arc::new(LogHook),
+        "approve" => Arc::new(ApproveHook {
+            timeout: Duration::from_secs(cfg.form_timeout_secs.max(10)),
+        }),
+        "deny" => {
+            let pattern = spec
+                .get("pattern")
+                .and_then(Value::as_str)
+                .ok_or_else(|| "deny needs a \"pattern\"".to_string())?;
+            Arc::new(DenyHook {
+                pattern: Regex::new(pattern).map_err(|err| err.to_string())?,
+            })
+        }
+        "default_args" => Arc::new(ArgsHook {
+            values: values()?,
+            force: false,
+        }),
+        "set_args" => Arc::new(ArgsHook {
+            values: values()?,
+            force: true,
+        }),
+        "strip_html" => Arc::new(StripHtmlHook),
+        other => return Err(format!("unknown hook '{other}'")),
+    })
+}
+
+struct LogHook;
+
+impl ToolHook for LogHook {
+    fn before<'a>(&'a self, tool: &'a str, args: &'a mut Value) -> HookFuture<'a> {
+        info!(
+            "tool {tool} called with {}",
+            preview(&args.to_string(), 500)
+        );
+        Box::pin(async { Ok(()) })
+    }
+
+    fn after(&self, tool: &str, output: String) -> String {
+        info!("tool {tool} returned {} chars", output.chars().count());
+        output
+    }
+}
+
+/// Rejects calls whose serialized arguments match `pattern`.
+struct DenyHook {
+    pattern: Regex,
+}
+
+impl ToolHook for DenyHook {
+    fn before<'a>(&'a self, tool: &'a str, args: &'a mut Value) -> HookFuture<'a> {
+        let denied = self.pattern.is_match(&args.to_string());
+        Box::pin(async move {
+            if denied {
+                warn!("tool {tool} call denied by hook pattern {}", self.pattern);
+                return Err(format!(
+                    "{tool} call blocked by policy (arguments match a denied pattern). Do not retry it."
+                ));
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Fills in (or, with `force`, overrides) argument values.
+struct ArgsHook {
+    values: serde_json::Map<String, Value>,
+    force: bool,
+}
+
+impl ToolHook for ArgsHook {
+    fn before<'a>(&'a self, _tool: &'a str, args: &'a mut Value) -> HookFuture<'a> {
+        if let Some(object) = args.as_object_mut() {
+            for (key, value) in &self.values {
+                if self.force || object.get(key).is_none_or(Value::is_null) {
+                    object.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Removes markup from outputs that carry raw HTML.
+struct StripHtmlHook;
+
+impl ToolHook for StripHtmlHook {
+    fn after(&self, _tool: &str, output: String) -> String {
+        strip_html(&output)
+    }
+}
+
+/// Asks the chat the turn came from before the tool runs.
+struct ApproveHook {
+    timeout: Duration,
+}
+
+impl ToolHook for ApproveHook {
+    fn before<'a>(&'a self, tool: &'a str, args: &'a mut Value) -> HookFuture<'a> {
+        let question = format!(
+            "Allow {tool} with {}? Reply yes or no.",
+            preview(&args.to_string(), 300)
+        );
+        Box::pin(ask_approval(tool, question, self.timeout))
+    }
+}
+
+/// Ask the current turn's chat `question` and wait for a yes.
+pub async fn ask_approval(tool: &str, question: String, timeout: Duration) -> Result<(), String> {
+    let Some(chat) = current_chat() else {
+        return Err(format!(
+            "{tool} needs the user's approval, but this turn has no chat to ask."
+        ));
+    };
+    let answer = chat
+        .forms
+        .ask(&chat.bus, &chat.channel, &chat.chat_id, &question, timeout)
+        .await;
+    match answer.as_deref().map(|a| a.trim().to_lowercase()) {
+        Some(a) if matches!(a.as_str(), "yes" | "y" | "ok" | "approve") => Ok(()),
+        Some(_) => Err(format!(
+            "The user declined the {tool} call. Do not retry it; ask what they want instead."
+        )),
+        None => Err(format!("No approval for {tool} arrived in time.")),
+    }
+}
+
+/// Where approval questions for the current turn go.
+#[derive(Clone)]
+pub struct TurnChat {
+    pub bus: MessageBus,
+    pub forms: FormBroker,
+    pub channel: String,
+    pub chat_id: String,
+    pub sender_id: String,
+    /// Session key the turn's history and memories are kept under, after
+    /// handoff links are followed.
+    pub session: String,
+    /// Exec profile (`tools.exec.profiles`) in force for this turn.
+    pub exec_profile: String,
+    /// Persona of the chat, which may withhold tools.
+    pub persona: Option<(String, Persona)>,
+    /// Tool calls made so far in this turn.
+    pub transcript: ToolTranscript,
+}
+
+tokio::task_local! {
+    static TURN_CHAT: Option<TurnChat>;
+}
+
+/// Run `fut` (an agent turn) with hooks asking `chat` for approvals.
+pub async fn scoped<F: Future>(chat: Option<TurnChat>, fut: F) -> F::Output {
+    TURN_CHAT.scope(chat, fut).await
+}
+
+/// Chat of the turn currently executing, if any.
+pub fn current_chat() -> Option<TurnChat> {
+    TURN_CHAT.try_with(Clone::clone).ok().flatten()
+}
+
+/// Why `tool` may not run in the current turn: its chat's persona does
+/// not allow it.
+pub fn withheld(tool: &str) -> Option<String> {
+    let (name, persona) = current_chat()?.persona?;
+    (!persona.allows_tool(tool))
+        .then(|| format!("{tool} is not available in this chat (persona '{name}')."))
+}
+
+/// Drop the tools the current chat's persona does not allow from those
+/// offered to the model; [`withheld`] still refuses any call that slips by.
+pub fn offered(tools: &mut Vec<ToolDefinition>) {
+    if let Some((_, persona)) = current_chat().and_then(|chat| chat.persona) {
+        tools.retain(|tool| persona.allows_tool(&tool.name));
+    }
+}
+
+/// Tools that send to the current chat when both `channel` and `chat_id`
+/// are omitted.
+const DEFAULTS_TO_CURRENT_CHAT: &[&str] = &["send_message", "set_timer"];
+
+/// Fill a tool call's `channel`/`chat_id` from the current turn when the
+/// model asks for the same chat: `channel: "current"`, a `chat_id` of
+/// `"current"` or missing, or (for [`DEFAULTS_TO_CURRENT_CHAT`]) no target
+/// at all. Models tend to invent chat ids otherwise.
+pub fn resolve_current_target(tool: &str, args: &mut Value) -> Result<(), String> {
+    let Some(obj) = args.as_object_mut() else {
+        return Ok(());
+    };
+    let text = |key: &str| {
+        obj.get(key)
+            .and_then(Value::as_str)
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+    };
+    let channel = text("channel");
+    let chat_id = text("chat_id");
+    let is_current = |v: &Option<String>| matches!(v.as_deref(), Some("current" | "same"));
+    let chat_missing = chat_id.is_none() || is_current(&chat_id);
+    let wants_current = is_current(&channel)
+        || (channel.is_none() && is_current(&chat_id))
+        || (channel.is_none() && chat_id.is_none() && DEFAULTS_TO_CURRENT_CHAT.contains(&tool));
+    let turn = current_chat();
+    let same_channel = turn
+        .as_ref()
+        .zip(channel.as_deref())
+        .is_some_and(|(turn, channel)| turn.channel == channel);
+    if !(wants_current || (same_channel && chat_missing)) {
+        return Ok(());
+    }
+    let Some(turn) = turn.filter(|turn| turn.channel != "cron") else {
+        return Err(format!(
+            "{tool}: this turn has no chat to send to (a cron job without channel/to). Pass channel and chat_id explicitly."
+        ));
+    };
+    obj.insert("channel".to_string(), Value::String(turn.channel));
+    if chat_missing || wants_current {
+        obj.insert("chat_id".to_string(), Value::String(turn.chat_id));
+    }
+    Ok(())
+}
+
+/// `text` cut to `max_chars` characters, with an ellipsis when shortened.
+pub fn preview(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    format!("{cut}…")
+}
+
+/// Drop tags, `<script>`/`<style>` bodies and decode common entities.
+fn strip_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let lower = rest.get(..8).unwrap_or(rest).to_ascii_lowercase();
+        let skip_to = if lower.starts_with("<script") {
+            Some("</script>")
+        } else if lower.starts_with("<style") {
+            Some("</style>")
+        } else {
+            None
+        };
+        if let Some(end_tag) = skip_to {
+            match rest.to_ascii_lowercase().find(end_tag) {
+                Some(end) => rest = &rest[end + end_tag.len()..],
+                None => rest = "",
+            }
+            continue;
+        }
+        match rest.find('>') {
+            Some(end) => {
+                let tag = rest[1..end].trim_start_matches('/').to_ascii_lowercase();
+                if ["p", "br", "div", "li", "tr", "h1", "h2", "h3", "h4"]
+                    .iter()
+                    .any(|block| tag == *block || tag.starts_with(&format!("{block} ")))
+                {
+                    out.push('\n');
+                }
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push_str(rest);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    let decoded = out
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    let mut lines: Vec<&str> = decoded.lines().map(str::trim).collect();
+    lines.dedup_by(|a, b| a.is_empty() && b.is_empty());
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        offered, resolve_current_target, scoped, strip_html, withheld, ToolHooks, ToolTranscript,
+        TurnChat,
+    };
+    use crate::bus::MessageBus;
+    use crate::config::{AppConfig, Persona};
+    use crate::tools::form::FormBroker;
+    use rig::completion::ToolDefinition;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn resolves_current_chat_targets() {
+        let chat = TurnChat {
+            bus: MessageBus::new(),
+            forms: FormBroker::default(),
+            channel: "telegram".to_string(),
+            chat_id: "42".to_string(),
+            sender_id: "cron".to_string(),
+            session: "telegram:42".to_string(),
+            exec_profile: "normal".to_string(),
+            persona: None,
+            transcript: ToolTranscript::default(),
+        };
+        scoped(Some(chat), async {
+            let mut args = json!({"channel": "current", "content": "hi"});
+            resolve_current_target("send_message", &mut args).unwrap();
+            assert_eq!(args["channel"], "telegram");
+            assert_eq!(args["chat_id"], "42");
+
+            let mut args = json!({"content": "hi"});
+            resolve_current_target("send_message", &mut args).unwrap();
+            assert_eq!(args["chat_id"], "42");
+
+            let mut args = json!({"channel": "discord", "chat_id": "7"});
+            resolve_current_target("send_message", &mut args).unwrap();
+            assert_eq!(args, json!({"channel": "discord", "chat_id": "7"}));
+
+            let mut args = json!({"contact": "Anna"});
+            resolve_current_target("schedule_message", &mut args).unwrap();
+            assert_eq!(args, json!({"contact": "Anna"}));
+        })
+        .await;
+
+        let mut args = json!({"channel": "current"});
+        assert!(resolve_current_target("send_message", &mut args).is_err());
+    }
+
+    #[tokio::test]
+    async fn personas_only_offer_their_tools() {
+        let persona = Persona {
+            tools: vec!["read_file".to_string(), "exec".to_string()],
+            deny_tools: vec!["exec".to_string()],
+            ..Default::default()
+        };
+        let chat = TurnChat {
+            bus: MessageBus::new(),
+            forms: FormBroker::default(),
+            channel: "telegram".to_string(),
+            chat_id: "42".to_string(),
+            sender_id: "7".to_string(),
+            session: "telegram:42".to_string(),
+            exec_profile: "normal".to_string(),
+            persona: Some(("reader".to_string(), persona)),
+            transcript: ToolTranscript::default(),
+        };
+        let definitions = || {
+            ["read_file", "exec", "web_fetch"]
+                .map(|name| ToolDefinition {
+                    name: name.to_string(),
+                    description: String::new(),
+                    parameters: json!({}),
+                })
+                .to_vec()
+        };
+        scoped(Some(chat), async {
+            let mut tools = definitions();
+            offered(&mut tools);
+            let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_str()).collect();
+            assert_eq!(names, ["read_file"]);
+            assert!(withheld("web_fetch").is_some());
+        })
+        .await;
+
+        let mut tools = definitions();
+        offered(&mut tools);
+        assert_eq!(tools.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn hooks_rewrite_reject_and_post_process() {
+        let mut cfg = AppConfig::defaults();
+        cfg.tool_hooks.insert(
+            "exec".to_string(),
+            vec![
+                json!({"hook": "default_args", "values": {"timeout": 30}}),
+                json!({"hook": "deny", "pattern": "rm\\s+-rf"}),
+                json!("strip_html"),
+            ],
+        );
+        let hooks = ToolHooks::for_tool(&cfg, "exec");
+
+        let mut args = json!({"command": "ls"});
+        hooks.before("exec", &mut args).await.unwrap();
+        assert_eq!(args["timeout"], 30);
+
+        let mut args = json!({"command": "rm -rf /"});
+        assert!(hooks.before("exec", &mut args).await.is_err());
+
+        let html = "<html><style>p{}</style><p>Hello &amp; welcome</p><script>x()</script><p>Bye</p></html>";
+        assert_eq!(
+            hooks.after("exec", html.to_string()),
+            "Hello & welcome\n\nBye"
+        );
+        assert_eq!(strip_html("plain"), "plain");
+    }
+}
@@ -1,25 +1,80 @@
 use crate::bus::MessageBus;
-use crate::config::AppConfig;
+use crate::config::{AppConfig, SchemaDialect};
 use crate::cron::CronService;
+use crate::error::ErrorKind;
+use crate::memory::vector_store::VectorMemoryStore;
+use crate::notify::Notifier;
+use guard::{guarded, Guarded, SchemaFailures};
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use tracing::warn;
 
+pub mod broadcast;
+pub mod contacts;
 pub mod cron;
+pub mod expense;
+pub mod form;
 pub mod fs;
+pub mod guard;
+pub mod habits;
+pub mod handoff;
+pub mod history;
+pub mod http;
+pub mod introspect;
+pub mod memory;
+pub mod middleware;
+pub mod notify;
+pub mod pending_send;
+pub mod sandbox;
+pub mod schedule_message;
+pub mod schema;
+pub mod scratchpad;
 pub mod send;
+pub mod share;
 pub mod shell;
+pub mod summarize;
+pub mod time;
+pub mod timer;
+pub mod transcript;
 pub mod web;
 
+/// Tools that arrange later work or a later delivery. A turn that called
+/// one has already scheduled whatever it promised.
+pub const SCHEDULING_TOOLS: &[&str] = &[
+    <cron::CronTool as Tool>::NAME,
+    <timer::TimerTool as Tool>::NAME,
+    <schedule_message::ScheduleMessageTool as Tool>::NAME,
+    <pending_send::PendingSendTool as Tool>::NAME,
+];
+
+/// A failed tool call: the text the model sees, and the kind of failure
+/// recorded in the turn's transcript.
 #[derive(Debug)]
-pub struct ToolError(String);
+pub struct ToolError {
+    kind: ErrorKind,
+    message: String,
+}
 
 impl ToolError {
     pub fn msg(msg: impl Into<String>) -> Self {
-        Self(msg.into())
+        Self::new(ErrorKind::Tool, msg)
+    }
+
+    pub fn new(kind: ErrorKind, msg: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: msg.into(),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
     }
 }
 
 impl std::fmt::Display for ToolError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", self.message)
     }
 }
 
@@ -27,34 +82,344 @@ impl std::error::Error for ToolError {}
 
 #[derive(Clone)]
 pub struct ToolRegistry {
-    pub read_file: fs::ReadFileTool,
-    pub write_file: fs::WriteFileTool,
-    pub edit_file: fs::EditFileTool,
-    pub list_dir: fs::ListDirTool,
-    pub exec: shell::ExecTool,
-    pub web_search: web::WebSearchTool,
-    pub web_fetch: web::WebFetchTool,
-    pub cron: cron::CronTool,
-    pub send_message: send::SendMessageTool,
+    pub schema_failures: SchemaFailures,
+    pub forms: form::FormBroker,
+    pub handoffs: crate::handoff::Handoffs,
+    pub delivery_failures: send::DeliveryFailures,
+    pub read_file: Guarded<fs::ReadFileTool>,
+    pub write_file: Guarded<fs::WriteFileTool>,
+    pub edit_file: Guarded<fs::EditFileTool>,
+    pub list_dir: Guarded<fs::ListDirTool>,
+    pub exec: Guarded<shell::ExecTool>,
+    pub web_search: Guarded<web::WebSearchTool>,
+    pub web_fetch: Guarded<web::WebFetchTool>,
+    pub cron: Guarded<cron::CronTool>,
+    pub send_message: Guarded<send::SendMessageTool>,
+    pub share_file: Guarded<share::ShareFileTool>,
+    pub set_timer: Guarded<timer::TimerTool>,
+    pub contacts: Guarded<contacts::ContactsTool>,
+    pub track_expense: Guarded<expense::ExpenseTool>,
+    pub habits: Guarded<habits::HabitsTool>,
+    pub summarize_url: Guarded<summarize::SummarizeUrlTool>,
+    pub search_history: Guarded<history::SearchHistoryTool>,
+    pub notification_settings: Guarded<notify::NotificationSettingsTool>,
+    pub current_time: Guarded<time::CurrentTimeTool>,
+    pub ask_form: Guarded<form::AskFormTool>,
+    pub memory: Guarded<memory::MemoryTool>,
+    pub continue_on: Guarded<handoff::ContinueOnTool>,
+    pub scratchpad: Guarded<scratchpad::ScratchpadTool>,
+    pub broadcast: Guarded<broadcast::BroadcastTool>,
+    pub schedule_message: Guarded<schedule_message::ScheduleMessageTool>,
+    pub pending_send: Guarded<pending_send::PendingSendTool>,
+    pub recent_tool_results: Guarded<transcript::RecentToolResultsTool>,
+    /// Config-defined HTTP tools (`tools.http`).
+    pub http: Vec<Guarded<http::HttpTool>>,
 }
 
 impl ToolRegistry {
-    pub fn new(cfg: AppConfig, cron_service: CronService, bus: MessageBus) -> Self {
+    pub fn new(
+        cfg: AppConfig,
+        cron_service: CronService,
+        bus: MessageBus,
+        vector_memory: Option<VectorMemoryStore>,
+        notifier: Notifier,
+    ) -> Self {
         let allowed_dir = if cfg.restrict_to_workspace {
             Some(cfg.workspace_dir.clone())
         } else {
             None
         };
+        let forms = form::FormBroker::default();
+        let handoffs = crate::handoff::Handoffs::new(&cfg.data_dir);
+        let delivery_failures = send::DeliveryFailures::watch(&bus);
+        let mut registry = Self {
+            schema_failures: SchemaFailures::default(),
+            forms: forms.clone(),
+            handoffs: handoffs.clone(),
+            delivery_failures: delivery_failures.clone(),
+            read_file: guarded(fs::ReadFileTool::new(allowed_dir.clone()), &cfg),
+            write_file: guarded(fs::WriteFileTool::new(allowed_dir.clone()), &cfg),
+            edit_file: guarded(fs::EditFileTool::new(allowed_dir.clone()), &cfg),
+            list_dir: guarded(fs::ListDirTool::new(allowed_dir.clone()), &cfg),
+            exec: guarded(shell::ExecTool::from_config(&cfg), &cfg),
+            web_search: guarded(
+                web::WebSearchTool::new(cfg.brave_api_key.clone(), cfg.network_web.clone()),
+                &cfg,
+            ),
+            web_fetch: guarded(web::WebFetchTool::new(cfg.network_web.clone()), &cfg),
+            cron: guarded(cron::CronTool::new(cron_service.clone()), &cfg),
+            send_message: guarded(
+                send::SendMessageTool::new(
+                    bus.clone(),
+                    notifier.clone(),
+                    delivery_failures,
+                    allowed_dir,
+                ),
+                &cfg,
+            ),
+            share_file: guarded(
+                share::ShareFileTool::new(bus.clone(), notifier.clone(), cfg.clone()),
+                &cfg,
+            ),
+            set_timer: guarded(
+                timer::TimerTool::new(bus.clone(), cron_service.clone()),
+                &cfg,
+            ),
+            contacts: guarded(contacts::ContactsTool::new(cfg.workspace_dir.clone()), &cfg),
+            track_expense: guarded(
+                expense::ExpenseTool::new(
+                    cfg.workspace_dir.clone(),
+                    cfg.expense_default_currency.clone(),
+                ),
+                &cfg,
+            ),
+            habits: guarded(
+                habits::HabitsTool::new(cfg.workspace_dir.clone(), cron_service.clone()),
+                &cfg,
+            ),
+            summarize_url: guarded(
+                summarize::SummarizeUrlTool::new(
+                    crate::memory::client::OpenRouterClient::from_config(&cfg).ok(),
+                    cfg.summarize_model.clone(),
+                    cfg.network_web.clone(),
+                ),
+                &cfg,
+            ),
+            search_history: guarded(
+                history::SearchHistoryTool::new(
+                    cfg.clone(),
+                    crate::history::HistoryLog::new(&cfg.workspace_dir),
+                    vector_memory.clone(),
+                ),
+                &cfg,
+            ),
+            notification_settings: guarded(
+                notify::NotificationSettingsTool::new(notifier.clone()),
+                &cfg,
+            ),
+            current_time: guarded(
+                time::CurrentTimeTool::new(crate::clock::resolve_timezone(&cfg.timezone)),
+                &cfg,
+            ),
+            ask_form: guarded(
+                form::AskFormTool::new(bus.clone(), forms.clone(), cfg.form_timeout_secs),
+                &cfg,
+            ),
+            memory: guarded(
+                memory::MemoryTool::new(vector_memory, cfg.workspace_dir.clone()),
+                &cfg,
+            ),
+            continue_on: guarded(
+                handoff::ContinueOnTool::new(cfg.clone(), bus.clone(), handoffs.clone()),
+                &cfg,
+            ),
+            scratchpad: guarded(scratchpad::ScratchpadTool::new(&cfg.data_dir), &cfg),
+            broadcast: guarded(
+                broadcast::BroadcastTool::new(cfg.clone(), cron_service.clone()),
+                &cfg,
+            ),
+            schedule_message: guarded(
+                schedule_message::ScheduleMessageTool::new(
+                    cron_service.clone(),
+                    cfg.workspace_dir.clone(),
+                ),
+                &cfg,
+            ),
+            pending_send: guarded(
+                pending_send::PendingSendTool::new(cron_service.clone(), cfg.workspace_dir.clone()),
+                &cfg,
+            ),
+            recent_tool_results: guarded(transcript::RecentToolResultsTool, &cfg),
+            http: Vec::new(),
+        };
+        let mut taken: Vec<String> = registry.names();
+        taken.push(introspect::IntrospectTool::NAME.to_string());
+        for spec in &cfg.http_tools {
+            if !http::HttpTool::valid_name(&spec.name) {
+                warn!(
+                    "skipping http tool '{}': names may use letters, digits, _ and -",
+                    spec.name
+                );
+            } else if taken.contains(&spec.name) {
+                warn!(
+                    "skipping http tool '{}': the name is already taken",
+                    spec.name
+                );
+            } else {
+                taken.push(spec.name.clone());
+                registry
+                    .http
+                    .push(guarded(http::HttpTool::new(spec.clone(), &cfg), &cfg));
+            }
+        }
+        registry
+    }
+
+    /// Definitions of every registered tool, in registration order.
+    pub async fn definitions(&self) -> Vec<ToolDefinition> {
+        let mut definitions = vec![
+            self.read_file.definition(String::new()).await,
+            self.write_file.definition(String::new()).await,
+            self.edit_file.definition(String::new()).await,
+            self.list_dir.definition(String::new()).await,
+            self.exec.definition(String::new()).await,
+            self.web_search.definition(String::new()).await,
+            self.web_fetch.definition(String::new()).await,
+            self.cron.definition(String::new()).await,
+            self.send_message.definition(String::new()).await,
+            self.share_file.definition(String::new()).await,
+            self.set_timer.definition(String::new()).await,
+            self.contacts.definition(String::new()).await,
+            self.track_expense.definition(String::new()).await,
+            self.habits.definition(String::new()).await,
+            self.summarize_url.definition(String::new()).await,
+            self.search_history.definition(String::new()).await,
+            self.notification_settings.definition(String::new()).await,
+            self.current_time.definition(String::new()).await,
+            self.ask_form.definition(String::new()).await,
+            self.memory.definition(String::new()).await,
+            self.continue_on.definition(String::new()).await,
+            self.scratchpad.definition(String::new()).await,
+            self.broadcast.definition(String::new()).await,
+            self.schedule_message.definition(String::new()).await,
+            self.pending_send.definition(String::new()).await,
+            self.recent_tool_results.definition(String::new()).await,
+        ];
+        for tool in &self.http {
+            definitions.push(tool.definition(String::new()).await);
+        }
+        definitions
+    }
+
+    /// Names of the built-in tools.
+    fn names(&self) -> Vec<String> {
+        vec![
+            self.read_file.name(),
+            self.write_file.name(),
+            self.edit_file.name(),
+            self.list_dir.name(),
+            self.exec.name(),
+            self.web_search.name(),
+            self.web_fetch.name(),
+            self.cron.name(),
+            self.send_message.name(),
+            self.share_file.name(),
+            self.set_timer.name(),
+            self.contacts.name(),
+            self.track_expense.name(),
+            self.habits.name(),
+            self.summarize_url.name(),
+            self.search_history.name(),
+            self.notification_settings.name(),
+            self.current_time.name(),
+            self.ask_form.name(),
+            self.memory.name(),
+            self.continue_on.name(),
+            self.scratchpad.name(),
+            self.broadcast.name(),
+            self.schedule_message.name(),
+            self.pending_send.name(),
+            self.recent_tool_results.name(),
+        ]
+    }
+
+    /// The config-defined tools, for `AgentBuilder::tools`.
+    pub fn http_tools(&self) -> Vec<Box<dyn rig::tool::ToolDyn>> {
+        self.http
+            .iter()
+            .map(|tool| Box::new(tool.clone()) as Box<dyn rig::tool::ToolDyn>)
+            .collect()
+    }
+
+    /// Copy of the registry whose tools attribute schema failures to `model`
+    /// and describe themselves in `dialect`.
+    pub fn for_model(&self, model: &str, dialect: SchemaDialect) -> Self {
         Self {
-            read_file: fs::ReadFileTool::new(allowed_dir.clone()),
-            write_file: fs::WriteFileTool::new(allowed_dir.clone()),
-            edit_file: fs::EditFileTool::new(allowed_dir.clone()),
-            list_dir: fs::ListDirTool::new(allowed_dir),
-            exec: shell::ExecTool::new(cfg.exec_timeout_secs, cfg.workspace_dir.clone()),
-            web_search: web::WebSearchTool::new(cfg.brave_api_key.clone()),
-            web_fetch: web::WebFetchTool::new(),
-            cron: cron::CronTool::new(cron_service),
-            send_message: send::SendMessageTool::new(bus),
+            schema_failures: self.schema_failures.clone(),
+            forms: self.forms.clone(),
+            handoffs: self.handoffs.clone(),
+            delivery_failures: self.delivery_failures.clone(),
+            read_file: self
+                .read_file
+                .for_model(model, &self.schema_failures, dialect),
+            write_file: self
+                .write_file
+                .for_model(model, &self.schema_failures, dialect),
+            edit_file: self
+                .edit_file
+                .for_model(model, &self.schema_failures, dialect),
+            list_dir: self
+                .list_dir
+                .for_model(model, &self.schema_failures, dialect),
+            exec: self.exec.for_model(model, &self.schema_failures, dialect),
+            web_search: self
+                .web_search
+                .for_model(model, &self.schema_failures, dialect),
+            web_fetch: self
+                .web_fetch
+                .for_model(model, &self.schema_failures, dialect),
+            cron: self.cron.for_model(model, &self.schema_failures, dialect),
+            send_message: self
+                .send_message
+                .for_model(model, &self.schema_failures, dialect),
+            share_file: self
+                .share_file
+                .for_model(model, &self.schema_failures, dialect),
+            set_timer: self
+                .set_timer
+                .for_model(model, &self.schema_failures, dialect),
+            contacts: self
+                .contacts
+                .for_model(model, &self.schema_failures, dialect),
+            track_expense: self
+                .track_expense
+                .for_model(model, &self.schema_failures, dialect),
+            habits: self.habits.for_model(model, &self.schema_failures, dialect),
+            summarize_url: self
+                .summarize_url
+                .for_model(model, &self.schema_failures, dialect),
+            search_history: self
+                .search_history
+                .for_model(model, &self.schema_failures, dialect),
+            notification_settings: self.notification_settings.for_model(
+                model,
+                &self.schema_failures,
+                dialect,
+            ),
+            current_time: self
+                .current_time
+                .for_model(model, &self.schema_failures, dialect),
+            ask_form: self
+                .ask_form
+                .for_model(model, &self.schema_failures, dialect),
+            memory: self.memory.for_model(model, &self.schema_failures, dialect),
+            continue_on: self
+                .continue_on
+                .for_model(model, &self.schema_failures, dialect),
+            scratchpad: self
+                .scratchpad
+                .for_model(model, &self.schema_failures, dialect),
+            broadcast: self
+                .broadcast
+                .for_model(model, &self.schema_failures, dialect),
+            schedule_message: self.schedule_message.for_model(
+                model,
+                &self.schema_failures,
+                dialect,
+            ),
+            pending_send: self
+                .pending_send
+                .for_model(model, &self.schema_failures, dialect),
+            recent_tool_results: self.recent_tool_results.for_model(
+                model,
+                &self.schema_failures,
+                dialect,
+            ),
+            http: self
+                .http
+                .iter()
+                .map(|tool| tool.for_model(model, &self.schema_failures, dialect))
+                .collect(),
         }
     }
 }
@@ -0,0 +1,149 @@
+use crate::notify::Notifier;
+use crate::tools::ToolError;
+use chrono::{Local, NaiveTime};
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+
+#[derive(Clone)]
+pub struct NotificationSettingsTool {
+    notifier: Notifier,
+}
+
+impl NotificationSettingsTool {
+    pub fn new(notifier: Notifier) -> Self {
+        Self { notifier }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct NotificationSettingsArgs {
+    /// One of: get, set_quiet_hours, clear_quiet_hours, set_digest, clear_digest, alerts
+    pub action: String,
+    /// Channel of the chat the settings apply to
+    pub channel: String,
+    /// Chat id the settings apply to
+    pub chat_id: String,
+    /// Quiet hours start as HH:MM local time (set_quiet_hours)
+    pub start: Option<String>,
+    /// Quiet hours end as HH:MM local time (set_quiet_hours)
+    pub end: Option<String>,
+    /// Daily digest delivery time as HH:MM local time (set_digest)
+    pub time: Option<String>,
+}
+
+impl Tool for NotificationSettingsTool {
+    const NAME: &'static str = "notification_settings";
+    type Args = NotificationSettingsArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Configure per-chat delivery of proactive notifications. set_quiet_hours holds non-urgent notifications between start and end (e.g. 22:00-07:00) and delivers them together afterwards; set_digest batches all non-urgent notifications into one combined message at a daily time; get shows current settings and queued count; alerts lists important alerts for the chat with their delivery/acknowledgement status.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(NotificationSettingsArgs))
+                    .unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let notifier = self.notifier.clone();
+        async move {
+            let channel = args.channel.trim().to_string();
+            let chat_id = args.chat_id.trim().to_string();
+            if channel.is_empty() {
+                return Err(ToolError::msg("Missing required field: channel"));
+            }
+            if chat_id.is_empty() {
+                return Err(ToolError::msg("Missing required field: chat_id"));
+            }
+
+            let prefs = match args.action.trim().to_lowercase().as_str() {
+                "get" => {
+                    let prefs = notifier.prefs(&channel, &chat_id).await;
+                    let queued = notifier.queued_count(&channel, &chat_id).await;
+                    return Ok(format!("{} | queued: {}", prefs.describe(), queued));
+                }
+                "alerts" => {
+                    let alerts = notifier.alerts(&channel, &chat_id).await;
+                    if alerts.is_empty() {
+                        return Ok("No important alerts for this chat.".to_string());
+                    }
+                    return Ok(alerts
+                        .iter()
+                        .map(|a| {
+                            format!(
+                                "{} | {} | sent {}x | {}",
+                                a.id,
+                                a.status(),
+                                a.sends,
+                                a.content.lines().next().unwrap_or("").trim()
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"));
+                }
+                "set_quiet_hours" => {
+                    let start = parse_time(args.start.as_deref(), "start")?;
+                    let end = parse_time(args.end.as_deref(), "end")?;
+                    notifier
+                        .update_prefs(&channel, &chat_id, |p| {
+                            p.quiet_start = Some(start);
+                            p.quiet_end = Some(end);
+                        })
+                        .await
+                }
+                "clear_quiet_hours" => {
+                    notifier
+                        .update_prefs(&channel, &chat_id, |p| {
+                            p.quiet_start = None;
+                            p.quiet_end = None;
+                        })
+                        .await
+                }
+                "set_digest" => {
+                    let at = parse_time(args.time.as_deref(), "time")?;
+                    let now = Local::now();
+                    notifier
+                        .update_prefs(&channel, &chat_id, |p| {
+                            p.digest_at = Some(at);
+                            // Start with the next occurrence rather than
+                            // flushing immediately when enabled late in the day.
+                            p.last_digest = (now.time() >= at).then(|| now.date_naive());
+                        })
+                        .await
+                }
+                "clear_digest" => {
+                    notifier
+                        .update_prefs(&channel, &chat_id, |p| {
+                            p.digest_at = None;
+                            p.last_digest = None;
+                        })
+                        .await
+                }
+                _ => {
+                    return Ok("Invalid action. Use: get, set_quiet_hours, clear_quiet_hours, set_digest, clear_digest, alerts.".to_string())
+                }
+            };
+            Ok(format!("Updated. {}", prefs.describe()))
+        }
+    }
+}
+
+fn parse_time(raw: Option<&str>, field: &str) -> Result<NaiveTime, ToolError> {
+    let raw = raw
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| ToolError::msg(format!("Missing required field: {field}")))?;
+    NaiveTime::parse_from_str(raw, "%H:%M")
+        .map_err(|_| ToolError::msg(format!("{field} must be HH:MM")))
+}
@@ -0,0 +1,259 @@
+use crate::cron::CronService;
+use crate::tools::middleware;
+use crate::tools::schedule_message::resolve_recipient;
+use crate::tools::timer::{format_duration, parse_duration_secs};
+use crate::tools::ToolError;
+use chrono::{TimeZone, Utc};
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Longest delay a pending send may wait; later sends belong in
+/// schedule_message.
+const MAX_DELAY_SECS: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Clone)]
+pub struct PendingSendTool {
+    cron: CronService,
+    workspace_dir: PathBuf,
+}
+
+impl PendingSendTool {
+    pub fn new(cron: CronService, workspace_dir: PathBuf) -> Self {
+        Self {
+            cron,
+            workspace_dir,
+        }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct PendingSendArgs {
+    /// "queue" a message, "list" this chat's pending sends, or "cancel" one by id
+    pub action: String,
+    /// For queue: exact text to deliver when the delay runs out
+    pub message: Option<String>,
+    /// For queue: how long to wait, e.g. "30m", "2h" or "1h30m"
+    pub delay: Option<String>,
+    /// For queue: saved contact to send to (resolved through the contact book) instead of channel/chat_id
+    pub contact: Option<String>,
+    /// For queue: destination channel (e.g. "telegram", or "current" for this chat)
+    pub channel: Option<String>,
+    /// For queue: destination chat id
+    pub chat_id: Option<String>,
+    /// For cancel: id of the pending send, as returned by queue or list
+    pub id: Option<String>,
+}
+
+impl Tool for PendingSendTool {
+    const NAME: &'static str = "pending_send";
+    type Args = PendingSendArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Queue a pre-written message that goes out after a delay unless it is cancelled first (\"I'll tell Anna in 30 minutes unless you say otherwise\"). action=queue takes message, delay and a contact or channel/chat_id; action=list shows the pending sends this chat queued; action=cancel removes one by id. When the user calls it off in a later message, cancel it. Pending sends survive restarts.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(PendingSendArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let cron = self.cron.clone();
+        let workspace_dir = self.workspace_dir.clone();
+        let origin =
+            middleware::current_chat().map(|chat| format!("{}:{}", chat.channel, chat.chat_id));
+        async move {
+            let Some(origin) = origin else {
+                return Err(ToolError::msg(
+                    "pending_send needs a chat that can cancel the message; this turn has none.",
+                ));
+            };
+            match args.action.trim() {
+                "queue" => {
+                    let message = args.message.as_deref().map(str::trim).unwrap_or_default();
+                    if message.is_empty() {
+                        return Err(ToolError::msg("Missing required field: message"));
+                    }
+                    let raw_delay = args.delay.as_deref().unwrap_or_default();
+                    let secs = parse_duration_secs(raw_delay).ok_or_else(|| {
+                        ToolError::msg(format!(
+                            "Invalid delay '{raw_delay}'. Use forms like 30m, 2h or 1h30m."
+                        ))
+                    })?;
+                    if secs == 0 {
+                        return Err(ToolError::msg("Delay must be greater than zero"));
+                    }
+                    if secs > MAX_DELAY_SECS {
+                        return Err(ToolError::msg(
+                            "Delay exceeds 7 days; use schedule_message for later sends",
+                        ));
+                    }
+                    let (channel, chat_id, to) = resolve_recipient(
+                        &workspace_dir,
+                        args.contact,
+                        args.channel,
+                        args.chat_id,
+                    )?;
+                    let at = Utc::now() + chrono::Duration::seconds(secs as i64);
+                    let preview: String = message.chars().take(40).collect();
+                    let id = cron
+                        .add_pending_send(
+                            format!("pending send to {to}: {preview}"),
+                            at.timestamp_millis(),
+                            message.to_string(),
+                            channel,
+                            chat_id,
+                            origin,
+                        )
+                        .await
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    Ok(format!(
+                        "Pending send {id} to {to} goes out in {} (at {}) unless cancelled with action=cancel, id={id}.",
+                        format_duration(secs),
+                        at.to_rfc3339()
+                    ))
+                }
+                "list" => {
+                    let jobs = cron
+                        .pending_sends(&origin)
+                        .await
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    if jobs.is_empty() {
+                        return Ok("No pending sends from this chat.".to_string());
+                    }
+                    let mut out = String::from("Pending sends from this chat:\n");
+                    for job in jobs {
+                        let at = job
+                            .state
+                            .next_run_at_ms
+                            .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+                            .map(|at| at.to_rfc3339())
+                            .unwrap_or_else(|| "?".to_string());
+                        out.push_str(&format!("- {} at {at}: {}\n", job.id, job.name));
+                    }
+                    Ok(out)
+                }
+                "cancel" => {
+                    let id = args.id.as_deref().map(str::trim).unwrap_or_default();
+                    if id.is_empty() {
+                        return Err(ToolError::msg("Missing required field: id"));
+                    }
+                    let pending = cron
+                        .pending_sends(&origin)
+                        .await
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    if !pending.iter().any(|job| job.id == id) {
+                        return Err(ToolError::msg(format!(
+                            "No pending send {id} from this chat; it may have gone out already. Use action=list."
+                        )));
+                    }
+                    cron.remove_job(id)
+                        .await
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    Ok(format!("Pending send {id} cancelled; it will not be sent."))
+                }
+                other => Err(ToolError::msg(format!(
+                    "Unknown action '{other}'. Use queue, list or cancel."
+                ))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PendingSendArgs, PendingSendTool};
+    use crate::bus::MessageBus;
+    use crate::config::AppConfig;
+    use crate::cron::CronService;
+    use crate::tools::form::FormBroker;
+    use crate::tools::middleware::{scoped, TurnChat};
+    use crate::tools::transcript::ToolTranscript;
+    use rig::tool::Tool;
+
+    fn args(action: &str) -> PendingSendArgs {
+        PendingSendArgs {
+            action: action.to_string(),
+            message: None,
+            delay: None,
+            contact: None,
+            channel: None,
+            chat_id: None,
+            id: None,
+        }
+    }
+
+    fn chat(chat_id: &str) -> Option<TurnChat> {
+        Some(TurnChat {
+            bus: MessageBus::new(),
+            forms: FormBroker::default(),
+            channel: "telegram".to_string(),
+            chat_id: chat_id.to_string(),
+            sender_id: "1".to_string(),
+            session: format!("telegram:{chat_id}"),
+            exec_profile: "normal".to_string(),
+            persona: None,
+            transcript: ToolTranscript::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn only_the_queueing_chat_sees_and_cancels_its_sends() {
+        let dir = std::env::temp_dir().join(format!("femtobot-pending-{}", uuid::Uuid::new_v4()));
+        let mut cfg = AppConfig::defaults();
+        cfg.data_dir = dir.clone();
+        cfg.workspace_dir = dir.join("workspace");
+        let tool = PendingSendTool::new(
+            CronService::new(&cfg, MessageBus::new()),
+            cfg.workspace_dir.clone(),
+        );
+
+        let mut queue = args("queue");
+        queue.message = Some("Running 10 minutes late".to_string());
+        queue.delay = Some("30m".to_string());
+        queue.channel = Some("telegram".to_string());
+        queue.chat_id = Some("7".to_string());
+        let queued = scoped(chat("42"), async { tool.call(queue).await })
+            .await
+            .unwrap();
+        let id = queued.split_whitespace().nth(2).unwrap().to_string();
+        assert!(queued.contains("in 30m"));
+
+        let listed = scoped(chat("42"), async { tool.call(args("list")).await })
+            .await
+            .unwrap();
+        assert!(listed.contains(&id));
+        let other = scoped(chat("99"), async { tool.call(args("list")).await })
+            .await
+            .unwrap();
+        assert_eq!(other, "No pending sends from this chat.");
+
+        let mut cancel = args("cancel");
+        cancel.id = Some(id.clone());
+        assert!(scoped(chat("99"), async { tool.call(cancel).await })
+            .await
+            .is_err());
+        let mut cancel = args("cancel");
+        cancel.id = Some(id);
+        assert!(scoped(chat("42"), async { tool.call(cancel).await })
+            .await
+            .is_ok());
+        let listed = scoped(chat("42"), async { tool.call(args("list")).await })
+            .await
+            .unwrap();
+        assert_eq!(listed, "No pending sends from this chat.");
+        assert!(tool.call(args("list")).await.is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
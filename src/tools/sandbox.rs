@@ -0,0 +1,87 @@
+use crate::config::AppConfig;
+use crate::history::sanitize_file_stem;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+tokio::task_local! {
+    static CHAT_SANDBOX: Option<ChatSandbox>;
+}
+
+/// Per-chat file jail for non-admin senders: fs tools may read and write
+/// only inside `root`, and additionally read (never write) the shared dirs.
+#[derive(Clone, Debug)]
+pub struct ChatSandbox {
+    pub root: PathBuf,
+    pub shared_readonly: Vec<PathBuf>,
+}
+
+impl ChatSandbox {
+    /// Sandbox for a message, or `None` when sandboxing is disabled or the
+    /// sender is an admin. Keyed like the session, so chats with the same id
+    /// on different channels get different roots.
+    pub fn for_sender(
+        cfg: &AppConfig,
+        channel: &str,
+        chat_id: &str,
+        sender_id: &str,
+    ) -> Option<Self> {
+        if !cfg.sandbox_chats || cfg.is_admin(sender_id) {
+            return None;
+        }
+        let root = cfg
+            .workspace_dir
+            .join("chats")
+            .join(sanitize_file_stem(&format!("{channel}:{chat_id}")));
+        let shared_readonly = cfg
+            .sandbox_shared_readonly
+            .iter()
+            .map(|p| {
+                if p.is_absolute() {
+                    p.clone()
+                } else {
+                    cfg.workspace_dir.join(p)
+                }
+            })
+            .collect();
+        Some(Self {
+            root,
+            shared_readonly,
+        })
+    }
+
+    /// Directories a tool may touch: the chat root, plus the shared
+    /// read-only areas unless the access writes.
+    pub fn allowed_dirs(&self, write: bool) -> Vec<&Path> {
+        let mut dirs = vec![self.root.as_path()];
+        if !write {
+            dirs.extend(self.shared_readonly.iter().map(PathBuf::as_path));
+        }
+        dirs
+    }
+}
+
+/// Run `fut` (an agent turn) with `sandbox` applied to every tool call it makes.
+pub async fn scoped<F: Future>(sandbox: Option<ChatSandbox>, fut: F) -> F::Output {
+    CHAT_SANDBOX.scope(sandbox, fut).await
+}
+
+/// Sandbox of the turn currently executing, if any.
+pub fn current() -> Option<ChatSandbox> {
+    CHAT_SANDBOX.try_with(Clone::clone).ok().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChatSandbox;
+    use crate::config::AppConfig;
+
+    #[test]
+    fn same_chat_id_on_two_channels_gets_two_roots() {
+        let mut cfg = AppConfig::defaults();
+        cfg.sandbox_chats = true;
+        let telegram = ChatSandbox::for_sender(&cfg, "telegram", "12345", "7").unwrap();
+        let irc = ChatSandbox::for_sender(&cfg, "irc", "12345", "7").unwrap();
+        assert_ne!(telegram.root, irc.root);
+        assert!(telegram.root.ends_with("chats/telegram_12345"));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::contacts::ContactBook;
+use crate::cron::CronService;
+use crate::tools::ToolError;
+use chrono::{TimeZone, Utc};
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// The `(channel, chat_id, label)` a message is addressed to: a saved
+/// contact (whose channel and chat id fill in what is not given), or an
+/// explicit channel/chat_id.
+pub(crate) fn resolve_recipient(
+    workspace_dir: &Path,
+    contact: Option<String>,
+    channel: Option<String>,
+    chat_id: Option<String>,
+) -> Result<(String, String, String), ToolError> {
+    let mut channel = channel.map(|c| c.trim().to_string());
+    let mut chat_id = chat_id.map(|c| c.trim().to_string());
+    let mut recipient = None;
+    if let Some(name) = contact.as_deref().map(str::trim) {
+        let mut book = ContactBook::new(workspace_dir.to_path_buf());
+        book.load().map_err(|e| ToolError::msg(e.to_string()))?;
+        let contact = match book.find(name).as_slice() {
+            [contact] => contact.clone(),
+            [] => return Err(ToolError::msg(format!("No contact matches '{name}'"))),
+            many => {
+                let names: Vec<&str> = many.iter().map(|c| c.name.as_str()).collect();
+                return Err(ToolError::msg(format!(
+                    "'{name}' matches several contacts: {}. Use the full name.",
+                    names.join(", ")
+                )));
+            }
+        };
+        channel = channel.filter(|c| !c.is_empty()).or(contact.channel);
+        chat_id = chat_id.filter(|c| !c.is_empty()).or(contact.chat_id);
+        recipient = Some(contact.name);
+    }
+    let (Some(channel), Some(chat_id)) = (
+        channel.filter(|c| !c.is_empty()),
+        chat_id.filter(|c| !c.is_empty()),
+    ) else {
+        return Err(ToolError::msg(match recipient {
+            Some(name) => format!(
+                "Contact '{name}' has no channel and chat id; save them with the contacts tool or pass channel/chat_id."
+            ),
+            None => "Provide a contact, or both channel and chat_id.".to_string(),
+        }));
+    };
+    let label = recipient.unwrap_or_else(|| format!("{channel}:{chat_id}"));
+    Ok((channel, chat_id, label))
+}
+
+#[derive(Clone)]
+pub struct ScheduleMessageTool {
+    cron: CronService,
+    workspace_dir: PathBuf,
+}
+
+impl ScheduleMessageTool {
+    pub fn new(cron: CronService, workspace_dir: PathBuf) -> Self {
+        Self {
+            cron,
+            workspace_dir,
+        }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct ScheduleMessageArgs {
+    /// Exact text to send; it is delivered as written, with no agent turn at send time
+    pub message: String,
+    /// When to send, e.g. "at midnight", "tomorrow at 9am", "on 2026-12-24 at 18:00" or "every monday at 8am"
+    pub when: String,
+    /// Saved contact to send to (resolved through the contact book) instead of channel/chat_id
+    pub contact: Option<String>,
+    /// Destination channel (e.g. "telegram", or "current" for this chat); defaults to the contact's channel
+    pub channel: Option<String>,
+    /// Destination chat id; defaults to the contact's chat id
+    pub chat_id: Option<String>,
+}
+
+impl Tool for ScheduleMessageTool {
+    const NAME: &'static str = "schedule_message";
+    type Args = ScheduleMessageArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Schedule a pre-written message to be sent at a given time (e.g. \"send Anna 'happy birthday' at midnight\"). The text is delivered exactly as given, without running the agent when it fires, so write the final wording now. Address it to a saved contact or to channel/chat_id. Use manage_cron instead when the work itself (checking, summarizing) must happen later.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(ScheduleMessageArgs))
+                    .unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let cron = self.cron.clone();
+        let workspace_dir = self.workspace_dir.clone();
+        async move {
+            let message = args.message.trim().to_string();
+            if message.is_empty() {
+                return Err(ToolError::msg("Missing required field: message"));
+            }
+
+            let (channel, chat_id, to) =
+                resolve_recipient(&workspace_dir, args.contact, args.channel, args.chat_id)?;
+
+            let schedule = cron
+                .parse_schedule(&args.when)
+                .map_err(|e| ToolError::msg(e.to_string()))?;
+            let preview: String = message.chars().take(40).collect();
+            let job = cron
+                .add_message(
+                    format!("message to {to}: {preview}"),
+                    schedule,
+                    message,
+                    channel,
+                    chat_id,
+                )
+                .await
+                .map_err(|e| ToolError::msg(e.to_string()))?;
+            let when = match job.state.next_run_at_ms {
+                Some(ms) => Utc.timestamp_millis_opt(ms).unwrap().to_rfc3339(),
+                None => args.when.trim().to_string(),
+            };
+            let repeats = if job.delete_after_run {
+                ""
+            } else {
+                ", then on the same schedule"
+            };
+            Ok(format!(
+                "Message {} scheduled for {to} at {when}{repeats}. Cancel it with manage_cron.",
+                job.id
+            ))
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! Shapes tool definitions for the model that will read them. Every tool's
+//! description gets example calls; routes using the simple dialect
+//! (`tools.schema_dialects`) also get schemas without references, unions or
+//! nullable types, which small models tend to fill in wrongly.
+
+use crate::config::SchemaDialect;
+use rig::completion::request::ToolDefinition;
+use serde_json::{Map, Value};
+
+/// Example arguments per tool, shown after its description.
+const EXAMPLES: &[(&str, &[&str])] = &[
+    (
+        "read_file",
+        &[r#"{"path": "notes/todo.md", "offset": 40, "limit": 20}"#],
+    ),
+    (
+        "edit_file",
+        &[
+            r#"{"path": "notes/todo.md", "old_text": "- [ ] call mom", "new_text": "- [x] call mom"}"#,
+        ],
+    ),
+    (
+        "exec",
+        &[r#"{"command": "ls -la", "working_dir": "projects"}"#],
+    ),
+    (
+        "web_search",
+        &[r#"{"query": "train strike berlin today", "count": 5}"#],
+    ),
+    (
+        "manage_cron",
+        &[
+            r#"{"action": "add", "name": "standup", "schedule": "every weekday at 9am", "message": "Ask what I'm working on today"}"#,
+            r#"{"action": "remove", "id": "standup"}"#,
+        ],
+    ),
+    ("send_message", &[r#"{"content": "The build finished."}"#]),
+    ("share_file", &[r#"{"path": "notes/trip.md"}"#]),
+    (
+        "set_timer",
+        &[r#"{"duration": "10m", "message": "Take the pasta off the stove"}"#],
+    ),
+    (
+        "track_expense",
+        &[
+            r#"{"action": "add", "amount": 12.5, "category": "lunch"}"#,
+            r#"{"action": "summary", "month": "2026-03"}"#,
+        ],
+    ),
+    ("habits", &[r#"{"action": "check_in", "name": "running"}"#]),
+    (
+        "ask_form",
+        &[
+            r#"{"channel": "telegram", "chat_id": "12345", "fields": [{"name": "amount", "prompt": "How much was it?", "type": "number"}]}"#,
+        ],
+    ),
+    ("memory", &[r#"{"action": "list", "contains": "dentist"}"#]),
+    (
+        "scratchpad",
+        &[r#"{"action": "incr", "namespace": "water", "key": "glasses", "by": 1}"#],
+    ),
+    (
+        "schedule_message",
+        &[r#"{"message": "Happy birthday!", "when": "tomorrow at 9am", "contact": "Anna"}"#],
+    ),
+    (
+        "pending_send",
+        &[
+            r#"{"action": "queue", "message": "Running late, be there at 8", "delay": "30m", "contact": "Anna"}"#,
+            r#"{"action": "cancel", "id": "a1b2c3d4"}"#,
+        ],
+    ),
+];
+
+/// `definition` adjusted for a route: examples appended to the description
+/// and, for [`SchemaDialect::Simple`], the parameters simplified.
+pub fn adapt(mut definition: ToolDefinition, dialect: SchemaDialect) -> ToolDefinition {
+    if let Some((_, examples)) = EXAMPLES.iter().find(|(name, _)| *name == definition.name) {
+        for example in *examples {
+            definition.description.push_str("\nExample: ");
+            definition.description.push_str(example);
+        }
+    }
+    if dialect == SchemaDialect::Simple {
+        definition.parameters = simplify(&definition.parameters);
+    }
+    definition
+}
+
+/// Inline `$ref`s, reduce unions to a single shape, drop `null` from types
+/// and strip keys models do not need (`$schema`, `title`, `default`).
+pub fn simplify(schema: &Value) -> Value {
+    let defs = schema
+        .get("$defs")
+        .or_else(|| schema.get("definitions"))
+        .cloned()
+        .unwrap_or(Value::Null);
+    simplify_node(schema, &defs, 0)
+}
+
+/// Nested `$ref`s deeper than this are replaced by a plain object.
+const MAX_REF_DEPTH: usize = 8;
+
+fn simplify_node(node: &Value, defs: &Value, depth: usize) -> Value {
+    let Some(obj) = node.as_object() else {
+        return node.clone();
+    };
+    if let Some(target) = obj.get("$ref").and_then(Value::as_str) {
+        let name = target.rsplit('/').next().unwrap_or_default();
+        return match defs.get(name) {
+            Some(def) if depth < MAX_REF_DEPTH => {
+                let mut inlined = simplify_node(def, defs, depth + 1);
+                if let (Some(description), Some(map)) =
+                    (obj.get("description"), inlined.as_object_mut())
+                {
+                    map.insert("description".to_string(), description.clone());
+                }
+                inlined
+            }
+            _ => serde_json::json!({ "type": "object" }),
+        };
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(options)) = obj.get(key) {
+            let options: Vec<Value> = options
+                .iter()
+                .filter(|option| option.get("type").and_then(Value::as_str) != Some("null"))
+                .map(|option| simplify_node(option, defs, depth))
+                .collect();
+            let mut merged = merge_options(options);
+            if let (Some(description), Some(map)) = (obj.get("description"), merged.as_object_mut())
+            {
+                map.insert("description".to_string(), description.clone());
+            }
+            return merged;
+        }
+    }
+
+    let mut out = Map::new();
+    for (key, value) in obj {
+        match key.as_str() {
+            "$schema" | "$defs" | "definitions" | "title" | "default" => {}
+            "type" => {
+                out.insert(key.clone(), non_null_type(value));
+            }
+            "description" => {
+                let text = value.as_str().unwrap_or_default();
+                out.insert(
+                    key.clone(),
+                    Value::String(text.split_whitespace().collect::<Vec<_>>().join(" ")),
+                );
+            }
+            "properties" => {
+                let props = value
+                    .as_object()
+                    .map(|props| {
+                        props
+                            .iter()
+                            .map(|(name, prop)| (name.clone(), simplify_node(prop, defs, depth)))
+                            .collect::<Map<_, _>>()
+                    })
+                    .unwrap_or_default();
+                out.insert(key.clone(), Value::Object(props));
+            }
+            "items" => {
+                out.insert(key.clone(), simplify_node(value, defs, depth));
+            }
+            _ => {
+                out.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Value::Object(out)
+}
+
+/// `["string", "null"]` becomes `"string"`.
+fn non_null_type(value: &Value) -> Value {
+    let Some(types) = value.as_array() else {
+        return value.clone();
+    };
+    types
+        .iter()
+        .find(|t| t.as_str() != Some("null"))
+        .cloned()
+        .unwrap_or_else(|| Value::String("string".to_string()))
+}
+
+/// One schema standing in for a union: string constants become a single
+/// enum, objects one object with every property (required only where all
+/// options require it), anything else the first option.
+fn merge_options(options: Vec<Value>) -> Value {
+    let mut options = options.into_iter();
+    let Some(first) = options.next() else {
+        return serde_json::json!({ "type": "string" });
+    };
+    let rest: Vec<Value> = options.collect();
+    if rest.is_empty() {
+        return first;
+    }
+    let all = || std::iter::once(&first).chain(rest.iter());
+
+    let constants: Option<Vec<Value>> = all()
+        .map(|option| {
+            option
+                .get("const")
+                .map(|c| vec![c.clone()])
+                .or_else(|| option.get("enum").and_then(Value::as_array).cloned())
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|lists| lists.into_iter().flatten().collect());
+    if let Some(values) = constants {
+        return serde_json::json!({ "type": "string", "enum": values });
+    }
+
+    if all().all(|option| option.get("type").and_then(Value::as_str) == Some("object")) {
+        let mut properties = Map::new();
+        let mut required: Option<Vec<Value>> = None;
+        for option in all() {
+            if let Some(props) = option.get("properties").and_then(Value::as_object) {
+                for (name, prop) in props {
+                    properties
+                        .entry(name.clone())
+                        .or_insert_with(|| prop.clone());
+                }
+            }
+            let option_required = option
+                .get("required")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+            required = Some(match required {
+                None => option_required,
+                Some(prev) => prev
+                    .into_iter()
+                    .filter(|name| option_required.contains(name))
+                    .collect(),
+            });
+        }
+        return serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": required.unwrap_or_default(),
+        });
+    }
+    first
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simplify;
+    use serde_json::json;
+
+    #[test]
+    fn simplify_inlines_refs_and_drops_nullable_unions() {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Args",
+            "type": "object",
+            "$defs": {
+                "Field": {"type": "object", "properties": {"name": {"type": "string"}}}
+            },
+            "properties": {
+                "note": {"type": ["string", "null"], "description": "A\n  note"},
+                "field": {"anyOf": [{"$ref": "#/$defs/Field"}, {"type": "null"}]},
+                "mode": {"oneOf": [{"const": "fast"}, {"const": "slow"}]}
+            },
+            "required": ["mode"]
+        });
+        let simple = simplify(&schema);
+        assert!(simple.get("$schema").is_none());
+        assert!(simple.get("$defs").is_none());
+        assert!(simple.get("title").is_none());
+        let props = &simple["properties"];
+        assert_eq!(
+            props["note"],
+            json!({"type": "string", "description": "A note"})
+        );
+        assert_eq!(props["field"]["properties"]["name"]["type"], "string");
+        assert_eq!(
+            props["mode"],
+            json!({"type": "string", "enum": ["fast", "slow"]})
+        );
+        assert_eq!(simple["required"], json!(["mode"]));
+    }
+}
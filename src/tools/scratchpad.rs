@@ -0,0 +1,160 @@
+use crate::scratchpad::Scratchpad;
+use crate::tools::ToolError;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Clone)]
+pub struct ScratchpadTool {
+    pad: Arc<Mutex<Scratchpad>>,
+}
+
+impl ScratchpadTool {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            pad: Arc::new(Mutex::new(Scratchpad::new(data_dir))),
+        }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct ScratchpadArgs {
+    /// One of: get, set, incr, delete, list, namespaces
+    pub action: String,
+    /// Namespace grouping related keys, e.g. the automation's name (default "default")
+    pub namespace: Option<String>,
+    /// Key (required for get, set, incr, delete; prefix filter for list)
+    pub key: Option<String>,
+    /// JSON value to store with set (number, string, boolean, array or object)
+    pub value: Option<Value>,
+    /// Amount to add with incr (default 1; negative to decrement)
+    pub by: Option<f64>,
+}
+
+fn valid_name(raw: &str, what: &str) -> Result<String, ToolError> {
+    let name = raw.trim();
+    if name.is_empty() || name.len() > 128 || name.chars().any(char::is_control) {
+        return Err(ToolError::msg(format!(
+            "Invalid {what}: use 1-128 printable characters"
+        )));
+    }
+    Ok(name.to_string())
+}
+
+impl Tool for ScratchpadTool {
+    const NAME: &'static str = "scratchpad";
+    type Args = ScratchpadArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Persistent key-value state for automations and cron jobs: counters, last-seen ids, previous prices, thresholds. action=get returns {value, updated_at} (value null if unset); set stores a JSON value and returns the previous one plus changed=true/false, so \"only alert if it changed since last check\" is one call; incr adds `by` to a number; delete removes a key; list shows keys (optionally by prefix); namespaces lists namespaces. Use a namespace per automation. Prefer this over editing files for small state.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(ScratchpadArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let pad = self.pad.clone();
+        async move {
+            let action = args.action.trim().to_lowercase();
+            let namespace =
+                valid_name(args.namespace.as_deref().unwrap_or("default"), "namespace")?;
+            let key = || {
+                args.key
+                    .as_deref()
+                    .ok_or_else(|| ToolError::msg("Missing required field: key"))
+                    .and_then(|key| valid_name(key, "key"))
+            };
+            let mut pad = pad.lock().await;
+            pad.load().map_err(|e| ToolError::msg(e.to_string()))?;
+
+            let result = match action.as_str() {
+                "get" => {
+                    let key = key()?;
+                    match pad.get(&namespace, &key) {
+                        Some(entry) => json!({
+                            "key": key,
+                            "value": entry.value,
+                            "updated_at": entry.updated_at.to_rfc3339(),
+                        }),
+                        None => json!({ "key": key, "value": Value::Null }),
+                    }
+                }
+                "set" => {
+                    let key = key()?;
+                    let value = args
+                        .value
+                        .ok_or_else(|| ToolError::msg("Missing required field: value"))?;
+                    let previous = pad
+                        .set(&namespace, &key, value.clone())
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    let changed = previous.as_ref().is_none_or(|p| p.value != value);
+                    json!({
+                        "key": key,
+                        "value": value,
+                        "previous": previous.as_ref().map(|p| &p.value),
+                        "previous_updated_at": previous.as_ref().map(|p| p.updated_at.to_rfc3339()),
+                        "changed": changed,
+                    })
+                }
+                "incr" => {
+                    let key = key()?;
+                    let value = pad
+                        .increment(&namespace, &key, args.by.unwrap_or(1.0))
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    json!({ "key": key, "value": value })
+                }
+                "delete" => {
+                    let key = key()?;
+                    let deleted = pad
+                        .delete(&namespace, &key)
+                        .map_err(|e| ToolError::msg(e.to_string()))?;
+                    json!({ "key": key, "deleted": deleted })
+                }
+                "list" => {
+                    let prefix = args.key.as_deref().unwrap_or("").trim();
+                    let entries: Vec<Value> = pad
+                        .list(&namespace, prefix)
+                        .into_iter()
+                        .map(|(key, entry)| {
+                            json!({
+                                "key": key,
+                                "value": entry.value,
+                                "updated_at": entry.updated_at.to_rfc3339(),
+                            })
+                        })
+                        .collect();
+                    json!({ "namespace": namespace, "entries": entries })
+                }
+                "namespaces" => {
+                    let namespaces: Vec<Value> = pad
+                        .namespaces()
+                        .into_iter()
+                        .map(|(name, keys)| json!({ "namespace": name, "keys": keys }))
+                        .collect();
+                    json!(namespaces)
+                }
+                _ => {
+                    return Err(ToolError::msg(
+                        "Invalid action. Use: get, set, incr, delete, list, namespaces.",
+                    ))
+                }
+            };
+            serde_json::to_string(&result).map_err(|e| ToolError::msg(e.to_string()))
+        }
+    }
+}
@@ -1,28 +1,284 @@
-use crate::bus::{MessageBus, OutboundMessage};
+use crate::bus::{Attachment, DeliveryReport, MessageBus, MessageMeta, OutboundMessage};
+use crate::notify::Notifier;
+use crate::tools::fs::resolve_readable;
+use crate::tools::middleware;
 use crate::tools::ToolError;
 use rig::completion::request::ToolDefinition;
 use rig::tool::Tool;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a delivered idempotency key suppresses repeats.
+const DEDUP_WINDOW: Duration = Duration::from_secs(6 * 60 * 60);
+const MAX_BUTTONS: usize = 12;
+/// Telegram's limit on callback data, which carries the label back.
+const MAX_BUTTON_BYTES: usize = 64;
+/// Telegram's upload limit for bots; larger files cannot be sent.
+const MAX_ATTACHMENT_BYTES: u64 = 50 * 1024 * 1024;
+/// How long a sent message waits for its delivery report.
+const DELIVERY_WATCH: Duration = Duration::from_secs(60 * 60);
+
+tokio::task_local! {
+    static TURN: Arc<TurnSends>;
+}
+
+/// Sends made by the current agent turn. A retried turn replays its tool
+/// calls from the start, so the n-th message to a chat in one attempt gets
+/// the same key as the n-th message of the previous attempt.
+struct TurnSends {
+    id: String,
+    counts: Mutex<HashMap<String, usize>>,
+}
+
+/// Run `fut` (an agent turn, including its retries) under `turn_id`.
+pub async fn in_turn<F: Future>(turn_id: String, fut: F) -> F::Output {
+    let turn = Arc::new(TurnSends {
+        id: turn_id,
+        counts: Mutex::new(HashMap::new()),
+    });
+    TURN.scope(turn, fut).await
+}
+
+/// Mark the start of a (re)try of the current turn.
+pub fn begin_attempt() {
+    let _ = TURN.try_with(|turn| {
+        if let Ok(mut counts) = turn.counts.lock() {
+            counts.clear();
+        }
+    });
+}
+
+fn turn_key(target: &str) -> Option<String> {
+    TURN.try_with(|turn| {
+        let mut counts = turn.counts.lock().ok()?;
+        let n = counts.entry(target.to_string()).or_insert(0);
+        *n += 1;
+        Some(format!("turn:{}:{target}:{n}", turn.id))
+    })
+    .ok()
+    .flatten()
+}
+
+/// Idempotency keys of recently delivered messages.
+#[derive(Clone, Default)]
+pub struct SentLedger {
+    sent: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl SentLedger {
+    fn contains(&self, key: &str) -> bool {
+        self.sent
+            .lock()
+            .map(|sent| sent.get(key).is_some_and(|at| at.elapsed() < DEDUP_WINDOW))
+            .unwrap_or(false)
+    }
+
+    fn insert(&self, key: String) {
+        if let Ok(mut sent) = self.sent.lock() {
+            sent.retain(|_, at| at.elapsed() < DEDUP_WINDOW);
+            sent.insert(key, Instant::now());
+        }
+    }
+}
+
+/// A sent message still waiting for its delivery report.
+struct PendingSend {
+    /// `channel:chat_id` of the turn that sent it.
+    origin: String,
+    target: String,
+    content: String,
+    sent_at: Instant,
+}
+
+#[derive(Default)]
+struct DeliveryState {
+    pending: HashMap<String, PendingSend>,
+    /// Undelivered-message notes per originating chat, shown on its next turn.
+    failed: HashMap<String, Vec<String>>,
+}
+
+/// Delivery failures of messages this tool already reported as sent, so
+/// the agent can learn on the chat's next turn that they never arrived.
+#[derive(Clone, Default)]
+pub struct DeliveryFailures {
+    state: Arc<Mutex<DeliveryState>>,
+}
+
+impl DeliveryFailures {
+    /// Follow delivery reports on `bus` in the background.
+    pub fn watch(bus: &MessageBus) -> Self {
+        let failures = Self::default();
+        let watcher = failures.clone();
+        let mut reports = bus.subscribe_deliveries();
+        tokio::spawn(async move {
+            loop {
+                match reports.recv().await {
+                    Ok(report) => watcher.settle(&report),
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        failures
+    }
+
+    fn track(&self, correlation_id: String, origin: String, msg: &OutboundMessage) {
+        if let Ok(mut state) = self.state.lock() {
+            state
+                .pending
+                .retain(|_, sent| sent.sent_at.elapsed() < DELIVERY_WATCH);
+            state.pending.insert(
+                correlation_id,
+                PendingSend {
+                    origin,
+                    target: format!("{}:{}", msg.channel, msg.chat_id),
+                    content: msg.content.clone(),
+                    sent_at: Instant::now(),
+                },
+            );
+        }
+    }
+
+    fn settle(&self, report: &DeliveryReport) {
+        let Some(id) = &report.correlation_id else {
+            return;
+        };
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let Some(sent) = state.pending.remove(id) else {
+            return;
+        };
+        if report.delivered {
+            return;
+        }
+        let mut preview: String = sent.content.chars().take(80).collect();
+        if preview.len() < sent.content.len() {
+            preview.push('…');
+        }
+        let reason = report
+            .error
+            .as_deref()
+            .map(|err| format!(": {err}"))
+            .unwrap_or_default();
+        state.failed.entry(sent.origin).or_default().push(format!(
+            "Your earlier send_message to {} (\"{preview}\") was reported as sent but was not delivered{reason}. Retry it or tell the user.",
+            sent.target
+        ));
+    }
+
+    /// Take the pending failure notes for turns in `channel:chat_id`.
+    pub fn take(&self, channel: &str, chat_id: &str) -> Vec<String> {
+        self.state
+            .lock()
+            .ok()
+            .and_then(|mut state| state.failed.remove(&format!("{channel}:{chat_id}")))
+            .unwrap_or_default()
+    }
+}
 
 #[derive(Clone)]
 pub struct SendMessageTool {
     bus: MessageBus,
+    notifier: Notifier,
+    ledger: SentLedger,
+    failures: DeliveryFailures,
+    /// Attachments are limited to this directory, like the file tools.
+    allowed_dir: Option<PathBuf>,
 }
 
 impl SendMessageTool {
-    pub fn new(bus: MessageBus) -> Self {
-        Self { bus }
+    pub fn new(
+        bus: MessageBus,
+        notifier: Notifier,
+        failures: DeliveryFailures,
+        allowed_dir: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            bus,
+            notifier,
+            ledger: SentLedger::default(),
+            failures,
+            allowed_dir,
+        }
     }
 }
 
+/// The file at `raw` as an attachment: images are sent as photos where the
+/// channel has them, everything else as a document.
+pub(crate) fn attachment(raw: &str, allowed_dir: Option<&Path>) -> Result<Attachment, String> {
+    let path = resolve_readable(raw, allowed_dir)?;
+    let meta = std::fs::metadata(&path).map_err(|e| e.to_string())?;
+    if !meta.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+    if meta.len() > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "{} is {} bytes; attachments can be at most {MAX_ATTACHMENT_BYTES}",
+            path.display(),
+            meta.len()
+        ));
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let mime = match ext.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        "gif" => Some("image/gif"),
+        "pdf" => Some("application/pdf"),
+        _ => None,
+    };
+    let kind = match mime {
+        Some(mime) if mime.starts_with("image/") && mime != "image/gif" => "photo",
+        _ => "document",
+    };
+    Ok(Attachment {
+        kind: kind.to_string(),
+        name,
+        mime: mime.map(str::to_string),
+        path: Some(path),
+    })
+}
+
 #[derive(Deserialize, schemars::JsonSchema)]
 pub struct SendMessageArgs {
-    /// Destination channel (e.g. "telegram")
+    /// Destination channel (e.g. "telegram"), or "current" for the chat this turn belongs to; omit both channel and chat_id for the current chat
+    #[serde(default)]
     pub channel: String,
-    /// Destination chat id (e.g. Telegram chat id)
+    /// Destination chat id (e.g. Telegram chat id); omit it to use the current chat's
+    #[serde(default)]
     pub chat_id: String,
     /// Message text to send
     pub content: String,
+    /// Set to true for proactive notifications so the chat's quiet hours and
+    /// digest settings apply; messages from cron-triggered turns always are
+    pub notification: Option<bool>,
+    /// Set to true for time-critical notifications that must bypass quiet hours and digests
+    pub urgent: Option<bool>,
+    /// Set to true for alerts that must not be missed: re-sent until the user
+    /// acknowledges, then escalated to the configured secondary chat
+    pub important: Option<bool>,
+    /// Quick-reply options shown as buttons under the message (e.g. three time slots); the label the user picks comes back as their next message
+    pub buttons: Option<Vec<String>>,
+    /// Stable key for this notification (e.g. "water-plants-2025-06-01"). A
+    /// message whose key was already delivered is not sent again; within a
+    /// turn, retries are recognised automatically
+    pub idempotency_key: Option<String>,
+    /// Path of a file to send along (e.g. a generated chart or PDF), resolved
+    /// like read_file paths. Images arrive as photos, other files as documents
+    pub attachment_path: Option<String>,
 }
 
 impl Tool for SendMessageTool {
@@ -38,7 +294,7 @@ impl Tool for SendMessageTool {
         async {
             ToolDefinition {
                 name: Self::NAME.to_string(),
-                description: "Send a message to a specific channel/chat. This is the delivery path for proactive notifications; in cron-triggered turns, call this tool whenever a user-visible notification should be sent.".to_string(),
+                description: "Send a message to a specific channel/chat, or to the chat this turn belongs to by omitting channel and chat_id (or passing channel=\"current\"); never guess chat ids. This is the delivery path for proactive notifications; in cron-triggered turns, call this tool whenever a user-visible notification should be sent (urgent=true only for time-critical alerts); such messages always respect the chat's quiet hours and digest mode. Pass buttons to offer quick-reply choices (e.g. time slots); the user's pick arrives as their next message. Pass attachment_path to deliver a file you created (image, PDF, CSV, ...) with the message. Use important=true for alerts that must not be missed; they are re-sent until acknowledged and then escalated. For cron notifications, pass an idempotency_key naming the occurrence (job and date) so a repeated run never delivers it twice.".to_string(),
                 parameters: serde_json::to_value(schemars::schema_for!(SendMessageArgs)).unwrap(),
             }
         }
@@ -49,6 +305,29 @@ impl Tool for SendMessageTool {
         args: Self::Args,
     ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
         let bus = self.bus.clone();
+        let notifier = self.notifier.clone();
+        let ledger = self.ledger.clone();
+        let failures = self.failures.clone();
+        let turn = middleware::current_chat();
+        let origin = turn
+            .as_ref()
+            .map(|turn| format!("{}:{}", turn.channel, turn.chat_id));
+        // Everything a cron turn sends is a proactive notification.
+        let from_cron = turn.is_some_and(|turn| turn.sender_id == "cron");
+        let target = format!("{}:{}", args.channel.trim(), args.chat_id.trim());
+        // Derived before any await so the turn scope is still visible.
+        let key = match args.idempotency_key.as_deref().map(str::trim) {
+            Some(key) if !key.is_empty() => Some(format!("key:{target}:{key}")),
+            _ => turn_key(&target),
+        }
+        .map(|key| hex::encode(Sha256::digest(key.as_bytes())));
+        // Resolved here too: the chat's sandbox is only visible in the turn scope.
+        let attachment = args
+            .attachment_path
+            .as_deref()
+            .map(str::trim)
+            .filter(|raw| !raw.is_empty())
+            .map(|raw| attachment(raw, self.allowed_dir.as_deref()));
         async move {
             let channel = args.channel.trim().to_string();
             let chat_id = args.chat_id.trim().to_string();
@@ -63,15 +342,194 @@ impl Tool for SendMessageTool {
             if content.is_empty() {
                 return Err(ToolError::msg("Missing required field: content"));
             }
+            let buttons: Vec<String> = args
+                .buttons
+                .unwrap_or_default()
+                .iter()
+                .map(|label| label.trim().to_string())
+                .filter(|label| !label.is_empty())
+                .collect();
+            if buttons.len() > MAX_BUTTONS {
+                return Err(ToolError::msg(format!(
+                    "At most {MAX_BUTTONS} buttons per message"
+                )));
+            }
+            if buttons.iter().any(|label| label.len() > MAX_BUTTON_BYTES) {
+                return Err(ToolError::msg(format!(
+                    "Button labels must be at most {MAX_BUTTON_BYTES} bytes; shorten them"
+                )));
+            }
+            let attachments = match attachment {
+                Some(Ok(attachment)) => vec![attachment],
+                Some(Err(err)) => return Err(ToolError::msg(format!("Cannot attach file: {err}"))),
+                None => Vec::new(),
+            };
 
-            bus.publish_outbound(OutboundMessage {
+            if let Some(key) = &key {
+                if ledger.contains(key) {
+                    return Ok(
+                        "Already delivered (same idempotency key); not sending again.".to_string(),
+                    );
+                }
+                ledger.insert(key.clone());
+            }
+
+            let mut msg = OutboundMessage {
                 channel,
                 chat_id,
                 content,
-            })
-            .await;
+                meta: MessageMeta {
+                    buttons,
+                    attachments,
+                    ..Default::default()
+                },
+            };
+            if args.important.unwrap_or(false) {
+                let id = notifier.send_important(msg).await;
+                let policy = notifier.policy();
+                return Ok(format!(
+                    "Important alert sent (id {}). It will be re-sent every {} min until acknowledged (up to {} re-send(s)){}.",
+                    id,
+                    policy.resend_after.as_secs() / 60,
+                    policy.max_resends,
+                    if policy.secondary.is_some() {
+                        ", then escalated to the secondary chat"
+                    } else {
+                        ""
+                    }
+                ));
+            }
+            if let Some(origin) = origin {
+                let id = format!("send-{}", uuid::Uuid::new_v4());
+                failures.track(id.clone(), origin, &msg);
+                msg.meta.correlation_id = Some(id);
+            }
+            if args.notification.unwrap_or(false) || from_cron {
+                let queued = notifier.deliver(msg, args.urgent.unwrap_or(false)).await;
+                if queued {
+                    return Ok(
+                        "Notification queued (quiet hours or digest mode is active for this chat)."
+                            .to_string(),
+                    );
+                }
+                return Ok("Message sent.".to_string());
+            }
+
+            bus.publish_outbound(msg).await;
 
             Ok("Message sent.".to_string())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        attachment, begin_attempt, in_turn, DeliveryFailures, SendMessageArgs, SendMessageTool,
+    };
+    use crate::bus::{DeliveryReport, MessageBus, OutboundMessage};
+    use crate::config::AppConfig;
+    use crate::notify::Notifier;
+    use rig::tool::Tool;
+
+    fn args(content: &str) -> SendMessageArgs {
+        SendMessageArgs {
+            channel: "telegram".to_string(),
+            chat_id: "42".to_string(),
+            content: content.to_string(),
+            notification: None,
+            urgent: None,
+            important: None,
+            buttons: None,
+            idempotency_key: None,
+            attachment_path: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn retried_turn_does_not_send_twice() {
+        let bus = MessageBus::new();
+        let tool = SendMessageTool::new(
+            bus.clone(),
+            Notifier::new(&AppConfig::defaults(), bus.clone()),
+            DeliveryFailures::default(),
+            None,
+        );
+        let replies = in_turn("t1".to_string(), async {
+            let mut replies = Vec::new();
+            for text in ["Water the plants", "Time to water the plants!"] {
+                begin_attempt();
+                replies.push(tool.call(args(text)).await.unwrap());
+            }
+            replies
+        })
+        .await;
+        assert_eq!(replies[0], "Message sent.");
+        assert!(replies[1].starts_with("Already delivered"));
+
+        let mut keyed = args("Water the plants");
+        keyed.idempotency_key = Some("plants-2025-06-01".to_string());
+        assert_eq!(tool.call(keyed).await.unwrap(), "Message sent.");
+        let mut again = args("Water the plants");
+        again.idempotency_key = Some("plants-2025-06-01".to_string());
+        assert!(tool
+            .call(again)
+            .await
+            .unwrap()
+            .starts_with("Already delivered"));
+        assert_eq!(tool.call(args("Unkeyed")).await.unwrap(), "Message sent.");
+    }
+
+    #[test]
+    fn failed_delivery_is_noted_for_the_sending_chat() {
+        let failures = DeliveryFailures::default();
+        let msg = OutboundMessage {
+            channel: "telegram".to_string(),
+            chat_id: "7".to_string(),
+            content: "Dinner is at 8".to_string(),
+            ..Default::default()
+        };
+        failures.track("send-1".to_string(), "telegram:42".to_string(), &msg);
+        failures.track("send-2".to_string(), "telegram:42".to_string(), &msg);
+        let report = |id: &str, delivered: bool| DeliveryReport {
+            channel: msg.channel.clone(),
+            chat_id: msg.chat_id.clone(),
+            content: msg.content.clone(),
+            correlation_id: Some(id.to_string()),
+            delivered,
+            error: (!delivered).then(|| "Forbidden: bot was blocked by the user".to_string()),
+            message_ids: Vec::new(),
+        };
+        failures.settle(&report("send-1", true));
+        failures.settle(&report("send-2", false));
+
+        assert!(failures.take("telegram", "7").is_empty());
+        let notes = failures.take("telegram", "42");
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].contains("telegram:7"));
+        assert!(notes[0].contains("bot was blocked"));
+        assert!(failures.take("telegram", "42").is_empty());
+    }
+
+    #[test]
+    fn attachments_stay_in_the_workspace() {
+        let dir = std::env::temp_dir().join(format!("femtobot-attach-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("chart.PNG"), b"png").unwrap();
+        std::fs::write(dir.join("report.csv"), b"a,b").unwrap();
+
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        let chart = attachment(&path("chart.PNG"), Some(&dir)).unwrap();
+        assert_eq!(
+            (chart.kind.as_str(), chart.name.as_str()),
+            ("photo", "chart.PNG")
+        );
+        assert_eq!(
+            attachment(&path("report.csv"), Some(&dir)).unwrap().kind,
+            "document"
+        );
+        assert!(attachment(&path("missing.pdf"), Some(&dir)).is_err());
+        assert!(attachment("/etc/hostname", Some(&dir)).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::bus::{Attachment, MessageBus, MessageMeta, OutboundMessage};
+use crate::config::AppConfig;
+use crate::models::format_size;
+use crate::notify::Notifier;
+use crate::tools::middleware;
+use crate::tools::sandbox;
+use crate::tools::send::attachment;
+use crate::tools::ToolError;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// The file at `raw` as a document for `sender_id`. Relative paths resolve
+/// against the chat's files directory or the workspace. Admins may share
+/// what the file tools can read; everyone else stays in the workspace (or
+/// their chat's sandbox) even when `restrict_to_workspace` is off.
+pub(crate) fn shareable(cfg: &AppConfig, raw: &str, sender_id: &str) -> Result<Attachment, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Err("no path given".to_string());
+    }
+    let base = sandbox::current()
+        .map(|sb| sb.root)
+        .unwrap_or_else(|| cfg.workspace_dir.clone());
+    let path = if raw.starts_with('~') || PathBuf::from(raw).is_absolute() {
+        PathBuf::from(raw)
+    } else {
+        base.join(raw)
+    };
+    let allowed_dir = (cfg.restrict_to_workspace || !cfg.is_admin(sender_id))
+        .then_some(cfg.workspace_dir.as_path());
+    let mut file = attachment(&path.to_string_lossy(), allowed_dir)?;
+    file.kind = "document".to_string();
+    Ok(file)
+}
+
+#[derive(Clone)]
+pub struct ShareFileTool {
+    bus: MessageBus,
+    notifier: Notifier,
+    cfg: AppConfig,
+}
+
+impl ShareFileTool {
+    pub fn new(bus: MessageBus, notifier: Notifier, cfg: AppConfig) -> Self {
+        Self { bus, notifier, cfg }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct ShareFileArgs {
+    /// Path of the file to send, relative to the workspace (e.g. "notes/trip.md") or absolute
+    pub path: String,
+    /// Short text sent with the file; defaults to the file name
+    pub caption: Option<String>,
+}
+
+impl Tool for ShareFileTool {
+    const NAME: &'static str = "share_file";
+    type Args = ShareFileArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Send a workspace file (notes, exports, reports you wrote) to the current chat as a document, so the user can download it. Use this when the user asks for a file itself rather than its contents.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(ShareFileArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let bus = self.bus.clone();
+        let notifier = self.notifier.clone();
+        let chat = middleware::current_chat();
+        // Resolved here: the chat's sandbox is only visible in the turn scope.
+        let file = chat
+            .as_ref()
+            .map(|chat| shareable(&self.cfg, &args.path, &chat.sender_id));
+        async move {
+            let (Some(chat), Some(file)) = (chat, file) else {
+                return Err(ToolError::msg(
+                    "share_file needs a chat to send to; this turn has none.",
+                ));
+            };
+            let file = file.map_err(|err| ToolError::msg(format!("Cannot share file: {err}")))?;
+            let size = file
+                .path
+                .as_ref()
+                .and_then(|path| std::fs::metadata(path).ok())
+                .map(|meta| format_size(meta.len()))
+                .unwrap_or_default();
+            let name = file.name.clone();
+            let content = args
+                .caption
+                .map(|caption| caption.trim().to_string())
+                .filter(|caption| !caption.is_empty())
+                .unwrap_or_else(|| name.clone());
+            let msg = OutboundMessage {
+                channel: chat.channel,
+                chat_id: chat.chat_id,
+                content,
+                meta: MessageMeta {
+                    attachments: vec![file],
+                    ..Default::default()
+                },
+            };
+            // Files from cron turns wait for quiet hours and digests too.
+            if chat.sender_id == "cron" {
+                if notifier.deliver(msg, false).await {
+                    return Ok(format!(
+                        "Queued {name} ({size}) for the chat's quiet hours or digest."
+                    ));
+                }
+            } else {
+                bus.publish_outbound(msg).await;
+            }
+            Ok(format!("Sent {name} ({size}) to the chat."))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::shareable;
+    use crate::config::AppConfig;
+
+    #[test]
+    fn only_admins_reach_outside_the_workspace() {
+        let dir = std::env::temp_dir().join(format!("femtobot-share-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("notes")).unwrap();
+        std::fs::write(dir.join("notes/trip.md"), b"# Trip").unwrap();
+        let outside =
+            std::env::temp_dir().join(format!("femtobot-share-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&outside, b"secret").unwrap();
+        let mut cfg = AppConfig::defaults();
+        cfg.workspace_dir = dir.clone();
+        cfg.restrict_to_workspace = false;
+        cfg.admins = vec!["1".to_string()];
+
+        let file = shareable(&cfg, "notes/trip.md", "2").unwrap();
+        assert_eq!(
+            (file.kind.as_str(), file.name.as_str()),
+            ("document", "trip.md")
+        );
+        let outside_path = outside.to_string_lossy();
+        assert!(shareable(&cfg, &outside_path, "2").is_err());
+        assert!(shareable(&cfg, "../../etc/hostname", "2").is_err());
+        assert!(shareable(&cfg, &outside_path, "1").is_ok());
+        cfg.restrict_to_workspace = true;
+        assert!(shareable(&cfg, &outside_path, "1").is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+        let _ = std::fs::remove_file(outside);
+    }
+}
@@ -0,0 +1,395 @@
+use crate::config::NetworkPolicy;
+use crate::memory::client::{ChatMessage, OpenRouterClient};
+use crate::tools::web::{build_fetch_client, html_to_text, send_with_retries, validate_url};
+use crate::tools::ToolError;
+use anyhow::{anyhow, Result};
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+/// Characters per map-step chunk (~3k tokens).
+const CHUNK_CHARS: usize = 12_000;
+/// Hard cap on chunks so a huge document cannot fan out unbounded requests.
+const MAX_CHUNKS: usize = 40;
+/// Maximum number of chunk summaries requested concurrently.
+const MAX_PARALLEL: usize = 4;
+/// Combined summaries above this size are reduced again before the final merge.
+const REDUCE_CHARS: usize = 16_000;
+const MAX_DOWNLOAD_BYTES: usize = 25 * 1024 * 1024;
+
+const MAP_PROMPT: &str = "Summarize the following section of a longer document. Keep concrete facts, numbers, names and conclusions; drop boilerplate and navigation text. Reply with concise bullet points only.";
+const REDUCE_PROMPT: &str = "Below are summaries of consecutive sections of one document. Merge them into a single coherent summary: remove duplication, keep the most important facts, and preserve the original order of ideas.";
+
+#[derive(Clone)]
+pub struct SummarizeUrlTool {
+    client: Option<OpenRouterClient>,
+    model: String,
+    network: NetworkPolicy,
+}
+
+impl SummarizeUrlTool {
+    pub fn new(client: Option<OpenRouterClient>, model: String, network: NetworkPolicy) -> Self {
+        Self {
+            client,
+            model,
+            network,
+        }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct SummarizeUrlArgs {
+    /// URL of the web page or PDF to summarize
+    pub url: String,
+    /// Optional focus for the summary (e.g. "pricing details")
+    pub focus: Option<String>,
+}
+
+impl Tool for SummarizeUrlTool {
+    const NAME: &'static str = "summarize_url";
+    type Args = SummarizeUrlArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Fetch a web page or PDF and return a summary, even for documents far larger than the context window (chunked map-reduce with a cheap model). Prefer this over web_fetch when the user wants the gist of a long document.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(SummarizeUrlArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let client = self.client.clone();
+        let model = self.model.clone();
+        let network = self.network.clone();
+        async move {
+            let Some(client) = client else {
+                return Ok("Error: summarizer is not configured".to_string());
+            };
+            if let Err(err) = validate_url(&args.url) {
+                return Ok(format!("Error: URL validation failed: {err}"));
+            }
+
+            let text = fetch_document_text(&args.url, &network)
+                .await
+                .map_err(|e| ToolError::msg(e.to_string()))?;
+            if text.trim().is_empty() {
+                return Ok(format!("Error: no readable text found at {}", args.url));
+            }
+
+            let chunks = chunk_text(&text, CHUNK_CHARS);
+            let truncated = chunks.len() > MAX_CHUNKS;
+            let chunks: Vec<String> = chunks.into_iter().take(MAX_CHUNKS).collect();
+            info!(
+                "summarize_url: url={} chars={} chunks={} truncated={}",
+                args.url,
+                text.len(),
+                chunks.len(),
+                truncated
+            );
+
+            let focus = args.focus.filter(|f| !f.trim().is_empty());
+            let summary = map_reduce(&client, &model, chunks, focus.as_deref())
+                .await
+                .map_err(|e| ToolError::msg(e.to_string()))?;
+
+            let mut out = format!("Summary of {}:\n\n{}", args.url, summary.trim());
+            if truncated {
+                out.push_str(&format!(
+                    "\n\n(Note: only the first {} sections were summarized.)",
+                    MAX_CHUNKS
+                ));
+            }
+            Ok(out)
+        }
+    }
+}
+
+async fn fetch_document_text(url: &str, network: &NetworkPolicy) -> Result<String> {
+    let client = build_fetch_client(network)?;
+    let mut res = send_with_retries(network, client.get(url))
+        .await?
+        .error_for_status()?;
+    if let Some(len) = res
+        .content_length()
+        .filter(|len| *len > MAX_DOWNLOAD_BYTES as u64)
+    {
+        return Err(anyhow!(
+            "document too large ({len} bytes, max {MAX_DOWNLOAD_BYTES})"
+        ));
+    }
+    let ctype = res
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    // Content-Length may be missing or wrong, so the body is counted too.
+    let mut bytes = Vec::new();
+    while let Some(chunk) = res.chunk().await? {
+        if bytes.len() + chunk.len() > MAX_DOWNLOAD_BYTES {
+            return Err(anyhow!(
+                "document too large (more than {MAX_DOWNLOAD_BYTES} bytes)"
+            ));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    if ctype.contains("application/pdf") || bytes.starts_with(b"%PDF") {
+        return pdf_to_text(&bytes).await;
+    }
+
+    let body = String::from_utf8_lossy(&bytes).to_string();
+    let lower = body.trim_start().to_ascii_lowercase();
+    if ctype.contains("text/html") || lower.starts_with("<!doctype") || lower.starts_with("<html") {
+        return Ok(html_to_text(&body));
+    }
+    Ok(body)
+}
+
+/// Extract PDF text with poppler's `pdftotext`, which keeps the binary free of
+/// a heavyweight PDF parser.
+pub async fn pdf_to_text(bytes: &[u8]) -> Result<String> {
+    let mut child = tokio::process::Command::new("pdftotext")
+        .args(["-layout", "-", "-"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow!("PDF support requires `pdftotext` (poppler-utils): {e}"))?;
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("failed to open pdftotext stdin"))?;
+    let data = bytes.to_vec();
+    let writer = tokio::spawn(async move {
+        let _ = stdin.write_all(&data).await;
+    });
+    let output = child.wait_with_output().await?;
+    let _ = writer.await;
+    if !output.status.success() {
+        return Err(anyhow!("pdftotext failed to extract text"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Split text into chunks of at most `max_chars`, preferring paragraph and
+/// line boundaries so sections are not cut mid-sentence.
+pub fn chunk_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        if rest.len() <= max_chars {
+            chunks.push(rest.to_string());
+            break;
+        }
+        let mut end = max_chars;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let window = &rest[..end];
+        let cut = ["\n\n", "\n", ". ", " "]
+            .iter()
+            .filter_map(|sep| window.rfind(sep).map(|pos| pos + sep.len()))
+            .find(|pos| *pos > end / 2)
+            .unwrap_or(end);
+        chunks.push(rest[..cut].trim().to_string());
+        rest = rest[cut..].trim_start();
+    }
+    chunks.retain(|c| !c.is_empty());
+    chunks
+}
+
+async fn map_reduce(
+    client: &OpenRouterClient,
+    model: &str,
+    chunks: Vec<String>,
+    focus: Option<&str>,
+) -> Result<String> {
+    let mut summaries = summarize_all(client, model, MAP_PROMPT, chunks, focus).await?;
+
+    // Collapse intermediate summaries until they fit in a single merge call.
+    while summaries.len() > 1 && summaries.iter().map(String::len).sum::<usize>() > REDUCE_CHARS {
+        let mut groups = Vec::new();
+        let mut current = String::new();
+        for summary in summaries {
+            if !current.is_empty() && current.len() + summary.len() > REDUCE_CHARS {
+                groups.push(std::mem::take(&mut current));
+            }
+            current.push_str(&summary);
+            current.push_str("\n\n");
+        }
+        if !current.is_empty() {
+            groups.push(current);
+        }
+        summaries = summarize_all(client, model, REDUCE_PROMPT, groups, focus).await?;
+    }
+
+    if summaries.len() == 1 {
+        return Ok(summaries.remove(0));
+    }
+    summarize_one(client, model, REDUCE_PROMPT, &summaries.join("\n\n"), focus).await
+}
+
+async fn summarize_all(
+    client: &OpenRouterClient,
+    model: &str,
+    instructions: &'static str,
+    inputs: Vec<String>,
+    focus: Option<&str>,
+) -> Result<Vec<String>> {
+    let mut results: Vec<Option<String>> = vec![None; inputs.len()];
+    let mut pending = inputs.into_iter().enumerate();
+    let mut set = JoinSet::new();
+
+    loop {
+        while set.len() < MAX_PARALLEL {
+            let Some((idx, input)) = pending.next() else {
+                break;
+            };
+            let client = client.clone();
+            let model = model.to_string();
+            let focus = focus.map(str::to_string);
+            set.spawn(async move {
+                let out =
+                    summarize_one(&client, &model, instructions, &input, focus.as_deref()).await;
+                (idx, out)
+            });
+        }
+        let Some(joined) = set.join_next().await else {
+            break;
+        };
+        let (idx, out) = joined.map_err(|e| anyhow!("summary task failed: {e}"))?;
+        match out {
+            Ok(text) => results[idx] = Some(text),
+            Err(err) => warn!("summarize_url: section {} failed: {err}", idx + 1),
+        }
+    }
+
+    let summaries: Vec<String> = results.into_iter().flatten().collect();
+    if summaries.is_empty() {
+        return Err(anyhow!("all summarization requests failed"));
+    }
+    Ok(summaries)
+}
+
+async fn summarize_one(
+    client: &OpenRouterClient,
+    model: &str,
+    instructions: &str,
+    input: &str,
+    focus: Option<&str>,
+) -> Result<String> {
+    let mut system = instructions.to_string();
+    if let Some(focus) = focus {
+        system.push_str(&format!(" Pay particular attention to: {focus}."));
+    }
+    client
+        .chat_completion(
+            model,
+            vec![
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: system,
+                },
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: input.to_string(),
+                },
+            ],
+            800,
+            0.2,
+            None,
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk_text, fetch_document_text, MAX_DOWNLOAD_BYTES};
+    use crate::config::AppConfig;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve `head` and then `body_len` bytes once per connection.
+    async fn serve(head: &'static str, body_len: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request).await;
+                let _ = stream.write_all(head.as_bytes()).await;
+                let block = vec![b'a'; 64 * 1024];
+                let mut sent = 0;
+                while sent < body_len {
+                    let n = block.len().min(body_len - sent);
+                    if stream.write_all(&block[..n]).await.is_err() {
+                        break;
+                    }
+                    sent += n;
+                }
+            }
+        });
+        format!("http://{addr}/doc.txt")
+    }
+
+    #[test]
+    fn chunks_respect_limit_and_paragraphs() {
+        let para = "word ".repeat(30);
+        let text = format!("{para}\n\n{para}\n\n{para}");
+        let chunks = chunk_text(&text, 200);
+        assert!(chunks.len() >= 3);
+        assert!(chunks.iter().all(|c| c.len() <= 200));
+        assert_eq!(
+            chunks.join(" ").split_whitespace().count(),
+            text.split_whitespace().count()
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_documents_are_refused_while_downloading() {
+        let mut network = AppConfig::defaults().network_web;
+        network.retries = 0;
+
+        let declared = serve(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 999999999\r\n\r\n",
+            0,
+        )
+        .await;
+        let err = fetch_document_text(&declared, &network).await.unwrap_err();
+        assert!(err.to_string().contains("999999999 bytes"), "{err}");
+
+        let undeclared = serve(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\n",
+            MAX_DOWNLOAD_BYTES + 1,
+        )
+        .await;
+        let err = fetch_document_text(&undeclared, &network)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("too large"), "{err}");
+
+        let small = serve(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\n",
+            10,
+        )
+        .await;
+        assert_eq!(
+            fetch_document_text(&small, &network).await.unwrap(),
+            "aaaaaaaaaa"
+        );
+    }
+}
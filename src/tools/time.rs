@@ -0,0 +1,61 @@
+use crate::clock::describe_now;
+use crate::tools::ToolError;
+use chrono_tz::Tz;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+
+#[derive(Clone)]
+pub struct CurrentTimeTool {
+    default_tz: Option<Tz>,
+}
+
+impl CurrentTimeTool {
+    pub fn new(default_tz: Option<Tz>) -> Self {
+        Self { default_tz }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct CurrentTimeArgs {
+    /// Optional IANA timezone name (e.g. "America/New_York"); defaults to the user's timezone
+    pub timezone: Option<String>,
+}
+
+impl Tool for CurrentTimeTool {
+    const NAME: &'static str = "current_time";
+    type Args = CurrentTimeArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Get the current date and time, in the user's timezone or any other IANA timezone.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(CurrentTimeArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let default_tz = self.default_tz;
+        async move {
+            let tz = match args.timezone.as_deref().map(str::trim) {
+                Some(name) if !name.is_empty() => Some(name.parse::<Tz>().map_err(|_| {
+                    ToolError::msg(format!(
+                        "unknown timezone '{name}' (use an IANA name like Europe/Paris)"
+                    ))
+                })?),
+                _ => default_tz,
+            };
+            Ok(describe_now(tz))
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::bus::{MessageBus, OutboundMessage};
+use crate::cron::CronService;
+use crate::tools::ToolError;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::info;
+
+/// Timers at or below this duration stay in-process; longer ones are
+/// persisted through the cron store so they survive restarts.
+const IN_PROCESS_MAX_SECS: u64 = 10 * 60;
+/// Upper bound on timer length; anything longer belongs in manage_cron.
+const MAX_TIMER_SECS: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Clone)]
+pub struct TimerTool {
+    bus: MessageBus,
+    cron: CronService,
+}
+
+impl TimerTool {
+    pub fn new(bus: MessageBus, cron: CronService) -> Self {
+        Self { bus, cron }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct TimerArgs {
+    /// Delay before firing, e.g. "90s", "10m", "1h30m" or plain seconds ("600")
+    pub duration: String,
+    /// Reminder text delivered when the timer fires
+    pub message: String,
+    /// Destination channel (e.g. "telegram"); omit channel and chat_id for the current chat
+    #[serde(default)]
+    pub channel: String,
+    /// Destination chat id (e.g. Telegram chat id); omit it to use the current chat's
+    #[serde(default)]
+    pub chat_id: String,
+}
+
+impl Tool for TimerTool {
+    const NAME: &'static str = "set_timer";
+    type Args = TimerArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Set a one-shot countdown timer (e.g. 'remind me in 10 minutes'). When it fires, the message is delivered verbatim to channel/chat_id without another agent turn. Prefer this over manage_cron for relative, one-off reminders.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(TimerArgs)).unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        let bus = self.bus.clone();
+        let cron = self.cron.clone();
+        async move {
+            let channel = args.channel.trim().to_string();
+            let chat_id = args.chat_id.trim().to_string();
+            let message = args.message.trim().to_string();
+
+            if channel.is_empty() {
+                return Err(ToolError::msg("Missing required field: channel"));
+            }
+            if chat_id.is_empty() {
+                return Err(ToolError::msg("Missing required field: chat_id"));
+            }
+            if message.is_empty() {
+                return Err(ToolError::msg("Missing required field: message"));
+            }
+
+            let secs = parse_duration_secs(&args.duration).ok_or_else(|| {
+                ToolError::msg(format!(
+                    "Invalid duration '{}'. Use forms like 90s, 10m, 1h30m or plain seconds.",
+                    args.duration
+                ))
+            })?;
+            if secs == 0 {
+                return Err(ToolError::msg("Duration must be greater than zero"));
+            }
+            if secs > MAX_TIMER_SECS {
+                return Err(ToolError::msg(
+                    "Duration exceeds 7 days; use manage_cron for long-range schedules",
+                ));
+            }
+
+            let fires_at = chrono::Utc::now() + chrono::Duration::seconds(secs as i64);
+
+            if secs <= IN_PROCESS_MAX_SECS {
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_secs(secs)).await;
+                    info!("timer fired: channel={} chat_id={}", channel, chat_id);
+                    cron.note_fired(&channel, &chat_id, &truncate_name(&message), &message);
+                    bus.publish_outbound(OutboundMessage {
+                        channel,
+                        chat_id,
+                        content: format!("{message}\n\n{}", crate::cron::SNOOZE_HINT),
+                        ..Default::default()
+                    })
+                    .await;
+                });
+                return Ok(format!(
+                    "Timer set for {} (fires at {}).",
+                    format_duration(secs),
+                    fires_at.to_rfc3339()
+                ));
+            }
+
+            let id = cron
+                .add_reminder(
+                    format!("timer: {}", truncate_name(&message)),
+                    fires_at.timestamp_millis(),
+                    message,
+                    channel,
+                    chat_id,
+                )
+                .await
+                .map_err(|e| ToolError::msg(e.to_string()))?;
+            Ok(format!(
+                "Timer {} set for {} (fires at {}).",
+                id,
+                format_duration(secs),
+                fires_at.to_rfc3339()
+            ))
+        }
+    }
+}
+
+/// Parse a compact duration such as "45", "90s", "10m", "1h30m" or "2h 5m".
+pub(crate) fn parse_duration_secs(raw: &str) -> Option<u64> {
+    let compact: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if compact.is_empty() {
+        return None;
+    }
+    if let Ok(secs) = compact.parse::<u64>() {
+        return Some(secs);
+    }
+
+    let mut total = 0u64;
+    let mut digits = String::new();
+    for ch in compact.chars() {
+        if ch.is_ascii_digit() {
+            digits.push(ch);
+            continue;
+        }
+        let value = digits.parse::<u64>().ok()?;
+        digits.clear();
+        let unit = match ch {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return None,
+        };
+        total = total.checked_add(value.checked_mul(unit)?)?;
+    }
+    if !digits.is_empty() {
+        return None;
+    }
+    Some(total)
+}
+
+pub(crate) fn format_duration(secs: u64) -> String {
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    let mut parts = Vec::new();
+    if h > 0 {
+        parts.push(format!("{h}h"));
+    }
+    if m > 0 {
+        parts.push(format!("{m}m"));
+    }
+    if s > 0 || parts.is_empty() {
+        parts.push(format!("{s}s"));
+    }
+    parts.join(" ")
+}
+
+fn truncate_name(message: &str) -> String {
+    message.chars().take(40).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_duration_secs;
+
+    #[test]
+    fn parses_compound_durations() {
+        assert_eq!(parse_duration_secs("600"), Some(600));
+        assert_eq!(parse_duration_secs("90s"), Some(90));
+        assert_eq!(parse_duration_secs("1h30m"), Some(5400));
+        assert_eq!(parse_duration_secs("2h 5m"), Some(7500));
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        assert_eq!(parse_duration_secs(""), None);
+        assert_eq!(parse_duration_secs("10x"), None);
+        assert_eq!(parse_duration_secs("m"), None);
+        assert_eq!(parse_duration_secs("5m3"), None);
+    }
+}
@@ -0,0 +1,295 @@
+use crate::error::ErrorKind;
+use crate::tools::middleware;
+use crate::tools::ToolError;
+use rig::completion::request::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+const DEFAULT_LIMIT: usize = 5;
+const DEFAULT_MAX_CHARS: usize = 2_000;
+/// Output kept per recorded call; longer results are cut here already.
+const MAX_RECORDED_CHARS: usize = 20_000;
+const RECORDED_ARGS_CHARS: usize = 300;
+/// Alternations between two tools on the same path that count as thrash.
+const THRASH_CYCLES: usize = 3;
+/// After this many stopped loops, no more tool calls run in the turn.
+const MAX_LOOP_STOPS: usize = 2;
+
+/// One tool call made earlier in the turn.
+#[derive(Clone, Debug)]
+pub struct ToolRecord {
+    pub tool: String,
+    pub args: String,
+    /// Kind of failure; `None` when the call succeeded.
+    pub error: Option<ErrorKind>,
+    pub output: String,
+    /// Hash of the tool name and full arguments.
+    signature: u64,
+    /// The `path` argument, for file tools.
+    target: Option<String>,
+}
+
+/// Tool calls of the current turn, in order, shared by every tool through
+/// the turn's [`middleware::TurnChat`].
+#[derive(Clone, Default)]
+pub struct ToolTranscript {
+    records: Arc<Mutex<Vec<ToolRecord>>>,
+    /// Why calls were refused as loops, in order.
+    stops: Arc<Mutex<Vec<String>>>,
+}
+
+impl ToolTranscript {
+    pub fn record(&self, tool: &str, args: &Value, result: Result<&str, &ToolError>) {
+        let (error, output) = match result {
+            Ok(output) => (None, output.chars().take(MAX_RECORDED_CHARS).collect()),
+            Err(err) => (Some(err.kind()), err.to_string()),
+        };
+        if let Ok(mut records) = self.records.lock() {
+            records.push(ToolRecord {
+                tool: tool.to_string(),
+                args: middleware::preview(&args.to_string(), RECORDED_ARGS_CHARS),
+                error,
+                output,
+                signature: signature(tool, args),
+                target: target(args),
+            });
+        }
+    }
+
+    /// Number of calls recorded so far.
+    pub fn calls(&self) -> usize {
+        self.records.lock().map_or(0, |records| records.len())
+    }
+
+    /// Refuse a call that would continue a loop: the same call made
+    /// `max_repeats` times already (0 disables this check), or two tools
+    /// alternating on one path. The refusal is noted for
+    /// [`ToolTranscript::stops`] and returned as the reason.
+    pub fn check_loop(&self, tool: &str, args: &Value, max_repeats: usize) -> Result<(), String> {
+        let reason = {
+            let (Ok(records), Ok(stops)) = (self.records.lock(), self.stops.lock()) else {
+                return Ok(());
+            };
+            if stops.len() >= MAX_LOOP_STOPS {
+                return Err(format!(
+                    "{tool} was not run: tool calls are stopped for the rest of this turn after repeated loops. Answer now with what you have and say what is unresolved."
+                ));
+            }
+            let call = signature(tool, args);
+            let repeats = records.iter().filter(|r| r.signature == call).count();
+            let target = target(args);
+            if max_repeats > 0 && repeats >= max_repeats {
+                format!("{tool} was called {repeats} times with the same arguments")
+            } else if let Some((other, path)) = target.and_then(|t| thrash(&records, tool, t)) {
+                format!("{other} and {tool} kept alternating on {path}")
+            } else {
+                return Ok(());
+            }
+        };
+        if let Ok(mut stops) = self.stops.lock() {
+            stops.push(reason.clone());
+        }
+        Err(format!(
+            "Loop stopped: {reason} in this turn. Repeating it will not change the result; use the earlier results (get_recent_tool_results) and answer, or try a different approach."
+        ))
+    }
+
+    /// Loops stopped in this turn, for the reply and the usage log.
+    pub fn stops(&self) -> Vec<String> {
+        self.stops
+            .lock()
+            .map(|stops| stops.clone())
+            .unwrap_or_default()
+    }
+
+    /// The last `limit` calls (optionally of one tool), oldest first, each
+    /// paired with its 1-based position in the turn.
+    pub fn recent(&self, limit: usize, tool: Option<&str>) -> Vec<(usize, ToolRecord)> {
+        let Ok(records) = self.records.lock() else {
+            return Vec::new();
+        };
+        let mut matching: Vec<(usize, ToolRecord)> = records
+            .iter()
+            .enumerate()
+            .filter(|(_, record)| tool.is_none_or(|name| record.tool == name))
+            .map(|(index, record)| (index + 1, record.clone()))
+            .collect();
+        let skip = matching.len().saturating_sub(limit);
+        matching.drain(..skip);
+        matching
+    }
+}
+
+fn signature(tool: &str, args: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    tool.hash(&mut hasher);
+    args.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+fn target(args: &Value) -> Option<String> {
+    args.get("path")
+        .and_then(Value::as_str)
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+}
+
+/// Whether calling `tool` on `path` next continues an alternation of two
+/// tools on that path (read, edit, read, edit, ...) over the last calls;
+/// returns the other tool and the path.
+fn thrash(records: &[ToolRecord], tool: &str, path: String) -> Option<(String, String)> {
+    let window = THRASH_CYCLES * 2 - 1;
+    let recent = records.get(records.len().checked_sub(window)?..)?;
+    let other = &recent.last()?.tool;
+    let alternates = recent.iter().rev().enumerate().all(|(i, record)| {
+        let expected = if i % 2 == 0 { other.as_str() } else { tool };
+        record.tool == expected && record.target.as_deref() == Some(path.as_str())
+    });
+    (alternates && other != tool).then(|| (other.clone(), path))
+}
+
+#[derive(Clone, Default)]
+pub struct RecentToolResultsTool;
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct RecentToolResultsArgs {
+    /// How many of the most recent calls to return (default 5)
+    pub limit: Option<usize>,
+    /// Only calls of this tool, e.g. "web_fetch"
+    pub tool: Option<String>,
+    /// Characters of each result to include (default 2000)
+    pub max_chars: Option<usize>,
+}
+
+impl Tool for RecentToolResultsTool {
+    const NAME: &'static str = "get_recent_tool_results";
+    type Args = RecentToolResultsArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl std::future::Future<Output = ToolDefinition> + Send {
+        async {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Show the tool calls made earlier in this turn with their arguments and (truncated) results. On long multi-step turns, use it to re-check what a previous step actually returned instead of calling that tool again or relying on memory.".to_string(),
+                parameters: serde_json::to_value(schemars::schema_for!(RecentToolResultsArgs))
+                    .unwrap(),
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl std::future::Future<Output = Result<Self::Output, Self::Error>> + Send {
+        async move {
+            let Some(chat) = middleware::current_chat() else {
+                return Err(ToolError::msg("No turn is in progress."));
+            };
+            let tool = args
+                .tool
+                .as_deref()
+                .map(str::trim)
+                .filter(|t| !t.is_empty());
+            let records = chat
+                .transcript
+                .recent(args.limit.unwrap_or(DEFAULT_LIMIT).max(1), tool);
+            if records.is_empty() {
+                return Ok(match tool {
+                    Some(tool) => format!("No {tool} calls so far in this turn."),
+                    None => "No tool calls so far in this turn.".to_string(),
+                });
+            }
+            let max_chars = args.max_chars.unwrap_or(DEFAULT_MAX_CHARS).max(100);
+            let mut out = String::new();
+            for (position, record) in records {
+                let total = record.output.chars().count();
+                let mut shown: String = record.output.chars().take(max_chars).collect();
+                if total > max_chars {
+                    shown.push_str(&format!("\n... ({total} chars, truncated)"));
+                }
+                let status = match record.error {
+                    None => "ok".to_string(),
+                    Some(kind) => format!("error ({kind})"),
+                };
+                out.push_str(&format!(
+                    "#{position} {} {}: {status}\n{shown}\n\n",
+                    record.tool, record.args
+                ));
+            }
+            Ok(out.trim_end().to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ToolTranscript;
+    use crate::error::ErrorKind;
+    use crate::tools::ToolError;
+    use serde_json::json;
+
+    #[test]
+    fn keeps_order_and_filters_by_tool() {
+        let transcript = ToolTranscript::default();
+        transcript.record("web_fetch", &json!({}), Ok("page one"));
+        transcript.record(
+            "exec",
+            &json!({}),
+            Err(&ToolError::new(ErrorKind::Timeout, "timed out")),
+        );
+        transcript.record("web_fetch", &json!({}), Ok("page two"));
+
+        let last = transcript.recent(2, None);
+        assert_eq!(last.len(), 2);
+        assert_eq!((last[0].0, last[0].1.error), (2, Some(ErrorKind::Timeout)));
+        let fetches = transcript.recent(5, Some("web_fetch"));
+        assert_eq!(fetches.len(), 2);
+        assert_eq!(fetches[1].1.output, "page two");
+    }
+
+    #[test]
+    fn stops_repeated_calls_and_read_edit_thrash() {
+        let transcript = ToolTranscript::default();
+        let read = json!({"path": "notes.md"});
+        for _ in 0..3 {
+            assert!(transcript.check_loop("read_file", &read, 3).is_ok());
+            transcript.record("read_file", &read, Ok("same"));
+        }
+        let err = transcript.check_loop("read_file", &read, 3).unwrap_err();
+        assert!(err.contains("called 3 times"));
+        assert!(transcript
+            .check_loop("read_file", &json!({"path": "other.md"}), 3)
+            .is_ok());
+
+        let thrashing = ToolTranscript::default();
+        for i in 0..5 {
+            let (tool, args) = if i % 2 == 0 {
+                ("read_file", read.clone())
+            } else {
+                (
+                    "edit_file",
+                    json!({"path": "notes.md", "old_text": i.to_string()}),
+                )
+            };
+            assert!(thrashing.check_loop(tool, &args, 0).is_ok());
+            thrashing.record(tool, &args, Ok("ok"));
+        }
+        let edit = json!({"path": "notes.md", "old_text": "5"});
+        assert!(thrashing.check_loop("edit_file", &edit, 0).is_err());
+        assert_eq!(thrashing.stops().len(), 1);
+        assert!(thrashing.check_loop("edit_file", &edit, 0).is_err());
+        // After two stops, nothing runs any more.
+        let err = thrashing
+            .check_loop("web_search", &json!({"query": "x"}), 0)
+            .unwrap_err();
+        assert!(err.contains("stopped for the rest of this turn"));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::config::NetworkPolicy;
+use crate::error::ErrorKind;
 use crate::tools::ToolError;
 use html2text::from_read;
 use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, USER_AGENT};
@@ -14,11 +16,12 @@ const MAX_REDIRECTS: usize = 5;
 #[derive(Clone)]
 pub struct WebSearchTool {
     api_key: Option<String>,
+    network: NetworkPolicy,
 }
 
 impl WebSearchTool {
-    pub fn new(api_key: Option<String>) -> Self {
-        Self { api_key }
+    pub fn new(api_key: Option<String>, network: NetworkPolicy) -> Self {
+        Self { api_key, network }
     }
 }
 
@@ -82,15 +85,19 @@ impl Tool for WebSearchTool {
                 return Ok("Error: BRAVE_API_KEY not configured".to_string());
             };
             let n = args.count.unwrap_or(5).min(10).max(1);
-            let client = reqwest::Client::new();
-            let res = client
+            let client = self
+                .network
+                .client_builder()
+                .build()
+                .map_err(|e| ToolError::msg(e.to_string()))?;
+            let request = client
                 .get("https://api.search.brave.com/res/v1/web/search")
                 .query(&[("q", &args.query), ("count", &n.to_string())])
                 .header(ACCEPT, "application/json")
-                .header("X-Subscription-Token", api_key)
-                .send()
+                .header("X-Subscription-Token", api_key);
+            let res = send_with_retries(&self.network, request)
                 .await
-                .map_err(|e| ToolError::msg(e.to_string()))?;
+                .map_err(|e| ToolError::new(ErrorKind::of_reqwest(&e), e.to_string()))?;
             let status = res.status();
             if !status.is_success() {
                 return Ok(format!("Error: Brave search failed with status {status}"));
@@ -142,11 +149,13 @@ mod tests {
 }
 
 #[derive(Clone)]
-pub struct WebFetchTool;
+pub struct WebFetchTool {
+    network: NetworkPolicy,
+}
 
 impl WebFetchTool {
-    pub fn new() -> Self {
-        Self
+    pub fn new(network: NetworkPolicy) -> Self {
+        Self { network }
     }
 }
 
@@ -221,18 +230,11 @@ impl Tool for WebFetchTool {
                 .map(|m| m.trim().to_ascii_lowercase())
                 .unwrap_or_else(|| "text".to_string());
             let max_chars = args.max_chars.unwrap_or(50_000);
-            let mut headers = HeaderMap::new();
-            headers.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_UA));
-            let client = reqwest::Client::builder()
-                .default_headers(headers)
-                .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
-                .build()
-                .map_err(|e| ToolError::msg(e.to_string()))?;
-            let res = client
-                .get(&args.url)
-                .send()
+            let client =
+                build_fetch_client(&self.network).map_err(|e| ToolError::msg(e.to_string()))?;
+            let res = send_with_retries(&self.network, client.get(&args.url))
                 .await
-                .map_err(|e| ToolError::msg(e.to_string()))?;
+                .map_err(|e| ToolError::new(ErrorKind::of_reqwest(&e), e.to_string()))?;
             let status = res.status();
             let final_url = res.url().to_string();
             let ctype = res
@@ -258,8 +260,7 @@ impl Tool for WebFetchTool {
                 || text.to_ascii_lowercase().starts_with("<!doctype")
                 || text.to_ascii_lowercase().starts_with("<html")
             {
-                let rendered = from_read(text.as_bytes(), 100);
-                out_text = rendered;
+                out_text = html_to_text(&text);
                 extractor = "html2text";
             }
             let truncated = out_text.len() > max_chars;
@@ -281,7 +282,49 @@ impl Tool for WebFetchTool {
     }
 }
 
-fn validate_url(raw: &str) -> Result<(), String> {
+/// HTTP client shared by tools that fetch arbitrary web pages.
+pub(crate) fn build_fetch_client(network: &NetworkPolicy) -> reqwest::Result<reqwest::Client> {
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_UA));
+    network
+        .client_builder()
+        .default_headers(headers)
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+        .build()
+}
+
+/// Send `request`, retrying timeouts, failed connections and rate-limit or
+/// gateway errors with backoff as far as `network` allows. Only for
+/// requests that are safe to repeat.
+pub(crate) async fn send_with_retries(
+    network: &NetworkPolicy,
+    request: reqwest::RequestBuilder,
+) -> reqwest::Result<reqwest::Response> {
+    let mut retry = 0;
+    loop {
+        let Some(attempt) = request.try_clone().filter(|_| retry < network.retries) else {
+            return request.send().await;
+        };
+        match attempt.send().await {
+            Ok(res) if !is_retryable_status(res.status()) => return Ok(res),
+            Err(err) if !err.is_timeout() && !err.is_connect() => return Err(err),
+            _ => {}
+        }
+        tokio::time::sleep(network.backoff(retry)).await;
+        retry += 1;
+    }
+}
+
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 502 | 503 | 504)
+}
+
+/// Convert an HTML body to plain text at the same width web_fetch uses.
+pub(crate) fn html_to_text(html: &str) -> String {
+    from_read(html.as_bytes(), 100)
+}
+
+pub(crate) fn validate_url(raw: &str) -> Result<(), String> {
     let url = Url::parse(raw).map_err(|e| e.to_string())?;
     match url.scheme() {
         "http" | "https" => Ok(()),
@@ -1,4 +1,4 @@
-use crate::config::AppConfig;
+use crate::config::{AppConfig, NetworkPolicy};
 use anyhow::{anyhow, Context, Result};
 use reqwest::multipart;
 use rig::prelude::TranscriptionClient;
@@ -26,6 +26,7 @@ pub struct Transcriber {
     model: String,
     language: Option<String>,
     max_bytes: usize,
+    network: NetworkPolicy,
 }
 
 impl Transcriber {
@@ -49,6 +50,7 @@ impl Transcriber {
                     &cfg.openai_api_key,
                     &cfg.openai_base_url,
                     &cfg.openai_extra_headers,
+                    &cfg.network_transcription,
                 ))
             }
             "mistral" => {
@@ -57,7 +59,11 @@ impl Transcriber {
                     return None;
                 }
                 Backend::Mistral {
-                    http: reqwest::Client::new(),
+                    http: cfg
+                        .network_transcription
+                        .client_builder()
+                        .build()
+                        .unwrap_or_default(),
                     api_key: cfg.mistral_api_key.clone(),
                     base_url: cfg.mistral_base_url.clone(),
                     diarize: cfg.transcription_mistral_diarize,
@@ -78,6 +84,7 @@ impl Transcriber {
             model: cfg.transcription_model.clone(),
             language: cfg.transcription_language.clone(),
             max_bytes: cfg.transcription_max_bytes.max(1),
+            network: cfg.network_transcription.clone(),
         })
     }
 
@@ -97,6 +104,21 @@ impl Transcriber {
             ));
         }
 
+        let mut retry = 0;
+        loop {
+            match self.request(filename.clone(), data.clone()).await {
+                Err(err) if retry < self.network.retries => {
+                    let wait = self.network.backoff(retry);
+                    warn!("transcription failed ({err:#}); retrying in {wait:?}");
+                    tokio::time::sleep(wait).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn request(&self, filename: String, data: Vec<u8>) -> Result<String> {
         match &self.backend {
             Backend::OpenAI(client) => {
                 let model = client.transcription_model(self.model.clone());
@@ -170,12 +192,14 @@ fn build_openai_client(
     api_key: &str,
     base_url: &str,
     extra_headers: &[(String, String)],
+    network: &NetworkPolicy,
 ) -> openai::Client {
     use http::{HeaderMap, HeaderValue};
 
-    let mut builder = openai::Client::builder()
+    let mut builder = openai::Client::<reqwest::Client>::builder()
         .api_key(api_key)
-        .base_url(base_url);
+        .base_url(base_url)
+        .http_client(network.client_builder().build().unwrap_or_default());
     let mut headers = HeaderMap::new();
     for (key, value) in extra_headers {
         if let Ok(name) = http::header::HeaderName::from_bytes(key.as_bytes()) {
@@ -0,0 +1,244 @@
+//! Session variables: `{{name}}` placeholders in project personas, skill
+//! files and cron messages, filled in for the chat they are rendered for.
+//! `today`, `weekday` and `time` are computed at render time; `user_name`
+//! and `last_topic` are kept up to date by the agent loop, and chats can
+//! set their own with `/vars set`. Values are persisted to
+//! `<data_dir>/session_vars.json`.
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Variables computed when a template is rendered; chats cannot set these.
+pub const COMPUTED: &[&str] = &["today", "weekday", "time"];
+/// Variables per chat.
+const MAX_VARIABLES: usize = 50;
+/// Characters per value.
+const MAX_VALUE_CHARS: usize = 500;
+/// Characters of the last user message kept as `last_topic`.
+const TOPIC_CHARS: usize = 80;
+
+pub type Variables = BTreeMap<String, String>;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct VariablesData {
+    version: i32,
+    /// Variables per session key (`channel:chat_id`).
+    #[serde(default)]
+    sessions: BTreeMap<String, Variables>,
+}
+
+pub struct SessionVariables {
+    path: PathBuf,
+    data: VariablesData,
+}
+
+impl SessionVariables {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join("session_vars.json"),
+            data: VariablesData::default(),
+        }
+    }
+
+    pub fn load(&mut self) -> Result<()> {
+        self.data = if self.path.exists() {
+            serde_json::from_str(&fs::read_to_string(&self.path)?)?
+        } else {
+            VariablesData::default()
+        };
+        Ok(())
+    }
+
+    fn save(&mut self) -> Result<()> {
+        self.data.version = 1;
+        self.data.sessions.retain(|_, vars| !vars.is_empty());
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&self.data)?)?;
+        fs::rename(tmp, &self.path)?;
+        Ok(())
+    }
+
+    pub fn get(&self, session_key: &str) -> Variables {
+        self.data
+            .sessions
+            .get(session_key)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn set(&mut self, session_key: &str, name: &str, value: &str) -> Result<()> {
+        let name = name.trim().to_lowercase();
+        if !valid_name(&name) {
+            return Err(anyhow!(
+                "'{name}' is not a valid name (letters, digits and _, up to 32)"
+            ));
+        }
+        if COMPUTED.contains(&name.as_str()) {
+            return Err(anyhow!("'{name}' is computed and cannot be set"));
+        }
+        let vars = self
+            .data
+            .sessions
+            .entry(session_key.to_string())
+            .or_default();
+        if !vars.contains_key(&name) && vars.len() >= MAX_VARIABLES {
+            return Err(anyhow!("this chat already has {MAX_VARIABLES} variables"));
+        }
+        vars.insert(name, value.trim().chars().take(MAX_VALUE_CHARS).collect());
+        self.save()
+    }
+
+    pub fn unset(&mut self, session_key: &str, name: &str) -> Result<bool> {
+        let removed = self
+            .data
+            .sessions
+            .get_mut(session_key)
+            .and_then(|vars| vars.remove(&name.trim().to_lowercase()))
+            .is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Update the variables the agent maintains after a user turn.
+    pub fn record_turn(
+        &mut self,
+        session_key: &str,
+        user_name: Option<&str>,
+        message: &str,
+    ) -> Result<()> {
+        let vars = self
+            .data
+            .sessions
+            .entry(session_key.to_string())
+            .or_default();
+        if let Some(name) = user_name.map(str::trim).filter(|n| !n.is_empty()) {
+            vars.insert("user_name".to_string(), name.to_string());
+        }
+        if let Some(topic) = topic_of(message) {
+            vars.insert("last_topic".to_string(), topic);
+        }
+        self.save()
+    }
+}
+
+/// Everything `{{name}}` can refer to in `session_key` right now: the stored
+/// variables plus the computed ones.
+pub fn resolve(data_dir: &Path, session_key: &str, tz: Option<Tz>) -> Variables {
+    let mut store = SessionVariables::new(data_dir);
+    if let Err(err) = store.load() {
+        tracing::warn!("failed to load session variables: {err}");
+    }
+    let mut vars = store.get(session_key);
+    vars.extend(computed(tz));
+    vars
+}
+
+fn computed(tz: Option<Tz>) -> Variables {
+    let now = Utc::now();
+    let (today, weekday, time) = match tz {
+        Some(tz) => {
+            let local = now.with_timezone(&tz);
+            let format = |f| local.format(f).to_string();
+            (format("%Y-%m-%d"), format("%A"), format("%H:%M"))
+        }
+        None => {
+            let local = now.with_timezone(&chrono::Local);
+            let format = |f| local.format(f).to_string();
+            (format("%Y-%m-%d"), format("%A"), format("%H:%M"))
+        }
+    };
+    Variables::from([
+        ("today".to_string(), today),
+        ("weekday".to_string(), weekday),
+        ("time".to_string(), time),
+    ])
+}
+
+/// `template` with each `{{name}}` replaced by its value; unknown names are
+/// left as written so a typo stays visible.
+pub fn render(template: &str, vars: &Variables) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = after[..end].trim().to_lowercase();
+        match vars.get(&name) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// First line of a user message, shortened; commands are not topics.
+fn topic_of(message: &str) -> Option<String> {
+    let line = message.lines().map(str::trim).find(|l| !l.is_empty())?;
+    if line.starts_with('/') {
+        return None;
+    }
+    let mut topic: String = line.chars().take(TOPIC_CHARS).collect();
+    if line.chars().count() > TOPIC_CHARS {
+        topic.push('…');
+    }
+    Some(topic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_fills_known_names_and_keeps_unknown() {
+        let vars = Variables::from([
+            ("user_name".to_string(), "Anna".to_string()),
+            ("today".to_string(), "2026-10-18".to_string()),
+        ]);
+        assert_eq!(
+            render("Hi {{user_name}}, it is {{ today }}. {{missing}} {{", &vars),
+            "Hi Anna, it is 2026-10-18. {{missing}} {{"
+        );
+    }
+
+    #[test]
+    fn store_keeps_variables_per_session() {
+        let dir = std::env::temp_dir().join(format!("femtobot-vars-{}", uuid::Uuid::new_v4()));
+        let mut store = SessionVariables::new(&dir);
+        store.set("telegram:1", "City", "Berlin").unwrap();
+        store
+            .record_turn("telegram:1", Some("Anna"), "\nplan a trip to Rome\nsoon")
+            .unwrap();
+        assert!(store.set("telegram:1", "today", "x").is_err());
+        assert!(store.set("telegram:1", "bad name", "x").is_err());
+
+        let vars = resolve(&dir, "telegram:1", None);
+        assert_eq!(vars["city"], "Berlin");
+        assert_eq!(vars["user_name"], "Anna");
+        assert_eq!(vars["last_topic"], "plan a trip to Rome");
+        assert!(vars.contains_key("today"));
+        assert!(!resolve(&dir, "telegram:2", None).contains_key("city"));
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,444 @@
+//! WebSocket channel for custom frontends.
+//!
+//! Each connection gets a `chat_id` and its `resume_key`, announced in a
+//! `ready` frame. A reloaded page keeps its session by connecting with both
+//! (`?chat_id=...&resume_key=...`); the key is an HMAC of the chat id under a
+//! secret kept in `<data_dir>/websocket_secret`, so nobody can attach to a
+//! chat whose key they were not given. Senders are `websocket:<chat_id>`, so
+//! a client can never claim an admin id.
+//! Text frames, plain or `{"type": "message", "content": "..."}`, become
+//! inbound messages (send a button's label back as a message to pick it);
+//! replies for that chat arrive as JSON frames. While a reply is being
+//! written, `partial` frames carry the text so far; the `message` frame with
+//! the same `correlation_id` replaces it:
+//!
+//! ```json
+//! {"type": "ready", "chat_id": "3f2a...", "resume_key": "9c1e..."}
+//! {"type": "partial", "chat_id": "3f2a...", "content": "...", "correlation_id": "..."}
+//! {"type": "message", "chat_id": "3f2a...", "content": "...", "correlation_id": "...", "buttons": ["..."]}
+//! {"type": "error", "message": "..."}
+//! ```
+
+use crate::bus::{InboundMessage, MessageBus, OutboundMessage, PartialOutput};
+use crate::channel::{self, Channel};
+use crate::config::AppConfig;
+use anyhow::{anyhow, Result};
+use futures_util::{SinkExt, StreamExt};
+use hmac::{Hmac, KeyInit, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tracing::{info, warn};
+
+/// Longest inbound message accepted, in characters.
+const MAX_INBOUND_CHARS: usize = 32_000;
+
+/// Open connections per chat; a chat can be open in several tabs.
+type Clients = Arc<Mutex<HashMap<String, Vec<mpsc::UnboundedSender<String>>>>>;
+
+pub struct WebSocketChannel {
+    cfg: AppConfig,
+}
+
+impl WebSocketChannel {
+    pub fn new(cfg: AppConfig) -> Self {
+        Self { cfg }
+    }
+}
+
+impl Channel for WebSocketChannel {
+    fn name(&self) -> &'static str {
+        "websocket"
+    }
+
+    fn start(self: Arc<Self>, bus: MessageBus) -> JoinHandle<()> {
+        channel::spawn(self.name(), serve(self, bus))
+    }
+
+    fn supports_buttons(&self) -> bool {
+        true
+    }
+}
+
+async fn serve(channel: Arc<WebSocketChannel>, bus: MessageBus) -> Result<()> {
+    let cfg = &channel.cfg;
+    let listener = TcpListener::bind(cfg.websocket_listen.trim())
+        .await
+        .map_err(|err| anyhow!("cannot listen on {}: {err}", cfg.websocket_listen))?;
+    info!("websocket channel listening on {}", listener.local_addr()?);
+    if cfg.websocket_token.is_empty() {
+        warn!("websocket channel has no token; any client that can connect may chat");
+    }
+
+    let clients: Clients = Arc::default();
+    spawn_partial_forwarder(channel.clone(), clients.clone(), bus.clone());
+    spawn_outbound_forwarder(channel.clone(), clients.clone(), bus.clone());
+
+    let auth = Arc::new(Handshake {
+        token: cfg.websocket_token.clone(),
+        allowed_origins: cfg.websocket_allowed_origins.clone(),
+        resume: ResumeKeys::load(&cfg.data_dir)?,
+    });
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let clients = clients.clone();
+        let bus = bus.clone();
+        let auth = auth.clone();
+        tokio::spawn(async move {
+            if let Err(err) = serve_client(stream, peer, &auth, clients, bus).await {
+                warn!("websocket client {peer} failed: {err}");
+            }
+        });
+    }
+}
+
+/// Connection checks applied during the HTTP upgrade.
+struct Handshake {
+    token: String,
+    allowed_origins: Vec<String>,
+    resume: ResumeKeys,
+}
+
+/// Keys that let a client resume a chat: HMAC-SHA256 of the chat id.
+struct ResumeKeys {
+    secret: Vec<u8>,
+}
+
+impl ResumeKeys {
+    /// Keys under the secret in `data_dir`, created on first use.
+    fn load(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join("websocket_secret");
+        let secret = match std::fs::read_to_string(&path) {
+            Ok(hex_secret) => hex::decode(hex_secret.trim())
+                .map_err(|err| anyhow!("invalid {}: {err}", path.display()))?,
+            Err(_) => {
+                let secret: Vec<u8> = [uuid::Uuid::new_v4(), uuid::Uuid::new_v4()]
+                    .iter()
+                    .flat_map(|id| id.into_bytes())
+                    .collect();
+                std::fs::create_dir_all(data_dir)?;
+                std::fs::write(&path, hex::encode(&secret))?;
+                secret
+            }
+        };
+        Ok(Self { secret })
+    }
+
+    fn mac(&self, chat_id: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts any key length");
+        mac.update(chat_id.as_bytes());
+        mac
+    }
+
+    fn key(&self, chat_id: &str) -> String {
+        hex::encode(self.mac(chat_id).finalize().into_bytes())
+    }
+
+    fn verify(&self, chat_id: &str, key: &str) -> bool {
+        hex::decode(key).is_ok_and(|key| self.mac(chat_id).verify_slice(&key).is_ok())
+    }
+}
+
+/// What the client asked for in the upgrade request.
+#[derive(Default)]
+struct Session {
+    chat_id: Option<String>,
+    name: Option<String>,
+}
+
+impl Handshake {
+    fn check(&self, req: &Request) -> Result<Session, (StatusCode, &'static str)> {
+        let query: HashMap<String, String> = req
+            .uri()
+            .query()
+            .map(|q| {
+                url::form_urlencoded::parse(q.as_bytes())
+                    .into_owned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !self.allowed_origins.is_empty() {
+            let origin = req
+                .headers()
+                .get("origin")
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            if !self.allowed_origins.iter().any(|allowed| allowed == origin) {
+                return Err((StatusCode::FORBIDDEN, "origin not allowed"));
+            }
+        }
+        if !self.token.is_empty() {
+            let bearer = req
+                .headers()
+                .get("authorization")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "));
+            let presented = bearer.or(query.get("token").map(String::as_str));
+            if presented != Some(self.token.as_str()) {
+                return Err((StatusCode::UNAUTHORIZED, "invalid token"));
+            }
+        }
+        let chat_id = match query.get("chat_id") {
+            Some(id) if !valid_chat_id(id) => {
+                return Err((StatusCode::BAD_REQUEST, "invalid chat_id"))
+            }
+            Some(id) => {
+                let key = query.get("resume_key").map(String::as_str);
+                if !key.is_some_and(|key| self.resume.verify(id, key)) {
+                    return Err((StatusCode::FORBIDDEN, "invalid resume_key for chat_id"));
+                }
+                Some(id.clone())
+            }
+            None => None,
+        };
+        let name = query
+            .get("name")
+            .map(|name| name.trim().chars().take(64).collect::<String>())
+            .filter(|name| !name.is_empty());
+        Ok(Session { chat_id, name })
+    }
+}
+
+fn valid_chat_id(id: &str) -> bool {
+    (1..=64).contains(&id.len())
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// The handshake callback's error type is tungstenite's `ErrorResponse`.
+#[allow(clippy::result_large_err)]
+async fn serve_client(
+    stream: TcpStream,
+    peer: SocketAddr,
+    auth: &Handshake,
+    clients: Clients,
+    bus: MessageBus,
+) -> Result<()> {
+    let mut session = Session::default();
+    let ws = tokio_tungstenite::accept_hdr_async(
+        stream,
+        |req: &Request, resp: Response| -> Result<Response, ErrorResponse> {
+            match auth.check(req) {
+                Ok(accepted) => {
+                    session = accepted;
+                    Ok(resp)
+                }
+                Err((status, reason)) => {
+                    let mut error = ErrorResponse::new(Some(reason.to_string()));
+                    *error.status_mut() = status;
+                    Err(error)
+                }
+            }
+        },
+    )
+    .await?;
+    let chat_id = session
+        .chat_id
+        .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+    info!("websocket client {peer} connected as chat {chat_id}");
+
+    let (mut sink, mut frames) = ws.split();
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    clients
+        .lock()
+        .unwrap()
+        .entry(chat_id.clone())
+        .or_default()
+        .push(tx.clone());
+    let ready = json!({
+        "type": "ready",
+        "chat_id": chat_id,
+        "resume_key": auth.resume.key(&chat_id),
+    });
+    let _ = tx.send(ready.to_string());
+
+    let writer = tokio::spawn(async move {
+        while let Some(frame) = rx.recv().await {
+            if sink.send(WsMessage::Text(frame)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(frame) = frames.next().await {
+        let text = match frame {
+            Ok(WsMessage::Text(text)) => text,
+            Ok(WsMessage::Close(_)) | Err(_) => break,
+            // Pings are answered by tungstenite.
+            Ok(_) => continue,
+        };
+        let content = match parse_frame(&text) {
+            Ok(Some(content)) => content,
+            Ok(None) => continue,
+            Err(message) => {
+                let _ = tx.send(json!({ "type": "error", "message": message }).to_string());
+                continue;
+            }
+        };
+        bus.publish_inbound(InboundMessage {
+            channel: "websocket".to_string(),
+            chat_id: chat_id.clone(),
+            sender_id: format!("websocket:{chat_id}"),
+            sender_name: session.name.clone(),
+            is_group: false,
+            content,
+            ..Default::default()
+        })
+        .await;
+    }
+
+    info!("websocket client {peer} (chat {chat_id}) disconnected");
+    // Stopping the writer drops its receiver, which closes this connection's
+    // sender in `clients`.
+    writer.abort();
+    let _ = writer.await;
+    if let Some(senders) = clients.lock().unwrap().get_mut(&chat_id) {
+        senders.retain(|sender| !sender.is_closed());
+    }
+    Ok(())
+}
+
+/// Message text of a client frame; `Ok(None)` for frames to ignore.
+fn parse_frame(text: &str) -> Result<Option<String>, String> {
+    let content = match serde_json::from_str::<Value>(text) {
+        Ok(Value::Object(frame)) => {
+            match frame.get("type").and_then(Value::as_str) {
+                None | Some("message") => {}
+                Some("ping") => return Ok(None),
+                Some(other) => return Err(format!("unknown frame type '{other}'")),
+            }
+            frame
+                .get("content")
+                .and_then(Value::as_str)
+                .ok_or_else(|| "message frames need a \"content\" string".to_string())?
+                .to_string()
+        }
+        _ => text.to_string(),
+    };
+    let content = content.trim();
+    if content.is_empty() {
+        return Ok(None);
+    }
+    if content.chars().count() > MAX_INBOUND_CHARS {
+        return Err(format!(
+            "message too long (limit {MAX_INBOUND_CHARS} characters)"
+        ));
+    }
+    Ok(Some(content.to_string()))
+}
+
+fn outbound_frame(msg: &OutboundMessage) -> String {
+    let attachments: Vec<Value> = msg
+        .meta
+        .attachments
+        .iter()
+        .map(|a| json!({ "kind": a.kind, "name": a.name, "mime": a.mime }))
+        .collect();
+    json!({
+        "type": "message",
+        "chat_id": msg.chat_id,
+        "content": msg.content,
+        "correlation_id": msg.meta.correlation_id,
+        "attachments": attachments,
+        "buttons": msg.meta.buttons,
+    })
+    .to_string()
+}
+
+fn partial_frame(partial: &PartialOutput) -> String {
+    json!({
+        "type": "partial",
+        "chat_id": partial.chat_id,
+        "content": partial.text,
+        "correlation_id": partial.correlation_id,
+    })
+    .to_string()
+}
+
+/// Forward partial replies to the chat's open connections; a chat with none
+/// simply misses them.
+fn spawn_partial_forwarder(channel: Arc<WebSocketChannel>, clients: Clients, bus: MessageBus) {
+    let mut partial_rx = bus.subscribe_partials();
+    tokio::spawn(async move {
+        loop {
+            let partial = match partial_rx.recv().await {
+                Ok(partial) => partial,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if partial.channel != channel.name() || partial.text.is_empty() {
+                continue;
+            }
+            let frame = partial_frame(&partial);
+            if let Some(senders) = clients.lock().unwrap().get_mut(&partial.chat_id) {
+                senders.retain(|sender| sender.send(frame.clone()).is_ok());
+            }
+        }
+    });
+}
+
+fn spawn_outbound_forwarder(channel: Arc<WebSocketChannel>, clients: Clients, bus: MessageBus) {
+    let mut outbound_rx = bus.outbound_queue(channel.name());
+    tokio::spawn(async move {
+        while let Some(msg) = outbound_rx.recv().await {
+            let frame = outbound_frame(&msg);
+            let delivered = {
+                let mut clients = clients.lock().unwrap();
+                let senders = clients.entry(msg.chat_id.clone()).or_default();
+                senders.retain(|sender| sender.send(frame.clone()).is_ok());
+                let delivered = !senders.is_empty();
+                if !delivered {
+                    clients.remove(&msg.chat_id);
+                }
+                delivered
+            };
+            if !delivered {
+                warn!("websocket chat {} has no open connection", msg.chat_id);
+            }
+            bus.report_delivery(&msg, delivered);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_frame, valid_chat_id, ResumeKeys};
+
+    #[test]
+    fn parses_plain_and_json_frames() {
+        assert_eq!(parse_frame(" hi ").unwrap().as_deref(), Some("hi"));
+        assert_eq!(
+            parse_frame(r#"{"type":"message","content":"hello"}"#)
+                .unwrap()
+                .as_deref(),
+            Some("hello")
+        );
+        assert_eq!(parse_frame(r#"{"type":"ping"}"#).unwrap(), None);
+        assert!(parse_frame(r#"{"type":"bogus"}"#).is_err());
+        assert!(valid_chat_id("web-123_a"));
+        assert!(!valid_chat_id("../etc"));
+    }
+
+    #[test]
+    fn resume_keys_are_per_chat_and_survive_restarts() {
+        let dir = std::env::temp_dir().join(format!("femtobot-ws-{}", uuid::Uuid::new_v4()));
+        let keys = ResumeKeys::load(&dir).unwrap();
+        let key = keys.key("web-1");
+        assert!(keys.verify("web-1", &key));
+        assert!(!keys.verify("web-2", &key));
+        assert!(!keys.verify("web-1", "00"));
+        assert!(ResumeKeys::load(&dir).unwrap().verify("web-1", &key));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
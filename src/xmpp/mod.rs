@@ -0,0 +1,545 @@
+//! XMPP (Jabber) channel: a client connection with STARTTLS, SASL
+//! (SCRAM-SHA-256 when offered, otherwise PLAIN over TLS) and resource
+//! binding. One-to-one `chat` messages become inbound turns keyed by the
+//! sender's bare JID, so each contact gets its own session. Only contacts in
+//! the bot's roster or `allow_from` may talk to it; subscription requests
+//! from `allow_from` JIDs are approved automatically.
+
+mod scram;
+mod xml;
+
+use crate::bus::{InboundMessage, MessageBus, MessageMeta};
+use crate::channel::{self, Channel};
+use crate::config::AppConfig;
+use crate::ratelimit::RateLimiter;
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+use tracing::{info, warn};
+use xml::{escape, Element, Event};
+
+const RETRY_DELAY: Duration = Duration::from_secs(15);
+/// Whitespace keepalive so idle connections are not dropped by NATs.
+const KEEPALIVE: Duration = Duration::from_secs(60);
+const RESOURCE: &str = "femtobot";
+
+const NS_TLS: &str = "urn:ietf:params:xml:ns:xmpp-tls";
+const NS_SASL: &str = "urn:ietf:params:xml:ns:xmpp-sasl";
+const NS_BIND: &str = "urn:ietf:params:xml:ns:xmpp-bind";
+const NS_ROSTER: &str = "jabber:iq:roster";
+
+pub struct XmppChannel {
+    cfg: AppConfig,
+}
+
+impl XmppChannel {
+    pub fn new(cfg: AppConfig) -> Self {
+        Self { cfg }
+    }
+}
+
+impl Channel for XmppChannel {
+    fn name(&self) -> &'static str {
+        "xmpp"
+    }
+
+    fn start(self: Arc<Self>, bus: MessageBus) -> JoinHandle<()> {
+        channel::spawn(self.name(), serve(self, bus))
+    }
+}
+
+async fn serve(channel: Arc<XmppChannel>, bus: MessageBus) -> Result<()> {
+    let cfg = &channel.cfg;
+    let account = Account::parse(&cfg.xmpp_jid)?;
+    let (stanza_tx, mut stanza_rx) = unbounded_channel::<String>();
+    spawn_outbound_forwarder(
+        channel.clone(),
+        stanza_tx,
+        bus.clone(),
+        RateLimiter::for_channel(cfg, "xmpp"),
+    );
+    loop {
+        match run_session(cfg, &account, &bus, &mut stanza_rx).await {
+            Ok(()) => warn!("xmpp stream closed by server"),
+            Err(err) => warn!("xmpp connection failed: {err}"),
+        }
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+struct Account {
+    local: String,
+    domain: String,
+}
+
+impl Account {
+    fn parse(jid: &str) -> Result<Self> {
+        let bare = bare_jid(jid.trim());
+        let (local, domain) = bare
+            .split_once('@')
+            .filter(|(l, d)| !l.is_empty() && !d.is_empty())
+            .ok_or_else(|| anyhow!("xmpp jid must look like user@domain"))?;
+        Ok(Self {
+            local: local.to_string(),
+            domain: domain.to_string(),
+        })
+    }
+}
+
+/// `user@domain/resource` -> `user@domain`, lowercased.
+fn bare_jid(jid: &str) -> String {
+    jid.split('/').next().unwrap_or(jid).to_lowercase()
+}
+
+/// Incremental stanza reader over any byte stream.
+#[derive(Default)]
+struct StanzaBuffer {
+    text: String,
+    /// Trailing bytes of an incomplete UTF-8 sequence.
+    pending: Vec<u8>,
+}
+
+impl StanzaBuffer {
+    async fn next<R: AsyncRead + Unpin>(&mut self, reader: &mut R) -> Result<Event> {
+        loop {
+            if let Some((event, used)) = xml::next_event(&self.text)? {
+                self.text.drain(..used);
+                return Ok(event);
+            }
+            let mut chunk = [0u8; 4096];
+            let n = reader.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok(Event::StreamEnd);
+            }
+            self.pending.extend_from_slice(&chunk[..n]);
+            let valid = match std::str::from_utf8(&self.pending) {
+                Ok(text) => text.len(),
+                Err(err) => err.valid_up_to(),
+            };
+            let bytes: Vec<u8> = self.pending.drain(..valid).collect();
+            self.text.push_str(std::str::from_utf8(&bytes)?);
+        }
+    }
+
+    async fn stanza<R: AsyncRead + Unpin>(&mut self, reader: &mut R) -> Result<Element> {
+        match self.next(reader).await? {
+            Event::Stanza(el) => Ok(el),
+            Event::StreamStart(_) => Err(anyhow!("unexpected stream restart")),
+            Event::StreamEnd => Err(anyhow!("stream closed")),
+        }
+    }
+
+    /// Open a new stream and return its `<stream:features>`.
+    async fn open<S: AsyncRead + AsyncWrite + Unpin>(
+        &mut self,
+        stream: &mut S,
+        domain: &str,
+    ) -> Result<Element> {
+        send(
+            stream,
+            &format!(
+                "<?xml version='1.0'?><stream:stream to='{}' version='1.0' xmlns='jabber:client' xmlns:stream='http://etherx.jabber.org/streams'>",
+                escape(domain)
+            ),
+        )
+        .await?;
+        loop {
+            match self.next(stream).await? {
+                Event::StreamStart(_) => continue,
+                Event::Stanza(el) if el.local_name() == "features" => return Ok(el),
+                Event::Stanza(el) if el.local_name() == "error" => {
+                    return Err(anyhow!("xmpp stream error: {}", describe_error(&el)))
+                }
+                Event::Stanza(_) => continue,
+                Event::StreamEnd => return Err(anyhow!("stream closed during negotiation")),
+            }
+        }
+    }
+}
+
+async fn send<W: AsyncWrite + Unpin>(writer: &mut W, data: &str) -> Result<()> {
+    writer.write_all(data.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+fn describe_error(el: &Element) -> String {
+    el.elements()
+        .map(|child| child.local_name().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+async fn run_session(
+    cfg: &AppConfig,
+    account: &Account,
+    bus: &MessageBus,
+    outbound: &mut UnboundedReceiver<String>,
+) -> Result<()> {
+    let server = cfg.xmpp_server.trim();
+    let (host, port) = match server.rsplit_once(':') {
+        Some((host, port)) => (
+            host.to_string(),
+            port.parse::<u16>()
+                .map_err(|_| anyhow!("invalid xmpp port in {server}"))?,
+        ),
+        None if !server.is_empty() => (server.to_string(), 5222),
+        None => (account.domain.clone(), 5222),
+    };
+    let mut tcp = TcpStream::connect((host.as_str(), port)).await?;
+
+    // STARTTLS is required; credentials never go over plain TCP.
+    let mut buffer = StanzaBuffer::default();
+    let features = buffer.open(&mut tcp, &account.domain).await?;
+    if features.child("starttls").is_none() {
+        return Err(anyhow!("server does not offer STARTTLS"));
+    }
+    send(&mut tcp, &format!("<starttls xmlns='{NS_TLS}'/>")).await?;
+    let reply = buffer.stanza(&mut tcp).await?;
+    if reply.local_name() != "proceed" {
+        return Err(anyhow!("STARTTLS refused"));
+    }
+    let mut stream = upgrade_tls(tcp, &account.domain).await?;
+
+    let mut buffer = StanzaBuffer::default();
+    let features = buffer.open(&mut stream, &account.domain).await?;
+    authenticate(&mut stream, &mut buffer, &features, account, cfg).await?;
+
+    let mut buffer = StanzaBuffer::default();
+    let features = buffer.open(&mut stream, &account.domain).await?;
+    if features.child("bind").is_none() {
+        return Err(anyhow!("server does not offer resource binding"));
+    }
+    send(
+        &mut stream,
+        &format!("<iq type='set' id='bind'><bind xmlns='{NS_BIND}'><resource>{RESOURCE}</resource></bind></iq>"),
+    )
+    .await?;
+    let bound = loop {
+        let stanza = buffer.stanza(&mut stream).await?;
+        if stanza.local_name() == "iq" && stanza.attr("id") == Some("bind") {
+            if stanza.attr("type") != Some("result") {
+                return Err(anyhow!("resource binding failed"));
+            }
+            break stanza
+                .child("bind")
+                .and_then(|b| b.child("jid"))
+                .map(Element::text)
+                .unwrap_or_default();
+        }
+    };
+    info!("xmpp connected as {bound}");
+
+    send(
+        &mut stream,
+        &format!("<iq type='get' id='roster'><query xmlns='{NS_ROSTER}'/></iq><presence/>"),
+    )
+    .await?;
+
+    let mut contacts = Contacts::new(&cfg.xmpp_allow_from, cfg.xmpp_roster_only);
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let mut keepalive = tokio::time::interval(KEEPALIVE);
+    keepalive.tick().await;
+    loop {
+        tokio::select! {
+            event = buffer.next(&mut reader) => {
+                let stanza = match event? {
+                    Event::Stanza(stanza) => stanza,
+                    Event::StreamEnd => return Ok(()),
+                    Event::StreamStart(_) => continue,
+                };
+                for reply in handle_stanza(&stanza, &mut contacts, bus).await {
+                    send(&mut writer, &reply).await?;
+                }
+            }
+            Some(stanza) = outbound.recv() => {
+                send(&mut writer, &stanza).await?;
+            }
+            _ = keepalive.tick() => {
+                send(&mut writer, " ").await?;
+            }
+        }
+    }
+}
+
+async fn upgrade_tls(
+    tcp: TcpStream,
+    domain: &str,
+) -> Result<tokio_rustls::client::TlsStream<TcpStream>> {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let tls =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    let name = ServerName::try_from(domain.to_string())?;
+    Ok(TlsConnector::from(Arc::new(tls)).connect(name, tcp).await?)
+}
+
+async fn authenticate<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    buffer: &mut StanzaBuffer,
+    features: &Element,
+    account: &Account,
+    cfg: &AppConfig,
+) -> Result<()> {
+    let mechanisms: Vec<String> = features
+        .child("mechanisms")
+        .map(|m| m.elements().map(Element::text).collect())
+        .unwrap_or_default();
+    let password = cfg.xmpp_password.as_str();
+    if mechanisms.iter().any(|m| m == "SCRAM-SHA-256") {
+        let mut scram = scram::ScramSha256::new(&account.local, password);
+        send(
+            stream,
+            &format!(
+                "<auth xmlns='{NS_SASL}' mechanism='SCRAM-SHA-256'>{}</auth>",
+                BASE64.encode(scram.client_first())
+            ),
+        )
+        .await?;
+        let challenge = sasl_step(buffer, stream, "challenge").await?;
+        let response = scram.client_final(&challenge)?;
+        send(
+            stream,
+            &format!(
+                "<response xmlns='{NS_SASL}'>{}</response>",
+                BASE64.encode(response)
+            ),
+        )
+        .await?;
+        let success = sasl_step(buffer, stream, "success").await?;
+        scram.verify_server(&success)?;
+    } else if mechanisms.iter().any(|m| m == "PLAIN") {
+        let token = format!("\0{}\0{password}", account.local);
+        send(
+            stream,
+            &format!(
+                "<auth xmlns='{NS_SASL}' mechanism='PLAIN'>{}</auth>",
+                BASE64.encode(token)
+            ),
+        )
+        .await?;
+        sasl_step(buffer, stream, "success").await?;
+    } else {
+        return Err(anyhow!(
+            "no supported SASL mechanism (server offers: {})",
+            mechanisms.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Wait for `<expected>` and return its decoded payload.
+async fn sasl_step<R: AsyncRead + Unpin>(
+    buffer: &mut StanzaBuffer,
+    reader: &mut R,
+    expected: &str,
+) -> Result<String> {
+    let stanza = buffer.stanza(reader).await?;
+    if stanza.local_name() == "failure" {
+        return Err(anyhow!(
+            "xmpp authentication failed: {}",
+            describe_error(&stanza)
+        ));
+    }
+    if stanza.local_name() != expected {
+        return Err(anyhow!(
+            "unexpected <{}> during authentication",
+            stanza.name
+        ));
+    }
+    let payload = BASE64.decode(stanza.text().trim())?;
+    Ok(String::from_utf8(payload)?)
+}
+
+/// Who may start turns: the roster plus `allow_from`.
+struct Contacts {
+    allow_from: HashSet<String>,
+    roster: HashSet<String>,
+    roster_only: bool,
+}
+
+impl Contacts {
+    fn new(allow_from: &[String], roster_only: bool) -> Self {
+        Self {
+            allow_from: allow_from
+                .iter()
+                .map(|jid| bare_jid(jid.trim()))
+                .filter(|jid| !jid.is_empty())
+                .collect(),
+            roster: HashSet::new(),
+            roster_only,
+        }
+    }
+
+    fn allows(&self, jid: &str) -> bool {
+        self.allow_from.contains(jid)
+            || self.roster.contains(jid)
+            || (!self.roster_only && self.allow_from.is_empty())
+    }
+
+    /// Apply roster items from a roster result or push.
+    fn update_roster(&mut self, query: &Element) {
+        for item in query.elements().filter(|el| el.local_name() == "item") {
+            let Some(jid) = item.attr("jid").map(bare_jid) else {
+                continue;
+            };
+            match item.attr("subscription").unwrap_or("none") {
+                "both" | "from" | "to" => {
+                    self.roster.insert(jid);
+                }
+                _ => {
+                    self.roster.remove(&jid);
+                }
+            }
+        }
+    }
+}
+
+/// React to one incoming stanza; returns stanzas to send back.
+async fn handle_stanza(stanza: &Element, contacts: &mut Contacts, bus: &MessageBus) -> Vec<String> {
+    let from = stanza.attr("from").unwrap_or_default();
+    match stanza.local_name() {
+        "iq" => {
+            let id = escape(stanza.attr("id").unwrap_or_default());
+            let kind = stanza.attr("type").unwrap_or_default();
+            if let Some(query) = stanza
+                .child("query")
+                .filter(|q| q.attr("xmlns") == Some(NS_ROSTER))
+            {
+                contacts.update_roster(query);
+                if kind == "set" {
+                    return vec![format!("<iq type='result' id='{id}'/>")];
+                }
+                return Vec::new();
+            }
+            match kind {
+                "get" if stanza.child("ping").is_some() => {
+                    vec![format!("<iq type='result' id='{id}' to='{}'/>", escape(from))]
+                }
+                "get" | "set" => vec![format!(
+                    "<iq type='error' id='{id}' to='{}'><error type='cancel'><service-unavailable xmlns='urn:ietf:params:xml:ns:xmpp-stanzas'/></error></iq>",
+                    escape(from)
+                )],
+                _ => Vec::new(),
+            }
+        }
+        "presence" => {
+            let jid = bare_jid(from);
+            if stanza.attr("type") == Some("subscribe") && contacts.allow_from.contains(&jid) {
+                info!("xmpp approving subscription from {jid}");
+                return vec![format!(
+                    "<presence type='subscribed' to='{0}'/><presence type='subscribe' to='{0}'/>",
+                    escape(&jid)
+                )];
+            }
+            Vec::new()
+        }
+        "message" => {
+            if let Some(inbound) = to_inbound(stanza) {
+                if contacts.allows(&inbound.sender_id) {
+                    bus.publish_inbound(inbound).await;
+                } else {
+                    info!(
+                        "xmpp message from {} ignored: not in roster or allow_from",
+                        inbound.sender_id
+                    );
+                }
+            }
+            Vec::new()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// One-to-one chat messages with a body become inbound messages.
+fn to_inbound(stanza: &Element) -> Option<InboundMessage> {
+    if !matches!(stanza.attr("type"), None | Some("chat" | "normal")) {
+        return None;
+    }
+    let body = stanza.child("body")?.text();
+    let body = body.trim();
+    if body.is_empty() {
+        return None;
+    }
+    let sender = bare_jid(stanza.attr("from")?);
+    Some(InboundMessage {
+        channel: "xmpp".to_string(),
+        chat_id: sender.clone(),
+        sender_id: sender,
+        sender_name: None,
+        is_group: false,
+        content: body.to_string(),
+        meta: MessageMeta {
+            message_id: stanza.attr("id").map(str::to_string),
+            ..Default::default()
+        },
+    })
+}
+
+fn spawn_outbound_forwarder(
+    channel: Arc<XmppChannel>,
+    stanzas: UnboundedSender<String>,
+    bus: MessageBus,
+    mut limiter: RateLimiter,
+) {
+    let mut rx = bus.outbound_queue(channel.name());
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let mut ok = true;
+            for chunk in channel.render(&msg) {
+                limiter.acquire(&msg.chat_id).await;
+                let stanza = format!(
+                    "<message to='{}' type='chat' id='{}'><body>{}</body></message>",
+                    escape(&msg.chat_id),
+                    uuid::Uuid::new_v4(),
+                    escape(&chunk)
+                );
+                if stanzas.send(stanza).is_err() {
+                    ok = false;
+                    break;
+                }
+            }
+            bus.report_delivery(&msg, ok);
+        }
+        info!("outbound channel closed, xmpp forwarder shutting down");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_inbound, xml, Contacts};
+
+    #[test]
+    fn chat_messages_map_to_bare_jid_sessions() {
+        let input = "<message from='Anna@example.org/phone' type='chat' id='m1'><body>hi there</body></message>";
+        let Some((xml::Event::Stanza(stanza), _)) = xml::next_event(input).unwrap() else {
+            panic!("expected a stanza");
+        };
+        let msg = to_inbound(&stanza).unwrap();
+        assert_eq!(msg.chat_id, "anna@example.org");
+        assert_eq!(msg.content, "hi there");
+
+        let mut contacts = Contacts::new(&[], true);
+        assert!(!contacts.allows("anna@example.org"));
+        let roster = "<query xmlns='jabber:iq:roster'><item jid='anna@example.org' subscription='both'/></query>";
+        let Some((xml::Event::Stanza(query), _)) = xml::next_event(roster).unwrap() else {
+            panic!("expected a stanza");
+        };
+        contacts.update_roster(&query);
+        assert!(contacts.allows("anna@example.org"));
+    }
+}
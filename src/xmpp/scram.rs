@@ -0,0 +1,131 @@
+//! SCRAM-SHA-256 client (RFC 5802 / RFC 7677) without channel binding.
+
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::{Digest, Sha256};
+
+pub struct ScramSha256 {
+    password: String,
+    nonce: String,
+    client_first_bare: String,
+    server_signature: Option<Vec<u8>>,
+}
+
+impl ScramSha256 {
+    pub fn new(username: &str, password: &str) -> Self {
+        Self::with_nonce(
+            username,
+            password,
+            &uuid::Uuid::new_v4().simple().to_string(),
+        )
+    }
+
+    fn with_nonce(username: &str, password: &str, nonce: &str) -> Self {
+        let username = username.replace('=', "=3D").replace(',', "=2C");
+        Self {
+            client_first_bare: format!("n={username},r={nonce}"),
+            password: password.to_string(),
+            nonce: nonce.to_string(),
+            server_signature: None,
+        }
+    }
+
+    pub fn client_first(&self) -> String {
+        format!("n,,{}", self.client_first_bare)
+    }
+
+    /// Answer the server-first message with the client proof.
+    pub fn client_final(&mut self, server_first: &str) -> Result<String> {
+        let nonce = field(server_first, 'r')?;
+        if !nonce.starts_with(&self.nonce) {
+            return Err(anyhow!("scram server nonce does not extend ours"));
+        }
+        let salt = BASE64.decode(field(server_first, 's')?)?;
+        let iterations: u32 = field(server_first, 'i')?
+            .parse()
+            .map_err(|_| anyhow!("invalid scram iteration count"))?;
+        if iterations == 0 {
+            return Err(anyhow!("invalid scram iteration count"));
+        }
+
+        let salted = pbkdf2(self.password.as_bytes(), &salt, iterations);
+        let client_key = hmac_sha256(&salted, b"Client Key");
+        let stored_key = Sha256::digest(&client_key);
+        let without_proof = format!("c=biws,r={nonce}");
+        let auth_message = format!("{},{server_first},{without_proof}", self.client_first_bare);
+        let client_signature = hmac_sha256(&stored_key, auth_message.as_bytes());
+        let proof: Vec<u8> = client_key
+            .iter()
+            .zip(&client_signature)
+            .map(|(a, b)| a ^ b)
+            .collect();
+        let server_key = hmac_sha256(&salted, b"Server Key");
+        self.server_signature = Some(hmac_sha256(&server_key, auth_message.as_bytes()));
+        Ok(format!("{without_proof},p={}", BASE64.encode(proof)))
+    }
+
+    /// Check the server's signature from the `<success>` payload.
+    pub fn verify_server(&self, server_final: &str) -> Result<()> {
+        if let Ok(error) = field(server_final, 'e') {
+            return Err(anyhow!("scram server error: {error}"));
+        }
+        let signature = BASE64.decode(field(server_final, 'v')?)?;
+        match &self.server_signature {
+            Some(expected) if *expected == signature => Ok(()),
+            _ => Err(anyhow!("scram server signature mismatch")),
+        }
+    }
+}
+
+fn field(message: &str, key: char) -> Result<&str> {
+    message
+        .split(',')
+        .find_map(|part| {
+            part.strip_prefix(key)
+                .and_then(|rest| rest.strip_prefix('='))
+        })
+        .ok_or_else(|| anyhow!("scram message is missing '{key}'"))
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// PBKDF2-HMAC-SHA256 with a single 32-byte output block.
+fn pbkdf2(password: &[u8], salt: &[u8], iterations: u32) -> Vec<u8> {
+    let mut block = salt.to_vec();
+    block.extend_from_slice(&1u32.to_be_bytes());
+    let mut u = hmac_sha256(password, &block);
+    let mut out = u.clone();
+    for _ in 1..iterations {
+        u = hmac_sha256(password, &u);
+        for (o, b) in out.iter_mut().zip(&u) {
+            *o ^= b;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScramSha256;
+
+    #[test]
+    fn matches_rfc7677_example() {
+        let mut scram = ScramSha256::with_nonce("user", "pencil", "rOprNGfwEbeRWgbNEkqO");
+        assert_eq!(scram.client_first(), "n,,n=user,r=rOprNGfwEbeRWgbNEkqO");
+        let server_first = "r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096";
+        let client_final = scram.client_final(server_first).unwrap();
+        assert_eq!(
+            client_final,
+            "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+        );
+        scram
+            .verify_server("v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=")
+            .unwrap();
+    }
+}
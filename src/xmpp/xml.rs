@@ -0,0 +1,324 @@
+//! Just enough XML for XMPP streams: the `<stream:stream>` header is read
+//! as an open tag and everything below it as complete stanza elements.
+//! Namespaces are not resolved; lookups use local names.
+
+use anyhow::{anyhow, Result};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Element {
+    /// Qualified name as written, e.g. `stream:features`.
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    pub fn local_name(&self) -> &str {
+        local(&self.name)
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(el) => Some(el),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// First child element with local name `name`.
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|el| el.local_name() == name)
+    }
+
+    /// Concatenated text content of this element (not descendants).
+    pub fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Text(text) => Some(text.as_str()),
+                Node::Element(_) => None,
+            })
+            .collect()
+    }
+}
+
+fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    StreamStart(Element),
+    StreamEnd,
+    Stanza(Element),
+}
+
+/// Parse the next event from the start of `input`. Returns the event and
+/// the bytes consumed, `Ok(None)` when more input is needed. Whitespace,
+/// XML declarations and comments between stanzas are skipped.
+pub fn next_event(input: &str) -> Result<Option<(Event, usize)>> {
+    let mut pos = 0;
+    loop {
+        pos += input[pos..].len() - input[pos..].trim_start().len();
+        let rest = &input[pos..];
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        if rest.starts_with("<?") {
+            let Some(end) = rest.find("?>") else {
+                return Ok(None);
+            };
+            pos += end + 2;
+            continue;
+        }
+        if rest.starts_with("<!--") {
+            let Some(end) = rest.find("-->") else {
+                return Ok(None);
+            };
+            pos += end + 3;
+            continue;
+        }
+        break;
+    }
+    let rest = &input[pos..];
+    if rest.starts_with("</") {
+        let Some(end) = rest.find('>') else {
+            return Ok(None);
+        };
+        return Ok(Some((Event::StreamEnd, pos + end + 1)));
+    }
+    let Some((tag, after, self_closing)) = parse_start_tag(input, pos)? else {
+        return Ok(None);
+    };
+    if tag.local_name() == "stream" && !self_closing {
+        return Ok(Some((Event::StreamStart(tag), after)));
+    }
+    if self_closing {
+        return Ok(Some((Event::Stanza(tag), after)));
+    }
+    match parse_content(input, after, tag)? {
+        Some((element, end)) => Ok(Some((Event::Stanza(element), end))),
+        None => Ok(None),
+    }
+}
+
+/// `<name attr='v' ...>` or `<name .../>` at `pos`.
+fn parse_start_tag(input: &str, pos: usize) -> Result<Option<(Element, usize, bool)>> {
+    let rest = &input[pos..];
+    if !rest.starts_with('<') {
+        return Err(anyhow!("expected '<' in xmpp stream"));
+    }
+    // Quoted values may contain '>', so walk the tag.
+    let bytes = rest.as_bytes();
+    let mut quote = None;
+    let mut end = None;
+    for (idx, &b) in bytes.iter().enumerate().skip(1) {
+        match (quote, b) {
+            (Some(q), _) if b == q => quote = None,
+            (Some(_), _) => {}
+            (None, b'\'' | b'"') => quote = Some(b),
+            (None, b'>') => {
+                end = Some(idx);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let Some(end) = end else {
+        return Ok(None);
+    };
+    let mut inner = &rest[1..end];
+    let self_closing = inner.ends_with('/');
+    if self_closing {
+        inner = &inner[..inner.len() - 1];
+    }
+    let name_end = inner
+        .find(|c: char| c.is_whitespace())
+        .unwrap_or(inner.len());
+    let name = inner[..name_end].to_string();
+    if name.is_empty() {
+        return Err(anyhow!("empty tag name in xmpp stream"));
+    }
+    let mut attrs = Vec::new();
+    let mut attr_src = inner[name_end..].trim_start();
+    while !attr_src.is_empty() {
+        let eq = attr_src
+            .find('=')
+            .ok_or_else(|| anyhow!("malformed attribute in <{name}>"))?;
+        let key = attr_src[..eq].trim().to_string();
+        let value_src = attr_src[eq + 1..].trim_start();
+        let q = value_src
+            .chars()
+            .next()
+            .filter(|c| *c == '\'' || *c == '"')
+            .ok_or_else(|| anyhow!("unquoted attribute in <{name}>"))?;
+        let close = value_src[1..]
+            .find(q)
+            .ok_or_else(|| anyhow!("unterminated attribute in <{name}>"))?;
+        attrs.push((key, unescape(&value_src[1..1 + close])));
+        attr_src = value_src[close + 2..].trim_start();
+    }
+    Ok(Some((
+        Element {
+            name,
+            attrs,
+            children: Vec::new(),
+        },
+        pos + end + 1,
+        self_closing,
+    )))
+}
+
+/// Children and text of `element` up to its end tag.
+fn parse_content(
+    input: &str,
+    mut pos: usize,
+    mut element: Element,
+) -> Result<Option<(Element, usize)>> {
+    loop {
+        let rest = &input[pos..];
+        let Some(lt) = rest.find('<') else {
+            return Ok(None);
+        };
+        if lt > 0 {
+            element.children.push(Node::Text(unescape(&rest[..lt])));
+            pos += lt;
+            continue;
+        }
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let Some(end) = cdata.find("]]>") else {
+                return Ok(None);
+            };
+            element.children.push(Node::Text(cdata[..end].to_string()));
+            pos += "<![CDATA[".len() + end + 3;
+            continue;
+        }
+        if rest.starts_with("<!--") {
+            let Some(end) = rest.find("-->") else {
+                return Ok(None);
+            };
+            pos += end + 3;
+            continue;
+        }
+        if let Some(close) = rest.strip_prefix("</") {
+            let Some(end) = close.find('>') else {
+                return Ok(None);
+            };
+            if close[..end].trim() != element.name {
+                return Err(anyhow!(
+                    "mismatched end tag </{}> for <{}>",
+                    close[..end].trim(),
+                    element.name
+                ));
+            }
+            return Ok(Some((element, pos + 2 + end + 1)));
+        }
+        let Some((child, after, self_closing)) = parse_start_tag(input, pos)? else {
+            return Ok(None);
+        };
+        if self_closing {
+            element.children.push(Node::Element(child));
+            pos = after;
+            continue;
+        }
+        let Some((child, after)) = parse_content(input, after, child)? else {
+            return Ok(None);
+        };
+        element.children.push(Node::Element(child));
+        pos = after;
+    }
+}
+
+fn unescape(raw: &str) -> String {
+    if !raw.contains('&') {
+        return raw.to_string();
+    }
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Escape text or attribute values for output.
+pub fn escape(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Not allowed in XML 1.0.
+            c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, next_event, Event};
+
+    #[test]
+    fn reads_stream_header_and_stanzas_incrementally() {
+        let input = "<?xml version='1.0'?><stream:stream xmlns='jabber:client' id='x'>\n<message from='anna@example.org/phone' type='chat'><body>1 &lt; 2 &amp; 3</body><active xmlns='http://jabber.org/protocol/chatstates'/></message>";
+        let (event, used) = next_event(input).unwrap().unwrap();
+        assert!(matches!(event, Event::StreamStart(ref el) if el.attr("id") == Some("x")));
+        let rest = &input[used..];
+        // Partial stanzas wait for more input.
+        assert_eq!(next_event(&rest[..20]).unwrap(), None);
+        let (event, used2) = next_event(rest).unwrap().unwrap();
+        let Event::Stanza(message) = event else {
+            panic!("expected a stanza");
+        };
+        assert_eq!(used2, rest.len());
+        assert_eq!(message.attr("from"), Some("anna@example.org/phone"));
+        assert_eq!(message.child("body").unwrap().text(), "1 < 2 & 3");
+        assert!(message.child("active").is_some());
+        assert_eq!(escape("a<b & 'c'"), "a&lt;b &amp; &apos;c&apos;");
+    }
+}